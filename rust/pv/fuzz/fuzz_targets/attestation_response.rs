@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../tests/parsers/mod.rs"]
+mod parsers;
+
+fuzz_target!(|data: &[u8]| parsers::attestation_response(data));
@@ -8,10 +8,16 @@ use std::{
 };
 
 use log::{debug, warn};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U32, U64};
 
 // (SE) boot request control block aka SE header
-use crate::{assert_size, request::MagicValue, static_assert, Error, Result, PAGESIZE};
+use crate::{
+    assert_size,
+    misc::{encode_hex, short_id},
+    request::{MagicValue, Summary},
+    static_assert, Error, Result, PAGESIZE,
+};
 
 /// Struct containing all SE-header tags.
 ///
@@ -187,6 +193,12 @@ where
     Ok(false)
 }
 
+impl Summary for BootHdrTags {
+    fn summary(&self) -> String {
+        format!("SE-header tag {}", short_id(&self.tag))
+    }
+}
+
 impl BootHdrTags {
     /// Returns a reference to the SE-header tag of this [`BootHdrTags`].
     pub fn tag(&self) -> &[u8; 16] {
@@ -282,6 +294,208 @@ impl BootHdrHead {
     const TAG_SIZE: usize = 0x10;
 }
 
+/// Unencrypted information of a Secure Execution header
+///
+/// The header of a bootable Secure Execution image, also called boot request control block.
+/// Contains everything except the encrypted area and the wrapped keys of the keyslots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeHdr {
+    version: u32,
+    size: u32,
+    nep: u64,
+    pcf: u64,
+    phkhs: Vec<[u8; SeHdr::PHKH_SIZE]>,
+    tags: BootHdrTags,
+}
+
+/// Reads up to `len` bytes into `buf`, less only if the input ends
+fn read_up_to<R: Read>(rd: &mut R, buf: &mut Vec<u8>, len: usize) -> Result<()> {
+    let start = buf.len();
+    rd.take((len - start) as u64).read_to_end(buf)?;
+    Ok(())
+}
+
+impl SeHdr {
+    /// Header version 1
+    pub const V1: u32 = 0x100;
+    /// Size of a public host-key hash
+    pub const PHKH_SIZE: usize = 0x20;
+    const KEYSLOT_SIZE: usize = 0x50;
+    const MAX_SIZE: usize = 2 * PAGESIZE;
+
+    /// Parses a Secure Execution header.
+    ///
+    /// `rd` must point to the start of the header, e.g. of a header extracted from an image. Use
+    /// [`Self::from_se_image`] to locate the header in a bootable image first.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::SeHdrInvMagic`] if the input is no Secure Execution
+    /// header, [`Error::SeHdrUnsupportedVersion`] for unknown header versions,
+    /// [`Error::SeHdrTruncated`] if the input ends before the header, and
+    /// [`Error::InvBootHdrSize`] if the header size does not match its content.
+    pub fn parse<R: Read>(rd: &mut R) -> Result<Self> {
+        let head_size = size_of::<BootHdrHead>();
+        let mut hdr = Vec::with_capacity(head_size);
+        read_up_to(rd, &mut hdr, head_size)?;
+
+        if !BootHdrMagic::starts_with_magic(&hdr) {
+            return if hdr.len() < BootHdrMagic::MAGIC.len() && BootHdrMagic::MAGIC.starts_with(&hdr)
+            {
+                Err(Error::SeHdrTruncated {
+                    size: hdr.len(),
+                    expected: head_size,
+                })
+            } else {
+                Err(Error::SeHdrInvMagic)
+            };
+        }
+        // the version is in front of the size field
+        if hdr.len() >= 12 {
+            let version = u32::from_be_bytes(hdr[8..12].try_into().unwrap());
+            if version != Self::V1 {
+                return Err(Error::SeHdrUnsupportedVersion(version));
+            }
+        }
+        let head = BootHdrHead::read_from_prefix(&hdr).ok_or(Error::SeHdrTruncated {
+            size: hdr.len(),
+            expected: head_size,
+        })?;
+
+        let size = head.size.get() as usize;
+        let nks: usize = head
+            .nks
+            .get()
+            .try_into()
+            .map_err(|_| Error::InvBootHdrSize(size))?;
+        let sea: usize = head
+            .sea
+            .get()
+            .try_into()
+            .map_err(|_| Error::InvBootHdrSize(size))?;
+        let expected = nks
+            .checked_mul(Self::KEYSLOT_SIZE)
+            .and_then(|ks| ks.checked_add(sea))
+            .and_then(|s| s.checked_add(head_size + BootHdrHead::TAG_SIZE));
+        if size > Self::MAX_SIZE || expected != Some(size) {
+            debug!("SE-header size {size:#x} does not match {nks} keyslots and {sea:#x} bytes encrypted area");
+            return Err(Error::InvBootHdrSize(size));
+        }
+
+        read_up_to(rd, &mut hdr, size)?;
+        if hdr.len() < size {
+            return Err(Error::SeHdrTruncated {
+                size: hdr.len(),
+                expected: size,
+            });
+        }
+
+        let phkhs = hdr[head_size..head_size + nks * Self::KEYSLOT_SIZE]
+            .chunks_exact(Self::KEYSLOT_SIZE)
+            // Panic: chunks are larger than a hash
+            .map(|ks| ks[..Self::PHKH_SIZE].try_into().unwrap())
+            .collect();
+        // Panic: the header is larger than a tag
+        let tag = hdr[size - BootHdrHead::TAG_SIZE..].try_into().unwrap();
+
+        Ok(Self {
+            version: head.version.get(),
+            size: head.size.get(),
+            nep: head.nep.get(),
+            pcf: head.pcf.get(),
+            phkhs,
+            tags: BootHdrTags {
+                pld: head.pld,
+                ald: head.ald,
+                tld: head.tld,
+                tag,
+            },
+        })
+    }
+
+    /// Locates and parses the Secure Execution header of a bootable image.
+    ///
+    /// Accepts extracted headers as well.
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::NoSeHdr`] if `img` contains no Secure Execution header
+    /// and the errors of [`Self::parse`] for invalid headers.
+    pub fn from_se_image<R>(img: &mut R) -> Result<Self>
+    where
+        R: Read + Seek,
+    {
+        if !seek_se_hdr_start(img)? {
+            return Err(Error::NoSeHdr);
+        }
+        Self::parse(img)
+    }
+
+    /// Returns the magic value of the header, `IBMSecEx`
+    pub fn magic(&self) -> &'static [u8; 8] {
+        &BootHdrMagic::MAGIC
+    }
+
+    /// Returns the header version
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the header size in bytes
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the size of the encrypted image components in bytes
+    ///
+    /// The number of encrypted pages times the page size.
+    pub fn image_size(&self) -> u64 {
+        self.nep.saturating_mul(PAGESIZE as u64)
+    }
+
+    /// Returns the plaintext control flags
+    pub fn pcf(&self) -> u64 {
+        self.pcf
+    }
+
+    /// Returns the number of keyslots
+    pub fn nks(&self) -> usize {
+        self.phkhs.len()
+    }
+
+    /// Returns the public host-key hashes of the keyslots in order
+    pub fn phkhs(&self) -> &[[u8; Self::PHKH_SIZE]] {
+        &self.phkhs
+    }
+
+    /// Returns the tags of the header
+    pub fn tags(&self) -> &BootHdrTags {
+        &self.tags
+    }
+}
+
+impl Serialize for SeHdr {
+    fn serialize<S>(&self, ser: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let hex = |v: &[u8]| format!("0x{}", encode_hex(v));
+        let mut hdr = ser.serialize_struct("SeHdr", 9)?;
+        hdr.serialize_field("magic", &hex(self.magic()))?;
+        hdr.serialize_field("version", &format!("{:#x}", self.version))?;
+        hdr.serialize_field("size", &self.size)?;
+        hdr.serialize_field("image_size", &self.image_size())?;
+        hdr.serialize_field("pcf", &format!("{:#018x}", self.pcf))?;
+        hdr.serialize_field("nks", &self.nks())?;
+        hdr.serialize_field(
+            "phkhs",
+            &self.phkhs.iter().map(|h| hex(h)).collect::<Vec<_>>(),
+        )?;
+        hdr.serialize_field("tag", &hex(&self.tags.tag))?;
+        hdr.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
@@ -392,4 +606,89 @@ mod tests {
 
         assert_eq!(metadata.version(), SeImgMetaData::V1);
     }
+
+    #[test]
+    fn se_hdr_parse() {
+        let bin_hdr = get_test_asset!("exp/secure_guest.hdr");
+        let hdr = SeHdr::parse(&mut bin_hdr.as_slice()).unwrap();
+        assert_eq!(hdr.magic(), b"IBMSecEx");
+        assert_eq!(hdr.version(), SeHdr::V1);
+        assert_eq!(hdr.size(), 0x280);
+        assert_eq!(hdr.image_size(), 0xfc25 * PAGESIZE as u64);
+        assert_eq!(hdr.pcf(), 0);
+        assert_eq!(hdr.nks(), 1);
+        assert_eq!(
+            encode_hex(hdr.phkhs()[0]),
+            "782966a6298f6f5968780c3134ca4c55958d2dbca5292f59e9f5d2670befaf2f"
+        );
+        assert_eq!(hdr.tags(), &EXP_HDR);
+
+        let mut img = vec![0u8; 0x13000];
+        img[0x12000..0x12280].copy_from_slice(bin_hdr);
+        assert_eq!(SeHdr::from_se_image(&mut Cursor::new(img)).unwrap(), hdr);
+        assert_eq!(
+            SeHdr::from_se_image(&mut Cursor::new(bin_hdr)).unwrap(),
+            hdr
+        );
+    }
+
+    #[test]
+    fn se_hdr_json() {
+        let bin_hdr = get_test_asset!("exp/secure_guest.hdr");
+        let hdr = SeHdr::parse(&mut bin_hdr.as_slice()).unwrap();
+        let exp = serde_json::json!({
+            "magic": "0x49424d5365634578",
+            "version": "0x100",
+            "size": 640,
+            "image_size": 0xfc25000_u64,
+            "pcf": "0x0000000000000000",
+            "nks": 1,
+            "phkhs": ["0x782966a6298f6f5968780c3134ca4c55958d2dbca5292f59e9f5d2670befaf2f"],
+            "tag": "0x92309d4589b9a85b427f8753171d1520",
+        });
+        assert_eq!(serde_json::to_value(&hdr).unwrap(), exp);
+    }
+
+    #[test]
+    fn se_hdr_parse_fail() {
+        let bin_hdr = get_test_asset!("exp/secure_guest.hdr");
+        let parse = |hdr: &[u8]| SeHdr::parse(&mut Cursor::new(hdr));
+
+        let mut hdr = *bin_hdr;
+        hdr.swap(0, 1);
+        assert!(matches!(parse(&hdr), Err(Error::SeHdrInvMagic)));
+        assert!(matches!(parse(&[0; 0x300]), Err(Error::SeHdrInvMagic)));
+
+        let mut hdr = *bin_hdr;
+        hdr[10] = 2;
+        assert!(matches!(
+            parse(&hdr),
+            Err(Error::SeHdrUnsupportedVersion(0x200))
+        ));
+
+        for len in [0, 4, 8, 0x100, 0x1a0, 0x27f] {
+            let expected = if len < 0x1a0 { 0x1a0 } else { 0x280 };
+            assert!(
+                matches!(
+                    parse(&bin_hdr[..len]),
+                    Err(Error::SeHdrTruncated { size, expected: e }) if size == len && e == expected
+                ),
+                "{len}: {:?}",
+                parse(&bin_hdr[..len])
+            );
+        }
+
+        // size does not match the number of keyslots and the encrypted area
+        let mut hdr = *bin_hdr;
+        hdr[15] = 0x90;
+        assert!(matches!(parse(&hdr), Err(Error::InvBootHdrSize(0x290))));
+        let mut hdr = *bin_hdr;
+        hdr[0x20..0x28].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(matches!(parse(&hdr), Err(Error::InvBootHdrSize(0x280))));
+
+        assert!(matches!(
+            SeHdr::from_se_image(&mut Cursor::new(vec![0u8; 0x13000])),
+            Err(Error::NoSeHdr)
+        ));
+    }
 }
@@ -4,21 +4,22 @@
 
 use std::{convert::TryInto, fmt::Display, ops::Range};
 
-use enum_dispatch::enum_dispatch;
 use openssl::{
     derive::Deriver,
     ec::{EcGroup, EcKey},
-    hash::{DigestBytes, MessageDigest},
-    md::MdRef,
+    error::ErrorStack,
+    hash::{DigestBytes, Hasher, MessageDigest},
+    md::{Md, MdRef},
+    md_ctx::MdCtx,
     nid::Nid,
     pkey::{HasPublic, Id, PKey, PKeyRef, Private, Public},
     pkey_ctx::{HkdfMode, PkeyCtx},
     rand::rand_bytes,
     rsa::Padding,
     sign::{Signer, Verifier},
-    symm::{decrypt_aead as openssl_decrypt_aead, encrypt_aead as openssl_encrypt_aead, Cipher},
+    symm::{encrypt_aead as openssl_encrypt_aead, Cipher, Crypter, Mode},
 };
-use pv_core::request::Confidential;
+use pv_core::request::{Confidential, Zeroize};
 
 use crate::{error::Result, Error};
 
@@ -27,6 +28,21 @@ pub type Aes256GcmKey = Confidential<[u8; SymKeyType::AES_256_GCM_KEY_LEN]>;
 /// An AES256-XTS key that will purge itself out of the memory when going out of scope
 pub type Aes256XtsKey = Confidential<[u8; SymKeyType::AES_256_XTS_KEY_LEN]>;
 
+/// Creates a key of `N` bytes initialized by `init`
+///
+/// The key lives in locked memory if the `mlock` feature is enabled.
+fn new_key<const N: usize, F>(init: F) -> Result<Confidential<[u8; N]>>
+where
+    F: FnOnce(&mut [u8; N]) -> Result<()>,
+{
+    #[cfg(feature = "mlock")]
+    let mut key = Confidential::new_locked([0; N]);
+    #[cfg(not(feature = "mlock"))]
+    let mut key = Confidential::new([0; N]);
+    init(key.value_mut())?;
+    Ok(key)
+}
+
 /// SHA-512 digest length (in bytes)
 pub const SHA_512_HASH_LEN: usize = 64;
 #[allow(dead_code)]
@@ -34,6 +50,9 @@ pub(crate) const SHA_256_HASH_LEN: u32 = 32;
 #[allow(dead_code)]
 pub(crate) type Sha256Hash = [u8; SHA_256_HASH_LEN as usize];
 
+/// Label of the KDF in [`derive_keys`]
+const DERIVE_KEYS_LABEL: &[u8] = b"s390-pv derive_keys";
+
 /// Types of symmetric keys, to specify during construction.
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -70,6 +89,14 @@ impl SymKeyType {
         }
     }
 
+    /// Returns the key length of the [`SymKeyType`] (in bytes)
+    pub const fn key_len(&self) -> usize {
+        match self {
+            SymKeyType::Aes256Gcm => Self::AES_256_GCM_KEY_LEN,
+            SymKeyType::Aes256Xts => Self::AES_256_XTS_KEY_LEN,
+        }
+    }
+
     /// Returns true if the [`SymKeyType`] is an AEAD key
     pub const fn is_aead(&self) -> bool {
         self.tag_len().is_some()
@@ -95,14 +122,8 @@ impl From<SymKeyType> for Nid {
     }
 }
 
-/// The `enum_dispatch` macros needs at least one local trait to be implemented.
-#[allow(unused)]
-#[enum_dispatch(SymKey)]
-trait SymKeyTrait {}
-
 /// Types of symmetric keys
 #[non_exhaustive]
-#[enum_dispatch()]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SymKey {
     /// AES 256 GCM key (32 bytes)
@@ -121,8 +142,8 @@ impl SymKey {
     /// This function will return an error if the Key cannot be generated.
     pub fn random(key_tp: SymKeyType) -> Result<Self> {
         match key_tp {
-            SymKeyType::Aes256Gcm => Ok(Self::Aes256(random_array().map(|v| v.into())?)),
-            SymKeyType::Aes256Xts => Ok(Self::Aes256Xts(random_array().map(|v| v.into())?)),
+            SymKeyType::Aes256Gcm => Ok(Self::Aes256(new_key(|k| Ok(rand_bytes(k)?))?)),
+            SymKeyType::Aes256Xts => Ok(Self::Aes256Xts(new_key(|k| Ok(rand_bytes(k)?))?)),
         }
     }
 
@@ -152,13 +173,102 @@ impl SymKey {
     /// This function will return an error if the key cannot be created, e.g.
     /// because the provided data is too small or too large.
     pub fn try_from_data(key_tp: SymKeyType, data: Confidential<Vec<u8>>) -> Result<Self> {
+        fn copy<const N: usize>(data: Confidential<Vec<u8>>) -> Result<Confidential<[u8; N]>> {
+            let data: Confidential<[u8; N]> = data.try_into()?;
+            new_key(|k| {
+                k.copy_from_slice(data.value());
+                Ok(())
+            })
+        }
+
         match key_tp {
-            SymKeyType::Aes256Gcm => Ok(Self::Aes256(data.try_into()?)),
-            SymKeyType::Aes256Xts => Ok(Self::Aes256Xts(data.try_into()?)),
+            SymKeyType::Aes256Gcm => Ok(Self::Aes256(copy(data)?)),
+            SymKeyType::Aes256Xts => Ok(Self::Aes256Xts(copy(data)?)),
+        }
+    }
+
+    fn wrong_type(&self, expected: SymKeyType) -> Error {
+        Error::WrongKeyType {
+            expected,
+            actual: self.key_type(),
+        }
+    }
+
+    /// Returns a reference to the AES256-GCM key of this [`SymKey`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::WrongKeyType`] if this is not an AES256-GCM key.
+    pub fn expect_gcm(&self) -> Result<&Aes256GcmKey> {
+        match self {
+            Self::Aes256(key) => Ok(key),
+            _ => Err(self.wrong_type(SymKeyType::Aes256Gcm)),
+        }
+    }
+
+    /// Returns a reference to the AES256-XTS key of this [`SymKey`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return [`Error::WrongKeyType`] if this is not an AES256-XTS key.
+    pub fn expect_xts(&self) -> Result<&Aes256XtsKey> {
+        match self {
+            Self::Aes256Xts(key) => Ok(key),
+            _ => Err(self.wrong_type(SymKeyType::Aes256Xts)),
+        }
+    }
+}
+
+impl From<Aes256GcmKey> for SymKey {
+    fn from(key: Aes256GcmKey) -> Self {
+        Self::Aes256(key)
+    }
+}
+
+impl From<Aes256XtsKey> for SymKey {
+    fn from(key: Aes256XtsKey) -> Self {
+        Self::Aes256Xts(key)
+    }
+}
+
+impl TryFrom<SymKey> for Aes256GcmKey {
+    type Error = Error;
+
+    fn try_from(key: SymKey) -> Result<Self> {
+        match key {
+            SymKey::Aes256(key) => Ok(key),
+            _ => Err(key.wrong_type(SymKeyType::Aes256Gcm)),
+        }
+    }
+}
+
+impl TryFrom<&SymKey> for Aes256GcmKey {
+    type Error = Error;
+
+    fn try_from(key: &SymKey) -> Result<Self> {
+        key.expect_gcm().cloned()
+    }
+}
+
+impl TryFrom<SymKey> for Aes256XtsKey {
+    type Error = Error;
+
+    fn try_from(key: SymKey) -> Result<Self> {
+        match key {
+            SymKey::Aes256Xts(key) => Ok(key),
+            _ => Err(key.wrong_type(SymKeyType::Aes256Xts)),
         }
     }
 }
 
+impl TryFrom<&SymKey> for Aes256XtsKey {
+    type Error = Error;
+
+    fn try_from(key: &SymKey) -> Result<Self> {
+        key.expect_xts().cloned()
+    }
+}
+
 /// Performs an hkdf according to RFC 5869.
 /// See [`OpenSSL HKDF`]()
 ///
@@ -166,7 +276,7 @@ impl SymKey {
 ///
 /// This function will return an OpenSSL error if the key could not be generated.
 pub(crate) fn hkdf_rfc_5869<const COUNT: usize>(
-    md: &MdRef,
+    md: Digest,
     ikm: &[u8],
     salt: &[u8],
     info: &[u8],
@@ -174,7 +284,7 @@ pub(crate) fn hkdf_rfc_5869<const COUNT: usize>(
     let mut ctx = PkeyCtx::new_id(Id::HKDF)?;
     ctx.derive_init()?;
     ctx.set_hkdf_mode(HkdfMode::EXTRACT_THEN_EXPAND)?;
-    ctx.set_hkdf_md(md)?;
+    ctx.set_hkdf_md(md.into())?;
     ctx.set_hkdf_salt(salt)?;
     ctx.set_hkdf_key(ikm)?;
     ctx.add_hkdf_info(info)?;
@@ -197,12 +307,96 @@ pub fn derive_aes256_gcm_key(k1: &PKeyRef<Private>, k2: &PKeyRef<Public>) -> Res
     let secr = Confidential::new(key);
 
     // Panic: does not panic as SHA256 digest is 32 bytes long
-    Ok(Aes256GcmKey::new(
-        hash(MessageDigest::sha256(), secr.value())?
-            .as_ref()
-            .try_into()
-            .unwrap(),
-    ))
+    new_key(|k| {
+        k.copy_from_slice(hash(Digest::Sha256, secr.value())?.as_ref());
+        Ok(())
+    })
+}
+
+/// Performs the counter-mode KDF of NIST SP 800-108 with HMAC as PRF on opaque fixed input data.
+///
+/// K(i) = HMAC(key, [i]_32 || fixed), with the counter starting at 1.
+fn kdf_ctr_fixed(
+    md: &MdRef,
+    key: &[u8],
+    fixed: &[u8],
+    out_len: usize,
+) -> Result<Confidential<Vec<u8>>> {
+    let hmac_key = PKey::hmac(key)?;
+    let h = md.size();
+    let blocks = out_len.div_ceil(h);
+    // allocate all blocks upfront; a reallocation would free the old buffer without zeroizing
+    let mut out = Confidential::new(vec![0; blocks * h]);
+    for (i, block) in out.value_mut().chunks_exact_mut(h).enumerate() {
+        // Panic: does not panic as kdf_ctr limits the output to less than 2^32 blocks
+        let ctr: u32 = (i + 1).try_into().unwrap();
+        let mut ctx = MdCtx::new()?;
+        ctx.digest_sign_init(Some(md), &hmac_key)?;
+        ctx.digest_sign_update(&ctr.to_be_bytes())?;
+        ctx.digest_sign_update(fixed)?;
+        ctx.digest_sign_final(Some(block))?;
+    }
+    out.value_mut().truncate(out_len);
+    Ok(out)
+}
+
+/// Derives keying material with the counter-mode KDF of NIST SP 800-108 using HMAC as PRF.
+///
+/// * `md` - digest of the HMAC
+/// * `key` - key derivation key, e.g. an ECDH shared secret
+/// * `label` - purpose of the derived keying material
+/// * `context` - information related to the derived keying material
+/// * `out_len` - length of the derived keying material in bytes
+///
+/// The 32-bit big-endian counter is placed before the fixed input data
+/// `label || 0x00 || context || [L]_32`, with L being the output length in bits.
+///
+/// # Errors
+///
+/// This function will return [`Error::KdfOutputLarge`] if the output length in bits does not fit
+/// into 32 bits, or an OpenSSL error if the HMAC could not be calculated.
+pub fn kdf_ctr(
+    md: &MdRef,
+    key: &[u8],
+    label: &[u8],
+    context: &[u8],
+    out_len: usize,
+) -> Result<Confidential<Vec<u8>>> {
+    let max = u32::MAX as usize / 8;
+    let l_bits: u32 = match out_len.checked_mul(8).map(u32::try_from) {
+        Some(Ok(l)) => l,
+        _ => return Err(Error::KdfOutputLarge { size: out_len, max }),
+    };
+    let mut fixed = Vec::with_capacity(label.len() + 1 + context.len() + 4);
+    fixed.extend_from_slice(label);
+    fixed.push(0);
+    fixed.extend_from_slice(context);
+    fixed.extend_from_slice(&l_bits.to_be_bytes());
+    let fixed = Confidential::new(fixed);
+    kdf_ctr_fixed(md, key, fixed.value(), out_len)
+}
+
+/// Derives several symmetric keys from one secret.
+///
+/// Calls [`kdf_ctr`] with HMAC-SHA512, the label `s390-pv derive_keys`, and an empty context for
+/// the sum of the key lengths and slices the output into keys of the given types in order.
+///
+/// # Errors
+///
+/// This function will return an error if the keying material could not be derived.
+pub fn derive_keys(secret: &[u8], specs: &[SymKeyType]) -> Result<Vec<SymKey>> {
+    let out_len = specs.iter().map(SymKeyType::key_len).sum();
+    // zeroized on drop, also if one of the keys cannot be created
+    let okm = kdf_ctr(Md::sha512(), secret, DERIVE_KEYS_LABEL, &[], out_len)?;
+    let mut rest = okm.value().as_slice();
+    specs
+        .iter()
+        .map(|&tp| {
+            let (key, r) = rest.split_at(tp.key_len());
+            rest = r;
+            SymKey::try_from_data(tp, Confidential::new(key.to_vec()))
+        })
+        .collect()
 }
 
 /// Generate a random array.
@@ -216,6 +410,83 @@ pub fn random_array<const COUNT: usize>() -> Result<[u8; COUNT]> {
     Ok(rand)
 }
 
+/// Pool of random bytes.
+///
+/// Fills its buffer with a single call to the random number generator and hands out the random
+/// bytes until the buffer is exhausted. Then the buffer is refilled. Bytes are never handed out
+/// twice and are overwritten in the pool as soon as they were handed out. The buffer is zeroized
+/// on drop.
+///
+/// # Example
+///
+/// ```rust
+/// # use s390_pv::request::RandomPool;
+/// # fn main() -> s390_pv::Result<()> {
+/// let mut pool = RandomPool::new(64);
+/// let iv: [u8; 12] = pool.array()?;
+/// let nonce: [u8; 16] = pool.array()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RandomPool {
+    buf: Confidential<Vec<u8>>,
+    pos: usize,
+}
+
+impl RandomPool {
+    /// Create a new, empty pool that generates `size` random bytes at once.
+    ///
+    /// The buffer is filled on first use. A `size` of zero is treated as one.
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            buf: Confidential::new(vec![0; size]),
+            pos: size,
+        }
+    }
+
+    fn refill(&mut self) -> Result<()> {
+        rand_bytes(self.buf.value_mut())?;
+        self.pos = 0;
+        Ok(())
+    }
+
+    /// Fill `out` with random bytes from the pool.
+    ///
+    /// Refills the pool as often as needed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the entropy source fails or is not available.
+    pub fn fill(&mut self, out: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < out.len() {
+            if self.pos == self.buf.value().len() {
+                self.refill()?;
+            }
+            let avail = &mut self.buf.value_mut()[self.pos..];
+            let n = avail.len().min(out.len() - filled);
+            out[filled..filled + n].copy_from_slice(&avail[..n]);
+            avail[..n].iter_mut().for_each(Zeroize::zeroize);
+            self.pos += n;
+            filled += n;
+        }
+        Ok(())
+    }
+
+    /// Generate a random array from the pool.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the entropy source fails or is not available.
+    pub fn array<const COUNT: usize>(&mut self) -> Result<[u8; COUNT]> {
+        let mut rand = [0; COUNT];
+        self.fill(&mut rand)?;
+        Ok(rand)
+    }
+}
+
 /// Generate a new random EC key.
 ///
 /// # Errors
@@ -264,6 +535,65 @@ pub struct AeadDecryptionResult {
 }
 
 impl AeadEncryptionResult {
+    /// Parse an AES-256-GCM encrypted buffer.
+    ///
+    /// `buf` must contain the additional authenticated data (`aad_len` bytes), the encrypted
+    /// data, and the tag in this order.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `buf` is too small to contain the
+    /// additional authenticated data and the tag.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use s390_pv::request::{
+    /// #     decrypt_aead, encrypt_aead, AeadEncryptionResult, SymKey, SymKeyType,
+    /// # };
+    /// # fn main() -> s390_pv::Result<()> {
+    /// let key = SymKey::random(SymKeyType::Aes256Gcm)?;
+    /// let iv = [0x42; SymKeyType::AES_256_GCM_IV_LEN];
+    /// let buf = encrypt_aead(&key, &iv, b"header", b"secret")?.into_buf();
+    ///
+    /// let res = AeadEncryptionResult::parse(buf, b"header".len())?;
+    /// let plain = decrypt_aead(&key, &iv, res.aad(), res.ciphertext(), res.tag())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse(buf: Vec<u8>, aad_len: usize) -> Result<Self> {
+        let tag_len = SymKeyType::AES_256_GCM_TAG_LEN;
+        let min_size = aad_len.saturating_add(tag_len);
+        if buf.len() < min_size {
+            return Err(Error::AeadBufferSmall {
+                size: buf.len(),
+                min_size,
+            });
+        }
+        let encr_end = buf.len() - tag_len;
+        Ok(Self {
+            aad_range: 0..aad_len,
+            encr_range: aad_len..encr_end,
+            tag_range: encr_end..buf.len(),
+            buf,
+        })
+    }
+
+    /// Returns the additional authenticated data.
+    pub fn aad(&self) -> &[u8] {
+        &self.buf[self.aad_range.clone()]
+    }
+
+    /// Returns the encrypted data.
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.buf[self.encr_range.clone()]
+    }
+
+    /// Returns the authentication tag.
+    pub fn tag(&self) -> &[u8] {
+        &self.buf[self.tag_range.clone()]
+    }
+
     /// Deconstruct the result to just the resulting data w/o ranges.
     pub fn into_buf(self) -> Vec<u8> {
         let Self { buf, .. } = self;
@@ -393,6 +723,54 @@ pub fn encrypt_aead(
     })
 }
 
+/// Maximum sizes of the inputs of [`decrypt_aead_into`]
+///
+/// The caller defines the limits, i.e., the largest inputs that are valid in its context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AeadLimits {
+    /// Maximum size of the additional authenticated data (in bytes)
+    pub aad: usize,
+    /// Maximum size of the encrypted data (in bytes)
+    pub encr: usize,
+}
+
+/// Decrypt `encr` into `out` and compare the GCM-tag
+///
+/// `out` must be at least `encr.len()` bytes long. If the decryption fails, the first
+/// `encr.len()` bytes of `out` are zeroed, such that no unauthenticated data is left behind.
+fn decrypt_gcm(
+    key: &SymKey,
+    iv: &[u8],
+    aad: &[u8],
+    encr: &[u8],
+    tag: &[u8],
+    out: &mut [u8],
+) -> Result<usize> {
+    key.expect_gcm()?;
+    let nid = key.key_type().into();
+    let cipher = Cipher::from_nid(nid).ok_or(Error::UnsupportedCipher(nid))?;
+    let out = &mut out[..encr.len()];
+
+    let mut decrypt = || -> std::result::Result<usize, ErrorStack> {
+        let mut crypter = Crypter::new(cipher, Mode::Decrypt, key.value(), Some(iv))?;
+        crypter.aad_update(aad)?;
+        let count = crypter.update(encr, out)?;
+        crypter.set_tag(tag)?;
+        // GCM is a stream mode and yields no more data
+        Ok(count + crypter.finalize(&mut out[count..])?)
+    };
+    decrypt().map_err(|ssl_err| {
+        out.fill(0);
+        // Empty error-stack -> no internal ssl error but decryption failed.
+        // Very likely due to a tag mismatch.
+        if ssl_err.errors().is_empty() {
+            Error::GcmTagMismatch
+        } else {
+            Error::Crypto(ssl_err)
+        }
+    })
+}
+
 /// Decrypt encrypted data with a symmetric key compare the GCM-tag.
 ///
 /// * `key` - symmetric key used for encryption
@@ -414,39 +792,24 @@ pub fn decrypt_aead(
     encr: &[u8],
     tag: &[u8],
 ) -> Result<AeadDecryptionResult> {
-    match key {
-        SymKey::Aes256(_) => {}
-        SymKey::Aes256Xts(_) => return Err(Error::NoAeadKey),
-    };
-    let nid = key.key_type().into();
-    let cipher = Cipher::from_nid(nid).ok_or(Error::UnsupportedCipher(nid))?;
-    let decr =
-        openssl_decrypt_aead(cipher, key.value(), Some(iv), aad, encr, tag).map_err(|ssl_err| {
-            // Empty error-stack -> no internal ssl error but decryption failed.
-            // Very likely due to a tag mismatch.
-            if ssl_err.errors().is_empty() {
-                Error::GcmTagMismatch
-            } else {
-                Error::Crypto(ssl_err)
-            }
-        })?;
-    let mut conf = Confidential::new(vec![0; aad.len() + decr.len() + tag.len()]);
+    let mut conf = Confidential::new(vec![0; aad.len() + encr.len() + tag.len()]);
     let aad_range = Range {
         start: 0,
         end: aad.len(),
     };
     let data_range = Range {
         start: aad.len(),
-        end: aad.len() + decr.len(),
+        end: aad.len() + encr.len(),
     };
     let tag_range = Range {
-        start: aad.len() + decr.len(),
-        end: aad.len() + decr.len() + tag.len(),
+        start: aad.len() + encr.len(),
+        end: aad.len() + encr.len() + tag.len(),
     };
 
     let buf = conf.value_mut();
+    // GCM does not change the size of the data
+    decrypt_gcm(key, iv, aad, encr, tag, &mut buf[data_range.clone()])?;
     buf[aad_range.clone()].copy_from_slice(aad);
-    buf[data_range.clone()].copy_from_slice(&decr);
     buf[tag_range.clone()].copy_from_slice(tag);
     Ok(AeadDecryptionResult {
         buf: conf,
@@ -456,125 +819,766 @@ pub fn decrypt_aead(
     })
 }
 
-/// Calculate the hash of a slice.
+/// Decrypt encrypted data with a symmetric key into a caller-provided buffer and compare the
+/// GCM-tag.
+///
+/// In contrast to [`decrypt_aead`], neither intermediate copies of the data are made nor is the
+/// additional authenticated data copied into the result.
+///
+/// * `key` - symmetric key used for encryption
+/// * `iv` - initialisation vector
+/// * `aad` - additional authenticated data
+/// * `encr` - encrypted data
+/// * `tag` - GCM-tag to compare with
+/// * `limits` - maximum sizes of `aad` and `encr`
+/// * `out` - buffer for the decrypted data, at least `encr.len()` bytes long
+///
+/// # Returns
+/// The number of decrypted bytes written to the start of `out`
 ///
 /// # Errors
 ///
-/// This function will return an error if OpenSSL could not compute the hash.
-pub(crate) fn hash(t: MessageDigest, data: &[u8]) -> Result<DigestBytes> {
-    openssl::hash::hash(t, data).map_err(Error::Crypto)
-}
-
-/// Calculate the HMAC of the given message.
-pub(crate) fn calculate_hmac(
-    hmac_key: &PKeyRef<Private>,
-    dgst: MessageDigest,
-    msg: &[u8],
-) -> Result<Vec<u8>> {
-    match hmac_key.id() {
-        Id::HMAC => Signer::new(dgst, hmac_key)?
-            .sign_oneshot_to_vec(msg)
-            .map_err(Error::Crypto),
-        _ => Err(Error::UnsupportedSigningKey),
+/// This function will return an error if an input exceeds `limits`, `out` is too small, or the
+/// data could not be decrypted by OpenSSL. `out` does not contain any decrypted data in that
+/// case.
+pub fn decrypt_aead_into(
+    key: &SymKey,
+    iv: &[u8],
+    aad: &[u8],
+    encr: &[u8],
+    tag: &[u8],
+    limits: AeadLimits,
+    out: &mut Confidential<Vec<u8>>,
+) -> Result<usize> {
+    for (what, size, max) in [
+        ("additional authenticated data", aad.len(), limits.aad),
+        ("encrypted data", encr.len(), limits.encr),
+    ] {
+        if size > max {
+            return Err(Error::AeadInputLarge { what, size, max });
+        }
+    }
+    let out = out.value_mut();
+    if out.len() < encr.len() {
+        return Err(Error::AeadOutputSmall {
+            size: out.len(),
+            min_size: encr.len(),
+        });
     }
+    decrypt_gcm(key, iv, aad, encr, tag, out)
 }
-/// Calculate a digital signature scheme.
+
+/// Calculate the GCM-tag of additional authenticated data.
 ///
-/// Calculates the digital signature of the provided message using the signing key. [`Id::EC`],
-/// and [`Id::RSA`] keys are supported. For [`Id::RSA`] [`Padding::PKCS1_PSS`] is used.
+/// Authenticates `aad` without encrypting any data, e.g., to integrity-protect a header.
+///
+/// * `key` - symmetric key used for authentication
+/// * `iv` - initialisation vector
+/// * `aad` - additional authenticated data
 ///
 /// # Errors
 ///
-/// This function will return an error if OpenSSL could not compute the signature.
-pub(crate) fn sign_msg(
-    skey: &PKeyRef<Private>,
-    dgst: MessageDigest,
-    msg: &[u8],
-) -> Result<Vec<u8>> {
-    match skey.id() {
-        Id::EC => {
-            let mut sgn = Signer::new(dgst, skey)?;
-            sgn.sign_oneshot_to_vec(msg).map_err(Error::Crypto)
-        }
-        Id::RSA => {
-            let mut sgn = Signer::new(dgst, skey)?;
-            sgn.set_rsa_padding(Padding::PKCS1_PSS)?;
-            sgn.sign_oneshot_to_vec(msg).map_err(Error::Crypto)
-        }
-        _ => Err(Error::UnsupportedSigningKey),
-    }
+/// This function will return an error if `key` is no AEAD key or OpenSSL could not compute the
+/// tag.
+pub fn gcm_tag(
+    key: &SymKey,
+    iv: &[u8],
+    aad: &[u8],
+) -> Result<[u8; SymKeyType::AES_256_GCM_TAG_LEN]> {
+    key.key_type().tag_len().ok_or(Error::NoAeadKey)?;
+    let nid = key.key_type().into();
+    let cipher = Cipher::from_nid(nid).ok_or(Error::UnsupportedCipher(nid))?;
+    let mut tag = [0u8; SymKeyType::AES_256_GCM_TAG_LEN];
+    openssl_encrypt_aead(cipher, key.value(), Some(iv), aad, &[], &mut tag)?;
+    Ok(tag)
 }
 
-/// Verify the digital signature of a message.
+/// Verify the GCM-tag of additional authenticated data.
 ///
-/// Verifies the digital signature of the provided message using the signing key.
-/// [`Id::EC`] and [`Id::RSA`] keys are supported. For [`Id::RSA`] [`Padding::PKCS1_PSS`] is used.
+/// Counterpart of [`gcm_tag`]. The tags are compared in constant time.
 ///
-/// # Returns
-/// true if signature could be verified, false otherwise
+/// * `key` - symmetric key used for authentication
+/// * `iv` - initialisation vector
+/// * `aad` - additional authenticated data
+/// * `tag` - GCM-tag to compare with
 ///
 /// # Errors
 ///
-/// This function will return an error if OpenSSL could not compute the signature.
-pub(crate) fn verify_signature<T: HasPublic>(
-    skey: &PKeyRef<T>,
-    dgst: MessageDigest,
-    msg: &[u8],
-    sign: &[u8],
-) -> Result<bool> {
-    match skey.id() {
-        Id::EC => {
-            let mut ctx = Verifier::new(dgst, skey)?;
-            ctx.update(msg)?;
-            ctx.verify(sign).map_err(Error::Crypto)
-        }
-        Id::RSA => {
-            let mut ctx = Verifier::new(dgst, skey)?;
-            ctx.set_rsa_padding(Padding::PKCS1_PSS)?;
-            ctx.verify_oneshot(sign, msg).map_err(Error::Crypto)
-        }
-        _ => Err(Error::UnsupportedVerificationKey),
+/// This function will return [`Error::GcmTagMismatch`] if `tag` does not authenticate `aad`, or
+/// an error if `key` is no AEAD key or OpenSSL could not compute the tag.
+pub fn gcm_tag_verify(key: &SymKey, iv: &[u8], aad: &[u8], tag: &[u8]) -> Result<()> {
+    let exp = gcm_tag(key, iv, aad)?;
+    // memcmp::eq panics for different lengths, a truncated tag is never accepted
+    match tag.len() == exp.len() && openssl::memcmp::eq(&exp, tag) {
+        true => Ok(()),
+        false => Err(Error::GcmTagMismatch),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{get_test_asset, test_utils::*, PvCoreError};
-
-    #[test]
-    fn sign_ec() {
-        let (ec_key, _) = get_test_keys();
-
-        let data = "sample".as_bytes();
-        let sign = sign_msg(&ec_key, MessageDigest::sha512(), data).unwrap();
-        assert!(sign.len() <= 139, "value is: {}", sign.len());
+/// Digest algorithms supported by the crate's hash, HKDF, and signing functions
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Digest {
+    /// SHA-256 (FIPS 180-4)
+    Sha256,
+    /// SHA-384 (FIPS 180-4)
+    Sha384,
+    /// SHA-512 (FIPS 180-4)
+    Sha512,
+    /// SHA3-256 (FIPS 202)
+    Sha3_256,
+    /// SHA3-512 (FIPS 202)
+    Sha3_512,
+}
 
-        assert!(verify_signature(&ec_key, MessageDigest::sha512(), data, &sign).unwrap());
+impl Digest {
+    /// Returns the size of the digest in bytes.
+    pub const fn size(&self) -> usize {
+        match self {
+            Self::Sha256 | Self::Sha3_256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 | Self::Sha3_512 => 64,
+        }
     }
 
-    #[test]
-    fn sign_rsa_2048() {
-        let keypair = get_test_asset!("keys/rsa2048key.pem");
-        let keypair = PKey::private_key_from_pem(keypair).unwrap();
+    /// Returns the corresponding OpenSSL [`MessageDigest`].
+    pub fn message_digest(&self) -> MessageDigest {
+        match self {
+            Self::Sha256 => MessageDigest::sha256(),
+            Self::Sha384 => MessageDigest::sha384(),
+            Self::Sha512 => MessageDigest::sha512(),
+            Self::Sha3_256 => MessageDigest::sha3_256(),
+            Self::Sha3_512 => MessageDigest::sha3_512(),
+        }
+    }
 
-        let data = "sample".as_bytes();
-        let sign = sign_msg(&keypair, MessageDigest::sha512(), data).unwrap();
-        assert_eq!(256, sign.len());
+    /// Returns the corresponding OpenSSL [`MdRef`].
+    pub fn md(&self) -> &'static MdRef {
+        match self {
+            Self::Sha256 => Md::sha256(),
+            Self::Sha384 => Md::sha384(),
+            Self::Sha512 => Md::sha512(),
+            Self::Sha3_256 => Md::sha3_256(),
+            Self::Sha3_512 => Md::sha3_512(),
+        }
+    }
+}
 
-        assert!(verify_signature(&keypair, MessageDigest::sha512(), data, &sign).unwrap());
+impl Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Sha256 => "SHA-256",
+                Self::Sha384 => "SHA-384",
+                Self::Sha512 => "SHA-512",
+                Self::Sha3_256 => "SHA3-256",
+                Self::Sha3_512 => "SHA3-512",
+            }
+        )
     }
+}
 
-    #[test]
-    fn sign_rsa_3072() {
-        let keypair = get_test_asset!("keys/rsa3072key.pem");
-        let keypair = PKey::private_key_from_pem(keypair).unwrap();
+impl From<Digest> for MessageDigest {
+    fn from(value: Digest) -> Self {
+        value.message_digest()
+    }
+}
 
-        let data = "sample".as_bytes();
-        let sign = sign_msg(&keypair, MessageDigest::sha512(), data).unwrap();
-        assert_eq!(384, sign.len());
+impl TryFrom<MessageDigest> for Digest {
+    type Error = Error;
 
-        assert!(verify_signature(&keypair, MessageDigest::sha512(), data, &sign).unwrap());
+    fn try_from(value: MessageDigest) -> Result<Self> {
+        [
+            Self::Sha256,
+            Self::Sha384,
+            Self::Sha512,
+            Self::Sha3_256,
+            Self::Sha3_512,
+        ]
+        .into_iter()
+        .find(|d| d.message_digest().type_() == value.type_())
+        .ok_or(Error::UnsupportedDigest(value.type_()))
+    }
+}
+
+impl From<Digest> for &'static MdRef {
+    fn from(value: Digest) -> Self {
+        value.md()
+    }
+}
+
+/// Calculate the hash of a slice.
+///
+/// # Errors
+///
+/// This function will return an error if OpenSSL could not compute the hash.
+pub(crate) fn hash(d: Digest, data: &[u8]) -> Result<DigestBytes> {
+    openssl::hash::hash(d.into(), data).map_err(Error::Crypto)
+}
+
+/// Calculate the digest of `data`.
+///
+/// # Errors
+///
+/// This function will return an error if OpenSSL could not compute the digest.
+pub fn digest(d: Digest, data: &[u8]) -> Result<Vec<u8>> {
+    hash(d, data).map(|h| h.to_vec())
+}
+
+/// Streaming digest calculation.
+///
+/// Hashes data that is provided in chunks, e.g., a large file via [`std::io::copy`].
+///
+/// ```rust
+/// # use s390_pv::request::{Digest, DigestCtx};
+/// # fn main() -> s390_pv::Result<()> {
+/// let mut ctx = DigestCtx::new(Digest::Sha256)?;
+/// ctx.update(b"a")?;
+/// ctx.update(b"bc")?;
+/// assert_eq!(ctx.finish()?, s390_pv::request::digest(Digest::Sha256, b"abc")?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DigestCtx {
+    digest: Digest,
+    hasher: Hasher,
+}
+
+impl std::fmt::Debug for DigestCtx {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DigestCtx")
+            .field("digest", &self.digest)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DigestCtx {
+    /// Create a new context for the digest `d`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if OpenSSL could not initialize the digest.
+    pub fn new(d: Digest) -> Result<Self> {
+        Ok(Self {
+            digest: d,
+            hasher: Hasher::new(d.into())?,
+        })
+    }
+
+    /// Returns the digest algorithm of this context.
+    pub const fn digest(&self) -> Digest {
+        self.digest
+    }
+
+    /// Add `data` to the digest.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if OpenSSL could not process the data.
+    pub fn update(&mut self, data: &[u8]) -> Result<()> {
+        self.hasher.update(data).map_err(Error::Crypto)
+    }
+
+    /// Finish the calculation and return the digest.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if OpenSSL could not compute the digest.
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        Ok(self.hasher.finish()?.to_vec())
+    }
+}
+
+impl std::io::Write for DigestCtx {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        std::io::Write::write(&mut self.hasher, buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Calculate the HMAC of the given message.
+pub(crate) fn calculate_hmac(
+    hmac_key: &PKeyRef<Private>,
+    dgst: Digest,
+    msg: &[u8],
+) -> Result<Vec<u8>> {
+    match hmac_key.id() {
+        Id::HMAC => Signer::new(dgst.into(), hmac_key)?
+            .sign_oneshot_to_vec(msg)
+            .map_err(Error::Crypto),
+        _ => Err(Error::UnsupportedSigningKey),
+    }
+}
+/// Calculate a digital signature scheme.
+///
+/// Calculates the digital signature of the provided message using the signing key. [`Id::EC`],
+/// and [`Id::RSA`] keys are supported. For [`Id::RSA`] [`Padding::PKCS1_PSS`] is used.
+///
+/// # Errors
+///
+/// This function will return an error if OpenSSL could not compute the signature.
+pub(crate) fn sign_msg(skey: &PKeyRef<Private>, dgst: Digest, msg: &[u8]) -> Result<Vec<u8>> {
+    match skey.id() {
+        Id::EC => {
+            let mut sgn = Signer::new(dgst.into(), skey)?;
+            sgn.sign_oneshot_to_vec(msg).map_err(Error::Crypto)
+        }
+        Id::RSA => {
+            let mut sgn = Signer::new(dgst.into(), skey)?;
+            sgn.set_rsa_padding(Padding::PKCS1_PSS)?;
+            sgn.sign_oneshot_to_vec(msg).map_err(Error::Crypto)
+        }
+        _ => Err(Error::UnsupportedSigningKey),
+    }
+}
+
+/// Reason why a signature did not verify.
+///
+/// OpenSSL does not always provide details about a failed verification. If no detail is
+/// available [`SignatureMismatch::Signature`] is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureMismatch {
+    /// The padding of the signature is invalid
+    Padding,
+    /// The signature was created for a different digest
+    Digest,
+    /// The signature is invalid, e.g. malformed, truncated, or created by another key
+    Signature,
+}
+
+impl Display for SignatureMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Padding => write!(f, "invalid signature padding"),
+            Self::Digest => write!(f, "digest mismatch"),
+            Self::Signature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+/// Outcome of a signature verification.
+///
+/// See [`verify_signature_digest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureVerification {
+    /// The signature was verified successfully
+    Verified,
+    /// The signature did not verify
+    Mismatch {
+        /// Why the signature did not verify
+        reason: SignatureMismatch,
+    },
+}
+
+impl SignatureVerification {
+    /// Returns `true` if the signature was verified successfully.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Self::Verified)
+    }
+}
+
+impl From<bool> for SignatureVerification {
+    fn from(verified: bool) -> Self {
+        match verified {
+            true => Self::Verified,
+            false => Self::Mismatch {
+                reason: SignatureMismatch::Signature,
+            },
+        }
+    }
+}
+
+// OpenSSL library and reason codes (openssl/err.h, openssl/rsaerr.h)
+// not exported by openssl-sys
+const ERR_LIB_RSA: i32 = 4;
+const ERR_LIB_ASN1: i32 = 13;
+const ERR_LIB_EC: i32 = 16;
+const RSA_R_BAD_PAD_BYTE_COUNT: i32 = 103;
+const RSA_R_BLOCK_TYPE_IS_NOT_01: i32 = 106;
+const RSA_R_PADDING_CHECK_FAILED: i32 = 114;
+const RSA_R_FIRST_OCTET_INVALID: i32 = 133;
+const RSA_R_LAST_OCTET_INVALID: i32 = 134;
+const RSA_R_SLEN_RECOVERY_FAILED: i32 = 135;
+const RSA_R_SLEN_CHECK_FAILED: i32 = 136;
+const RSA_R_INVALID_PADDING: i32 = 138;
+const RSA_R_INVALID_TRAILER: i32 = 139;
+const RSA_R_DIGEST_DOES_NOT_MATCH: i32 = 158;
+
+/// Interpret an OpenSSL error that occurred during the final signature verification step.
+///
+/// OpenSSL reports some mismatches, like a malformed or truncated signature, as an error
+/// instead of a negative result. Depending on the key type and configuration the error stack
+/// may even be empty. Maps all errors that stem from decoding or checking the signature to
+/// [`SignatureMismatch`] and leaves everything else, e.g., provider errors, as an operational
+/// error.
+pub(crate) fn signature_mismatch(err: ErrorStack) -> Result<SignatureMismatch> {
+    let mut reason = SignatureMismatch::Signature;
+    for e in err.errors() {
+        match (e.library_code(), e.reason_code()) {
+            (
+                ERR_LIB_RSA,
+                RSA_R_BAD_PAD_BYTE_COUNT
+                | RSA_R_BLOCK_TYPE_IS_NOT_01
+                | RSA_R_PADDING_CHECK_FAILED
+                | RSA_R_FIRST_OCTET_INVALID
+                | RSA_R_LAST_OCTET_INVALID
+                | RSA_R_SLEN_RECOVERY_FAILED
+                | RSA_R_SLEN_CHECK_FAILED
+                | RSA_R_INVALID_PADDING
+                | RSA_R_INVALID_TRAILER,
+            ) => reason = SignatureMismatch::Padding,
+            (ERR_LIB_RSA, RSA_R_DIGEST_DOES_NOT_MATCH) => reason = SignatureMismatch::Digest,
+            (ERR_LIB_RSA | ERR_LIB_ASN1 | ERR_LIB_EC, _) => (),
+            _ => return Err(Error::Crypto(err)),
+        }
+    }
+    Ok(reason)
+}
+
+/// Verify the digital signature of a message.
+///
+/// Verifies the digital signature of the provided message using the signing key.
+/// [`Id::EC`] and [`Id::RSA`] keys are supported. For [`Id::RSA`] [`Padding::PKCS1_PSS`] is used.
+///
+/// # Returns
+/// [`SignatureVerification::Verified`] if the signature could be verified,
+/// [`SignatureVerification::Mismatch`] otherwise, including malformed or truncated signatures.
+///
+/// # Errors
+///
+/// This function will return an error if the key is not supported or OpenSSL could not set up
+/// the verification.
+pub fn verify_signature_digest<T: HasPublic>(
+    skey: &PKeyRef<T>,
+    dgst: Digest,
+    msg: &[u8],
+    sign: &[u8],
+) -> Result<SignatureVerification> {
+    let res = match skey.id() {
+        Id::EC => {
+            let mut ctx = Verifier::new(dgst.into(), skey)?;
+            ctx.update(msg)?;
+            ctx.verify(sign)
+        }
+        Id::RSA => {
+            let mut ctx = Verifier::new(dgst.into(), skey)?;
+            ctx.set_rsa_padding(Padding::PKCS1_PSS)?;
+            ctx.verify_oneshot(sign, msg)
+        }
+        _ => return Err(Error::UnsupportedVerificationKey),
+    };
+    match res {
+        Ok(verified) => Ok(verified.into()),
+        Err(e) => signature_mismatch(e).map(|reason| SignatureVerification::Mismatch { reason }),
+    }
+}
+
+/// Use OAEP padding with `md` as OAEP and MGF1 digest for an initialized RSA context.
+fn set_rsa_oaep<T>(ctx: &mut PkeyCtx<T>, md: MessageDigest) -> Result<()> {
+    let md = Md::from_nid(md.type_()).ok_or(Error::UnsupportedDigest(md.type_()))?;
+    ctx.set_rsa_padding(Padding::PKCS1_OAEP)?;
+    ctx.set_rsa_oaep_md(md)?;
+    ctx.set_rsa_mgf1_md(md)?;
+    Ok(())
+}
+
+/// Encrypt data with RSA-OAEP.
+///
+/// * `pub_key` - public RSA key of the recipient
+/// * `data` - data to encrypt, e.g. a symmetric key
+/// * `md` - digest used for OAEP and MGF1
+///
+/// # Errors
+///
+/// This function will return [`Error::UnsupportedEncryptionKey`] if `pub_key` is no RSA key,
+/// [`Error::RsaOaepDataLarge`] if `data` does not fit into one RSA-OAEP block, or an error if
+/// OpenSSL could not encrypt the data.
+pub fn rsa_oaep_encrypt<T: HasPublic>(
+    pub_key: &PKeyRef<T>,
+    data: &[u8],
+    md: MessageDigest,
+) -> Result<Vec<u8>> {
+    if pub_key.id() != Id::RSA {
+        return Err(Error::UnsupportedEncryptionKey);
+    }
+    // RFC 8017 7.1.1: mLen <= k - 2hLen - 2
+    let max = pub_key.size().saturating_sub(2 * md.size() + 2);
+    if data.len() > max {
+        return Err(Error::RsaOaepDataLarge {
+            size: data.len(),
+            max,
+        });
+    }
+    let mut ctx = PkeyCtx::new(pub_key)?;
+    ctx.encrypt_init()?;
+    set_rsa_oaep(&mut ctx, md)?;
+    let mut encr = Vec::new();
+    ctx.encrypt_to_vec(data, &mut encr)?;
+    Ok(encr)
+}
+
+/// Decrypt RSA-OAEP encrypted data.
+///
+/// Counterpart of [`rsa_oaep_encrypt`].
+///
+/// * `priv_key` - private RSA key of the recipient
+/// * `encr` - encrypted data
+/// * `md` - digest used for OAEP and MGF1
+///
+/// # Errors
+///
+/// This function will return [`Error::UnsupportedEncryptionKey`] if `priv_key` is no RSA key,
+/// or an error if OpenSSL could not decrypt the data, e.g. because it was encrypted for another
+/// key.
+pub fn rsa_oaep_decrypt(
+    priv_key: &PKeyRef<Private>,
+    encr: &[u8],
+    md: MessageDigest,
+) -> Result<Confidential<Vec<u8>>> {
+    if priv_key.id() != Id::RSA {
+        return Err(Error::UnsupportedEncryptionKey);
+    }
+    let mut ctx = PkeyCtx::new(priv_key)?;
+    ctx.decrypt_init()?;
+    set_rsa_oaep(&mut ctx, md)?;
+    // allocate the maximum size upfront; a reallocation would free the old buffer without
+    // zeroizing
+    let mut data = Confidential::new(vec![0; ctx.decrypt(encr, None)?]);
+    let len = ctx.decrypt(encr, Some(data.value_mut()))?;
+    data.value_mut().truncate(len);
+    Ok(data)
+}
+
+/// Encrypt a symmetric key for the holder of an RSA key pair.
+///
+/// See [`rsa_oaep_encrypt`].
+///
+/// # Errors
+///
+/// This function will return an error if the key could not be encrypted.
+pub fn wrap_sym_key_rsa<T: HasPublic>(
+    pub_key: &PKeyRef<T>,
+    key: &SymKey,
+    md: MessageDigest,
+) -> Result<Vec<u8>> {
+    rsa_oaep_encrypt(pub_key, key.value(), md)
+}
+
+/// Decrypt a symmetric key of type `key_tp` wrapped by [`wrap_sym_key_rsa`].
+///
+/// # Errors
+///
+/// This function will return an error if the key could not be decrypted or the decrypted key
+/// does not have the length of `key_tp`.
+pub fn unwrap_sym_key_rsa(
+    priv_key: &PKeyRef<Private>,
+    encr: &[u8],
+    key_tp: SymKeyType,
+    md: MessageDigest,
+) -> Result<SymKey> {
+    SymKey::try_from_data(key_tp, rsa_oaep_decrypt(priv_key, encr, md)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_test_asset, test_utils::*, PvCoreError};
+
+    #[test]
+    fn sign_ec() {
+        let (ec_key, _) = get_test_keys();
+
+        let data = "sample".as_bytes();
+        let sign = sign_msg(&ec_key, Digest::Sha512, data).unwrap();
+        assert!(sign.len() <= 139, "value is: {}", sign.len());
+
+        assert_eq!(
+            verify_signature_digest(&ec_key, Digest::Sha512, data, &sign).unwrap(),
+            SignatureVerification::Verified
+        );
+    }
+
+    #[test]
+    fn sign_digests() {
+        let (ec_key, _) = get_test_keys();
+        let data = "sample".as_bytes();
+
+        let sign = sign_msg(&ec_key, Digest::Sha3_512, data).unwrap();
+        assert_eq!(
+            verify_signature_digest(&ec_key, Digest::Sha3_512, data, &sign).unwrap(),
+            SignatureVerification::Verified
+        );
+        assert!(matches!(
+            verify_signature_digest(&ec_key, Digest::Sha384, data, &sign).unwrap(),
+            SignatureVerification::Mismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn sign_rsa_2048() {
+        let keypair = get_test_asset!("keys/rsa2048key.pem");
+        let keypair = PKey::private_key_from_pem(keypair).unwrap();
+
+        let data = "sample".as_bytes();
+        let sign = sign_msg(&keypair, Digest::Sha512, data).unwrap();
+        assert_eq!(256, sign.len());
+
+        assert_eq!(
+            verify_signature_digest(&keypair, Digest::Sha512, data, &sign).unwrap(),
+            SignatureVerification::Verified
+        );
+    }
+
+    #[test]
+    fn sign_rsa_3072() {
+        let keypair = get_test_asset!("keys/rsa3072key.pem");
+        let keypair = PKey::private_key_from_pem(keypair).unwrap();
+
+        let data = "sample".as_bytes();
+        let sign = sign_msg(&keypair, Digest::Sha512, data).unwrap();
+        assert_eq!(384, sign.len());
+
+        assert_eq!(
+            verify_signature_digest(&keypair, Digest::Sha512, data, &sign).unwrap(),
+            SignatureVerification::Verified
+        );
+    }
+
+    #[track_caller]
+    fn verify_mismatch<T: HasPublic>(key: &PKeyRef<T>, data: &[u8], sign: &[u8]) {
+        let res = verify_signature_digest(key, Digest::Sha512, data, sign).unwrap();
+        assert!(
+            matches!(res, SignatureVerification::Mismatch { .. }),
+            "value is: {res:?}"
+        );
+    }
+
+    #[test]
+    fn verify_fail_ec() {
+        let (ec_key, _) = get_test_keys();
+        let other_key = gen_ec_key(Nid::SECP521R1).unwrap();
+
+        let data = "sample".as_bytes();
+        let sign = sign_msg(&ec_key, Digest::Sha512, data).unwrap();
+
+        let mut corrupted = sign.clone();
+        let len = corrupted.len();
+        corrupted[len / 2] ^= 0xff;
+        verify_mismatch(&ec_key, data, &corrupted);
+        verify_mismatch(&other_key, data, &sign);
+        verify_mismatch(&ec_key, data, &sign[..len - 5]);
+        verify_mismatch(&ec_key, data, &[]);
+    }
+
+    #[test]
+    fn signature_mismatch_operational() {
+        let err = openssl::x509::X509::from_pem(b"no certificate").unwrap_err();
+        assert!(matches!(signature_mismatch(err), Err(Error::Crypto(_))));
+        assert_eq!(
+            signature_mismatch(ErrorStack::get()).unwrap(),
+            SignatureMismatch::Signature
+        );
+    }
+
+    #[test]
+    fn verify_fail_rsa() {
+        let keypair = get_test_asset!("keys/rsa2048key.pem");
+        let keypair = PKey::private_key_from_pem(keypair).unwrap();
+        let other_key = get_test_asset!("keys/rsa3072key.pem");
+        let other_key = PKey::private_key_from_pem(other_key).unwrap();
+
+        let data = "sample".as_bytes();
+        let sign = sign_msg(&keypair, Digest::Sha512, data).unwrap();
+
+        let mut corrupted = sign.clone();
+        let len = corrupted.len();
+        corrupted[len / 2] ^= 0xff;
+        verify_mismatch(&keypair, data, &corrupted);
+        verify_mismatch(&other_key, data, &sign);
+        verify_mismatch(&keypair, data, &sign[..len - 5]);
+        verify_mismatch(&keypair, data, &[]);
+    }
+
+    #[test]
+    fn verify_unsupported_key() {
+        let key = PKey::hmac(&[0x11; 32]).unwrap();
+        let res = verify_signature_digest(&key, Digest::Sha512, &[], &[]);
+        assert!(matches!(res, Err(Error::UnsupportedVerificationKey)));
+    }
+
+    fn rsa_keys() -> [PKey<Private>; 2] {
+        [
+            PKey::private_key_from_pem(get_test_asset!("keys/rsa2048key.pem")).unwrap(),
+            PKey::private_key_from_pem(get_test_asset!("keys/rsa3072key.pem")).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn rsa_oaep_roundtrip() {
+        for key in rsa_keys() {
+            for md in [MessageDigest::sha256(), MessageDigest::sha512()] {
+                let max = key.size() - 2 * md.size() - 2;
+                for len in [0, 32, max] {
+                    let data = vec![0x42; len];
+                    let encr = rsa_oaep_encrypt(&key, &data, md).unwrap();
+                    assert_eq!(encr.len(), key.size());
+                    let decr = rsa_oaep_decrypt(&key, &encr, md).unwrap();
+                    assert_eq!(decr.value(), &data);
+                }
+                assert!(matches!(
+                    rsa_oaep_encrypt(&key, &vec![0; max + 1], md),
+                    Err(Error::RsaOaepDataLarge { size, max: m }) if size == max + 1 && m == max
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn rsa_oaep_sym_key() {
+        let [key_2048, key_3072] = rsa_keys();
+        let pub_key =
+            PKey::public_key_from_pem(get_test_asset!("keys/rsa3072key.pub.pem")).unwrap();
+        let md = MessageDigest::sha512();
+        for tp in [SymKeyType::Aes256Gcm, SymKeyType::Aes256Xts] {
+            let sym_key = SymKey::random(tp).unwrap();
+            let wrapped = wrap_sym_key_rsa(&pub_key, &sym_key, md).unwrap();
+            let unwrapped = unwrap_sym_key_rsa(&key_3072, &wrapped, tp, md).unwrap();
+            assert_eq!(unwrapped.value(), sym_key.value());
+            assert_eq!(unwrapped.key_type(), tp);
+
+            // wrong key, digest, or key type
+            assert!(unwrap_sym_key_rsa(&key_2048, &wrapped, tp, md).is_err());
+            assert!(unwrap_sym_key_rsa(&key_3072, &wrapped, tp, MessageDigest::sha256()).is_err());
+        }
+
+        let sym_key = SymKey::random(SymKeyType::Aes256Gcm).unwrap();
+        let wrapped = wrap_sym_key_rsa(&pub_key, &sym_key, md).unwrap();
+        assert!(matches!(
+            unwrap_sym_key_rsa(&key_3072, &wrapped, SymKeyType::Aes256Xts, md),
+            Err(Error::PvCore(PvCoreError::LengthMismatch {
+                expected: 64,
+                actual: 32
+            }))
+        ));
+    }
+
+    #[test]
+    fn rsa_oaep_unsupported_key() {
+        let (ec_key, _) = get_test_keys();
+        let md = MessageDigest::sha256();
+        assert!(matches!(
+            rsa_oaep_encrypt(&ec_key, &[0; 32], md),
+            Err(Error::UnsupportedEncryptionKey)
+        ));
+        assert!(matches!(
+            rsa_oaep_decrypt(&ec_key, &[0; 256], md),
+            Err(Error::UnsupportedEncryptionKey)
+        ));
     }
 
     #[test]
@@ -595,7 +1599,6 @@ mod tests {
 
     #[test]
     fn hkdf_rfc_5869() {
-        use openssl::md::Md;
         // RFC 6869 test vector 1
         let ikm = [0x0bu8; 22];
         let salt: [u8; 13] = [
@@ -607,11 +1610,258 @@ mod tests {
             0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
             0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
         ];
-        let res: [u8; 42] = super::hkdf_rfc_5869(Md::sha256(), &ikm, &salt, &info).unwrap();
+        let res: [u8; 42] = super::hkdf_rfc_5869(Digest::Sha256, &ikm, &salt, &info).unwrap();
 
         assert_eq!(exp, res);
     }
 
+    #[test]
+    fn kdf_ctr_cavp() {
+        // NIST CAVP KBKDF, CTRLOCATION=BEFORE_FIXED, RLEN=32_BITS, PRF=HMAC_SHA256, COUNT=0
+        let ki = crate::misc::decode_hex(
+            "dd1d91b7d90b2bd3138533ce92b272fbf8a369316aefe242e659cc0ae238afe0",
+        )
+        .unwrap();
+        let fixed = crate::misc::decode_hex(concat!(
+            "01322b96b30acd197979444e468e1c5c6859bf1b1cf951b7e725303e237e46b8",
+            "64a145fab25e517b08f8683d0315bb2911d80a0e8aba17f3b413faac"
+        ))
+        .unwrap();
+        let ko = kdf_ctr_fixed(Md::sha256(), &ki, &fixed, 16).unwrap();
+        assert_eq!(
+            ko.value(),
+            &crate::misc::decode_hex("10621342bfb0fd40046c0e29f2cfdbf0").unwrap()
+        );
+    }
+
+    #[test]
+    fn kdf_ctr() {
+        let key: Vec<u8> = (0..32).collect();
+
+        // two and a half blocks, crosschecked with the OpenSSL KBKDF
+        let exp = crate::misc::decode_hex(concat!(
+            "793c8c6322234b76061bb6be858d899cf6c2cf3a2cf075f22bed2b8a17d10ff1",
+            "cd9a6cdfd6c0f4bcaea6f6700b4decc8cb28a019968a859f4e2d4ec8c686dbd5",
+            "a33e6943413e3db9b6c04a28022f936e"
+        ))
+        .unwrap();
+        let res = super::kdf_ctr(Md::sha256(), &key, b"label", b"context", 80).unwrap();
+        assert_eq!(res.value(), &exp);
+
+        let exp = crate::misc::decode_hex(concat!(
+            "027d21f54d54bb6974f0e2f0251328e71c6c514199d41c7e53141c869207ba07",
+            "07fed5892b4dc04ab395806220b69702"
+        ))
+        .unwrap();
+        let res = super::kdf_ctr(Md::sha512(), &key, b"label", b"context", 48).unwrap();
+        assert_eq!(res.value(), &exp);
+
+        // L is part of the input, a shorter output is no prefix
+        let short = super::kdf_ctr(Md::sha512(), &key, b"label", b"context", 32).unwrap();
+        assert_ne!(short.value()[..], exp[..32]);
+
+        let res = super::kdf_ctr(Md::sha256(), &key, b"label", b"context", 0).unwrap();
+        assert!(res.value().is_empty());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn kdf_ctr_large() {
+        let res = super::kdf_ctr(Md::sha256(), &[0; 32], &[], &[], 1 << 29);
+        assert!(matches!(
+            res,
+            Err(Error::KdfOutputLarge {
+                size: 0x2000_0000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn derive_keys() {
+        let secret = [0x42; 66];
+        let specs = [
+            SymKeyType::Aes256Gcm,
+            SymKeyType::Aes256Xts,
+            SymKeyType::Aes256Gcm,
+        ];
+        let keys = super::derive_keys(&secret, &specs).unwrap();
+        let okm = super::kdf_ctr(Md::sha512(), &secret, DERIVE_KEYS_LABEL, &[], 128).unwrap();
+
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys.iter().map(SymKey::key_type).collect::<Vec<_>>(), specs);
+        assert_eq!(keys[0].value(), &okm.value()[..32]);
+        assert_eq!(keys[1].value(), &okm.value()[32..96]);
+        assert_eq!(keys[2].value(), &okm.value()[96..]);
+
+        assert!(super::derive_keys(&secret, &[]).unwrap().is_empty());
+    }
+
+    /// NIST known-answer vectors: "", "abc", and the 448 bit message of FIPS 180-2
+    const DIGEST_MSGS: [&[u8]; 3] = [
+        b"",
+        b"abc",
+        b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+    ];
+
+    fn digest_kat(d: Digest, exp: [&str; 3]) {
+        for (msg, exp) in DIGEST_MSGS.iter().zip(exp) {
+            let res = digest(d, msg).unwrap();
+            assert_eq!(crate::misc::encode_hex(&res), exp, "{d} of {msg:?}");
+            assert_eq!(res.len(), d.size());
+
+            let mut ctx = DigestCtx::new(d).unwrap();
+            msg.chunks(5).for_each(|c| ctx.update(c).unwrap());
+            assert_eq!(ctx.finish().unwrap(), res);
+        }
+    }
+
+    #[test]
+    fn digest_sha256() {
+        digest_kat(
+            Digest::Sha256,
+            [
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ],
+        );
+    }
+
+    #[test]
+    fn digest_sha384() {
+        digest_kat(
+            Digest::Sha384,
+            [
+                "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+                "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05abfe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b",
+            ],
+        );
+    }
+
+    #[test]
+    fn digest_sha512() {
+        digest_kat(
+            Digest::Sha512,
+            [
+                "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+                "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c33596fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445",
+            ],
+        );
+    }
+
+    #[test]
+    fn digest_sha3_256() {
+        digest_kat(
+            Digest::Sha3_256,
+            [
+                "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+                "41c0dba2a9d6240849100376a8235e2c82e1b9998a999e21db32dd97496d3376",
+            ],
+        );
+    }
+
+    #[test]
+    fn digest_sha3_512() {
+        digest_kat(
+            Digest::Sha3_512,
+            [
+                "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26",
+                "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+                "04a371e84ecfb5b8b77cb48610fca8182dd457ce6f326a0fd3d7ec2f1e91636dee691fbe0c985302ba1b0d8dc78c086346b533b49c030d99a27daf1139d6e75e",
+            ],
+        );
+    }
+
+    #[test]
+    fn digest_ctx_stream() {
+        use std::io::Read;
+
+        // NIST long message: one million times 'a'
+        let mut ctx = DigestCtx::new(Digest::Sha256).unwrap();
+        std::io::copy(&mut std::io::repeat(b'a').take(1_000_000), &mut ctx).unwrap();
+        assert_eq!(
+            crate::misc::encode_hex(ctx.finish().unwrap()),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+
+    #[test]
+    fn digest_conversion() {
+        for d in [
+            Digest::Sha256,
+            Digest::Sha384,
+            Digest::Sha512,
+            Digest::Sha3_256,
+            Digest::Sha3_512,
+        ] {
+            let md: MessageDigest = d.into();
+            assert_eq!(md.size(), d.size());
+            assert_eq!(Digest::try_from(md).unwrap(), d);
+            assert_eq!(d.md().size(), d.size());
+        }
+        assert_eq!(Digest::Sha3_256.to_string(), "SHA3-256");
+        assert!(matches!(
+            Digest::try_from(MessageDigest::sha1()),
+            Err(Error::UnsupportedDigest(nid)) if nid == Nid::SHA1
+        ));
+    }
+
+    #[test]
+    fn gcm_tag_nist() {
+        // NIST GCM test vectors (gcmEncryptExtIV256.rsp) with empty plaintext
+        let key = SymKey::Aes256([0; 32].into());
+        let tag = gcm_tag(&key, &[0; 12], &[]).unwrap();
+        assert_eq!(
+            crate::misc::encode_hex(tag),
+            "530f8afbc74536b9a963b4f1c4cb738b"
+        );
+
+        let key = crate::misc::decode_hex(
+            "78dc4e0aaf52d935c3c01eea57428f00ca1fd475f5da86a49c8dd73d68c8e223",
+        )
+        .unwrap();
+        let key = SymKey::Aes256(<[u8; 32]>::try_from(key).unwrap().into());
+        let iv = crate::misc::decode_hex("d79cf22d504cc793c3fb6c8a").unwrap();
+        let mut aad = crate::misc::decode_hex("b96baa8c1c75a671bfb2d08d06be5f36").unwrap();
+        let exp = crate::misc::decode_hex("3e5d486aa2e30b22e040b85723a06e76").unwrap();
+
+        let tag = gcm_tag(&key, &iv, &aad).unwrap();
+        assert_eq!(tag.as_slice(), exp);
+        // same tag as a GCM encryption of no data
+        assert_eq!(encrypt_aead(&key, &iv, &aad, &[]).unwrap().tag(), exp);
+        gcm_tag_verify(&key, &iv, &aad, &exp).unwrap();
+
+        // flipped AAD bit
+        aad[3] ^= 0x10;
+        assert!(matches!(
+            gcm_tag_verify(&key, &iv, &aad, &exp),
+            Err(Error::GcmTagMismatch)
+        ));
+        aad[3] ^= 0x10;
+        // truncated tag
+        assert!(matches!(
+            gcm_tag_verify(&key, &iv, &aad, &exp[..12]),
+            Err(Error::GcmTagMismatch)
+        ));
+    }
+
+    #[test]
+    fn gcm_tag_xts() {
+        let key = SymKey::random(SymKeyType::Aes256Xts).unwrap();
+        assert!(matches!(
+            gcm_tag(&key, &[0; 12], b"aad"),
+            Err(Error::NoAeadKey)
+        ));
+        assert!(matches!(
+            gcm_tag_verify(&key, &[0; 12], b"aad", &[0; 16]),
+            Err(Error::NoAeadKey)
+        ));
+    }
+
     #[test]
     fn encrypt_decrypt_aes_256_gcm() {
         let aes_gcm_key = [
@@ -695,6 +1945,248 @@ mod tests {
         encrypt_aead(&key, &aes_gcm_iv, &aes_gcm_aad, aes_gcm_plain.value()).expect_err("");
     }
 
+    #[test]
+    fn decrypt_aead_wrong_keytype() {
+        let key = SymKey::random(SymKeyType::Aes256Xts).unwrap();
+        let err = decrypt_aead(&key, &[0; 12], &[], &[], &[0; 16]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongKeyType {
+                expected: SymKeyType::Aes256Gcm,
+                actual: SymKeyType::Aes256Xts
+            }
+        ));
+    }
+
+    #[test]
+    fn sym_key_gcm_conversion() {
+        let gcm: Aes256GcmKey = [0x17; 32].into();
+        let key = SymKey::from(gcm.clone());
+        assert_eq!(key, SymKey::Aes256(gcm.clone()));
+
+        assert_eq!(key.expect_gcm().unwrap(), &gcm);
+        assert_eq!(Aes256GcmKey::try_from(&key).unwrap(), gcm);
+        assert_eq!(Aes256GcmKey::try_from(key.clone()).unwrap(), gcm);
+
+        let err = key.expect_xts().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongKeyType {
+                expected: SymKeyType::Aes256Xts,
+                actual: SymKeyType::Aes256Gcm
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Wrong key type: expected an AES-256-XTS key, but got an AES-256-GCM key"
+        );
+        assert!(matches!(
+            Aes256XtsKey::try_from(&key),
+            Err(Error::WrongKeyType {
+                expected: SymKeyType::Aes256Xts,
+                actual: SymKeyType::Aes256Gcm
+            })
+        ));
+        assert!(matches!(
+            Aes256XtsKey::try_from(key),
+            Err(Error::WrongKeyType {
+                expected: SymKeyType::Aes256Xts,
+                actual: SymKeyType::Aes256Gcm
+            })
+        ));
+    }
+
+    #[test]
+    fn sym_key_xts_conversion() {
+        let xts: Aes256XtsKey = [0x42; 64].into();
+        let key: SymKey = xts.clone().into();
+        assert_eq!(key, SymKey::Aes256Xts(xts.clone()));
+
+        assert_eq!(key.expect_xts().unwrap(), &xts);
+        assert_eq!(Aes256XtsKey::try_from(&key).unwrap(), xts);
+        let back: Aes256XtsKey = key.clone().try_into().unwrap();
+        assert_eq!(back, xts);
+
+        let err = key.expect_gcm().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Wrong key type: expected an AES-256-GCM key, but got an AES-256-XTS key"
+        );
+        assert!(matches!(
+            Aes256GcmKey::try_from(&key),
+            Err(Error::WrongKeyType {
+                expected: SymKeyType::Aes256Gcm,
+                actual: SymKeyType::Aes256Xts
+            })
+        ));
+        assert!(matches!(
+            Aes256GcmKey::try_from(key),
+            Err(Error::WrongKeyType {
+                expected: SymKeyType::Aes256Gcm,
+                actual: SymKeyType::Aes256Xts
+            })
+        ));
+    }
+
+    fn aes_gcm_parse_round_trip(aad: &[u8], plain: &[u8]) {
+        let key = SymKey::random(SymKeyType::Aes256Gcm).unwrap();
+        let iv = [0x17; SymKeyType::AES_256_GCM_IV_LEN];
+        let encr = encrypt_aead(&key, &iv, aad, plain).unwrap();
+        let (exp_aad, exp_cipher, exp_tag) = encr.into_parts();
+
+        let buf = [exp_aad.as_slice(), &exp_cipher, &exp_tag].concat();
+        let res = AeadEncryptionResult::parse(buf.clone(), aad.len()).unwrap();
+        assert_eq!(res.aad(), aad);
+        assert_eq!(res.ciphertext(), exp_cipher);
+        assert_eq!(res.tag(), exp_tag);
+
+        let decr = decrypt_aead(&key, &iv, res.aad(), res.ciphertext(), res.tag()).unwrap();
+        assert_eq!(decr.into_plain().value(), plain);
+        assert_eq!(res.into_buf(), buf);
+    }
+
+    #[test]
+    fn aes_gcm_parse() {
+        aes_gcm_parse_round_trip(b"aad", b"plain text");
+    }
+
+    #[test]
+    fn aes_gcm_parse_empty_aad() {
+        aes_gcm_parse_round_trip(&[], b"plain text");
+    }
+
+    #[test]
+    fn aes_gcm_parse_empty_plain() {
+        aes_gcm_parse_round_trip(b"aad", &[]);
+        aes_gcm_parse_round_trip(&[], &[]);
+    }
+
+    #[test]
+    fn aes_gcm_parse_small() {
+        let tag_len = SymKeyType::AES_256_GCM_TAG_LEN;
+        assert!(matches!(
+            AeadEncryptionResult::parse(vec![0; tag_len - 1], 0),
+            Err(Error::AeadBufferSmall { size, min_size }) if size == tag_len - 1 && min_size == tag_len
+        ));
+        assert!(matches!(
+            AeadEncryptionResult::parse(vec![0; tag_len + 3], 4),
+            Err(Error::AeadBufferSmall { .. })
+        ));
+        let res = AeadEncryptionResult::parse(vec![0; tag_len + 4], 4).unwrap();
+        assert!(res.ciphertext().is_empty());
+    }
+
+    const NO_LIMITS: AeadLimits = AeadLimits {
+        aad: usize::MAX,
+        encr: usize::MAX,
+    };
+
+    #[test]
+    fn aes_gcm_decrypt_into() {
+        let key = SymKey::random(SymKeyType::Aes256Gcm).unwrap();
+        let iv = [0x17; SymKeyType::AES_256_GCM_IV_LEN];
+        let plain = [0x42; 100];
+        let res = encrypt_aead(&key, &iv, b"aad", &plain).unwrap();
+        let exp = decrypt_aead(&key, &iv, res.aad(), res.ciphertext(), res.tag())
+            .unwrap()
+            .into_plain();
+
+        // exactly-sized and oversized buffers
+        for size in [plain.len(), plain.len() + 17] {
+            let mut out = Confidential::new(vec![0xff; size]);
+            let len = decrypt_aead_into(
+                &key,
+                &iv,
+                res.aad(),
+                res.ciphertext(),
+                res.tag(),
+                NO_LIMITS,
+                &mut out,
+            )
+            .unwrap();
+            assert_eq!(len, plain.len());
+            assert_eq!(&out.value()[..len], exp.value());
+            assert_eq!(out.value()[len..], vec![0xff; size - len]);
+        }
+
+        // empty data
+        let res = encrypt_aead(&key, &iv, &[], &[]).unwrap();
+        let mut out = Confidential::new(vec![0; 0]);
+        let len = decrypt_aead_into(&key, &iv, &[], &[], res.tag(), NO_LIMITS, &mut out).unwrap();
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    fn aes_gcm_decrypt_into_limits() {
+        let key = SymKey::random(SymKeyType::Aes256Gcm).unwrap();
+        let iv = [0x17; SymKeyType::AES_256_GCM_IV_LEN];
+        let res = encrypt_aead(&key, &iv, b"aad", &[0x42; 100]).unwrap();
+        let decrypt = |limits, out: &mut Confidential<Vec<u8>>| {
+            decrypt_aead_into(
+                &key,
+                &iv,
+                res.aad(),
+                res.ciphertext(),
+                res.tag(),
+                limits,
+                out,
+            )
+        };
+        let mut out = Confidential::new(vec![0; 100]);
+
+        let limits = AeadLimits { aad: 2, encr: 100 };
+        assert!(matches!(
+            decrypt(limits, &mut out),
+            Err(Error::AeadInputLarge {
+                size: 3,
+                max: 2,
+                ..
+            })
+        ));
+        let limits = AeadLimits { aad: 3, encr: 99 };
+        let err = decrypt(limits, &mut out).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The encrypted data is too large (100 bytes). Expected at most 99 bytes"
+        );
+        let limits = AeadLimits { aad: 3, encr: 100 };
+        assert_eq!(decrypt(limits, &mut out).unwrap(), 100);
+
+        let mut small = Confidential::new(vec![0; 99]);
+        assert!(matches!(
+            decrypt(NO_LIMITS, &mut small),
+            Err(Error::AeadOutputSmall {
+                size: 99,
+                min_size: 100
+            })
+        ));
+    }
+
+    #[test]
+    fn aes_gcm_decrypt_into_tag_mismatch() {
+        let key = SymKey::random(SymKeyType::Aes256Gcm).unwrap();
+        let iv = [0x17; SymKeyType::AES_256_GCM_IV_LEN];
+        let res = encrypt_aead(&key, &iv, b"aad", &[0x42; 100]).unwrap();
+        let mut tag = res.tag().to_vec();
+        tag[0] ^= 1;
+
+        let mut out = Confidential::new(vec![0xff; 110]);
+        let err = decrypt_aead_into(
+            &key,
+            &iv,
+            res.aad(),
+            res.ciphertext(),
+            &tag,
+            NO_LIMITS,
+            &mut out,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::GcmTagMismatch));
+        // no unauthenticated data is left behind
+        assert_eq!(out.value()[..100], [0; 100]);
+        assert_eq!(out.value()[100..], [0xff; 10]);
+    }
+
     #[test]
     fn hmac_sha512_rfc_4868() {
         // use a  test vector with key=64bytes of RFC 4868:
@@ -711,7 +2203,7 @@ mod tests {
         ];
         let pkey = PKey::hmac(&key).unwrap();
 
-        let hmac = calculate_hmac(&pkey, MessageDigest::sha512(), &data).unwrap();
+        let hmac = calculate_hmac(&pkey, Digest::Sha512, &data).unwrap();
 
         assert_eq!(hmac, exp);
     }
@@ -766,4 +2258,53 @@ mod tests {
             }))
         ));
     }
+
+    #[test]
+    fn random_pool_refill() {
+        let mut pool = RandomPool::new(16);
+        assert_eq!(pool.pos, 16);
+
+        let _: [u8; 10] = pool.array().unwrap();
+        assert_eq!(pool.pos, 10);
+        // handed out bytes are removed from the pool
+        assert_eq!(&pool.buf.value()[..10], &[0; 10]);
+
+        // exactly exhausts the pool
+        let _: [u8; 6] = pool.array().unwrap();
+        assert_eq!(pool.pos, 16);
+        assert_eq!(pool.buf.value(), &[0; 16]);
+
+        // crosses the refill boundary
+        let _: [u8; 20] = pool.array().unwrap();
+        assert_eq!(pool.pos, 4);
+
+        let a: [u8; 0] = pool.array().unwrap();
+        assert_eq!(a, [0u8; 0]);
+        assert_eq!(pool.pos, 4);
+    }
+
+    #[test]
+    fn random_pool_zero_size() {
+        let mut pool = RandomPool::new(0);
+        let _: [u8; 3] = pool.array().unwrap();
+        assert_eq!(pool.buf.value().len(), 1);
+    }
+
+    #[test]
+    fn random_pool_unique() {
+        fn assert_unique(values: &[[u8; 16]]) {
+            let mut sorted = values.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            assert_eq!(sorted.len(), values.len());
+        }
+
+        let mut pool = RandomPool::new(100);
+        let pool_values: Vec<[u8; 16]> = (0..64).map(|_| pool.array().unwrap()).collect();
+        let direct_values: Vec<[u8; 16]> = (0..64).map(|_| random_array().unwrap()).collect();
+
+        assert_unique(&pool_values);
+        assert_unique(&direct_values);
+        assert_unique(&[pool_values, direct_values].concat());
+    }
 }
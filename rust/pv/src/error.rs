@@ -4,6 +4,7 @@
 
 use std::path::PathBuf;
 
+use crate::request::{SignatureMismatch, SymKeyType};
 use crate::secret::UserDataType;
 
 /// Result type for this crate
@@ -26,6 +27,9 @@ pub enum Error {
     #[error("Too many host-keys provided")]
     ManyHostkeys,
 
+    #[error("The request contains no keyslot for the provided host-key")]
+    NoMatchingKeyslot,
+
     #[error("Cannot load {ty}  from {path}")]
     X509Load {
         path: PathBuf,
@@ -33,6 +37,9 @@ pub enum Error {
         source: openssl::error::ErrorStack,
     },
 
+    #[error("{0} contains no certificate and no CRL")]
+    NoX509InFile(PathBuf),
+
     #[error("Internal (unexpected) error: {0}, caused by {1}")]
     InternalSsl(&'static str, #[source] openssl::error::ErrorStack),
 
@@ -42,9 +49,18 @@ pub enum Error {
     #[error("Verifying signatures is only supported for EC and RSA keys")]
     UnsupportedVerificationKey,
 
+    #[error("RSA-OAEP encryption is only supported for RSA keys")]
+    UnsupportedEncryptionKey,
+
+    #[error("The data is too large for RSA-OAEP ({size} bytes). Expected at most {max} bytes for this key and digest")]
+    RsaOaepDataLarge { size: usize, max: usize },
+
     #[error("Provided binary request is too small")]
     BinRequestSmall,
 
+    #[error("The binary request is truncated: It is {size} bytes long, but must be {expected} bytes long")]
+    BinRequestTruncated { size: u64, expected: usize },
+
     #[error("No Configuration UID found: {0}")]
     NoCuid(String),
 
@@ -55,6 +71,22 @@ pub enum Error {
     #[error("Decryption failed. Probably due to a GCM tag mismatch.")]
     GcmTagMismatch,
 
+    #[error("The encrypted data is too small ({size} bytes). Expected at least {min_size} bytes")]
+    AeadBufferSmall { size: usize, min_size: usize },
+
+    #[error("The {what} is too large ({size} bytes). Expected at most {max} bytes")]
+    AeadInputLarge {
+        what: &'static str,
+        size: usize,
+        max: usize,
+    },
+
+    #[error("The KDF output is too large ({size} bytes). Expected at most {max} bytes")]
+    KdfOutputLarge { size: usize, max: usize },
+
+    #[error("The output buffer is too small ({size} bytes). Expected at least {min_size} bytes")]
+    AeadOutputSmall { size: usize, min_size: usize },
+
     #[error("Invalid {0} user-data for signing provided. Max {max} bytes allowed", max=.0.max())]
     AsrcbInvSgnUserData(UserDataType),
 
@@ -67,13 +99,27 @@ pub enum Error {
     #[error("Input does not contain an add-secret request version 1")]
     BinAsrcbInvVersion,
 
+    #[error("The authenticated area of the add-secret request is {size} bytes long, but must be {expected} bytes long for {nks} keyslot(s) and the secret header")]
+    BinAsrcbAadSize {
+        size: usize,
+        expected: usize,
+        nks: usize,
+    },
+
+    #[error("The encrypted area of the add-secret request is {size} bytes long, but must be {expected} bytes long for a secret of {secret_len} bytes")]
+    BinAsrcbEncrSize {
+        size: usize,
+        expected: usize,
+        secret_len: u32,
+    },
+
     #[error("Provided user-data key type ({key}) does not match with the user-data ({kind})")]
     AsrcbUserDataKeyMismatch { key: String, kind: UserDataType },
 
     #[error(
-        "The user-defined request signature could not be verified with the provided certificate"
+        "The user-defined request signature did not verify with the provided certificate ({0})"
     )]
-    AsrcbUserDataSgnFail,
+    AsrcbUserDataSgnFail(SignatureMismatch),
 
     #[error("The provided Host Key Document in '{hkd}' is not in PEM or DER format")]
     HkdNotPemOrDer {
@@ -84,9 +130,38 @@ pub enum Error {
     #[error("The provided host key document in {0} contains no certificate!")]
     NoHkdInFile(String),
 
+    #[error("The host-key document in '{hkd}' could not be verified: {source}")]
+    HkdVerifyFile { hkd: String, source: Box<Self> },
+
     #[error("Invalid input size ({0}) for boot hdr")]
     InvBootHdrSize(usize),
 
+    #[error("Input does not contain a Secure Execution header")]
+    NoSeHdr,
+
+    #[error("Input does not start with a Secure Execution header: Invalid magic")]
+    SeHdrInvMagic,
+
+    #[error("Unsupported Secure Execution header version {0:#x}")]
+    SeHdrUnsupportedVersion(u32),
+
+    #[error("The Secure Execution header is truncated: It is {size} bytes long, but must be {expected} bytes long")]
+    SeHdrTruncated { size: usize, expected: usize },
+
+    #[error("Invalid dump key: Must be 64 bytes long, but is {0} bytes long")]
+    DumpKeySize(usize),
+
+    #[error(
+        "The dump page {index} is truncated: It is {size} bytes long, but must be 4096 bytes long"
+    )]
+    DumpPageShort { index: u64, size: usize },
+
+    #[error("Invalid page state in the dump tweak component {}", crate::misc::encode_hex(.0))]
+    DumpInvPageState([u8; 16]),
+
+    #[error("Cannot decrypt the dump page")]
+    DumpDecrypt(#[source] openssl::error::ErrorStack),
+
     #[error("Input does not contain an attestation request")]
     NoArcb,
 
@@ -106,6 +181,27 @@ pub enum Error {
     )]
     AddDataMissing(&'static str),
 
+    #[error(
+        "The additional data is truncated: {field} needs {size} bytes, but only {available} bytes are left"
+    )]
+    AddDataTruncated {
+        field: &'static str,
+        size: usize,
+        available: usize,
+    },
+
+    #[error("Invalid add-secret request tag: Must be 16 bytes long, but is {0} bytes long")]
+    InvSecretTagSize(usize),
+
+    #[error("Cannot add a secret to a locked secret store")]
+    SecretStoreLocked,
+
+    #[error("Invalid add-secret receipt: {0}")]
+    InvAddSecretReceipt(&'static str),
+
+    #[error("The add-secret receipt does not continue the secret store: {0}")]
+    AddSecretReceiptMismatch(&'static str),
+
     #[error("An ASCII string was expected, but non-ASCII characters were received.")]
     NonAscii,
 
@@ -133,8 +229,17 @@ pub enum Error {
     #[error("No Authenticated Encryption with Associated Data (AEAD) key")]
     NoAeadKey,
 
+    #[error("Wrong key type: expected an {expected} key, but got an {actual} key")]
+    WrongKeyType {
+        expected: SymKeyType,
+        actual: SymKeyType,
+    },
+
     #[error("Unsupported cipher: {:?}", .0.as_raw())]
     UnsupportedCipher(Nid),
+
+    #[error("Unsupported digest: {:?}", .0.as_raw())]
+    UnsupportedDigest(Nid),
 }
 
 // used in macros
@@ -149,12 +254,26 @@ impl Error {
 #[derive(thiserror::Error, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum HkdVerifyErrorType {
-    #[error("Signature verification failed")]
-    Signature,
+    #[error("Signature verification failed: {0}")]
+    Signature(SignatureMismatch),
     #[error("No valid CRL found")]
     NoCrl,
+    #[error("No CRL found for issuer '{0}'")]
+    MissingCrl(String),
+    #[error("The CRL of issuer '{0}' is expired")]
+    CrlExpired(String),
     #[error("Host-key document is revoked.")]
     HkdRevoked,
+    #[error("Host-key document is revoked according to the OCSP responder.")]
+    OcspRevoked,
+    #[error("The OCSP responder does not know the host-key document.")]
+    OcspUnknown,
+    #[error("Invalid OCSP response: {0}")]
+    OcspInvalidResponse(&'static str),
+    #[error("The host-key document specifies no OCSP responder")]
+    OcspNoResponder,
+    #[error("No OCSP responder of the host-key document could be reached")]
+    OcspUnreachable,
     #[error("Not enough bits of security. ({0}, {1} expected)")]
     SecurityBits(u32, u32),
     #[error("Authority Key Id mismatch")]
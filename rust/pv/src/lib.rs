@@ -33,6 +33,11 @@
 //!
 //! # Verify
 //! [`attest::AttestationItems`], [`attest::AttestationMeasurement`]
+//!
+//! # Dump
+//!
+//! This crate provides functionalities for decrypting the memory of Secure Execution dumps. See
+//! [`dump::DumpDecryptor`]
 mod brcb;
 mod crypto;
 mod error;
@@ -41,6 +46,7 @@ mod pem_utils;
 mod req;
 mod utils;
 mod uvattest;
+mod uvdump;
 mod uvsecret;
 mod verify;
 
@@ -69,6 +75,24 @@ pub mod attest {
             AttestationAuthenticated, AttestationFlags, AttestationRequest, AttestationVersion,
         },
         attest::{AttestationItems, AttestationMeasurement},
+        diff::{diff, AttestationDiff, DecryptedAttestation, FieldChange, FieldDiff},
+        reply::ReplySizes,
+        secret_store::{
+            secret_store_hash, secret_store_hash_requests, SecretStoreState, SECRET_TAG_SIZE,
+        },
+    };
+}
+
+/// Functionalities for reading Secure Execution headers
+pub mod se_hdr {
+    pub use crate::brcb::SeHdr;
+}
+
+/// Functionalities for decrypting Secure Execution dumps
+pub mod dump {
+    pub use crate::uvdump::{
+        dump_tweak, DumpDecryptor, DumpPageState, DumpReader, DumpTweakComponent, DUMP_PAGE_SIZE,
+        DUMP_TWEAK_LEN,
     };
 }
 
@@ -81,7 +105,7 @@ pub mod pem {
 pub mod misc {
     pub use pv_core::misc::*;
 
-    pub use crate::utils::read_certs;
+    pub use crate::utils::{read_certs, read_x509_bundle};
 }
 
 pub use error::{Error, Result};
@@ -94,12 +118,18 @@ pub mod request {
     pub use crate::{
         brcb::{seek_se_hdr_start, BootHdrTags, SeImgMetaData},
         crypto::{
-            decrypt_aead, derive_aes256_gcm_key, encrypt_aead, gen_ec_key, random_array,
-            AeadDecryptionResult, AeadEncryptionResult, Aes256GcmKey, Aes256XtsKey, SymKey,
-            SymKeyType, SHA_512_HASH_LEN,
+            decrypt_aead, decrypt_aead_into, derive_aes256_gcm_key, derive_keys, digest,
+            encrypt_aead, gcm_tag, gcm_tag_verify, gen_ec_key, kdf_ctr, random_array,
+            rsa_oaep_decrypt, rsa_oaep_encrypt, unwrap_sym_key_rsa, verify_signature_digest,
+            wrap_sym_key_rsa, AeadDecryptionResult, AeadEncryptionResult, AeadLimits, Aes256GcmKey,
+            Aes256XtsKey, Digest, DigestCtx, RandomPool, SignatureMismatch, SignatureVerification,
+            SymKey, SymKeyType, SHA_512_HASH_LEN,
         },
         req::{EcPubKeyCoord, Encrypt, Keyslot, ReqEncrCtx, Request},
-        verify::{CertVerifier, HkdVerifier, NoVerifyHkd},
+        verify::{
+            CertVerifier, CertVerifierBuilder, CertificateKind, CertificateValidity, CrlValidity,
+            HkdVerifier, NoVerifyHkd, OcspMode, Timestamp, VerificationReport,
+        },
     };
 
     /// Reexports some useful OpenSSL symbols
@@ -120,9 +150,10 @@ pub mod secret {
     pub use pv_core::secret::*;
 
     pub use crate::uvsecret::{
-        asrcb::{AddSecretFlags, AddSecretRequest, AddSecretVersion},
+        asrcb::{AddSecretContext, AddSecretFlags, AddSecretRequest, AddSecretVersion, AsrcbInfo},
         ext_secret::ExtSecret,
         guest_secret::GuestSecret,
+        receipt::AddSecretReceipt,
         retr_secret::{IbmProtectedKey, RetrievedSecret},
         user_data::verify_asrcb_and_get_user_data,
     };
@@ -8,8 +8,10 @@
 mod akid;
 mod bio;
 mod crl;
+mod ocsp;
 mod stackable_crl;
 
 pub use akid::*;
 pub use bio::*;
 pub use crl::*;
+pub use ocsp::*;
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use foreign_types::ForeignTypeRef;
+use openssl::{
+    error::ErrorStack,
+    ocsp::{OcspBasicResponseRef, OcspRequestRef},
+};
+use std::ffi::{c_int, c_uchar};
+
+mod ffi {
+    extern "C" {
+        pub fn OCSP_request_add1_nonce(
+            req: *mut openssl_sys::OCSP_REQUEST,
+            val: *mut super::c_uchar,
+            len: super::c_int,
+        ) -> super::c_int;
+        pub fn OCSP_check_nonce(
+            req: *mut openssl_sys::OCSP_REQUEST,
+            bs: *mut openssl_sys::OCSP_BASICRESP,
+        ) -> super::c_int;
+    }
+}
+
+/// Result of the comparison of the nonces of an OCSP request and its response
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OcspNonceCheck {
+    /// Nonce present in both and equal
+    Equal,
+    /// Nonce present in both but not equal
+    NotEqual,
+    /// Nonce absent in both
+    Absent,
+    /// Nonce only present in the response
+    ResponseOnly,
+    /// Nonce only present in the request
+    RequestOnly,
+}
+
+pub trait OcspRequestExtension {
+    fn add_nonce(&mut self, nonce: &[u8]) -> Result<(), ErrorStack>;
+    fn check_nonce(&self, bs: &OcspBasicResponseRef) -> OcspNonceCheck;
+}
+
+impl OcspRequestExtension for OcspRequestRef {
+    /// Add the nonce extension with the given value to the request
+    fn add_nonce(&mut self, nonce: &[u8]) -> Result<(), ErrorStack> {
+        // OpenSSL would choose a random nonce for empty values
+        assert!(!nonce.is_empty());
+        // Panic: nonces are a few bytes long
+        let len: c_int = nonce.len().try_into().unwrap();
+        unsafe {
+            // OpenSSL copies the value and does not modify it
+            let r = ffi::OCSP_request_add1_nonce(self.as_ptr(), nonce.as_ptr() as *mut _, len);
+            if r <= 0 {
+                Err(ErrorStack::get())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Compare the nonce of the request with the nonce of a basic response
+    fn check_nonce(&self, bs: &OcspBasicResponseRef) -> OcspNonceCheck {
+        match unsafe { ffi::OCSP_check_nonce(self.as_ptr(), bs.as_ptr()) } {
+            1 => OcspNonceCheck::Equal,
+            2 => OcspNonceCheck::Absent,
+            3 => OcspNonceCheck::ResponseOnly,
+            -1 => OcspNonceCheck::RequestOnly,
+            _ => OcspNonceCheck::NotEqual,
+        }
+    }
+}
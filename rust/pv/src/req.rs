@@ -1,13 +1,13 @@
 // SPDX-License-Identifier: MIT
 //
 // Copyright IBM Corp. 2023
-use std::mem::size_of;
+use std::{mem::size_of, ops::Range};
 
 use openssl::{
     bn::{BigNum, BigNumContext},
     ec::{EcGroup, EcGroupRef, EcKey, EcPointRef},
     error::ErrorStack,
-    hash::{DigestBytes, MessageDigest},
+    hash::DigestBytes,
     nid::Nid,
     pkey::{PKey, PKeyRef, Private, Public},
 };
@@ -17,8 +17,8 @@ use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U32};
 use crate::{
     assert_size,
     crypto::{
-        decrypt_aead, derive_aes256_gcm_key, encrypt_aead, gen_ec_key, hash, random_array,
-        AeadEncryptionResult, SymKey, SymKeyType,
+        decrypt_aead_into, derive_aes256_gcm_key, encrypt_aead, gen_ec_key, hash, random_array,
+        AeadEncryptionResult, AeadLimits, Digest, SymKey, SymKeyType,
     },
     misc::to_u32,
     request::Confidential,
@@ -89,6 +89,23 @@ impl Keyslot {
     pub fn new(hostkey: PKey<Public>) -> Self {
         Self(hostkey)
     }
+
+    /// Returns the public host-key hash (PHKH) of `host_key`.
+    ///
+    /// This is the hash a keyslot for `host_key` starts with.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `host_key` is no EC key or OpenSSL could not compute
+    /// the hash.
+    pub fn host_key_hash<K>(host_key: K) -> Result<[u8; Self::PHKH_SIZE as usize]>
+    where
+        K: TryInto<EcPubKeyCoord, Error = ErrorStack>,
+    {
+        let hash = host_key.try_into()?.sha256()?;
+        // Panic: SHA-256 hashes are PHKH_SIZE bytes long
+        Ok(hash.as_ref().try_into().unwrap())
+    }
 }
 
 impl Encrypt for Keyslot {
@@ -112,15 +129,80 @@ impl Encrypt for Keyslot {
         let derived_key = derive_aes256_gcm_key(priv_key, &self.0)?;
         let mut wrpk_and_kst =
             encrypt_aead(&derived_key.into(), &[0; 12], &[], prot_key)?.into_buf();
-        let phk: EcPubKeyCoord = self.0.as_ref().try_into()?;
-
         to.reserve(80);
-        to.extend_from_slice(&hash(MessageDigest::sha256(), phk.as_ref())?);
+        to.extend_from_slice(&Self::host_key_hash(self.0.as_ref())?);
         to.append(&mut wrpk_and_kst);
         Ok(())
     }
 }
 
+/// Size of a binary [`Keyslot`]
+pub(crate) const KEYSLOT_SIZE: usize = 0x50;
+
+/// Returns the host-key hashes of the first `nks` binary keyslots in `keyslots`.
+///
+/// # Errors
+///
+/// This function will return an error if `keyslots` is too small for `nks` keyslots.
+pub(crate) fn keyslot_hashes(
+    keyslots: &[u8],
+    nks: usize,
+) -> Result<Vec<[u8; Keyslot::PHKH_SIZE as usize]>> {
+    let keyslots = keyslots
+        .get(..nks * KEYSLOT_SIZE)
+        .ok_or(Error::BinRequestSmall)?;
+    Ok(keyslots
+        .chunks_exact(KEYSLOT_SIZE)
+        // Panic: chunks are KEYSLOT_SIZE bytes long, which is larger than PHKH_SIZE
+        .map(|ks| ks[..Keyslot::PHKH_SIZE as usize].try_into().unwrap())
+        .collect())
+}
+
+/// Unwraps the request protection key from binary keyslots.
+///
+/// Searches `keyslots` for the keyslot of `host_key` and decrypts the protection key in the same
+/// way as the Ultravisor does. `cust_pub_key` are the public coordinates of the customer key the
+/// keyslots were wrapped with.
+///
+/// # Errors
+///
+/// This function will return an error if there is no keyslot for `host_key` or the keyslot could
+/// not be decrypted.
+pub(crate) fn unwrap_keyslot(
+    cust_pub_key: &[u8],
+    keyslots: &[u8],
+    host_key: &PKeyRef<Private>,
+) -> Result<SymKey> {
+    let cust_pub_key: [u8; 160] = cust_pub_key
+        .try_into()
+        .map_err(|_| Error::BinRequestSmall)?;
+    let cust_pub_key: PKey<Public> = EcPubKeyCoord(cust_pub_key).try_into()?;
+    let phkh = Keyslot::host_key_hash(host_key)?;
+
+    let keyslot = keyslots
+        .chunks_exact(KEYSLOT_SIZE)
+        .find(|ks| ks[..Keyslot::PHKH_SIZE as usize] == phkh)
+        .ok_or(Error::NoMatchingKeyslot)?;
+    let (wrpk, kst) = keyslot[Keyslot::PHKH_SIZE as usize..].split_at(0x20);
+
+    let derived_key = derive_aes256_gcm_key(host_key, &cust_pub_key)?;
+    let mut prot_key = Confidential::new(vec![0; wrpk.len()]);
+    let limits = AeadLimits {
+        aad: 0,
+        encr: SymKeyType::AES_256_GCM_KEY_LEN,
+    };
+    decrypt_aead_into(
+        &derived_key.into(),
+        &[0; 12],
+        &[],
+        wrpk,
+        kst,
+        limits,
+        &mut prot_key,
+    )?;
+    SymKey::try_from_data(SymKeyType::Aes256Gcm, prot_key)
+}
+
 /// Context used to manage the encryption of requests.
 /// Intended to be used by [`Request`] implementations
 #[derive(Debug)]
@@ -288,7 +370,7 @@ impl EcPubKeyCoord {
     ///
     /// If [`EcPubKeyCoord`] was built from a host-key, this value is the public host-key hash.
     pub fn sha256(&self) -> Result<DigestBytes> {
-        hash(MessageDigest::sha256(), self.as_ref())
+        hash(Digest::Sha256, self.as_ref())
     }
 
     /// Construct a [`EcPubKeyCoord`]
@@ -427,6 +509,17 @@ pub trait Request {
     ///
     /// Must be called at least once, otherwise {`Request::encrypt`} will fail
     fn add_hostkey(&mut self, hostkey: PKey<Public>);
+
+    /// Add a keyslot for each of the host-keys to this request
+    ///
+    /// All keyslots wrap the same request protection key. A request with keyslots for several
+    /// host-keys can be used on each of the corresponding machines, e.g., if the guest may be
+    /// relocated.
+    fn add_hostkeys(&mut self, hostkeys: &[PKey<Public>]) {
+        hostkeys
+            .iter()
+            .for_each(|hostkey| self.add_hostkey(hostkey.clone()))
+    }
 }
 
 /// A struct to represent some parts of a binary/encrypted request.
@@ -440,32 +533,53 @@ pub(crate) struct BinReqValues<'a> {
     tag: &'a [u8],
     version: u32,
     len: usize,
+    nks: u8,
 }
 impl<'a> BinReqValues<'a> {
     pub(crate) const TAG_LEN: usize = SymKeyType::AES_256_GCM_TAG_LEN;
+    pub(crate) const HDR_LEN: usize = size_of::<RequestHdr>();
 
-    /// Get the locations from this request.
-    ///
-    /// Does minimal sanity test, just tests to prevent panics.
-    /// `req` may be larger than the actual request.
-    pub(crate) fn get(req: &'a [u8]) -> Result<Self> {
-        let hdr = RequestHdr::read_from_prefix(req).ok_or(Error::BinRequestSmall)?;
+    /// Get the request length, size of the encrypted area, and size of the authenticated area
+    /// from the request header.
+    fn sizes(hdr: &RequestHdr) -> Result<(usize, usize, usize)> {
         let rql = hdr.rql.get() as usize;
         let sea = hdr.sea.get() as usize;
 
-        if rql < req.len() || sea + Self::TAG_LEN > rql {
+        if sea + Self::TAG_LEN > rql {
             return Err(Error::BinRequestSmall);
         }
         let aad_size = rql - sea - Self::TAG_LEN;
         if aad_size < size_of::<RequestHdr>() {
             return Err(Error::BinRequestSmall);
         }
+        Ok((rql, sea, aad_size))
+    }
+
+    /// Get the location of the tag from the header `hdr` of a request.
+    ///
+    /// Does the same sanity tests as [`Self::get`], except for the size of the actual request.
+    pub(crate) fn tag_range(hdr: &[u8]) -> Result<Range<usize>> {
+        let hdr = RequestHdr::read_from_prefix(hdr).ok_or(Error::BinRequestSmall)?;
+        let (rql, _, _) = Self::sizes(&hdr)?;
+        Ok(rql - Self::TAG_LEN..rql)
+    }
+
+    /// Get the locations from this request.
+    ///
+    /// Does minimal sanity test, just tests to prevent panics.
+    /// `req` may be larger than the actual request.
+    pub(crate) fn get(req: &'a [u8]) -> Result<Self> {
+        let hdr = RequestHdr::read_from_prefix(req).ok_or(Error::BinRequestSmall)?;
+        let (rql, sea, aad_size) = Self::sizes(&hdr)?;
+        if rql > req.len() {
+            return Err(Error::BinRequestSmall);
+        }
 
         let iv = &req[0x10..0x1c];
         let aad = &req[..aad_size];
         let req_dep_aad = &req[size_of::<RequestHdr>()..aad_size];
         let encr = &req[aad_size..(aad_size + sea)];
-        let tag = &req[rql - Self::TAG_LEN..];
+        let tag = &req[rql - Self::TAG_LEN..rql];
 
         Ok(Self {
             iv,
@@ -475,6 +589,7 @@ impl<'a> BinReqValues<'a> {
             tag,
             version: hdr.rqvn.get(),
             len: rql,
+            nks: hdr.nks,
         })
     }
 
@@ -488,15 +603,36 @@ impl<'a> BinReqValues<'a> {
         self.len
     }
 
+    /// Returns the size of the authenticated area of this [`BinReqValues`].
+    pub(crate) fn aad_len(&self) -> usize {
+        self.aad.len()
+    }
+
+    /// Returns the number of keyslots of this [`BinReqValues`].
+    pub(crate) fn nks(&self) -> usize {
+        self.nks as usize
+    }
+
     /// Returns the size of the encrypted area
     pub(crate) fn sea(&self) -> u32 {
         self.encr.len() as u32
     }
 
     /// Decrypts the encrypted area with the provided key
-    pub(crate) fn decrypt(&self, key: &SymKey) -> Result<Confidential<Vec<u8>>> {
-        let result = decrypt_aead(key, self.iv, self.aad, self.encr, self.tag)?;
-        Ok(result.into_plain())
+    ///
+    /// `limits` are the maximum sizes the request type allows.
+    pub(crate) fn decrypt(
+        &self,
+        key: &SymKey,
+        limits: AeadLimits,
+    ) -> Result<Confidential<Vec<u8>>> {
+        // do not allocate more than allowed, decrypt_aead_into reports the violation
+        let mut conf = Confidential::new(vec![0; self.encr.len().min(limits.encr)]);
+        let len = decrypt_aead_into(
+            key, self.iv, self.aad, self.encr, self.tag, limits, &mut conf,
+        )?;
+        conf.value_mut().truncate(len);
+        Ok(conf)
     }
 
     /// Returns a reference to the request dependent authenticated area of this [`BinReqValues`]
@@ -511,6 +647,11 @@ impl<'a> BinReqValues<'a> {
         T::ref_from_prefix(self.req_dep_aad)
     }
 
+    /// Returns a reference to the request dependent authenticated area of this [`BinReqValues`].
+    pub(crate) fn req_dep_aad_bytes(&self) -> &[u8] {
+        self.req_dep_aad
+    }
+
     /// Returns a reference to the tag of this [`BinReqValues`].
     pub(crate) fn tag(&self) -> &[u8] {
         self.tag
@@ -629,6 +770,34 @@ mod tests {
         assert_eq!(hdr_bin, &hdr_bin_exp);
     }
 
+    #[test]
+    fn bin_req_values() {
+        let mut req = RequestHdr::new(0x100, 0x60, [0x11; 12], 1, 0x10, None)
+            .as_bytes()
+            .to_vec();
+        req.extend_from_slice(&[0x22; 0x10]); // request type dependent AAD
+        req.extend_from_slice(&[0x33; 0x10]); // encrypted data
+        req.extend_from_slice(&[0x44; 0x10]); // tag
+
+        let values = BinReqValues::get(&req).unwrap();
+        assert_eq!(values.len(), 0x60);
+        assert_eq!(values.sea(), 0x10);
+        assert_eq!(values.req_dep_aad_bytes(), [0x22; 0x10]);
+        assert_eq!(values.tag(), [0x44; 0x10]);
+
+        // data behind the request is not part of it
+        let mut long = req.clone();
+        long.extend_from_slice(&[0x55; 0x10]);
+        let values = BinReqValues::get(&long).unwrap();
+        assert_eq!(values.len(), 0x60);
+        assert_eq!(values.tag(), [0x44; 0x10]);
+
+        assert!(matches!(
+            BinReqValues::get(&req[..0x5f]),
+            Err(Error::BinRequestSmall)
+        ));
+    }
+
     #[test]
     fn keyslot() {
         let (cust_key, host_key) = get_test_keys();
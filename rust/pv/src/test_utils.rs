@@ -122,3 +122,18 @@ fn get_keypair(pub_coords: &[u8], priv_num: &[u8]) -> Result<PKey<Private>, Erro
     key.check_key()?;
     PKey::from_ec_key(key)
 }
+
+/// TEST ONLY! Asserts that no part of `secret` shows up in `summary`
+///
+/// Checks every four consecutive bytes of `secret`, raw and hex-encoded
+pub fn assert_no_secret(summary: &str, secret: &[u8]) {
+    let lower = summary.to_lowercase();
+    for part in secret.windows(4) {
+        let hex: String = part.iter().map(|b| format!("{b:02x}")).collect();
+        assert!(!lower.contains(&hex), "'{summary}' contains secret bytes");
+        assert!(
+            !summary.as_bytes().windows(4).any(|w| w == part),
+            "'{summary}' contains raw secret bytes"
+        );
+    }
+}
@@ -32,6 +32,29 @@ pub fn read_certs<T: AsRef<[u8]>>(buf: T) -> Result<Vec<X509>, ErrorStack> {
         .or_else(|_| X509::stack_from_pem(buf.as_ref()))
 }
 
+/// Read all certificates and CRLs from the buffer.
+///
+/// `buf` contains either one `DER` encoded certificate or CRL, or any number of `PEM` blocks in
+/// any order. `PEM` blocks of other types are ignored.
+///
+/// # Errors
+///
+/// This function will return an error if the underlying OpenSSL implementation cannot parse `buf`
+/// as `DER` or `PEM`.
+pub fn read_x509_bundle<T: AsRef<[u8]>>(buf: T) -> Result<(Vec<X509>, Vec<X509Crl>)> {
+    use crate::openssl_extensions::StackableX509Crl;
+    let buf = buf.as_ref();
+    if let Ok(crt) = X509::from_der(buf) {
+        return Ok((vec![crt], vec![]));
+    }
+    if let Ok(crl) = X509Crl::from_der(buf) {
+        return Ok((vec![], vec![crl]));
+    }
+    let certs = X509::stack_from_pem(buf)?;
+    let crls = StackableX509Crl::stack_from_pem(buf)?;
+    Ok((certs, crls))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::test_utils::*;
@@ -55,4 +78,18 @@ mod tests {
         assert_eq!(super::read_certs(crt_der).unwrap().len(), 1);
         assert_eq!(super::read_certs(fail).unwrap().len(), 0);
     }
+
+    #[test]
+    fn read_x509_bundle() {
+        let (certs, crls) = super::read_x509_bundle(get_cert_asset("bundle.pem")).unwrap();
+        assert_eq!(certs.len(), 3);
+        assert_eq!(crls.len(), 2);
+
+        let (certs, crls) = super::read_x509_bundle(get_cert_asset("der.crt")).unwrap();
+        assert_eq!((certs.len(), crls.len()), (1, 0));
+        let (certs, crls) = super::read_x509_bundle(get_cert_asset("der.crl")).unwrap();
+        assert_eq!((certs.len(), crls.len()), (0, 1));
+        let (certs, crls) = super::read_x509_bundle(get_cert_asset("ibm.key")).unwrap();
+        assert_eq!((certs.len(), crls.len()), (0, 0));
+    }
 }
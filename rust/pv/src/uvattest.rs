@@ -5,5 +5,8 @@
 pub mod additional;
 pub mod arcb;
 pub mod attest;
+pub mod diff;
+pub mod reply;
+pub mod secret_store;
 
 type AttNonce = [u8; 16];
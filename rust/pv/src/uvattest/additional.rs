@@ -14,7 +14,7 @@ use super::arcb::AttestationFlags;
 /// Hash for additional-data stuff used for parsing [`AdditionalData`]
 pub(super) const PHKH_SIZE: u32 = 0x20;
 static_assert!(Keyslot::PHKH_SIZE == PHKH_SIZE);
-pub(super) const SECRET_STORE_HASH_SIZE: u32 = 0x40;
+pub(crate) const SECRET_STORE_HASH_SIZE: u32 = 0x40;
 pub(super) const FW_STATE_SIZE: u32 = 0x140;
 
 /// Additional-data of an Attestation Request
@@ -73,7 +73,12 @@ fn read_value<'a>(
     let size = size as usize;
     match read {
         true if data.len() >= size => Ok((Some(&data[..size]), &data[size..])),
-        true => Err(Error::AddDataMissing(name)),
+        true if data.is_empty() => Err(Error::AddDataMissing(name)),
+        true => Err(Error::AddDataTruncated {
+            field: name,
+            size,
+            available: data.len(),
+        }),
         false => Ok((None, data)),
     }
 }
@@ -163,7 +168,9 @@ impl<'a> AdditionalData<&'a [u8]> {
     /// # Error
     ///
     /// Fails if there is a mismatch between the data and the flags. Should not happen after a
-    /// successful attestation verification.
+    /// successful attestation verification. The error names the first field that could not be
+    /// read. Data after the fields requested by `flags` is kept as
+    /// [`AdditionalData::unrecognized`].
     pub fn from_slice(data: &'a [u8], flags: &AttestationFlags) -> Result<Self> {
         let (image_phkh, data) = read_value(data, PHKH_SIZE, flags.image_phkh(), "Image PHKH")?;
         let (attestation_phkh, data) =
@@ -172,10 +179,14 @@ impl<'a> AdditionalData<&'a [u8]> {
             data,
             SECRET_STORE_HASH_SIZE,
             flags.secret_store_hash(),
-            "Secret store state",
+            "Secret store hash",
+        )?;
+        let (firmware_state, data) = read_value(
+            data,
+            FW_STATE_SIZE,
+            flags.firmware_state(),
+            "Firmware state",
         )?;
-        let (firmware_state, data) =
-            read_value(data, FW_STATE_SIZE, flags.firmware_state(), "Firmware hash")?;
         let unrecognized = (!data.is_empty()).then_some(data);
 
         Ok(Self {
@@ -285,6 +296,71 @@ mod test {
             ],
         );
     }
+    fn all_flags() -> AttestationFlags {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        flags.set_attest_phkh();
+        flags.set_secret_store_hash();
+        flags.set_firmware_state();
+        flags
+    }
+
+    /// Additional-data with all known fields, filled with 1, 2, 3, 4, and `trailing`
+    fn blob(trailing: &[u8]) -> Vec<u8> {
+        [
+            (1, PHKH_SIZE),
+            (2, PHKH_SIZE),
+            (3, SECRET_STORE_HASH_SIZE),
+            (4, FW_STATE_SIZE),
+        ]
+        .into_iter()
+        .flat_map(|(v, size)| vec![v; size as usize])
+        .chain(trailing.iter().copied())
+        .collect()
+    }
+
+    #[test]
+    fn from_slice_all() {
+        let data = blob(&[0xde, 0xad]);
+        let add = AdditionalData::from_slice(&data, &all_flags()).unwrap();
+        assert_eq!(
+            add.image_public_host_key_hash(),
+            Some(&[1; 0x20].as_slice())
+        );
+        assert_eq!(
+            add.attestation_public_host_key_hash(),
+            Some(&[2; 0x20].as_slice())
+        );
+        assert_eq!(add.secret_store_hash(), Some(&[3; 0x40].as_slice()));
+        assert_eq!(add.firmware_state(), Some(&[4; 0x140].as_slice()));
+        assert_eq!(add.unrecognized(), Some(&[0xde, 0xad].as_slice()));
+
+        let data = blob(&[]);
+        let add = AdditionalData::from_slice(&data, &all_flags()).unwrap();
+        assert!(add.unrecognized().is_none());
+    }
+
+    #[test]
+    fn from_slice_truncated() {
+        let data = blob(&[]);
+        let err = AdditionalData::from_slice(&data[..0x30], &all_flags()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::AddDataTruncated {
+                field: "Attestation PHKH",
+                size: 0x20,
+                available: 0x10
+            }
+        ));
+        let err = AdditionalData::from_slice(&data[..data.len() - 1], &all_flags()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The additional data is truncated: Firmware state needs 320 bytes, but only 319 bytes are left"
+        );
+        let err = AdditionalData::from_slice(&data[..0x80], &all_flags()).unwrap_err();
+        assert!(matches!(err, Error::AddDataMissing("Firmware state")));
+    }
+
     #[test]
     fn ser_no() {
         let add: AdditionalData<u8> = AdditionalData {
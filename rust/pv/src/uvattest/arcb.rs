@@ -2,19 +2,19 @@
 //
 // Copyright IBM Corp. 2024
 
-use openssl::pkey::{PKey, Public};
+use openssl::pkey::{PKey, PKeyRef, Private, Public};
 use std::mem::size_of;
 use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U32};
 
 use crate::{
     assert_size,
     attest::{AttestationMagic, AttestationMeasAlg},
-    crypto::random_array,
+    crypto::{AeadLimits, RandomPool},
     misc::Flags,
-    req::{Aad, BinReqValues, Keyslot, ReqEncrCtx},
-    request::{Confidential, MagicValue, Request, RequestVersion, SymKey, Zeroize},
+    req::{unwrap_keyslot, Aad, BinReqValues, Keyslot, ReqEncrCtx},
+    request::{Confidential, MagicValue, Request, RequestVersion, Summary, SymKey, Zeroize},
     static_assert,
-    uv::UvFlags,
+    uv::{AttestationCmd, UvFlags},
     Error, Result,
 };
 
@@ -26,15 +26,15 @@ use super::{
 #[cfg(doc)]
 use crate::{
     request::SymKeyType,
-    uv::AttestationCmd,
     verify::{CertVerifier, HkdVerifier},
 };
 
 /// Retrieve Attestation Request Control Block
 ///
 /// An ARCB holds an Attestation Measurement key to attest a SE-guest.
-/// The (architectural optional) nonce is always used and freshly generated for a new
-/// [`AttestationRequest`].
+/// The (architectural optional) nonce is used and freshly generated for a new
+/// [`AttestationRequest`] by default. Use [`AttestationRequest::set_nonce`] to provide the nonce,
+/// e.g., if the verifier chooses it, or [`AttestationRequest::remove_nonce`] to omit it.
 ///
 /// Layout:
 /// ```none
@@ -69,8 +69,8 @@ use crate::{
 /// // IBM issued HKD certificates typically have one X509
 /// let hkd = hkd.first().unwrap().public_key()?;
 /// arcb.add_hostkey(hkd);
-/// // you can add multiple hostkeys
-/// // arcb.add_hostkey(another_hkd);
+/// // you can add multiple hostkeys, one keyslot each
+/// // arcb.add_hostkeys(&[another_hkd, yet_another_hkd]);
 /// // encrypt it
 /// let ctx = ReqEncrCtx::random(SymKeyType::Aes256)?;
 /// let arcb = arcb.encrypt(&ctx)?;
@@ -102,7 +102,7 @@ impl AttestationRequest {
         mai: AttestationMeasAlg,
         mut flags: AttestationFlags,
     ) -> Result<Self> {
-        // This implementation enforces using a nonce
+        // A nonce is used unless explicitly removed
         flags.set_nonce();
         Ok(Self {
             version,
@@ -117,6 +117,31 @@ impl AttestationRequest {
         &self.aad.flags
     }
 
+    /// Use `nonce` instead of the randomly generated nonce.
+    ///
+    /// Enables the nonce again if it was removed with [`Self::remove_nonce`].
+    pub fn set_nonce(&mut self, nonce: [u8; 16]) {
+        self.aad.flags.set_nonce();
+        self.conf.value_mut().nonce = nonce;
+    }
+
+    /// Create the request without a nonce.
+    ///
+    /// The attestation measurement will not cover a nonce.
+    pub fn remove_nonce(&mut self) {
+        self.aad.flags.unset_nonce();
+        self.conf.value_mut().nonce.zeroize();
+    }
+
+    /// Size of the confidential data that is encrypted
+    fn conf_size(&self) -> usize {
+        match self.aad.flags.nonce() {
+            true => size_of::<ReqConfData>(),
+            // the nonce is the last member
+            false => size_of::<ReqConfData>() - size_of::<AttNonce>(),
+        }
+    }
+
     /// Returns a copy of the confidential data of this [`AttestationRequest`].
     ///
     /// Gives a copy of the confidential data of this request for further
@@ -124,7 +149,8 @@ impl AttestationRequest {
     /// the creator and the verifier of this request.
     pub fn confidential_data(&self) -> AttestationConfidential {
         let conf = self.conf.value();
-        AttestationConfidential::new(conf.meas_key.to_vec(), conf.nonce.into())
+        let nonce = self.aad.flags.nonce().then_some(conf.nonce);
+        AttestationConfidential::new(conf.meas_key.to_vec(), nonce)
     }
 
     fn aad(&self, ctx: &ReqEncrCtx) -> Result<Vec<u8>> {
@@ -136,7 +162,7 @@ impl AttestationRequest {
         ctx.build_aad(
             self.version.into(),
             &aad,
-            size_of::<ReqConfData>(),
+            self.conf_size(),
             AttestationMagic::MAGIC,
         )
     }
@@ -189,7 +215,11 @@ impl AttestationRequest {
             return Err(Error::BinArcbSeaSmall(values.sea()));
         }
 
-        let decr = values.decrypt(arpk)?;
+        let limits = AeadLimits {
+            aad: AttestationCmd::ARCB_MAX_SIZE as usize,
+            encr: size_of::<ReqConfData>(),
+        };
+        let decr = values.decrypt(arpk, limits)?;
 
         // size sanitized by fence before
         let meas_key = &decr.value()[..keysize];
@@ -206,6 +236,32 @@ impl AttestationRequest {
 
         Ok((auth.to_owned(), conf))
     }
+
+    /// Unwraps the attestation request protection key with a host private key.
+    ///
+    /// Searches the keyslots of `arcb` for `host_key` and decrypts the protection key as the
+    /// Ultravisor does. The private host-key of an IBM Z machine is never available outside of
+    /// the Ultravisor. Therefore, this is only usable for simulations, e.g., in tests.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the request is malformed, contains no keyslot for `host_key`, or the
+    /// keyslot decryption failed.
+    pub fn unwrap_arpk_bin(arcb: &[u8], host_key: &PKeyRef<Private>) -> Result<SymKey> {
+        const CUST_PUB_KEY_OFFSET: usize = size_of::<AttestationAuthenticated>();
+        const KEYSLOTS_OFFSET: usize = CUST_PUB_KEY_OFFSET + 160;
+
+        let values = Self::bin_values(arcb)?;
+        let aad = values.req_dep_aad_bytes();
+        if aad.len() < KEYSLOTS_OFFSET {
+            return Err(Error::BinRequestSmall);
+        }
+        unwrap_keyslot(
+            &aad[CUST_PUB_KEY_OFFSET..KEYSLOTS_OFFSET],
+            &aad[KEYSLOTS_OFFSET..],
+            host_key,
+        )
+    }
 }
 
 /// Confidential Data of an attestation request
@@ -236,9 +292,34 @@ impl AttestationConfidential {
     }
 }
 
+impl Summary for AttestationRequest {
+    fn summary(&self) -> String {
+        format!(
+            "Attestation request v{:#06x}: {:?}, {} host-key(s), flags {:#018x}",
+            RequestVersion::from(self.version),
+            self.aad.mai(),
+            self.keyslots.len(),
+            u64::from(self.aad.flags.0),
+        )
+    }
+}
+
+impl Summary for AttestationConfidential {
+    fn summary(&self) -> String {
+        format!(
+            "Attestation confidential data: {} byte measurement key, {}",
+            self.measurement_key.value().len(),
+            match self.nonce {
+                Some(_) => "nonce",
+                None => "no nonce",
+            }
+        )
+    }
+}
+
 impl Request for AttestationRequest {
     fn encrypt(&self, ctx: &ReqEncrCtx) -> Result<Vec<u8>> {
-        let conf = self.conf.value().as_bytes();
+        let conf = &self.conf.value().as_bytes()[..self.conf_size()];
         let aad = self.aad(ctx)?;
         ctx.encrypt_aead(&aad, conf).map(|res| res.into_buf())
     }
@@ -335,12 +416,21 @@ impl AttestationFlags {
 
     /// Flag 1 - use a nonce
     ///
-    /// This attestation implementation forces the use of a nonce, so this will always be on and
-    /// the function is non-public
+    /// Managed by [`AttestationRequest::set_nonce`] and [`AttestationRequest::remove_nonce`],
+    /// so the function is non-public
     fn set_nonce(&mut self) {
         self.0.set_bit(1);
     }
 
+    fn unset_nonce(&mut self) {
+        self.0.unset_bit(1);
+    }
+
+    /// Check whether the nonce flag is on
+    pub fn nonce(&self) -> bool {
+        self.0.is_set(1)
+    }
+
     /// Flag 2 - request the image public host-key hash
     ///
     /// Asks the Ultravisor to provide the host-key hash that unpacked the SE-image to be added in
@@ -349,7 +439,7 @@ impl AttestationFlags {
         self.0.set_bit(2);
     }
 
-    /// Check weather the image public host key hash flag is on
+    /// Check whether the image public host key hash flag is on
     pub fn image_phkh(&self) -> bool {
         self.0.is_set(2)
     }
@@ -362,7 +452,7 @@ impl AttestationFlags {
         self.0.set_bit(3);
     }
 
-    /// Check weather the attestation public host key hash flag is on
+    /// Check whether the attestation public host key hash flag is on
     pub fn attest_phkh(&self) -> bool {
         self.0.is_set(3)
     }
@@ -374,7 +464,7 @@ impl AttestationFlags {
         self.0.set_bit(4);
     }
 
-    /// Check weather the hash of the added secret requests flag is on
+    /// Check whether the hash of the added secret requests flag is on
     pub fn secret_store_hash(&self) -> bool {
         self.0.is_set(4)
     }
@@ -386,7 +476,7 @@ impl AttestationFlags {
         self.0.set_bit(5);
     }
 
-    /// Check weather the hash of the added secret requests flag is on
+    /// Check whether the firmware state flag is on
     pub fn firmware_state(&self) -> bool {
         self.0.is_set(5)
     }
@@ -402,9 +492,10 @@ assert_size!(ReqConfData, 80);
 
 impl ReqConfData {
     fn random() -> Result<Confidential<Self>> {
+        let mut pool = RandomPool::new(size_of::<Self>());
         Ok(Confidential::new(Self {
-            meas_key: random_array()?,
-            nonce: random_array()?,
+            meas_key: pool.array()?,
+            nonce: pool.array()?,
         }))
     }
 }
@@ -420,7 +511,19 @@ impl Zeroize for ReqConfData {
 mod test {
     use super::*;
 
-    use crate::{get_test_asset, request::SymKey, test_utils::get_test_keys};
+    use openssl::nid::Nid;
+
+    use crate::{
+        crypto::gen_ec_key,
+        get_test_asset,
+        req::keyslot_hashes,
+        request::{EcPubKeyCoord, SymKey, SymKeyType},
+        test_utils::get_test_keys,
+    };
+
+    fn public_key(key: &PKeyRef<Private>) -> PKey<Public> {
+        PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap()
+    }
 
     const ARPK: [u8; 32] = [0x17; 32];
     const NONCE: [u8; 16] = [0xab; 16];
@@ -446,8 +549,8 @@ mod test {
         )
         .unwrap();
 
-        // manually set confidential data (API does not allow this)
-        arcb.conf.value_mut().nonce = NONCE;
+        // manually set the measurement key (API does not allow this)
+        arcb.set_nonce(NONCE);
         arcb.conf.value_mut().meas_key = MEAS;
 
         arcb.add_hostkey(host_key);
@@ -480,6 +583,108 @@ mod test {
         assert_eq!(conf.nonce().as_ref().unwrap().value(), &NONCE);
     }
 
+    #[test]
+    fn nonce() {
+        let ctx = ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap();
+        let mut arcb = AttestationRequest::new(
+            AttestationVersion::One,
+            AttestationMeasAlg::HmacSha512,
+            AttestationFlags::default(),
+        )
+        .unwrap();
+        arcb.add_hostkey(get_test_keys().1);
+        assert!(arcb.flags().nonce());
+
+        arcb.remove_nonce();
+        assert!(!arcb.flags().nonce());
+        assert!(arcb.confidential_data().nonce().is_none());
+        let request = arcb.encrypt(&ctx).unwrap();
+        let (auth, conf) = AttestationRequest::decrypt_bin(&request, ctx.prot_key()).unwrap();
+        assert!(!auth.flags().nonce());
+        assert!(conf.nonce().is_none());
+        assert_eq!(
+            conf.measurement_key(),
+            arcb.confidential_data().measurement_key()
+        );
+
+        arcb.set_nonce(NONCE);
+        assert!(arcb.flags().nonce());
+        let request = arcb.encrypt(&ctx).unwrap();
+        let (auth, conf) = AttestationRequest::decrypt_bin(&request, ctx.prot_key()).unwrap();
+        assert!(auth.flags().nonce());
+        assert_eq!(conf.nonce().as_ref().unwrap().value(), &NONCE);
+    }
+
+    #[test]
+    fn multiple_keyslots() {
+        let host_keys: Vec<_> = (0..3)
+            .map(|_| gen_ec_key(Nid::SECP521R1).unwrap())
+            .collect();
+        let pub_keys: Vec<_> = host_keys.iter().map(|k| public_key(k)).collect();
+        let ctx = ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap();
+        let mut arcb = AttestationRequest::new(
+            AttestationVersion::One,
+            AttestationMeasAlg::HmacSha512,
+            AttestationFlags::default(),
+        )
+        .unwrap();
+        arcb.add_hostkeys(&pub_keys);
+        let request = arcb.encrypt(&ctx).unwrap();
+
+        let values = AttestationRequest::bin_values(&request).unwrap();
+        assert_eq!(values.nks(), 3);
+        let keyslots = &values.req_dep_aad_bytes()[size_of::<AttestationAuthenticated>() + 160..];
+        let phkhs = keyslot_hashes(keyslots, values.nks()).unwrap();
+        for (phkh, key) in phkhs.iter().zip(&pub_keys) {
+            let exp = EcPubKeyCoord::try_from(key.as_ref())
+                .unwrap()
+                .sha256()
+                .unwrap();
+            assert_eq!(phkh, exp.as_ref());
+        }
+
+        // every keyslot wraps the same protection key
+        for host_key in &host_keys {
+            let arpk = AttestationRequest::unwrap_arpk_bin(&request, host_key).unwrap();
+            assert_eq!(&arpk, ctx.prot_key());
+        }
+    }
+
+    #[test]
+    fn unwrap_arpk_bin() {
+        let host_key = gen_ec_key(Nid::SECP521R1).unwrap();
+        let other_key = gen_ec_key(Nid::SECP521R1).unwrap();
+        let ctx = ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap();
+        let mut arcb = AttestationRequest::new(
+            AttestationVersion::One,
+            AttestationMeasAlg::HmacSha512,
+            AttestationFlags::default(),
+        )
+        .unwrap();
+        arcb.add_hostkey(public_key(&other_key));
+        arcb.add_hostkey(public_key(&host_key));
+        let request = arcb.encrypt(&ctx).unwrap();
+
+        let arpk = AttestationRequest::unwrap_arpk_bin(&request, &host_key).unwrap();
+        assert_eq!(&arpk, ctx.prot_key());
+        let arpk = AttestationRequest::unwrap_arpk_bin(&request, &other_key).unwrap();
+        assert_eq!(&arpk, ctx.prot_key());
+
+        let (_, conf) = AttestationRequest::decrypt_bin(&request, &arpk).unwrap();
+        assert_eq!(
+            conf.measurement_key(),
+            arcb.confidential_data().measurement_key()
+        );
+    }
+
+    #[test]
+    fn unwrap_arpk_bin_fail() {
+        let request = mk_arcb();
+        let host_key = gen_ec_key(Nid::SECP521R1).unwrap();
+        let ret = AttestationRequest::unwrap_arpk_bin(&request, &host_key);
+        assert!(matches!(ret, Err(Error::NoMatchingKeyslot)));
+    }
+
     #[test]
     fn decrypt_bin_fail_magic() {
         let arpk = SymKey::Aes256(ARPK.into());
@@ -516,4 +721,29 @@ mod test {
         let ret = AttestationRequest::decrypt_bin(&tamp_arcb, &arpk);
         assert!(matches!(ret, Err(Error::GcmTagMismatch)));
     }
+
+    #[test]
+    fn summary_no_secret() {
+        let mut arcb = AttestationRequest::new(
+            AttestationVersion::One,
+            AttestationMeasAlg::HmacSha512,
+            AttestationFlags::default(),
+        )
+        .unwrap();
+        arcb.add_hostkey(get_test_keys().1);
+        let conf = arcb.confidential_data();
+
+        for summary in [arcb.summary(), conf.summary()] {
+            crate::test_utils::assert_no_secret(&summary, conf.measurement_key());
+            crate::test_utils::assert_no_secret(&summary, conf.nonce().as_ref().unwrap().value());
+        }
+        assert_eq!(
+            arcb.summary(),
+            "Attestation request v0x0100: HmacSha512, 1 host-key(s), flags 0x4000000000000000"
+        );
+        assert_eq!(
+            conf.summary(),
+            "Attestation confidential data: 64 byte measurement key, nonce"
+        );
+    }
 }
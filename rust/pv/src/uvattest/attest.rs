@@ -4,13 +4,14 @@
 
 use super::AttNonce;
 use crate::{
-    attest::AttestationMeasAlg, brcb::BootHdrTags, crypto::calculate_hmac, request::Confidential,
-    uv::ConfigUid, Result,
-};
-use openssl::{
-    hash::MessageDigest,
-    pkey::{PKeyRef, Private},
+    attest::AttestationMeasAlg,
+    brcb::BootHdrTags,
+    crypto::{calculate_hmac, Digest},
+    request::Confidential,
+    uv::ConfigUid,
+    Result,
 };
+use openssl::pkey::{PKeyRef, Private};
 use std::mem::size_of;
 use zerocopy::{AsBytes, BigEndian, U16, U32};
 
@@ -117,7 +118,7 @@ impl AttestationMeasurement {
     ) -> Result<Self> {
         match mai {
             AttestationMeasAlg::HmacSha512 => {
-                calculate_hmac(meas_key, MessageDigest::sha512(), items.0.value()).map(Self)
+                calculate_hmac(meas_key, Digest::Sha512, items.0.value()).map(Self)
             }
         }
     }
@@ -0,0 +1,360 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use serde::Serialize;
+use std::fmt::Display;
+
+use crate::{misc::encode_hex, Result};
+
+use super::{additional::AdditionalData, arcb::AttestationFlags};
+
+/// Maximum number of bytes shown for a value of a [`FieldDiff`]
+const DIFF_VALUE_MAX_LEN: usize = 32;
+
+/// Values of a decrypted attestation response
+///
+/// Contains everything of an attestation response that can be compared with another response
+/// using [`diff`]. The additional-data is interpreted using the flags of the attestation request.
+/// The measurement is not part of this as it includes the nonce and therefore differs with each
+/// request.
+#[derive(Debug)]
+pub struct DecryptedAttestation<'a> {
+    config_uid: &'a [u8],
+    user_data: Option<&'a [u8]>,
+    additional: Option<AdditionalData<&'a [u8]>>,
+}
+
+impl<'a> DecryptedAttestation<'a> {
+    /// Create a new [`DecryptedAttestation`]
+    ///
+    /// `flags`: Flags of the decrypted attestation request indicating which additional-data
+    /// fields are present.
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is a mismatch between the additional-data and the flags.
+    pub fn new(
+        config_uid: &'a [u8],
+        user_data: Option<&'a [u8]>,
+        additional: Option<&'a [u8]>,
+        flags: &AttestationFlags,
+    ) -> Result<Self> {
+        Ok(Self {
+            config_uid,
+            user_data,
+            additional: additional
+                .map(|a| AdditionalData::from_slice(a, flags))
+                .transpose()?,
+        })
+    }
+
+    /// Provides the comparable fields with their names
+    fn fields(&self) -> [(&'static str, Option<&'a [u8]>); 7] {
+        let add = self.additional.as_ref();
+        [
+            ("config_uid", Some(self.config_uid)),
+            ("user_data", self.user_data),
+            (
+                "image_phkh",
+                add.and_then(|a| a.image_public_host_key_hash().copied()),
+            ),
+            (
+                "attestation_phkh",
+                add.and_then(|a| a.attestation_public_host_key_hash().copied()),
+            ),
+            (
+                "secret_store_hash",
+                add.and_then(|a| a.secret_store_hash().copied()),
+            ),
+            (
+                "firmware_state",
+                add.and_then(|a| a.firmware_state().copied()),
+            ),
+            ("unrecognized", add.and_then(|a| a.unrecognized().copied())),
+        ]
+    }
+}
+
+/// Hex representation of a value, truncated if it is large
+fn diff_value(data: &[u8]) -> String {
+    match data.len() {
+        len if len > DIFF_VALUE_MAX_LEN => {
+            format!(
+                "{}... ({len} bytes)",
+                encode_hex(&data[..DIFF_VALUE_MAX_LEN])
+            )
+        }
+        _ => encode_hex(data),
+    }
+}
+
+/// Change of a single field between two attestation responses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum FieldChange {
+    /// Field is only present in the second response
+    Added {
+        /// Value in the second response
+        new: String,
+    },
+    /// Field is only present in the first response
+    Removed {
+        /// Value in the first response
+        old: String,
+    },
+    /// Field is present in both responses but with different values
+    Changed {
+        /// Value in the first response
+        old: String,
+        /// Value in the second response
+        new: String,
+    },
+}
+
+impl FieldChange {
+    fn new(old: Option<&[u8]>, new: Option<&[u8]>) -> Option<Self> {
+        match (old, new) {
+            (None, None) => None,
+            (Some(old), Some(new)) if old == new => None,
+            (None, Some(new)) => Some(Self::Added {
+                new: diff_value(new),
+            }),
+            (Some(old), None) => Some(Self::Removed {
+                old: diff_value(old),
+            }),
+            (Some(old), Some(new)) => Some(Self::Changed {
+                old: diff_value(old),
+                new: diff_value(new),
+            }),
+        }
+    }
+}
+
+/// Named [`FieldChange`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldDiff {
+    field: &'static str,
+    #[serde(flatten)]
+    change: FieldChange,
+}
+
+impl FieldDiff {
+    /// Name of the changed field
+    pub fn field(&self) -> &'static str {
+        self.field
+    }
+
+    /// The change of the field
+    pub fn change(&self) -> &FieldChange {
+        &self.change
+    }
+}
+
+/// Differences between two attestation responses
+///
+/// Created by [`diff`]. Contains only fields that differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct AttestationDiff(Vec<FieldDiff>);
+
+impl AttestationDiff {
+    /// Returns true if both responses have the same values
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Provides all changed fields
+    pub fn changes(&self) -> &[FieldDiff] {
+        &self.0
+    }
+}
+
+impl Display for AttestationDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for FieldDiff { field, change } in &self.0 {
+            match change {
+                FieldChange::Added { new } => writeln!(f, "{field}: added\n  + {new}")?,
+                FieldChange::Removed { old } => writeln!(f, "{field}: removed\n  - {old}")?,
+                FieldChange::Changed { old, new } => {
+                    writeln!(f, "{field}: changed\n  - {old}\n  + {new}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare two attestation responses
+///
+/// Fields that are present in only one of the responses are reported as added or removed.
+pub fn diff(a: &DecryptedAttestation, b: &DecryptedAttestation) -> AttestationDiff {
+    AttestationDiff(
+        a.fields()
+            .into_iter()
+            .zip(b.fields())
+            .filter_map(|((field, old), (_, new))| {
+                FieldChange::new(old, new).map(|change| FieldDiff { field, change })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_test::Token;
+
+    use super::*;
+
+    const CUID: [u8; 16] = [0x11; 16];
+
+    fn flags(ssh: bool, fw: bool) -> AttestationFlags {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        if ssh {
+            flags.set_secret_store_hash();
+        }
+        if fw {
+            flags.set_firmware_state();
+        }
+        flags
+    }
+
+    fn additional(phkh: u8, ssh: Option<u8>, fw: Option<u8>) -> Vec<u8> {
+        let mut data = vec![phkh; 0x20];
+        if let Some(ssh) = ssh {
+            data.extend([ssh; 0x40]);
+        }
+        if let Some(fw) = fw {
+            data.extend([fw; 0x140]);
+        }
+        data
+    }
+
+    #[test]
+    fn no_diff() {
+        let add = additional(1, Some(2), None);
+        let flags = flags(true, false);
+        let a = DecryptedAttestation::new(&CUID, None, Some(&add), &flags).unwrap();
+        let b = DecryptedAttestation::new(&CUID, None, Some(&add), &flags).unwrap();
+
+        let diff = diff(&a, &b);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No differences\n");
+    }
+
+    #[test]
+    fn diff_one() {
+        let add_a = additional(1, Some(2), None);
+        let add_b = additional(1, Some(3), None);
+        let flags = flags(true, false);
+        let a = DecryptedAttestation::new(&CUID, None, Some(&add_a), &flags).unwrap();
+        let b = DecryptedAttestation::new(&CUID, None, Some(&add_b), &flags).unwrap();
+
+        let diff = diff(&a, &b);
+        assert_eq!(
+            diff.changes(),
+            [FieldDiff {
+                field: "secret_store_hash",
+                change: FieldChange::Changed {
+                    old: format!("{}... (64 bytes)", "02".repeat(32)),
+                    new: format!("{}... (64 bytes)", "03".repeat(32)),
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_many() {
+        let cuid_b = [0x12; 16];
+        let add_a = additional(1, Some(2), None);
+        let add_b = additional(1, None, Some(4));
+        let a = DecryptedAttestation::new(&CUID, Some(&[5, 6]), Some(&add_a), &flags(true, false))
+            .unwrap();
+        let b =
+            DecryptedAttestation::new(&cuid_b, None, Some(&add_b), &flags(false, true)).unwrap();
+
+        let diff = diff(&a, &b);
+        let fields: Vec<_> = diff.changes().iter().map(FieldDiff::field).collect();
+        assert_eq!(
+            fields,
+            [
+                "config_uid",
+                "user_data",
+                "secret_store_hash",
+                "firmware_state"
+            ]
+        );
+        assert_eq!(
+            diff.changes()[0].change(),
+            &FieldChange::Changed {
+                old: "11".repeat(16),
+                new: "12".repeat(16)
+            }
+        );
+        assert_eq!(
+            diff.changes()[1].change(),
+            &FieldChange::Removed {
+                old: "0506".to_string()
+            }
+        );
+        assert!(matches!(
+            diff.changes()[2].change(),
+            FieldChange::Removed { .. }
+        ));
+        assert!(matches!(
+            diff.changes()[3].change(),
+            FieldChange::Added { .. }
+        ));
+    }
+
+    #[test]
+    fn diff_no_additional() {
+        let add = additional(1, None, None);
+        let flags = flags(false, false);
+        let a = DecryptedAttestation::new(&CUID, None, None, &flags).unwrap();
+        let b = DecryptedAttestation::new(&CUID, None, Some(&add), &flags).unwrap();
+
+        let diff = diff(&a, &b);
+        assert_eq!(
+            diff.changes(),
+            [FieldDiff {
+                field: "image_phkh",
+                change: FieldChange::Added {
+                    new: "01".repeat(32)
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn serialize() {
+        let diff = AttestationDiff(vec![FieldDiff {
+            field: "user_data",
+            change: FieldChange::Changed {
+                old: "01".to_string(),
+                new: "02".to_string(),
+            },
+        }]);
+        serde_test::assert_ser_tokens(
+            &diff,
+            &[
+                Token::Seq { len: Some(1) },
+                Token::Map { len: None },
+                Token::Str("field"),
+                Token::Str("user_data"),
+                Token::Str("change"),
+                Token::Str("changed"),
+                Token::Str("old"),
+                Token::Str("01"),
+                Token::Str("new"),
+                Token::Str("02"),
+                Token::MapEnd,
+                Token::SeqEnd,
+            ],
+        );
+    }
+}
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use crate::attest::AttestationMeasAlg;
+
+use super::arcb::AttestationFlags;
+
+/// Sizes of the output buffers of a Retrieve Attestation Measurement call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplySizes {
+    /// Size of the attestation measurement
+    pub measurement: usize,
+    /// Size of the additional-data; zero if no additional-data is requested
+    pub additional: usize,
+}
+
+impl ReplySizes {
+    /// Determines the reply sizes of an attestation request with `flags` and the measurement
+    /// algorithm `mai`.
+    ///
+    /// The measurement size is defined by the algorithm, the additional-data size is the
+    /// architected maximum of the additional-data requested by `flags`. The Ultravisor truncates
+    /// the additional-data to the size it actually provides.
+    pub fn for_flags(flags: &AttestationFlags, mai: AttestationMeasAlg) -> Self {
+        Self {
+            measurement: mai.exp_size() as usize,
+            additional: flags.expected_additional_size() as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type FlagSetter = fn(&mut AttestationFlags);
+
+    /// All combinations of the additional-data flags with the architected additional-data size
+    fn flag_combinations() -> Vec<(AttestationFlags, usize)> {
+        let setters: [(FlagSetter, usize); 4] = [
+            (AttestationFlags::set_image_phkh, 0x20),
+            (AttestationFlags::set_attest_phkh, 0x20),
+            (AttestationFlags::set_secret_store_hash, 0x40),
+            (AttestationFlags::set_firmware_state, 0x140),
+        ];
+        (0..1 << setters.len())
+            .map(|comb| {
+                let mut flags = AttestationFlags::default();
+                let mut size = 0;
+                for (bit, (set, flag_size)) in setters.iter().enumerate() {
+                    if comb & (1 << bit) != 0 {
+                        set(&mut flags);
+                        size += flag_size;
+                    }
+                }
+                (flags, size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn architected() {
+        for (flags, additional) in flag_combinations() {
+            assert_eq!(
+                ReplySizes::for_flags(&flags, AttestationMeasAlg::HmacSha512),
+                ReplySizes {
+                    measurement: 64,
+                    additional
+                },
+                "{flags:?}"
+            );
+        }
+
+        let mut all = AttestationFlags::default();
+        all.set_image_phkh();
+        all.set_attest_phkh();
+        all.set_secret_store_hash();
+        all.set_firmware_state();
+        let sizes = ReplySizes::for_flags(&all, AttestationMeasAlg::HmacSha512);
+        assert_eq!(sizes.additional, 0x1c0);
+    }
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use super::additional::SECRET_STORE_HASH_SIZE;
+use crate::{
+    crypto::{hash, Digest},
+    uvsecret::asrcb::AddSecretRequest,
+    Error, Result,
+};
+
+/// Size of the tag of an add-secret request
+pub const SECRET_TAG_SIZE: usize = 0x10;
+
+/// Calculate the secret-store-hash of a secret store
+///
+/// The secret-store-hash is the SHA-512 hash over the `tags` of all add-secret requests in the
+/// order they were added, followed by one byte with the lock state of the secret store. It is
+/// the value the Ultravisor provides as additional-data if requested.
+///
+/// # Errors
+///
+/// This function will return an error if a tag is not [`SECRET_TAG_SIZE`] bytes long or OpenSSL
+/// could not compute the hash.
+pub fn secret_store_hash<T: AsRef<[u8]>>(
+    tags: &[T],
+    locked: bool,
+) -> Result<[u8; SECRET_STORE_HASH_SIZE as usize]> {
+    let mut store = Vec::with_capacity(tags.len() * SECRET_TAG_SIZE + 1);
+    for tag in tags {
+        let tag = tag.as_ref();
+        if tag.len() != SECRET_TAG_SIZE {
+            return Err(Error::InvSecretTagSize(tag.len()));
+        }
+        store.extend_from_slice(tag);
+    }
+    store.push(locked as u8);
+    // Panic: SHA-512 hashes are 64 bytes long
+    Ok(hash(Digest::Sha512, &store)?.as_ref().try_into().unwrap())
+}
+
+/// Calculate the secret-store-hash of a secret store from binary add-secret requests
+///
+/// Extracts the tags of `asrcbs` and calculates the hash as [`secret_store_hash`] does.
+///
+/// # Errors
+///
+/// This function will return an error if one of `asrcbs` is no add-secret request or OpenSSL
+/// could not compute the hash.
+pub fn secret_store_hash_requests<A: AsRef<[u8]>>(
+    asrcbs: &[A],
+    locked: bool,
+) -> Result<[u8; SECRET_STORE_HASH_SIZE as usize]> {
+    let tags = asrcbs
+        .iter()
+        .map(|asrcb| AddSecretRequest::bin_tag(asrcb.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+    secret_store_hash(&tags, locked)
+}
+
+/// State of a secret store, built up add-secret request by add-secret request
+///
+/// Tracks the tags of the added requests in order and the lock state, i.e., everything the
+/// secret-store-hash covers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecretStoreState {
+    tags: Vec<[u8; SECRET_TAG_SIZE]>,
+    locked: bool,
+}
+
+impl SecretStoreState {
+    /// Create the state of an empty, unlocked secret store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the tag of a successful add-secret request
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the secret store is locked.
+    pub fn add(&mut self, tag: [u8; SECRET_TAG_SIZE]) -> Result<()> {
+        if self.locked {
+            return Err(Error::SecretStoreLocked);
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    /// Lock the secret store
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    /// Tags of the added requests in the order they were added
+    pub fn tags(&self) -> &[[u8; SECRET_TAG_SIZE]] {
+        &self.tags
+    }
+
+    /// Returns true if the secret store is locked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Calculate the secret-store-hash of this state
+    ///
+    /// See [`secret_store_hash`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if OpenSSL could not compute the hash.
+    pub fn hash(&self) -> Result<[u8; SECRET_STORE_HASH_SIZE as usize]> {
+        secret_store_hash(&self.tags, self.locked)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::get_test_asset;
+
+    const ASRCBS: [&[u8]; 9] = [
+        get_test_asset!("exp/asrcb/assoc_derived_default_cuid_one"),
+        get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one"),
+        get_test_asset!("exp/asrcb/null_none_default_cuid_one"),
+        get_test_asset!("exp/asrcb/null_none_default_ncuid_one"),
+        get_test_asset!("exp/asrcb/null_simple_default_cuid_one"),
+        get_test_asset!("exp/asrcb/assoc_none_default_cuid_one"),
+        get_test_asset!("exp/asrcb/null_derived_default_cuid_one"),
+        get_test_asset!("exp/asrcb/null_none_default_cuid_seven"),
+        get_test_asset!("exp/asrcb/null_none_dump_cuid_one"),
+    ];
+
+    #[test]
+    fn hash_requests() {
+        let hash = secret_store_hash_requests(&ASRCBS, true).unwrap();
+        let exp = [
+            0xd0, 0x48, 0x70, 0x2b, 0x4a, 0x79, 0x47, 0x8b, 0x98, 0x5e, 0x92, 0xe7, 0xed, 0xff,
+            0x45, 0x3f, 0x63, 0xf2, 0x4, 0x4e, 0x7d, 0x72, 0xfa, 0xf1, 0x2e, 0xfd, 0x2e, 0xae,
+            0xa0, 0xcd, 0x5, 0x5, 0x55, 0x9e, 0xd6, 0x66, 0x5a, 0x6, 0xf6, 0xb4, 0xf9, 0xc6, 0xfc,
+            0xf9, 0xf2, 0x96, 0xe, 0x6c, 0xd3, 0xc3, 0xcc, 0x8c, 0xaf, 0xe5, 0x7a, 0xc8, 0x40,
+            0x6e, 0x61, 0x6b, 0xf9, 0x52, 0x95, 0x17,
+        ];
+        assert_eq!(exp, hash);
+
+        let hash = secret_store_hash_requests(&ASRCBS, false).unwrap();
+        let exp = [
+            0x51, 0xce, 0x62, 0xaf, 0x1f, 0x67, 0xb9, 0xe3, 0x25, 0x4b, 0x18, 0x4e, 0x33, 0xb2,
+            0xaa, 0xd3, 0x10, 0x7, 0x58, 0x1a, 0x39, 0xe9, 0x9c, 0xde, 0xb0, 0x29, 0x98, 0xa3,
+            0xb6, 0x7f, 0xf4, 0x56, 0xc4, 0x4a, 0x5, 0xee, 0x7d, 0x68, 0xe2, 0x4d, 0xfd, 0x43,
+            0x6f, 0x2b, 0xe4, 0xc1, 0xe9, 0xf5, 0xc1, 0x1, 0x64, 0x68, 0xda, 0x64, 0x1, 0x5e, 0x9f,
+            0x9f, 0xa3, 0x15, 0x6e, 0x11, 0xd, 0x6c,
+        ];
+        assert_eq!(exp, hash);
+
+        let tags: Vec<_> = ASRCBS
+            .iter()
+            .map(|asrcb| AddSecretRequest::bin_tag(asrcb).unwrap())
+            .collect();
+        assert_eq!(secret_store_hash(&tags, false).unwrap(), hash);
+    }
+
+    #[test]
+    fn hash_empty() {
+        let hash = secret_store_hash::<&[u8]>(&[], true).unwrap();
+        let exp = [
+            0x7b, 0x54, 0xb6, 0x68, 0x36, 0xc1, 0xfb, 0xdd, 0x13, 0xd2, 0x44, 0x1d, 0x9e, 0x14,
+            0x34, 0xdc, 0x62, 0xca, 0x67, 0x7f, 0xb6, 0x8f, 0x5f, 0xe6, 0x6a, 0x46, 0x4b, 0xaa,
+            0xde, 0xcd, 0xbd, 0x0, 0x57, 0x6f, 0x8d, 0x6b, 0x5a, 0xc3, 0xbc, 0xc8, 0x8, 0x44, 0xb7,
+            0xd5, 0xb, 0x1c, 0xc6, 0x60, 0x34, 0x44, 0xbb, 0xe7, 0xcf, 0xcf, 0x8f, 0xc0, 0xaa,
+            0x1e, 0xe3, 0xc6, 0x36, 0xd9, 0xe3, 0x39,
+        ];
+        assert_eq!(exp, hash);
+
+        let hash = secret_store_hash_requests::<&[u8]>(&[], false).unwrap();
+        let exp = [
+            0xb8, 0x24, 0x4d, 0x2, 0x89, 0x81, 0xd6, 0x93, 0xaf, 0x7b, 0x45, 0x6a, 0xf8, 0xef,
+            0xa4, 0xca, 0xd6, 0x3d, 0x28, 0x2e, 0x19, 0xff, 0x14, 0x94, 0x2c, 0x24, 0x6e, 0x50,
+            0xd9, 0x35, 0x1d, 0x22, 0x70, 0x4a, 0x80, 0x2a, 0x71, 0xc3, 0x58, 0xb, 0x63, 0x70,
+            0xde, 0x4c, 0xeb, 0x29, 0x3c, 0x32, 0x4a, 0x84, 0x23, 0x34, 0x25, 0x57, 0xd4, 0xe5,
+            0xc3, 0x84, 0x38, 0xf0, 0xe3, 0x69, 0x10, 0xee,
+        ];
+        assert_eq!(exp, hash);
+    }
+
+    #[test]
+    fn hash_invalid_tag() {
+        for len in [0, SECRET_TAG_SIZE - 1, SECRET_TAG_SIZE + 1] {
+            let tags = [vec![0; SECRET_TAG_SIZE], vec![0; len]];
+            assert!(matches!(
+                secret_store_hash(&tags, true),
+                Err(Error::InvSecretTagSize(l)) if l == len
+            ));
+        }
+        assert!(secret_store_hash_requests(&[b"no add-secret request"], true).is_err());
+    }
+
+    #[test]
+    fn state() {
+        let mut state = SecretStoreState::new();
+        assert_eq!(
+            state.hash().unwrap(),
+            secret_store_hash::<&[u8]>(&[], false).unwrap()
+        );
+        for asrcb in ASRCBS {
+            let tag = AddSecretRequest::bin_tag(asrcb).unwrap();
+            state.add(tag.try_into().unwrap()).unwrap();
+        }
+        assert_eq!(
+            state.hash().unwrap(),
+            secret_store_hash_requests(&ASRCBS, false).unwrap()
+        );
+        assert!(!state.is_locked());
+
+        state.lock();
+        assert!(state.is_locked());
+        assert_eq!(state.tags().len(), ASRCBS.len());
+        assert_eq!(
+            state.hash().unwrap(),
+            secret_store_hash_requests(&ASRCBS, true).unwrap()
+        );
+        assert!(matches!(
+            state.add([0; SECRET_TAG_SIZE]),
+            Err(Error::SecretStoreLocked)
+        ));
+        assert_eq!(state.tags().len(), ASRCBS.len());
+    }
+}
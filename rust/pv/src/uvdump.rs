@@ -0,0 +1,442 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::io::{ErrorKind, Read};
+
+use openssl::{cipher::Cipher, cipher_ctx::CipherCtx, error::ErrorStack};
+use pv_core::request::Confidential;
+
+use crate::{
+    crypto::{Aes256XtsKey, SymKeyType},
+    Error, Result, PAGESIZE,
+};
+
+/// Size of a page of the configuration storage state (in bytes)
+pub const DUMP_PAGE_SIZE: usize = PAGESIZE;
+
+/// Size of a tweak component and of the tweak nonce (in bytes)
+pub const DUMP_TWEAK_LEN: usize = SymKeyType::AES_256_XTS_TWEAK_LEN;
+
+/// Tweak component of a page as returned by the _Dump Configuration Storage State_ UVC
+pub type DumpTweakComponent = [u8; DUMP_TWEAK_LEN];
+
+/// First four bytes of a special tweak component
+const SPECIAL_INDICATOR: [u8; 4] = [0xff; 4];
+/// Flags of a special tweak component (last byte, MSB0 bit-fields)
+const SPECIAL_ZERO_PAGE: u8 = 0x01;
+const SPECIAL_SHARED_PAGE: u8 = 0x02;
+const SPECIAL_MAPPED_PAGE: u8 = 0x04;
+const SPECIAL_RESERVED: u8 = 0xf8;
+
+/// State of a page in the configuration storage state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpPageState {
+    /// The page is encrypted with the dump key
+    Encrypted,
+    /// The page is shared with the host and therefore not encrypted
+    Shared,
+    /// The page contains only zeros; its content in the dump is meaningless
+    Zero,
+}
+
+impl DumpPageState {
+    /// Determine the page state from the tweak component of the page
+    ///
+    /// Any tweak component that is not a special one belongs to an encrypted page. Special tweak
+    /// components must flag the page either as zero or as shared page, but never as mapped page.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `component` is a special tweak component with an
+    /// invalid combination of flags.
+    pub fn from_component(component: &DumpTweakComponent) -> Result<Self> {
+        if component[..4] != SPECIAL_INDICATOR {
+            return Ok(Self::Encrypted);
+        }
+        let flags = component[DUMP_TWEAK_LEN - 1];
+        let invalid = || Err(Error::DumpInvPageState(*component));
+        // mapped pages are never part of a dump, reject them in any combination
+        if component[DUMP_TWEAK_LEN - 2] != 0
+            || flags & (SPECIAL_RESERVED | SPECIAL_MAPPED_PAGE) != 0
+        {
+            return invalid();
+        }
+        match flags {
+            SPECIAL_ZERO_PAGE => Ok(Self::Zero),
+            SPECIAL_SHARED_PAGE => Ok(Self::Shared),
+            _ => invalid(),
+        }
+    }
+}
+
+/// Calculate the XTS tweak of a page from its tweak component and the tweak nonce
+///
+/// The tweak is the bitwise or of both.
+pub fn dump_tweak(
+    component: &DumpTweakComponent,
+    nonce: &[u8; DUMP_TWEAK_LEN],
+) -> [u8; DUMP_TWEAK_LEN] {
+    std::array::from_fn(|i| component[i] | nonce[i])
+}
+
+/// Decrypts the pages of the configuration storage state of a Secure Execution dump
+///
+/// The dump key and the tweak nonce are part of the confidential area of the dump completion
+/// data. Each page is decrypted with AES-256-XTS and the tweak calculated by [`dump_tweak`].
+#[derive(Debug)]
+pub struct DumpDecryptor {
+    key: Aes256XtsKey,
+    nonce: Confidential<[u8; DUMP_TWEAK_LEN]>,
+}
+
+impl DumpDecryptor {
+    /// Create a new decryptor for the dump `key` and the tweak `nonce`
+    pub fn new(key: Aes256XtsKey, nonce: [u8; DUMP_TWEAK_LEN]) -> Self {
+        Self {
+            key,
+            nonce: nonce.into(),
+        }
+    }
+
+    /// Create a new decryptor from the raw key data
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `key` is not 64 bytes long.
+    pub fn from_key_data(key: Confidential<Vec<u8>>, nonce: [u8; DUMP_TWEAK_LEN]) -> Result<Self> {
+        let len = key.value().len();
+        let key = key.try_into().map_err(|_| Error::DumpKeySize(len))?;
+        Ok(Self::new(key, nonce))
+    }
+
+    /// Decrypt one page
+    ///
+    /// * `component` - tweak component of the page
+    /// * `page` - encrypted page
+    ///
+    /// The state of the page is not checked, see [`DumpPageState`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if OpenSSL could not decrypt the page.
+    pub fn decrypt_page(
+        &self,
+        component: &DumpTweakComponent,
+        page: &[u8; DUMP_PAGE_SIZE],
+    ) -> Result<Confidential<[u8; DUMP_PAGE_SIZE]>> {
+        let tweak = Confidential::new(dump_tweak(component, self.nonce.value()));
+        let mut decr = Confidential::new([0; DUMP_PAGE_SIZE]);
+
+        let mut decrypt = || -> std::result::Result<(), ErrorStack> {
+            let mut ctx = CipherCtx::new()?;
+            ctx.decrypt_init(
+                Some(Cipher::aes_256_xts()),
+                Some(self.key.value()),
+                Some(tweak.value()),
+            )?;
+            let count = ctx.cipher_update(page, Some(decr.value_mut()))?;
+            // XTS yields all data with the update of a full page
+            ctx.cipher_final(&mut decr.value_mut()[count..])?;
+            Ok(())
+        };
+        decrypt().map_err(Error::DumpDecrypt)?;
+        Ok(decr)
+    }
+
+    /// Decrypt a sequence of pages from `rd`
+    ///
+    /// `components` are the tweak components of the pages in the same order. Encrypted pages
+    /// are decrypted, shared pages are passed through, and zero pages yield zeros. The reader
+    /// ends at the end of `rd` or if there are no more tweak components.
+    pub fn reader<R, I>(&self, rd: R, components: I) -> DumpReader<'_, R, I::IntoIter>
+    where
+        R: Read,
+        I: IntoIterator<Item = DumpTweakComponent>,
+    {
+        DumpReader {
+            decryptor: self,
+            inner: rd,
+            components: components.into_iter(),
+            page: Confidential::new([0; DUMP_PAGE_SIZE]),
+            pos: DUMP_PAGE_SIZE,
+            index: 0,
+        }
+    }
+}
+
+/// [`Read`] adapter that decrypts the pages of a configuration storage state
+///
+/// Created by [`DumpDecryptor::reader`]. Errors of this crate are returned as
+/// [`std::io::Error`] that wrap an [`Error`].
+#[derive(Debug)]
+pub struct DumpReader<'a, R, I> {
+    decryptor: &'a DumpDecryptor,
+    inner: R,
+    components: I,
+    /// current (decrypted) page
+    page: Confidential<[u8; DUMP_PAGE_SIZE]>,
+    /// read position in `page`
+    pos: usize,
+    /// index of the next page
+    index: u64,
+}
+
+impl<R: Read, I: Iterator<Item = DumpTweakComponent>> DumpReader<'_, R, I> {
+    /// Read and decrypt the next page
+    ///
+    /// Returns false at the end of the input.
+    fn next_page(&mut self) -> Result<bool> {
+        let Some(component) = self.components.next() else {
+            return Ok(false);
+        };
+        let mut page = Confidential::new([0; DUMP_PAGE_SIZE]);
+        let mut size = 0;
+        while size < DUMP_PAGE_SIZE {
+            match self.inner.read(&mut page.value_mut()[size..]) {
+                Ok(0) => break,
+                Ok(n) => size += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        match size {
+            0 => return Ok(false),
+            DUMP_PAGE_SIZE => (),
+            size => {
+                return Err(Error::DumpPageShort {
+                    index: self.index,
+                    size,
+                })
+            }
+        }
+
+        self.page = match DumpPageState::from_component(&component)? {
+            DumpPageState::Encrypted => self.decryptor.decrypt_page(&component, page.value())?,
+            DumpPageState::Shared => page,
+            DumpPageState::Zero => Confidential::new([0; DUMP_PAGE_SIZE]),
+        };
+        self.pos = 0;
+        self.index += 1;
+        Ok(true)
+    }
+}
+
+impl<R: Read, I: Iterator<Item = DumpTweakComponent>> Read for DumpReader<'_, R, I> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos == DUMP_PAGE_SIZE && !self.next_page().map_err(std::io::Error::other)? {
+            return Ok(0);
+        }
+        let count = buf.len().min(DUMP_PAGE_SIZE - self.pos);
+        buf[..count].copy_from_slice(&self.page.value()[self.pos..self.pos + count]);
+        self.pos += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::get_test_asset;
+
+    // Known-answer values generated with `calculate_tweak` of zgetdump (zdump/pv_utils.c) and
+    // the same AES-256-XTS BIO_f_cipher setup zgetdump uses
+    const KEY: [u8; 64] = {
+        let mut key = [0; 64];
+        let mut i = 0;
+        while i < 64 {
+            key[i] = i as u8;
+            i += 1;
+        }
+        key
+    };
+    const NONCE: [u8; 16] = [
+        0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0,
+        0x0f,
+    ];
+    const COMPONENTS: [DumpTweakComponent; 3] = [
+        [0; 16],
+        [0, 0, 0, 0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0, 0x10, 0],
+        [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ],
+    ];
+    const TWEAKS: [[u8; 16]; 3] = [
+        NONCE,
+        [
+            0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xb0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f, 0xa0, 0x0f,
+            0xb0, 0x0f,
+        ],
+        [
+            0xa0, 0x1f, 0xa2, 0x3f, 0xe4, 0x5f, 0xe6, 0x7f, 0xa8, 0x9f, 0xaa, 0xbf, 0xec, 0xdf,
+            0xee, 0xff,
+        ],
+    ];
+    const SHARED: DumpTweakComponent = [
+        0xff,
+        0xff,
+        0xff,
+        0xff,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        SPECIAL_SHARED_PAGE,
+    ];
+    const ZERO: DumpTweakComponent = [
+        0xff,
+        0xff,
+        0xff,
+        0xff,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        SPECIAL_ZERO_PAGE,
+    ];
+
+    /// Plaintext of the encrypted page `idx` of the known-answer test
+    fn plain(idx: usize) -> [u8; DUMP_PAGE_SIZE] {
+        std::array::from_fn(|j| (idx * 7 + j) as u8)
+    }
+
+    fn encr_page(idx: usize) -> &'static [u8; DUMP_PAGE_SIZE] {
+        let pages = get_test_asset!("exp/dump/xts_pages.bin");
+        pages[idx * DUMP_PAGE_SIZE..(idx + 1) * DUMP_PAGE_SIZE]
+            .try_into()
+            .unwrap()
+    }
+
+    fn decryptor() -> DumpDecryptor {
+        DumpDecryptor::new(KEY.into(), NONCE)
+    }
+
+    #[test]
+    fn tweak() {
+        for (component, tweak) in COMPONENTS.iter().zip(TWEAKS) {
+            assert_eq!(dump_tweak(component, &NONCE), tweak);
+        }
+    }
+
+    #[test]
+    fn decrypt_page() {
+        let decr = decryptor();
+        for (idx, component) in COMPONENTS.iter().enumerate() {
+            let page = decr.decrypt_page(component, encr_page(idx)).unwrap();
+            assert_eq!(page.value(), &plain(idx));
+        }
+        // the tweak nonce matters
+        let other = DumpDecryptor::new(KEY.into(), [0; 16]);
+        let page = other.decrypt_page(&COMPONENTS[1], encr_page(1)).unwrap();
+        assert_ne!(page.value(), &plain(1));
+    }
+
+    #[test]
+    fn key_size() {
+        assert!(DumpDecryptor::from_key_data(KEY.to_vec().into(), NONCE).is_ok());
+        assert!(matches!(
+            DumpDecryptor::from_key_data(vec![0; 32].into(), NONCE),
+            Err(Error::DumpKeySize(32))
+        ));
+    }
+
+    #[test]
+    fn page_state() {
+        let state = |c: &DumpTweakComponent| DumpPageState::from_component(c);
+        assert_eq!(state(&COMPONENTS[2]).unwrap(), DumpPageState::Encrypted);
+        assert_eq!(state(&SHARED).unwrap(), DumpPageState::Shared);
+        assert_eq!(state(&ZERO).unwrap(), DumpPageState::Zero);
+
+        let mut inv = ZERO;
+        for flags in [
+            0,
+            SPECIAL_MAPPED_PAGE,
+            SPECIAL_ZERO_PAGE | SPECIAL_SHARED_PAGE,
+            SPECIAL_ZERO_PAGE | SPECIAL_MAPPED_PAGE,
+            SPECIAL_SHARED_PAGE | SPECIAL_MAPPED_PAGE,
+            SPECIAL_ZERO_PAGE | SPECIAL_SHARED_PAGE | SPECIAL_MAPPED_PAGE,
+            SPECIAL_ZERO_PAGE | 0x80,
+        ] {
+            inv[15] = flags;
+            assert!(matches!(state(&inv), Err(Error::DumpInvPageState(_))));
+        }
+        let mut inv = ZERO;
+        inv[14] = 1;
+        assert!(matches!(state(&inv), Err(Error::DumpInvPageState(_))));
+    }
+
+    #[test]
+    fn reader() {
+        let shared = plain(42);
+        let mut input = Vec::new();
+        input.extend_from_slice(encr_page(0));
+        input.extend_from_slice(&shared);
+        input.extend_from_slice(&[0xff; DUMP_PAGE_SIZE]);
+        input.extend_from_slice(encr_page(1));
+        input.extend_from_slice(encr_page(2));
+        let components = [COMPONENTS[0], SHARED, ZERO, COMPONENTS[1], COMPONENTS[2]];
+
+        let decr = decryptor();
+        let mut out = Vec::new();
+        decr.reader(input.as_slice(), components)
+            .read_to_end(&mut out)
+            .unwrap();
+        let mut exp = Vec::new();
+        for page in [plain(0), shared, [0; DUMP_PAGE_SIZE], plain(1), plain(2)] {
+            exp.extend_from_slice(&page);
+        }
+        assert_eq!(out, exp);
+
+        // the tweak components limit the output
+        let mut out = Vec::new();
+        decr.reader(input.as_slice(), components[..2].iter().copied())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, exp[..2 * DUMP_PAGE_SIZE]);
+
+        // small reads
+        let mut rd = decr.reader(input.as_slice(), components);
+        let mut buf = [0; 100];
+        rd.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, plain(0)[..100]);
+    }
+
+    #[test]
+    fn reader_short_page() {
+        let input = &get_test_asset!("exp/dump/xts_pages.bin")[..DUMP_PAGE_SIZE + 10];
+        let decr = decryptor();
+        let err = decr
+            .reader(input, COMPONENTS)
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        let err = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert!(matches!(*err, Error::DumpPageShort { index: 1, size: 10 }));
+
+        let mut inv = ZERO;
+        inv[15] = 0;
+        let err = decr
+            .reader(input, [inv])
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        let err = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert!(matches!(*err, Error::DumpInvPageState(_)));
+    }
+}
@@ -10,5 +10,6 @@
 pub mod asrcb;
 pub mod ext_secret;
 pub mod guest_secret;
+pub mod receipt;
 pub mod retr_secret;
 pub mod user_data;
@@ -2,27 +2,31 @@
 //
 // Copyright IBM Corp. 2023
 
+use std::{
+    fmt::Display,
+    io::{Read, Seek, SeekFrom},
+    mem::size_of,
+};
+
 use super::user_data::UserData;
 use crate::{
     assert_size,
-    crypto::{hkdf_rfc_5869, AeadEncryptionResult},
-    misc::Flags,
-    req::{Aad, BinReqValues, Keyslot, ReqEncrCtx},
-    request::{BootHdrTags, Confidential, Request},
-    secret::{ExtSecret, GuestSecret},
-    uv::{ConfigUid, UvFlags},
-    Result,
-};
-use openssl::{
-    md::Md,
-    pkey::{PKey, Private, Public},
+    crypto::{hkdf_rfc_5869, AeadEncryptionResult, Digest},
+    misc::{encode_hex, short_id, Flags},
+    req::{keyslot_hashes, Aad, BinReqValues, Keyslot, ReqEncrCtx, KEYSLOT_SIZE},
+    request::{BootHdrTags, Confidential, EcPubKeyCoord, Request, Summary},
+    secret::{ExtSecret, GuestSecret, UserDataType},
+    uv::{ConfigUid, ListableSecretType, SecretId, UvFlags},
+    Error, Result,
 };
+use byteorder::{BigEndian, ByteOrder};
+use openssl::pkey::{PKey, PKeyRef, Private, Public};
 use pv_core::{request::RequestVersion, secret::AddSecretMagic};
-use zerocopy::AsBytes;
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
 
 /// Authenticated data w/o user data
 #[repr(C)]
-#[derive(Debug, Clone, Copy, AsBytes)]
+#[derive(Debug, Clone, Copy, AsBytes, FromBytes, FromZeroes)]
 struct ReqAuthData {
     flags: UvFlags,
     boot_tags: BootHdrTags,
@@ -62,6 +66,24 @@ impl ReqConfData {
     }
 }
 
+/// Returns the extension secret as it is placed in the request
+fn ext_secret_bytes(
+    ext_secret: ExtSecret,
+    boot_tags: &BootHdrTags,
+) -> Result<Confidential<[u8; 32]>> {
+    const DER_EXT_SECRET_INFO: &[u8] = "IBM Z Ultravisor Add-Secret".as_bytes();
+    Ok(match ext_secret {
+        ExtSecret::Simple(s) => s,
+        ExtSecret::Derived(cck) => hkdf_rfc_5869(
+            Digest::Sha512,
+            cck.value(),
+            boot_tags.tag(),
+            DER_EXT_SECRET_INFO,
+        )?
+        .into(),
+    })
+}
+
 /// Flags for [`AddSecretRequest`]
 #[derive(Default, Clone, Copy, Debug)]
 pub struct AddSecretFlags(UvFlags);
@@ -143,6 +165,9 @@ pub struct AddSecretRequest {
 impl AddSecretRequest {
     /// Offset of the user-data in the add-secret request in bytes
     pub(super) const V1_USER_DATA_OFFS: usize = 0x218;
+    /// Offset of the keyslots in the request type dependent AAD in bytes
+    const V1_KEYSLOTS_OFFS: usize =
+        size_of::<ReqAuthData>() + UserData::USER_DATA_SIZE + size_of::<EcPubKeyCoord>();
 
     /// Create a new add-secret request.
     ///
@@ -177,17 +202,7 @@ impl AddSecretRequest {
     ///
     /// This function will return an error if the key derivation fails for a [`ExtSecret::Derived`].
     pub fn set_ext_secret(&mut self, ext_secret: ExtSecret) -> Result<()> {
-        const DER_EXT_SECRET_INFO: &[u8] = "IBM Z Ultravisor Add-Secret".as_bytes();
-        self.conf.extension_secret = match ext_secret {
-            ExtSecret::Simple(s) => s,
-            ExtSecret::Derived(cck) => hkdf_rfc_5869(
-                Md::sha512(),
-                cck.value(),
-                self.aad.boot_tags.tag(),
-                DER_EXT_SECRET_INFO,
-            )?
-            .into(),
-        };
+        self.conf.extension_secret = ext_secret_bytes(ext_secret, &self.aad.boot_tags)?;
         Ok(())
     }
 
@@ -288,6 +303,82 @@ impl AddSecretRequest {
         AddSecretMagic::try_from_bytes(asrcb)?;
         BinReqValues::get(asrcb).map(|v| v.tag().to_vec())
     }
+
+    /// Read the add secret request tag from `reader`
+    ///
+    /// The request starts at the current position of `reader`. Only the request header and the
+    /// tag are read. In contrast to [`Self::bin_tag`], the (partly confidential) rest of the
+    /// request is not read into memory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `reader` contains no add-secret request, the
+    /// request is truncated, or reading fails.
+    pub fn bin_tag_from_reader<R: Read + Seek>(
+        reader: &mut R,
+    ) -> Result<[u8; BinReqValues::TAG_LEN]> {
+        let start = reader.stream_position()?;
+        let mut hdr = Vec::with_capacity(BinReqValues::HDR_LEN);
+        reader
+            .by_ref()
+            .take(BinReqValues::HDR_LEN as u64)
+            .read_to_end(&mut hdr)?;
+        AddSecretMagic::try_from_bytes(&hdr)?;
+        let tag_range = BinReqValues::tag_range(&hdr)?;
+
+        let size = reader.seek(SeekFrom::End(0))? - start;
+        if size < tag_range.end as u64 {
+            return Err(Error::BinRequestTruncated {
+                size,
+                expected: tag_range.end,
+            });
+        }
+        reader.seek(SeekFrom::Start(start + tag_range.start as u64))?;
+        let mut tag = [0; BinReqValues::TAG_LEN];
+        reader.read_exact(&mut tag)?;
+        Ok(tag)
+    }
+
+    /// Get the host-key hashes of all keyslots of a binary add-secret request
+    ///
+    /// These are the SHA-256 hashes of the public host-keys the request was created for. Only
+    /// IBM Z machines with one of these host-keys can add the secret.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `asrcb` is no add-secret request or its keyslots
+    /// exceed the request.
+    pub fn bin_host_key_hashes(asrcb: &[u8]) -> Result<Vec<[u8; Keyslot::PHKH_SIZE as usize]>> {
+        AddSecretMagic::try_from_bytes(asrcb)?;
+        let values = BinReqValues::get(asrcb)?;
+        let keyslots = values
+            .req_dep_aad_bytes()
+            .get(Self::V1_KEYSLOTS_OFFS..)
+            .ok_or(Error::BinRequestSmall)?;
+        keyslot_hashes(keyslots, values.nks())
+    }
+}
+
+impl Summary for AddSecretRequest {
+    fn summary(&self) -> String {
+        let cuid = match self.aad.cuid {
+            cuid if cuid == [0; 0x10] => "no CUID".to_string(),
+            cuid => format!("CUID {}", short_id(&cuid)),
+        };
+        let user_data = match self.user_data {
+            UserData::Null => "no user-data",
+            UserData::Unsigned(_) => "unsigned user-data",
+            UserData::Signed(_) => "signed user-data",
+        };
+        format!(
+            "Add-secret request v{:#06x}: {}, {} host-key(s), {}, {cuid}, flags {:#018x}, {user_data}",
+            RequestVersion::from(self.version),
+            self.conf.secret.summary(),
+            self.keyslots.len(),
+            self.aad.boot_tags.summary(),
+            u64::from(self.aad.flags),
+        )
+    }
 }
 
 impl Request for AddSecretRequest {
@@ -307,3 +398,548 @@ impl Request for AddSecretRequest {
         self.keyslots.push(Keyslot::new(hostkey))
     }
 }
+
+/// Settings shared by all add-secret requests for one guest
+///
+/// Holds the version, the SE-header tags, the configuration UID, the host-keys, and the extension
+/// secret. Use it to create multiple [`AddSecretRequest`]s for the same guest without setting up
+/// (and verifying) the host-keys for each of them.
+#[derive(Debug, Clone)]
+pub struct AddSecretContext {
+    version: AddSecretVersion,
+    boot_tags: BootHdrTags,
+    cuid: ConfigUid,
+    keyslots: Vec<Keyslot>,
+    extension_secret: Confidential<[u8; 32]>,
+}
+
+impl AddSecretContext {
+    /// Create a new add-secret context.
+    ///
+    /// The context has no extension secret, no configuration UID, and no host-keys
+    pub fn new(version: AddSecretVersion, boot_tags: BootHdrTags) -> Self {
+        Self {
+            version,
+            boot_tags,
+            cuid: [0; 0x10],
+            keyslots: vec![],
+            extension_secret: Confidential::new([0; 32]),
+        }
+    }
+
+    /// Sets the Configuration Unique Id of this [`AddSecretContext`].
+    pub fn set_cuid(&mut self, cuid: ConfigUid) {
+        self.cuid = cuid;
+    }
+
+    /// Sets the extension secret of this [`AddSecretContext`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the key derivation fails for a [`ExtSecret::Derived`].
+    pub fn set_ext_secret(&mut self, ext_secret: ExtSecret) -> Result<()> {
+        self.extension_secret = ext_secret_bytes(ext_secret, &self.boot_tags)?;
+        Ok(())
+    }
+
+    /// Adds a host-key to this [`AddSecretContext`].
+    pub fn add_hostkey(&mut self, hostkey: PKey<Public>) {
+        self.keyslots.push(Keyslot::new(hostkey))
+    }
+
+    /// Create an add-secret request for `secret` with the settings of this context.
+    ///
+    /// The request has no user data. Encrypt each request with its own [`ReqEncrCtx`].
+    pub fn request(&self, secret: GuestSecret, flags: AddSecretFlags) -> AddSecretRequest {
+        let mut aad = ReqAuthData::new(self.boot_tags, flags);
+        aad.cuid = self.cuid;
+        AddSecretRequest {
+            version: self.version,
+            aad,
+            keyslots: self.keyslots.clone(),
+            conf: ReqConfData {
+                secret,
+                extension_secret: self.extension_secret.clone(),
+            },
+            user_data: UserData::Null,
+        }
+    }
+}
+
+/// Information about a binary add-secret request
+///
+/// Contains the parts of the request that are not encrypted, i.e., the header, the host-key
+/// hashes of the keyslots, and the secret header with the type and ID of the secret. Nothing is
+/// decrypted, so neither the secret nor the integrity of the request is verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsrcbInfo {
+    version: RequestVersion,
+    user_data: UserDataType,
+    flags: u64,
+    cuid: ConfigUid,
+    host_key_hashes: Vec<[u8; Keyslot::PHKH_SIZE as usize]>,
+    secret_kind: u16,
+    secret_len: u32,
+    secret_id: Option<SecretId>,
+}
+
+impl AsrcbInfo {
+    /// Size of the secret header of a null secret
+    const NULL_SECRET_HDR_SIZE: usize = 0x10;
+    /// Size of the secret header of a listable secret
+    const LISTABLE_SECRET_HDR_SIZE: usize = 0x30;
+
+    /// Parse the unencrypted parts of a binary add-secret request
+    ///
+    /// Additional data behind the request is ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `asrcb` is no add-secret request version 1, is
+    /// truncated, or the sizes in its header do not match its content.
+    pub fn parse(asrcb: &[u8]) -> Result<Self> {
+        let magic = AddSecretMagic::try_from_bytes(asrcb)?;
+        if asrcb.len() < BinReqValues::HDR_LEN {
+            return Err(Error::BinRequestTruncated {
+                size: asrcb.len() as u64,
+                expected: BinReqValues::HDR_LEN,
+            });
+        }
+        let req_len = BinReqValues::tag_range(asrcb)?.end;
+        if asrcb.len() < req_len {
+            return Err(Error::BinRequestTruncated {
+                size: asrcb.len() as u64,
+                expected: req_len,
+            });
+        }
+        let values = BinReqValues::get(asrcb)?;
+        if values.version() != AddSecretVersion::One as u32 {
+            return Err(Error::BinAsrcbInvVersion);
+        }
+
+        let req_dep_aad = values.req_dep_aad_bytes();
+        let secret_hdr_offs = AddSecretRequest::V1_KEYSLOTS_OFFS + values.nks() * KEYSLOT_SIZE;
+        let secret_kind = req_dep_aad
+            .get(secret_hdr_offs + 2..secret_hdr_offs + 4)
+            .map(BigEndian::read_u16);
+        let secret_hdr_size = match secret_kind {
+            Some(ListableSecretType::NULL) => Self::NULL_SECRET_HDR_SIZE,
+            _ => Self::LISTABLE_SECRET_HDR_SIZE,
+        };
+        let aad_size = BinReqValues::HDR_LEN + secret_hdr_offs + secret_hdr_size;
+        let (Some(secret_kind), true) = (secret_kind, values.aad_len() == aad_size) else {
+            return Err(Error::BinAsrcbAadSize {
+                size: values.aad_len(),
+                expected: aad_size,
+                nks: values.nks(),
+            });
+        };
+
+        let secret_hdr = &req_dep_aad[secret_hdr_offs..];
+        let secret_len = BigEndian::read_u32(&secret_hdr[4..8]);
+        let secret_id = match secret_kind {
+            ListableSecretType::NULL => None,
+            // Panic: the listable secret header is 0x30 bytes long
+            _ => Some(SecretId::from(secret_hdr[0x10..0x30].try_into().unwrap())),
+        };
+        // the secret is followed by the 32 byte extension secret
+        let encr_size = secret_len as usize + 32;
+        if values.sea() as usize != encr_size {
+            return Err(Error::BinAsrcbEncrSize {
+                size: values.sea() as usize,
+                expected: encr_size,
+                secret_len,
+            });
+        }
+
+        // Panic: the AAD is larger than the keyslot offset
+        let aad = ReqAuthData::read_from_prefix(req_dep_aad).unwrap();
+        Ok(Self {
+            version: values.version(),
+            user_data: magic.kind(),
+            flags: aad.flags.into(),
+            cuid: aad.cuid,
+            host_key_hashes: AddSecretRequest::bin_host_key_hashes(asrcb)?,
+            secret_kind,
+            secret_len,
+            secret_id,
+        })
+    }
+
+    /// Returns the version of the request.
+    pub fn version(&self) -> RequestVersion {
+        self.version
+    }
+
+    /// Returns the plaintext add-secret flags of the request.
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// Returns `true` if the request disables dumping of the guest.
+    pub fn disable_dump(&self) -> bool {
+        UvFlags::from(self.flags).is_set(0)
+    }
+
+    /// Returns the configuration UID the request is bound to, if any.
+    pub fn cuid(&self) -> Option<&ConfigUid> {
+        match self.cuid {
+            cuid if cuid == [0; 0x10] => None,
+            _ => Some(&self.cuid),
+        }
+    }
+
+    /// Returns the type of the user-data of the request.
+    pub fn user_data_type(&self) -> UserDataType {
+        self.user_data
+    }
+
+    /// Returns the host-key hashes of all keyslots.
+    ///
+    /// These are the SHA-256 hashes of the public host-keys the request was created for.
+    pub fn host_key_hashes(&self) -> &[[u8; Keyslot::PHKH_SIZE as usize]] {
+        &self.host_key_hashes
+    }
+
+    /// Returns `true` if the request has a keyslot for `host_key`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `host_key` is no EC key or OpenSSL could not compute
+    /// the hash.
+    pub fn has_host_key(&self, host_key: &PKeyRef<Public>) -> Result<bool> {
+        let hash = Keyslot::host_key_hash(host_key)?;
+        Ok(self.host_key_hashes.contains(&hash))
+    }
+
+    /// Returns the UV secret-type id of the secret.
+    ///
+    /// See [`ListableSecretType`] for the known ids.
+    pub fn secret_kind(&self) -> u16 {
+        self.secret_kind
+    }
+
+    /// Returns the size of the secret in bytes.
+    pub fn secret_len(&self) -> u32 {
+        self.secret_len
+    }
+
+    /// Returns the ID of the secret, if it is not a null secret.
+    pub fn secret_id(&self) -> Option<&SecretId> {
+        self.secret_id.as_ref()
+    }
+}
+
+impl Display for AsrcbInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Version: {:#06x}", self.version)?;
+        write!(f, "Flags: {:#018x}", self.flags())?;
+        match self.disable_dump() {
+            true => writeln!(f, " (disable-dump)")?,
+            false => writeln!(f)?,
+        }
+        match self.cuid() {
+            Some(cuid) => writeln!(f, "CUID: 0x{}", encode_hex(cuid))?,
+            None => writeln!(f, "CUID: none")?,
+        }
+        writeln!(f, "User-data: {}", self.user_data)?;
+        match self.secret_kind {
+            ListableSecretType::NULL => writeln!(f, "Secret: Null")?,
+            kind => writeln!(
+                f,
+                "Secret: {} ({} bytes)",
+                ListableSecretType::from(kind),
+                self.secret_len
+            )?,
+        }
+        if let Some(id) = &self.secret_id {
+            writeln!(f, "Secret ID: {id}")?;
+        }
+        writeln!(f, "Keyslots: {}", self.host_key_hashes.len())?;
+        for hash in &self.host_key_hashes {
+            writeln!(f, "  0x{}", encode_hex(hash))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        misc::read_certs,
+        request::SymKeyType,
+        test_utils::{assert_no_secret, get_cert_asset_path, get_test_keys},
+    };
+
+    fn phkh(key: &PKey<Public>) -> [u8; 32] {
+        Keyslot::host_key_hash(key.as_ref()).unwrap()
+    }
+
+    fn encrypted_asrcb(host_keys: &[PKey<Public>]) -> Vec<u8> {
+        let mut asrcb = AddSecretRequest::new(
+            AddSecretVersion::One,
+            GuestSecret::Null,
+            BootHdrTags::try_from(vec![4; 0xd0]).unwrap(),
+            AddSecretFlags::default(),
+        );
+        host_keys
+            .iter()
+            .for_each(|k| asrcb.add_hostkey(k.to_owned()));
+        asrcb
+            .encrypt(&ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn bin_host_key_hashes() {
+        let host_key = get_test_keys().1;
+        let crt = std::fs::read(get_cert_asset_path("host.crt")).unwrap();
+        let host_crt = read_certs(crt).unwrap()[0].public_key().unwrap();
+
+        let asrcb = encrypted_asrcb(&[host_key.clone(), host_crt.clone()]);
+        assert_eq!(
+            AddSecretRequest::bin_host_key_hashes(&asrcb).unwrap(),
+            [phkh(&host_key), phkh(&host_crt)]
+        );
+
+        let asrcb = encrypted_asrcb(std::slice::from_ref(&host_crt));
+        assert_eq!(
+            AddSecretRequest::bin_host_key_hashes(&asrcb).unwrap(),
+            [phkh(&host_crt)]
+        );
+    }
+
+    #[test]
+    fn bin_host_key_hashes_invalid() {
+        let asrcb = encrypted_asrcb(&[get_test_keys().1]);
+        // truncated requests must not panic
+        for len in [0, 0x30, 0x4b8, asrcb.len() - 1] {
+            assert!(AddSecretRequest::bin_host_key_hashes(&asrcb[..len]).is_err());
+        }
+        assert!(AddSecretRequest::bin_tag(&asrcb[..asrcb.len() - 1]).is_err());
+
+        // more keyslots than fit into the request
+        let mut many_ks = asrcb.clone();
+        many_ks[0x27] = 0xff;
+        assert!(matches!(
+            AddSecretRequest::bin_host_key_hashes(&many_ks),
+            Err(Error::BinRequestSmall)
+        ));
+
+        // additional data behind the request is ignored
+        let mut long = asrcb.clone();
+        long.extend_from_slice(&[0; 0x10]);
+        assert_eq!(
+            AddSecretRequest::bin_host_key_hashes(&long).unwrap(),
+            AddSecretRequest::bin_host_key_hashes(&asrcb).unwrap()
+        );
+        assert_eq!(
+            AddSecretRequest::bin_tag(&long).unwrap(),
+            AddSecretRequest::bin_tag(&asrcb).unwrap()
+        );
+
+        let mut no_asrcb = asrcb;
+        no_asrcb[0] = 0;
+        assert!(AddSecretRequest::bin_host_key_hashes(&no_asrcb).is_err());
+    }
+
+    #[test]
+    fn bin_tag_reader() {
+        use std::io::Cursor;
+
+        for asrcb in [
+            crate::get_test_asset!("exp/asrcb/assoc_derived_default_cuid_one").as_slice(),
+            crate::get_test_asset!("exp/asrcb/null_none_default_cuid_seven"),
+            crate::get_test_asset!("exp/asrcb/null_simple_default_cuid_one"),
+        ] {
+            let tag = AddSecretRequest::bin_tag_from_reader(&mut Cursor::new(asrcb)).unwrap();
+            assert_eq!(tag.as_slice(), AddSecretRequest::bin_tag(asrcb).unwrap());
+
+            // the request starts at the current position
+            let mut prefixed = Cursor::new([&[0; 7], asrcb].concat());
+            prefixed.set_position(7);
+            assert_eq!(
+                AddSecretRequest::bin_tag_from_reader(&mut prefixed).unwrap(),
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn bin_tag_reader_invalid() {
+        use std::io::Cursor;
+
+        let asrcb = crate::get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one");
+        let truncated = &asrcb[..asrcb.len() - 1];
+        assert!(matches!(
+            AddSecretRequest::bin_tag_from_reader(&mut Cursor::new(truncated)),
+            Err(Error::BinRequestTruncated { size, expected })
+                if size == truncated.len() as u64 && expected == asrcb.len()
+        ));
+        assert!(matches!(
+            AddSecretRequest::bin_tag_from_reader(&mut Cursor::new(&asrcb[..0x20])),
+            Err(Error::BinRequestSmall)
+        ));
+
+        let mut no_asrcb = asrcb.to_vec();
+        no_asrcb[0] = 0;
+        for input in [no_asrcb.as_slice(), &[]] {
+            assert!(matches!(
+                AddSecretRequest::bin_tag_from_reader(&mut Cursor::new(input)),
+                Err(Error::PvCore(pv_core::Error::NoAsrcb))
+            ));
+        }
+    }
+
+    #[test]
+    fn asrcb_info() {
+        let host_key = get_test_keys().1;
+        let info =
+            AsrcbInfo::parse(crate::get_test_asset!("exp/asrcb/null_none_dump_cuid_one")).unwrap();
+        assert_eq!(info.version(), AddSecretVersion::One as u32);
+        assert_eq!(info.flags(), 0x8000000000000000);
+        assert!(info.disable_dump());
+        assert_eq!(info.cuid(), Some(&[0x42; 16]));
+        assert_eq!(info.user_data_type(), UserDataType::Null);
+        assert_eq!(info.secret_kind(), ListableSecretType::NULL);
+        assert_eq!(info.secret_len(), 0);
+        assert_eq!(info.secret_id(), None);
+        assert_eq!(info.host_key_hashes(), [phkh(&host_key)]);
+        assert!(info.has_host_key(&host_key).unwrap());
+        assert_eq!(
+            info.to_string(),
+            "Version: 0x0100\n\
+             Flags: 0x8000000000000000 (disable-dump)\n\
+             CUID: 0x42424242424242424242424242424242\n\
+             User-data: None\n\
+             Secret: Null\n\
+             Keyslots: 1\n  \
+             0x415c25a5d19c381e619733e1c1b7fbd88bdd1c31d882d5ade04b97043c5010f0\n"
+        );
+
+        let info = AsrcbInfo::parse(crate::get_test_asset!(
+            "exp/asrcb/retr_ec_none_default_ncuid_one"
+        ))
+        .unwrap();
+        assert!(!info.disable_dump());
+        assert_eq!(info.cuid(), None);
+        assert_eq!(info.secret_kind(), ListableSecretType::ECDSA_P521_KEY);
+        assert_eq!(info.secret_len(), 80);
+        assert!(info.secret_id().is_some());
+        assert!(info
+            .to_string()
+            .contains("Secret: EC-SECP521R1-PRIVATE-KEY (80 bytes)\nSecret ID: 0x"));
+
+        let info = AsrcbInfo::parse(crate::get_test_asset!(
+            "exp/asrcb/null_none_default_cuid_seven"
+        ))
+        .unwrap();
+        assert_eq!(info.host_key_hashes(), [phkh(&host_key); 7]);
+        let crt = std::fs::read(get_cert_asset_path("host.crt")).unwrap();
+        let host_crt = read_certs(crt).unwrap()[0].public_key().unwrap();
+        assert!(!info.has_host_key(&host_crt).unwrap());
+
+        // additional data behind the request is ignored
+        let asrcb = crate::get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one");
+        let info = AsrcbInfo::parse(asrcb).unwrap();
+        assert_eq!(
+            AsrcbInfo::parse(&[asrcb.as_slice(), &[0; 0x10]].concat()).unwrap(),
+            info
+        );
+        assert_eq!(info.secret_kind(), ListableSecretType::ASSOCIATION);
+
+        let asrcb = encrypted_asrcb(&[host_key.clone(), host_crt.clone()]);
+        let info = AsrcbInfo::parse(&asrcb).unwrap();
+        assert_eq!(info.host_key_hashes(), [phkh(&host_key), phkh(&host_crt)]);
+    }
+
+    #[test]
+    fn asrcb_info_invalid() {
+        let asrcb = crate::get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one");
+        // truncated requests must not panic
+        for len in 0..asrcb.len() {
+            assert!(AsrcbInfo::parse(&asrcb[..len]).is_err());
+        }
+        assert!(matches!(
+            AsrcbInfo::parse(&asrcb[..7]),
+            Err(Error::PvCore(pv_core::Error::NoAsrcb))
+        ));
+        assert!(matches!(
+            AsrcbInfo::parse(&asrcb[..0x20]),
+            Err(Error::BinRequestTruncated {
+                size: 0x20,
+                expected: 0x30
+            })
+        ));
+        assert!(matches!(
+            AsrcbInfo::parse(&asrcb[..asrcb.len() - 1]),
+            Err(Error::BinRequestTruncated { size, expected })
+                if size == asrcb.len() as u64 - 1 && expected == asrcb.len()
+        ));
+
+        let mut no_asrcb = asrcb.to_vec();
+        no_asrcb[0] = 0;
+        assert!(matches!(
+            AsrcbInfo::parse(&no_asrcb),
+            Err(Error::PvCore(pv_core::Error::NoAsrcb))
+        ));
+
+        let mut version = asrcb.to_vec();
+        version[0xa] = 2;
+        assert!(matches!(
+            AsrcbInfo::parse(&version),
+            Err(Error::BinAsrcbInvVersion)
+        ));
+
+        // keyslots that do not fit to the secret header
+        for nks in [0, 2, 0xff] {
+            let mut ks = asrcb.to_vec();
+            ks[0x27] = nks;
+            assert!(matches!(
+                AsrcbInfo::parse(&ks),
+                Err(Error::BinAsrcbAadSize { size: 0x538, nks: n, .. }) if n == nks as usize
+            ));
+        }
+
+        // secret size that does not fit to the encrypted area
+        let mut secret_len = asrcb.to_vec();
+        secret_len[0x538 - 0x30 + 7] = 0x21;
+        assert!(matches!(
+            AsrcbInfo::parse(&secret_len),
+            Err(Error::BinAsrcbEncrSize {
+                size: 0x40,
+                expected: 0x41,
+                secret_len: 0x21
+            })
+        ));
+    }
+
+    #[test]
+    fn summary_no_secret() {
+        let secret_value = [0x5a; 32];
+        let ext_secret = [0xa5; 32];
+        let mut asrcb = AddSecretRequest::new(
+            AddSecretVersion::One,
+            GuestSecret::association("association secret", secret_value).unwrap(),
+            BootHdrTags::try_from(vec![4; 0xd0]).unwrap(),
+            AddSecretFlags::default(),
+        );
+        asrcb.add_hostkey(get_test_keys().1);
+        asrcb.set_cuid([0x11; 16]);
+        asrcb
+            .set_ext_secret(ExtSecret::Simple(ext_secret.into()))
+            .unwrap();
+        asrcb.set_user_data(vec![0x42; 32], None).unwrap();
+
+        let summary = asrcb.summary();
+        assert_no_secret(&summary, &secret_value);
+        assert_no_secret(&summary, &ext_secret);
+        assert_eq!(
+            summary,
+            "Add-secret request v0x0100: Association secret 'association secret' (ID \
+             0x75ad01b4..., 32 bytes), 1 host-key(s), SE-header tag 0x04040404..., CUID \
+             0x11111111..., flags 0x0000000000000000, unsigned user-data"
+        );
+    }
+}
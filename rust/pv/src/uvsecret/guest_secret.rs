@@ -6,10 +6,10 @@
 use super::asrcb::AddSecretRequest;
 use crate::{
     assert_size,
-    crypto::{hash, random_array, SymKeyType},
+    crypto::{hash, random_array, Digest, SymKeyType},
     request::{
         openssl::{NID_ED25519, NID_ED448},
-        Confidential,
+        Confidential, Summary,
     },
     uv::{
         AesSizes, AesXtsSizes, EcCurves, HmacShaSizes, ListableSecretType, RetrievableSecret,
@@ -19,7 +19,6 @@ use crate::{
 };
 use byteorder::BigEndian;
 use openssl::{
-    hash::MessageDigest,
     nid::Nid,
     pkey::{Id, PKey, PKeyRef, Private},
 };
@@ -94,7 +93,7 @@ macro_rules! retr_constructor {
 impl GuestSecret {
     /// Hashes the name with sha256
     pub fn name_to_id(name: &str) -> Result<SecretId> {
-        let id: [u8; SecretId::ID_SIZE] = hash(MessageDigest::sha256(), name.as_bytes())?
+        let id: [u8; SecretId::ID_SIZE] = hash(Digest::Sha256, name.as_bytes())?
             .to_vec()
             .try_into()
             .unwrap();
@@ -125,7 +124,7 @@ impl GuestSecret {
         })
     }
 
-    retr_constructor!(#[doc = r"This function will return an error if the secret is larger than 8 pages"]
+    retr_constructor!(#[doc = r"This function will return an error if OpenSSL cannot create a hash or the secret is larger than 8190 bytes"]
                       | #[doc = r"plaintext"] => Confidential<Vec<u8>>, plaintext);
     retr_constructor!(#[doc = r"This function will return an error if  OpenSSL cannot create a hash or the secret size is invalid"]
                       | #[doc = r"AES Key"] => Confidential<Vec<u8>>, aes);
@@ -217,7 +216,7 @@ fn extend_to_multiple(mut key: Vec<u8>, multiple: usize) -> Confidential<Vec<u8>
 /// ```
 fn plaintext(inp: Confidential<Vec<u8>>) -> Result<RetrKeyInfo> {
     let key_len = inp.value().len();
-    if key_len > RetrieveCmd::MAX_SIZE {
+    if key_len > MAX_SIZE_PLAIN_PAYLOAD {
         return Err(Error::RetrInvKey {
             what: "key size",
             value: key_len.to_string(),
@@ -364,6 +363,19 @@ impl Display for GuestSecret {
     }
 }
 
+impl Summary for GuestSecret {
+    fn summary(&self) -> String {
+        match self {
+            Self::Null => format!("{self} secret"),
+            Self::Association { name, id, .. } | Self::Retrievable { name, id, .. } => format!(
+                "{self} secret '{name}' (ID {}, {} bytes)",
+                id.summary(),
+                self.confidential().len()
+            ),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum SecretAuth {
     Null,
@@ -499,6 +511,19 @@ mod test {
         assert_eq!(secret, exp);
     }
 
+    #[test]
+    fn plaintext_max_size() {
+        let (_, key) = plaintext(vec![7; MAX_SIZE_PLAIN_PAYLOAD].into()).unwrap();
+        assert_eq!(key.value().len(), RetrieveCmd::MAX_SIZE);
+
+        let err = plaintext(vec![7; MAX_SIZE_PLAIN_PAYLOAD + 1].into()).unwrap_err();
+        assert!(matches!(err, Error::RetrInvKey { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Incorrect key size for a PLAINTEXT. Is: 8191; expected: at most 8190 bytes"
+        );
+    }
+
     #[track_caller]
     fn gen_ec(nid: Nid) -> PKey<Private> {
         let group = EcGroup::from_curve_name(nid).unwrap();
@@ -643,4 +668,24 @@ mod test {
         assert_eq!(exp, gs_bytes_auth);
         assert_eq!(&[2; 32], gs.confidential());
     }
+
+    #[test]
+    fn summary_no_secret() {
+        let secret_value = [0x5a; 32];
+        let assoc = GuestSecret::association("association secret", secret_value).unwrap();
+        let summary = assoc.summary();
+        assert_eq!(
+            summary,
+            "Association secret 'association secret' (ID 0x75ad01b4..., 32 bytes)"
+        );
+        crate::test_utils::assert_no_secret(&summary, &secret_value);
+
+        let secret_value = vec![0xa5; 32];
+        let aes = GuestSecret::aes("aes secret", secret_value.clone().into()).unwrap();
+        let summary = aes.summary();
+        assert!(summary.starts_with("AES-256-KEY secret 'aes secret' (ID 0x"));
+        crate::test_utils::assert_no_secret(&summary, &secret_value);
+
+        assert_eq!(GuestSecret::Null.summary(), "Meta secret");
+    }
 }
@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::io::Cursor;
+
+use pv_core::misc::{decode_hex, encode_hex};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::asrcb::AddSecretRequest;
+use crate::{
+    crypto::{hash, Digest},
+    uvattest::{
+        additional::SECRET_STORE_HASH_SIZE,
+        secret_store::{SecretStoreState, SECRET_TAG_SIZE},
+    },
+    Error, Result,
+};
+
+/// Size of a SHA-256 digest
+const SHA256_SIZE: usize = 0x20;
+/// Return code of the Ultravisor for a successful add-secret request
+const UV_RC_SUCCESS: u16 = 0x0001;
+
+/// Byte array serialized as `0x` prefixed hex string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hex<const N: usize>([u8; N]);
+
+impl<const N: usize> Serialize for Hex<N> {
+    fn serialize<S>(&self, ser: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ser.serialize_str(&format!("0x{}", encode_hex(self.0)))
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Hex<N> {
+    fn deserialize<D>(de: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        let hex = s
+            .strip_prefix("0x")
+            .ok_or_else(|| serde::de::Error::custom(format!("'{s}' has no 0x prefix")))?;
+        let bytes = decode_hex(hex).map_err(serde::de::Error::custom)?;
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|b: Vec<u8>| serde::de::Error::invalid_length(b.len(), &&*format!("{N}")))
+    }
+}
+
+/// Receipt of a successful add-secret request
+///
+/// Binds the tag of the add-secret request to the state of the secret store before and after the
+/// secret was added. The store state is given by the tags of all added requests in order. A
+/// receipt optionally refers to the receipt of the previous add-secret request by its SHA-256
+/// digest. A chain of receipts therefore reproduces the secret-store-hash the Ultravisor reports
+/// in an attestation response.
+///
+/// The receipt does not contain any secret and it is not signed. It is as trustworthy as the
+/// environment that created it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AddSecretReceipt {
+    version: u32,
+    request_tag: Hex<SECRET_TAG_SIZE>,
+    request_sha256: Hex<SHA256_SIZE>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_receipt_sha256: Option<Hex<SHA256_SIZE>>,
+    uv_rc: u16,
+    store_tags: Vec<Hex<SECRET_TAG_SIZE>>,
+    store_hash_before: Hex<{ SECRET_STORE_HASH_SIZE as usize }>,
+    store_hash_after: Hex<{ SECRET_STORE_HASH_SIZE as usize }>,
+}
+
+fn sha256(data: &[u8]) -> Result<Hex<SHA256_SIZE>> {
+    // Panic: SHA-256 digests are 32 bytes long
+    Ok(Hex(hash(Digest::Sha256, data)?
+        .as_ref()
+        .try_into()
+        .unwrap()))
+}
+
+impl AddSecretReceipt {
+    /// Version of the receipt format
+    pub const VERSION: u32 = 1;
+
+    /// Maximum size of a serialized receipt
+    ///
+    /// Far larger than the receipt of a full secret store.
+    pub const MAX_SIZE: usize = 0x800000;
+
+    /// Create the receipt of adding `asrcb` to a secret store in the state `before`
+    ///
+    /// `uv_rc` is the return code of the Ultravisor. `previous` is the receipt (as written to
+    /// disk) of the add-secret request that resulted in `before`, if any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `asrcb` is no add-secret request, the secret store
+    /// is locked, or OpenSSL could not compute a hash.
+    pub fn new(
+        before: &SecretStoreState,
+        asrcb: &[u8],
+        uv_rc: u16,
+        previous: Option<&[u8]>,
+    ) -> Result<Self> {
+        let tag = AddSecretRequest::bin_tag_from_reader(&mut Cursor::new(asrcb))?;
+        let mut after = before.clone();
+        after.add(tag)?;
+        Ok(Self {
+            version: Self::VERSION,
+            request_tag: Hex(tag),
+            request_sha256: sha256(asrcb)?,
+            previous_receipt_sha256: previous.map(sha256).transpose()?,
+            uv_rc,
+            store_tags: after.tags().iter().copied().map(Hex).collect(),
+            store_hash_before: Hex(before.hash()?),
+            store_hash_after: Hex(after.hash()?),
+        })
+    }
+
+    /// Tag of the added add-secret request
+    pub fn request_tag(&self) -> &[u8; SECRET_TAG_SIZE] {
+        &self.request_tag.0
+    }
+
+    /// Secret-store-hash after the secret was added
+    pub fn store_hash_after(&self) -> &[u8; SECRET_STORE_HASH_SIZE as usize] {
+        &self.store_hash_after.0
+    }
+
+    /// Verify that the receipt is consistent and return the secret store state after the add
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the receipt has an unsupported version, does not
+    /// report a successful add-secret request, or the hashes do not match with the tags.
+    pub fn verify(&self) -> Result<SecretStoreState> {
+        if self.version != Self::VERSION {
+            return Err(Error::InvAddSecretReceipt("Unsupported version"));
+        }
+        if self.uv_rc != UV_RC_SUCCESS {
+            return Err(Error::InvAddSecretReceipt(
+                "The Ultravisor did not add the secret",
+            ));
+        }
+        let Some((last, tags)) = self.store_tags.split_last() else {
+            return Err(Error::InvAddSecretReceipt("The secret store is empty"));
+        };
+        if *last != self.request_tag {
+            return Err(Error::InvAddSecretReceipt(
+                "The request tag is not the last tag of the secret store",
+            ));
+        }
+        let mut state = SecretStoreState::new();
+        for tag in tags {
+            state.add(tag.0)?;
+        }
+        if state.hash()? != self.store_hash_before.0 {
+            return Err(Error::InvAddSecretReceipt(
+                "The secret-store-hash before adding the secret does not match",
+            ));
+        }
+        state.add(last.0)?;
+        if state.hash()? != self.store_hash_after.0 {
+            return Err(Error::InvAddSecretReceipt(
+                "The secret-store-hash after adding the secret does not match",
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Apply the receipt to the secret store `state`
+    ///
+    /// `previous` is the receipt (as written to disk) applied to `state` before, if any. Adds the
+    /// request tag to `state` if the receipt is consistent and was created for `state`. If the
+    /// receipt refers to a previous receipt, `previous` must be that receipt.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the receipt is inconsistent, was created for
+    /// another state, or does not refer to `previous`. `state` is not changed in that case.
+    pub fn apply(&self, state: &mut SecretStoreState, previous: Option<&[u8]>) -> Result<()> {
+        let after = self.verify()?;
+        if state.is_locked() {
+            return Err(Error::SecretStoreLocked);
+        }
+        if after.tags()[..after.tags().len() - 1] != *state.tags() {
+            return Err(Error::AddSecretReceiptMismatch(
+                "The secret store contains different secrets",
+            ));
+        }
+        if let (Some(digest), Some(previous)) = (&self.previous_receipt_sha256, previous) {
+            if *digest != sha256(previous)? {
+                return Err(Error::AddSecretReceiptMismatch(
+                    "The receipt refers to another previous receipt",
+                ));
+            }
+        }
+        *state = after;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{get_test_asset, uvattest::secret_store::secret_store_hash_requests};
+
+    const ASRCBS: [&[u8]; 3] = [
+        get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one"),
+        get_test_asset!("exp/asrcb/null_none_default_cuid_seven"),
+        get_test_asset!("exp/asrcb/assoc_derived_default_cuid_one"),
+    ];
+
+    /// Secret-store-hash of the unlocked store after adding [`ASRCBS`]
+    const HASH_CHAIN: [u8; 64] = [
+        0xa6, 0x61, 0xc0, 0x46, 0x6f, 0xbc, 0x98, 0x3a, 0x73, 0x8f, 0xe3, 0x6f, 0xb6, 0xce, 0x6d,
+        0x3e, 0xac, 0x08, 0xbc, 0xcd, 0x0b, 0x1f, 0xca, 0x77, 0xc5, 0x85, 0x9a, 0xd1, 0x2b, 0x7d,
+        0x0f, 0x9b, 0x7c, 0xc2, 0x00, 0xc7, 0xeb, 0x0b, 0x0b, 0x43, 0x57, 0x72, 0x8f, 0xbd, 0x79,
+        0x84, 0xd7, 0x15, 0xa5, 0x5d, 0x5f, 0x19, 0x02, 0x86, 0x9d, 0xf9, 0x0b, 0x50, 0x78, 0x62,
+        0x28, 0x0b, 0x98, 0x12,
+    ];
+
+    /// Receipts of adding [`ASRCBS`] one after another, serialized
+    fn chain() -> Vec<Vec<u8>> {
+        let mut state = SecretStoreState::new();
+        let mut receipts: Vec<Vec<u8>> = Vec::new();
+        for asrcb in ASRCBS {
+            let receipt = AddSecretReceipt::new(
+                &state,
+                asrcb,
+                UV_RC_SUCCESS,
+                receipts.last().map(|r| r.as_slice()),
+            )
+            .unwrap();
+            state = receipt.verify().unwrap();
+            receipts.push(serde_json::to_vec(&receipt).unwrap());
+        }
+        receipts
+    }
+
+    fn parse(receipt: &[u8]) -> AddSecretReceipt {
+        serde_json::from_slice(receipt).unwrap()
+    }
+
+    #[test]
+    fn chain_hash() {
+        let receipts = chain();
+        let mut state = SecretStoreState::new();
+        let mut previous: Option<&[u8]> = None;
+        for receipt in &receipts {
+            parse(receipt).apply(&mut state, previous).unwrap();
+            previous = Some(receipt);
+        }
+        assert_eq!(state.hash().unwrap(), HASH_CHAIN);
+        assert_eq!(
+            secret_store_hash_requests(&ASRCBS, false).unwrap(),
+            HASH_CHAIN
+        );
+        assert_eq!(parse(&receipts[2]).store_hash_after(), &HASH_CHAIN);
+        assert_eq!(
+            parse(&receipts[1]).request_tag().as_slice(),
+            AddSecretRequest::bin_tag(ASRCBS[1]).unwrap()
+        );
+
+        // the last receipt alone reproduces the state
+        assert_eq!(parse(&receipts[2]).verify().unwrap(), state);
+    }
+
+    #[test]
+    fn chain_broken() {
+        let receipts = chain();
+
+        // out of order
+        let mut state = SecretStoreState::new();
+        let err = parse(&receipts[1]).apply(&mut state, None).unwrap_err();
+        assert!(matches!(err, Error::AddSecretReceiptMismatch(_)));
+        assert_eq!(state, SecretStoreState::new());
+
+        // wrong previous receipt
+        parse(&receipts[0]).apply(&mut state, None).unwrap();
+        parse(&receipts[1])
+            .apply(&mut state, Some(&receipts[0]))
+            .unwrap();
+        let err = parse(&receipts[2])
+            .apply(&mut state.clone(), Some(&receipts[0]))
+            .unwrap_err();
+        assert!(matches!(err, Error::AddSecretReceiptMismatch(_)));
+
+        // locked secret store
+        state.lock();
+        let err = parse(&receipts[2]).apply(&mut state, None).unwrap_err();
+        assert!(matches!(err, Error::SecretStoreLocked));
+    }
+
+    #[test]
+    fn tampered() {
+        let mut receipt = parse(&chain()[2]);
+        receipt.store_tags.swap(0, 1);
+        assert!(matches!(
+            receipt.verify(),
+            Err(Error::InvAddSecretReceipt(_))
+        ));
+
+        let mut receipt = parse(&chain()[2]);
+        receipt.request_tag.0[0] ^= 1;
+        assert!(matches!(
+            receipt.verify(),
+            Err(Error::InvAddSecretReceipt(_))
+        ));
+
+        let mut receipt = parse(&chain()[0]);
+        receipt.uv_rc = 0x0102;
+        assert!(matches!(
+            receipt.verify(),
+            Err(Error::InvAddSecretReceipt(_))
+        ));
+
+        let mut receipt = parse(&chain()[0]);
+        receipt.version = 2;
+        assert!(matches!(
+            receipt.verify(),
+            Err(Error::InvAddSecretReceipt(_))
+        ));
+    }
+
+    #[test]
+    fn serde() {
+        let receipts = chain();
+        let first = String::from_utf8(receipts[0].clone()).unwrap();
+        let tag = encode_hex(AddSecretRequest::bin_tag(ASRCBS[0]).unwrap());
+        assert!(first.starts_with(&format!(
+            r#"{{"version":1,"request_tag":"0x{tag}","request_sha256":"0x"#
+        )));
+        assert!(!first.contains("previous_receipt_sha256"));
+        assert!(String::from_utf8_lossy(&receipts[1]).contains("previous_receipt_sha256"));
+        assert_eq!(
+            serde_json::to_vec(&parse(&receipts[1])).unwrap(),
+            receipts[1]
+        );
+
+        let unknown = first.replacen('{', r#"{"unknown":1,"#, 1);
+        assert!(serde_json::from_str::<AddSecretReceipt>(&unknown).is_err());
+        let no_prefix = first.replace("\"0x", "\"");
+        assert!(serde_json::from_str::<AddSecretReceipt>(&no_prefix).is_err());
+        let short = first.replace(&tag, &tag[2..]);
+        assert!(serde_json::from_str::<AddSecretReceipt>(&short).is_err());
+    }
+}
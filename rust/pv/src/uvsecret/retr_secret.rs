@@ -7,7 +7,7 @@ use crate::{pem::Pem, uvsecret::guest_secret::MAX_SIZE_PLAIN_PAYLOAD, Result};
 use byteorder::BigEndian;
 use log::warn;
 use pv_core::{
-    request::Confidential,
+    request::{hex_encode_confidential, Confidential, Summary},
     uv::{ListableSecretType, RetrievableSecret, RetrieveCmd},
 };
 use zerocopy::{FromBytes, U16};
@@ -110,6 +110,25 @@ pub enum RetrievedSecret {
     ProtectedKey(IbmProtectedKey),
 }
 
+impl Summary for IbmProtectedKey {
+    fn summary(&self) -> String {
+        format!(
+            "IBM protected key {} ({} bytes)",
+            self.kind,
+            self.data().len()
+        )
+    }
+}
+
+impl Summary for RetrievedSecret {
+    fn summary(&self) -> String {
+        match self {
+            Self::Plaintext(p) => format!("Plaintext secret ({} bytes)", p.value().len()),
+            Self::ProtectedKey(p) => p.summary(),
+        }
+    }
+}
+
 impl RetrievedSecret {
     /// Create a new IBM PROTECTED KEY object
     pub fn from_cmd(cmd: RetrieveCmd) -> Self {
@@ -142,6 +161,16 @@ impl RetrievedSecret {
             RetrievedSecret::ProtectedKey(p) => p.to_pem(),
         }
     }
+
+    /// Get the data as lowercase hex string with a trailing newline.
+    pub fn to_hex(&self) -> Confidential<Vec<u8>> {
+        let hex = hex_encode_confidential(self.data());
+        // preallocate, so that no unzeroized copies remain
+        let mut res = Confidential::new(Vec::with_capacity(hex.value().len() + 1));
+        res.value_mut().extend_from_slice(hex.value().as_bytes());
+        res.value_mut().push(b'\n');
+        res
+    }
 }
 
 #[cfg(test)]
@@ -231,4 +260,37 @@ mod test {
         let pem_str = pem.to_string();
         assert_eq!(pem_str, exp);
     }
+
+    #[test]
+    fn hex() {
+        let secret = vec![
+            0, 10, 1, 2, 3, 0xab, 0xcd, 0xef, 8, 9, 0xa, 0xff, 0, 0, 0, 0,
+        ];
+        let retr = mk_retr(&secret);
+        let hex = retr.to_hex();
+        assert_eq!(hex.value(), b"010203abcdef08090aff\n");
+        assert_eq!(hex.value().capacity(), hex.value().len());
+
+        let prot = RetrievedSecret::ProtectedKey(IbmProtectedKey::new(
+            ListableSecretType::Retrievable(RetrievableSecret::Aes(AesSizes::Bits128)),
+            vec![0x11; 4],
+        ));
+        assert_eq!(prot.to_hex().value(), b"11111111\n");
+    }
+
+    #[test]
+    fn summary_no_secret() {
+        let secret = vec![0, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0xa, 0, 0, 0, 0];
+        let retr = mk_retr(&secret);
+        assert_eq!(retr.summary(), "Plaintext secret (10 bytes)");
+        crate::test_utils::assert_no_secret(&retr.summary(), retr.data());
+
+        let key = IbmProtectedKey::new(
+            ListableSecretType::Retrievable(RetrievableSecret::Aes(AesSizes::Bits256)),
+            vec![0x5a; 64],
+        );
+        let summary = RetrievedSecret::ProtectedKey(key).summary();
+        assert_eq!(summary, "IBM protected key AES-256-KEY (64 bytes)");
+        crate::test_utils::assert_no_secret(&summary, &[0x5a; 64]);
+    }
 }
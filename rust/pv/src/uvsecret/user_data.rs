@@ -4,7 +4,7 @@
 
 use crate::assert_size;
 use crate::{
-    crypto::{sign_msg, verify_signature},
+    crypto::{sign_msg, verify_signature_digest, Digest, SignatureVerification},
     req::BinReqValues,
     request::{
         openssl::pkey::{HasParams, HasPublic, Id, PKey, PKeyRef, Private, Public},
@@ -13,7 +13,6 @@ use crate::{
     secret::{AddSecretMagic, AddSecretRequest, AddSecretVersion, UserDataType},
     Error, Result,
 };
-use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
 use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U16};
 
@@ -94,7 +93,7 @@ pub(super) struct SignedUserData {
 }
 
 impl UserData {
-    const USER_DATA_SIZE: usize = 0x200;
+    pub(super) const USER_DATA_SIZE: usize = 0x200;
 
     fn user_data_type<P: HasPublic>(sign_key: &PKeyRef<P>) -> Result<UserDataType> {
         fn check_curve<P: HasParams>(pkey: &PKeyRef<P>) -> Result<bool> {
@@ -147,7 +146,7 @@ impl UserData {
 
     /// Signs data in buf, writes signature to `buf+user_data_offset+sign_offset` if applicable.
     ///
-    /// Uses [`MessageDigest::sha512`] as digest. Does not modify the abritary user data buffer.
+    /// Uses [`Digest::Sha512`] as digest. Does not modify the abritary user data buffer.
     ///
     /// * buf: user data buffer, must be at least 512 bytes long
     ///
@@ -170,7 +169,7 @@ impl UserData {
         buf[sgn_offset..user_data_offset + USER_DATA_SIZE].fill(0);
 
         // calculate signature
-        let sgn = sign_msg(&signed_data.sign_key, MessageDigest::sha512(), buf)?;
+        let sgn = sign_msg(&signed_data.sign_key, Digest::Sha512, buf)?;
 
         // insert signature
         if let UserDataType::SgnEcSECP521R1 = self.data_type() {
@@ -304,9 +303,9 @@ pub fn verify_asrcb_and_get_user_data(
         (None, _) => return Err(Error::BinAsrcbNoUserDataSgnKey),
     };
 
-    match verify_signature(&key, MessageDigest::sha512(), &asrcb, user_data.signature())? {
-        false => Err(Error::AsrcbUserDataSgnFail),
-        true => Ok(Some(user_data.into())),
+    match verify_signature_digest(&key, Digest::Sha512, &asrcb, user_data.signature())? {
+        SignatureVerification::Mismatch { reason } => Err(Error::AsrcbUserDataSgnFail(reason)),
+        SignatureVerification::Verified => Ok(Some(user_data.into())),
     }
 }
 
@@ -421,14 +420,9 @@ mod test {
         user_data.sign(&mut buf, 0).unwrap();
 
         let vrf_user_data = VerifiedUserData::new(&mut buf, UserDataType::SgnRsa2048);
-        let res = verify_signature(
-            &rsa,
-            MessageDigest::sha512(),
-            &buf,
-            vrf_user_data.signature(),
-        )
-        .unwrap();
-        assert!(res);
+        let res =
+            verify_signature_digest(&rsa, Digest::Sha512, &buf, vrf_user_data.signature()).unwrap();
+        assert_eq!(res, SignatureVerification::Verified);
     }
 
     #[test]
@@ -446,14 +440,9 @@ mod test {
         user_data.sign(&mut buf, 0).unwrap();
 
         let vrf_user_data = VerifiedUserData::new(&mut buf, UserDataType::SgnRsa3072);
-        let res = verify_signature(
-            &rsa,
-            MessageDigest::sha512(),
-            &buf,
-            vrf_user_data.signature(),
-        )
-        .unwrap();
-        assert!(res);
+        let res =
+            verify_signature_digest(&rsa, Digest::Sha512, &buf, vrf_user_data.signature()).unwrap();
+        assert_eq!(res, SignatureVerification::Verified);
     }
 
     #[test]
@@ -495,14 +484,9 @@ mod test {
         assert_eq!(res_192, &[0u8; 110]);
 
         let vrf_user_data = VerifiedUserData::new(&mut buf, UserDataType::SgnEcSECP521R1);
-        let res = verify_signature(
-            &ec,
-            MessageDigest::sha512(),
-            &buf,
-            vrf_user_data.signature(),
-        )
-        .unwrap();
-        assert!(res);
+        let res =
+            verify_signature_digest(&ec, Digest::Sha512, &buf, vrf_user_data.signature()).unwrap();
+        assert_eq!(res, SignatureVerification::Verified);
     }
 
     #[test]
@@ -2,27 +2,36 @@
 //
 // Copyright IBM Corp. 2023
 
-use crate::openssl_extensions::{StackableX509Crl, X509StoreContextExtension, X509StoreExtension};
-use core::slice;
-use log::{debug, trace};
+use crate::openssl_extensions::{StackableX509Crl, X509StoreContextExtension};
+use log::{debug, info, trace, warn};
 use openssl::error::ErrorStack;
 use openssl::stack::Stack;
 use openssl::x509::store::X509Store;
-use openssl::x509::{CrlStatus, X509NameRef, X509Ref, X509StoreContext, X509StoreContextRef, X509};
-use std::path::Path;
+use openssl::x509::{
+    CrlStatus, X509Crl, X509NameRef, X509Ref, X509StoreContext, X509StoreContextRef, X509,
+};
+use std::path::{Path, PathBuf};
 
 #[cfg(not(test))]
 use helper::download_first_crl_from_x509;
+#[cfg(not(test))]
+use ocsp::{ocsp_nonce, query_ocsp_responder};
 #[cfg(test)]
-use test::download_first_crl_from_x509;
+use test::{download_first_crl_from_x509, ocsp_nonce, query_ocsp_responder};
 
+use crate::crypto::{signature_mismatch, SignatureVerification};
 use crate::error::bail_hkd_verify;
-use crate::misc::{read_certs, read_file};
-use crate::Result;
+use crate::{Error, HkdVerifyErrorType::*, Result};
 
 mod helper;
+mod ocsp;
+mod report;
 mod test;
 
+pub use report::{
+    CertificateKind, CertificateValidity, CrlValidity, Timestamp, VerificationReport,
+};
+
 /// A `HkdVerifier` verifies that a host-key document(HKD) can be trusted.
 ///
 /// If the verification fails the HKD should not be used to create requests.
@@ -34,6 +43,20 @@ pub trait HkdVerifier {
     /// This function will return an error if the host-key document cannot be
     /// trusted. Refer to the concrete Error type for the specific reason.
     fn verify(&self, hkd: &X509Ref) -> Result<()>;
+
+    /// Checks if the given host-key document can be trusted and reports the validity windows of
+    /// the artifacts used for that.
+    ///
+    /// By default, the report contains the host-key document only.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the host-key document cannot be
+    /// trusted. Refer to the concrete Error type for the specific reason.
+    fn verify_report(&self, hkd: &X509Ref) -> Result<VerificationReport> {
+        self.verify(hkd)?;
+        VerificationReport::new(hkd, [], &[])
+    }
 }
 
 /// A verifier that does not verify and accepts all given host-keys as valid.
@@ -45,11 +68,29 @@ impl HkdVerifier for NoVerifyHkd {
     }
 }
 
+/// Revocation checking of host-key documents with OCSP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcspMode {
+    /// The OCSP responder of the host-key document must report it as good.
+    Required,
+    /// Like [`OcspMode::Required`], but check the CRLs if the host-key document specifies no
+    /// OCSP responder or none of them could be reached.
+    FallbackCrl,
+}
+
 /// A verifier that checks the host-key document against a chain of trust.
+///
+/// Create it with [`CertVerifier::new`] or, for more control over the trusted root CAs, with
+/// [`CertVerifierBuilder`].
 pub struct CertVerifier {
     store: X509Store,
     ibm_z_sign_key: X509,
+    intermediate_cas: Vec<X509>,
+    root_ca: X509,
+    /// CRLs of the intermediate CAs and the root CA used to verify the chain of trust
+    chain_crls: Vec<X509Crl>,
     offline: bool,
+    ocsp: Option<OcspMode>,
 }
 
 impl std::fmt::Debug for CertVerifier {
@@ -65,42 +106,100 @@ impl HkdVerifier for CertVerifier {
     /// 1. `issuer(host_key`) == `subject(ibm_z_sign_key`)
     /// 2. Signature verification
     /// 3. @hkd must not be expired
-    /// 4. @hkd must not be revoked
+    /// 4. @hkd must not be revoked (OCSP if enabled, CRL otherwise)
     fn verify(&self, hkd: &X509Ref) -> Result<()> {
+        self.verify_hkd(hkd).map(|_| ())
+    }
+
+    /// Like [`CertVerifier::verify`], and reports the host-key document, the chain of trust, and
+    /// the CRLs used to check the revocation status of the host-key document and the chain.
+    fn verify_report(&self, hkd: &X509Ref) -> Result<VerificationReport> {
+        let hkd_crls = self.verify_hkd(hkd)?;
+        let chain = std::iter::once((CertificateKind::IbmSigningKey, self.ibm_z_sign_key.as_ref()))
+            .chain(
+                self.intermediate_cas
+                    .iter()
+                    .map(|crt| (CertificateKind::IntermediateCa, crt.as_ref())),
+            )
+            .chain([(CertificateKind::RootCa, self.root_ca.as_ref())]);
+        let crls: Vec<_> = hkd_crls
+            .iter()
+            .chain(&self.chain_crls)
+            .map(|crl| crl.as_ref())
+            .collect();
+        VerificationReport::new(hkd, chain, &crls)
+    }
+}
+
+impl CertVerifier {
+    /// Verifies the host-key document, see [`CertVerifier::verify`].
+    ///
+    /// Returns the CRLs used to check the revocation status, none if OCSP was used.
+    fn verify_hkd(&self, hkd: &X509Ref) -> Result<Vec<X509Crl>> {
         helper::verify_hkd_options(hkd, &self.ibm_z_sign_key)?;
 
         // verify that the HKD was signed with the key of the IBM signing key
-        if !hkd.verify(self.ibm_z_sign_key.public_key()?.as_ref())? {
-            bail_hkd_verify!(Signature);
+        // a malformed signature is no operational error, but a failed verification
+        let verified = match hkd.verify(self.ibm_z_sign_key.public_key()?.as_ref()) {
+            Ok(verified) => verified.into(),
+            Err(e) => SignatureVerification::Mismatch {
+                reason: signature_mismatch(e)?,
+            },
+        };
+        if let SignatureVerification::Mismatch { reason } = verified {
+            return Err(Error::HkdVerify(Signature(reason)));
+        }
+
+        if let Some(mode) = self.ocsp {
+            match self.ocsp_status(hkd) {
+                Ok(()) => {
+                    debug!("HKD: verified (OCSP)");
+                    return Ok(vec![]);
+                }
+                Err(e @ Error::HkdVerify(OcspNoResponder | OcspUnreachable))
+                    if mode == OcspMode::FallbackCrl =>
+                {
+                    warn!("{e}. Checking the CRLs instead.")
+                }
+                Err(e) => return Err(e),
+            }
         }
 
         // Find matching CRL for sign key in the store or download them
         let crls = self.hkd_crls(hkd)?;
+        let issuer = || helper::x509_name_to_string(hkd.issuer_name());
+        if crls.is_empty() {
+            return Err(Error::HkdVerify(MissingCrl(issuer())));
+        }
 
         // Verify that the CRLs are still valid
         let mut verified_crls = Vec::with_capacity(crls.len());
-        for crl in &crls {
-            if helper::verify_crl(crl, &self.ibm_z_sign_key).is_some() {
-                verified_crls.push(crl.to_owned());
+        let mut expired = false;
+        for crl in crls {
+            if helper::verify_crl(&crl, &self.ibm_z_sign_key).is_some() {
+                verified_crls.push(X509Crl::from(crl));
+            } else {
+                expired |= helper::crl_expired(&crl);
             }
         }
 
         // Test if HKD was revoked (min1 required)
         if verified_crls.is_empty() {
+            if expired {
+                return Err(Error::HkdVerify(CrlExpired(issuer())));
+            }
             bail_hkd_verify!(NoCrl);
         }
-        for crl in verified_crls {
+        for crl in &verified_crls {
             match crl.get_by_serial(hkd.serial_number()) {
                 CrlStatus::NotRevoked => (),
                 _ => bail_hkd_verify!(HkdRevoked),
             }
         }
         debug!("HKD: verified");
-        Ok(())
+        Ok(verified_crls)
     }
-}
 
-impl CertVerifier {
     fn quirk_crls(
         ctx: &mut X509StoreContextRef,
         subject: &X509NameRef,
@@ -140,6 +239,23 @@ impl CertVerifier {
         Stack::new()
     }
 
+    /// Query the OCSP responders of the HKD until one answers and check its response.
+    fn ocsp_status(&self, hkd: &X509Ref) -> Result<()> {
+        let responders = ocsp::ocsp_responders(hkd);
+        if responders.is_empty() {
+            bail_hkd_verify!(OcspNoResponder);
+        }
+        let req = ocsp::ocsp_request(hkd, &self.ibm_z_sign_key, &ocsp_nonce()?)?;
+        let req_der = req.to_der()?;
+        for uri in responders {
+            if let Some(resp) = query_ocsp_responder(&uri, &req_der)? {
+                return ocsp::check_ocsp_response(&req, &resp, hkd, &self.ibm_z_sign_key);
+            }
+            debug!("OCSP responder '{uri}' not reachable");
+        }
+        bail_hkd_verify!(OcspUnreachable)
+    }
+
     /// Download the CRLs that a HKD refers to.
     pub fn hkd_crls(&self, hkd: &X509Ref) -> Result<Stack<StackableX509Crl>> {
         let mut ctx = X509StoreContext::new()?;
@@ -166,10 +282,13 @@ impl CertVerifier {
     ///
     /// * `cert_paths` - Paths to certificates for the chain of trust
     /// * `crl_paths` - Paths to certificate revocation lists for the chain of trust
-    /// * `root_ca_path` - Path to the root of trust
+    /// * `root_ca_path` - Path to the root of trust. If `None`, the system root CAs are trusted.
     /// * `offline` - if set to true the verification process will not try to download CRLs from the
     ///               internet.
     ///
+    /// Each file may contain any number of PEM encoded certificates and CRLs, or one DER encoded
+    /// certificate or CRL. Duplicate certificates are ignored.
+    ///
     /// # Errors
     ///
     /// This function will return an error if the chain of trust could not be established.
@@ -184,31 +303,155 @@ impl CertVerifier {
         Q: AsRef<Path>,
         R: AsRef<Path>,
     {
-        let mut store = helper::store_setup(root_ca_path, crl_paths, cert_paths)?;
-        let mut untr_certs = Vec::with_capacity(cert_paths.len());
+        let mut builder = CertVerifierBuilder::new().offline(offline);
         for path in cert_paths {
-            let mut crt = read_certs(&read_file(path, "certificate")?)?;
-            if !offline {
-                for c in &crt {
-                    if let Some(crl) = download_first_crl_from_x509(c)? {
-                        crl.iter().try_for_each(|c| store.add_crl(c))?;
-                    }
+            builder = builder.add_cert(path);
+        }
+        for path in crl_paths {
+            builder = builder.add_crl(path);
+        }
+        if let Some(path) = root_ca_path {
+            builder = builder.add_root_ca(path).default_roots(false);
+        }
+        builder.build()
+    }
+
+    /// The root CA that anchors the chain of trust of the IBM Z signing key
+    pub fn root_ca(&self) -> &X509Ref {
+        &self.root_ca
+    }
+
+    /// Check the revocation status of host-key documents with OCSP.
+    ///
+    /// The OCSP responders are taken from the authority information access extension of the
+    /// host-key document. Has no effect in offline mode, the CRLs are checked instead.
+    pub fn with_ocsp(mut self, mode: OcspMode) -> Self {
+        self.ocsp = (!self.offline).then_some(mode);
+        self
+    }
+}
+
+/// Builder for a [`CertVerifier`]
+#[derive(Debug, Clone)]
+pub struct CertVerifierBuilder {
+    certs: Vec<PathBuf>,
+    crls: Vec<PathBuf>,
+    root_cas: Vec<PathBuf>,
+    default_roots: bool,
+    offline: bool,
+}
+
+impl Default for CertVerifierBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CertVerifierBuilder {
+    /// Create a new builder
+    ///
+    /// By default, the root CAs installed on the system are trusted and CRLs are downloaded.
+    pub fn new() -> Self {
+        Self {
+            certs: vec![],
+            crls: vec![],
+            root_cas: vec![],
+            default_roots: true,
+            offline: false,
+        }
+    }
+
+    /// Use the certificates of the file for the chain of trust.
+    ///
+    /// The file may contain any number of PEM encoded certificates and CRLs, or one DER encoded
+    /// certificate. Can be called multiple times.
+    pub fn add_cert<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.certs.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// Use the certificate revocation lists of the file for the chain of trust.
+    ///
+    /// The file may contain any number of PEM encoded CRLs and certificates, or one DER encoded
+    /// CRL. Can be called multiple times.
+    pub fn add_crl<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.crls.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// Trust the root CA(s) of the file.
+    ///
+    /// Can be called multiple times. The chain of trust may be anchored by any of them.
+    pub fn add_root_ca<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.root_cas.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// Trust the root CAs installed on the system in addition to the added ones (default: true).
+    pub fn default_roots(mut self, default_roots: bool) -> Self {
+        self.default_roots = default_roots;
+        self
+    }
+
+    /// Do not try to download CRLs from the internet (default: false).
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Build the [`CertVerifier`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the chain of trust could not be established.
+    pub fn build(self) -> Result<CertVerifier> {
+        let mut untr_certs = Vec::with_capacity(self.certs.len());
+        let mut crls = Vec::with_capacity(self.crls.len());
+        // Files may contain any combination of certificates and CRLs
+        for path in self.crls.iter().chain(&self.certs) {
+            helper::read_x509_file(path, &mut untr_certs, &mut crls)?;
+        }
+        if !self.offline {
+            for c in &untr_certs {
+                if let Some(mut crl) = download_first_crl_from_x509(c)? {
+                    crls.append(&mut crl);
                 }
             }
-            untr_certs.append(&mut crt);
         }
+        let store = helper::store_setup(&self.root_cas, self.default_roots, &crls)?;
 
         // remove the IBM signing certificate from chain.
         // We have to verify them separately as they are not marked as intermediate certs
         let (ibm_z_sign_key, chain) = helper::extract_ibm_sign_key(untr_certs)?;
 
         let store = store.build();
-        helper::verify_chain(&store, &chain, slice::from_ref(&ibm_z_sign_key))?;
+        let (intermediate_cas, root_ca) = helper::verify_chain(&store, &chain, &ibm_z_sign_key)?;
+        info!(
+            "The chain of trust is anchored by the root CA '{}'",
+            helper::x509_name_to_string(root_ca.subject_name())
+        );
+
+        // keep the CRLs that were issued by a CA of the chain
+        let chain_crls = crls
+            .into_iter()
+            .filter(|crl| {
+                intermediate_cas.iter().chain([&root_ca]).any(|ca| {
+                    crl.issuer_name()
+                        .try_cmp(ca.subject_name())
+                        .is_ok_and(|o| o.is_eq())
+                        && helper::verify_crl(crl, ca).is_some()
+                })
+            })
+            .collect();
 
-        Ok(Self {
+        Ok(CertVerifier {
             store,
             ibm_z_sign_key,
-            offline,
+            intermediate_cas,
+            root_ca,
+            chain_crls,
+            offline: self.offline,
+            ocsp: None,
         })
     }
 }
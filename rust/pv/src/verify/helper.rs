@@ -3,10 +3,12 @@
 // Copyright IBM Corp. 2023
 
 use crate::error::bail_hkd_verify;
-use crate::openssl_extensions::{AkidCheckResult, AkidExtension};
+use crate::misc::read_file;
+use crate::openssl_extensions::{AkidCheckResult, AkidExtension, X509StoreExtension};
+use crate::utils::read_x509_bundle;
 use crate::HkdVerifyErrorType::*;
-use crate::{Error, Result};
-use log::debug;
+use crate::{Error, HkdVerifyErrorType, Result};
+use log::{debug, info};
 use openssl::{
     asn1::{Asn1Time, Asn1TimeRef},
     error::ErrorStack,
@@ -16,7 +18,7 @@ use openssl::{
     x509::{
         store::{File, X509Lookup, X509StoreBuilder, X509StoreRef},
         verify::{X509VerifyFlags, X509VerifyParam},
-        X509CrlRef, X509Name, X509NameRef, X509PurposeId, X509Ref, X509StoreContext,
+        X509Crl, X509CrlRef, X509Name, X509NameRef, X509PurposeId, X509Ref, X509StoreContext,
         X509StoreContextRef, X509VerifyResult, X509,
     },
 };
@@ -73,33 +75,49 @@ pub fn verify_crl(crl: &X509CrlRef, issuer: &X509Ref) -> Option<()> {
     }
 }
 
+/// Returns true if the next update of the CRL is in the past
+pub fn crl_expired(crl: &X509CrlRef) -> bool {
+    matches!(
+        crl.next_update()
+            .map(|next| check_validity_period(crl.last_update(), next)),
+        Some(Err(Error::HkdVerify(AfterValidity)))
+    )
+}
+
+/// Returns the name in the one-line form `C=US, O=..., CN=...`
+pub fn x509_name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            match entry.data().as_utf8() {
+                Ok(value) => format!("{key}={value}"),
+                Err(_) => format!("{key}=?"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Setup the x509Store such that it can be used it for verifying certificates
-pub fn store_setup<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
-    root_ca_path: Option<P>,
-    crl_paths: &[Q],
-    cert_w_crl_paths: &[R],
+///
+/// The store trusts all `root_ca_paths` and, if `default_roots` is set, the root CAs installed on
+/// the system.
+pub fn store_setup<P: AsRef<Path>>(
+    root_ca_paths: &[P],
+    default_roots: bool,
+    crls: &[X509Crl],
 ) -> Result<X509StoreBuilder> {
     let mut x509store = X509StoreBuilder::new()?;
 
-    match root_ca_path {
-        None => x509store.set_default_paths()?,
-        Some(p) => load_root_ca(p, &mut x509store)?,
+    if default_roots {
+        x509store.set_default_paths()?;
     }
-
-    for crl in crl_paths {
-        load_crl_to_store(&mut x509store, crl, true).map_err(|source| Error::X509Load {
-            path: crl.as_ref().into(),
-            ty: Error::CRL,
-            source,
-        })?;
+    for path in root_ca_paths {
+        load_root_ca(path, &mut x509store)?;
     }
 
-    for crl in cert_w_crl_paths {
-        load_crl_to_store(&mut x509store, crl, false).map_err(|source| Error::X509Load {
-            path: crl.as_ref().into(),
-            ty: Error::CRL,
-            source,
-        })?;
+    for crl in crls {
+        x509store.add_crl(crl)?;
     }
     let mut param = X509VerifyParam::new()?;
     let flags = X509VerifyFlags::X509_STRICT
@@ -120,11 +138,14 @@ pub fn store_setup<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
 
 /// Verify that the given IBM signing keys can be trusted
 /// -> check the chain: `IBMsignKey`<-InterCA(s)<-`RootCA`
+///
+/// Returns the intermediate CAs, closest to the signing key first, and the root CA that anchors
+/// the chain.
 pub fn verify_chain(
     store: &X509StoreRef,
     untrusted_certs: &Stack<X509>,
-    sign_keys: &[X509],
-) -> Result<()> {
+    sign_key: &X509Ref,
+) -> Result<(Vec<X509>, X509)> {
     fn verify_fun(ctx: &mut X509StoreContextRef) -> std::result::Result<bool, ErrorStack> {
         // verify certificate
         let res = ctx.verify_cert()?;
@@ -150,22 +171,55 @@ pub fn verify_chain(
         }
     }
 
-    let mut store_ctx = X509StoreContext::new()?;
-
-    for sign_key in sign_keys {
-        // (rust)OpenSSL should not error out on `X509_verify_cert`\
-        // (Internal (probably unrecoverable) error like OOM)
-        if !store_ctx
-            .init(store, sign_key, untrusted_certs, verify_fun)
-            .map_err(|e| Error::InternalSsl("The IBM Z signing key could not be verified.", e))?
-        {
-            return Err(Error::HkdVerify(IbmSignInvalid(
-                store_ctx.error(),
-                store_ctx.error_depth(),
-            )));
+    /// Missing or expired CRL of the certificate that failed the verification
+    fn crl_error(ctx: &X509StoreContextRef) -> Option<HkdVerifyErrorType> {
+        let issuer = || {
+            ctx.current_cert()
+                .map(|crt| x509_name_to_string(crt.issuer_name()))
+                .unwrap_or_default()
+        };
+        match ctx.error().as_raw() {
+            openssl_sys::X509_V_ERR_UNABLE_TO_GET_CRL => Some(MissingCrl(issuer())),
+            openssl_sys::X509_V_ERR_CRL_HAS_EXPIRED => Some(CrlExpired(issuer())),
+            _ => None,
         }
     }
-    Ok(())
+
+    let mut store_ctx = X509StoreContext::new()?;
+    let mut crl_err = None;
+    let mut chain = None;
+    // (rust)OpenSSL should not error out on `X509_verify_cert`\
+    // (Internal (probably unrecoverable) error like OOM)
+    if !store_ctx
+        .init(store, sign_key, untrusted_certs, |ctx| {
+            let res = verify_fun(ctx);
+            match res {
+                Ok(true) => {
+                    chain = ctx
+                        .chain()
+                        .map(|c| c.iter().map(|c| c.to_owned()).collect::<Vec<_>>())
+                }
+                Ok(false) => crl_err = crl_error(ctx),
+                Err(_) => (),
+            }
+            res
+        })
+        .map_err(|e| Error::InternalSsl("The IBM Z signing key could not be verified.", e))?
+    {
+        return Err(Error::HkdVerify(crl_err.unwrap_or(IbmSignInvalid(
+            store_ctx.error(),
+            store_ctx.error_depth(),
+        ))));
+    }
+    // verify_fun ensures that there is a chain of the signing key, the intermediate CAs, and the
+    // root CA
+    match chain.as_deref() {
+        Some([_, intermediates @ .., root]) => Ok((intermediates.to_vec(), root.to_owned())),
+        _ => Err(Error::HkdVerify(IbmSignInvalid(
+            X509VerifyResult::APPLICATION_VERIFICATION,
+            0,
+        ))),
+    }
 }
 
 /// Consumes and splits the given vector into a single IBM Z signing key and other certificates
@@ -256,20 +310,45 @@ fn load_root_ca<P: AsRef<Path>>(path: P, x509_store: &mut X509StoreBuilder) -> R
     }
 }
 
-fn load_crl_to_store<P: AsRef<Path>>(
-    x509_store: &mut X509StoreBuilder,
+/// Read all certificates and CRLs of a file
+///
+/// Certificates already in `certs` are skipped.
+pub fn read_x509_file<P: AsRef<Path>>(
     path: P,
-    err_out_empty_crl: bool,
-) -> std::result::Result<(), ErrorStack> {
-    let lu = x509_store.add_lookup(X509Lookup::<File>::file())?;
-    // Try to load cert as PEM file
-    if lu.load_crl_file(&path, SslFiletype::PEM).is_err() {
-        // Not a PEM file? try read as ASN1
-        let res = lu.load_crl_file(path, SslFiletype::ASN1);
-        if err_out_empty_crl {
-            res?;
+    certs: &mut Vec<X509>,
+    crls: &mut Vec<X509Crl>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let (file_certs, file_crls) =
+        read_x509_bundle(read_file(path, "certificate")?).map_err(|e| match e {
+            Error::Crypto(source) => Error::X509Load {
+                path: path.into(),
+                ty: Error::CERT,
+                source,
+            },
+            e => e,
+        })?;
+    if file_certs.is_empty() && file_crls.is_empty() {
+        return Err(Error::NoX509InFile(path.into()));
+    }
+
+    for crt in file_certs {
+        let subject = x509_name_to_string(crt.subject_name());
+        if certs.contains(&crt) {
+            info!(
+                "Skip duplicate certificate '{subject}' in '{}'",
+                path.display()
+            );
+        } else {
+            info!("Use certificate '{subject}' of '{}'", path.display());
+            certs.push(crt);
         }
     }
+    for crl in file_crls {
+        let issuer = x509_name_to_string(crl.issuer_name());
+        info!("Use CRL of '{issuer}' of '{}'", path.display());
+        crls.push(crl);
+    }
     Ok(())
 }
 
@@ -305,7 +384,7 @@ pub fn x509_dist_points(cert: &X509Ref) -> Vec<String> {
 /// Error if something bad(=unexpected) happens
 /// CRL not available at all URIs and unexpected format at all URIs are mapped to Ok(None)
 #[cfg(not(test))]
-pub fn download_first_crl_from_x509(cert: &X509Ref) -> Result<Option<Vec<openssl::x509::X509Crl>>> {
+pub fn download_first_crl_from_x509(cert: &X509Ref) -> Result<Option<Vec<X509Crl>>> {
     use crate::utils::read_crls;
     use curl::easy::{Easy2, Handler, WriteError};
     use std::time::Duration;
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use crate::error::bail_hkd_verify;
+use crate::openssl_extensions::{OcspNonceCheck, OcspRequestExtension};
+use crate::HkdVerifyErrorType::*;
+use crate::{Error, Result};
+use openssl::{
+    hash::MessageDigest,
+    ocsp::{
+        OcspCertId, OcspCertStatus, OcspFlag, OcspRequest, OcspRequestRef, OcspResponse,
+        OcspResponseStatus,
+    },
+    stack::Stack,
+    x509::{store::X509StoreBuilder, verify::X509VerifyFlags, X509Ref},
+};
+
+/// Size of the nonce of an OCSP request
+pub const OCSP_NONCE_SIZE: usize = 16;
+
+/// Allowed clock skew in seconds for the validity period of an OCSP response
+const OCSP_VALIDITY_SLACK: u32 = 300;
+
+fn invalid(reason: &'static str) -> Error {
+    Error::HkdVerify(OcspInvalidResponse(reason))
+}
+
+fn cert_id(cert: &X509Ref, issuer: &X509Ref) -> Result<OcspCertId> {
+    // SHA-1 is the hash every OCSP responder supports for certificate IDs
+    Ok(OcspCertId::from_cert(MessageDigest::sha1(), cert, issuer)?)
+}
+
+/// Returns the OCSP responder URIs of the authority information access extension
+pub fn ocsp_responders(cert: &X509Ref) -> Vec<String> {
+    match cert.ocsp_responders() {
+        Ok(uris) => uris.iter().map(|uri| uri.to_string()).collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Creates an OCSP request for the status of `cert` issued by `issuer`
+pub fn ocsp_request(cert: &X509Ref, issuer: &X509Ref, nonce: &[u8]) -> Result<OcspRequest> {
+    let mut req = OcspRequest::new()?;
+    req.add_id(cert_id(cert, issuer)?)?;
+    req.add_nonce(nonce)?;
+    Ok(req)
+}
+
+/// Checks the DER encoded OCSP response for the status of `cert`
+///
+/// The response must be signed by `issuer` or a responder delegated by `issuer`, must repeat the
+/// nonce of `req`, and must be inside its validity period.
+///
+/// # Errors
+///
+/// Returns [`OcspRevoked`] or [`OcspUnknown`] if the responder does not report the certificate as
+/// good and [`OcspInvalidResponse`] if the response cannot be trusted.
+pub fn check_ocsp_response(
+    req: &OcspRequestRef,
+    resp: &[u8],
+    cert: &X509Ref,
+    issuer: &X509Ref,
+) -> Result<()> {
+    let resp = OcspResponse::from_der(resp).map_err(|_| invalid("malformed response"))?;
+    if resp.status() != OcspResponseStatus::SUCCESSFUL {
+        return Err(invalid("the responder reported an error"));
+    }
+    let basic = resp.basic().map_err(|_| invalid("no basic response"))?;
+
+    // the issuer is the only trust anchor for the responder
+    let mut store = X509StoreBuilder::new()?;
+    store.add_cert(issuer.to_owned())?;
+    store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+    let store = store.build();
+    let mut certs = Stack::new()?;
+    certs.push(issuer.to_owned())?;
+    basic
+        .verify(&certs, &store, OcspFlag::empty())
+        .map_err(|_| invalid("signature verification failed"))?;
+
+    if req.check_nonce(&basic) != OcspNonceCheck::Equal {
+        return Err(invalid("nonce mismatch"));
+    }
+
+    let id = cert_id(cert, issuer)?;
+    let status = basic
+        .find_status(&id)
+        .ok_or(invalid("no status for the host-key document"))?;
+    status
+        .check_validity(OCSP_VALIDITY_SLACK, None)
+        .map_err(|_| invalid("outside the validity period"))?;
+
+    match status.status {
+        OcspCertStatus::GOOD => Ok(()),
+        OcspCertStatus::REVOKED => bail_hkd_verify!(OcspRevoked),
+        _ => bail_hkd_verify!(OcspUnknown),
+    }
+}
+
+/// Creates a random nonce for an OCSP request
+#[cfg(not(test))]
+pub fn ocsp_nonce() -> Result<[u8; OCSP_NONCE_SIZE]> {
+    crate::crypto::random_array()
+}
+
+/// Sends the DER encoded OCSP request to the responder at `uri`
+///
+/// Error if something bad(=unexpected) happens
+/// An unreachable responder or an HTTP error are mapped to Ok(None)
+#[cfg(not(test))]
+pub fn query_ocsp_responder(uri: &str, req: &[u8]) -> Result<Option<Vec<u8>>> {
+    use curl::easy::{Easy2, Handler, List, WriteError};
+    use std::time::Duration;
+    const OCSP_TIMEOUT_MAX: Duration = Duration::from_secs(3);
+    struct Buf(Vec<u8>);
+
+    impl Handler for Buf {
+        fn write(&mut self, data: &[u8]) -> std::result::Result<usize, WriteError> {
+            self.0.extend_from_slice(data);
+            Ok(data.len())
+        }
+    }
+
+    let mut headers = List::new();
+    headers.append("Content-Type: application/ocsp-request")?;
+
+    // A typical response is about 2000 bytes long
+    let mut handle = Easy2::new(Buf(Vec::with_capacity(2500)));
+    handle.url(uri)?;
+    handle.post(true)?;
+    handle.post_fields_copy(req)?;
+    handle.http_headers(headers)?;
+    handle.follow_location(true)?;
+    handle.timeout(OCSP_TIMEOUT_MAX)?;
+    handle.useragent("s390-tools-pv-ocsp")?;
+
+    if handle.perform().is_err() || handle.response_code()? != 200 {
+        return Ok(None);
+    }
+    Ok(Some(handle.get_ref().0.clone()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::load_gen_cert;
+    use crate::verify::test::OCSP_TEST_NONCE;
+
+    fn check(resp: &str) -> Result<()> {
+        let hkd = load_gen_cert("host_ocsp.crt");
+        let ibm = load_gen_cert("ibm.crt");
+        let req = ocsp_request(&hkd, &ibm, &OCSP_TEST_NONCE).unwrap();
+        let resp = std::fs::read(crate::test_utils::get_cert_asset_path(resp)).unwrap();
+        check_ocsp_response(&req, &resp, &hkd, &ibm)
+    }
+
+    #[test]
+    fn responders() {
+        let hkd = load_gen_cert("host_ocsp.crt");
+        assert_eq!(ocsp_responders(&hkd), ["ocsp_good.der"]);
+        assert!(ocsp_responders(&load_gen_cert("host.crt")).is_empty());
+    }
+
+    #[test]
+    fn response_status() {
+        check("ocsp_good.der").unwrap();
+        assert!(matches!(
+            check("ocsp_revoked.der"),
+            Err(Error::HkdVerify(OcspRevoked))
+        ));
+        assert!(matches!(
+            check("ocsp_unknown.der"),
+            Err(Error::HkdVerify(OcspUnknown))
+        ));
+    }
+
+    #[test]
+    fn response_invalid() {
+        for (resp, reason) in [
+            ("ocsp_nonce_mismatch.der", "nonce mismatch"),
+            ("ocsp_no_nonce.der", "nonce mismatch"),
+            ("ocsp_fake_signer.der", "signature verification failed"),
+            ("ocsp_expired.der", "outside the validity period"),
+            ("ocsp_other_cert.der", "no status for the host-key document"),
+            ("host_ocsp.crt", "malformed response"),
+        ] {
+            assert!(
+                matches!(check(resp), Err(Error::HkdVerify(OcspInvalidResponse(r))) if r == reason),
+                "{resp}: {:?}",
+                check(resp)
+            );
+        }
+
+        let hkd = load_gen_cert("host_ocsp.crt");
+        let ibm = load_gen_cert("ibm.crt");
+        let req = ocsp_request(&hkd, &ibm, &OCSP_TEST_NONCE).unwrap();
+        let resp = OcspResponse::create(OcspResponseStatus::TRY_LATER, None)
+            .unwrap()
+            .to_der()
+            .unwrap();
+        assert!(matches!(
+            check_ocsp_response(&req, &resp, &hkd, &ibm),
+            Err(Error::HkdVerify(OcspInvalidResponse(
+                "the responder reported an error"
+            )))
+        ));
+    }
+}
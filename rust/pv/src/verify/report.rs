@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use openssl::{
+    asn1::{Asn1Time, Asn1TimeRef},
+    x509::{X509CrlRef, X509Ref},
+};
+use pv_core::misc::encode_hex;
+use serde::{Serialize, Serializer};
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::helper::x509_name_to_string;
+use crate::Result;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// A point in time of a validity window, with a precision of seconds
+///
+/// Displays and serializes in the RFC 3339 format, e.g., `2024-03-21T14:52:39Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    fn from_asn1(time: &Asn1TimeRef) -> Result<Self> {
+        let diff = Asn1Time::from_unix(0)?.diff(time)?;
+        Ok(Self(
+            i64::from(diff.days) * SECS_PER_DAY + i64::from(diff.secs),
+        ))
+    }
+
+    fn now() -> Self {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => Self(d.as_secs() as i64),
+            Err(e) => Self(-(e.duration().as_secs() as i64)),
+        }
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn unix_seconds(&self) -> i64 {
+        self.0
+    }
+}
+
+/// Converts days since the Unix epoch into a (year, month, day) date of the proleptic
+/// Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_from_days(self.0.div_euclid(SECS_PER_DAY));
+        let secs = self.0.rem_euclid(SECS_PER_DAY);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The role of a certificate in the chain of trust of a host-key document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificateKind {
+    /// The host-key document itself
+    HostKeyDocument,
+    /// The IBM Z signing key that signed the host-key document
+    IbmSigningKey,
+    /// An intermediate CA between the IBM Z signing key and the root CA
+    IntermediateCa,
+    /// The root CA that anchors the chain of trust
+    RootCa,
+}
+
+impl Display for CertificateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::HostKeyDocument => "host-key document",
+            Self::IbmSigningKey => "IBM Z signing key",
+            Self::IntermediateCa => "intermediate CA",
+            Self::RootCa => "root CA",
+        })
+    }
+}
+
+/// Validity window of a certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CertificateValidity {
+    kind: CertificateKind,
+    subject: String,
+    serial: String,
+    not_before: Timestamp,
+    not_after: Timestamp,
+}
+
+impl CertificateValidity {
+    pub(super) fn new(kind: CertificateKind, crt: &X509Ref) -> Result<Self> {
+        Ok(Self {
+            kind,
+            subject: x509_name_to_string(crt.subject_name()),
+            serial: format!("0x{}", encode_hex(crt.serial_number().to_bn()?.to_vec())),
+            not_before: Timestamp::from_asn1(crt.not_before())?,
+            not_after: Timestamp::from_asn1(crt.not_after())?,
+        })
+    }
+
+    /// The role of the certificate in the chain of trust
+    pub fn kind(&self) -> CertificateKind {
+        self.kind
+    }
+
+    /// The subject name in the one-line form `C=US, O=..., CN=...`
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The serial number as `0x`-prefixed hex string
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// Start of the validity window (notBefore)
+    pub fn not_before(&self) -> Timestamp {
+        self.not_before
+    }
+
+    /// End of the validity window (notAfter)
+    pub fn not_after(&self) -> Timestamp {
+        self.not_after
+    }
+}
+
+/// Validity window of a certificate revocation list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrlValidity {
+    issuer: String,
+    this_update: Timestamp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_update: Option<Timestamp>,
+}
+
+impl CrlValidity {
+    pub(super) fn new(crl: &X509CrlRef) -> Result<Self> {
+        Ok(Self {
+            issuer: x509_name_to_string(crl.issuer_name()),
+            this_update: Timestamp::from_asn1(crl.last_update())?,
+            next_update: crl.next_update().map(Timestamp::from_asn1).transpose()?,
+        })
+    }
+
+    /// The issuer name in the one-line form `C=US, O=..., CN=...`
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Issue date of the CRL (thisUpdate)
+    pub fn this_update(&self) -> Timestamp {
+        self.this_update
+    }
+
+    /// Date by which the next CRL will be issued (nextUpdate), if specified
+    pub fn next_update(&self) -> Option<Timestamp> {
+        self.next_update
+    }
+}
+
+fn serialize_secs<S: Serializer>(
+    d: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(d.as_secs())
+}
+
+/// Validity windows of a host-key document and the artifacts used to verify it
+///
+/// Created by [`super::HkdVerifier::verify_report`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerificationReport {
+    certificates: Vec<CertificateValidity>,
+    crls: Vec<CrlValidity>,
+    /// Serialized in seconds
+    #[serde(serialize_with = "serialize_secs")]
+    expires_in: Duration,
+    expiring: String,
+}
+
+impl VerificationReport {
+    /// Creates a report of the host-key document `hkd`, the certificates of its chain of trust,
+    /// and the CRLs used for the verification.
+    pub(super) fn new<'a, I>(hkd: &'a X509Ref, chain: I, crls: &[&X509CrlRef]) -> Result<Self>
+    where
+        I: IntoIterator<Item = (CertificateKind, &'a X509Ref)>,
+    {
+        let certificates = std::iter::once((CertificateKind::HostKeyDocument, hkd))
+            .chain(chain)
+            .map(|(kind, crt)| CertificateValidity::new(kind, crt))
+            .collect::<Result<Vec<_>>>()?;
+        let crls = crls
+            .iter()
+            .map(|crl| CrlValidity::new(crl))
+            .collect::<Result<Vec<_>>>()?;
+
+        // the host-key document is always part of the report
+        let (expiry, expiring) = certificates
+            .iter()
+            .map(|c| (c.not_after, format!("{} '{}'", c.kind, c.subject)))
+            .chain(crls.iter().filter_map(|c| {
+                c.next_update
+                    .map(|next| (next, format!("CRL of '{}'", c.issuer)))
+            }))
+            .min_by_key(|(expiry, _)| *expiry)
+            .unwrap_or_else(|| (Timestamp::now(), String::new()));
+        let expires_in = Duration::from_secs(
+            expiry
+                .0
+                .saturating_sub(Timestamp::now().0)
+                .try_into()
+                .unwrap_or(0),
+        );
+
+        Ok(Self {
+            certificates,
+            crls,
+            expires_in,
+            expiring,
+        })
+    }
+
+    /// The host-key document and the certificates of its chain of trust, host-key document
+    /// first and root CA last
+    pub fn certificates(&self) -> &[CertificateValidity] {
+        &self.certificates
+    }
+
+    /// The CRLs used to check the revocation status of the certificates
+    pub fn crls(&self) -> &[CrlValidity] {
+        &self.crls
+    }
+
+    /// Time until the earliest-expiring certificate or CRL of the report expires
+    ///
+    /// [`Duration::ZERO`] if it is already expired.
+    pub fn expires_in(&self) -> Duration {
+        self.expires_in
+    }
+
+    /// Description of the earliest-expiring certificate or CRL of the report
+    pub fn expiring(&self) -> &str {
+        &self.expiring
+    }
+
+    /// Returns true if any certificate or CRL of the report is expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_in.is_zero()
+    }
+
+    /// Returns true if any certificate or CRL of the report expires in less than `period`
+    pub fn expires_within(&self, period: Duration) -> bool {
+        self.expires_in < period
+    }
+
+    /// Full days until the earliest-expiring certificate or CRL of the report expires
+    pub fn expires_in_days(&self) -> u64 {
+        self.expires_in.as_secs() / SECS_PER_DAY as u64
+    }
+
+    /// Warning if any certificate or CRL of the report expires in less than `days` days
+    ///
+    /// The warning names the earliest-expiring certificate or CRL, e.g., `The root CA '...'
+    /// expires in 3 day(s)`.
+    pub fn expiry_warning(&self, days: u32) -> Option<String> {
+        let period = Duration::from_secs(u64::from(days) * SECS_PER_DAY as u64);
+        match (self.is_expired(), self.expires_within(period)) {
+            (true, _) => Some(format!("The {} is expired", self.expiring)),
+            (false, true) => Some(format!(
+                "The {} expires in {} day(s)",
+                self.expiring,
+                self.expires_in_days()
+            )),
+            (false, false) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timestamp_display() {
+        let cases = [
+            (0, "1970-01-01T00:00:00Z"),
+            (-1, "1969-12-31T23:59:59Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (1_711_032_759, "2024-03-21T14:52:39Z"),
+            (13_182_735_159, "2387-09-29T22:52:39Z"),
+        ];
+        for (secs, exp) in cases {
+            assert_eq!(Timestamp(secs).to_string(), exp);
+        }
+    }
+
+    #[test]
+    fn timestamp_asn1() {
+        let time = Asn1Time::from_str("20240321145239Z").unwrap();
+        assert_eq!(
+            Timestamp::from_asn1(&time).unwrap(),
+            Timestamp(1_711_032_759)
+        );
+        assert_eq!(
+            serde_json::to_string(&Timestamp(1_711_032_759)).unwrap(),
+            "\"2024-03-21T14:52:39Z\""
+        );
+    }
+}
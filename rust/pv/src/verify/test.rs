@@ -5,14 +5,27 @@
 #![cfg(test)]
 
 use super::{helper, helper::*, *};
-use crate::{utils::read_crls, Error, HkdVerifyErrorType::*};
-use openssl::{stack::Stack, x509::X509Crl};
-use std::path::Path;
+use crate::{crypto::SignatureMismatch, utils::read_crls, Error};
+use openssl::{
+    stack::Stack,
+    x509::{X509Crl, X509},
+};
+use std::{cell::Cell, path::Path};
 
 use crate::test_utils::*;
 
+thread_local! {
+    /// Number of (mocked) CRL downloads of this thread
+    static DOWNLOADS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn downloads() -> usize {
+    DOWNLOADS.with(Cell::get)
+}
+
 // Mock function
 pub fn download_first_crl_from_x509(cert: &X509Ref) -> Result<Option<Vec<X509Crl>>> {
+    DOWNLOADS.with(|d| d.set(d.get() + 1));
     fn mock_download<P: AsRef<Path>>(path: P) -> Result<Vec<X509Crl>> {
         read_crls(std::fs::read(path)?)
     }
@@ -31,16 +44,73 @@ pub fn download_first_crl_from_x509(cert: &X509Ref) -> Result<Option<Vec<X509Crl
     Ok(None)
 }
 
+thread_local! {
+    /// Number of (mocked) OCSP requests of this thread
+    static OCSP_QUERIES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Nonce of the canned OCSP responses
+pub const OCSP_TEST_NONCE: [u8; ocsp::OCSP_NONCE_SIZE] =
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+// Mock function
+pub fn ocsp_nonce() -> Result<[u8; ocsp::OCSP_NONCE_SIZE]> {
+    Ok(OCSP_TEST_NONCE)
+}
+
+// Mock function
+pub fn query_ocsp_responder(uri: &str, _req: &[u8]) -> Result<Option<Vec<u8>>> {
+    OCSP_QUERIES.with(|q| q.set(q.get() + 1));
+    Ok(std::fs::read(get_cert_asset_path(uri)).ok())
+}
+
 #[test]
 fn store_setup() {
-    let ibm_path = get_cert_asset_path("ibm.crt");
-    let inter_path = get_cert_asset_path("inter.crt");
-    let crls: [String; 0] = [];
+    let crls = read_crls(get_cert_asset("ibm.crl")).unwrap();
 
-    let store = helper::store_setup(None::<String>, &crls, &[&ibm_path, &inter_path]);
+    let store = helper::store_setup::<String>(&[], true, &crls);
     assert!(store.is_ok());
 }
 
+#[test]
+fn read_x509_file() {
+    let mut certs = vec![];
+    let mut crls = vec![];
+    helper::read_x509_file(get_cert_asset_path("bundle.pem"), &mut certs, &mut crls).unwrap();
+    // the duplicate IBM signing key is skipped
+    assert_eq!(certs.len(), 2);
+    assert_eq!(crls.len(), 2);
+
+    helper::read_x509_file(get_cert_asset_path("ibm.crt"), &mut certs, &mut crls).unwrap();
+    helper::read_x509_file(get_cert_asset_path("der.crl"), &mut certs, &mut crls).unwrap();
+    assert_eq!(certs.len(), 2);
+    assert_eq!(crls.len(), 3);
+
+    let key = get_cert_asset_path("ibm.key");
+    assert!(matches!(
+        helper::read_x509_file(&key, &mut certs, &mut crls),
+        Err(Error::NoX509InFile(p)) if p == key
+    ));
+}
+
+#[test]
+fn verify_bundle() {
+    let bundle = get_cert_asset_path("bundle.pem");
+    let root_crt = get_cert_asset_path("root_ca.chained.crt");
+    let empty: [String; 0] = [];
+
+    // certificates and CRLs in one file, as certificate or CRL
+    let verifier = CertVerifier::new(&[&bundle], &empty, Some(&root_crt), true).unwrap();
+    assert!(verifier.verify(&load_gen_cert("host.crt")).is_ok());
+    assert!(matches!(
+        verifier.verify(&load_gen_cert("host_rev.crt")),
+        Err(Error::HkdVerify(HkdRevoked))
+    ));
+    let verifier = CertVerifier::new(&empty, &[&bundle], Some(&root_crt), true).unwrap();
+    assert!(verifier.verify(&load_gen_cert("host.crt")).is_ok());
+    assert_eq!(downloads(), 0);
+}
+
 #[test]
 fn verify_chain_online() {
     let ibm_crt = get_cert_asset_path("ibm.crt");
@@ -58,15 +128,18 @@ fn verify_chain_offline() {
     let inter_crl = get_cert_asset_path("inter_ca.crl");
     let inter_crt = load_gen_cert("inter_ca.crt");
     let root_crt = get_cert_asset_path("root_ca.chained.crt");
-    let certs: [String; 0] = [];
 
-    let store = helper::store_setup(Some(&root_crt), &[&inter_crl], &certs)
+    let crls = read_crls(std::fs::read(inter_crl).unwrap()).unwrap();
+
+    let store = helper::store_setup(&[&root_crt], false, &crls)
         .unwrap()
         .build();
 
     let mut sk = Stack::<X509>::new().unwrap();
     sk.push(inter_crt).unwrap();
-    assert!(verify_chain(&store, &sk, &[ibm_crt]).is_ok());
+    let (intermediates, root) = verify_chain(&store, &sk, &ibm_crt).unwrap();
+    assert_eq!(intermediates, [load_gen_cert("inter_ca.crt")]);
+    assert_eq!(root, load_gen_cert("root_ca.crt"));
 }
 
 #[test]
@@ -108,6 +181,23 @@ fn verify(offline: bool, ibm_crt: &'static str, ibm_crl: &'static str, hkd: &'st
         verifier.verify(&hkd_exp),
         Err(Error::HkdVerify(AfterValidity))
     ));
+
+    // corrupt the signature at the end of the certificate
+    let mut der = hkd.to_der().unwrap();
+    let len = der.len();
+    der[len - 8] ^= 0xff;
+    let err = verifier.verify(&X509::from_der(&der).unwrap()).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::HkdVerify(Signature(SignatureMismatch::Signature))
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "Host-key verification failed: Signature verification failed: invalid signature"
+    );
 }
 
 #[test]
@@ -149,3 +239,306 @@ fn verify_armonk_hkd_online() {
 fn verify_armonk_hkd_offline() {
     verify(true, "ibm_armonk.crt", "ibm_armonk.crl", "host_armonk.crt")
 }
+
+#[test]
+fn verify_offline_missing_crl() {
+    let root_crt = get_cert_asset_path("root_ca.chained.crt");
+    let inter_crt = get_cert_asset_path("inter_ca.crt");
+    let inter_crl = get_cert_asset_path("inter_ca.crl");
+    let ibm_crt = get_cert_asset_path("ibm.crt");
+    let hkd = load_gen_cert("host.crt");
+
+    let verifier = CertVerifier::new(
+        &[&ibm_crt, &inter_crt],
+        &[&inter_crl],
+        Some(&root_crt),
+        true,
+    )
+    .unwrap();
+    assert!(matches!(
+        verifier.verify(&hkd),
+        Err(Error::HkdVerify(MissingCrl(issuer))) if issuer == x509_name_to_string(hkd.issuer_name())
+    ));
+
+    let crls: [String; 0] = [];
+    let inter = load_gen_cert("inter_ca.crt");
+    assert!(matches!(
+        CertVerifier::new(&[&ibm_crt, &inter_crt], &crls, Some(&root_crt), true),
+        Err(Error::HkdVerify(MissingCrl(issuer))) if issuer == x509_name_to_string(inter.subject_name())
+    ));
+    assert_eq!(downloads(), 0);
+
+    // online the CRLs are downloaded
+    let verifier =
+        CertVerifier::new(&[&ibm_crt, &inter_crt], &crls, Some(&root_crt), false).unwrap();
+    assert!(verifier.verify(&hkd).is_ok());
+    assert!(downloads() > 0);
+}
+
+#[test]
+fn verify_expired_crl() {
+    let root_crt = get_cert_asset_path("root_ca.chained.crt");
+    let inter_crt = get_cert_asset_path("inter_ca.crt");
+    let inter_crl = get_cert_asset_path("inter_ca.crl");
+    let ibm_crt = get_cert_asset_path("ibm.crt");
+    let ibm_crl = get_cert_asset_path("ibm_outdated_late.crl");
+    let hkd = load_gen_cert("host.crt");
+
+    let verifier = CertVerifier::new(
+        &[&ibm_crt, &inter_crt],
+        &[&ibm_crl, &inter_crl],
+        Some(&root_crt),
+        true,
+    )
+    .unwrap();
+    assert!(matches!(
+        verifier.verify(&hkd),
+        Err(Error::HkdVerify(CrlExpired(issuer))) if issuer == x509_name_to_string(hkd.issuer_name())
+    ));
+
+    let ibm_crl = get_cert_asset_path("ibm.crl");
+    let inter_crl = get_cert_asset_path("inter_ca.invalid_date.crl");
+    let inter = load_gen_cert("inter_ca.crt");
+    assert!(matches!(
+        CertVerifier::new(
+            &[&ibm_crt, &inter_crt],
+            &[&ibm_crl, &inter_crl],
+            Some(&root_crt),
+            true,
+        ),
+        Err(Error::HkdVerify(CrlExpired(issuer))) if issuer == x509_name_to_string(inter.subject_name())
+    ));
+    assert_eq!(downloads(), 0);
+}
+
+fn ocsp_verifier(offline: bool, mode: OcspMode) -> CertVerifier {
+    let root_crt = get_cert_asset_path("root_ca.chained.crt");
+    let inter_crt = get_cert_asset_path("inter_ca.crt");
+    let inter_crl = get_cert_asset_path("inter_ca.crl");
+    let ibm_crt = get_cert_asset_path("ibm.crt");
+    let ibm_crl = get_cert_asset_path("ibm.crl");
+
+    CertVerifier::new(
+        &[&ibm_crt, &inter_crt],
+        &[&ibm_crl, &inter_crl],
+        Some(&root_crt),
+        offline,
+    )
+    .unwrap()
+    .with_ocsp(mode)
+}
+
+#[test]
+fn verify_ocsp_offline() {
+    let verifier = ocsp_verifier(true, OcspMode::Required);
+    assert!(verifier.verify(&load_gen_cert("host_ocsp_na.crt")).is_ok());
+    assert_eq!(OCSP_QUERIES.with(Cell::get), 0);
+}
+
+#[test]
+fn verify_ocsp() {
+    let hkd = load_gen_cert("host_ocsp.crt");
+    let hkd_rev = load_gen_cert("host_ocsp_rev.crt");
+    let hkd_na = load_gen_cert("host_ocsp_na.crt");
+    let hkd_no_ocsp = load_gen_cert("host.crt");
+
+    for mode in [OcspMode::Required, OcspMode::FallbackCrl] {
+        let verifier = ocsp_verifier(false, mode);
+        assert!(verifier.verify(&hkd).is_ok());
+        // the CRL does not know this HKD
+        assert!(matches!(
+            verifier.verify(&hkd_rev),
+            Err(Error::HkdVerify(OcspRevoked))
+        ));
+    }
+
+    let verifier = ocsp_verifier(false, OcspMode::Required);
+    assert!(matches!(
+        verifier.verify(&hkd_na),
+        Err(Error::HkdVerify(OcspUnreachable))
+    ));
+    assert!(matches!(
+        verifier.verify(&hkd_no_ocsp),
+        Err(Error::HkdVerify(OcspNoResponder))
+    ));
+
+    let verifier = ocsp_verifier(false, OcspMode::FallbackCrl);
+    assert!(verifier.verify(&hkd_na).is_ok());
+    assert!(verifier.verify(&hkd_no_ocsp).is_ok());
+    assert!(matches!(
+        verifier.verify(&load_gen_cert("host_rev.crt")),
+        Err(Error::HkdVerify(HkdRevoked))
+    ));
+}
+
+fn fake_hierarchy(builder: CertVerifierBuilder) -> Result<CertVerifier> {
+    builder
+        .add_cert(get_cert_asset_path("fake_ibm.crt"))
+        .add_crl(get_cert_asset_path("fake_root_ca.crl"))
+        .add_crl(get_cert_asset_path("fake_ibm.crl"))
+        .offline(true)
+        .build()
+}
+
+fn ibm_hierarchy(builder: CertVerifierBuilder) -> Result<CertVerifier> {
+    builder
+        .add_cert(get_cert_asset_path("bundle.pem"))
+        .offline(true)
+        .build()
+}
+
+#[test]
+fn verify_multiple_roots() {
+    let root = load_gen_cert("root_ca.crt");
+    let fake_root = load_gen_cert("fake_root_ca.crt");
+    // both hierarchies use the same names
+    assert_eq!(
+        x509_name_to_string(root.subject_name()),
+        x509_name_to_string(fake_root.subject_name())
+    );
+    let builder = CertVerifierBuilder::new()
+        .add_root_ca(get_cert_asset_path("root_ca.chained.crt"))
+        .add_root_ca(get_cert_asset_path("fake_root_ca.crt"))
+        .default_roots(false);
+
+    let verifier = ibm_hierarchy(builder.clone()).unwrap();
+    assert!(verifier.root_ca() == &*root);
+    assert!(verifier.verify(&load_gen_cert("host.crt")).is_ok());
+    assert!(verifier.verify(&load_gen_cert("fake_host.crt")).is_err());
+
+    let verifier = fake_hierarchy(builder).unwrap();
+    assert!(verifier.root_ca() == &*fake_root);
+    assert!(verifier.verify(&load_gen_cert("fake_host.crt")).is_ok());
+    assert!(verifier.verify(&load_gen_cert("host.crt")).is_err());
+    assert_eq!(downloads(), 0);
+}
+
+#[test]
+fn verify_single_root() {
+    let builder = CertVerifierBuilder::new().default_roots(false);
+    let root = builder
+        .clone()
+        .add_root_ca(get_cert_asset_path("root_ca.chained.crt"));
+    let fake_root = builder
+        .clone()
+        .add_root_ca(get_cert_asset_path("fake_root_ca.crt"));
+
+    assert!(ibm_hierarchy(root.clone()).is_ok());
+    assert!(fake_hierarchy(root).is_err());
+    assert!(fake_hierarchy(fake_root.clone()).is_ok());
+    assert!(ibm_hierarchy(fake_root).is_err());
+    // nothing is trusted
+    assert!(ibm_hierarchy(builder.clone()).is_err());
+    assert!(fake_hierarchy(builder).is_err());
+}
+
+#[test]
+fn verify_report() {
+    let root_crt = get_cert_asset_path("root_ca.chained.crt");
+    let inter_crt = get_cert_asset_path("inter_ca.crt");
+    let inter_crl = get_cert_asset_path("inter_ca.crl");
+    let ibm_crt = get_cert_asset_path("ibm.crt");
+    let ibm_crl = get_cert_asset_path("ibm.crl");
+    let hkd = load_gen_cert("host.crt");
+
+    let verifier = CertVerifier::new(
+        &[&ibm_crt, &inter_crt],
+        &[&ibm_crl, &inter_crl],
+        Some(&root_crt),
+        true,
+    )
+    .unwrap();
+    let report = verifier.verify_report(&hkd).unwrap();
+
+    let certs: Vec<_> = report
+        .certificates()
+        .iter()
+        .map(|c| (c.kind(), c.subject().to_string()))
+        .collect();
+    let subject = |name| x509_name_to_string(load_gen_cert(name).subject_name());
+    assert_eq!(
+        certs,
+        [
+            (CertificateKind::HostKeyDocument, subject("host.crt")),
+            (CertificateKind::IbmSigningKey, subject("ibm.crt")),
+            (CertificateKind::IntermediateCa, subject("inter_ca.crt")),
+            (CertificateKind::RootCa, subject("root_ca.crt")),
+        ]
+    );
+    let host = &report.certificates()[0];
+    assert_eq!(
+        host.serial(),
+        format!(
+            "0x{}",
+            pv_core::misc::encode_hex(hkd.serial_number().to_bn().unwrap().to_vec())
+        )
+    );
+    assert_eq!(host.not_before().to_string(), "2024-03-21T14:52:39Z");
+    assert_eq!(host.not_after().to_string(), "2388-12-23T14:52:39Z");
+
+    let crls: Vec<_> = report.crls().iter().map(|c| c.issuer()).collect();
+    assert_eq!(crls, [subject("ibm.crt"), subject("inter_ca.crt")]);
+    let crl = &report.crls()[0];
+    assert_eq!(crl.this_update().to_string(), "2024-03-11T15:52:39Z");
+    assert_eq!(
+        crl.next_update().map(|t| t.to_string()).as_deref(),
+        Some("2388-12-23T15:52:39Z")
+    );
+
+    // the root CA expires first
+    assert_eq!(
+        report.expiring(),
+        format!("root CA '{}'", subject("root_ca.crt"))
+    );
+    assert!(!report.is_expired());
+    assert!(report.expires_in() > std::time::Duration::from_secs(300 * 365 * 24 * 60 * 60));
+    assert!(!report.expires_within(std::time::Duration::from_secs(365 * 24 * 60 * 60)));
+    assert!(report.expires_in_days() > 300 * 365);
+    assert_eq!(report.expiry_warning(365), None);
+    assert_eq!(
+        report.expiry_warning(u32::MAX),
+        Some(format!(
+            "The root CA '{}' expires in {} day(s)",
+            subject("root_ca.crt"),
+            report.expires_in_days()
+        ))
+    );
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["certificates"][0]["kind"], "host_key_document");
+    assert_eq!(
+        json["certificates"][3]["not_before"],
+        "2024-03-21T14:52:37Z"
+    );
+    assert_eq!(json["expires_in"], report.expires_in().as_secs());
+
+    assert!(matches!(
+        verifier.verify_report(&load_gen_cert("host_crt_expired.crt")),
+        Err(Error::HkdVerify(AfterValidity))
+    ));
+}
+
+#[test]
+fn verify_report_no_verify() {
+    let hkd = load_gen_cert("host_crt_expired.crt");
+    let report = NoVerifyHkd.verify_report(&hkd).unwrap();
+    assert_eq!(report.certificates().len(), 1);
+    assert!(report.crls().is_empty());
+    assert!(report.is_expired());
+    assert!(report.expires_within(std::time::Duration::from_secs(1)));
+    assert_eq!(report.expires_in_days(), 0);
+    assert_eq!(
+        report.expiry_warning(0),
+        Some(format!(
+            "The host-key document '{}' is expired",
+            x509_name_to_string(hkd.subject_name())
+        ))
+    );
+    assert_eq!(
+        report.expiring(),
+        format!(
+            "host-key document '{}'",
+            x509_name_to_string(hkd.subject_name())
+        )
+    );
+}
@@ -15,8 +15,8 @@ use s390_pv::{
         BootHdrTags, ReqEncrCtx, Request, SymKey,
     },
     secret::{
-        verify_asrcb_and_get_user_data, AddSecretFlags, AddSecretRequest, AddSecretVersion,
-        ExtSecret, GuestSecret,
+        verify_asrcb_and_get_user_data, AddSecretContext, AddSecretFlags, AddSecretRequest,
+        AddSecretVersion, ExtSecret, GuestSecret,
     },
     test_utils::get_test_keys,
     uv::ConfigUid,
@@ -27,6 +27,7 @@ const TAGS: BootHdrTags = BootHdrTags::new([1; 64], [2; 64], [3; 64], [4; 16]);
 const CUID: ConfigUid = [0x42u8; 16];
 const ASSOC_SECRET: [u8; 32] = [0x11; 32];
 const ASSOC_ID: &str = "add_secret_request";
+const RETR_ID: &str = "add_secret_request_retrievable";
 
 fn create_asrcb(
     guest_secret: GuestSecret,
@@ -89,6 +90,19 @@ fn ext_derived() -> ExtSecret {
     ExtSecret::Derived([0; 32].into())
 }
 
+fn retr_plain() -> GuestSecret {
+    GuestSecret::plaintext(RETR_ID, vec![0x33; 40].into()).unwrap()
+}
+
+fn retr_aes() -> GuestSecret {
+    GuestSecret::aes(RETR_ID, vec![0x44; 32].into()).unwrap()
+}
+
+fn retr_ec() -> GuestSecret {
+    let key = PKey::private_key_from_pem(get_test_asset!("cert/host.key")).unwrap();
+    GuestSecret::ec(RETR_ID, key).unwrap()
+}
+
 fn no_flag() -> AddSecretFlags {
     AddSecretFlags::default()
 }
@@ -251,6 +265,92 @@ fn null_none_default_cuid_seven() {
     assert_eq!(asrcb, exp);
 }
 
+/// The request tag is the last field of the request
+fn assert_tag(asrcb: &[u8]) {
+    let tag = AddSecretRequest::bin_tag(asrcb).unwrap();
+    assert_eq!(tag, asrcb[asrcb.len() - 0x10..]);
+}
+
+#[test]
+fn retr_plain_none_default_cuid_one() {
+    let asrcb = gen_asrcb(retr_plain(), None, no_flag(), true).unwrap();
+    let exp = get_test_asset!("exp/asrcb/retr_plain_none_default_cuid_one");
+    assert_eq!(asrcb, exp);
+    assert_tag(&asrcb);
+}
+
+#[test]
+fn retr_aes_simple_default_cuid_one() {
+    let asrcb = gen_asrcb(retr_aes(), ext_simple(), no_flag(), true).unwrap();
+    let exp = get_test_asset!("exp/asrcb/retr_aes_simple_default_cuid_one");
+    assert_eq!(asrcb, exp);
+    assert_tag(&asrcb);
+}
+
+#[test]
+fn retr_ec_none_default_ncuid_one() {
+    let asrcb = gen_asrcb(retr_ec(), None, no_flag(), false).unwrap();
+    let exp = get_test_asset!("exp/asrcb/retr_ec_none_default_ncuid_one");
+    assert_eq!(asrcb, exp);
+    assert_tag(&asrcb);
+}
+
+#[test]
+fn context_simple_cuid_one() {
+    let (host_key, ctx) = get_crypto();
+    let mut asrcb_ctx = AddSecretContext::new(AddSecretVersion::One, TAGS);
+    asrcb_ctx.add_hostkey(host_key);
+    asrcb_ctx.set_cuid(CUID);
+    asrcb_ctx.set_ext_secret(ext_simple()).unwrap();
+
+    let exp: [(GuestSecret, &[u8]); 3] = [
+        (
+            association(),
+            get_test_asset!("exp/asrcb/assoc_simple_default_cuid_one"),
+        ),
+        (
+            GuestSecret::Null,
+            get_test_asset!("exp/asrcb/null_simple_default_cuid_one"),
+        ),
+        (
+            retr_aes(),
+            get_test_asset!("exp/asrcb/retr_aes_simple_default_cuid_one"),
+        ),
+    ];
+    for (secret, exp) in exp {
+        let asrcb = asrcb_ctx.request(secret, no_flag()).encrypt(&ctx).unwrap();
+        assert_eq!(asrcb, exp);
+        assert_eq!(
+            AddSecretRequest::bin_tag(&asrcb).unwrap(),
+            AddSecretRequest::bin_tag(exp).unwrap()
+        );
+        assert_tag(&asrcb);
+    }
+}
+
+#[test]
+fn context_derived_cuid_one() {
+    let (host_key, ctx) = get_crypto();
+    let mut asrcb_ctx = AddSecretContext::new(AddSecretVersion::One, TAGS);
+    asrcb_ctx.add_hostkey(host_key);
+    asrcb_ctx.set_cuid(CUID);
+    asrcb_ctx.set_ext_secret(ext_derived()).unwrap();
+
+    let asrcb = asrcb_ctx
+        .request(association(), no_flag())
+        .encrypt(&ctx)
+        .unwrap();
+    let exp = get_test_asset!("exp/asrcb/assoc_derived_default_cuid_one");
+    assert_eq!(asrcb, exp);
+
+    let asrcb = asrcb_ctx
+        .request(GuestSecret::Null, no_flag())
+        .encrypt(&ctx)
+        .unwrap();
+    let exp = get_test_asset!("exp/asrcb/null_derived_default_cuid_one");
+    assert_eq!(asrcb, exp);
+}
+
 #[test]
 fn verify_no_user_data() {
     let req = get_test_asset!("exp/asrcb/null_none_default_ncuid_one");
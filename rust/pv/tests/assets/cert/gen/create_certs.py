@@ -70,6 +70,7 @@ def createCert(
     not_before=None,
     not_after=None,
     pub_key=None,
+    ocsp_uri=None,
 ):
     sha = hashes.SHA256
     not_before = not_before or datetime.datetime.utcnow()
@@ -107,6 +108,19 @@ def createCert(
             critical=False,
         )
 
+    if ocsp_uri is not None:
+        cert_builder = cert_builder.add_extension(
+            x509.AuthorityInformationAccess(
+                [
+                    x509.AccessDescription(
+                        x509.oid.AuthorityInformationAccessOID.OCSP,
+                        x509.UniformResourceIdentifier(ocsp_uri),
+                    )
+                ]
+            ),
+            critical=False,
+        )
+
     if t == CertType.ROOT_CA:
         cert_builder = cert_builder.add_extension(
             x509.BasicConstraints(ca=True, path_length=None),
@@ -771,3 +785,14 @@ if __name__ == "__main__":
         f.write(ibm_pougkeepsie_crt.public_bytes(serialization.Encoding.DER))
     with open("der.crl", "wb") as f:
         f.write(ibm_poughkeepsie_crl.public_bytes(serialization.Encoding.DER))
+
+    # store the chain of trust with its CRLs (and a duplicate) in one file
+    with open("bundle.pem", "wb") as f:
+        for obj in [
+            ibm_pougkeepsie_crt,
+            inter_ca_crt,
+            inter_ca_crl,
+            ibm_poughkeepsie_crl,
+            ibm_pougkeepsie_crt,
+        ]:
+            f.write(obj.public_bytes(serialization.Encoding.PEM))
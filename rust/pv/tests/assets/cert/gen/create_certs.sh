@@ -14,4 +14,5 @@ source "${path}"/gen_venv/bin/activate
 pip3 install -r "${path}"/requirements.txt
 cd "${path}" || exit 2
 python3 ./create_certs.py
+python3 ./create_ocsp.py
 deactivate
@@ -0,0 +1,129 @@
+#!/bin/env python3
+# Creates host-key documents with an OCSP responder and canned OCSP responses.
+# Requires the certificates and keys of create_certs.py.
+import datetime
+
+from cryptography import x509
+from cryptography.hazmat.backends import default_backend
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.x509 import ocsp
+
+from create_certs import ONE_DAY, CertType, createCert, getPrivKey
+
+# Nonce the unit tests use instead of a random one
+OCSP_TEST_NONCE = bytes(range(16))
+
+
+def loadCert(path):
+    with open(path, "rb") as f:
+        return x509.load_pem_x509_certificate(f.read(), default_backend())
+
+
+def createOcspResponse(
+    cert,
+    issuer_crt,
+    responder_crt,
+    responder_pkey,
+    status=ocsp.OCSPCertStatus.GOOD,
+    nonce=OCSP_TEST_NONCE,
+    this_update=None,
+    next_update=None,
+):
+    this_update = this_update or datetime.datetime.today() - ONE_DAY
+    next_update = next_update or datetime.datetime.today() + 365 * 365 * ONE_DAY
+    revocation_time = None
+    revocation_reason = None
+    if status == ocsp.OCSPCertStatus.REVOKED:
+        revocation_time = datetime.datetime.today() - ONE_DAY
+        revocation_reason = x509.ReasonFlags.key_compromise
+    builder = (
+        ocsp.OCSPResponseBuilder()
+        .add_response(
+            cert=cert,
+            issuer=issuer_crt,
+            algorithm=hashes.SHA1(),
+            cert_status=status,
+            this_update=this_update,
+            next_update=next_update,
+            revocation_time=revocation_time,
+            revocation_reason=revocation_reason,
+        )
+        .responder_id(ocsp.OCSPResponderEncoding.HASH, responder_crt)
+    )
+    if responder_crt != issuer_crt:
+        builder = builder.certificates([responder_crt])
+    if nonce is not None:
+        builder = builder.add_extension(x509.OCSPNonce(nonce), critical=False)
+    return builder.sign(responder_pkey, hashes.SHA256())
+
+
+if __name__ == "__main__":
+    ibm_crt = loadCert("ibm.crt")
+    ibm_pkey = getPrivKey("ibm.key", None)
+    fake_ibm_crt = loadCert("fake_ibm.crt")
+    fake_ibm_pkey = getPrivKey("fake_ibm.key", None)
+    host_pkey = getPrivKey("host.key", None)
+    host_subject = loadCert("host.crt").subject
+
+    def host_ocsp_crt(ocsp_uri):
+        return createCert(
+            pkey=host_pkey,
+            subject=host_subject,
+            issuer_crt=ibm_crt,
+            issuer_pkey=ibm_pkey,
+            crl_uri="ibm.crl",
+            t=CertType.HOST_CERT,
+            ocsp_uri=ocsp_uri,
+        )
+
+    host_ocsp = host_ocsp_crt("ocsp_good.der")
+    host_ocsp_rev = host_ocsp_crt("ocsp_rev_revoked.der")
+    host_ocsp_na = host_ocsp_crt("notavailable")
+
+    responses = {
+        "ocsp_good.der": createOcspResponse(host_ocsp, ibm_crt, ibm_crt, ibm_pkey),
+        "ocsp_revoked.der": createOcspResponse(
+            host_ocsp, ibm_crt, ibm_crt, ibm_pkey, status=ocsp.OCSPCertStatus.REVOKED
+        ),
+        "ocsp_unknown.der": createOcspResponse(
+            host_ocsp, ibm_crt, ibm_crt, ibm_pkey, status=ocsp.OCSPCertStatus.UNKNOWN
+        ),
+        "ocsp_nonce_mismatch.der": createOcspResponse(
+            host_ocsp, ibm_crt, ibm_crt, ibm_pkey, nonce=bytes(16)
+        ),
+        "ocsp_no_nonce.der": createOcspResponse(
+            host_ocsp, ibm_crt, ibm_crt, ibm_pkey, nonce=None
+        ),
+        "ocsp_fake_signer.der": createOcspResponse(
+            host_ocsp, ibm_crt, fake_ibm_crt, fake_ibm_pkey
+        ),
+        "ocsp_expired.der": createOcspResponse(
+            host_ocsp,
+            ibm_crt,
+            ibm_crt,
+            ibm_pkey,
+            this_update=datetime.datetime.today() - 2 * 365 * ONE_DAY,
+            next_update=datetime.datetime.today() - 1 * 365 * ONE_DAY,
+        ),
+        "ocsp_other_cert.der": createOcspResponse(
+            host_ocsp_rev, ibm_crt, ibm_crt, ibm_pkey
+        ),
+        "ocsp_rev_revoked.der": createOcspResponse(
+            host_ocsp_rev,
+            ibm_crt,
+            ibm_crt,
+            ibm_pkey,
+            status=ocsp.OCSPCertStatus.REVOKED,
+        ),
+    }
+
+    for name, crt in [
+        ("host_ocsp.crt", host_ocsp),
+        ("host_ocsp_rev.crt", host_ocsp_rev),
+        ("host_ocsp_na.crt", host_ocsp_na),
+    ]:
+        with open(name, "wb") as f:
+            f.write(crt.public_bytes(serialization.Encoding.PEM))
+    for name, resp in responses.items():
+        with open(name, "wb") as f:
+            f.write(resp.public_bytes(serialization.Encoding.DER))
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+#![allow(missing_docs)]
+
+mod parsers;
+
+use std::{fs, path::Path};
+
+/// Runs every file in `tests/corpus-regressions/<target>/` through the parser of the fuzz target
+#[test]
+fn corpus_regressions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus-regressions");
+    for (target, parse) in parsers::TARGETS {
+        let entries = fs::read_dir(dir.join(target)).unwrap();
+        for entry in entries {
+            let path = entry.unwrap().path();
+            println!("{target}: {}", path.display());
+            parse(&fs::read(&path).unwrap());
+        }
+    }
+}
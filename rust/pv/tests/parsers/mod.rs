@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+//! Entry points of the binary parsers for fuzzing
+//!
+//! Shared by the fuzz targets in `fuzz/` and the replay of `tests/corpus-regressions/`. The
+//! functions ignore all errors; only panics are findings.
+
+// each fuzz target uses one parser only
+#![allow(dead_code)]
+
+use std::{io::Cursor, sync::OnceLock};
+
+use s390_pv::{
+    attest::{AdditionalData, AttestationFlags, AttestationRequest},
+    request::{
+        openssl::pkey::{PKey, Public},
+        BootHdrTags, SymKey,
+    },
+    secret::{verify_asrcb_and_get_user_data, AddSecretRequest},
+    test_utils::get_test_keys,
+};
+
+/// Public keys for each type of signed user-data
+fn user_data_keys() -> &'static [PKey<Public>] {
+    static KEYS: OnceLock<Vec<PKey<Public>>> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let rsa = |pem: &[u8]| PKey::public_key_from_pem(pem).unwrap();
+        vec![
+            get_test_keys().1,
+            rsa(include_bytes!("../assets/keys/rsa2048key.pub.pem")),
+            rsa(include_bytes!("../assets/keys/rsa3072key.pub.pem")),
+        ]
+    })
+}
+
+/// Parse `data` as binary add-secret request
+///
+/// The user-data is verified without a key and with a key for each signed user-data type.
+pub fn asrcb(data: &[u8]) {
+    let _ = AddSecretRequest::bin_tag(data);
+    let _ = AddSecretRequest::bin_host_key_hashes(data);
+    let keys = user_data_keys().iter().cloned().map(Some);
+    for key in [None].into_iter().chain(keys) {
+        let _ = verify_asrcb_and_get_user_data(data.to_vec(), key);
+    }
+}
+
+/// Parse `data` as parts of an attestation response
+///
+/// The first byte selects the additional-data flags, the remainder is parsed as additional-data
+/// and as the attestation request of the response.
+pub fn attestation_response(data: &[u8]) {
+    let Some((sel, data)) = data.split_first() else {
+        return;
+    };
+    let mut flags = AttestationFlags::default();
+    let setters = [
+        AttestationFlags::set_image_phkh,
+        AttestationFlags::set_attest_phkh,
+        AttestationFlags::set_secret_store_hash,
+        AttestationFlags::set_firmware_state,
+    ];
+    for (bit, set) in setters.iter().enumerate() {
+        if sel & (1 << bit) != 0 {
+            set(&mut flags);
+        }
+    }
+    let _ = AdditionalData::from_slice(data, &flags);
+
+    let _ = AttestationRequest::auth_bin(data);
+    let _ = AttestationRequest::decrypt_bin(data, &SymKey::Aes256([0; 32].into()));
+}
+
+/// Parse `data` as SE-image or SE-header
+pub fn se_header(data: &[u8]) {
+    let _ = BootHdrTags::from_se_image(&mut Cursor::new(data));
+}
+
+/// Parser entry point of a fuzz target
+pub type Parser = fn(&[u8]);
+
+/// All parsers by the name of their fuzz target
+pub const TARGETS: [(&str, Parser); 3] = [
+    ("asrcb", asrcb),
+    ("attestation_response", attestation_response),
+    ("se_header", se_header),
+];
@@ -29,6 +29,15 @@ const SYS_BUS_AP_BIND_TIMEOUT_MS: u64 = 10000;
 const SYS_BUS_AP_ASSOC_POLL_MS: u64 = 500;
 const SYS_BUS_AP_ASSOC_TIMEOUT_MS: u64 = 10000;
 
+macro_rules! parse_error {
+    ($subject:expr, $content:expr) => {
+        Error::ParseError {
+            subject: $subject,
+            content: $content,
+        }
+    };
+}
+
 /// APQN mode
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ApqnMode {
@@ -40,6 +49,41 @@ pub enum ApqnMode {
     Cca,
 }
 
+impl ApqnMode {
+    /// Parse the generation and mode from a card type string, such as
+    /// `CEX8P`
+    ///
+    /// The last character of the type string defines the mode of the
+    /// Crypto Express adapter: `A` for accelerator, `C` for CCA, and `P`
+    /// for EP11.
+    /// # Panics
+    /// Panics if the compilation of a static regular expression fails.
+    pub fn parse_card_type(card_type: &str) -> Result<(u32, Self)> {
+        let re_card_type = Regex::new(RE_CARD_TYPE).unwrap();
+        let caps = re_card_type
+            .captures(card_type)
+            .ok_or_else(|| parse_error!("card type".to_string(), card_type.to_string()))?;
+        let gen = caps.get(1).unwrap().as_str().parse::<u32>().unwrap();
+        let mode = match caps.get(2).unwrap().as_str() {
+            "A" => Self::Accel,
+            "C" => Self::Cca,
+            "P" => Self::Ep11,
+            _ => unreachable!("Code inconsistency between regex RE_CARD_TYPE and evaluation code."),
+        };
+        Ok((gen, mode))
+    }
+}
+
+impl fmt::Display for ApqnMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accel => write!(f, "accelerator"),
+            Self::Ep11 => write!(f, "EP11"),
+            Self::Cca => write!(f, "CCA"),
+        }
+    }
+}
+
 /// Info on an APQN configured for accelerator
 #[derive(Debug, Clone)]
 pub struct ApqnInfoAccel {
@@ -79,15 +123,6 @@ pub enum ApqnInfo {
     Cca(ApqnInfoCca),
 }
 
-macro_rules! parse_error {
-    ($subject:expr, $content:expr) => {
-        Error::ParseError {
-            subject: $subject,
-            content: $content,
-        }
-    };
-}
-
 impl ApqnInfo {
     fn accel_info(_carddir: &str, _queuedir: &str) -> Result<Self> {
         Ok(Self::Accel(ApqnInfoAccel {}))
@@ -194,7 +229,6 @@ impl TryFrom<&str> for Apqn {
     /// parse, e.g. when the capture `([[:xdigit:]]{2})` does not
     /// parse as hex string.
     fn try_from(name: &str) -> Result<Self> {
-        let re_card_type = Regex::new(RE_CARD_TYPE).unwrap();
         let re_queue_dir = Regex::new(RE_QUEUE_DIR).unwrap();
 
         let caps = re_queue_dir
@@ -208,16 +242,7 @@ impl TryFrom<&str> for Apqn {
         let path = format!("{PATH_SYS_DEVICES_AP}/card{cardstr}");
         let card_type =
             read_file_string(format!("{path}/type"), "card type").map(|s| s.trim().to_string())?;
-        let caps = re_card_type
-            .captures(&card_type)
-            .ok_or_else(|| parse_error!("card type".to_string(), card_type.to_string()))?;
-        let gen = caps.get(1).unwrap().as_str().parse::<u32>().unwrap();
-        let mode = match caps.get(2).unwrap().as_str().parse::<char>().unwrap() {
-            'A' => ApqnMode::Accel,
-            'C' => ApqnMode::Cca,
-            'P' => ApqnMode::Ep11,
-            _ => unreachable!("Code inconsistency between regex RE_CARD_TYPE and evaluation code."),
-        };
+        let (gen, mode) = ApqnMode::parse_card_type(&card_type)?;
         // the UV blocks requests to CCA cards within SE guest with AP
         // pass-through support. However, filter out CCA cards as
         // these cards cause hangs during information gathering.
@@ -288,7 +313,7 @@ impl Apqn {
 }
 
 /// Bind state of an APQN
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BindState {
     /// APQN is bound
     Bound,
@@ -357,7 +382,7 @@ pub fn set_apqn_bind_state(card: u32, dom: u32, state: BindState) -> Result<()>
 }
 
 /// Association state of an APQN
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AssocState {
     /// Associated with index
     Associated(u16),
@@ -466,3 +491,48 @@ pub fn set_apqn_associate_state(card: u32, dom: u32, state: AssocState) -> Resul
         _ => panic!("set_apqn_associate_state called with invalid AssocState."),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_card_type() {
+        for (card_type, gen, mode) in [
+            ("CEX3A", 3, ApqnMode::Accel),
+            ("CEX4C", 4, ApqnMode::Cca),
+            ("CEX5P", 5, ApqnMode::Ep11),
+            ("CEX6A", 6, ApqnMode::Accel),
+            ("CEX7C", 7, ApqnMode::Cca),
+            ("CEX8P", 8, ApqnMode::Ep11),
+        ] {
+            assert_eq!(
+                ApqnMode::parse_card_type(card_type).unwrap(),
+                (gen, mode),
+                "{card_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_card_type_invalid() {
+        for card_type in [
+            "", "CEX2A", "CEX9P", "CEX8X", "CEX8", "cex8p", "CEX8P ", "XCEX8P",
+        ] {
+            assert!(
+                matches!(
+                    ApqnMode::parse_card_type(card_type),
+                    Err(Error::ParseError { .. })
+                ),
+                "{card_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn mode_display() {
+        assert_eq!(ApqnMode::Accel.to_string(), "accelerator");
+        assert_eq!(ApqnMode::Ep11.to_string(), "EP11");
+        assert_eq!(ApqnMode::Cca.to_string(), "CCA");
+    }
+}
@@ -6,6 +6,10 @@ use std::fmt::Debug;
 
 use crate::Error;
 
+mod locked;
+pub use locked::set_lock_warning_hook;
+use locked::LockedBox;
+
 /// Trait for securely zeroizing  memory.
 ///
 /// To be used with [`Confidential`]
@@ -91,6 +95,7 @@ impl Zeroize for String {
 ///
 /// A `Confidential` represents a confidential value that must be securely overwritten during drop.
 /// Will never leak its wrapped value during [`Debug`]
+/// With the `serde` feature byte-like values are (de)serializable without leaving plain copies behind.
 ///
 /// ```rust
 /// # use s390_pv_core::request::Confidential;
@@ -106,19 +111,47 @@ impl Zeroize for String {
 /// //     value: Confidential(***)
 /// # }
 /// ```
-#[derive(Clone, PartialEq, Eq, Default)]
-pub struct Confidential<C: Zeroize>(C);
+///
+/// Values created with [`Confidential::new_locked`] live on the heap in pages locked with
+/// `mlock(2)`, so that they are never swapped out. Only the memory of `C` itself is locked.
+/// Memory owned by `C`, like the buffer of a [`Vec`], is not. Use arrays for locked values.
+pub struct Confidential<C: Zeroize>(Storage<C>);
+
+enum Storage<C> {
+    Inline(C),
+    Locked(LockedBox<C>),
+}
+
 impl<C: Zeroize> Confidential<C> {
     /// Convert a type into a self overwriting one.
     ///
     /// Prefer using [`Into`]
     pub fn new(v: C) -> Self {
-        Self(v)
+        Self(Storage::Inline(v))
+    }
+
+    /// Move a value into self overwriting, locked memory.
+    ///
+    /// If the memory cannot be locked, e.g. because `RLIMIT_MEMLOCK` is exhausted, the value is
+    /// used unlocked and the hook of [`set_lock_warning_hook`] is called once.
+    ///
+    /// ```rust
+    /// # use s390_pv_core::request::Confidential;
+    /// let key = Confidential::new_locked([0x17u8; 32]);
+    /// assert_eq!(key.value(), &[0x17; 32]);
+    /// // `false` if the memory could not be locked
+    /// println!("locked: {}", key.is_locked());
+    /// ```
+    pub fn new_locked(v: C) -> Self {
+        Self(Storage::Locked(LockedBox::new(v)))
     }
 
     /// Get a reference to the contained value
     pub fn value(&self) -> &C {
-        &self.0
+        match &self.0 {
+            Storage::Inline(v) => v,
+            Storage::Locked(v) => v.value(),
+        }
     }
 
     /// Get a mutable reference to the contained value
@@ -127,7 +160,18 @@ impl<C: Zeroize> Confidential<C> {
     /// e.g. a [`Vec`] might expand if more space needed. -> preallocate enough space
     /// or operate on slices. The old locations can and will **NOT** be zeroized.
     pub fn value_mut(&mut self) -> &mut C {
-        &mut self.0
+        match &mut self.0 {
+            Storage::Inline(v) => v,
+            Storage::Locked(v) => v.value_mut(),
+        }
+    }
+
+    /// Returns `true` if the memory of the value is locked.
+    pub fn is_locked(&self) -> bool {
+        match &self.0 {
+            Storage::Inline(_) => false,
+            Storage::Locked(v) => v.is_locked(),
+        }
     }
 }
 
@@ -137,7 +181,31 @@ impl<C: Zeroize + Clone> Confidential<C> {
     /// This disables any cleanups for the result.
     pub fn into_inner(self) -> C {
         // The clone is required because drop is implemented (E0509)
-        self.0.clone()
+        self.value().clone()
+    }
+}
+
+/// The clone is stored the same way, in locked memory or not.
+impl<C: Zeroize + Clone> Clone for Confidential<C> {
+    fn clone(&self) -> Self {
+        match &self.0 {
+            Storage::Inline(v) => Self::new(v.clone()),
+            Storage::Locked(v) => Self::new_locked(v.value().clone()),
+        }
+    }
+}
+
+impl<C: Zeroize + PartialEq> PartialEq for Confidential<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()
+    }
+}
+
+impl<C: Zeroize + Eq> Eq for Confidential<C> {}
+
+impl<C: Zeroize + Default> Default for Confidential<C> {
+    fn default() -> Self {
+        Self::new(C::default())
     }
 }
 
@@ -149,26 +217,27 @@ impl<C: Zeroize + Debug> Debug for Confidential<C> {
         return write!(f, "Confidential(***)");
 
         let mut b = f.debug_tuple("Confidential");
-        b.field(&self.0);
+        b.field(self.value());
         b.finish()
     }
 }
 
 impl<C: Zeroize> From<C> for Confidential<C> {
     fn from(v: C) -> Self {
-        Self(v)
+        Self::new(v)
     }
 }
 
 impl<C: Zeroize> Zeroize for Confidential<C> {
     fn zeroize(&mut self) {
-        self.0.zeroize();
+        self.value_mut().zeroize();
     }
 }
 
 impl<C: Zeroize> Drop for Confidential<C> {
     fn drop(&mut self) {
-        self.0.zeroize();
+        // locked memory is unlocked afterwards, when the storage is dropped
+        self.value_mut().zeroize();
     }
 }
 
@@ -176,11 +245,12 @@ impl<const N: usize> TryFrom<Confidential<Vec<u8>>> for Confidential<[u8; N]> {
     type Error = Error;
 
     fn try_from(value: Confidential<Vec<u8>>) -> Result<Self, Self::Error> {
-        let len = value.0.len();
+        let len = value.value().len();
         if len == N {
-            Ok(Self::new(
-                TryInto::<[u8; N]>::try_into(value.0.clone()).unwrap(),
-            ))
+            // copy in place; a temporary Vec would be freed without zeroizing
+            let mut arr = Self::new([0; N]);
+            arr.value_mut().copy_from_slice(value.value());
+            Ok(arr)
         } else {
             Err(Error::LengthMismatch {
                 expected: N,
@@ -192,7 +262,191 @@ impl<const N: usize> TryFrom<Confidential<Vec<u8>>> for Confidential<[u8; N]> {
 
 impl<const N: usize> From<Confidential<[u8; N]>> for Confidential<Vec<u8>> {
     fn from(value: Confidential<[u8; N]>) -> Self {
-        Self::new(value.0.to_vec())
+        Self::new(value.value().to_vec())
+    }
+}
+
+/// Serializes byte-like values as bytes or as lowercase hex string for human-readable formats.
+#[cfg(feature = "serde")]
+impl<C: Zeroize + AsRef<[u8]>> serde::Serialize for Confidential<C> {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if ser.is_human_readable() {
+            ser.serialize_str(hex_encode_confidential(self.value().as_ref()).value())
+        } else {
+            ser.serialize_bytes(self.value().as_ref())
+        }
+    }
+}
+
+/// Deserializes from bytes or a hex string.
+///
+/// The data is collected in a [`Confidential`] buffer only.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Confidential<Vec<u8>> {
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if de.is_human_readable() {
+            de.deserialize_str(ConfidentialVisitor)
+        } else {
+            de.deserialize_bytes(ConfidentialVisitor)
+        }
+    }
+}
+
+/// Deserializes from bytes or a hex string of exactly `N` bytes.
+///
+/// The data is collected in a [`Confidential`] buffer only.
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for Confidential<[u8; N]> {
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let buf = Confidential::<Vec<u8>>::deserialize(de)?;
+        let len = buf.value().len();
+        buf.try_into()
+            .map_err(|_| serde::de::Error::invalid_length(len, &format!("{N} bytes").as_str()))
+    }
+}
+
+/// Sign-extends the sign bit of `v` into a mask: all ones if `v` is negative, else zero
+const fn neg_mask(v: i16) -> u8 {
+    (v >> 8) as u8
+}
+
+/// Encodes a nibble (`0..16`) as lowercase hex character without branches
+const fn ct_hex_char(nibble: u8) -> u8 {
+    // 0xff if nibble > 9
+    let letter = neg_mask(9 - nibble as i16);
+    nibble + b'0' + (letter & (b'a' - b'0' - 10))
+}
+
+/// Decodes a hex character without branches
+///
+/// Returns the nibble and a mask that is all ones if `c` is a valid hex character.
+const fn ct_hex_nibble(c: u8) -> (u8, u8) {
+    let c = c as i16;
+    let digit = neg_mask((b'0' as i16 - 1 - c) & (c - (b'9' as i16 + 1)));
+    // the ASCII case bit folds 'A'..='F' onto 'a'..='f'
+    let lower = c | 0x20;
+    let letter = neg_mask((b'a' as i16 - 1 - lower) & (lower - (b'f' as i16 + 1)));
+    let nibble = (digit & (c - b'0' as i16) as u8) | (letter & (lower - b'a' as i16 + 10) as u8);
+    (nibble, digit | letter)
+}
+
+/// Encodes confidential data as lowercase hex string.
+///
+/// Runs in constant time for a given length: there are no branches or memory accesses that
+/// depend on the data. The result is allocated once, so that no copies of the encoded data are
+/// left behind.
+pub fn hex_encode_confidential(data: &[u8]) -> Confidential<String> {
+    let mut hex = Vec::with_capacity(2 * data.len());
+    for b in data {
+        hex.push(ct_hex_char(b >> 4));
+        hex.push(ct_hex_char(b & 0xf));
+    }
+    // Panic: the encoding produces ASCII only
+    Confidential::new(String::from_utf8(hex).unwrap())
+}
+
+/// Decodes a hex string of confidential data.
+///
+/// Accepts lower- and uppercase characters, but no prefix or separators. Runs in constant time
+/// for a given length of valid input: there are no branches or memory accesses that depend on the
+/// data. The result is allocated once, so that no copies of the decoded data are left behind.
+///
+/// # Errors
+///
+/// This function will return an error if `hex` has an odd length or contains a non-hex
+/// character. The error names the byte position of the first invalid character.
+pub fn hex_decode_confidential(hex: &str) -> crate::Result<Confidential<Vec<u8>>> {
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+        return Err(Error::HexOddLength(hex.len()));
+    }
+    let mut data = Confidential::new(Vec::with_capacity(hex.len() / 2));
+    // all ones after the first invalid character
+    let mut invalid = 0usize;
+    let mut pos = 0usize;
+    for (i, pair) in hex.chunks_exact(2).enumerate() {
+        let (high, high_valid) = ct_hex_nibble(pair[0]);
+        let (low, low_valid) = ct_hex_nibble(pair[1]);
+        data.value_mut().push((high << 4) | low);
+
+        // select the position of the first invalid character
+        let high_invalid = !(high_valid as i8 as isize as usize);
+        let low_invalid = !(low_valid as i8 as isize as usize);
+        let first_high = high_invalid & !invalid;
+        let first_low = low_invalid & !high_invalid & !invalid;
+        pos |= (first_high & (2 * i)) | (first_low & (2 * i + 1));
+        invalid |= high_invalid | low_invalid;
+    }
+    match invalid {
+        0 => Ok(data),
+        _ => Err(Error::HexInvalidChar(pos)),
+    }
+}
+
+#[cfg(feature = "serde")]
+struct ConfidentialVisitor;
+
+#[cfg(feature = "serde")]
+impl ConfidentialVisitor {
+    fn decode_hex<E: serde::de::Error>(&self, hex: &str) -> Result<Confidential<Vec<u8>>, E> {
+        // never put the string into the error, it might be (a part of) the secret
+        hex_decode_confidential(hex)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Other("a non-hex string"), self))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for ConfidentialVisitor {
+    type Value = Confidential<Vec<u8>>;
+
+    fn expecting(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str("bytes or a hex string")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Confidential::new(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Confidential::new(v))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.decode_hex(v)
+    }
+
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+        // take ownership to zeroize the hex representation as well
+        let v = Confidential::new(v);
+        self.decode_hex(v.value())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        // do not trust the hint for large allocations
+        let cap = seq.size_hint().unwrap_or(0).clamp(32, 0x1000);
+        let mut bin = Confidential::new(Vec::with_capacity(cap));
+        while let Some(b) = seq.next_element()? {
+            if bin.value().len() == bin.value().capacity() {
+                // grow manually, a reallocation would not zeroize the old buffer
+                let mut new = Confidential::new(Vec::with_capacity(2 * bin.value().capacity()));
+                new.value_mut().extend_from_slice(bin.value());
+                bin = new;
+            }
+            bin.value_mut().push(b);
+        }
+        Ok(bin)
     }
 }
 
@@ -277,4 +531,122 @@ mod test {
         let vec: Confidential<Vec<u8>> = Confidential::new(data).into();
         assert_eq!(vec.value(), data.as_slice());
     }
+
+    #[test]
+    fn hex_confidential_roundtrip() {
+        let data = Confidential::new((0..=255).collect::<Vec<u8>>());
+        let hex = hex_encode_confidential(data.value());
+        assert_eq!(hex.value(), &crate::utils::encode_hex(data.value()));
+        assert_eq!(
+            hex_decode_confidential(hex.value()).unwrap().value(),
+            data.value()
+        );
+        assert_eq!(
+            hex_decode_confidential(&hex.value().to_uppercase())
+                .unwrap()
+                .value(),
+            data.value()
+        );
+
+        let empty = hex_encode_confidential(&[]);
+        assert_eq!(empty.value(), "");
+        assert!(hex_decode_confidential("").unwrap().value().is_empty());
+    }
+
+    #[test]
+    fn hex_confidential_invalid() {
+        assert!(matches!(
+            hex_decode_confidential("abc"),
+            Err(Error::HexOddLength(3))
+        ));
+        for (hex, pos) in [
+            ("g0", 0),
+            ("0g", 1),
+            ("00 1", 2),
+            ("0011x2", 4),
+            ("0x01", 1),
+            ("00/:@G`g", 2),
+            ("0000zz", 4),
+            ("ab\u{e4}", 2),
+        ] {
+            match hex_decode_confidential(hex) {
+                Err(Error::HexInvalidChar(p)) => assert_eq!(p, pos, "{hex}"),
+                res => panic!("Unexpected result for {hex}: {res:?}"),
+            }
+        }
+        assert_eq!(
+            Error::HexInvalidChar(4).to_string(),
+            "Invalid hex string: Invalid character at position 4"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use super::*;
+        use ::serde::de::Visitor;
+
+        #[test]
+        fn json() {
+            let conf = Confidential::new(vec![0x00u8, 0x12, 0xab, 0xff]);
+            let json = serde_json::to_string(&conf).unwrap();
+            assert_eq!(json, r#""0012abff""#);
+            let de: Confidential<Vec<u8>> = serde_json::from_str(&json).unwrap();
+            assert_eq!(de, conf);
+
+            let arr: Confidential<[u8; 4]> = serde_json::from_str(&json).unwrap();
+            assert_eq!(arr.value(), conf.value().as_slice());
+            assert_eq!(serde_json::to_string(&arr).unwrap(), json);
+
+            let upper: Confidential<Vec<u8>> = serde_json::from_str(r#""0012ABFF""#).unwrap();
+            assert_eq!(upper, conf);
+            let empty: Confidential<Vec<u8>> = serde_json::from_str(r#""""#).unwrap();
+            assert!(empty.value().is_empty());
+        }
+
+        #[test]
+        fn json_invalid() {
+            for json in [r#""0012abf""#, r#""0012abfg""#, "17"] {
+                let err = serde_json::from_str::<Confidential<Vec<u8>>>(json).unwrap_err();
+                assert!(!err.to_string().contains("0012"), "{err}");
+            }
+            assert!(serde_json::from_str::<Confidential<[u8; 3]>>(r#""0012abff""#).is_err());
+        }
+
+        #[test]
+        fn cbor() {
+            let conf = Confidential::new((0..=255u8).collect::<Vec<_>>());
+            let mut cbor = vec![];
+            ciborium::into_writer(&conf, &mut cbor).unwrap();
+            // major type 2 (byte string) with a two byte length
+            assert_eq!(&cbor[..3], &[0x59, 0x01, 0x00]);
+            assert_eq!(&cbor[3..], conf.value().as_slice());
+
+            let de: Confidential<Vec<u8>> = ciborium::from_reader(cbor.as_slice()).unwrap();
+            assert_eq!(de, conf);
+            let arr: Confidential<[u8; 256]> = ciborium::from_reader(cbor.as_slice()).unwrap();
+            assert_eq!(arr.value(), conf.value().as_slice());
+            assert!(ciborium::from_reader::<Confidential<[u8; 255]>, _>(cbor.as_slice()).is_err());
+        }
+
+        #[test]
+        fn seq() {
+            // sequences of numbers are accepted as well, e.g. from `Vec<u8>` serializations
+            let data: Vec<u8> = (0..100).collect();
+            let mut cbor = vec![];
+            ciborium::into_writer(&data, &mut cbor).unwrap();
+            let de: Confidential<Vec<u8>> = ciborium::from_reader(cbor.as_slice()).unwrap();
+            assert_eq!(de.value(), &data);
+        }
+
+        #[test]
+        fn intermediate_zeroizing() {
+            fn zeroizing<Z: Zeroize>() {}
+            zeroizing::<<ConfidentialVisitor as Visitor<'static>>::Value>();
+            zeroizing::<Confidential<String>>();
+
+            let hex = hex_encode_confidential(&[0x12, 0x34]);
+            assert_eq!(hex.value(), "1234");
+            assert_eq!(hex.value().capacity(), 4);
+        }
+    }
 }
@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io,
+    mem::size_of,
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+};
+
+use log::info;
+
+/// Set the function that is called if confidential memory cannot be locked.
+///
+/// The hook is called at most once per process, for the first failed lock. The default hook logs
+/// the error at info level. The memory is used unlocked anyway.
+pub fn set_lock_warning_hook(hook: fn(&io::Error)) {
+    locks().hook = hook;
+}
+
+fn default_hook(e: &io::Error) {
+    info!("Cannot lock confidential memory, it might be swapped out: {e}");
+}
+
+fn mlock(addr: usize, len: usize) -> io::Result<()> {
+    // SAFETY: mlock only changes the attributes of the pages, it does not access the memory
+    match unsafe { libc::mlock(addr as *const libc::c_void, len) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn munlock(addr: usize, len: usize) {
+    // SAFETY: munlock only changes the attributes of the pages, it does not access the memory
+    // Nothing to do on failure, the pages were locked before
+    unsafe { libc::munlock(addr as *const libc::c_void, len) };
+}
+
+fn addr<C>(v: &C) -> usize {
+    v as *const C as usize
+}
+
+fn locks() -> MutexGuard<'static, PageLocks> {
+    static LOCKS: OnceLock<Mutex<PageLocks>> = OnceLock::new();
+    LOCKS
+        .get_or_init(|| {
+            // SAFETY: no preconditions
+            let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            Mutex::new(PageLocks::new(page_size.try_into().unwrap_or(4096)))
+        })
+        .lock()
+        // the bookkeeping is always consistent, unlocking must be possible in drop
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Reference counts of the locked pages.
+///
+/// `munlock` unlocks a page regardless how many values on that page were locked.
+#[derive(Debug)]
+struct PageLocks {
+    page_size: usize,
+    pages: BTreeMap<usize, usize>,
+    warned: bool,
+    hook: fn(&io::Error),
+}
+
+impl PageLocks {
+    fn new(page_size: usize) -> Self {
+        Self {
+            page_size,
+            pages: BTreeMap::new(),
+            warned: false,
+            hook: default_hook,
+        }
+    }
+
+    /// Page aligned start and length of the pages containing the memory region
+    fn span(&self, addr: usize, len: usize) -> (usize, usize) {
+        let start = addr - addr % self.page_size;
+        let end = (addr + len).div_ceil(self.page_size) * self.page_size;
+        (start, end - start)
+    }
+
+    /// Locks the pages of the region; `false` if they cannot be locked.
+    fn lock<L>(&mut self, addr: usize, len: usize, mlock: L) -> bool
+    where
+        L: FnOnce(usize, usize) -> io::Result<()>,
+    {
+        if len == 0 {
+            return true;
+        }
+        let (start, len) = self.span(addr, len);
+        let mut pages = (start..start + len).step_by(self.page_size);
+        // already locked pages are not locked again, the rlimit counts them only once anyway
+        if !pages.all(|page| self.pages.contains_key(&page)) {
+            if let Err(e) = mlock(start, len) {
+                if !self.warned {
+                    self.warned = true;
+                    (self.hook)(&e);
+                }
+                return false;
+            }
+        }
+        for page in (start..start + len).step_by(self.page_size) {
+            *self.pages.entry(page).or_default() += 1;
+        }
+        true
+    }
+
+    /// Unlocks the pages of the region that contain no other locked region.
+    fn unlock<U>(&mut self, addr: usize, len: usize, mut munlock: U)
+    where
+        U: FnMut(usize, usize),
+    {
+        if len == 0 {
+            return;
+        }
+        let (start, len) = self.span(addr, len);
+        for page in (start..start + len).step_by(self.page_size) {
+            match self.pages.get_mut(&page) {
+                Some(1) => {
+                    self.pages.remove(&page);
+                    munlock(page, self.page_size);
+                }
+                Some(cnt) => *cnt -= 1,
+                None => {}
+            }
+        }
+    }
+}
+
+/// Heap storage of a [`Confidential`] whose memory is locked into RAM.
+///
+/// The pages of the value are locked with `mlock(2)` until the storage is dropped. The owning
+/// [`Confidential`] zeroizes the value before. If the memory cannot be locked, e.g. because
+/// `RLIMIT_MEMLOCK` is exhausted, the value is used unlocked and the hook of
+/// [`set_lock_warning_hook`] is called once.
+pub(super) struct LockedBox<C> {
+    value: Box<C>,
+    locked: bool,
+}
+
+impl<C> LockedBox<C> {
+    pub(super) fn new(v: C) -> Self {
+        let value = Box::new(v);
+        let locked = locks().lock(addr(value.as_ref()), size_of::<C>(), mlock);
+        Self { value, locked }
+    }
+
+    pub(super) fn value(&self) -> &C {
+        &self.value
+    }
+
+    pub(super) fn value_mut(&mut self) -> &mut C {
+        &mut self.value
+    }
+
+    pub(super) fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl<C> Drop for LockedBox<C> {
+    fn drop(&mut self) {
+        if self.locked {
+            locks().unlock(addr(self.value.as_ref()), size_of::<C>(), munlock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        collections::BTreeSet,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::request::{Confidential, Zeroize};
+
+    const PAGE: usize = 0x1000;
+
+    fn pages_of(&(addr, len): &(usize, usize)) -> impl Iterator<Item = usize> {
+        (addr..addr + len).step_by(PAGE)
+    }
+
+    /// Simulates `mlock` under a memlock rlimit of `limit` bytes
+    struct Rlimit {
+        limit: usize,
+        locked: RefCell<Vec<(usize, usize)>>,
+        unlocked: RefCell<Vec<(usize, usize)>>,
+    }
+
+    impl Rlimit {
+        fn new(limit: usize) -> Self {
+            Self {
+                limit,
+                locked: RefCell::new(vec![]),
+                unlocked: RefCell::new(vec![]),
+            }
+        }
+
+        fn mlock(&self) -> impl FnOnce(usize, usize) -> io::Result<()> + '_ {
+            |addr, len| {
+                let mut pages: BTreeSet<_> =
+                    self.locked.borrow().iter().flat_map(pages_of).collect();
+                pages.extend(pages_of(&(addr, len)));
+                if pages.len() * PAGE > self.limit {
+                    return Err(io::Error::from_raw_os_error(libc::ENOMEM));
+                }
+                self.locked.borrow_mut().push((addr, len));
+                Ok(())
+            }
+        }
+
+        fn munlock(&self) -> impl FnMut(usize, usize) + '_ {
+            |addr, len| self.unlocked.borrow_mut().push((addr, len))
+        }
+    }
+
+    #[test]
+    fn span() {
+        let locks = PageLocks::new(PAGE);
+        assert_eq!(locks.span(0x1000, 1), (0x1000, PAGE));
+        assert_eq!(locks.span(0x1010, 0x20), (0x1000, PAGE));
+        assert_eq!(locks.span(0x1ff0, 0x20), (0x1000, 2 * PAGE));
+        assert_eq!(locks.span(0x1000, 0x1000), (0x1000, PAGE));
+        assert_eq!(locks.span(0x1000, 0x1001), (0x1000, 2 * PAGE));
+    }
+
+    #[test]
+    fn bookkeeping() {
+        let mut locks = PageLocks::new(PAGE);
+        let rlimit = Rlimit::new(usize::MAX);
+
+        assert!(locks.lock(0x1010, 0x20, rlimit.mlock()));
+        assert!(locks.lock(0x1800, 0x1000, rlimit.mlock()));
+        assert!(locks.lock(0x5000, 0, rlimit.mlock()));
+        assert_eq!(
+            &*rlimit.locked.borrow(),
+            &[(0x1000, PAGE), (0x1000, 2 * PAGE)]
+        );
+        assert_eq!(locks.pages, [(0x1000, 2), (0x2000, 1)].into());
+
+        // the first page still contains the second region
+        locks.unlock(0x1010, 0x20, rlimit.munlock());
+        assert!(rlimit.unlocked.borrow().is_empty());
+        assert_eq!(locks.pages, [(0x1000, 1), (0x2000, 1)].into());
+
+        locks.unlock(0x1800, 0x1000, rlimit.munlock());
+        assert_eq!(
+            &*rlimit.unlocked.borrow(),
+            &[(0x1000, PAGE), (0x2000, PAGE)]
+        );
+        assert!(locks.pages.is_empty());
+
+        // unknown regions are ignored
+        locks.unlock(0x8000, 0x10, rlimit.munlock());
+        assert_eq!(rlimit.unlocked.borrow().len(), 2);
+    }
+
+    #[test]
+    fn rlimit_exhausted() {
+        static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+        fn hook(e: &io::Error) {
+            assert_eq!(e.raw_os_error(), Some(libc::ENOMEM));
+            WARNINGS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let mut locks = PageLocks::new(PAGE);
+        locks.hook = hook;
+        let rlimit = Rlimit::new(PAGE);
+
+        assert!(locks.lock(0x1000, 0x10, rlimit.mlock()));
+        assert!(!locks.lock(0x3000, 0x10, rlimit.mlock()));
+        assert!(!locks.lock(0x4000, 0x10, rlimit.mlock()));
+        // the first page does not count again
+        assert!(locks.lock(0x1100, 0x10, rlimit.mlock()));
+        assert_eq!(WARNINGS.load(Ordering::SeqCst), 1);
+        assert_eq!(locks.pages, [(0x1000, 2)].into());
+
+        // failed locks are never unlocked
+        locks.unlock(0x3000, 0x10, rlimit.munlock());
+        assert!(rlimit.unlocked.borrow().is_empty());
+    }
+
+    #[test]
+    fn locked_confidential() {
+        let mut conf = Confidential::new_locked([0x17u8; 32]);
+        assert_eq!(conf.value(), &[0x17; 32]);
+        let clone = conf.clone();
+        assert_eq!(clone, conf);
+        assert_eq!(clone.is_locked(), conf.is_locked());
+        assert!(!Confidential::new([0x17u8; 32]).is_locked());
+
+        conf.zeroize();
+        assert_eq!(conf.value(), &[0; 32]);
+        assert_ne!(clone, conf);
+        assert_eq!(clone.into_inner(), [0x17; 32]);
+    }
+}
@@ -38,6 +38,13 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[error("{} at `{path}` is larger than {}", sentence_case(ctx), size_str(*max_len))]
+    FileTooLarge {
+        ctx: String,
+        path: PathBuf,
+        max_len: usize,
+    },
+
     #[error("Cannot rename '{src}' to '{dst}'")]
     FileAccessRename {
         src: String,
@@ -45,12 +52,24 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[error(
+        "Cannot find the uvdevice. Tried: {}",
+        .0.iter().map(|p| format!("'{}'", p.display())).collect::<Vec<_>>().join(", ")
+    )]
+    UvDeviceNotFound(Vec<PathBuf>),
+
+    #[error("`{path}` is not the uvdevice: {reason}")]
+    InvUvDevice { path: PathBuf, reason: String },
+
     #[error("Cannot encode secrets (Too many secrets)")]
     ManySecrets,
 
     #[error("Cannot decode secret list")]
     InvSecretList(#[source] std::io::Error),
 
+    #[error("The secret store is full. It can hold at most {0} secrets")]
+    SecretStoreFull(usize),
+
     #[error("Input does not contain an add-secret request")]
     NoAsrcb,
 
@@ -119,10 +138,35 @@ pub enum Error {
     #[error("Cannot decode hex string")]
     InvHexStringChar { source: std::num::ParseIntError },
 
+    #[error("Invalid hex string: Odd length of {0} characters")]
+    HexOddLength(usize),
+
+    #[error("Invalid hex string: Invalid character at position {0}")]
+    HexInvalidChar(usize),
+
     #[error("Expected size {expected}, actual {actual}")]
     LengthMismatch { expected: usize, actual: usize },
 }
 
+/// Upper case the first character of `s`
+fn sentence_case(s: &str) -> String {
+    let mut chars = s.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// Format `size` in the largest binary unit it is an integral multiple of
+fn size_str(size: usize) -> String {
+    match size {
+        0 => "0 bytes".to_string(),
+        s if s % (1 << 30) == 0 => format!("{} GiB", s >> 30),
+        s if s % (1 << 20) == 0 => format!("{} MiB", s >> 20),
+        s if s % (1 << 10) == 0 => format!("{} KiB", s >> 10),
+        s => format!("{s} bytes"),
+    }
+}
+
 /// Error cases for I/O operations
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
@@ -6,6 +6,7 @@ mod apdevice;
 mod confidential;
 mod error;
 mod macros;
+mod summary;
 mod utils;
 mod uvattest;
 mod uvdevice;
@@ -20,10 +21,12 @@ pub mod attest {
 
 /// Miscellaneous functions and definitions
 pub mod misc {
+    pub use crate::summary::short_id;
     pub use crate::utils::pv_guest_bit_set;
     pub use crate::utils::{create_file, open_file};
-    pub use crate::utils::{decode_hex, encode_hex, parse_hex};
+    pub use crate::utils::{decode_hex, decode_hex_formatted, encode_hex, parse_hex};
     pub use crate::utils::{read, write};
+    pub use crate::utils::{read_confidential_file, read_file_limited, CONFIDENTIAL_FILE_MAX_LEN};
     pub use crate::utils::{read_exact_file, read_file, read_file_string, write_file};
     pub use crate::utils::{to_u16, to_u32, try_parse_u128, try_parse_u64};
     pub use crate::utils::{Flags, Lsb0Flags64, Msb0Flags64};
@@ -38,13 +41,25 @@ pub mod uv {
     pub use crate::uvdevice::retr_secret::RetrievableSecret;
     pub use crate::uvdevice::retr_secret::{AesSizes, AesXtsSizes, EcCurves, HmacShaSizes};
     pub use crate::uvdevice::secret::{AddCmd, ListCmd, LockCmd, RetrieveCmd};
-    pub use crate::uvdevice::secret_list::{ListableSecretType, SecretEntry, SecretId, SecretList};
-    pub use crate::uvdevice::{ConfigUid, UvCmd, UvDevice, UvDeviceInfo, UvFlags, UvcSuccess};
+    pub use crate::uvdevice::secret_list::{
+        ListableSecretType, SecretEntry, SecretId, SecretList, SecretStoreUsage,
+    };
+    #[cfg(feature = "async")]
+    pub use crate::uvdevice::AsyncUvDevice;
+    pub use crate::uvdevice::{
+        ConfigUid, UvCmd, UvCmdExecutor, UvDevice, UvDeviceInfo, UvFlags, UvInfo, UvcSuccess,
+    };
+    #[cfg(feature = "mock")]
+    pub use crate::uvdevice::{MockCmd, MockResponse, MockUvDevice};
 }
 
 /// Functionalities to verify UV requests
 pub mod request {
-    pub use crate::confidential::{Confidential, Zeroize};
+    pub use crate::confidential::{
+        hex_decode_confidential, hex_encode_confidential, set_lock_warning_hook, Confidential,
+        Zeroize,
+    };
+    pub use crate::summary::Summary;
     /// Version number of the request in system endianness
     pub type RequestVersion = u32;
     /// Request magic value
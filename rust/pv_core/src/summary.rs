@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use crate::uv::{SecretEntry, SecretId};
+
+/// Number of bytes shown by [`short_id`]
+const SHORT_ID_LEN: usize = 4;
+
+/// Short, log-safe one-line description of a value.
+///
+/// In contrast to [`Debug`], a summary never contains confidential data, e.g., secrets, keys, or
+/// nonces, not even in debug builds. It contains the type, version, sizes, and identifiers in
+/// the short form of [`short_id`]. Use it instead of [`Debug`] when logging requests, responses,
+/// and secrets.
+pub trait Summary {
+    /// Returns a short description of `self` without confidential data.
+    fn summary(&self) -> String;
+}
+
+/// Short form of a (non-confidential) identifier, hash, or tag.
+///
+/// Formats the first four bytes as hex with a `0x` prefix. Appends `...` if `id` is longer.
+///
+/// ```rust
+/// # use s390_pv_core::misc::short_id;
+/// assert_eq!(short_id(&[0xc0, 0xff, 0xee]), "0xc0ffee");
+/// assert_eq!(short_id(&[0x11; 32]), "0x11111111...");
+/// ```
+pub fn short_id(id: &[u8]) -> String {
+    let mut res = String::from("0x");
+    id.iter()
+        .take(SHORT_ID_LEN)
+        .for_each(|b| res.push_str(&format!("{b:02x}")));
+    if id.len() > SHORT_ID_LEN {
+        res.push_str("...");
+    }
+    res
+}
+
+impl Summary for SecretId {
+    fn summary(&self) -> String {
+        short_id(self.as_ref())
+    }
+}
+
+impl Summary for SecretEntry {
+    fn summary(&self) -> String {
+        format!(
+            "Secret {}: {} (ID {}, {} bytes)",
+            self.index(),
+            self.stype(),
+            short_id(self.id()),
+            self.secret_size()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uv::{ListableSecretType, RetrievableSecret};
+
+    #[test]
+    fn short_id_len() {
+        assert_eq!(short_id(&[]), "0x");
+        assert_eq!(short_id(&[1, 2, 3, 4]), "0x01020304");
+        assert_eq!(short_id(&[1, 2, 3, 4, 5]), "0x01020304...");
+    }
+
+    #[test]
+    fn secret_entry() {
+        let entry = SecretEntry::new(
+            7,
+            ListableSecretType::Retrievable(RetrievableSecret::PlainText),
+            [0xab; 32].into(),
+            42,
+        );
+        assert_eq!(
+            entry.summary(),
+            "Secret 7: PLAINTEXT (ID 0xabababab..., 42 bytes)"
+        );
+    }
+}
@@ -11,6 +11,7 @@ use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U64};
 
 use crate::{
     macros::{bail_spec, file_error},
+    request::Confidential,
     Error, FileAccessErrorType, Result,
 };
 
@@ -285,6 +286,112 @@ pub fn read_file<P: AsRef<Path>>(path: P, ctx: &str) -> Result<Vec<u8>> {
     std::fs::read(&path).map_err(|e| file_error!(Read, ctx, path, e))
 }
 
+/// Read content from a file with at most `max_len` bytes and add context in case of an error
+///
+/// * `path` - Path to file
+/// * `ctx` - Error context string in case of an error
+/// * `max_len` - Maximum size of the content
+///
+/// In contrast to [`read_file`] this never reads more than `max_len + 1` bytes, even from FIFOs
+/// or devices like `/dev/zero`.
+///
+/// # Errors
+/// Returns [`Error::FileTooLarge`] if the file is larger than `max_len` and passes through any
+/// kind of error `std::fs::read` produces
+pub fn read_file_limited<P: AsRef<Path>>(path: P, ctx: &str, max_len: usize) -> Result<Vec<u8>> {
+    let too_large = || Error::FileTooLarge {
+        ctx: ctx.to_string(),
+        path: path.as_ref().to_path_buf(),
+        max_len,
+    };
+    let f = open_file(&path)?;
+    let meta = f.metadata().map_err(|e| file_error!(Read, ctx, path, e))?;
+    if meta.is_file() && meta.len() > max_len as u64 {
+        return Err(too_large());
+    }
+
+    let mut buf = Vec::with_capacity(if meta.is_file() {
+        meta.len() as usize
+    } else {
+        0
+    });
+    // one more byte detects content beyond the limit of non-regular files
+    f.take(max_len as u64 + 1)
+        .read_to_end(&mut buf)
+        .map_err(|e| file_error!(Read, ctx, path, e))?;
+    if buf.len() > max_len {
+        return Err(too_large());
+    }
+    Ok(buf)
+}
+
+/// Maximum size of a file read by [`read_confidential_file`] without an exact length
+pub const CONFIDENTIAL_FILE_MAX_LEN: usize = 1 << 20;
+
+/// Read confidential content, like key material, from a file and add context in case of an error
+///
+/// * `path` - Path to file
+/// * `ctx` - Error context string in case of an error
+/// * `exact_len` - Required size of the content, if any
+///
+/// The content is read into a buffer that is zeroized on drop; no other copies of the content are
+/// left in memory. Without `exact_len` the content must not be larger than
+/// [`CONFIDENTIAL_FILE_MAX_LEN`].
+///
+/// # Errors
+/// Returns an error if the file has not exactly `exact_len` bytes or is larger than
+/// [`CONFIDENTIAL_FILE_MAX_LEN`] and passes through any kind of error `std::fs::read` produces
+pub fn read_confidential_file<P: AsRef<Path>>(
+    path: P,
+    ctx: &str,
+    exact_len: Option<usize>,
+) -> Result<Confidential<Vec<u8>>> {
+    let max_len = exact_len.unwrap_or(CONFIDENTIAL_FILE_MAX_LEN);
+    let mut f = open_file(&path)?;
+    let meta = f.metadata().map_err(|e| file_error!(Read, ctx, path, e))?;
+    let invalid_len = || match exact_len {
+        Some(len) => Error::Specification(format!("{ctx} must be exactly {len} bytes long")),
+        None => Error::FileTooLarge {
+            ctx: ctx.to_string(),
+            path: path.as_ref().to_path_buf(),
+            max_len,
+        },
+    };
+    if meta.is_file() && meta.len() > max_len as u64 {
+        return Err(invalid_len());
+    }
+
+    // never let the Vec reallocate; grow by copying into a new Confidential buffer instead
+    let hint = if meta.is_file() {
+        meta.len() as usize
+    } else {
+        0
+    };
+    let mut buf = Confidential::new(vec![0u8; hint.max(32) + 1]);
+    let mut filled = 0;
+    loop {
+        if filled == buf.value().len() {
+            if filled > max_len {
+                return Err(invalid_len());
+            }
+            let mut larger = Confidential::new(vec![0u8; (2 * filled).min(max_len + 1)]);
+            larger.value_mut()[..filled].copy_from_slice(&buf.value()[..filled]);
+            buf = larger;
+        }
+        match f.read(&mut buf.value_mut()[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(file_error!(Read, ctx, path, e)),
+        }
+    }
+    if filled > max_len || exact_len.is_some_and(|len| len != filled) {
+        return Err(invalid_len());
+    }
+    buf.value_mut().truncate(filled);
+    Ok(buf)
+}
+
 /// Reads all content from a [`std::io::Read`] and add context in case of an error
 ///
 /// * `path` - Path to file
@@ -384,6 +491,41 @@ pub fn decode_hex<S: AsRef<str>>(s: S) -> Result<Vec<u8>> {
         .collect()
 }
 
+/// Converts a formatted hexstring into a byte vector.
+///
+/// In addition to [`decode_hex`], accepts an optional `0x` prefix and bytes
+/// separated by colons, as in `0xaa:bb:cc`.
+///
+/// # Errors
+///
+/// Raises an error if a non-hex character or a misplaced separator was found
+/// or the number of digits was not a multiple of two.
+pub fn decode_hex_formatted<S: AsRef<str>>(s: S) -> Result<Vec<u8>> {
+    let hex = s.as_ref();
+    let start = match hex.starts_with("0x") || hex.starts_with("0X") {
+        true => 2,
+        false => 0,
+    };
+    let digits = &hex.as_bytes()[start..];
+    let separated = digits.contains(&b':');
+    if separated && digits.last() == Some(&b':') {
+        return Err(Error::HexInvalidChar(hex.len() - 1));
+    }
+
+    let mut nibbles = Vec::with_capacity(digits.len());
+    for (i, c) in digits.iter().enumerate() {
+        match (separated && i % 3 == 2, *c as char) {
+            (true, ':') => continue,
+            (false, c) if c.is_ascii_hexdigit() => nibbles.push(c.to_digit(16).unwrap() as u8),
+            _ => return Err(Error::HexInvalidChar(start + i)),
+        }
+    }
+    if nibbles.len() % 2 != 0 {
+        return Err(Error::HexOddLength(nibbles.len()));
+    }
+    Ok(nibbles.chunks_exact(2).map(|n| n[0] << 4 | n[1]).collect())
+}
+
 /// Converts the hexstring into a byte vector.
 ///
 /// Stops if the end or until a non hex chat is found
@@ -577,6 +719,54 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn decode_hex_formatted() {
+        assert_eq!(
+            super::decode_hex_formatted("c0ffee").unwrap(),
+            [0xc0, 0xff, 0xee]
+        );
+        assert_eq!(
+            super::decode_hex_formatted("0xC0fFeE").unwrap(),
+            [0xc0, 0xff, 0xee]
+        );
+        assert_eq!(
+            super::decode_hex_formatted("c0:ff:ee").unwrap(),
+            [0xc0, 0xff, 0xee]
+        );
+        assert_eq!(
+            super::decode_hex_formatted("0xc0:FF:ee").unwrap(),
+            [0xc0, 0xff, 0xee]
+        );
+        assert_eq!(super::decode_hex_formatted("").unwrap(), [0u8; 0]);
+        assert_eq!(super::decode_hex_formatted("0x").unwrap(), [0u8; 0]);
+
+        for (hex, pos) in [
+            (" ", 0),
+            ("coffee", 1),
+            ("c0 a", 2),
+            ("+a", 0),
+            ("a\u{e9}a", 1),
+            ("aa:b:cc", 4),
+            ("aa0b:cc", 2),
+            ("aa::cc", 3),
+            (":aa", 0),
+            ("aa:", 2),
+            ("0xaa:", 4),
+            ("aa:xz", 3),
+        ] {
+            match super::decode_hex_formatted(hex) {
+                Err(Error::HexInvalidChar(p)) => assert_eq!(p, pos, "{hex}"),
+                res => panic!("{hex}: {res:?}"),
+            }
+        }
+        for (hex, len) in [("c0a", 3), ("0xa", 1), ("aa:b", 3)] {
+            match super::decode_hex_formatted(hex) {
+                Err(Error::HexOddLength(l)) => assert_eq!(l, len, "{hex}"),
+                res => panic!("{hex}: {res:?}"),
+            }
+        }
+    }
+
     #[test]
     fn to_u32() {
         assert_eq!(Some(17), super::to_u32(17));
@@ -659,4 +849,130 @@ mod tests {
             try_parse_u128("00112233445566778899aabbccddeeff", "").unwrap()
         );
     }
+
+    /// Temporary directory for file reading tests
+    struct TestDir(std::path::PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("pv_core_read_{name}_{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        /// Regular file with `data`
+        fn file(&self, name: &str, data: &[u8]) -> std::path::PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        }
+
+        /// FIFO a thread writes `len` bytes into
+        fn fifo(&self, name: &str, len: usize) -> std::path::PathBuf {
+            let path = self.0.join(name);
+            let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+            assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+            let writer = path.clone();
+            std::thread::spawn(move || {
+                // the reader may close the FIFO early
+                let _ = File::create(writer).and_then(|mut f| f.write_all(&vec![0x42; len]));
+            });
+            path
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn read_limited() {
+        let dir = TestDir::new("limited");
+        let path = dir.file("small", &[1; 10]);
+        assert_eq!(read_file_limited(&path, "data", 10).unwrap(), [1; 10]);
+        assert!(matches!(
+            read_file_limited(&path, "data", 9),
+            Err(Error::FileTooLarge { max_len: 9, .. })
+        ));
+
+        // sparse; the size is rejected before reading
+        let path = dir.0.join("sparse");
+        File::create(&path).unwrap().set_len(1 << 40).unwrap();
+        let err = read_file_limited(&path, "add-secret request", 1 << 20).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Add-secret request at `{}` is larger than 1 MiB",
+                path.display()
+            )
+        );
+
+        assert_eq!(
+            read_file_limited(dir.fifo("fifo", 100), "data", 100).unwrap(),
+            [0x42; 100]
+        );
+        assert!(matches!(
+            read_file_limited(dir.fifo("fifo_large", 1 << 20), "data", 4096),
+            Err(Error::FileTooLarge { max_len: 4096, .. })
+        ));
+        assert!(matches!(
+            read_file_limited(dir.0.join("missing"), "data", 10),
+            Err(Error::FileAccess { .. })
+        ));
+    }
+
+    #[test]
+    fn read_confidential() {
+        let dir = TestDir::new("confidential");
+        let path = dir.file("key", &[3; 32]);
+        assert_eq!(
+            read_confidential_file(&path, "key", Some(32))
+                .unwrap()
+                .value(),
+            &[3; 32]
+        );
+        assert_eq!(
+            read_confidential_file(&path, "key", None).unwrap().value(),
+            &[3; 32]
+        );
+        for len in [31, 33] {
+            let err = read_confidential_file(&path, "key", Some(len)).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("key must be exactly {len} bytes long")
+            );
+        }
+
+        let path = dir.0.join("sparse");
+        File::create(&path)
+            .unwrap()
+            .set_len(CONFIDENTIAL_FILE_MAX_LEN as u64 + 1)
+            .unwrap();
+        assert!(matches!(
+            read_confidential_file(&path, "key", None),
+            Err(Error::FileTooLarge { .. })
+        ));
+
+        // FIFOs have no size; the buffer grows while reading
+        let key = read_confidential_file(dir.fifo("fifo", 1000), "key", None).unwrap();
+        assert_eq!(key.value(), &[0x42; 1000]);
+        let key = read_confidential_file(dir.fifo("fifo_exact", 64), "key", Some(64)).unwrap();
+        assert_eq!(key.value(), &[0x42; 64]);
+        assert!(matches!(
+            read_confidential_file(dir.fifo("fifo_short", 63), "key", Some(64)),
+            Err(Error::Specification(_))
+        ));
+        assert!(matches!(
+            read_confidential_file(dir.fifo("fifo_long", 1 << 20), "key", Some(64)),
+            Err(Error::Specification(_))
+        ));
+        assert!(matches!(
+            read_confidential_file(dir.fifo("fifo_large", 2 << 20), "key", None),
+            Err(Error::FileTooLarge { .. })
+        ));
+    }
 }
@@ -10,7 +10,12 @@ use std::{
     convert::TryInto,
     ffi::c_ulong,
     fs::File,
-    os::unix::prelude::{AsRawFd, RawFd},
+    io::ErrorKind,
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt},
+        prelude::{AsRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
 };
 
 #[cfg(not(test))]
@@ -20,8 +25,12 @@ use test::mock_libc::ioctl;
 
 /// Contains the rust representation of asm/uvdevice.h
 /// from kernel version: 6.5 verify
+#[cfg(feature = "async")]
+mod async_uv;
 mod ffi;
 mod info;
+#[cfg(feature = "mock")]
+mod mock;
 mod test;
 pub(crate) use ffi::uv_ioctl;
 pub mod attest;
@@ -29,7 +38,14 @@ pub mod retr_secret;
 pub mod secret;
 pub mod secret_list;
 
-pub use info::UvDeviceInfo;
+#[cfg(feature = "async")]
+pub use async_uv::AsyncUvDevice;
+pub use info::{UvDeviceInfo, UvInfo};
+#[cfg(feature = "mock")]
+pub use mock::{MockCmd, MockResponse, MockUvDevice};
+
+use attest::AttestationCmd;
+use secret::{AddCmd, ListCmd, LockCmd, RetrieveCmd};
 
 /// User data for the attestation UVC
 pub type AttestationUserData = [u8; ffi::UVIO_ATT_USER_DATA_LEN];
@@ -204,26 +220,100 @@ pub struct UvDevice(File);
 
 impl UvDevice {
     const PATH: &'static str = "/dev/uv";
+    /// Locations at which the uvdevice is searched if no path is specified.
+    const SEARCH_PATHS: &'static [&'static str] = &[Self::PATH, "/dev/misc/uv"];
+    /// sysfs attribute holding `major:minor` of the uvdevice misc device.
+    const SYSFS_DEV: &'static str = "/sys/class/misc/uv/dev";
+    const MISC_MAJOR: u32 = 10;
     const RC_MORE_DATA: u16 = 0x0100;
     const RC_SUCCESS: u16 = 0x0001;
 
     /// Open the uvdevice located at `/dev/uv`
     ///
+    /// If it does not exist, `/dev/misc/uv` is tried.
+    ///
     /// # Errors
     ///
-    /// This function will return an error if the device file cannot be opened.
+    /// This function will return an error if the device file cannot be opened, or the file is not
+    /// the uvdevice.
     pub fn open() -> Result<Self> {
-        Ok(Self(
-            std::fs::OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(Self::PATH)
-                .map_err(|e| Error::FileAccess {
-                    ty: FileAccessErrorType::Open,
-                    path: (Self::PATH).into(),
-                    source: e,
-                })?,
-        ))
+        Self::open_with(None::<&Path>, true)
+    }
+
+    /// Open the uvdevice located at `path`
+    ///
+    /// If `path` is [`None`] the same locations as for [`Self::open`] are tried. If `check` is
+    /// true, the file must be a character device with the major and minor number of the
+    /// uvdevice.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the device file cannot be opened, or `check` is
+    /// true and the file is not the uvdevice.
+    pub fn open_with<P: AsRef<Path>>(path: Option<P>, check: bool) -> Result<Self> {
+        if let Some(path) = path {
+            return Self::open_path(path.as_ref(), check);
+        }
+
+        let mut tried = Vec::with_capacity(Self::SEARCH_PATHS.len());
+        for path in Self::SEARCH_PATHS.iter().map(PathBuf::from) {
+            match Self::open_path(&path, check) {
+                Err(Error::FileAccess { source, .. }) if source.kind() == ErrorKind::NotFound => {
+                    debug!("No uvdevice at '{}'", path.display());
+                    tried.push(path);
+                }
+                res => return res,
+            }
+        }
+        Err(Error::UvDeviceNotFound(tried))
+    }
+
+    fn open_path(path: &Path, check: bool) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| Error::FileAccess {
+                ty: FileAccessErrorType::Open,
+                path: path.to_owned(),
+                source: e,
+            })?;
+        if check {
+            Self::check_dev(&file, path)?;
+        }
+        Ok(Self(file))
+    }
+
+    /// Verifies that `file` is a character device with the uvdevice major/minor.
+    ///
+    /// The uvdevice is a misc device with a dynamic minor. The minor is only checked if it is
+    /// available in sysfs.
+    fn check_dev(file: &File, path: &Path) -> Result<()> {
+        let inv_dev = |reason: String| Error::InvUvDevice {
+            path: path.to_owned(),
+            reason,
+        };
+        let meta = file.metadata()?;
+        if !meta.file_type().is_char_device() {
+            return Err(inv_dev("not a character device".to_string()));
+        }
+        // SAFETY: major and minor only do arithmetic on the passed value.
+        let (major, minor) = unsafe { (::libc::major(meta.rdev()), ::libc::minor(meta.rdev())) };
+        if major != Self::MISC_MAJOR {
+            return Err(inv_dev(format!(
+                "unexpected major number {major}, expected {}",
+                Self::MISC_MAJOR
+            )));
+        }
+        let exp_minor = std::fs::read_to_string(Self::SYSFS_DEV)
+            .ok()
+            .and_then(|dev| dev.trim().split_once(':')?.1.parse::<u32>().ok());
+        match exp_minor {
+            Some(exp) if exp != minor => Err(inv_dev(format!(
+                "unexpected minor number {minor}, expected {exp}"
+            ))),
+            _ => Ok(()),
+        }
     }
 
     /// Send an Ultravisor Command via this uvdevice.
@@ -253,3 +343,54 @@ impl UvDevice {
         }
     }
 }
+
+/// Executes the Ultravisor commands the tools need.
+///
+/// Implemented by [`UvDevice`]. Functions that issue Ultravisor commands take any
+/// [`UvCmdExecutor`], so that they can be tested with a mock (see `MockUvDevice` with the
+/// `mock` feature) on any architecture.
+///
+/// # Errors
+///
+/// All functions return an error if the command cannot be sent or the Ultravisor does not report
+/// a success, see [`UvDevice::send_cmd`].
+pub trait UvCmdExecutor {
+    /// Get information about the supported calls, see [`UvDeviceInfo::get`].
+    fn info(&self) -> Result<UvDeviceInfo>;
+    /// Send a _Retrieve Attestation Measurement_ UVC.
+    fn attest(&self, cmd: &mut AttestationCmd) -> Result<UvcSuccess>;
+    /// Send an _Add Secret_ UVC.
+    fn add_secret(&self, cmd: &mut AddCmd) -> Result<UvcSuccess>;
+    /// Send a _List Secrets_ UVC.
+    fn list_secrets(&self, cmd: &mut ListCmd) -> Result<UvcSuccess>;
+    /// Send a _Lock Secret Store_ UVC.
+    fn lock_secrets(&self, cmd: &mut LockCmd) -> Result<UvcSuccess>;
+    /// Send a _Retrieve Secret_ UVC.
+    fn retrieve_secret(&self, cmd: &mut RetrieveCmd) -> Result<UvcSuccess>;
+}
+
+impl UvCmdExecutor for UvDevice {
+    fn info(&self) -> Result<UvDeviceInfo> {
+        UvDeviceInfo::get(self)
+    }
+
+    fn attest(&self, cmd: &mut AttestationCmd) -> Result<UvcSuccess> {
+        self.send_cmd(cmd)
+    }
+
+    fn add_secret(&self, cmd: &mut AddCmd) -> Result<UvcSuccess> {
+        self.send_cmd(cmd)
+    }
+
+    fn list_secrets(&self, cmd: &mut ListCmd) -> Result<UvcSuccess> {
+        self.send_cmd(cmd)
+    }
+
+    fn lock_secrets(&self, cmd: &mut LockCmd) -> Result<UvcSuccess> {
+        self.send_cmd(cmd)
+    }
+
+    fn retrieve_secret(&self, cmd: &mut RetrieveCmd) -> Result<UvcSuccess> {
+        self.send_cmd(cmd)
+    }
+}
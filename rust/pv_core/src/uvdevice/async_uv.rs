@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use super::{
+    AddCmd, AttestationCmd, ListCmd, LockCmd, RetrieveCmd, UvCmdExecutor, UvDevice, UvDeviceInfo,
+    UvcSuccess,
+};
+use crate::{uv::SecretList, Error, Result};
+use std::{io::ErrorKind, sync::Arc};
+
+/// Asynchronous interface to the uvdevice
+///
+/// Every call runs the blocking IOCTL of the wrapped [`UvCmdExecutor`] with
+/// [`tokio::task::spawn_blocking`], so that it does not block the threads of the async runtime.
+/// The behavior of the calls does not change. Commands with output data are moved into the
+/// blocking task and are returned together with the result.
+///
+/// Requires a tokio runtime. Cloning is cheap, all clones share the same executor.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use s390_pv_core::uv::AsyncUvDevice;
+/// # async fn example() -> s390_pv_core::Result<()> {
+/// let uv = AsyncUvDevice::open().await?;
+/// let list = uv.secret_list().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncUvDevice<U = UvDevice>(Arc<U>);
+
+impl<U> Clone for AsyncUvDevice<U> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Runs `f` on the blocking thread pool of tokio
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(Error::Io(std::io::Error::new(ErrorKind::Interrupted, e))),
+    }
+}
+
+impl AsyncUvDevice {
+    /// Open the uvdevice, see [`UvDevice::open`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the device file cannot be opened, or the file is not
+    /// the uvdevice.
+    pub async fn open() -> Result<Self> {
+        Ok(Self::new(blocking(UvDevice::open).await?))
+    }
+}
+
+impl<U: UvCmdExecutor + Send + Sync + 'static> AsyncUvDevice<U> {
+    /// Creates a new [`AsyncUvDevice`] that sends the commands to `uv`.
+    pub fn new(uv: U) -> Self {
+        Self(Arc::new(uv))
+    }
+
+    /// Runs `f` with the executor on the blocking thread pool
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&U) -> Result<T> + Send + 'static,
+    {
+        let uv = self.0.clone();
+        blocking(move || f(&uv)).await
+    }
+
+    /// Get information about the supported calls, see [`UvCmdExecutor::info`].
+    pub async fn info(&self) -> Result<UvDeviceInfo> {
+        self.run(|uv| uv.info()).await
+    }
+
+    /// Send a _Retrieve Attestation Measurement_ UVC, see [`UvCmdExecutor::attest`].
+    pub async fn attest(&self, mut cmd: AttestationCmd) -> Result<(AttestationCmd, UvcSuccess)> {
+        self.run(move |uv| uv.attest(&mut cmd).map(|rc| (cmd, rc)))
+            .await
+    }
+
+    /// Send an _Add Secret_ UVC, see [`UvCmdExecutor::add_secret`].
+    pub async fn add_secret(&self, mut cmd: AddCmd) -> Result<UvcSuccess> {
+        self.run(move |uv| uv.add_secret(&mut cmd)).await
+    }
+
+    /// Send a _List Secrets_ UVC, see [`UvCmdExecutor::list_secrets`].
+    pub async fn list_secrets(&self, mut cmd: ListCmd) -> Result<(ListCmd, UvcSuccess)> {
+        self.run(move |uv| uv.list_secrets(&mut cmd).map(|rc| (cmd, rc)))
+            .await
+    }
+
+    /// Send a _Lock Secret Store_ UVC, see [`UvCmdExecutor::lock_secrets`].
+    pub async fn lock_secrets(&self) -> Result<UvcSuccess> {
+        self.run(|uv| uv.lock_secrets(&mut LockCmd)).await
+    }
+
+    /// Send a _Retrieve Secret_ UVC, see [`UvCmdExecutor::retrieve_secret`].
+    pub async fn retrieve_secret(&self, mut cmd: RetrieveCmd) -> Result<(RetrieveCmd, UvcSuccess)> {
+        self.run(move |uv| uv.retrieve_secret(&mut cmd).map(|rc| (cmd, rc)))
+            .await
+    }
+
+    /// Perform an attestation and return the command with the response of the Ultravisor.
+    ///
+    /// See [`AttestationCmd::new_request`] for the arguments.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the arguments are invalid or the UVC fails.
+    pub async fn perform_attestation(
+        &self,
+        arcb: Box<[u8]>,
+        user_data: Option<Vec<u8>>,
+        exp_measurement: u32,
+        exp_additional: u32,
+    ) -> Result<AttestationCmd> {
+        let cmd = AttestationCmd::new_request(arcb, user_data, exp_measurement, exp_additional)?;
+        Ok(self.attest(cmd).await?.0)
+    }
+
+    /// Add the secret of the binary add-secret request `asrcb`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `asrcb` is no add-secret request or the UVC fails.
+    pub async fn add_secret_request(&self, asrcb: &[u8]) -> Result<UvcSuccess> {
+        let cmd = AddCmd::new(&mut &asrcb[..])?;
+        self.add_secret(cmd).await
+    }
+
+    /// Do a List Secrets UVC and decode the result, see [`SecretList::from_uv`].
+    pub async fn secret_list(&self) -> Result<SecretList> {
+        self.run(|uv| SecretList::from_uv(uv)).await
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::uv::{
+        ListableSecretType, MockCmd, MockResponse, MockUvDevice, SecretEntry, SecretId,
+    };
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// [`MockUvDevice`] whose calls block until `peers` calls are active at the same time
+    ///
+    /// Gives up after a few seconds, so that serialized calls fail instead of blocking forever.
+    struct BlockingUv {
+        uv: MockUvDevice,
+        peers: usize,
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    impl BlockingUv {
+        fn new(peers: usize) -> Self {
+            Self {
+                uv: MockUvDevice::new(),
+                peers,
+                active: AtomicUsize::new(0),
+                max_active: AtomicUsize::new(0),
+            }
+        }
+
+        fn call<T>(&self, f: impl FnOnce(&MockUvDevice) -> T) -> T {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            let start = Instant::now();
+            while self.max_active.load(Ordering::SeqCst) < self.peers
+                && start.elapsed() < Duration::from_secs(5)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            f(&self.uv)
+        }
+    }
+
+    impl UvCmdExecutor for BlockingUv {
+        fn info(&self) -> Result<UvDeviceInfo> {
+            self.call(|uv| uv.info())
+        }
+
+        fn attest(&self, cmd: &mut AttestationCmd) -> Result<UvcSuccess> {
+            self.call(|uv| uv.attest(cmd))
+        }
+
+        fn add_secret(&self, cmd: &mut AddCmd) -> Result<UvcSuccess> {
+            self.call(|uv| uv.add_secret(cmd))
+        }
+
+        fn list_secrets(&self, cmd: &mut ListCmd) -> Result<UvcSuccess> {
+            self.call(|uv| uv.list_secrets(cmd))
+        }
+
+        fn lock_secrets(&self, cmd: &mut LockCmd) -> Result<UvcSuccess> {
+            self.call(|uv| uv.lock_secrets(cmd))
+        }
+
+        fn retrieve_secret(&self, cmd: &mut RetrieveCmd) -> Result<UvcSuccess> {
+            self.call(|uv| uv.retrieve_secret(cmd))
+        }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn concurrent_calls() {
+        const PEERS: usize = 4;
+        let uv = AsyncUvDevice::new(BlockingUv::new(PEERS));
+        for _ in 0..PEERS {
+            uv.0.uv.respond(MockResponse::Attestation {
+                measurement: vec![0x11; 64],
+                additional: None,
+                cuid: [0x22; 16],
+            });
+        }
+        runtime().block_on(async {
+            let tasks: Vec<_> = (0..PEERS)
+                .map(|n| {
+                    let uv = uv.clone();
+                    tokio::spawn(async move {
+                        uv.perform_attestation(vec![n as u8; 16].into(), None, 64, 0)
+                            .await
+                    })
+                })
+                .collect();
+            for task in tasks {
+                let cmd = task.await.unwrap().unwrap();
+                assert_eq!(cmd.measurement(), [0x11; 64]);
+                assert_eq!(cmd.cuid(), &[0x22; 16]);
+            }
+        });
+        // more peers than worker threads were active at the same time
+        assert_eq!(uv.0.max_active.load(Ordering::SeqCst), PEERS);
+        assert_eq!(uv.0.uv.issued().len(), PEERS);
+    }
+
+    #[test]
+    fn flows() {
+        let entry = SecretEntry::new(
+            1,
+            ListableSecretType::Association,
+            SecretId::from([0x42; 32]),
+            32,
+        );
+        let uv = AsyncUvDevice::new(BlockingUv::new(1));
+        uv.0.uv
+            .respond(MockResponse::SecretList(SecretList::new(
+                1,
+                vec![entry.clone()],
+            )))
+            .respond(MockResponse::Success)
+            .respond(MockResponse::Error { rc: 0x0102, rrc: 0 });
+        runtime().block_on(async {
+            assert!(uv.info().await.unwrap().supp_uv_cmds.is_some());
+            let list = uv.secret_list().await.unwrap();
+            assert_eq!(list.iter().next(), Some(&entry));
+            uv.lock_secrets().await.unwrap();
+            assert!(matches!(
+                uv.lock_secrets().await,
+                Err(Error::Uv { rc: 0x0102, .. })
+            ));
+        });
+        assert_eq!(
+            uv.0.uv.issued(),
+            [
+                MockCmd::ListSecrets,
+                MockCmd::LockSecrets,
+                MockCmd::LockSecrets
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let uv = AsyncUvDevice::new(BlockingUv::new(1));
+        runtime().block_on(async {
+            assert!(matches!(
+                uv.add_secret_request(b"no request").await,
+                Err(Error::NoAsrcb)
+            ));
+            assert!(matches!(
+                uv.perform_attestation(vec![0; 16].into(), None, 0, 0).await,
+                Err(Error::AttDataSizeSmall { .. })
+            ));
+        });
+        // invalid arguments do not reach the Ultravisor
+        assert!(uv.0.uv.issued().is_empty());
+    }
+
+    #[test]
+    #[should_panic = "No canned response left"]
+    fn panic() {
+        let uv = AsyncUvDevice::new(BlockingUv::new(1));
+        let _ = runtime().block_on(uv.lock_secrets());
+    }
+}
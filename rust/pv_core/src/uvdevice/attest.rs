@@ -188,6 +188,40 @@ impl AttestationCmd {
     }
 }
 
+#[cfg(feature = "mock")]
+impl AttestationCmd {
+    /// Returns the user-data of the request.
+    pub(crate) fn user_data(&self) -> &[u8] {
+        &self.uvio_attest.user_data[..self.uvio_attest.user_data_len as usize]
+    }
+
+    /// Writes a response into the output buffers, as the Ultravisor would.
+    ///
+    /// Returns the UV return code in the error case if a buffer is too small.
+    pub(crate) fn set_response(
+        &mut self,
+        measurement: &[u8],
+        additional: Option<&[u8]>,
+        cuid: &ConfigUid,
+    ) -> std::result::Result<(), u16> {
+        // only the content of the buffers is changed, the pointers stay valid
+        self.measurement
+            .get_mut(..measurement.len())
+            .ok_or(0x010du16)?
+            .copy_from_slice(measurement);
+        if let Some(additional) = additional {
+            self.additional
+                .as_mut()
+                .and_then(|a| a.get_mut(..additional.len()))
+                .ok_or(0x010eu16)?
+                .copy_from_slice(additional);
+        }
+        self.uvio_attest.add_data_len = additional.map_or(0, |a| a.len() as u32);
+        self.uvio_attest.config_uid = *cuid;
+        Ok(())
+    }
+}
+
 impl UvCmd for AttestationCmd {
     const UV_IOCTL_NR: u8 = ffi::UVIO_IOCTL_ATT_NR;
 
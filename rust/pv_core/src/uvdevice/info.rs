@@ -4,11 +4,12 @@
 
 use super::ffi::{self, uvio_uvdev_info};
 use crate::{
+    macros::file_error,
     misc::{Flags, Lsb0Flags64},
-    uv::{UvCmd, UvDevice},
-    Result,
+    uv::{ListableSecretType, SecretStoreUsage, UvCmd, UvCmdExecutor, UvDevice, UvFlags},
+    Error, Result,
 };
-use std::fmt::Display;
+use std::{fmt::Display, io::ErrorKind, path::Path};
 use zerocopy::{AsBytes, FromZeroes};
 
 /// Information of supported functions by the uvdevice
@@ -24,10 +25,10 @@ use zerocopy::{AsBytes, FromZeroes};
 ///
 /// Note that bit 0 is always zero for `supp_uv_cmds`
 /// as there is no corresponding Info UV-call.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UvDeviceInfo {
-    supp_uvio_cmds: Lsb0Flags64,
-    supp_uv_cmds: Option<Lsb0Flags64>,
+    pub(super) supp_uvio_cmds: Lsb0Flags64,
+    pub(super) supp_uv_cmds: Option<Lsb0Flags64>,
 }
 
 impl UvDeviceInfo {
@@ -47,7 +48,7 @@ impl UvDeviceInfo {
         let mut cmd = uvio_uvdev_info::new_zeroed();
         match uv.send_cmd(&mut cmd) {
             Ok(_) => Ok(cmd.into()),
-            Err(crate::Error::Io(e)) if e.raw_os_error() == Some(libc::ENOTTY) => {
+            Err(Error::Io(e)) if e.raw_os_error() == Some(libc::ENOTTY) => {
                 let mut supp_uvio_cmds = Lsb0Flags64::default();
                 supp_uvio_cmds.set_bit(ffi::UVIO_IOCTL_ATT_NR);
 
@@ -89,6 +90,7 @@ fn nr_as_string(nr: u8) -> Option<&'static str> {
         ffi::UVIO_IOCTL_ADD_SECRET_NR => Some("Add Secret"),
         ffi::UVIO_IOCTL_LIST_SECRETS_NR => Some("List Secrets"),
         ffi::UVIO_IOCTL_LOCK_SECRETS_NR => Some("Lock Secret Store"),
+        ffi::UVIO_IOCTL_RETR_SECRET_NR => Some("Retrieve Secret"),
         _ => None,
     }
 }
@@ -107,8 +109,7 @@ fn parse_flags(uv_cmds: &Lsb0Flags64, f: &mut std::fmt::Formatter<'_>) -> std::f
         .collect();
     let num_supp_cmds = supp_cmds.len();
     if num_supp_cmds == 0 {
-        println!("None");
-        return Ok(());
+        return writeln!(f, "None");
     }
 
     for (n, cmd) in supp_cmds {
@@ -130,3 +131,470 @@ impl Display for UvDeviceInfo {
         }
     }
 }
+
+/// Bits of the Ultravisor-call facilities (MSB0 ordering over all words)
+const UVC_BIT_RETR_ATTEST: usize = 28;
+const UVC_BIT_ADD_SECRET: usize = 29;
+const UVC_BIT_LIST_SECRETS: usize = 30;
+const UVC_BIT_LOCK_SECRETS: usize = 31;
+const UVC_BIT_RETR_SECRET: usize = 33;
+
+fn uvc_as_string(bit: usize) -> Option<&'static str> {
+    match bit {
+        0 => Some("Query Ultravisor Information"),
+        1 => Some("Initialize Ultravisor"),
+        2 => Some("Create Secure Configuration"),
+        3 => Some("Destroy Secure Configuration"),
+        4 => Some("Create Secure CPU"),
+        5 => Some("Destroy Secure CPU"),
+        6 => Some("Convert to Secure Storage"),
+        7 => Some("Convert from Secure Storage"),
+        8 => Some("Set Shared Access"),
+        9 => Some("Remove Shared Access"),
+        11 => Some("Set Secure Configuration Parameters"),
+        13 => Some("Unpack"),
+        14 => Some("CPU Reset"),
+        15 => Some("CPU Reset Initial"),
+        16 => Some("Prepare Reset"),
+        17 => Some("CPU Reset Clear"),
+        18 => Some("CPU Set State"),
+        19 => Some("Unpin Page Shared"),
+        24 => Some("Dump Initialize"),
+        25 => Some("Dump Configuration Storage State"),
+        26 => Some("Dump CPU"),
+        27 => Some("Dump Complete"),
+        UVC_BIT_RETR_ATTEST => Some("Retrieve Attestation Measurement"),
+        UVC_BIT_ADD_SECRET => Some("Add Secret"),
+        UVC_BIT_LIST_SECRETS => Some("List Secrets"),
+        UVC_BIT_LOCK_SECRETS => Some("Lock Secret Store"),
+        UVC_BIT_RETR_SECRET => Some("Retrieve Secret"),
+        34 => Some("Query Keys"),
+        _ => None,
+    }
+}
+
+/// Information about the Ultravisor of this machine
+///
+/// Combines the [`UvDeviceInfo`] of the uvdevice with the Ultravisor query information the kernel
+/// provides at [`UvInfo::SYSFS_UV_QUERY`]. Each part is optional, as older kernels and firmware
+/// do not provide all of it.
+///
+/// The `supports_*` functions check the uvdevice and the Ultravisor. The Ultravisor part is taken
+/// from the uvdevice if it reports it and from the query information otherwise. If neither is
+/// available the call is assumed to be supported and the IOCTL will report an error.
+///
+/// All raw bits, including ones this library does not know, are kept and can be accessed via
+/// [`UvInfo::facilities`], [`UvInfo::secret_types_flags`], and [`UvInfo::att_pflags`].
+#[derive(Debug, Clone, Default)]
+pub struct UvInfo {
+    device: Option<UvDeviceInfo>,
+    facilities: Option<Vec<UvFlags>>,
+    secret_types: Option<UvFlags>,
+    att_pflags: Option<UvFlags>,
+    max_secrets: Option<usize>,
+}
+
+impl UvInfo {
+    /// Location of the Ultravisor query information
+    pub const SYSFS_UV_QUERY: &'static str = "/sys/firmware/uv/query";
+
+    /// Get the information from the uvdevice and the Ultravisor query information.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the info IOCTL fails (see [`UvDeviceInfo::get`]) or
+    /// if the query information exists but cannot be read or parsed.
+    pub fn query<U: UvCmdExecutor + ?Sized>(uv: &U) -> Result<Self> {
+        let device = uv.info()?;
+        Ok(Self {
+            device: Some(device),
+            ..Self::from_sysfs()?
+        })
+    }
+
+    /// Get the information from the Ultravisor query information only.
+    ///
+    /// Use this if no uvdevice is available, e.g. on older kernels. Whether the uvdevice supports
+    /// a call is unknown in this case.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the query information exists but cannot be read or
+    /// parsed.
+    pub fn from_sysfs() -> Result<Self> {
+        Self::from_sysfs_dir(Self::SYSFS_UV_QUERY)
+    }
+
+    /// Get the information from the Ultravisor query information in `dir`.
+    ///
+    /// See [`UvInfo::from_sysfs`].
+    pub fn from_sysfs_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let facilities = read_query(dir.join("facilities"))?
+            .map(|f| -> Result<Vec<UvFlags>> {
+                Ok(parse_hex_words(&f, "Ultravisor facilities")?
+                    .iter()
+                    .map(UvFlags::from)
+                    .collect())
+            })
+            .transpose()?;
+        let secret_types = read_query_word(dir, "supp_secret_types", "supported secret types")?;
+        let att_pflags = read_query_word(dir, "supp_att_pflags", "supported attestation flags")?;
+        let max_secrets = SecretStoreUsage::read_max_from(dir.join("max_secrets"))?;
+
+        Ok(Self {
+            device: None,
+            facilities,
+            secret_types,
+            att_pflags,
+            max_secrets,
+        })
+    }
+
+    fn supports(&self, nr: u8, uvc: usize) -> bool {
+        let device = self.device.as_ref();
+        if device.is_some_and(|d| !d.supp_uvio_cmds.is_set(nr)) {
+            return false;
+        }
+        let uv_cmds = device.and_then(|d| d.supp_uv_cmds.as_ref());
+        match (uv_cmds, &self.facilities) {
+            (Some(cmds), _) => cmds.is_set(nr),
+            (None, Some(facilities)) => facility_set(facilities, uvc),
+            (None, None) => true,
+        }
+    }
+
+    /// Returns `true` if the Retrieve Attestation Measurement call is supported.
+    pub fn supports_attestation(&self) -> bool {
+        self.supports(ffi::UVIO_IOCTL_ATT_NR, UVC_BIT_RETR_ATTEST)
+    }
+
+    /// Returns `true` if the Add Secret call is supported.
+    pub fn supports_add_secret(&self) -> bool {
+        self.supports(ffi::UVIO_IOCTL_ADD_SECRET_NR, UVC_BIT_ADD_SECRET)
+    }
+
+    /// Returns `true` if the List Secrets call is supported.
+    pub fn supports_list_secrets(&self) -> bool {
+        self.supports(ffi::UVIO_IOCTL_LIST_SECRETS_NR, UVC_BIT_LIST_SECRETS)
+    }
+
+    /// Returns `true` if the Lock Secret Store call is supported.
+    pub fn supports_lock_secrets(&self) -> bool {
+        self.supports(ffi::UVIO_IOCTL_LOCK_SECRETS_NR, UVC_BIT_LOCK_SECRETS)
+    }
+
+    /// Returns `true` if the Retrieve Secret call is supported.
+    pub fn supports_retrieve_secrets(&self) -> bool {
+        self.supports(ffi::UVIO_IOCTL_RETR_SECRET_NR, UVC_BIT_RETR_SECRET)
+    }
+
+    /// Returns the maximum number of secrets in the secret store.
+    ///
+    /// `None` if the Ultravisor does not report it.
+    pub fn max_secrets(&self) -> Option<usize> {
+        self.max_secrets
+    }
+
+    /// Returns the secret types the Ultravisor supports.
+    ///
+    /// Types this library does not know are reported as [`ListableSecretType::Unknown`]. Empty if
+    /// the Ultravisor does not report the supported types.
+    pub fn supported_secret_types(&self) -> Vec<ListableSecretType> {
+        let Some(types) = &self.secret_types else {
+            return vec![];
+        };
+        (0u8..64)
+            .filter(|bit| types.is_set(*bit))
+            .map(|bit| ListableSecretType::from(bit as u16))
+            .collect()
+    }
+
+    /// Returns the information of the uvdevice, if available.
+    pub fn device(&self) -> Option<&UvDeviceInfo> {
+        self.device.as_ref()
+    }
+
+    /// Returns the raw Ultravisor-call facilities, if available.
+    ///
+    /// Bit `n` of the facilities is bit `n % 64` of word `n / 64`.
+    pub fn facilities(&self) -> Option<&[UvFlags]> {
+        self.facilities.as_deref()
+    }
+
+    /// Returns the raw supported secret types, if available.
+    ///
+    /// Bit `n` is set if the secret type `n` is supported.
+    pub fn secret_types_flags(&self) -> Option<UvFlags> {
+        self.secret_types
+    }
+
+    /// Returns the raw supported plaintext attestation flags, if available.
+    pub fn att_pflags(&self) -> Option<UvFlags> {
+        self.att_pflags
+    }
+}
+
+fn facility_set(facilities: &[UvFlags], bit: usize) -> bool {
+    facilities
+        .get(bit / 64)
+        .is_some_and(|word| word.is_set((bit % 64) as u8))
+}
+
+/// Reads the query information at `path`; `None` if it does not exist
+fn read_query<P: AsRef<Path>>(path: P) -> Result<Option<String>> {
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(file_error!(Read, "Ultravisor query information", path, e)),
+    }
+}
+
+/// Reads the query information `name` in `dir` that consists of a single hex word
+fn read_query_word(dir: &Path, name: &str, subject: &str) -> Result<Option<UvFlags>> {
+    let Some(content) = read_query(dir.join(name))? else {
+        return Ok(None);
+    };
+    match parse_hex_words(&content, subject)?.as_slice() {
+        [word] => Ok(Some(word.into())),
+        _ => Err(Error::ParseError {
+            subject: subject.to_string(),
+            content: content.trim().to_string(),
+        }),
+    }
+}
+
+/// Parses whitespace separated hex words, with or without `0x` prefix
+fn parse_hex_words(content: &str, subject: &str) -> Result<Vec<u64>> {
+    content
+        .split_whitespace()
+        .map(|word| {
+            u64::from_str_radix(word.trim_start_matches("0x"), 16).map_err(|_| Error::ParseError {
+                subject: subject.to_string(),
+                content: content.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+impl Display for UvInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.device {
+            Some(device) => write!(f, "{device}")?,
+            None => writeln!(f, "uvdevice information not available")?,
+        }
+
+        write!(f, "Ultravisor facilities: ")?;
+        match &self.facilities {
+            Some(facilities) => {
+                let calls: Vec<_> = (0..facilities.len() * 64)
+                    .filter(|bit| facility_set(facilities, *bit))
+                    .map(|bit| match uvc_as_string(bit) {
+                        Some(s) => s.to_string(),
+                        None => format!("Unknown ({bit})"),
+                    })
+                    .collect();
+                let raw: Vec<_> = facilities
+                    .iter()
+                    .map(|word| format!("{:016x}", u64::from(*word)))
+                    .collect();
+                writeln!(f, "{}", raw.join(" "))?;
+                for call in calls {
+                    writeln!(f, "  {call}")?;
+                }
+            }
+            None => writeln!(f, "Data not available")?,
+        }
+
+        write!(f, "Maximum number of secrets: ")?;
+        match self.max_secrets {
+            Some(max) => writeln!(f, "{max}")?,
+            None => writeln!(f, "Data not available")?,
+        }
+
+        write!(f, "Supported secret types: ")?;
+        match self.secret_types {
+            Some(types) => {
+                writeln!(f, "{:016x}", u64::from(types))?;
+                for stype in self.supported_secret_types() {
+                    writeln!(f, "  {stype}")?;
+                }
+            }
+            None => writeln!(f, "Data not available")?,
+        }
+
+        write!(f, "Supported plaintext attestation flags: ")?;
+        match self.att_pflags {
+            Some(flags) => writeln!(f, "{:016x}", u64::from(flags)),
+            None => writeln!(f, "Data not available"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uv::RetrievableSecret;
+    use std::path::PathBuf;
+
+    const QUERY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/uv_query");
+
+    /// Synthetic query information of `machine`, see the README in [`QUERY_DIR`]
+    fn query(machine: &str) -> UvInfo {
+        UvInfo::from_sysfs_dir(format!("{QUERY_DIR}/{machine}")).unwrap()
+    }
+
+    fn device(uvio_cmds: u64, uv_cmds: Option<u64>) -> UvDeviceInfo {
+        UvDeviceInfo {
+            supp_uvio_cmds: uvio_cmds.into(),
+            supp_uv_cmds: uv_cmds.map(Lsb0Flags64::from),
+        }
+    }
+
+    /// Temporary directory with Ultravisor query information
+    struct QueryDir(PathBuf);
+
+    impl QueryDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("pv_core_uv_query_{name}_{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir(&path).unwrap();
+            for (file, content) in files {
+                std::fs::write(path.join(file), content).unwrap();
+            }
+            Self(path)
+        }
+    }
+
+    impl Drop for QueryDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn sysfs_z15() {
+        let info = query("z15");
+        assert!(!info.supports_attestation());
+        assert!(!info.supports_add_secret());
+        assert!(!info.supports_list_secrets());
+        assert!(!info.supports_lock_secrets());
+        assert!(!info.supports_retrieve_secrets());
+        assert_eq!(info.max_secrets(), None);
+        assert!(info.supported_secret_types().is_empty());
+        assert_eq!(info.facilities().unwrap().len(), 4);
+        assert_eq!(u64::from(info.att_pflags().unwrap()), 0);
+    }
+
+    #[test]
+    fn sysfs_z16() {
+        let info = query("z16");
+        assert!(info.supports_attestation());
+        assert!(info.supports_add_secret());
+        assert!(info.supports_list_secrets());
+        assert!(info.supports_lock_secrets());
+        assert!(!info.supports_retrieve_secrets());
+        assert_eq!(info.max_secrets(), Some(510));
+        assert_eq!(
+            info.supported_secret_types(),
+            [
+                ListableSecretType::Invalid(ListableSecretType::NULL),
+                ListableSecretType::Association
+            ]
+        );
+        assert_eq!(u64::from(info.att_pflags().unwrap()), 0x3000000000000000);
+    }
+
+    #[test]
+    fn sysfs_unknown_bits() {
+        let dir = QueryDir::new(
+            "unknown",
+            &[
+                ("facilities", "0000000040000000\n4000000000000001\n"),
+                ("supp_secret_types", "0x1000000000000001\n"),
+            ],
+        );
+        let info = UvInfo::from_sysfs_dir(&dir.0).unwrap();
+        assert!(info.supports_retrieve_secrets());
+        assert!(!info.supports_add_secret());
+        assert_eq!(
+            info.supported_secret_types(),
+            [
+                ListableSecretType::Retrievable(RetrievableSecret::PlainText),
+                ListableSecretType::Unknown(63)
+            ]
+        );
+        let flags: Vec<u64> = info
+            .facilities()
+            .unwrap()
+            .iter()
+            .map(|w| u64::from(*w))
+            .collect();
+        assert_eq!(flags, [0x40000000, 0x4000000000000001]);
+        assert_eq!(info.max_secrets(), None);
+        assert_eq!(info.att_pflags().map(u64::from), None);
+
+        let s = info.to_string();
+        assert!(s.contains("  Unknown (65)\n"), "{s}");
+        assert!(s.contains("  Retrieve Secret\n"), "{s}");
+        assert!(s.contains("  Unknown (127)\n"), "{s}");
+        assert!(s.contains("  Unknown(0x003f)\n"), "{s}");
+        assert!(s.contains("0000000040000000 4000000000000001\n"), "{s}");
+    }
+
+    #[test]
+    fn sysfs_missing() {
+        let dir = QueryDir::new("missing", &[]);
+        let info = UvInfo::from_sysfs_dir(&dir.0).unwrap();
+        assert!(info.facilities().is_none());
+        assert!(info.supports_add_secret());
+        assert!(info.supported_secret_types().is_empty());
+    }
+
+    #[test]
+    fn sysfs_invalid() {
+        let dir = QueryDir::new("invalid", &[("facilities", "xyz\n")]);
+        assert!(matches!(
+            UvInfo::from_sysfs_dir(&dir.0),
+            Err(Error::ParseError { content, .. }) if content == "xyz"
+        ));
+        let dir = QueryDir::new("invalid_word", &[("supp_att_pflags", "1\n2\n")]);
+        assert!(matches!(
+            UvInfo::from_sysfs_dir(&dir.0),
+            Err(Error::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn device_overrides_sysfs() {
+        let add = 1 << ffi::UVIO_IOCTL_ADD_SECRET_NR;
+        let att = 1 << ffi::UVIO_IOCTL_ATT_NR;
+
+        // the uvdevice cannot add secrets, even if the Ultravisor can
+        let info = UvInfo {
+            device: Some(device(att, None)),
+            ..query("z16")
+        };
+        assert!(info.supports_attestation());
+        assert!(!info.supports_add_secret());
+
+        // the uvdevice reports the Ultravisor support
+        let info = UvInfo {
+            device: Some(device(add | att, Some(add))),
+            ..query("z16")
+        };
+        assert!(info.supports_add_secret());
+        assert!(!info.supports_attestation());
+
+        // older uvdevice without info IOCTL on a z15
+        let info = UvInfo {
+            device: Some(device(att, None)),
+            ..query("z15")
+        };
+        assert!(!info.supports_attestation());
+        assert!(info
+            .to_string()
+            .starts_with("uvdevice supports:Attestation\n"));
+    }
+}
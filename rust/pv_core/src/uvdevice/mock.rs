@@ -0,0 +1,328 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use super::{
+    ffi, rc_fmt, AddCmd, AttestationCmd, ConfigUid, ListCmd, LockCmd, RetrieveCmd, UvCmd,
+    UvCmdExecutor, UvDeviceInfo, UvcSuccess,
+};
+use crate::{
+    misc::{Flags, Lsb0Flags64},
+    uv::SecretList,
+    Error, Result,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Ultravisor command issued to a [`MockUvDevice`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCmd {
+    /// _Retrieve Attestation Measurement_ with the request and user-data
+    Attestation {
+        /// Attestation request
+        arcb: Vec<u8>,
+        /// User-data
+        user_data: Vec<u8>,
+    },
+    /// _Add Secret_ with the add-secret request
+    AddSecret(Vec<u8>),
+    /// _List Secrets_
+    ListSecrets,
+    /// _Lock Secret Store_
+    LockSecrets,
+    /// _Retrieve Secret_ with the index of the secret
+    RetrieveSecret(u16),
+}
+
+/// Canned response of a [`MockUvDevice`]
+#[derive(Debug)]
+pub enum MockResponse {
+    /// Successful execution without output data
+    Success,
+    /// The Ultravisor reports the return code `rc` and the return reason code `rrc`
+    Error {
+        /// Return code
+        rc: u16,
+        /// Return reason code
+        rrc: u16,
+    },
+    /// Output of the _Retrieve Attestation Measurement_ UVC
+    Attestation {
+        /// Attestation measurement
+        measurement: Vec<u8>,
+        /// Additional-data
+        additional: Option<Vec<u8>>,
+        /// Configuration Unique ID
+        cuid: ConfigUid,
+    },
+    /// Output of the _List Secrets_ UVC
+    SecretList(SecretList),
+    /// Output of the _Retrieve Secret_ UVC
+    Secret(Vec<u8>),
+}
+
+/// Mock of the [`UvDevice`](super::UvDevice) for tests
+///
+/// Records every issued command and answers with the canned responses in the order they were
+/// added with [`MockUvDevice::respond`]. By default all calls are supported.
+///
+/// # Panics
+///
+/// A command panics if there is no canned response left or the response does not fit the
+/// command.
+#[derive(Debug)]
+pub struct MockUvDevice {
+    info: UvDeviceInfo,
+    responses: Mutex<VecDeque<MockResponse>>,
+    issued: Mutex<Vec<MockCmd>>,
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    match m.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl MockUvDevice {
+    /// Creates a new [`MockUvDevice`] that supports all calls and has no canned responses.
+    pub fn new() -> Self {
+        let mut uvio_cmds = Lsb0Flags64::default();
+        let mut uv_cmds = Lsb0Flags64::default();
+        uvio_cmds.set_bit(ffi::UVIO_IOCTL_UVDEV_INFO_NR);
+        for nr in [
+            ffi::UVIO_IOCTL_ATT_NR,
+            ffi::UVIO_IOCTL_ADD_SECRET_NR,
+            ffi::UVIO_IOCTL_LIST_SECRETS_NR,
+            ffi::UVIO_IOCTL_LOCK_SECRETS_NR,
+            ffi::UVIO_IOCTL_RETR_SECRET_NR,
+        ] {
+            uvio_cmds.set_bit(nr);
+            uv_cmds.set_bit(nr);
+        }
+        Self {
+            info: UvDeviceInfo {
+                supp_uvio_cmds: uvio_cmds,
+                supp_uv_cmds: Some(uv_cmds),
+            },
+            responses: Mutex::new(VecDeque::new()),
+            issued: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Marks the command `C` as not supported by the Ultravisor.
+    pub fn unsupported<C: UvCmd>(mut self) -> Self {
+        if let Some(uv_cmds) = &mut self.info.supp_uv_cmds {
+            uv_cmds.unset_bit(C::UV_IOCTL_NR);
+        }
+        self
+    }
+
+    /// Adds a canned response for the next command.
+    pub fn respond(&self, response: MockResponse) -> &Self {
+        lock(&self.responses).push_back(response);
+        self
+    }
+
+    /// Returns the commands issued so far.
+    pub fn issued(&self) -> Vec<MockCmd> {
+        lock(&self.issued).clone()
+    }
+
+    /// Records `issued` and returns the next canned response.
+    fn next(&self, issued: MockCmd) -> MockResponse {
+        let name = format!("{issued:?}");
+        lock(&self.issued).push(issued);
+        lock(&self.responses)
+            .pop_front()
+            .unwrap_or_else(|| panic!("No canned response left for {name}"))
+    }
+}
+
+impl Default for MockUvDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn uv_error<C: UvCmd>(rc: u16, rrc: u16, cmd: &mut C) -> Error {
+    Error::Uv {
+        rc,
+        rrc,
+        msg: rc_fmt(rc, rrc, cmd),
+    }
+}
+
+/// Copies `data` into the data of `cmd`
+fn copy_out<C: UvCmd>(cmd: &mut C, data: &[u8]) {
+    let buf = cmd.data().expect("Command has no output data");
+    assert!(data.len() <= buf.len(), "Canned response too large");
+    buf[..data.len()].copy_from_slice(data);
+}
+
+/// Answers `cmd` with `response` if the response has no output data
+fn answer<C: UvCmd>(cmd: &mut C, response: MockResponse) -> Result<UvcSuccess> {
+    match response {
+        MockResponse::Success => Ok(UvcSuccess::RC_SUCCESS),
+        MockResponse::Error { rc, rrc } => Err(uv_error(rc, rrc, cmd)),
+        r => panic!("Unexpected canned response {r:?}"),
+    }
+}
+
+impl UvCmdExecutor for MockUvDevice {
+    fn info(&self) -> Result<UvDeviceInfo> {
+        Ok(self.info.clone())
+    }
+
+    fn attest(&self, cmd: &mut AttestationCmd) -> Result<UvcSuccess> {
+        let issued = MockCmd::Attestation {
+            arcb: cmd.arcb().to_vec(),
+            user_data: cmd.user_data().to_vec(),
+        };
+        match self.next(issued) {
+            MockResponse::Attestation {
+                measurement,
+                additional,
+                cuid,
+            } => match cmd.set_response(&measurement, additional.as_deref(), &cuid) {
+                Ok(()) => Ok(UvcSuccess::RC_SUCCESS),
+                Err(rc) => Err(uv_error(rc, 0, cmd)),
+            },
+            r => answer(cmd, r),
+        }
+    }
+
+    fn add_secret(&self, cmd: &mut AddCmd) -> Result<UvcSuccess> {
+        let issued = MockCmd::AddSecret(cmd.data().unwrap_or_default().to_vec());
+        let response = self.next(issued);
+        answer(cmd, response)
+    }
+
+    fn list_secrets(&self, cmd: &mut ListCmd) -> Result<UvcSuccess> {
+        match self.next(MockCmd::ListSecrets) {
+            MockResponse::SecretList(list) => {
+                let mut data = Vec::new();
+                list.encode(&mut data)?;
+                copy_out(cmd, &data);
+                Ok(UvcSuccess::RC_SUCCESS)
+            }
+            r => answer(cmd, r),
+        }
+    }
+
+    fn lock_secrets(&self, cmd: &mut LockCmd) -> Result<UvcSuccess> {
+        let response = self.next(MockCmd::LockSecrets);
+        answer(cmd, response)
+    }
+
+    fn retrieve_secret(&self, cmd: &mut RetrieveCmd) -> Result<UvcSuccess> {
+        match self.next(MockCmd::RetrieveSecret(cmd.meta_data().index())) {
+            MockResponse::Secret(secret) => {
+                copy_out(cmd, &secret);
+                Ok(UvcSuccess::RC_SUCCESS)
+            }
+            r => answer(cmd, r),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uv::{ListableSecretType, SecretEntry, SecretId, UvInfo};
+
+    #[test]
+    fn attest() {
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::Attestation {
+            measurement: vec![0x11; 64],
+            additional: Some(vec![0x22; 32]),
+            cuid: [0x33; 16],
+        })
+        .respond(MockResponse::Attestation {
+            measurement: vec![0x11; 65],
+            additional: None,
+            cuid: [0x33; 16],
+        });
+
+        let mut cmd =
+            AttestationCmd::new_request(vec![1, 2, 3].into(), Some(vec![4, 5]), 64, 64).unwrap();
+        assert_eq!(uv.attest(&mut cmd).unwrap(), UvcSuccess::RC_SUCCESS);
+        assert_eq!(cmd.measurement(), [0x11; 64]);
+        assert_eq!(cmd.additional_owned().unwrap(), [0x22; 32]);
+        assert_eq!(cmd.cuid(), &[0x33; 16]);
+
+        // the measurement does not fit
+        let mut cmd = AttestationCmd::new_request(vec![1].into(), None, 64, 0).unwrap();
+        assert!(matches!(
+            uv.attest(&mut cmd),
+            Err(Error::Uv { rc: 0x010d, .. })
+        ));
+
+        assert_eq!(
+            uv.issued(),
+            [
+                MockCmd::Attestation {
+                    arcb: vec![1, 2, 3],
+                    user_data: vec![4, 5]
+                },
+                MockCmd::Attestation {
+                    arcb: vec![1],
+                    user_data: vec![]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn secrets() {
+        let entry = SecretEntry::new(
+            7,
+            ListableSecretType::Association,
+            SecretId::from([0x42; 32]),
+            32,
+        );
+        let list = SecretList::new(1, vec![entry.clone()]);
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::SecretList(list))
+            .respond(MockResponse::Error { rc: 0x0102, rrc: 0 })
+            .respond(MockResponse::Success);
+
+        let list = SecretList::from_uv(&uv).unwrap();
+        assert_eq!(list.total_num_secrets(), 1);
+        assert_eq!(list.iter().next(), Some(&entry));
+
+        let err = uv.lock_secrets(&mut LockCmd).unwrap_err();
+        assert!(
+            err.to_string().contains("secret store already locked"),
+            "{err}"
+        );
+        uv.lock_secrets(&mut LockCmd).unwrap();
+
+        assert_eq!(
+            uv.issued(),
+            [
+                MockCmd::ListSecrets,
+                MockCmd::LockSecrets,
+                MockCmd::LockSecrets
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let uv = MockUvDevice::new().unsupported::<AddCmd>();
+        let info = UvInfo::query(&uv).unwrap();
+        assert!(!info.supports_add_secret());
+        assert!(info.supports_list_secrets());
+        assert!(uv.issued().is_empty());
+    }
+
+    #[test]
+    #[should_panic = "No canned response left"]
+    fn no_response() {
+        let _ = MockUvDevice::new().lock_secrets(&mut LockCmd);
+    }
+}
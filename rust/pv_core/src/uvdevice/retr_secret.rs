@@ -215,7 +215,8 @@ impl RetrievableSecret {
     /// Report expected input types
     pub fn expected(&self) -> String {
         match self {
-            Self::PlainText => format!("less than {}", RetrieveCmd::MAX_SIZE),
+            // the payload is preceded by its size (2 bytes)
+            Self::PlainText => format!("at most {} bytes", RetrieveCmd::MAX_SIZE - 2),
             Self::Aes(_) => "128, 192, or 256".to_string(),
             Self::AesXts(_) => "128 or 256".to_string(),
             Self::HmacSha(_) => "256 or 512".to_string(),
@@ -67,6 +67,9 @@ impl UvCmd for ListCmd {
 pub struct AddCmd(Vec<u8>);
 
 impl AddCmd {
+    /// Maximum size of an add-secret request
+    pub const MAX_SIZE: usize = ffi::UVIO_ADD_SECRET_MAX_LEN;
+
     /// Create a new Add Secret command using the provided data.
     ///
     /// # Errors
@@ -75,9 +78,12 @@ impl AddCmd {
     /// with the `AddSecretRequest` magic Value.
     pub fn new<R: Read>(bin_add_secret_req: &mut R) -> Result<Self> {
         let mut data = Vec::with_capacity(PAGESIZE);
-        bin_add_secret_req.read_to_end(&mut data)?;
+        // one more byte detects larger requests
+        bin_add_secret_req
+            .take(Self::MAX_SIZE as u64 + 1)
+            .read_to_end(&mut data)?;
 
-        if data.len() > ffi::UVIO_ADD_SECRET_MAX_LEN {
+        if data.len() > Self::MAX_SIZE {
             return Err(Error::AscrbLarge);
         }
 
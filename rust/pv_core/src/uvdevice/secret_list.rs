@@ -4,19 +4,23 @@
 
 use crate::{
     assert_size,
+    macros::file_error,
     uv::{AesSizes, AesXtsSizes, EcCurves, HmacShaSizes, ListCmd, RetrievableSecret},
-    uvdevice::UvCmd,
+    uvdevice::{UvCmd, UvCmdExecutor},
     Error, Result,
 };
 use byteorder::{BigEndian, ByteOrder};
-use serde::{Deserialize, Serialize, Serializer};
+use log::{info, warn};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use std::{
     cmp::min,
     ffi::CStr,
     fmt::{Debug, Display, LowerHex, UpperHex},
-    io::{Cursor, Read, Seek, Write},
+    io::{Cursor, ErrorKind, Read, Seek, Write},
     mem::size_of,
+    path::Path,
     slice::Iter,
+    str::FromStr,
     vec::IntoIter,
 };
 use zerocopy::{AsBytes, FromBytes, FromZeroes, U16, U32};
@@ -64,6 +68,14 @@ impl SecretId {
         };
         None
     }
+
+    /// Returns `true` if the hex representation of the Id starts with `prefix`
+    ///
+    /// `prefix` may start with `0x` and is compared case-insensitive.
+    pub fn has_hex_prefix(&self, prefix: &str) -> bool {
+        let prefix = prefix.strip_prefix("0x").unwrap_or(prefix);
+        format!("{self:x}").starts_with(&prefix.to_ascii_lowercase())
+    }
 }
 
 impl Serialize for SecretId {
@@ -131,16 +143,15 @@ impl AsRef<[u8]> for SecretId {
 }
 
 /// A secret in a [`SecretList`]
+///
+/// Serializes the secret type as its raw code (`stype`) and its name (`stype_name`). Unknown
+/// types keep their code, e.g., for types introduced by newer firmware.
 #[repr(C)]
-#[derive(Debug, Clone, PartialEq, Eq, AsBytes, FromZeroes, FromBytes, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, AsBytes, FromZeroes, FromBytes)]
 pub struct SecretEntry {
-    #[serde(serialize_with = "ser_u16")]
     index: U16<BigEndian>,
-    #[serde(serialize_with = "ser_u16")]
     stype: U16<BigEndian>,
-    #[serde(serialize_with = "ser_u32")]
     len: U32<BigEndian>,
-    #[serde(skip)]
     res_8: u64,
     id: SecretId,
 }
@@ -192,7 +203,7 @@ impl SecretEntry {
     }
 
     /// Get the id as [`SecretId`] reference
-    pub(crate) fn secret_id(&self) -> &SecretId {
+    pub fn secret_id(&self) -> &SecretId {
         &self.id
     }
 
@@ -202,6 +213,21 @@ impl SecretEntry {
     }
 }
 
+impl Serialize for SecretEntry {
+    fn serialize<S>(&self, ser: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entry = ser.serialize_struct("SecretEntry", 5)?;
+        entry.serialize_field("index", &self.index())?;
+        entry.serialize_field("stype", &self.stype.get())?;
+        entry.serialize_field("stype_name", &self.stype().to_string())?;
+        entry.serialize_field("len", &self.secret_size())?;
+        entry.serialize_field("id", &self.id)?;
+        entry.end()
+    }
+}
+
 impl Display for SecretEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let stype: ListableSecretType = self.stype.get().into();
@@ -241,6 +267,7 @@ assert_size!(SecretListHdr, 16);
 /// List of secrets used to parse the [`crate::uv::ListCmd`] result.
 ///
 /// The list should ONLY be created from an UV-Call result using either:
+/// - [`SecretList::from_uv`]
 /// - [`TryInto::try_into`] from [`ListCmd`]
 /// - [`SecretList::decode`]
 /// Any other ways can create invalid lists that do not represent the UV secret store.
@@ -318,6 +345,14 @@ impl SecretList {
         self.hdr.total_num_secrets.get() as usize
     }
 
+    /// Retains only the [`SecretEntry`]s specified by the predicate.
+    ///
+    /// The total number of secrets in the UV stays untouched.
+    pub fn retain<F: FnMut(&SecretEntry) -> bool>(&mut self, f: F) {
+        self.secrets.retain(f);
+        self.hdr.num_secrets_stored = (self.secrets.len() as u16).into();
+    }
+
     /// Find the first [`SecretEntry`] that has the provided [`SecretId`]
     pub fn find(&self, id: &SecretId) -> Option<SecretEntry> {
         self.iter().find(|e| e.id() == id.as_ref()).cloned()
@@ -353,6 +388,40 @@ impl SecretList {
     }
 }
 
+impl SecretList {
+    /// Number of pages for the list of secrets
+    ///
+    /// Large enough for the maximum list size supported by the uvdevice.
+    const LIST_PAGES: usize = 8;
+
+    /// Do a List Secrets UVC and decode the result
+    ///
+    /// Requests a list larger than one page. Falls back to a one page list if the uvdevice does
+    /// not support longer lists. If the Ultravisor reports more secrets than fit into the list,
+    /// a warning is logged and the list holds only the first ones.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the UVC fails or the result is no valid list.
+    pub fn from_uv<U: UvCmdExecutor + ?Sized>(uv: &U) -> Result<Self> {
+        let mut cmd = ListCmd::with_pages(Self::LIST_PAGES);
+        let more_data = match uv.list_secrets(&mut cmd) {
+            Ok(v) => Ok(v),
+            Err(Error::Io(e)) if e.kind() == ErrorKind::InvalidInput => {
+                info!("Uvdevice does not support longer list. Fallback to one page list.");
+                cmd = ListCmd::default();
+                uv.list_secrets(&mut cmd)
+            }
+            Err(e) => Err(e),
+        }?
+        .more_data();
+        if more_data {
+            warn!("The secret list contains more data but the uvdevice cannot show all.");
+        }
+        cmd.try_into()
+    }
+}
+
 impl TryFrom<ListCmd> for SecretList {
     type Error = Error;
 
@@ -374,8 +443,114 @@ impl Display for SecretList {
     }
 }
 
-fn ser_u32<S: Serializer>(v: &U32<BigEndian>, ser: S) -> Result<S::Ok, S::Error> {
-    ser.serialize_u32(v.get())
+/// Usage of the Ultravisor secret store
+///
+/// The number of used slots is the number of secrets in the store as reported by
+/// [`SecretList::total_num_secrets`]. The maximum is reported by the Ultravisor query
+/// information, see [`SecretStoreUsage::read_max`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SecretStoreUsage {
+    used: usize,
+    max: usize,
+}
+
+impl SecretStoreUsage {
+    /// Location of the maximum number of secrets in the Ultravisor query information
+    pub const SYSFS_MAX_SECRETS: &'static str = "/sys/firmware/uv/query/max_secrets";
+
+    /// Creates a new [`SecretStoreUsage`] with `used` of `max` slots in use.
+    ///
+    /// Returns `None` if `max` is zero, a secret store without any slots is not supported.
+    pub fn new(used: usize, max: usize) -> Option<Self> {
+        match max {
+            0 => None,
+            max => Some(Self { used, max }),
+        }
+    }
+
+    /// Reads the maximum number of secrets from the Ultravisor query information.
+    ///
+    /// Returns `None` if the firmware does not report the maximum number of secrets.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the query information exists but cannot be read or
+    /// parsed.
+    pub fn read_max() -> Result<Option<usize>> {
+        Self::read_max_from(Self::SYSFS_MAX_SECRETS)
+    }
+
+    /// Reads the maximum number of secrets from `path`.
+    ///
+    /// See [`SecretStoreUsage::read_max`].
+    pub fn read_max_from<P: AsRef<Path>>(path: P) -> Result<Option<usize>> {
+        let max = match std::fs::read_to_string(&path) {
+            Ok(max) => max,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(file_error!(Read, "maximum number of secrets", path, e)),
+        };
+        let max = max.trim().parse().map_err(|_| Error::ParseError {
+            subject: "maximum number of secrets".to_string(),
+            content: max.trim().to_string(),
+        })?;
+
+        Ok(match max {
+            0 => None,
+            max => Some(max),
+        })
+    }
+
+    /// Returns the number of used slots of this [`SecretStoreUsage`].
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Returns the total number of slots of this [`SecretStoreUsage`].
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Returns the used slots in percent, rounded down.
+    pub fn percent(&self) -> usize {
+        self.used * 100 / self.max
+    }
+
+    /// Returns `true` if at least `percent` percent of the slots are used.
+    pub fn exceeds(&self, percent: u8) -> bool {
+        self.used * 100 >= percent as usize * self.max
+    }
+
+    /// Returns `true` if no more secrets can be added.
+    pub fn is_full(&self) -> bool {
+        self.used >= self.max
+    }
+
+    /// Returns the usage after adding one more secret.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the secret store is full.
+    pub fn add_one(&self) -> Result<Self> {
+        if self.is_full() {
+            return Err(Error::SecretStoreFull(self.max));
+        }
+        Ok(Self {
+            used: self.used + 1,
+            ..*self
+        })
+    }
+}
+
+impl Display for SecretStoreUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Used secret slots: {}/{} ({}%)",
+            self.used,
+            self.max,
+            self.percent()
+        )
+    }
 }
 
 fn ser_u16<S: Serializer>(v: &U16<BigEndian>, ser: S) -> Result<S::Ok, S::Error> {
@@ -446,6 +621,34 @@ impl Display for ListableSecretType {
     }
 }
 
+impl FromStr for ListableSecretType {
+    type Err = Error;
+
+    /// Parses the name of a listable secret type or a raw type code.
+    ///
+    /// The name is one of the names [`Display`] uses, compared case-insensitive. Codes are decimal
+    /// or hexadecimal with a `0x` prefix. Any code is accepted, even if the type is unknown.
+    fn from_str(s: &str) -> Result<Self> {
+        let code = match s.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        };
+        if let Some(code) = code {
+            return Ok(code.into());
+        }
+        (Self::ASSOCIATION..=Self::ECDSA_ED448_KEY)
+            .map(Self::from)
+            .find(|t| {
+                !matches!(t, Self::Unknown(_) | Self::Invalid(_))
+                    && t.to_string().eq_ignore_ascii_case(s)
+            })
+            .ok_or_else(|| Error::ParseError {
+                subject: "secret type".to_string(),
+                content: s.to_string(),
+            })
+    }
+}
+
 impl<O: ByteOrder> From<U16<O>> for ListableSecretType {
     fn from(value: U16<O>) -> Self {
         value.get().into()
@@ -643,12 +846,14 @@ mod test {
             &[
                 Token::Struct {
                     name: "SecretEntry",
-                    len: (4),
+                    len: (5),
                 },
                 Token::String("index"),
                 Token::U16(0),
                 Token::String("stype"),
                 Token::U16(0),
+                Token::String("stype_name"),
+                Token::String("Invalid(0x0000)"),
                 Token::String("len"),
                 Token::U32(0),
                 Token::String("id"),
@@ -696,12 +901,14 @@ mod test {
                 Token::Seq { len: Some(1) },
                 Token::Struct {
                     name: "SecretEntry",
-                    len: (4),
+                    len: (5),
                 },
                 Token::String("index"),
                 Token::U16(1),
                 Token::String("stype"),
                 Token::U16(2),
+                Token::String("stype_name"),
+                Token::String("Association"),
                 Token::String("len"),
                 Token::U32(32),
                 Token::String("id"),
@@ -713,6 +920,104 @@ mod test {
         )
     }
 
+    const SECRET_LIST: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/secret_list.bin"
+    ));
+
+    #[test]
+    fn secret_list_dec_asset() {
+        let list = SecretList::decode(&mut Cursor::new(SECRET_LIST)).unwrap();
+        assert_eq!(list.len(), 6);
+        assert_eq!(list.total_num_secrets(), 6);
+
+        let types: Vec<_> = list.iter().map(|e| e.stype().to_string()).collect();
+        assert_eq!(
+            types,
+            [
+                "Association",
+                "AES-256-KEY",
+                "EC-SECP521R1-PRIVATE-KEY",
+                "Unknown(0x0030)",
+                "PLAINTEXT",
+                "HMAC-SHA-512-KEY"
+            ]
+        );
+        let unknown = &list.secrets[3];
+        assert_eq!(unknown.index(), 3);
+        assert_eq!(unknown.stype(), ListableSecretType::Unknown(0x30));
+        assert_eq!(unknown.secret_size(), 16);
+        assert_eq!(
+            format!("{:#x}", unknown.secret_id()),
+            "0xea8d87bf267dda52087633f11e9f2cb93ca73f9d80a0dff57979b327cebe8a03"
+        );
+
+        let mut buf = vec![];
+        list.encode(&mut buf).unwrap();
+        assert_eq!(buf, &SECRET_LIST[..buf.len()]);
+    }
+
+    #[test]
+    fn secret_list_filter() {
+        let list = SecretList::decode(&mut Cursor::new(SECRET_LIST)).unwrap();
+        let filter = |stype: &str| {
+            let stype: ListableSecretType = stype.parse().unwrap();
+            let mut list = SecretList::decode(&mut Cursor::new(SECRET_LIST)).unwrap();
+            list.retain(|e| e.stype() == stype);
+            list.iter().map(|e| e.index()).collect::<Vec<_>>()
+        };
+        assert_eq!(filter("aes-256-key"), [1]);
+        assert_eq!(filter("Association"), [0]);
+        assert_eq!(filter("0x13"), [2]);
+        assert_eq!(filter("48"), [3]);
+        assert_eq!(filter("0x30"), [3]);
+        assert_eq!(filter("0x0015"), [0u16; 0]);
+
+        let mut by_id = SecretList::decode(&mut Cursor::new(SECRET_LIST)).unwrap();
+        by_id.retain(|e| e.secret_id().has_hex_prefix("0xE6B4"));
+        assert_eq!(by_id.len(), 1);
+        assert_eq!(by_id.secrets[0].stype().to_string(), "AES-256-KEY");
+        assert_eq!(by_id.total_num_secrets(), list.total_num_secrets());
+
+        // the header reflects the remaining entries
+        let mut buf = vec![];
+        by_id.encode(&mut buf).unwrap();
+        assert_eq!(SecretList::decode(&mut Cursor::new(buf)).unwrap(), by_id);
+
+        assert!(list.iter().all(|e| e.secret_id().has_hex_prefix("")));
+        assert!(!list.iter().any(|e| e.secret_id().has_hex_prefix("0xg")));
+    }
+
+    #[test]
+    fn secret_type_parse() {
+        assert_eq!(
+            "ec-secp256r1-private-key"
+                .parse::<ListableSecretType>()
+                .unwrap(),
+            ListableSecretType::Retrievable(RetrievableSecret::Ec(EcCurves::Secp256R1))
+        );
+        assert_eq!(
+            "4".parse::<ListableSecretType>().unwrap(),
+            ListableSecretType::Retrievable(RetrievableSecret::Aes(AesSizes::Bits128))
+        );
+        assert_eq!(
+            "0xffff".parse::<ListableSecretType>().unwrap(),
+            ListableSecretType::Unknown(0xffff)
+        );
+        for inv in [
+            "Unknown(0x0030)",
+            "Invalid(0x0001)",
+            "AES-KEY",
+            "0x10000",
+            "",
+        ] {
+            assert!(matches!(
+                inv.parse::<ListableSecretType>(),
+                Err(Error::ParseError { .. })
+            ));
+        }
+    }
+
     #[test]
     fn secret_id_display() {
         let text = "Fancy secret ID";
@@ -788,4 +1093,100 @@ mod test {
             "4E69636520546573742031323300000000000000000000000000000000000000"
         );
     }
+
+    /// Mocks the Ultravisor query information for the maximum number of secrets
+    struct MaxSecretsFile(std::path::PathBuf);
+
+    impl MaxSecretsFile {
+        fn new(name: &str, content: Option<&str>) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("pv_core_max_secrets_{name}_{}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            if let Some(content) = content {
+                std::fs::write(&path, content).unwrap();
+            }
+            Self(path)
+        }
+    }
+
+    impl Drop for MaxSecretsFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn store_usage_read_max() {
+        let max = MaxSecretsFile::new("read_max", Some("16\n"));
+        assert_eq!(SecretStoreUsage::read_max_from(&max.0).unwrap(), Some(16));
+
+        let list = SecretList::new(12, vec![]);
+        let usage = SecretStoreUsage::new(list.total_num_secrets(), 16).unwrap();
+        assert_eq!(usage.percent(), 75);
+        assert!(!usage.is_full());
+        assert_eq!(usage.to_string(), "Used secret slots: 12/16 (75%)");
+    }
+
+    #[test]
+    fn store_usage_not_reported() {
+        let max = MaxSecretsFile::new("not_reported", None);
+        assert_eq!(SecretStoreUsage::read_max_from(&max.0).unwrap(), None);
+
+        let max = MaxSecretsFile::new("zero", Some("0\n"));
+        assert_eq!(SecretStoreUsage::read_max_from(&max.0).unwrap(), None);
+    }
+
+    #[test]
+    fn store_usage_invalid() {
+        let max = MaxSecretsFile::new("invalid", Some("many\n"));
+        assert!(matches!(
+            SecretStoreUsage::read_max_from(&max.0),
+            Err(Error::ParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn store_usage_exceeds() {
+        let usage = SecretStoreUsage::new(7, 10).unwrap();
+        assert!(usage.exceeds(70));
+        assert!(!usage.exceeds(71));
+        assert!(!usage.exceeds(80));
+        assert!(usage.add_one().unwrap().exceeds(80));
+        assert!(SecretStoreUsage::new(0, 10).unwrap().exceeds(0));
+        assert_eq!(SecretStoreUsage::new(0, 0), None);
+    }
+
+    #[test]
+    fn store_usage_add_one() {
+        let usage = SecretStoreUsage::new(14, 16).unwrap().add_one().unwrap();
+        assert_eq!(usage, SecretStoreUsage::new(15, 16).unwrap());
+        let usage = usage.add_one().unwrap();
+        assert!(usage.is_full());
+        assert_eq!(usage.percent(), 100);
+
+        let err = usage.add_one().unwrap_err();
+        assert!(matches!(err, Error::SecretStoreFull(16)));
+        assert_eq!(
+            err.to_string(),
+            "The secret store is full. It can hold at most 16 secrets"
+        );
+    }
+
+    #[test]
+    fn store_usage_ser() {
+        assert_ser_tokens(
+            &SecretStoreUsage::new(3, 8).unwrap(),
+            &[
+                Token::Struct {
+                    name: "SecretStoreUsage",
+                    len: 2,
+                },
+                Token::Str("used"),
+                Token::U64(3),
+                Token::Str("max"),
+                Token::U64(8),
+                Token::StructEnd,
+            ],
+        );
+    }
 }
@@ -234,3 +234,45 @@ fn ioctl_read_data() {
     assert!(get_lock(&IOCTL_MTX).reset(), "IOCTL was never called");
     assert_eq!(res.unwrap(), UvcSuccess::RC_SUCCESS);
 }
+
+#[test]
+fn open_not_found() {
+    let path = "/this/path/does/not/exist";
+    let res = UvDevice::open_with(Some(path), false);
+    assert!(matches!(res, Err(Error::FileAccess{ path: p, .. }) if p == Path::new(path)));
+}
+
+#[test]
+fn open_no_check() {
+    // any character device can be used if the check is disabled
+    assert!(UvDevice::open_with(Some("/dev/null"), false).is_ok());
+}
+
+#[test]
+fn open_check_char_dev() {
+    let res = UvDevice::open_with(Some("/dev/null"), true);
+    assert!(matches!(res, Err(Error::InvUvDevice { .. })));
+}
+
+#[test]
+fn open_check_regular_file() {
+    let path = std::env::temp_dir().join(format!("uvdevice-test-{}", std::process::id()));
+    File::create(&path).unwrap();
+    let checked = UvDevice::open_with(Some(&path), true);
+    let unchecked = UvDevice::open_with(Some(&path), false);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(
+        matches!(checked, Err(Error::InvUvDevice { reason, .. }) if reason == "not a character device")
+    );
+    assert!(unchecked.is_ok());
+}
+
+#[test]
+fn not_found_msg() {
+    let err = Error::UvDeviceNotFound(vec!["/dev/uv".into(), "/dev/misc/uv".into()]);
+    assert_eq!(
+        err.to_string(),
+        "Cannot find the uvdevice. Tried: '/dev/uv', '/dev/misc/uv'"
+    );
+}
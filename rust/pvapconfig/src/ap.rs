@@ -5,9 +5,13 @@
 //! AP support functions for pvapconfig
 //
 
+use crate::cli::ExternalChange;
 use crate::helper::*;
 use pv_core::ap::*;
 use pv_core::misc::read_file_string;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::path::Path;
 use std::slice::Iter;
 use std::thread;
@@ -22,6 +26,10 @@ const PATH_SYS_DEVICES_AP: &str = "/sys/devices/ap";
 
 const SYS_BUS_AP_BINDINGS_POLL_MS: u64 = 500;
 
+/// Number of writes of an APQN attribute before giving up with
+/// --on-external-change=retry
+const EXTERNAL_CHANGE_WRITE_ATTEMPTS: usize = 3;
+
 /// Check if AP bus support is available.
 /// Returns Result with Ok(()) or Err(failurestring).
 pub fn check_ap_bus_support() -> Result<(), String> {
@@ -76,11 +84,272 @@ pub fn wait_for_ap_bus_bindings_complete() -> bool {
         thread::sleep(time::Duration::from_millis(SYS_BUS_AP_BINDINGS_POLL_MS));
         counter += 1;
         if counter % 10 == 0 {
-            println!("Waiting for AP bus bindings complete.");
+            eprintln!("Waiting for AP bus bindings complete.");
         }
     }
 }
 
+/// Write an APQN attribute and verify it by reading it back.
+///
+/// The write function sets the attribute, the read function reads it
+/// back. If the attribute does not hold the expected value right after
+/// the write, somebody else modified the APQN in between. Depending on
+/// the action this is either reported immediately or the write is
+/// repeated up to EXTERNAL_CHANGE_WRITE_ATTEMPTS times.
+/// Returns Result with Ok(()) or Err(failurestring).
+fn write_and_verify<T, W, R>(
+    apqn: &Apqn,
+    expected: T,
+    action: ExternalChange,
+    mut write: W,
+    mut read: R,
+) -> Result<(), String>
+where
+    T: PartialEq + fmt::Debug,
+    W: FnMut() -> pv_core::Result<()>,
+    R: FnMut() -> pv_core::Result<T>,
+{
+    let mut attempt = 1;
+    loop {
+        write().map_err(|err| err.to_string())?;
+        let actual = read().map_err(|err| err.to_string())?;
+        if actual == expected {
+            return Ok(());
+        }
+        let msg = format!(
+            "external modification detected on APQN {apqn} (expected {expected:?}, found {actual:?})"
+        );
+        if action == ExternalChange::Abort || attempt >= EXTERNAL_CHANGE_WRITE_ATTEMPTS {
+            return Err(msg);
+        }
+        eprintln!("Warning: {msg}, writing again.");
+        attempt += 1;
+    }
+}
+
+/// Access to the bind and associate state attributes of APQNs
+///
+/// All reads and writes of APQN attributes done while applying an AP
+/// configuration go through this trait. This way the same decision
+/// code applies the configuration to sysfs or only plans it in a
+/// dry run.
+pub trait ApqnState {
+    /// Read the bind state of the APQN.
+    fn bind_state(&self, apqn: &Apqn) -> pv_core::Result<bind_state::BindState>;
+    /// Set the bind state of the APQN.
+    fn set_bind_state(&self, apqn: &Apqn, state: bind_state::BindState) -> pv_core::Result<()>;
+    /// Read the associate state of the APQN.
+    fn associate_state(&self, apqn: &Apqn) -> pv_core::Result<assoc_state::AssocState>;
+    /// Set the associate state of the APQN.
+    fn set_associate_state(
+        &self,
+        apqn: &Apqn,
+        state: assoc_state::AssocState,
+    ) -> pv_core::Result<()>;
+}
+
+impl<S: ApqnState + ?Sized> ApqnState for &S {
+    fn bind_state(&self, apqn: &Apqn) -> pv_core::Result<bind_state::BindState> {
+        (**self).bind_state(apqn)
+    }
+
+    fn set_bind_state(&self, apqn: &Apqn, state: bind_state::BindState) -> pv_core::Result<()> {
+        (**self).set_bind_state(apqn, state)
+    }
+
+    fn associate_state(&self, apqn: &Apqn) -> pv_core::Result<assoc_state::AssocState> {
+        (**self).associate_state(apqn)
+    }
+
+    fn set_associate_state(
+        &self,
+        apqn: &Apqn,
+        state: assoc_state::AssocState,
+    ) -> pv_core::Result<()> {
+        (**self).set_associate_state(apqn, state)
+    }
+}
+
+/// APQN attributes in sysfs
+#[derive(Debug, Default)]
+pub struct SysfsApqnState;
+
+impl ApqnState for SysfsApqnState {
+    fn bind_state(&self, apqn: &Apqn) -> pv_core::Result<bind_state::BindState> {
+        apqn.bind_state()
+    }
+
+    fn set_bind_state(&self, apqn: &Apqn, state: bind_state::BindState) -> pv_core::Result<()> {
+        apqn.set_bind_state(state)
+    }
+
+    fn associate_state(&self, apqn: &Apqn) -> pv_core::Result<assoc_state::AssocState> {
+        apqn.associate_state()
+    }
+
+    fn set_associate_state(
+        &self,
+        apqn: &Apqn,
+        state: assoc_state::AssocState,
+    ) -> pv_core::Result<()> {
+        apqn.set_associate_state(state)
+    }
+}
+
+/// APQN attributes for a dry run
+///
+/// Reads are passed to the underlying state as long as the APQN
+/// attribute has not been written. Writes are NOT passed on but
+/// only kept in memory, so reading an attribute back returns the
+/// value pvapconfig would have written.
+#[derive(Debug)]
+pub struct DryRunApqnState<S: ApqnState> {
+    inner: S,
+    bind: RefCell<HashMap<(u32, u32), bind_state::BindState>>,
+    assoc: RefCell<HashMap<(u32, u32), assoc_state::AssocState>>,
+}
+
+impl<S: ApqnState> DryRunApqnState<S> {
+    /// Create a dry run on top of the given APQN state.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            bind: RefCell::new(HashMap::new()),
+            assoc: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S: ApqnState + Default> Default for DryRunApqnState<S> {
+    fn default() -> Self {
+        Self::new(S::default())
+    }
+}
+
+impl<S: ApqnState> ApqnState for DryRunApqnState<S> {
+    fn bind_state(&self, apqn: &Apqn) -> pv_core::Result<bind_state::BindState> {
+        match self.bind.borrow().get(&(apqn.card, apqn.domain)) {
+            Some(state) => Ok(*state),
+            None => self.inner.bind_state(apqn),
+        }
+    }
+
+    fn set_bind_state(&self, apqn: &Apqn, state: bind_state::BindState) -> pv_core::Result<()> {
+        let key = (apqn.card, apqn.domain);
+        self.bind.borrow_mut().insert(key, state);
+        // unbinding an APQN also removes its association
+        if state == bind_state::Unbound {
+            self.assoc
+                .borrow_mut()
+                .insert(key, assoc_state::Unassociated);
+        }
+        Ok(())
+    }
+
+    fn associate_state(&self, apqn: &Apqn) -> pv_core::Result<assoc_state::AssocState> {
+        match self.assoc.borrow().get(&(apqn.card, apqn.domain)) {
+            Some(state) => Ok(*state),
+            None => self.inner.associate_state(apqn),
+        }
+    }
+
+    fn set_associate_state(
+        &self,
+        apqn: &Apqn,
+        state: assoc_state::AssocState,
+    ) -> pv_core::Result<()> {
+        self.assoc
+            .borrow_mut()
+            .insert((apqn.card, apqn.domain), state);
+        Ok(())
+    }
+}
+
+/// Set the bind state of an APQN and verify it by reading it back.
+///
+/// See write_and_verify() for the handling of external modifications.
+pub fn set_bind_state_verified(
+    apst: &dyn ApqnState,
+    apqn: &Apqn,
+    state: bind_state::BindState,
+    action: ExternalChange,
+) -> Result<(), String> {
+    write_and_verify(
+        apqn,
+        state,
+        action,
+        || apst.set_bind_state(apqn, state),
+        || apst.bind_state(apqn),
+    )
+}
+
+/// Set the associate state of an APQN and verify it by reading it back.
+///
+/// See write_and_verify() for the handling of external modifications.
+pub fn set_associate_state_verified(
+    apst: &dyn ApqnState,
+    apqn: &Apqn,
+    state: assoc_state::AssocState,
+    action: ExternalChange,
+) -> Result<(), String> {
+    write_and_verify(
+        apqn,
+        state,
+        action,
+        || apst.set_associate_state(apqn, state),
+        || apst.associate_state(apqn),
+    )
+}
+
+/// APQN attributes of a system on which all APQNs are unbound and
+/// unassociated
+///
+/// Only useful as base of a [`DryRunApqnState`], which never passes
+/// writes on.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct UnboundApqnState;
+
+#[cfg(test)]
+impl ApqnState for UnboundApqnState {
+    fn bind_state(&self, _apqn: &Apqn) -> pv_core::Result<bind_state::BindState> {
+        Ok(bind_state::Unbound)
+    }
+
+    fn set_bind_state(&self, _apqn: &Apqn, _state: bind_state::BindState) -> pv_core::Result<()> {
+        unreachable!("APQN attributes are only written through DryRunApqnState")
+    }
+
+    fn associate_state(&self, _apqn: &Apqn) -> pv_core::Result<assoc_state::AssocState> {
+        Ok(assoc_state::Unassociated)
+    }
+
+    fn set_associate_state(
+        &self,
+        _apqn: &Apqn,
+        _state: assoc_state::AssocState,
+    ) -> pv_core::Result<()> {
+        unreachable!("APQN attributes are only written through DryRunApqnState")
+    }
+}
+
+/// In-memory APQN attributes, a mocked sysfs tree for testing
+#[cfg(test)]
+pub type MockApqnState = DryRunApqnState<UnboundApqnState>;
+
+#[cfg(test)]
+impl MockApqnState {
+    /// Bind state of the APQN, APQNs not set up are unbound.
+    pub fn bind_of(&self, apqn: &Apqn) -> bind_state::BindState {
+        self.bind_state(apqn).unwrap()
+    }
+
+    /// Associate state of the APQN, APQNs not set up are unassociated.
+    pub fn assoc_of(&self, apqn: &Apqn) -> assoc_state::AssocState {
+        self.associate_state(apqn).unwrap()
+    }
+}
+
 /// Wrapper object around Vector of Apqns
 #[derive(Debug)]
 pub struct ApqnList(Vec<Apqn>);
@@ -223,6 +492,118 @@ impl ApqnList {
 mod tests {
 
     use super::*;
+    use std::cell::Cell;
+
+    fn test_apqn() -> Apqn {
+        Apqn {
+            name: String::from("10.0007"),
+            card: 16,
+            domain: 7,
+            gen: 8,
+            mode: apqn_mode::Accel,
+            info: None,
+        }
+    }
+
+    /// Bind the test APQN with a concurrent writer which unbinds it
+    /// again right after the first `flips` writes.
+    fn bind_with_concurrent_writer(
+        flips: usize,
+        action: ExternalChange,
+    ) -> (Result<(), String>, usize) {
+        let apqn = test_apqn();
+        let attr = Cell::new(bind_state::Unbound);
+        let writes = Cell::new(0);
+        let r = write_and_verify(
+            &apqn,
+            bind_state::Bound,
+            action,
+            || {
+                attr.set(bind_state::Bound);
+                writes.set(writes.get() + 1);
+                if writes.get() <= flips {
+                    attr.set(bind_state::Unbound);
+                }
+                Ok(())
+            },
+            || Ok(attr.get()),
+        );
+        (r, writes.get())
+    }
+
+    #[test]
+    fn test_write_and_verify() {
+        for action in [ExternalChange::Abort, ExternalChange::Retry] {
+            assert_eq!(bind_with_concurrent_writer(0, action), (Ok(()), 1));
+        }
+    }
+    #[test]
+    fn test_write_and_verify_abort() {
+        let (r, writes) = bind_with_concurrent_writer(1, ExternalChange::Abort);
+        assert_eq!(writes, 1);
+        let err = r.unwrap_err();
+        assert!(err.starts_with("external modification detected on APQN (16,7)"));
+        assert!(err.ends_with("(expected Bound, found Unbound)"));
+    }
+    #[test]
+    fn test_write_and_verify_retry() {
+        let (r, writes) = bind_with_concurrent_writer(2, ExternalChange::Retry);
+        assert_eq!((r, writes), (Ok(()), 3));
+        let (r, writes) = bind_with_concurrent_writer(usize::MAX, ExternalChange::Retry);
+        assert_eq!(writes, EXTERNAL_CHANGE_WRITE_ATTEMPTS);
+        assert!(r
+            .unwrap_err()
+            .starts_with("external modification detected on APQN (16,7)"));
+    }
+    #[test]
+    fn test_write_and_verify_failure() {
+        let apqn = test_apqn();
+        let r = write_and_verify(
+            &apqn,
+            assoc_state::Associated(3),
+            ExternalChange::Retry,
+            || Ok(()),
+            || Err(pv_core::Error::UnknownAssocState(String::from("???"))),
+        );
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_dry_run_apqn_state() {
+        let apqn = test_apqn();
+        let mock = MockApqnState::default();
+        mock.set_bind_state(&apqn, bind_state::Bound).unwrap();
+        mock.set_associate_state(&apqn, assoc_state::Associated(5))
+            .unwrap();
+
+        let dry = DryRunApqnState::new(mock);
+        // reads pass through until written
+        assert_eq!(dry.bind_state(&apqn).unwrap(), bind_state::Bound);
+        assert_eq!(
+            dry.associate_state(&apqn).unwrap(),
+            assoc_state::Associated(5)
+        );
+        set_bind_state_verified(&dry, &apqn, bind_state::Unbound, ExternalChange::Abort).unwrap();
+        assert_eq!(dry.bind_state(&apqn).unwrap(), bind_state::Unbound);
+        assert_eq!(
+            dry.associate_state(&apqn).unwrap(),
+            assoc_state::Unassociated
+        );
+        set_associate_state_verified(
+            &dry,
+            &apqn,
+            assoc_state::Associated(7),
+            ExternalChange::Abort,
+        )
+        .unwrap();
+        assert_eq!(
+            dry.associate_state(&apqn).unwrap(),
+            assoc_state::Associated(7)
+        );
+        // the underlying state is untouched
+        assert_eq!(dry.inner.bind_of(&apqn), bind_state::Bound);
+        assert_eq!(dry.inner.assoc_of(&apqn), assoc_state::Associated(5));
+    }
 
     // These tests assume, there is an AP bus available
     // Also for each APQN which is online, it is assumed
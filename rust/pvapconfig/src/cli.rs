@@ -18,9 +18,18 @@ pub struct Cli {
     pub config: Option<String>,
 
     /// Dry run: display the actions but don't actually perform them on the APQNs.
+    ///
+    /// Prints the plan: the APQNs which would be bound, associated, or
+    /// unbound, the MKVP checks, and the AP config entries which can not
+    /// be satisfied. Exits with failure if not all entries can be satisfied.
     #[arg(short = 'n', long = "dry-run")]
     pub dryrun: bool,
 
+    /// Output format of the dry run plan.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = OutputFormat::Human,
+          requires = "dryrun", conflicts_with = "verbose")]
+    pub format: OutputFormat,
+
     /// Enforce strict match: All config entries need to be fulfilled.
     ///
     /// By default it is enough to successfully apply at least one config entry.
@@ -29,6 +38,15 @@ pub struct Cli {
     #[arg(long = "strict")]
     pub strict: bool,
 
+    /// Action when an APQN attribute is modified by somebody else.
+    ///
+    /// After each bind, unbind, or associate pvapconfig reads back the
+    /// APQN attribute. If it does not hold the value just written, some
+    /// other process (for example zcryptctl or a manual sysfs write)
+    /// modified the APQN in between. Either abort or retry the write.
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = ExternalChange::Abort)]
+    pub on_external_change: ExternalChange,
+
     /// Provide more detailed output.
     #[arg(short, long)]
     pub verbose: bool,
@@ -38,6 +56,24 @@ pub struct Cli {
     pub version: bool,
 }
 
+/// Action on an external modification of an APQN attribute
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExternalChange {
+    /// Stop applying the AP configuration with an error.
+    Abort,
+    /// Write the attribute again, give up after a few attempts.
+    Retry,
+}
+
+/// Output format of the dry run plan
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-focused, non-parsable output format.
+    Human,
+    /// Use json format.
+    Json,
+}
+
 lazy_static! {
     pub static ref ARGS: Cli = Cli::parse();
 }
@@ -55,9 +91,20 @@ impl Cli {
         self.dryrun
     }
 
+    /// format returns the output format of the dry run plan.
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
     /// strict returns true if the strict flag was given, otherwise
     /// false is returned.
     pub fn strict(&self) -> bool {
         self.strict
     }
+
+    /// on_external_change returns the action to take if an APQN
+    /// attribute has been modified by somebody else.
+    pub fn on_external_change(&self) -> ExternalChange {
+        self.on_external_change
+    }
 }
@@ -12,6 +12,7 @@ use serde::{Deserialize, Serialize};
 use serde_yaml::{self};
 use std::fs::File;
 use std::slice::Iter;
+use utils::read_text;
 
 pub const STR_MODE_EP11: &str = "ep11";
 pub const STR_MODE_ACCEL: &str = "accel";
@@ -180,7 +181,16 @@ impl ApConfigList {
                 ))
             }
         };
-        match serde_yaml::from_reader(file) {
+        let text = match read_text(file, "AP config file") {
+            Ok(t) => t,
+            Err(err) => {
+                return Err(format!(
+                    "Failure to read AP config file {}: {:?}",
+                    fname, err
+                ))
+            }
+        };
+        match serde_yaml::from_str(&text) {
             Ok(cfg) => Ok(cfg),
             Err(err) => Err(format!(
                 "Failure parsing AP config file {}: {:?}",
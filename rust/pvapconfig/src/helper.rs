@@ -8,9 +8,7 @@
 use regex::Regex;
 use std::error::Error;
 use std::fs;
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::path::PathBuf;
+use utils::FileLock;
 
 pub const PATH_PVAPCONFIG_LOCK: &str = "/run/lock/pvapconfig.lock";
 
@@ -55,75 +53,21 @@ pub fn sysfs_get_list_of_subdirs_matching_regex(
     Ok(v)
 }
 
-/// LockFile for inter-process locking
+/// Establish the pvapconfig lock.
 ///
-/// Simple class for process locking for pvapconfig.
-/// The lock concept is simple: The existence of a file is used as the
-/// locking indicator. If the file exists something is locked, if it does
-/// not exist something is not locked. In the lock file the PID of the
-/// process created the file ("owning this file") is written in.
-/// With the ProcessLock object leaving scope the associated lock file
-/// is automatically deleted. It is assumed that the creation of a file
-/// is an atomic operation - that's true for most filesystems but may
-/// cause problems with network based filesystems.
-/// Example:
-/// ```
-/// let lock = LockFile::lock("/var/lock/process.lock");
-/// assert!(lock.is_ok());
-/// let lock2 = LockFile::lock("/var/lock/process.lock");
-/// assert!(lock2.is_err());
-/// drop(lock);
-/// let lock3 = LockFile::lock("/var/lock/process.lock");
-/// assert!(lock3.is_ok());
-/// ```
-#[derive(Debug)]
-pub struct LockFile {
-    lockfile: PathBuf,
-}
-
-impl LockFile {
-    /// Try to establish the lock file.
-    /// Upon success the given file is fresh created and has the pid of this
-    /// process written in. The function returns a new LockFile object
-    /// which has implemented the Drop Trait. So with this object going out
-    /// of scope the lock file is deleted. If establishing the lock file
-    /// fails for any reason (for example the file already exists), the
-    /// function fails with returning an Error string. This function does
-    /// NOT panic if establishing the lock file fails for any reason. If
-    /// the lock file could be esablished but writing in the PID fails, a
-    /// warning is printed but the function continues with returning a
-    /// LockFile object.
-    pub fn try_lock(fname: &str) -> Result<Self, String> {
-        let lockfile = PathBuf::from(fname);
-        let mut file = match OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&lockfile)
-        {
-            Err(err) => {
-                return Err(format!(
-                    "Failure trying to create lock file {fname}: {err:?}."
-                ))
-            }
-            Ok(f) => f,
-        };
-        let _ = file
-            .write(format!("{}", std::process::id()).as_bytes())
-            .map_err(|err| {
-                println!("Warning: could not write PID into lockfile {fname}: {err:?}.")
-            });
-        Ok(Self { lockfile })
-    }
-}
-
-impl Drop for LockFile {
-    fn drop(&mut self) {
-        let _ = fs::remove_file(&self.lockfile).map_err(|err| {
-            println!(
-                "Warning: could not remove lockfile {}: {err:?}.",
-                self.lockfile.display()
-            )
-        });
+/// The lock is an exclusive advisory lock (flock) on the given file.
+/// It is held as long as the returned FileLock object lives and is
+/// released by the kernel when the process terminates in any way, so
+/// a leftover lock file from a crashed pvapconfig does not block
+/// further invocations. If another process holds the lock, the
+/// function does NOT wait but fails with returning an Error string.
+pub fn lock_pvapconfig(fname: &str) -> Result<FileLock, String> {
+    match FileLock::try_exclusive(fname) {
+        Ok(Some(lock)) => Ok(lock),
+        Ok(None) => Err(format!(
+            "Failure trying to lock {fname}: Another pvapconfig instance is running."
+        )),
+        Err(err) => Err(format!("Failure trying to lock {fname}: {err}.")),
     }
 }
 
@@ -152,18 +96,20 @@ mod tests {
         }
     }
     #[test]
-    fn test_lockfile() {
+    fn test_lock_pvapconfig() {
         let temp_dir =
             TemporaryDirectory::new().expect("creating a temporary directory should work");
         let file_path = temp_dir.path().join("my.lock");
         let file_path_str = file_path.to_str().expect("should work");
 
-        let r1 = LockFile::try_lock(file_path_str);
+        let r1 = lock_pvapconfig(file_path_str);
         assert!(r1.is_ok());
-        let r2 = LockFile::try_lock(file_path_str);
+        let r2 = lock_pvapconfig(file_path_str);
         assert!(r2.is_err());
         drop(r1);
-        let r3 = LockFile::try_lock(file_path_str);
+        // a leftover lock file does not block
+        assert!(file_path.exists());
+        let r3 = lock_pvapconfig(file_path_str);
         assert!(r3.is_ok());
     }
 }
@@ -10,12 +10,14 @@ mod ap;
 mod cli;
 mod config;
 mod helper;
+mod plan;
 mod uv;
 
-use ap::ApqnList;
-use cli::ARGS;
+use ap::{ApqnList, ApqnState, DryRunApqnState, SysfsApqnState};
+use cli::{ExternalChange, OutputFormat, ARGS};
 use config::{ApConfigEntry, ApConfigList};
-use helper::{LockFile, PATH_PVAPCONFIG_LOCK};
+use helper::{lock_pvapconfig, PATH_PVAPCONFIG_LOCK};
+use plan::{Action, ApPlan, EntryRef, MkvpCheck, ModeMismatch};
 use pv_core::ap::{self as pvap, Apqn};
 use pv_core::misc::encode_hex;
 use pv_core::uv::{ListableSecretType, SecretList};
@@ -64,10 +66,12 @@ fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
-    // make sure only one pvapconfig instance is running
-    let r = LockFile::try_lock(PATH_PVAPCONFIG_LOCK);
+    // make sure only one pvapconfig instance is running, the lock
+    // is held until main returns and released by the kernel if
+    // pvapconfig gets terminated by a signal
+    let r = lock_pvapconfig(PATH_PVAPCONFIG_LOCK);
     on_error_print_and_exit!(r);
-    let _lockfile = r.unwrap();
+    let _lock = r.unwrap();
 
     // AP bus check
     info!("Checking AP bus support and facilities...\n");
@@ -100,6 +104,9 @@ fn main() -> ExitCode {
         Err(err) => println_and_exit_failure!("{}", err),
     };
     if apconfig.is_empty() {
+        if ARGS.dryrun() {
+            return print_plan(&ApPlan::new(0));
+        }
         println!(
             "No AP configuration entries in config file '{}': Nothing to do.",
             configfile
@@ -122,7 +129,7 @@ fn main() -> ExitCode {
         .filter(|apc| apc.mode != config::STR_MODE_ACCEL)
         .count();
     if non_accel_apc > 0 && secrets.is_empty() {
-        println!(
+        eprintln!(
             "Warning: No UV Secrets given but at least one AP config entry requires a Secret."
         );
     }
@@ -136,7 +143,8 @@ fn main() -> ExitCode {
         Some(l) => l,
         None => return ExitCode::FAILURE,
     };
-    if apqns.is_empty() {
+    // in a dry run, the plan reports the AP config entries as unsatisfied
+    if apqns.is_empty() && !ARGS.dryrun() {
         info!("List of available APQNs is empty: So there's nothing to do.\n");
         return ExitCode::SUCCESS;
     }
@@ -146,13 +154,37 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
+    // a dry run lists the mode mismatches as blocking findings of the plan
+    if !ARGS.dryrun() {
+        if let Err(e) = check_modes(&apconfig, &apqns) {
+            println_and_exit_failure!("{}", e);
+        }
+    }
+
     // now the real work
-    info!("Applying AP configuration...\n");
-    let n = match do_ap_config(&mut apqns, &secrets, &apconfig, false) {
+    let apst: Box<dyn ApqnState> = if ARGS.dryrun() {
+        info!("Planning AP configuration...\n");
+        Box::new(DryRunApqnState::new(SysfsApqnState))
+    } else {
+        info!("Applying AP configuration...\n");
+        Box::new(SysfsApqnState)
+    };
+    let plan = match do_ap_config(
+        &mut apqns,
+        &secrets,
+        &apconfig,
+        apst.as_ref(),
+        ARGS.on_external_change(),
+    ) {
         Err(e) => println_and_exit_failure!("{}", e),
-        Ok(n) => n,
+        Ok(plan) => plan,
     };
 
+    if ARGS.dryrun() {
+        return print_plan(&plan);
+    }
+
+    let n = plan.satisfied;
     if n == 0 {
         println_and_exit_failure!(
             "None out of {} AP config entries could be applied.",
@@ -175,23 +207,67 @@ fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
+/// Print the plan of a dry run
+///
+/// Returns with exit success only if all AP config entries can be
+/// satisfied, so a dry run can gate automation.
+fn print_plan(plan: &ApPlan) -> ExitCode {
+    match ARGS.format() {
+        OutputFormat::Human => println!("{plan}"),
+        OutputFormat::Json => match serde_json::to_string_pretty(plan) {
+            Ok(s) => println!("{s}"),
+            Err(err) => println_and_exit_failure!("Failure serializing the plan: {err}"),
+        },
+    }
+    if plan.is_satisfiable() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Compare the MKVP of each EP11 AP config entry with each EP11 APQN
+fn mkvp_checks(apqns: &ApqnList, apconfig: &ApConfigList) -> Vec<MkvpCheck> {
+    let mut checks = Vec::new();
+    for (ci, apc) in apconfig.iter().enumerate() {
+        if apc.mode != config::STR_MODE_EP11 {
+            continue;
+        }
+        for apqn in apqns.iter() {
+            if let Some(pvap::apqn_info::Ep11(info)) = &apqn.info {
+                checks.push(MkvpCheck {
+                    entry: EntryRef::new(ci, apc),
+                    apqn: apqn.name.clone(),
+                    passed: apc.mkvp == info.mkvp,
+                });
+            }
+        }
+    }
+    checks
+}
+
 /// The real worker function
 ///
 /// This is the real algorithm which is trying to apply the
 /// AP configuration read from the config file to the existing
 /// APQNs with the info from the list of secrets from the UV.
-/// Returns the nr of AP config entries which are fulfilled
-/// after the function ended.
+/// All APQN attributes are read and written via apst, so with
+/// a DryRunApqnState nothing is changed on the APQNs.
+/// Returns the plan which holds the actions taken and the nr
+/// of AP config entries which are fulfilled after the function
+/// ended.
 /// apqns needs to be mutable as the function does a resort
 /// but content stays the same.
 fn do_ap_config(
     apqns: &mut ApqnList,
     secrets: &SecretList,
     apconfig: &ApConfigList,
-    fntest: bool,
-) -> Result<usize, String> {
-    let mut resolved_entries = 0;
+    apst: &dyn ApqnState,
+    on_external_change: ExternalChange,
+) -> Result<ApPlan, String> {
+    let mut plan = ApPlan::new(apconfig.len());
     let mut apconfig_done = vec![false; apconfig.len()];
+    let mut apconfig_reasons: Vec<Vec<plan::Reason>> = vec![Vec::new(); apconfig.len()];
     let mut apqn_done = vec![false; apqns.len()];
 
     // Preparation: Sort APQNs by generation.
@@ -201,6 +277,7 @@ fn do_ap_config(
     // generations we prefer newer card generations over
     // older card generations.
     apqns.sort_by_gen();
+    plan.mkvp_checks = mkvp_checks(apqns, apconfig);
 
     // Step 1:
     // Go through all AP config entries and try to find an APQN
@@ -210,11 +287,7 @@ fn do_ap_config(
     // the next steps.
 
     for (ci, apc) in apconfig.iter().enumerate() {
-        let cistr = if !apc.name.is_empty() {
-            format!("#{} '{}'", ci + 1, apc.name)
-        } else {
-            format!("#{}", ci + 1)
-        };
+        let entry = EntryRef::new(ci, apc);
         for (ai, apqn) in apqns.iter().enumerate() {
             if apqn_done[ai] {
                 continue;
@@ -222,13 +295,10 @@ fn do_ap_config(
             if !config_and_apqn_match(apc, apqn) {
                 continue;
             }
-            if fntest {
-                continue;
-            }
             match apqn.mode {
                 pvap::apqn_mode::Accel => {
                     // check bind state of this APQN
-                    let bind_state_ok = match apqn.bind_state() {
+                    let bind_state_ok = match apst.bind_state(apqn) {
                         Err(err) => {
                             eprintln!("Warning: Failure reading APQN {apqn} bind state: {err}");
                             false
@@ -242,15 +312,16 @@ fn do_ap_config(
                     // This APQN matches to the current AP config entry and is already bound.
                     // So this AP config entry is satisfied: mark this config entry as done
                     // and mark this APQN as used.
-                    info!("Accelerator APQN {apqn} already satisfies AP config entry {cistr}.\n");
+                    info!("Accelerator APQN {apqn} already satisfies AP config entry {entry}.\n");
+                    plan.step(apqn, Action::Keep, Some(entry));
                     apconfig_done[ci] = true;
                     apqn_done[ai] = true;
-                    resolved_entries += 1;
+                    plan.satisfied += 1;
                     break;
                 }
                 pvap::apqn_mode::Ep11 => {
                     // check association state of this APQN
-                    let (assoc_state_ok, assoc_idx) = match apqn.associate_state() {
+                    let (assoc_state_ok, assoc_idx) = match apst.associate_state(apqn) {
                         Err(err) => {
                             eprintln!(
                                 "Warning: Failure reading APQN {apqn} associate state: {err}"
@@ -276,10 +347,11 @@ fn do_ap_config(
                     // This APQN matches to the current AP config entry and is already
                     // associated with the right secret id. So this AP config entry is
                     // satisfied: mark this config entry as done and mark this APQN as used.
-                    info!("EP11 APQN {apqn} already satisfies AP config entry {cistr}.\n");
+                    info!("EP11 APQN {apqn} already satisfies AP config entry {entry}.\n");
+                    plan.step(apqn, Action::Keep, Some(entry));
                     apconfig_done[ci] = true;
                     apqn_done[ai] = true;
-                    resolved_entries += 1;
+                    plan.satisfied += 1;
                     break;
                 }
                 _ => {
@@ -294,18 +366,21 @@ fn do_ap_config(
     // and association state and maybe reset to "unbound".
 
     for (ai, apqn) in apqns.iter().enumerate() {
-        if apqn_done[ai] || fntest {
+        if apqn_done[ai] {
             continue;
         }
-        match apqn.bind_state() {
+        match apst.bind_state(apqn) {
             Err(err) => eprintln!("Warning: Failure reading APQN {apqn} bind state: {err}"),
             Ok(pvap::bind_state::Bound) => {
                 info!("Unbind APQN {apqn} as this bind/associate does not match to any AP config entry.\n");
-                if !ARGS.dryrun() {
-                    if let Err(err) = apqn.set_bind_state(pvap::bind_state::Unbound) {
-                        return Err(format!("Failure unbinding APQN {apqn}: {err}"));
-                    }
-                }
+                ap::set_bind_state_verified(
+                    apst,
+                    apqn,
+                    pvap::bind_state::Unbound,
+                    on_external_change,
+                )
+                .map_err(|err| format!("Failure unbinding APQN {apqn}: {err}"))?;
+                plan.step(apqn, Action::Unbind, None);
             }
             Ok(_) => {}
         };
@@ -316,11 +391,7 @@ fn do_ap_config(
     // by searching for an APQN which would match to this config entry and
     // then prepare this APQN (bind, maybe associate).
     for (ci, apc) in apconfig.iter().enumerate() {
-        let cistr = if !apc.name.is_empty() {
-            format!("#{} '{}'", ci + 1, apc.name)
-        } else {
-            format!("#{}", ci + 1)
-        };
+        let entry = EntryRef::new(ci, apc);
         if apconfig_done[ci] {
             continue;
         }
@@ -334,19 +405,21 @@ fn do_ap_config(
             match apqn.mode {
                 pvap::apqn_mode::Accel => {
                     // try to bind this accelerator APQN
-                    if ARGS.verbose() || fntest {
-                        println!("Bind APQN {apqn} to match to AP config entry {cistr}.");
-                    }
-                    if !(ARGS.dryrun() || fntest) {
-                        if let Err(err) = apqn.set_bind_state(pvap::bind_state::Bound) {
-                            // bind failed, unbind/reset this apqn, return with failure
-                            let _ = apqn.set_bind_state(pvap::bind_state::Unbound);
-                            return Err(format!("Failure binding APQN {apqn}: {err}"));
-                        }
+                    info!("Bind APQN {apqn} to match to AP config entry {entry}.\n");
+                    if let Err(err) = ap::set_bind_state_verified(
+                        apst,
+                        apqn,
+                        pvap::bind_state::Bound,
+                        on_external_change,
+                    ) {
+                        // bind failed, unbind/reset this apqn, return with failure
+                        let _ = apst.set_bind_state(apqn, pvap::bind_state::Unbound);
+                        return Err(format!("Failure binding APQN {apqn}: {err}"));
                     }
+                    plan.step(apqn, Action::Bind, Some(entry));
                     apconfig_done[ci] = true;
                     apqn_done[ai] = true;
-                    resolved_entries += 1;
+                    plan.satisfied += 1;
                     break;
                 }
                 pvap::apqn_mode::Ep11 => {
@@ -359,39 +432,39 @@ fn do_ap_config(
                     }) {
                         None => {
                             eprintln!("Warning: Secret id '{}' from config entry {} not found in UV secrets list.",
-				      apc.secretid, cistr);
+				      apc.secretid, entry);
+                            apconfig_reasons[ci].push(plan::Reason::SecretNotFound {
+                                secretid: apc.secretid.clone(),
+                            });
                             break;
                         }
                         Some(se) => se,
                     };
                     // try to bind
-                    if ARGS.verbose() || fntest {
-                        println!(
-                            "Bind APQN {apqn} to match to AP config entry {cistr} (step 1/2)."
-                        );
-                    }
-                    if !(ARGS.dryrun() || fntest) {
-                        if let Err(err) = apqn.set_bind_state(pvap::bind_state::Bound) {
-                            // bind failed, unbind/reset this apqn, return with failure
-                            let _ = apqn.set_bind_state(pvap::bind_state::Unbound);
-                            return Err(format!("Failure binding APQN {}: {}", apqn, err));
-                        }
+                    info!("Bind APQN {apqn} to match to AP config entry {entry} (step 1/2).\n");
+                    if let Err(err) = ap::set_bind_state_verified(
+                        apst,
+                        apqn,
+                        pvap::bind_state::Bound,
+                        on_external_change,
+                    ) {
+                        // bind failed, unbind/reset this apqn, return with failure
+                        let _ = apst.set_bind_state(apqn, pvap::bind_state::Unbound);
+                        return Err(format!("Failure binding APQN {}: {}", apqn, err));
                     }
+                    plan.step(apqn, Action::Bind, Some(entry.clone()));
                     // try to associate
-                    if ARGS.verbose() || fntest {
-                        println!(
-			    "Associate APQN {} with uv secrets index {} to match AP config entry {} (step 2/2).",
-			    apqn, se.index(), cistr
-			);
-                    }
-                    if !(ARGS.dryrun() || fntest) {
-                        let apas = pvap::assoc_state::Associated(se.index());
-                        apqn.set_associate_state(apas)
-                            .map_err(|err| format!("Failure associating APQN {apqn}: {err}"))?;
-                    }
+                    info!(
+                        "Associate APQN {} with uv secrets index {} to match AP config entry {} (step 2/2).\n",
+                        apqn, se.index(), entry
+                    );
+                    let apas = pvap::assoc_state::Associated(se.index());
+                    ap::set_associate_state_verified(apst, apqn, apas, on_external_change)
+                        .map_err(|err| format!("Failure associating APQN {apqn}: {err}"))?;
+                    plan.step(apqn, Action::Associate { index: se.index() }, Some(entry));
                     apconfig_done[ci] = true;
                     apqn_done[ai] = true;
-                    resolved_entries += 1;
+                    plan.satisfied += 1;
                     break;
                 }
                 _ => {
@@ -401,7 +474,81 @@ fn do_ap_config(
         }
     }
 
-    Ok(resolved_entries)
+    // Step 4:
+    // Collect the reasons for the remaining AP config entries that cannot
+    // be fulfilled, e.g. because no card is in the required mode.
+    for (ci, apc) in apconfig.iter().enumerate() {
+        if apconfig_done[ci] {
+            continue;
+        }
+        let mut reasons = std::mem::take(&mut apconfig_reasons[ci]);
+        reasons.extend(
+            mode_mismatches(apc, apqns)
+                .into_iter()
+                .map(plan::Reason::ModeMismatch),
+        );
+        if reasons.is_empty() {
+            reasons.push(plan::Reason::NoMatchingApqn);
+        }
+        plan.unsatisfied.push(plan::Unsatisfied {
+            entry: EntryRef::new(ci, apc),
+            reasons,
+        });
+    }
+
+    Ok(plan)
+}
+
+/// Find the cards blocking an AP config entry by their mode
+///
+/// Returns nothing if at least one APQN is in the mode the AP config
+/// entry requires. Otherwise, each card in another mode is returned
+/// once. The mode of a card can not be changed from within the guest.
+fn mode_mismatches(apc: &ApConfigEntry, apqns: &ApqnList) -> Vec<ModeMismatch> {
+    let required = match apc.mode.as_str() {
+        config::STR_MODE_ACCEL => pvap::apqn_mode::Accel,
+        config::STR_MODE_EP11 => pvap::apqn_mode::Ep11,
+        _ => return Vec::new(),
+    };
+    if apqns.iter().any(|apqn| apqn.mode == required) {
+        return Vec::new();
+    }
+    let mut mismatches: Vec<ModeMismatch> = Vec::new();
+    for apqn in apqns.iter() {
+        if !mismatches.iter().any(|mm| mm.card == apqn.card) {
+            mismatches.push(ModeMismatch {
+                card: apqn.card,
+                current: apqn.mode.clone(),
+                required: required.clone(),
+            });
+        }
+    }
+    mismatches
+}
+
+/// Check that the cards are in the modes the AP config entries require
+///
+/// Fails with all mode mismatches of all AP config entries, see
+/// [`mode_mismatches`]. Such a configuration can not be applied.
+fn check_modes(apconfig: &ApConfigList, apqns: &ApqnList) -> Result<(), String> {
+    let errors: Vec<String> = apconfig
+        .iter()
+        .enumerate()
+        .flat_map(|(ci, apc)| {
+            mode_mismatches(apc, apqns).into_iter().map(move |mm| {
+                format!(
+                    "Error: AP config entry {} cannot be applied: {mm}. \
+                     Convert the card to {} mode outside of the guest, e.g. on the HMC.",
+                    EntryRef::new(ci, apc),
+                    mm.required
+                )
+            })
+        })
+        .collect();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors.join("\n")),
+    }
 }
 
 /// # Panics
@@ -452,6 +599,7 @@ fn config_and_apqn_match(apc: &ApConfigEntry, apqn: &Apqn) -> bool {
 mod tests {
 
     use super::*;
+    use ap::MockApqnState;
     use pv_core::{misc::decode_hex, uv::SecretEntry};
 
     // This is more or less only a test for the do_ap_config() function
@@ -611,9 +759,15 @@ mod tests {
         let apconfig: Vec<ApConfigEntry> = vec![test_apconfigs[0].clone()];
         let apcfglist = ApConfigList::from_apconfigentry_vec(apconfig);
         let mut apqnlist = ApqnList::from_apqn_vec(apqns);
-        let r = do_ap_config(&mut apqnlist, &secretlist, &apcfglist, true);
+        let r = do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            &MockApqnState::default(),
+            ExternalChange::Abort,
+        );
         assert!(r.is_ok());
-        let n = r.unwrap();
+        let n = r.unwrap().satisfied;
         assert!(n == 1);
     }
 
@@ -628,9 +782,15 @@ mod tests {
         let apconfig: Vec<ApConfigEntry> = vec![test_apconfigs[1].clone()];
         let apcfglist = ApConfigList::from_apconfigentry_vec(apconfig);
         let mut apqnlist = ApqnList::from_apqn_vec(apqns);
-        let r = do_ap_config(&mut apqnlist, &secretlist, &apcfglist, true);
+        let r = do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            &MockApqnState::default(),
+            ExternalChange::Abort,
+        );
         assert!(r.is_ok());
-        let n = r.unwrap();
+        let n = r.unwrap().satisfied;
         assert!(n == 1);
     }
 
@@ -651,9 +811,305 @@ mod tests {
         }
         let apcfglist = ApConfigList::from_apconfigentry_vec(apconfig);
         let mut apqnlist = ApqnList::from_apqn_vec(apqns);
-        let r = do_ap_config(&mut apqnlist, &secretlist, &apcfglist, true);
+        let r = do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            &MockApqnState::default(),
+            ExternalChange::Abort,
+        );
         assert!(r.is_ok());
-        let n = r.unwrap();
+        let n = r.unwrap().satisfied;
         assert!(n == 3, "n = {} != 3", n);
     }
+
+    fn apqn_by_name(apqns: &[Apqn], name: &str) -> Apqn {
+        apqns.iter().find(|a| a.name == name).unwrap().clone()
+    }
+
+    /// Mocked sysfs: 10.0007 is already bound and 12.000a is bound and
+    /// associated to a secret no AP config entry asks for.
+    fn make_test_sysfs(apqns: &[Apqn]) -> MockApqnState {
+        let sysfs = MockApqnState::default();
+        let a = apqn_by_name(apqns, "10.0007");
+        sysfs.set_bind_state(&a, pvap::bind_state::Bound).unwrap();
+        let a = apqn_by_name(apqns, "12.000a");
+        sysfs.set_bind_state(&a, pvap::bind_state::Bound).unwrap();
+        sysfs
+            .set_associate_state(&a, pvap::assoc_state::Associated(33))
+            .unwrap();
+        sysfs
+    }
+
+    fn run_test_config(apst: &dyn ApqnState) -> ApPlan {
+        let secrets = make_test_secrets();
+        let secretlist = SecretList::new(secrets.len() as u16, secrets);
+        let apcfglist = ApConfigList::from_apconfigentry_vec(make_test_apconfigs());
+        let mut apqnlist = ApqnList::from_apqn_vec(make_test_apqns());
+        do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            apst,
+            ExternalChange::Abort,
+        )
+        .unwrap()
+    }
+
+    fn step(apqn: &str, action: Action, entry: Option<(usize, &str)>) -> plan::Step {
+        plan::Step {
+            apqn: apqn.to_string(),
+            action,
+            entry: entry.map(|(index, name)| EntryRef {
+                index,
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    fn expected_steps() -> Vec<plan::Step> {
+        vec![
+            step("10.0007", Action::Keep, Some((1, "test_1"))),
+            step("12.000a", Action::Unbind, None),
+            step("11.0008", Action::Bind, Some((2, "test_2"))),
+            step(
+                "11.0008",
+                Action::Associate { index: 13 },
+                Some((2, "test_2")),
+            ),
+            step("12.0009", Action::Bind, Some((3, "test_3"))),
+            step(
+                "12.0009",
+                Action::Associate { index: 15 },
+                Some((3, "test_3")),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_do_ap_config_apply() {
+        let apqns = make_test_apqns();
+        let sysfs = make_test_sysfs(&apqns);
+        let plan = run_test_config(&sysfs);
+
+        assert_eq!(plan.steps, expected_steps());
+        assert_eq!((plan.entries, plan.satisfied), (4, 3));
+        assert!(!plan.is_satisfiable());
+        assert_eq!(
+            plan.unsatisfied,
+            [plan::Unsatisfied {
+                entry: EntryRef {
+                    index: 4,
+                    name: "test_4".to_string()
+                },
+                reasons: vec![plan::Reason::NoMatchingApqn],
+            }]
+        );
+        // 3 EP11 entries, 4 EP11 APQNs with info
+        assert_eq!(plan.mkvp_checks.len(), 12);
+        let passed: Vec<_> = plan
+            .mkvp_checks
+            .iter()
+            .filter(|c| c.passed)
+            .map(|c| (c.entry.index, c.apqn.as_str()))
+            .collect();
+        assert_eq!(passed.len(), 2);
+        assert!(passed.contains(&(2, "11.0008")));
+        assert!(passed.contains(&(3, "12.0009")));
+
+        // the mocked sysfs has been modified
+        let state = |name| {
+            let a = apqn_by_name(&apqns, name);
+            (sysfs.bind_of(&a), sysfs.assoc_of(&a))
+        };
+        use pvap::{assoc_state::*, bind_state::*};
+        assert_eq!(state("10.0007"), (Bound, Unassociated));
+        assert_eq!(state("11.0008"), (Bound, Associated(13)));
+        assert_eq!(state("12.0009"), (Bound, Associated(15)));
+        assert_eq!(state("12.000a"), (Unbound, Unassociated));
+        assert_eq!(state("13.000d"), (Unbound, Unassociated));
+    }
+
+    #[test]
+    fn test_do_ap_config_dry_run() {
+        let apqns = make_test_apqns();
+        let sysfs = make_test_sysfs(&apqns);
+        let plan = run_test_config(&DryRunApqnState::new(&sysfs));
+
+        // same plan as for the real application
+        assert_eq!(plan, run_test_config(&make_test_sysfs(&apqns)));
+
+        // but the mocked sysfs below the dry run is untouched
+        let orig = make_test_sysfs(&apqns);
+        for a in &apqns {
+            assert_eq!(sysfs.bind_of(a), orig.bind_of(a));
+            assert_eq!(sysfs.assoc_of(a), orig.assoc_of(a));
+        }
+    }
+
+    #[test]
+    fn test_do_ap_config_secret_not_found() {
+        let test_apqns = make_test_apqns();
+        let mut apqnlist = ApqnList::from_apqn_vec(vec![test_apqns[1].clone()]);
+        let mut secrets = make_test_secrets();
+        secrets.truncate(1);
+        let secretlist = SecretList::new(secrets.len() as u16, secrets);
+        let apcfglist =
+            ApConfigList::from_apconfigentry_vec(vec![make_test_apconfigs()[1].clone()]);
+        let sysfs = MockApqnState::default();
+        let plan = do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            &sysfs,
+            ExternalChange::Abort,
+        )
+        .unwrap();
+        assert!(plan.steps.is_empty());
+        assert_eq!(
+            plan.unsatisfied[0].reasons,
+            [plan::Reason::SecretNotFound {
+                secretid: "bc9d46c052bc3574454c5715757274629a283767ed237922cfb8651c0e77320a"
+                    .to_string()
+            }]
+        );
+        assert_eq!(sysfs.bind_of(&test_apqns[1]), pvap::bind_state::Unbound);
+    }
+
+    #[test]
+    fn test_plan_output() {
+        let apqns = make_test_apqns();
+        let mut plan = ApPlan::new(2);
+        plan.satisfied = 1;
+        plan.step(
+            &apqns[0],
+            Action::Bind,
+            Some(EntryRef::new(0, &make_test_apconfigs()[0])),
+        );
+        plan.step(&apqns[3], Action::Unbind, None);
+        plan.unsatisfied.push(plan::Unsatisfied {
+            entry: EntryRef {
+                index: 2,
+                name: String::new(),
+            },
+            reasons: mode_mismatches(
+                &make_test_apconfigs()[1],
+                &ApqnList::from_apqn_vec(vec![apqns[0].clone()]),
+            )
+            .into_iter()
+            .map(plan::Reason::ModeMismatch)
+            .collect(),
+        });
+
+        assert_eq!(
+            plan.to_string(),
+            "Planned actions:
+  Bind APQN 10.0007 for AP config entry #1 'test_1'
+  Unbind APQN 12.000a
+Unsatisfied AP config entries:
+  #2: card 10 is in accelerator mode, but EP11 mode is required
+1 out of 2 AP config entries can be satisfied."
+        );
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap(),
+            serde_json::json!({
+                "entries": 2,
+                "satisfied": 1,
+                "steps": [
+                    {"apqn": "10.0007", "action": "bind", "entry": {"index": 1, "name": "test_1"}},
+                    {"apqn": "12.000a", "action": "unbind"},
+                ],
+                "mkvp_checks": [],
+                "unsatisfied": [
+                    {
+                        "entry": {"index": 2},
+                        "reasons": [{
+                            "reason": "mode-mismatch",
+                            "card": 16,
+                            "current": "accelerator",
+                            "required": "EP11",
+                        }],
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_mode_mismatches_match() {
+        let apqns = ApqnList::from_apqn_vec(make_test_apqns());
+        for apc in make_test_apconfigs() {
+            assert!(mode_mismatches(&apc, &apqns).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_mode_mismatches_ep11() {
+        // only EP11 APQNs, some of them on the same card
+        let test_apqns = make_test_apqns();
+        let apqns = ApqnList::from_apqn_vec(test_apqns[1..].to_vec());
+        let apc = make_test_apconfigs()[0].clone();
+        let mm = mode_mismatches(&apc, &apqns);
+        let cards: Vec<u32> = mm.iter().map(|mm| mm.card).collect();
+        assert_eq!(cards, [17, 18, 19]);
+        assert!(
+            mm.iter()
+                .all(|mm| mm.current == pvap::apqn_mode::Ep11
+                    && mm.required == pvap::apqn_mode::Accel)
+        );
+        assert_eq!(
+            mm[0].to_string(),
+            "card 11 is in EP11 mode, but accelerator mode is required"
+        );
+
+        // the config entry cannot be fulfilled
+        let secrets = make_test_secrets();
+        let secretlist = SecretList::new(secrets.len() as u16, secrets);
+        let apcfglist = ApConfigList::from_apconfigentry_vec(vec![apc]);
+        let mut apqnlist = apqns;
+        let plan = do_ap_config(
+            &mut apqnlist,
+            &secretlist,
+            &apcfglist,
+            &MockApqnState::default(),
+            ExternalChange::Abort,
+        )
+        .unwrap();
+        assert_eq!(plan.satisfied, 0);
+        assert_eq!(plan.unsatisfied.len(), 1);
+        assert_eq!(plan.unsatisfied[0].reasons.len(), 3);
+    }
+
+    #[test]
+    fn test_check_modes() {
+        let test_apqns = make_test_apqns();
+        let apconfig = ApConfigList::from_apconfigentry_vec(make_test_apconfigs());
+        let apqns = ApqnList::from_apqn_vec(test_apqns.clone());
+        assert_eq!(check_modes(&apconfig, &apqns), Ok(()));
+
+        let apqns = ApqnList::from_apqn_vec(vec![test_apqns[0].clone()]);
+        let err = check_modes(&apconfig, &apqns).unwrap_err();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines.len(), apconfig.len() - 1);
+        assert!(lines.iter().all(|l| l.contains(
+            "card 10 is in accelerator mode, but EP11 mode is required. \
+             Convert the card to EP11 mode outside of the guest"
+        )));
+    }
+
+    #[test]
+    fn test_mode_mismatches_accel() {
+        let test_apqns = make_test_apqns();
+        let apqns = ApqnList::from_apqn_vec(vec![test_apqns[0].clone()]);
+        for apc in &make_test_apconfigs()[1..] {
+            assert_eq!(
+                mode_mismatches(apc, &apqns),
+                [ModeMismatch {
+                    card: 16,
+                    current: pvap::apqn_mode::Accel,
+                    required: pvap::apqn_mode::Ep11,
+                }]
+            );
+        }
+    }
 }
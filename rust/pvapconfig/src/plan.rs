@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+//
+//! The AP configuration plan of pvapconfig
+//
+
+use crate::config::ApConfigEntry;
+use pv_core::ap::{apqn_mode::ApqnMode, Apqn};
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+fn ser_display<T: fmt::Display, S: Serializer>(v: &T, ser: S) -> Result<S::Ok, S::Error> {
+    ser.collect_str(v)
+}
+
+/// Reference to an AP config entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntryRef {
+    /// Position of the entry in the config file, starting with 1
+    pub index: usize,
+    /// Name of the entry, may be empty
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+}
+
+impl EntryRef {
+    /// Reference the AP config entry at (zero based) index ci.
+    pub fn new(ci: usize, apc: &ApConfigEntry) -> Self {
+        Self {
+            index: ci + 1,
+            name: apc.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for EntryRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "#{}", self.index)
+        } else {
+            write!(f, "#{} '{}'", self.index, self.name)
+        }
+    }
+}
+
+/// Action on an APQN
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "action")]
+pub enum Action {
+    /// The APQN already satisfies the AP config entry
+    Keep,
+    /// Bind the APQN
+    Bind,
+    /// Associate the APQN with the UV secret at index
+    Associate { index: u16 },
+    /// Unbind the APQN as it does not match to any AP config entry
+    Unbind,
+}
+
+/// One step of the plan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    /// Name of the APQN, e.g. "10.0007"
+    pub apqn: String,
+    #[serde(flatten)]
+    pub action: Action,
+    /// The AP config entry this step is done for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<EntryRef>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.action {
+            Action::Keep => write!(f, "Keep APQN {}", self.apqn)?,
+            Action::Bind => write!(f, "Bind APQN {}", self.apqn)?,
+            Action::Associate { index } => write!(
+                f,
+                "Associate APQN {} with UV secrets index {index}",
+                self.apqn
+            )?,
+            Action::Unbind => write!(f, "Unbind APQN {}", self.apqn)?,
+        }
+        if let Some(entry) = &self.entry {
+            write!(f, " for AP config entry {entry}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of comparing the MKVP of an EP11 AP config entry with an EP11 APQN
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MkvpCheck {
+    pub entry: EntryRef,
+    pub apqn: String,
+    pub passed: bool,
+}
+
+/// Card which is not in the mode required by an AP config entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModeMismatch {
+    pub card: u32,
+    #[serde(serialize_with = "ser_display")]
+    pub current: ApqnMode,
+    #[serde(serialize_with = "ser_display")]
+    pub required: ApqnMode,
+}
+
+impl fmt::Display for ModeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "card {:02x} is in {} mode, but {} mode is required",
+            self.card, self.current, self.required
+        )
+    }
+}
+
+/// Reason why an AP config entry can not be satisfied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "reason")]
+pub enum Reason {
+    /// A card is not in the mode the entry requires
+    ModeMismatch(ModeMismatch),
+    /// The association secret of the entry is not in the UV secrets list
+    SecretNotFound { secretid: String },
+    /// No (unused) APQN matches to the entry
+    NoMatchingApqn,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModeMismatch(mm) => write!(f, "{mm}"),
+            Self::SecretNotFound { secretid } => {
+                write!(f, "secret id '{secretid}' not found in UV secrets list")
+            }
+            Self::NoMatchingApqn => write!(f, "no available APQN matches"),
+        }
+    }
+}
+
+/// AP config entry which can not be satisfied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Unsatisfied {
+    pub entry: EntryRef,
+    pub reasons: Vec<Reason>,
+}
+
+/// The actions to apply an AP configuration
+///
+/// The plan is built by the same code that applies the AP
+/// configuration, so a dry run shows exactly what a real run would do.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ApPlan {
+    /// Number of AP config entries
+    pub entries: usize,
+    /// Number of satisfied AP config entries
+    pub satisfied: usize,
+    pub steps: Vec<Step>,
+    pub mkvp_checks: Vec<MkvpCheck>,
+    pub unsatisfied: Vec<Unsatisfied>,
+}
+
+impl ApPlan {
+    /// Create an empty plan for the given number of AP config entries.
+    pub fn new(entries: usize) -> Self {
+        Self {
+            entries,
+            ..Default::default()
+        }
+    }
+
+    /// Add a step on an APQN.
+    pub fn step(&mut self, apqn: &Apqn, action: Action, entry: Option<EntryRef>) {
+        self.steps.push(Step {
+            apqn: apqn.name.clone(),
+            action,
+            entry,
+        });
+    }
+
+    /// Returns true if all AP config entries can be satisfied.
+    pub fn is_satisfiable(&self) -> bool {
+        self.unsatisfied.is_empty() && self.satisfied == self.entries
+    }
+}
+
+impl fmt::Display for ApPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Planned actions:")?;
+        if self.steps.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for step in &self.steps {
+            writeln!(f, "  {step}")?;
+        }
+        if !self.mkvp_checks.is_empty() {
+            writeln!(f, "MKVP checks:")?;
+            for check in &self.mkvp_checks {
+                writeln!(
+                    f,
+                    "  {}: APQN {} for AP config entry {}",
+                    if check.passed { "passed" } else { "failed" },
+                    check.apqn,
+                    check.entry
+                )?;
+            }
+        }
+        if !self.unsatisfied.is_empty() {
+            writeln!(f, "Unsatisfied AP config entries:")?;
+            for u in &self.unsatisfied {
+                for reason in &u.reasons {
+                    writeln!(f, "  {}: {reason}", u.entry)?;
+                }
+            }
+        }
+        write!(
+            f,
+            "{} out of {} AP config entries can be satisfied.",
+            self.satisfied, self.entries
+        )
+    }
+}
@@ -58,7 +58,9 @@ pub fn gather_secrets() -> Result<SecretList, String> {
     let mut cmd = ListCmd::default();
     match uv.send_cmd(&mut cmd).map_err(|e| format!("{e:?}"))? {
         UvcSuccess::RC_SUCCESS => (),
-        UvcSuccess::RC_MORE_DATA => println!("Warning: There is more data available than expected"),
+        UvcSuccess::RC_MORE_DATA => {
+            eprintln!("Warning: There is more data available than expected")
+        }
     };
     cmd.try_into().map_err(|e| format!("{e:?}"))
 }
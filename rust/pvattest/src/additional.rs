@@ -3,7 +3,8 @@
 // Copyright IBM Corp. 2024
 
 use crate::exchange::ExchangeFormatResponse;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use log::warn;
 use pv::attest::{AdditionalData, AttestationFlags};
 use serde::Serialize;
 use std::fmt::Display;
@@ -20,15 +21,45 @@ pub struct AttestationResult<'a> {
     pub user_data: Option<HexSlice<'a>>,
 }
 
+/// Names of the additional-data fields requested by `flags`
+fn requested_fields(flags: &AttestationFlags) -> Vec<&'static str> {
+    [
+        (flags.image_phkh(), "image_phkh"),
+        (flags.attest_phkh(), "attestation_phkh"),
+        (flags.secret_store_hash(), "secret_store_hash"),
+        (flags.firmware_state(), "firmware_state"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect()
+}
+
 impl<'a> AttestationResult<'a> {
+    /// Interprets the response using the `flags` of the attestation request
+    ///
+    /// Fails if the response lacks additional-data that was requested. Warns if it contains more
+    /// additional-data than requested.
     pub fn from_exchange(
         resp: &'a ExchangeFormatResponse,
         flags: &AttestationFlags,
     ) -> Result<Self> {
+        let requested = requested_fields(flags);
+        if resp.additional().is_none() && !requested.is_empty() {
+            bail!(
+                "The attestation request asked for additional-data ({}), but the response contains none",
+                requested.join(", ")
+            );
+        }
         let add_fields = resp
             .additional()
-            .map(|a| AdditionalData::from_slice_sized(a, flags))
+            .map(|a| AdditionalData::<HexSlice>::from_slice_sized(a, flags))
             .transpose()?;
+        if let Some(unrecognized) = add_fields.as_ref().and_then(|a| a.unrecognized()) {
+            warn!(
+                "The response contains {} bytes of additional-data that were not requested",
+                unrecognized.as_ref().len()
+            );
+        }
         Ok(Self {
             cuid: resp.config_uid().into(),
             add: resp.additional().map(|a| a.into()),
@@ -57,3 +88,101 @@ impl Display for AttestationResult<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CUID: [u8; 16] = [0x11; 16];
+
+    fn flags(ssh: bool) -> AttestationFlags {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        if ssh {
+            flags.set_secret_store_hash();
+        }
+        flags
+    }
+
+    fn response(additional: Option<Vec<u8>>) -> ExchangeFormatResponse {
+        ExchangeFormatResponse::new(vec![0; 0x20], vec![0; 0x40], additional, None, CUID).unwrap()
+    }
+
+    #[test]
+    fn requested_fields_names() {
+        let mut flags = flags(true);
+        flags.set_firmware_state();
+        assert_eq!(
+            requested_fields(&flags),
+            ["image_phkh", "secret_store_hash", "firmware_state"]
+        );
+        assert!(requested_fields(&AttestationFlags::default()).is_empty());
+    }
+
+    #[test]
+    fn applied_equal() {
+        let resp = response(Some(vec![1; 0x60]));
+        let res = AttestationResult::from_exchange(&resp, &flags(true)).unwrap();
+        let add = res.add_fields.unwrap();
+        assert!(add.image_public_host_key_hash().is_some());
+        assert!(add.secret_store_hash().is_some());
+        assert!(add.unrecognized().is_none());
+    }
+
+    #[test]
+    fn applied_subset() {
+        // secret store hash requested, but only the image PHKH returned
+        let resp = response(Some(vec![1; 0x20]));
+        assert!(AttestationResult::from_exchange(&resp, &flags(true)).is_err());
+    }
+
+    #[test]
+    fn applied_none() {
+        let resp = response(None);
+        let err = AttestationResult::from_exchange(&resp, &flags(true))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("(image_phkh, secret_store_hash)"));
+
+        let res = AttestationResult::from_exchange(&resp, &AttestationFlags::default()).unwrap();
+        assert!(res.add_fields.is_none());
+    }
+
+    #[test]
+    fn applied_all_unknown() {
+        let mut flags = flags(true);
+        flags.set_attest_phkh();
+        flags.set_firmware_state();
+        let fields = [
+            ("image_phkh", 1, 0x20),
+            ("attestation_phkh", 2, 0x20),
+            ("secret_store_hash", 3, 0x40),
+            ("firmware_state", 4, 0x140),
+            ("unrecognized", 5, 0x10),
+        ];
+        let blob = fields
+            .iter()
+            .flat_map(|(_, v, size)| vec![*v; *size])
+            .collect();
+        let resp = response(Some(blob));
+        let res = AttestationResult::from_exchange(&resp, &flags).unwrap();
+
+        let json = serde_json::to_value(res.add_fields.unwrap()).unwrap();
+        let json = json.as_object().unwrap();
+        assert_eq!(json.len(), fields.len());
+        for (name, v, size) in fields {
+            let exp = format!("0x{}", format!("{v:02x}").repeat(size));
+            assert_eq!(json[name], exp, "{name}");
+        }
+    }
+
+    #[test]
+    fn applied_superset() {
+        // secret store hash returned, but only the image PHKH requested
+        let resp = response(Some(vec![1; 0x60]));
+        let res = AttestationResult::from_exchange(&resp, &flags(false)).unwrap();
+        let add = res.add_fields.unwrap();
+        assert!(add.secret_store_hash().is_none());
+        assert_eq!(add.unrecognized().map(|u| u.as_ref().len()), Some(0x40));
+    }
+}
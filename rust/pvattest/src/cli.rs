@@ -2,10 +2,19 @@
 //
 // Copyright IBM Corp. 2024
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::{Args, Parser, Subcommand, ValueEnum, ValueHint};
-use utils::{CertificateOptions, DeprecatedVerbosityOptions};
+use clap::{ArgGroup, ArgMatches, Args, FromArgMatches, Parser, Subcommand, ValueEnum, ValueHint};
+use pv::{attest::SECRET_TAG_SIZE, misc::decode_hex_formatted};
+#[cfg(target_arch = "s390x")]
+use utils::UvDeviceOptions;
+use utils::{CertVerifyOptions, CertificateOptions, DeprecatedVerbosityOptions, SizeValueParser};
+
+/// Exit status of the `verify` and `check` commands
+const EXIT_STATUS_HELP: &str = "Exit status:
+  0  The attestation response is verified, or fulfills all policies
+  1  An error occurred, e.g., an input file is not readable or malformed
+  2  The verification failed, or the attestation response does not fulfill all policies";
 
 /// create, perform, and verify attestation measurements
 ///
@@ -32,7 +41,8 @@ pub enum Command {
     /// Create attestation measurement requests to attest an IBM Secure Execution guest. Only build
     /// attestation requests in a trusted environment such as your Workstation. To avoid
     /// compromising the attestation do not publish the attestation request protection key and
-    /// shred it after verification. Every 'create' will generate a new, random protection key.
+    /// shred it after verification. Every 'create' will generate a new, random protection key,
+    /// unless an existing key is given with '--arpk-in'.
     Create(Box<CreateAttOpt>),
 
     /// Send the attestation request to the Ultravisor.
@@ -43,6 +53,14 @@ pub enum Command {
     /// from the Ultravisor.
     Perform(PerformAttOpt),
 
+    /// Perform attestation requests received over the network.
+    ///
+    /// Listen for attestation requests sent by 'pvattest perform --remote' and send them to the
+    /// Ultravisor through ’/dev/uv’. Run this command inside the IBM Secure Execution guest to be
+    /// attested. The responses are protected by the attestation request protection key, so that
+    /// only the creator of a request can verify the response.
+    Serve(ServeOpt),
+
     /// Verify an attestation response.
     ///
     /// Verify that a previously generated attestation measurement of an IBM Secure Execution guest
@@ -54,12 +72,32 @@ pub enum Command {
     /// command solely verifies that the Attestation measurement is correct. It does not check for
     /// the content of additional data or user data. See `pvattest check` for policy checks after
     /// you verified the Attestation measurement.
+    #[command(after_long_help = EXIT_STATUS_HELP)]
     Verify(VerifyOpt),
 
     /// Check if the attestation result matches defined policies.
     ///
     /// After the attestation verification, check whether the attestation result complies with user-defined policies.
-    Check(CheckOpt),
+    /// The check result lists every check with its status (passed, failed, or skipped), and
+    /// whether the attestation response fulfills all policies. A failed check does not stop the
+    /// remaining checks. Errors, e.g., an unreadable input file, abort the check immediately.
+    ///
+    /// If certificates are given with --cert, the host-key documents given with
+    /// --host-key-document are verified and must be valid. The check result in json or yaml
+    /// format contains the validity windows of the host-key documents, the certificates of their
+    /// chain of trust, and the CRLs used for the verification, and the time in seconds until the
+    /// first of them expires.
+    #[command(after_long_help = EXIT_STATUS_HELP)]
+    Check(Box<CheckOpt>),
+
+    /// Show the differences between two attestation responses.
+    ///
+    /// Compare the configuration UID, user data, and additional data of two attestation responses,
+    /// e.g., two responses of the same guest created at different times. Fields that are only
+    /// present in one of the responses are shown as added or removed. Only compare responses in a
+    /// trusted environment, such as your workstation. The diff command does not verify the
+    /// attestation measurement. Use `pvattest verify` to verify the responses.
+    Diff(DiffOpt),
 
     /// Print version information and exit.
     #[command(aliases(["--version"]), hide(true))]
@@ -71,15 +109,45 @@ pub struct CreateAttOpt {
     #[command(flatten)]
     pub certificate_args: CertificateOptions,
 
-    /// Write the generated request to FILE.
+    /// Write the generated request to FILE. Use '-' for stdout.
     #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub output: String,
 
+    /// Overwrite an existing output file.
+    #[arg(long)]
+    pub force: bool,
+
     /// Save the protection key as unencrypted GCM-AES256 key in FILE
     ///
     /// Do not publish this key, otherwise your attestation is compromised.
-    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
-    pub arpk: String,
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,
+          required_unless_present("arpk_in"), conflicts_with("arpk_in"))]
+    pub arpk: Option<String>,
+
+    /// Use the existing protection key in FILE instead of generating one.
+    ///
+    /// FILE must contain an unencrypted GCM-AES256 key of 32 bytes, e.g., the key of an earlier
+    /// 'pvattest create'. No new protection key is generated and FILE is not modified. Use this to
+    /// create requests for a protection key that is managed by the verifier.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
+    pub arpk_in: Option<String>,
+
+    /// Use NONCE instead of a randomly generated nonce.
+    ///
+    /// NONCE is either the hex-encoded 16-byte nonce or a file containing the nonce as raw 16
+    /// bytes or hex-encoded. Use this if the verifier chooses the nonce, e.g., to bind the
+    /// attestation to a larger challenge. The nonce is part of the encrypted request and
+    /// 'pvattest verify' uses it to verify the attestation measurement.
+    #[arg(long, value_name = "NONCE", value_parser = parse_nonce, value_hint = ValueHint::FilePath,
+          conflicts_with("no_nonce"))]
+    pub nonce_in: Option<HexOrFile<NONCE_SIZE>>,
+
+    /// Create the request without a nonce.
+    ///
+    /// The attestation measurement does not cover a nonce then. Use this only if the firmware
+    /// rejects attestation requests with a nonce.
+    #[arg(long)]
+    pub no_nonce: bool,
 
     /// Specify additional data for the request.
     ///
@@ -110,29 +178,28 @@ pub enum AttAddFlags {
     FirmwareState,
 }
 
-// all members s390x only
 #[derive(Args, Debug)]
 pub struct PerformAttOpt {
     /// Specify the request to be sent.
-    #[cfg(target_arch = "s390x")]
     #[arg(hide=true, short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub input: Option<String>,
 
     /// Specify the request to be sent.
-    #[cfg(target_arch = "s390x")]
     #[arg(value_name = "IN", value_hint = ValueHint::FilePath, required_unless_present("input"), conflicts_with("input"))]
     pub input_pos: Option<String>,
 
-    /// Write the result to FILE.
-    #[cfg(target_arch = "s390x")]
+    /// Write the result to FILE. Use '-' for stdout.
     #[arg(hide=true, short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub output: Option<String>,
 
-    /// Write the result to FILE.
+    /// Write the result to FILE. Use '-' for stdout.
     #[arg(value_name = "OUT", value_hint = ValueHint::FilePath, required_unless_present("output"), conflicts_with("output"))]
-    #[cfg(target_arch = "s390x")]
     pub output_pos: Option<String>,
 
+    /// Overwrite an existing output file.
+    #[arg(long)]
+    pub force: bool,
+
     /// Provide up to 256 bytes of user input
     ///
     /// User-data is arbitrary user-defined data appended to the Attestation measurement.
@@ -140,17 +207,31 @@ pub struct PerformAttOpt {
     /// May be any arbitrary data, as long as it is less or equal to 256 bytes
     #[arg(short, long, value_name = "File", value_hint = ValueHint::FilePath,)]
     pub user_data: Option<String>,
+
+    /// Send the request to a 'pvattest serve' running at ADDR.
+    ///
+    /// Instead of the local Ultravisor, the Ultravisor of the IBM Secure Execution guest running
+    /// 'pvattest serve' performs the attestation. ADDR is either HOST:PORT for TCP or
+    /// vsock:CID:PORT for vsock. Available on all architectures.
+    #[cfg_attr(target_arch = "s390x", arg(conflicts_with_all(["device", "device_no_check"])))]
+    #[cfg_attr(feature = "simulate", arg(conflicts_with("simulate")))]
+    #[arg(long, value_name = "ADDR", value_parser = parse_remote_addr)]
+    pub remote: Option<RemoteAddr>,
+
+    #[command(flatten)]
+    pub backend: UvBackendOpt,
 }
 
-#[cfg(target_arch = "s390x")]
 #[derive(Debug)]
 pub struct PerformAttOptComb<'a> {
     pub input: &'a str,
     pub output: &'a str,
+    pub force: bool,
     pub user_data: Option<&'a str>,
+    pub remote: Option<&'a RemoteAddr>,
+    pub backend: &'a UvBackendOpt,
 }
 
-#[cfg(target_arch = "s390x")]
 impl<'a> From<&'a PerformAttOpt> for PerformAttOptComb<'a> {
     fn from(value: &'a PerformAttOpt) -> Self {
         let input = match (&value.input, &value.input_pos) {
@@ -169,21 +250,128 @@ impl<'a> From<&'a PerformAttOpt> for PerformAttOptComb<'a> {
         Self {
             input,
             output,
+            force: value.force,
             user_data,
+            remote: value.remote.as_ref(),
+            backend: &value.backend,
         }
     }
 }
 
+/// Selects the Ultravisor that performs the attestation
+// all members s390x or simulate only
+#[derive(Args, Debug)]
+pub struct UvBackendOpt {
+    #[cfg(target_arch = "s390x")]
+    #[command(flatten)]
+    pub device: UvDeviceOptions,
+
+    /// Use an INSECURE software simulation instead of the Ultravisor.
+    ///
+    /// Never use the simulation for a real attestation! It calculates the response like an
+    /// Ultravisor running an IBM Secure Execution guest with the SE-header in HDR would. The
+    /// private host-key of the simulated host is publicly known. Create the request with the
+    /// host-key document 'tests/assets/simulate/host.crt' of the pvattest sources and
+    /// '--no-verify'. Only available if pvattest was built with the 'simulate' feature.
+    #[cfg(feature = "simulate")]
+    #[cfg_attr(target_arch = "s390x", arg(conflicts_with_all(["device", "device_no_check"])))]
+    #[arg(long, value_name = "HDR", value_hint = ValueHint::FilePath,)]
+    pub simulate: Option<PathBuf>,
+}
+
+/// CID to listen on any vsock address (VMADDR_CID_ANY)
+pub const VSOCK_CID_ANY: u32 = u32::MAX;
+
+/// Address of a 'pvattest serve'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteAddr {
+    /// TCP address, e.g., `localhost:7070` or `[::1]:7070`
+    Tcp(String),
+    /// vsock context ID and port
+    Vsock { cid: u32, port: u32 },
+}
+
+impl std::fmt::Display for RemoteAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Vsock { cid, port } if *cid == VSOCK_CID_ANY => write!(f, "vsock:any:{port}"),
+            Self::Vsock { cid, port } => write!(f, "vsock:{cid}:{port}"),
+        }
+    }
+}
+
+/// Parse `HOST:PORT` as TCP address or `vsock:CID:PORT` as vsock address
+///
+/// The CID `any` listens on all vsock addresses.
+fn parse_remote_addr(s: &str) -> Result<RemoteAddr, String> {
+    let invalid = || format!("'{s}' is no valid address. Use HOST:PORT or vsock:CID:PORT");
+    if let Some(vsock) = s.strip_prefix("vsock:") {
+        let (cid, port) = vsock.split_once(':').ok_or_else(invalid)?;
+        let cid = match cid {
+            "any" => VSOCK_CID_ANY,
+            cid => cid.parse().map_err(|_| invalid())?,
+        };
+        let port = port.parse().map_err(|_| invalid())?;
+        return Ok(RemoteAddr::Vsock { cid, port });
+    }
+    match s.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(RemoteAddr::Tcp(s.to_string()))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Maximum payload size of a remote attestation frame
+///
+/// Attestation requests and responses are at most a few pages large.
+pub const MAX_FRAME_SIZE: u32 = 0x10_0000;
+
+#[derive(Args, Debug)]
+pub struct ServeOpt {
+    /// Listen for attestation requests at ADDR.
+    ///
+    /// ADDR is either HOST:PORT for TCP or vsock:CID:PORT for vsock. Use the CID 'any' to listen
+    /// on all vsock addresses of this guest.
+    #[arg(long, value_name = "ADDR", value_parser = parse_remote_addr)]
+    pub listen: RemoteAddr,
+
+    /// Serve at most NUM connections at the same time.
+    ///
+    /// Further connections wait until a connection finished.
+    #[arg(long, value_name = "NUM", default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    pub max_connections: u16,
+
+    /// Reject attestation requests larger than SIZE bytes.
+    ///
+    /// SIZE is a number of bytes with an optional K or M suffix (1024-based). Requests are at most
+    /// a few pages large.
+    #[arg(long, value_name = "SIZE", default_value = "1M",
+          value_parser = SizeValueParser::new().range(1..=MAX_FRAME_SIZE.into()))]
+    pub max_request_size: u64,
+
+    #[command(flatten)]
+    pub backend: UvBackendOpt,
+}
+
 #[derive(Args, Debug)]
 pub struct VerifyOpt {
     /// Specify the attestation response to be verified.
     #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub input: String,
 
-    /// Specify the output for the verification result
+    #[command(flatten)]
+    pub input_opt: ResponseInputOpt,
+
+    /// Specify the output for the verification result. Use '-' for stdout.
     #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub output: Option<String>,
 
+    /// Overwrite an existing output file.
+    #[arg(long)]
+    pub force: bool,
+
     /// Specifies the header of the guest image.
     ///
     /// Can be an IBM Secure Execution image created by genprotimg or an extracted IBM Secure
@@ -202,6 +390,14 @@ pub struct VerifyOpt {
     #[arg(long, value_enum, default_value_t)]
     pub format: OutputType,
 
+    /// Print the decoded additional-data in FORMAT to stdout.
+    ///
+    /// Decodes the additional-data fields requested by the attestation request, e.g., the image
+    /// and attestation public host-key hashes, the secret store hash, and the firmware state.
+    /// Additional-data that the request did not ask for is printed as unrecognized raw data.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub print_additional: Option<OutputType>,
+
     /// Write the user data to the FILE if any.
     ///
     /// Writes the user data, if the response contains any, to FILE
@@ -212,23 +408,139 @@ pub struct VerifyOpt {
     pub user_data: Option<String>,
 }
 
+#[derive(Args, Debug)]
+pub struct DiffOpt {
+    /// Specify the first attestation response.
+    #[arg(value_name = "RESP_A", value_hint = ValueHint::FilePath,)]
+    pub first: String,
+
+    /// Specify the second attestation response.
+    #[arg(value_name = "RESP_B", value_hint = ValueHint::FilePath,)]
+    pub second: String,
+
+    /// Use FILE to decrypt the attestation requests.
+    ///
+    /// Specify once if both responses were created with the same protection key or twice to
+    /// specify one key for each response in the order of the responses.
+    /// Do not publish this key, otherwise your attestation is compromised.
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, required = true)]
+    pub arpk: Vec<String>,
+
+    /// Write the differences to FILE. Use '-' for stdout.
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
+    pub output: Option<String>,
+
+    /// Overwrite an existing output file.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Define the output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputType,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
 pub enum OutputType {
     /// Use yaml format.
     #[default]
     Yaml,
+    /// Use json format.
+    Json,
+    /// Use a human readable format.
+    Human,
+}
+
+/// Format of an attestation response
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Default)]
+pub enum ResponseFormat {
+    /// Detect the format.
+    #[default]
+    Auto,
+    /// Use the binary format written by 'pvattest perform'.
+    Binary,
+    /// Use the base64-encoded binary format.
+    Base64,
+    /// Use a JSON object with base64-encoded fields, see --json-fields.
+    Json,
+}
+
+impl std::fmt::Display for ResponseFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Auto => "auto",
+            Self::Binary => "binary",
+            Self::Base64 => "base64",
+            Self::Json => "json",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Names of the fields of an attestation response in JSON format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonFields {
+    /// Field with the base64-encoded attestation response
+    pub response: String,
+    /// Optional field with the base64-encoded additional-data
+    pub additional: Option<String>,
+}
+
+/// Parses RESP[,ADD]
+fn parse_json_fields(s: &str) -> Result<JsonFields, String> {
+    let (response, additional) = match s.split_once(',') {
+        Some((response, additional)) => (response, Some(additional)),
+        None => (s, None),
+    };
+    if response.is_empty() || additional.is_some_and(|a| a.is_empty() || a.contains(',')) {
+        return Err("Must be RESP or RESP,ADD with non-empty field names".to_string());
+    }
+    Ok(JsonFields {
+        response: response.to_string(),
+        additional: additional.map(str::to_string),
+    })
+}
+
+#[derive(Args, Debug)]
+pub struct ResponseInputOpt {
+    /// Read the attestation response in FORMAT.
+    ///
+    /// By default, the binary format written by 'pvattest perform', its base64 encoding, and a
+    /// JSON object as described at --json-fields are tried in this order.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t)]
+    pub input_format: ResponseFormat,
+
+    /// Read the attestation response in JSON format from the fields RESP and ADD.
+    ///
+    /// RESP is the field with the base64-encoded attestation response. ADD is an optional field
+    /// with the base64-encoded additional-data. The additional-data is used if the attestation
+    /// response contains none, otherwise it must match the additional-data of the response.
+    #[arg(
+        long,
+        value_name = "RESP[,ADD]",
+        default_value = "response,additional-data",
+        value_parser = parse_json_fields
+    )]
+    pub json_fields: JsonFields,
 }
 
 #[derive(Args, Debug)]
+#[command(group(ArgGroup::new("host_keys").multiple(true).args(["host_key_documents", "host_key_hashes"])))]
 pub struct CheckOpt {
     /// Specify the attestation response to check whether the policies are validated.
     #[arg(value_name = "IN", value_hint = ValueHint::FilePath,)]
     pub input: PathBuf,
 
-    /// Specify the output file for the check result.
+    #[command(flatten)]
+    pub input_opt: ResponseInputOpt,
+
+    /// Specify the output file for the check result. Use '-' for stdout.
     #[arg(value_name = "OUT", value_hint = ValueHint::FilePath,)]
     pub output: PathBuf,
 
+    /// Overwrite an existing output file.
+    #[arg(long)]
+    pub force: bool,
+
     /// Define the output format.
     #[arg(long, value_enum, default_value_t)]
     pub format: OutputType,
@@ -248,39 +560,87 @@ pub struct CheckOpt {
         )]
     pub host_key_documents: Vec<PathBuf>,
 
+    /// Use HASH as an acceptable host-key hash.
+    ///
+    /// HASH is the hex-encoded SHA-256 hash of a public host-key as contained in the attestation
+    /// response. Use it instead of --host-key-document if the host-key document is not available,
+    /// e.g., for the old host-key during a host-key rotation. The check passes if the host-key
+    /// hash in the attestation response matches any of the given host-key documents or hashes.
+    /// This parameter can be specified multiple times.
+    #[arg(
+        long = "host-key-hash",
+        value_name = "HASH",
+        value_parser = parse_host_key_hash,
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        )]
+    pub host_key_hashes: Vec<[u8; HOST_KEY_HASH_SIZE]>,
+
     /// Define the host-key check policy
     ///
     /// By default, all host-key hashes are checked, and it is not considered a failure if a hash
     /// is missing from the attestation response. Use this policy switch to trigger a failure if no
-    /// corresponding hash is found. Requires at least one host-key document.
+    /// corresponding hash is found. Requires at least one host-key document or host-key hash.
     #[arg(
         long = "host-key-check",
-        requires("host_key_documents"),
+        requires("host_keys"),
         use_value_delimiter = true,
         value_delimiter = ','
     )]
     pub host_key_checks: Vec<HostKeyCheckPolicy>,
 
+    // If certificates are given with --cert, the host-key documents are verified
+    #[command(flatten)]
+    pub verify_args: CertVerifyOptions,
+
+    /// Warn if a host-key document or its chain of trust expires in less than DAYS days.
+    ///
+    /// A near expiry is reported as warning and does not fail the check.
+    #[arg(long, value_name = "DAYS", requires("host_key_documents"))]
+    pub warn_expiry: Option<u32>,
+
+    /// Check that the Secure Execution image in FILE has a keyslot for the image host-key hash.
+    ///
+    /// FILE is the bootable Secure Execution image of the guest or its extracted Secure Execution
+    /// header. The check passes if one keyslot of the image matches the image public host-key hash
+    /// of the attestation response and reports the matching keyslot. It fails if no keyslot
+    /// matches and is skipped if the attestation response contains no image public host-key
+    /// hash. Use 'pvattest verify --hdr' to verify that the guest booted exactly this image.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub se_image: Option<PathBuf>,
+
     /// Check if the provided user data matches the data from the attestation response.
+    ///
+    /// If --user-data-cert is given, compares only the signed payload of the user-data.
     #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub user_data: Option<PathBuf>,
 
-    /// Use FILE to include as successful Add-secret request.
+    /// Verify the signature of the user-data with the public key in FILE.
     ///
-    /// Checks if the Attestation response contains the hash of all specified add secret
-    /// requests-tags. The hash is sensible to the order in which the secrets where added. This
-    /// means that if the order of adding here different from the order the add-secret requests
-    /// where sent to the UV this check will fail even though the same secrets are included in the
-    /// UV secret store. Can be specified multiple times.
-    #[arg(
-        long,
-        value_name = "FILE",
-        value_hint = ValueHint::FilePath,
-        use_value_delimiter = true,
-        value_delimiter = ',',
-        requires("secret_store_locked"),
-        )]
-    pub secret: Vec<PathBuf>,
+    /// FILE is a X509 certificate or a public key in PEM or DER format. The user-data must end
+    /// with the signature of the preceding payload followed by the signature size as 2-byte big
+    /// endian number. EC keys and RSA keys (PSS padding) are supported, the digest is SHA-512. The
+    /// signature of the key must fit into the 256 bytes of user-data. The check fails if the
+    /// attestation response contains no user-data.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
+    pub user_data_cert: Option<PathBuf>,
+
+    /// Check if the configuration unique ID (CUID) of the attestation response matches CUID.
+    ///
+    /// CUID is either the hex-encoded 16-byte CUID or a file containing the CUID as raw 16 bytes
+    /// or hex-encoded.
+    #[arg(long, value_name = "CUID", value_parser = parse_cuid, value_hint = ValueHint::FilePath)]
+    pub cuid: Option<HexOrFile<CUID_SIZE>>,
+
+    #[command(flatten)]
+    pub secrets: SecretInputs,
+
+    /// Define the order of the add-secret requests in a directory given with --secret.
+    ///
+    /// The order must match the order in which the add-secret requests were sent to the
+    /// Ultravisor.
+    #[arg(long, value_enum, default_value_t, requires("secret"))]
+    pub secret_order: SecretOrder,
 
     /// Check whether the guests secret store is locked or not.
     ///
@@ -303,6 +663,191 @@ pub struct CheckOpt {
     /// Use an endpoint you trust. Requires the --firmware option.
     #[arg(long, requires("firmware"), value_name = "URL", value_hint = ValueHint::Url)]
     pub firmware_verify_url: Option<String>,
+
+    /// Check whether the firmware hash is one of the acceptable hashes in FILE.
+    ///
+    /// FILE contains one hex-encoded firmware hash per line. Empty lines and everything after
+    /// a '#' are ignored. The check fails if the attestation response contains no firmware hash
+    /// or none of the listed hashes matches it. In contrast to --firmware, no internet access is
+    /// required.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub firmware_hashes: Option<PathBuf>,
+}
+
+/// Size of the configuration unique ID (in bytes)
+pub const CUID_SIZE: usize = 16;
+
+/// Parse a hex-encoded CUID or the path of a CUID file
+fn parse_cuid(s: &str) -> Result<HexOrFile<CUID_SIZE>, String> {
+    HexOrFile::parse(s, "CUID")
+}
+
+/// Size of the attestation request nonce (in bytes)
+pub const NONCE_SIZE: usize = 16;
+
+/// Parse a hex-encoded nonce or the path of a nonce file
+fn parse_nonce(s: &str) -> Result<HexOrFile<NONCE_SIZE>, String> {
+    HexOrFile::parse(s, "nonce")
+}
+
+/// Value of `N` bytes given either hex-encoded or as file, e.g., with --cuid or --nonce-in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HexOrFile<const N: usize> {
+    /// Hex-encoded value
+    Value([u8; N]),
+    /// File containing the raw or hex-encoded value
+    File(PathBuf),
+}
+
+impl<const N: usize> HexOrFile<N> {
+    /// Parse a hex-encoded value or the path of a file
+    ///
+    /// Values that are no hex string are interpreted as path. Hex strings of the wrong size are
+    /// rejected, unless such a file exists. `what` names the value in error messages.
+    fn parse(s: &str, what: &str) -> Result<Self, String> {
+        match parse_hex_sized(s, what) {
+            Ok(val) => Ok(Self::Value(val)),
+            Err(err) if decode_hex_formatted(s).is_ok() && !Path::new(s).exists() => Err(err),
+            Err(_) => Ok(Self::File(s.into())),
+        }
+    }
+}
+
+/// An add-secret request for the secret-store check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SecretInput {
+    /// Add-secret request file or directory given with --secret
+    Request(PathBuf),
+    /// Tag of an add-secret request given with --secret-tag
+    Tag([u8; SECRET_TAG_SIZE]),
+    /// Receipt of an add-secret request given with --secret-receipt
+    Receipt(PathBuf),
+}
+
+/// Add-secret requests from --secret, --secret-tag, and --secret-receipt in command-line order
+///
+/// The order is significant for the secret-store hash, but lost if both options are parsed
+/// into separate vectors. Therefore, the values are merged by their index on the command-line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SecretInputs(pub Vec<SecretInput>);
+
+#[derive(Args, Debug)]
+struct SecretArgs {
+    /// Use FILE to include as successful Add-secret request.
+    ///
+    /// Checks if the Attestation response contains the hash of all specified add secret
+    /// requests-tags. The hash is sensible to the order in which the secrets where added. This
+    /// means that if the order of adding here different from the order the add-secret requests
+    /// where sent to the UV this check will fail even though the same secrets are included in the
+    /// UV secret store. If FILE is a directory, all regular files in it are used in the order
+    /// defined by --secret-order. Can be specified multiple times.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        requires("secret_store_locked"),
+        )]
+    secret: Vec<PathBuf>,
+
+    /// Use TAG as the tag of a successful Add-secret request.
+    ///
+    /// TAG is the hex-encoded 16-byte tag of an add-secret request. Use it instead of --secret if
+    /// the add-secret request itself is not available. Both options can be mixed, the order of
+    /// all --secret and --secret-tag options on the command-line defines the order of the
+    /// add-secret requests. Can be specified multiple times.
+    #[arg(
+        long,
+        value_name = "TAG",
+        value_parser = parse_secret_tag,
+        requires("secret_store_locked"),
+        )]
+    secret_tag: Vec<[u8; SECRET_TAG_SIZE]>,
+
+    /// Use the receipt FILE of a successful Add-secret request.
+    ///
+    /// FILE is a receipt written by 'pvsecret add --receipt'. Each receipt is verified against
+    /// the secret store state of all add-secret requests given before it. If two receipts follow
+    /// each other, the second one must refer to the first one. Can be mixed with --secret and
+    /// --secret-tag and specified multiple times.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        requires("secret_store_locked"),
+        )]
+    secret_receipt: Vec<PathBuf>,
+}
+
+impl Args for SecretInputs {
+    fn augment_args(cmd: clap::Command) -> clap::Command {
+        SecretArgs::augment_args(cmd)
+    }
+
+    fn augment_args_for_update(cmd: clap::Command) -> clap::Command {
+        SecretArgs::augment_args_for_update(cmd)
+    }
+}
+
+impl FromArgMatches for SecretInputs {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let SecretArgs {
+            secret,
+            secret_tag,
+            secret_receipt,
+        } = SecretArgs::from_arg_matches(matches)?;
+        let indices = |id| matches.indices_of(id).into_iter().flatten();
+        let mut inputs: Vec<_> = indices("secret")
+            .zip(secret.into_iter().map(SecretInput::Request))
+            .chain(indices("secret_tag").zip(secret_tag.into_iter().map(SecretInput::Tag)))
+            .chain(
+                indices("secret_receipt").zip(secret_receipt.into_iter().map(SecretInput::Receipt)),
+            )
+            .collect();
+        inputs.sort_by_key(|(idx, _)| *idx);
+        Ok(Self(inputs.into_iter().map(|(_, input)| input).collect()))
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+/// Parse a hex-encoded value of exactly `N` bytes
+///
+/// `what` names the value in error messages.
+fn parse_hex_sized<const N: usize>(s: &str, what: &str) -> Result<[u8; N], String> {
+    let val = decode_hex_formatted(s).map_err(|e| format!("'{s}' is not a valid {what}: {e}"))?;
+    val.try_into().map_err(|val: Vec<u8>| {
+        format!(
+            "'{s}' is not a valid {what}: Must be {N} bytes long, but is {} bytes long",
+            val.len()
+        )
+    })
+}
+
+/// Parse a hex-encoded add-secret request tag
+fn parse_secret_tag(s: &str) -> Result<[u8; SECRET_TAG_SIZE], String> {
+    parse_hex_sized(s, "tag")
+}
+
+/// Size of a public host-key hash (in bytes)
+pub const HOST_KEY_HASH_SIZE: usize = 32;
+
+/// Parse a hex-encoded public host-key hash
+fn parse_host_key_hash(s: &str) -> Result<[u8; HOST_KEY_HASH_SIZE], String> {
+    parse_hex_sized(s, "host-key hash")
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
+pub enum SecretOrder {
+    /// Sort the files by their name.
+    #[default]
+    Name,
+    /// Sort the files by their modification time, oldest first.
+    Mtime,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -326,4 +871,59 @@ mod test {
         use clap::CommandFactory;
         super::CliOptions::command().debug_assert()
     }
+
+    #[test]
+    fn remote_addr() {
+        use super::{parse_remote_addr, RemoteAddr, VSOCK_CID_ANY};
+
+        for addr in ["localhost:7070", "127.0.0.1:7070", "[::1]:7070"] {
+            assert_eq!(parse_remote_addr(addr), Ok(RemoteAddr::Tcp(addr.into())));
+        }
+        assert_eq!(
+            parse_remote_addr("vsock:3:7070"),
+            Ok(RemoteAddr::Vsock { cid: 3, port: 7070 })
+        );
+        let any = parse_remote_addr("vsock:any:7070").unwrap();
+        assert_eq!(
+            any,
+            RemoteAddr::Vsock {
+                cid: VSOCK_CID_ANY,
+                port: 7070
+            }
+        );
+        assert_eq!(any.to_string(), "vsock:any:7070");
+        for addr in [
+            "localhost",
+            ":7070",
+            "host:port",
+            "host:70000",
+            "vsock:3",
+            "vsock:x:1",
+        ] {
+            assert!(parse_remote_addr(addr).is_err(), "{addr}");
+        }
+    }
+
+    #[test]
+    fn json_fields() {
+        use super::{parse_json_fields, JsonFields};
+
+        assert_eq!(
+            parse_json_fields("response,additional-data"),
+            Ok(JsonFields {
+                response: "response".into(),
+                additional: Some("additional-data".into())
+            })
+        );
+        assert_eq!(
+            parse_json_fields("resp"),
+            Ok(JsonFields {
+                response: "resp".into(),
+                additional: None
+            })
+        );
+        for fields in ["", ",add", "resp,", "a,b,c"] {
+            assert!(parse_json_fields(fields).is_err(), "{fields}");
+        }
+    }
 }
@@ -4,32 +4,126 @@
 //
 pub mod check;
 pub mod create;
-#[cfg(target_arch = "s390x")]
+pub mod diff;
 pub mod perform;
+pub mod serve;
 pub mod verify;
 
 pub use check::check;
 pub use create::create;
+pub use diff::diff;
+pub use perform::perform;
+pub use serve::serve;
 pub use verify::verify;
 
-pub const CMD_FN: &[&str] = &["+create", "+verify"];
-// s390 branch
-#[cfg(target_arch = "s390x")]
-mod uv_cmd {
-    pub use super::perform::perform;
-    pub const UV_CMD_FN: &[&str] = &["+perform"];
+use crate::cli::{HexOrFile, OutputType};
+use anyhow::{bail, Context, Result};
+use pv::{
+    misc::{decode_hex_formatted, read_confidential_file, read_file},
+    request::{SymKey, SymKeyType},
+    PvCoreError,
+};
+use serde::Serialize;
+use std::{fmt::Display, io::Write, path::Path};
+use utils::read_text;
+
+/// A failed verification or policy check
+///
+/// Unlike operational errors, e.g., an unreadable input file, `main` reports this error with
+/// [`EXIT_CODE_ATTESTATION_FAIL`](crate::EXIT_CODE_ATTESTATION_FAIL).
+#[derive(Debug)]
+pub struct CheckFailed(String);
+
+impl CheckFailed {
+    pub fn new<M: Into<String>>(msg: M) -> Self {
+        Self(msg.into())
+    }
 }
 
-// non s390-branch
-#[cfg(not(target_arch = "s390x"))]
-mod uv_cmd {
-    use std::process::ExitCode;
+impl Display for CheckFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
-    use anyhow::{bail, Result};
+impl std::error::Error for CheckFailed {}
 
-    pub fn perform(_: &crate::cli::PerformAttOpt) -> Result<ExitCode> {
-        bail!("Command only available on s390x")
+/// Read an attestation request protection key
+///
+/// Distinguishes inaccessible files from files with a wrong size in the error message.
+fn read_arpk<P: AsRef<Path>>(path: P) -> Result<SymKey> {
+    let path = path.as_ref();
+    match read_confidential_file(
+        path,
+        "Attestation request protection key",
+        Some(SymKeyType::AES_256_GCM_KEY_LEN),
+    ) {
+        // the key lives in locked memory, see the mlock feature of pv
+        Ok(key) => Ok(SymKey::try_from_data(SymKeyType::Aes256Gcm, key)?),
+        Err(PvCoreError::FileAccess { source, .. } | PvCoreError::FileIo { source, .. }) => {
+            bail!(
+                "Cannot read the attestation request protection key '{}': {source}",
+                path.display()
+            )
+        }
+        Err(PvCoreError::Specification(msg)) => {
+            bail!(
+                "Invalid attestation request protection key '{}': {msg}",
+                path.display()
+            )
+        }
+        Err(e) => Err(e.into()),
     }
-    pub const UV_CMD_FN: &[&str] = &[];
 }
-pub use uv_cmd::*;
+
+/// Read the value of a [`HexOrFile`] option
+///
+/// A file contains either exactly `N` raw bytes or the hex-encoded value. `what` names the value
+/// in error messages.
+fn read_hex_or_file<const N: usize>(input: &HexOrFile<N>, what: &str) -> Result<[u8; N]> {
+    let path = match input {
+        HexOrFile::Value(val) => return Ok(*val),
+        HexOrFile::File(path) => path,
+    };
+    let content = read_file(path, &format!("{what} file"))?;
+    if let Ok(val) = content.as_slice().try_into() {
+        return Ok(val);
+    }
+
+    let ctx = || format!("Invalid {what} file '{}'", path.display());
+    let text = read_text(content.as_slice(), &format!("{what} file")).with_context(ctx)?;
+    let val = decode_hex_formatted(text.trim()).with_context(ctx)?;
+    match val.try_into() {
+        Ok(val) => Ok(val),
+        Err(val) => bail!(
+            "{}: Must contain {N} bytes, but contains {} bytes",
+            ctx(),
+            val.len()
+        ),
+    }
+}
+
+/// Write `value` in the requested `format` to `output`.
+fn write_output<T, W>(output: &mut W, format: OutputType, value: &T) -> Result<()>
+where
+    T: Serialize + Display,
+    W: Write,
+{
+    match format {
+        OutputType::Yaml => serde_yaml::to_writer(output, value)?,
+        OutputType::Json => {
+            serde_json::to_writer_pretty(&mut *output, value)?;
+            writeln!(output)?;
+        }
+        OutputType::Human => write!(output, "{value}")?,
+    };
+    Ok(())
+}
+
+pub const CMD_FN: &[&str] = &["+create", "+verify", "+remote"];
+#[cfg(all(target_arch = "s390x", not(feature = "simulate")))]
+pub const UV_CMD_FN: &[&str] = &["+perform", "+serve"];
+#[cfg(feature = "simulate")]
+pub const UV_CMD_FN: &[&str] = &["+perform", "+serve", "+simulate"];
+#[cfg(not(any(target_arch = "s390x", feature = "simulate")))]
+pub const UV_CMD_FN: &[&str] = &[];
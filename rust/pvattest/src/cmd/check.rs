@@ -2,154 +2,362 @@
 //
 // Copyright IBM Corp. 2024
 
+mod cuid;
 mod firmware;
+mod hkd_validity;
 mod host_key;
+mod se_image;
 mod secret_store;
+mod user_data;
 
 use self::{
-    firmware::firmware_check,
-    host_key::{host_key_check, HostKeyCheck},
+    cuid::cuid_check,
+    firmware::{firmware_check, firmware_hashes_check},
+    hkd_validity::hkd_validity_check,
+    host_key::host_key_check,
+    se_image::se_image_check,
     secret_store::secret_store_check,
-    secret_store::SecretStoreCheck,
+    user_data::{user_data_check, user_data_signature_check},
 };
-use crate::{additional::AttestationResult, cli::CheckOpt, exchange::ExchangeFormatResponse};
-use anyhow::Result;
-use log::{debug, info, warn};
-use pv::{
-    attest::AttestationRequest,
-    misc::{create_file, open_file, read_file},
+use crate::{
+    additional::AttestationResult,
+    cli::CheckOpt,
+    cmd::{write_output, CheckFailed},
+    response::read_response,
 };
+use anyhow::{bail, Result};
+use log::{info, warn};
+use pv::attest::AttestationRequest;
 use serde::Serialize;
-use std::process::ExitCode;
-use utils::HexSlice;
-
-#[derive(Default, Debug)]
-enum CheckState<T> {
-    #[default]
-    None,
-    Data(T),
-    Err(String),
+use std::{fmt::Display, process::ExitCode};
+use utils::{open_output, HexSlice, HkdReport};
+
+/// Status of a single [`CheckState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check was not requested or is not applicable
+    Skipped,
 }
 
-impl<T> CheckState<T> {
-    fn check(self, issues: &mut Vec<String>) -> Option<T> {
-        match self {
-            Self::None => None,
-            Self::Data(d) => Some(d),
-            Self::Err(e) => {
-                issues.push(e.to_string());
-                warn!("✘ {e}");
-                None
-            }
+/// Structured result of a single policy check
+#[derive(Debug, Serialize)]
+struct CheckState {
+    name: &'static str,
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual: Option<String>,
+    /// Acceptable values if one of several is expected
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    candidates: Vec<String>,
+    message: String,
+}
+
+impl CheckState {
+    fn new<M: Into<String>>(name: &'static str, status: CheckStatus, message: M) -> Self {
+        Self {
+            name,
+            status,
+            expected: None,
+            actual: None,
+            candidates: Vec::new(),
+            message: message.into(),
+        }
+    }
+
+    fn passed<M: Into<String>>(name: &'static str, message: M) -> Self {
+        Self::new(name, CheckStatus::Passed, message)
+    }
+
+    fn failed<M: Into<String>>(name: &'static str, message: M) -> Self {
+        Self::new(name, CheckStatus::Failed, message)
+    }
+
+    fn skipped<M: Into<String>>(name: &'static str, message: M) -> Self {
+        Self::new(name, CheckStatus::Skipped, message)
+    }
+
+    /// Add the expected value as hex
+    fn expected<E: AsRef<[u8]>>(mut self, expected: E) -> Self {
+        self.expected = Some(format!("{:#}", HexSlice::from(expected.as_ref())));
+        self
+    }
+
+    /// Add the actual value as hex
+    fn actual<A: AsRef<[u8]>>(mut self, actual: A) -> Self {
+        self.actual = Some(format!("{:#}", HexSlice::from(actual.as_ref())));
+        self
+    }
+
+    /// Add the acceptable values as hex
+    fn candidates<I, C>(mut self, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: AsRef<[u8]>,
+    {
+        self.candidates = candidates
+            .into_iter()
+            .map(|c| format!("{:#}", HexSlice::from(c.as_ref())))
+            .collect();
+        self
+    }
+
+    /// Log the outcome of the check
+    fn report(self) -> Self {
+        match self.status {
+            CheckStatus::Passed => info!("✓ {}", self.message),
+            CheckStatus::Failed => warn!("✘ {}", self.message),
+            CheckStatus::Skipped => info!("- {}", self.message),
         }
+        self
     }
 }
 
-impl<T> From<Option<T>> for CheckState<T> {
-    fn from(value: Option<T>) -> Self {
-        value.map_or(Self::None, Self::Data)
+impl Display for CheckState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            CheckStatus::Passed => "✓",
+            CheckStatus::Failed => "✘",
+            CheckStatus::Skipped => "-",
+        };
+        writeln!(f, "{status} {}: {}", self.name, self.message)?;
+        if let Some(expected) = &self.expected {
+            writeln!(f, "    expected: {expected}")?;
+        }
+        if let Some(actual) = &self.actual {
+            writeln!(f, "    actual:   {actual}")?;
+        }
+        for candidate in &self.candidates {
+            writeln!(f, "    candidate: {candidate}")?;
+        }
+        Ok(())
     }
 }
 
-/// Return a [`CheckState::Err`]`
+/// Return a failed [`CheckState`] of the check `name`
+///
+/// Unlike an error, a failed check does not stop the remaining checks.
 #[allow(unused_macro_rules)]
 macro_rules! bail_check {
-    ($msg:literal) => {
-        return Ok(CheckState::Err($msg.to_string()))
+    ($name:expr, $msg:literal) => {
+        return Ok($crate::cmd::check::CheckState::failed($name, format!($msg)))
     };
-    ($err:expr) => {
-        return Ok(CheckState::Err($err.to_string()))
+    ($name:expr, $err:expr) => {
+        return Ok($crate::cmd::check::CheckState::failed($name, $err.to_string()))
     };
-    ($fmt:expr, $($arg:tt)*) => {
-        return Ok(CheckState::Err(format!($fmt, $($arg)*)))
+    ($name:expr, $fmt:expr, $($arg:tt)*) => {
+        return Ok($crate::cmd::check::CheckState::failed($name, format!($fmt, $($arg)*)))
     };
 }
 use bail_check;
 
-/// Check if the user-data matches with the user-data in the attestation response
-fn user_data_check<'a>(
-    opt: &CheckOpt,
-    att_res: &'a AttestationResult,
-) -> Result<CheckState<HexSlice<'a>>> {
-    let user_data = match &opt.user_data {
-        Some(file) => read_file(file, "user-data")?,
-        None => return Ok(CheckState::None),
-    };
+/// Result of all policy checks
+#[derive(Debug, Serialize)]
+pub struct CheckResult {
+    successful: bool,
+    checks: Vec<CheckState>,
+    /// Validity reports of the host-key documents given with --host-key-document
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    host_key_documents: Vec<HkdReport>,
+}
 
-    if Some(HexSlice::from(&user_data)) != att_res.user_data {
-        bail_check!(
-            "The Provided user data does not match the user data from the attestation response."
-        );
+impl CheckResult {
+    fn new(checks: Vec<CheckState>) -> Self {
+        Self {
+            successful: checks
+                .iter()
+                .all(|c| matches!(c.status, CheckStatus::Passed | CheckStatus::Skipped)),
+            checks,
+            host_key_documents: Vec::new(),
+        }
     }
-    info!("✓ Checked user-data");
-    Ok(att_res.user_data.clone().into())
 }
 
-#[derive(Debug, Serialize, Default)]
-pub struct CheckResult<'a> {
-    successful: bool,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    issues: Vec<String>,
-    #[serde(skip_serializing_if = "HostKeyCheck::hide")]
-    image_host_key: HostKeyCheck<'a>,
-    #[serde(skip_serializing_if = "HostKeyCheck::hide")]
-    attest_host_key: HostKeyCheck<'a>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    user_data: Option<HexSlice<'a>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    secret_store: Option<SecretStoreCheck<'a>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    valid_firmware: Option<bool>,
+impl Display for CheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.checks.iter().try_for_each(|c| write!(f, "{c}"))?;
+        match self.successful {
+            true => writeln!(f, "The attestation response fulfills all policies"),
+            false => writeln!(f, "The attestation response does not fulfill all policies"),
+        }
+    }
+}
+
+/// Perform all policy checks on `att_res`
+///
+/// Failed checks do not stop the remaining checks. An error, e.g., an unreadable input file,
+/// aborts immediately.
+fn run_checks(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckResult> {
+    let mut hkd_reports = Vec::new();
+    let checks = [
+        host_key_check(opt, host_key::HkCheck::Image, att_res)?.report(),
+        host_key_check(opt, host_key::HkCheck::Attest, att_res)?.report(),
+        hkd_validity_check(opt, &mut hkd_reports)?.report(),
+        se_image_check(opt, att_res)?.report(),
+        user_data_signature_check(opt, att_res)?.report(),
+        user_data_check(opt, att_res)?.report(),
+        cuid_check(opt, att_res)?.report(),
+        secret_store_check(opt, att_res)?.report(),
+        firmware_check(opt, att_res)?.report(),
+        firmware_hashes_check(opt, att_res)?.report(),
+    ];
+    Ok(CheckResult {
+        host_key_documents: hkd_reports,
+        ..CheckResult::new(checks.into())
+    })
 }
 
 /// Perform the policy checks
 pub fn check(opt: &CheckOpt) -> Result<ExitCode> {
-    let mut input = open_file(&opt.input)?;
-    let inp = ExchangeFormatResponse::read(&mut input)?;
+    let inp = read_response(&opt.input, &opt.input_opt)?;
     let auth = AttestationRequest::auth_bin(inp.arcb())?;
     let att_res = AttestationResult::from_exchange(&inp, auth.flags())?;
-    let mut issues = vec![];
-
-    let image_host_key = host_key_check(opt, host_key::HkCheck::Image, &att_res)?
-        .check(&mut issues)
-        .unwrap();
-    let attest_host_key = host_key_check(opt, host_key::HkCheck::Attest, &att_res)?
-        .check(&mut issues)
-        .unwrap();
-
-    let user_data = user_data_check(opt, &att_res)?.check(&mut issues);
-    let secret_store = secret_store_check(opt, &att_res)?.check(&mut issues);
-
-    let firmware_check = firmware_check(opt, &att_res)?;
-    let valid_firmware = match firmware_check {
-        CheckState::None => None,
-        CheckState::Data(_) => Some(true),
-        CheckState::Err(_) => Some(false),
-    };
-    firmware_check.check(&mut issues);
-
-    let res = CheckResult {
-        successful: !issues.is_empty(),
-        issues,
-        image_host_key,
-        attest_host_key,
-        user_data,
-        secret_store,
-        valid_firmware,
-    };
 
-    debug!("res {res:?}");
-    let output = create_file(&opt.output)?;
-    serde_yaml::to_writer(output, &res)?;
+    let res = run_checks(opt, &att_res)?;
+
+    let mut output = open_output(&opt.output, opt.force)?;
+    write_output(&mut output, opt.format, &res)?;
 
     match res.successful {
         true => {
             warn!("✓ The Attestation response fulfills all policies");
             Ok(ExitCode::SUCCESS)
         }
-        false => {
-            warn!("✘ The Attestation response does not fulfill all policies");
-            Ok(ExitCode::from(crate::EXIT_CODE_ATTESTATION_FAIL))
+        false => bail!(CheckFailed::new(
+            "✘ The Attestation response does not fulfill all policies"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use clap::Parser;
+    use pv::attest::{AdditionalData, AttestationFlags};
+
+    use super::{user_data::USER_DATA_SIGNATURE_CHECK, *};
+    use crate::cli::{CliOptions, Command};
+
+    const CERT: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../pv/tests/assets/cert/host.crt"
+    );
+
+    /// Parse the check options `args` given after `pvattest check in out`
+    pub(super) fn try_check_opt<I, T>(args: I) -> Result<CheckOpt>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = ["pvattest", "check", "in", "out"]
+            .into_iter()
+            .map(OsString::from)
+            .chain(args.into_iter().map(Into::into));
+        match CliOptions::try_parse_from(args)?.cmd {
+            Command::Check(opt) => Ok(*opt),
+            cmd => panic!("Unexpected command {cmd:?}"),
+        }
+    }
+
+    /// Parse the check options `args` given after `pvattest check in out`
+    pub(super) fn check_opt<I, T>(args: I) -> CheckOpt
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        try_check_opt(args).unwrap()
+    }
+
+    /// Attestation result with an all-zero configuration UID and nothing else
+    pub(super) fn att_res() -> AttestationResult<'static> {
+        AttestationResult {
+            cuid: (&[0; 16]).into(),
+            add: None,
+            add_fields: None,
+            user_data: None,
         }
     }
+
+    /// Attestation result with the additional-data `add` as requested by `flags`
+    pub(super) fn att_res_add<'a>(
+        add: &'a [u8],
+        flags: &AttestationFlags,
+    ) -> AttestationResult<'a> {
+        AttestationResult {
+            add_fields: Some(AdditionalData::from_slice_sized(add, flags).unwrap()),
+            ..att_res()
+        }
+    }
+
+    fn failing_check(fail: bool) -> Result<CheckState> {
+        if fail {
+            bail_check!("test", "failed {}", 42);
+        }
+        Ok(CheckState::passed("test", "passed"))
+    }
+
+    #[test]
+    fn bail_check() {
+        let state = failing_check(true).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert_eq!(state.name, "test");
+        assert_eq!(state.message, "failed 42");
+        assert_eq!(failing_check(false).unwrap().status, CheckStatus::Passed);
+    }
+
+    #[test]
+    fn run_checks_collects_failures() {
+        let cuid = format!("0x{}", "00".repeat(16));
+        let opt = check_opt(["--user-data-cert", CERT, "--cuid", &cuid]);
+        let att_res = AttestationResult {
+            cuid: (&[1; 16]).into(),
+            ..att_res()
+        };
+        let res = run_checks(&opt, &att_res).unwrap();
+        assert!(!res.successful);
+
+        let failed: Vec<_> = res
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Failed)
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(failed, [USER_DATA_SIGNATURE_CHECK, cuid::CUID_CHECK]);
+        let out = res.to_string();
+        assert!(out.contains("✘ user-data-signature: "), "{out}");
+        assert!(out.contains("✘ cuid: "), "{out}");
+    }
+
+    #[test]
+    fn run_checks_error() {
+        let opt = check_opt(["--user-data", "/missing"]);
+        assert!(run_checks(&opt, &att_res()).is_err());
+    }
+
+    #[test]
+    fn result_verdict() {
+        let res = CheckResult::new(vec![
+            CheckState::skipped("a", "skipped"),
+            CheckState::passed("b", "passed"),
+        ]);
+        assert!(res.successful);
+
+        let res = CheckResult::new(vec![
+            CheckState::failed("a", "failed")
+                .expected([1, 2])
+                .actual([3]),
+            CheckState::passed("b", "passed"),
+        ]);
+        assert!(!res.successful);
+        assert_eq!(
+            res.to_string(),
+            "✘ a: failed\n    expected: 0x0102\n    actual:   0x03\n✓ b: passed\nThe attestation \
+             response does not fulfill all policies\n"
+        );
+    }
 }
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::Result;
+
+use super::CheckState;
+use crate::{additional::AttestationResult, cli::CheckOpt, cmd::read_hex_or_file};
+
+/// Name of the CUID check in the check result
+pub const CUID_CHECK: &str = "cuid";
+
+/// Check if the CUID of the attestation response matches the expected CUID
+pub fn cuid_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState> {
+    let expected = match &opt.cuid {
+        None => return Ok(CheckState::skipped(CUID_CHECK, "No CUID given")),
+        Some(cuid) => read_hex_or_file(cuid, "CUID")?,
+    };
+
+    let state = match att_res.cuid.as_ref() == expected {
+        true => CheckState::passed(CUID_CHECK, "The CUID matches"),
+        false => CheckState::failed(
+            CUID_CHECK,
+            "The CUID of the attestation response does not match the expected CUID",
+        ),
+    };
+    Ok(state.expected(expected).actual(&att_res.cuid))
+}
+
+#[cfg(test)]
+mod test {
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::cli::{HexOrFile, CUID_SIZE};
+    use crate::cmd::check::{
+        test::{att_res, check_opt, try_check_opt},
+        CheckStatus,
+    };
+
+    const CUID: [u8; CUID_SIZE] = [0x11; CUID_SIZE];
+    const CUID_HEX: &str = "0x11111111111111111111111111111111";
+
+    fn cuid_res(cuid: &[u8]) -> AttestationResult<'_> {
+        AttestationResult {
+            cuid: cuid.into(),
+            ..att_res()
+        }
+    }
+
+    fn cuid_opt(cuid: Option<&str>) -> CheckOpt {
+        check_opt(cuid.into_iter().flat_map(|c| ["--cuid", c]))
+    }
+
+    #[test]
+    fn cuid_match() {
+        let state = cuid_check(&cuid_opt(Some(CUID_HEX)), &cuid_res(&CUID)).unwrap();
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert_eq!(state.expected.as_deref(), Some(CUID_HEX));
+        assert_eq!(state.expected, state.actual);
+    }
+
+    #[test]
+    fn cuid_mismatch() {
+        let state = cuid_check(&cuid_opt(Some(CUID_HEX)), &cuid_res(&[0x22; 16])).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert_eq!(state.expected.as_deref(), Some(CUID_HEX));
+        assert_eq!(state.actual, Some(format!("0x{}", "22".repeat(16))));
+    }
+
+    #[test]
+    fn cuid_absent() {
+        let state = cuid_check(&cuid_opt(None), &cuid_res(&CUID)).unwrap();
+        assert_eq!(state.status, CheckStatus::Skipped);
+        assert!(state.expected.is_none());
+    }
+
+    #[test]
+    fn cuid_file() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let raw = dir.path().join("raw");
+        std::fs::write(&raw, CUID).unwrap();
+        let hex = dir.path().join("hex");
+        std::fs::write(&hex, format!("{CUID_HEX}\r\n")).unwrap();
+        for path in [&raw, &hex] {
+            let opt = cuid_opt(Some(path.to_str().unwrap()));
+            assert_eq!(opt.cuid, Some(HexOrFile::File(path.to_owned())));
+            let state = cuid_check(&opt, &cuid_res(&CUID)).unwrap();
+            assert_eq!(state.status, CheckStatus::Passed);
+        }
+
+        let short = dir.path().join("short");
+        std::fs::write(&short, "0x1111").unwrap();
+        let err = cuid_check(&cuid_opt(Some(short.to_str().unwrap())), &cuid_res(&CUID));
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .ends_with("Must contain 16 bytes, but contains 2 bytes"));
+        let missing = dir.path().join("missing");
+        assert!(cuid_check(&cuid_opt(Some(missing.to_str().unwrap())), &cuid_res(&CUID)).is_err());
+    }
+
+    #[test]
+    fn cuid_invalid_hex() {
+        assert!(try_check_opt(["--cuid", "0x1111"]).is_err());
+    }
+}
@@ -2,17 +2,24 @@
 //
 // Copyright IBM Corp. 2024
 
-use std::{fmt::Display, time::Duration};
+use std::{fmt::Display, path::Path, time::Duration};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use base64::prelude::*;
 use curl::easy::{Easy2, Handler, List, WriteError};
-use log::{debug, info, log_enabled};
+use log::{debug, log_enabled};
+use pv::misc::{decode_hex_formatted, open_file};
 use serde::{Deserialize, Serialize};
+use utils::read_text_lines;
 
 use super::{bail_check, CheckState};
 use crate::{additional::AttestationResult, cli::CheckOpt};
 
+/// Name of the firmware check in the check result
+pub const FIRMWARE_CHECK: &str = "firmware";
+/// Name of the offline firmware-hash check in the check result
+pub const FIRMWARE_HASHES_CHECK: &str = "firmware-hashes";
+
 const CHECK_DEFAULT_ENDP: &str = "https://www.ibm.com/support/resourcelink/api";
 const VERIFY_API: &str = "firmware-attestation/verify/v1";
 const TIMEOUT_MAX: Duration = Duration::from_secs(3);
@@ -71,7 +78,7 @@ impl Handler for Buf {
     }
 }
 
-fn check<U: AsRef<[u8]>>(fw_hash: &U, endp: &str) -> Result<CheckState<()>> {
+fn check<U: AsRef<[u8]>>(fw_hash: &U, endp: &str) -> Result<CheckState> {
     let req = serde_json::to_vec(&Request::new_v1(fw_hash.as_ref()))?;
 
     let url = format!("{endp}/{VERIFY_API}");
@@ -116,16 +123,17 @@ fn check<U: AsRef<[u8]>>(fw_hash: &U, endp: &str) -> Result<CheckState<()>> {
     debug!("Firmware check {resp:?}");
 
     match resp.valid {
-        true => info!("✓ {resp}"),
-        false => bail_check!(&format!("{resp}")),
+        true => Ok(CheckState::passed(FIRMWARE_CHECK, resp.to_string())),
+        false => bail_check!(FIRMWARE_CHECK, resp),
     }
-
-    Ok(CheckState::Data(()))
 }
 
-pub fn firmware_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState<()>> {
+pub fn firmware_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState> {
     if !opt.firmware {
-        return Ok(None.into());
+        return Ok(CheckState::skipped(
+            FIRMWARE_CHECK,
+            "Firmware verification not requested",
+        ));
     }
 
     let endp = opt
@@ -141,8 +149,172 @@ pub fn firmware_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<Che
         Some(hash) => check(hash, endp),
         None => {
             bail_check!(
+                FIRMWARE_CHECK,
                 "The Attestation response contains no firmware hash, but checking was enabled"
             )
         }
     }
 }
+
+/// Read the acceptable firmware hashes and their line numbers from `path`
+///
+/// One hex-encoded hash per line. Empty lines and everything after a `#` are ignored.
+fn read_firmware_hashes(path: &Path) -> Result<Vec<(usize, Vec<u8>)>> {
+    let lines = read_text_lines(open_file(path)?, "firmware-hash file")
+        .with_context(|| format!("Cannot read the firmware-hash file '{}'", path.display()))?;
+
+    let mut hashes = Vec::new();
+    for (nr, line) in lines.iter().enumerate().map(|(idx, l)| (idx + 1, l)) {
+        let hash = line.split('#').next().unwrap_or_default().trim();
+        if hash.is_empty() {
+            continue;
+        }
+        match decode_hex_formatted(hash) {
+            Ok(h) if !h.is_empty() => hashes.push((nr, h)),
+            Ok(_) => bail!("Empty firmware hash in '{}' line {nr}", path.display()),
+            Err(e) => bail!(
+                "Invalid firmware hash in '{}' line {nr}: {e}",
+                path.display()
+            ),
+        }
+    }
+    if hashes.is_empty() {
+        bail!(
+            "The firmware-hash file '{}' contains no firmware hashes",
+            path.display()
+        );
+    }
+    Ok(hashes)
+}
+
+/// Check if the firmware hash matches one of the acceptable hashes given by --firmware-hashes
+pub fn firmware_hashes_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState> {
+    let path = match &opt.firmware_hashes {
+        Some(path) => path,
+        None => {
+            return Ok(CheckState::skipped(
+                FIRMWARE_HASHES_CHECK,
+                "No acceptable firmware hashes given",
+            ))
+        }
+    };
+    let hashes = read_firmware_hashes(path)?;
+
+    let fw_hash = match att_res
+        .add_fields
+        .as_ref()
+        .and_then(|add| add.firmware_state())
+    {
+        Some(hash) => hash,
+        None => bail_check!(
+            FIRMWARE_HASHES_CHECK,
+            "The Attestation response contains no firmware hash, but checking was enabled"
+        ),
+    };
+
+    match hashes.iter().find(|(_, h)| h == fw_hash.as_ref()) {
+        Some((nr, _)) => Ok(CheckState::passed(
+            FIRMWARE_HASHES_CHECK,
+            format!(
+                "The firmware hash matches line {nr} of '{}'",
+                path.display()
+            ),
+        )
+        .actual(fw_hash)),
+        None => bail_check!(
+            FIRMWARE_HASHES_CHECK,
+            "The firmware hash {fw_hash:#} is not listed in '{}'",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pv::attest::AttestationFlags;
+    use utils::{HexSlice, TemporaryDirectory};
+
+    use super::*;
+    use crate::cmd::check::{
+        test::{att_res, att_res_add, check_opt},
+        CheckStatus,
+    };
+
+    const FW_STATE_SIZE: usize = 0x140;
+
+    fn hashes_opt(hashes: Option<&Path>) -> CheckOpt {
+        check_opt(
+            hashes
+                .into_iter()
+                .flat_map(|p| ["--firmware-hashes".as_ref(), p.as_os_str()]),
+        )
+    }
+
+    fn check(hashes: Option<&Path>, fw_state: Option<&[u8]>) -> Result<CheckState> {
+        let mut flags = AttestationFlags::default();
+        if fw_state.is_some() {
+            flags.set_firmware_state();
+        }
+        let att_res = match fw_state {
+            Some(fw) => att_res_add(fw, &flags),
+            None => att_res(),
+        };
+        firmware_hashes_check(&hashes_opt(hashes), &att_res)
+    }
+
+    fn hex(fw_state: &[u8]) -> String {
+        format!("{:#}", HexSlice::from(fw_state))
+    }
+
+    #[test]
+    fn firmware_hashes() {
+        let (fw1, fw2) = ([1; FW_STATE_SIZE], [2; FW_STATE_SIZE]);
+        let dir = TemporaryDirectory::new().unwrap();
+        let file = dir.path().join("hashes");
+        std::fs::write(
+            &file,
+            format!(
+                "# acceptable firmware levels\n\n{}  # level 1\n#{}\n",
+                hex(&fw1),
+                hex(&fw2)
+            ),
+        )
+        .unwrap();
+        let hashes = read_firmware_hashes(&file).unwrap();
+        assert_eq!(hashes, [(3, fw1.to_vec())]);
+
+        let state = check(Some(&file), Some(&fw1)).unwrap();
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert!(state.message.contains("line 3"));
+
+        // commented-out hashes are not acceptable
+        let state = check(Some(&file), Some(&fw2)).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert!(state.message.contains(&hex(&fw2)));
+
+        let state = check(Some(&file), None).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+    }
+
+    #[test]
+    fn firmware_hashes_skipped() {
+        let state = check(None, Some(&[1; FW_STATE_SIZE])).unwrap();
+        assert_eq!(state.status, CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn firmware_hashes_malformed() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let file = dir.path().join("hashes");
+        for (content, err) in [
+            ("0x0102\n# comment\n01zz\n", "line 3: "),
+            ("0102\n0x\n", "Empty firmware hash in"),
+            ("# only comments\n\n", "contains no firmware hashes"),
+        ] {
+            std::fs::write(&file, content).unwrap();
+            let msg = read_firmware_hashes(&file).unwrap_err().to_string();
+            assert!(msg.contains(err), "'{msg}' does not contain '{err}'");
+        }
+        assert!(read_firmware_hashes(&dir.path().join("missing")).is_err());
+    }
+}
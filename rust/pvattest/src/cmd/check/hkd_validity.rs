@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::Result;
+use log::warn;
+use pv::request::{HkdVerifier, NoVerifyHkd};
+use utils::{read_hkd, HkdReport};
+
+use super::{bail_check, CheckState};
+use crate::cli::CheckOpt;
+
+/// Name of the host-key document validity check in the check result
+pub const HKD_VALIDITY_CHECK: &str = "host-key-validity";
+
+/// Check that the host-key documents are valid and trusted
+///
+/// Adds the validity reports of all host-key documents to `reports`. The host-key documents are
+/// only verified if certificates are given with --cert, the check is skipped otherwise.
+pub fn hkd_validity_check(opt: &CheckOpt, reports: &mut Vec<HkdReport>) -> Result<CheckState> {
+    if opt.host_key_documents.is_empty() {
+        return Ok(CheckState::skipped(
+            HKD_VALIDITY_CHECK,
+            "No host-key document given",
+        ));
+    }
+    let verifier: Option<Box<dyn HkdVerifier>> = match opt.verify_args.certs.is_empty() {
+        true => None,
+        false => Some(Box::new(opt.verify_args.verifier()?)),
+    };
+
+    for path in &opt.host_key_documents {
+        let hkd = read_hkd(path)?;
+        let report = match &verifier {
+            None => NoVerifyHkd.verify_report(&hkd)?,
+            Some(verifier) => match verifier.verify_report(&hkd) {
+                Ok(report) => report,
+                Err(e) => bail_check!(
+                    HKD_VALIDITY_CHECK,
+                    "The host-key document '{}' cannot be trusted: {e}",
+                    path.display()
+                ),
+            },
+        };
+        reports.push(HkdReport::new(path, report));
+    }
+
+    let warnings: Vec<_> = opt
+        .warn_expiry
+        .map(|days| {
+            reports
+                .iter()
+                .filter_map(|r| r.expiry_warning(days))
+                .collect()
+        })
+        .unwrap_or_default();
+    warnings.iter().for_each(|w| warn!("{w}"));
+
+    Ok(match (&verifier, warnings.is_empty()) {
+        (None, _) => CheckState::skipped(
+            HKD_VALIDITY_CHECK,
+            "The host-key documents are not verified. Specify --cert to verify them",
+        ),
+        (Some(_), true) => {
+            CheckState::passed(HKD_VALIDITY_CHECK, "The host-key documents are valid")
+        }
+        (Some(_), false) => CheckState::passed(
+            HKD_VALIDITY_CHECK,
+            format!("The host-key documents are valid. {}", warnings.join(". ")),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmd::check::{test::check_opt, CheckStatus};
+
+    const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/cert");
+
+    fn check(hkd: &str, args: &[&str]) -> (CheckState, Vec<HkdReport>) {
+        let hkd = format!("{CERT_DIR}/{hkd}");
+        let mut all = vec!["-k", &hkd];
+        all.extend(args);
+        let mut reports = Vec::new();
+        let state = hkd_validity_check(&check_opt(all), &mut reports).unwrap();
+        (state, reports)
+    }
+
+    fn verify_args() -> Vec<String> {
+        [
+            "ibm.crt",
+            "inter_ca.crt",
+            "ibm.crl",
+            "inter_ca.crl",
+            "root_ca.chained.crt",
+        ]
+        .iter()
+        .zip(["--cert", "--cert", "--crl", "--crl", "--root-ca"])
+        .flat_map(|(file, arg)| [arg.to_string(), format!("{CERT_DIR}/{file}")])
+        .chain(["--offline".to_string()])
+        .collect()
+    }
+
+    #[test]
+    fn skipped() {
+        let mut reports = Vec::new();
+        let state = hkd_validity_check(&check_opt::<_, &str>([]), &mut reports).unwrap();
+        assert_eq!(state.status, CheckStatus::Skipped);
+        assert!(reports.is_empty());
+
+        // not verified, but reported
+        let (state, reports) = check("host_crt_expired.crt", &["--warn-expiry", "30"]);
+        assert_eq!(state.status, CheckStatus::Skipped);
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].report().is_expired());
+        assert!(reports[0].expiry_warning(30).is_some());
+    }
+
+    #[test]
+    fn verified() {
+        let args = verify_args();
+        let args: Vec<_> = args.iter().map(String::as_str).collect();
+        let (state, reports) = check("host.crt", &args);
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert_eq!(state.message, "The host-key documents are valid");
+        assert_eq!(reports[0].report().certificates().len(), 4);
+
+        let json = serde_json::to_value(&reports).unwrap();
+        assert!(json[0]["path"].as_str().unwrap().ends_with("/host.crt"));
+        assert_eq!(json[0]["crls"].as_array().unwrap().len(), 2);
+
+        // a near expiry is a warning only
+        let (state, _) = check(
+            "host.crt",
+            &[&args[..], &["--warn-expiry", "999999"]].concat(),
+        );
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert!(
+            state.message.contains("': The root CA '"),
+            "{}",
+            state.message
+        );
+
+        let (state, reports) = check("host_crt_expired.crt", &args);
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert!(state.message.contains("cannot be trusted"));
+        assert!(reports.is_empty());
+    }
+}
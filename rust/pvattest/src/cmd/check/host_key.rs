@@ -3,19 +3,18 @@
 // Copyright IBM Corp. 2024
 
 use anyhow::Result;
-use log::{debug, info};
+use log::debug;
 use pv::{
     misc::{read_certs, read_file},
     request::{openssl::DigestBytes, EcPubKeyCoord},
 };
-use serde::Serialize;
 use std::{fmt::Display, path::Path};
 use utils::HexSlice;
 
 use super::CheckState;
 use crate::{
     additional::AttestationResult,
-    cli::{CheckOpt, HostKeyCheckPolicy},
+    cli::{CheckOpt, HostKeyCheckPolicy, HOST_KEY_HASH_SIZE},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -24,6 +23,16 @@ pub enum HkCheck {
     Attest,
 }
 
+impl HkCheck {
+    /// Name of the check in the check result
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Image => "image-host-key",
+            Self::Attest => "attestation-host-key",
+        }
+    }
+}
+
 impl Display for HkCheck {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -52,59 +61,78 @@ fn load_host_keys<A: AsRef<Path>>(hkds: &[A]) -> Result<Vec<(&Path, DigestBytes)
     Ok(hkd_hash)
 }
 
-fn contains_phkh<'a>(
-    hkd_hashes: &[(&'a Path, DigestBytes)],
-    phkh: &HexSlice<'_>,
-    mode: HkCheck,
-    check_enforced: bool,
-) -> CheckState<HostKeyCheck<'a>> {
-    let hk: Vec<_> = hkd_hashes
-        .iter()
-        .filter_map(|(path, hash)| match hash.as_ref() == phkh.as_ref() {
-            true => Some(*path),
-            false => None,
-        })
-        .collect();
+/// Origin of an acceptable host-key hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HkSource<'a> {
+    /// Given by --host-key-document
+    Document(&'a Path),
+    /// Given by --host-key-hash
+    Hash,
+}
 
-    debug!("HK: {hk:?}");
-    match hk.len() {
-        0 => CheckState::Err(format!(
-            "No given host-key document matches the given {mode}"
-        )),
-        1 => CheckState::Data(HostKeyCheck::new(check_enforced, hk.first().copied())),
-        _ => CheckState::Err(format!(
-            "More than one host-key document matches the given {mode}"
-        )),
+impl Display for HkSource<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Document(path) => write!(f, "the host-key document '{}'", path.display()),
+            Self::Hash => write!(f, "a given host-key hash"),
+        }
     }
 }
 
-#[derive(Debug, Serialize, Default)]
-pub struct HostKeyCheck<'a> {
-    check_enforced: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    hash: Option<&'a Path>,
+/// Acceptable host-key hashes of all given host-key documents and hashes
+fn candidates<'a>(
+    hkd_hashes: &'a [(&'a Path, DigestBytes)],
+    hashes: &'a [[u8; HOST_KEY_HASH_SIZE]],
+) -> Vec<(HkSource<'a>, &'a [u8])> {
+    hkd_hashes
+        .iter()
+        .map(|(path, hash)| (HkSource::Document(path), hash.as_ref()))
+        .chain(hashes.iter().map(|hash| (HkSource::Hash, hash.as_slice())))
+        .collect()
 }
 
-impl<'a> HostKeyCheck<'a> {
-    pub const fn new(check_enforced: bool, hash: Option<&'a Path>) -> Self {
-        Self {
-            check_enforced,
-            hash,
-        }
-    }
+fn contains_phkh(
+    candidates: &[(HkSource<'_>, &[u8])],
+    phkh: &HexSlice<'_>,
+    mode: HkCheck,
+) -> CheckState {
+    let hk: Vec<_> = candidates
+        .iter()
+        .filter(|(_, hash)| *hash == phkh.as_ref())
+        .collect();
 
-    pub const fn hide(&self) -> bool {
-        self.hash.is_none() && !self.check_enforced
-    }
+    debug!(
+        "HK: {:?}",
+        hk.iter().map(|(src, _)| src).collect::<Vec<_>>()
+    );
+    let state = match hk.first() {
+        None => CheckState::failed(
+            mode.name(),
+            format!("No given host-key document or hash matches the given {mode}"),
+        )
+        .candidates(candidates.iter().map(|(_, hash)| hash)),
+        Some((_, hash)) => {
+            let sources: Vec<_> = hk.iter().map(|(src, _)| src.to_string()).collect();
+            CheckState::passed(
+                mode.name(),
+                format!("The {mode} matches {}", sources.join(", ")),
+            )
+            .expected(hash)
+        }
+    };
+    state.actual(phkh)
 }
 
-pub fn host_key_check<'a, 'b>(
-    opt: &'a CheckOpt,
+pub fn host_key_check(
+    opt: &CheckOpt,
     kind: HkCheck,
-    att_res: &'b AttestationResult<'b>,
-) -> Result<CheckState<HostKeyCheck<'a>>> {
-    if opt.host_key_documents.is_empty() {
-        return Ok(CheckState::Data(HostKeyCheck::default()));
+    att_res: &AttestationResult<'_>,
+) -> Result<CheckState> {
+    if opt.host_key_documents.is_empty() && opt.host_key_hashes.is_empty() {
+        return Ok(CheckState::skipped(
+            kind.name(),
+            format!("No host-key documents or hashes given to check the {kind}"),
+        ));
     }
 
     let check_enforced = opt.host_key_checks.contains(&match kind {
@@ -113,6 +141,7 @@ pub fn host_key_check<'a, 'b>(
     });
 
     let hkd_hashes = load_host_keys(&opt.host_key_documents)?;
+    let candidates = candidates(&hkd_hashes, &opt.host_key_hashes);
 
     let res = match att_res
         .add_fields
@@ -121,23 +150,89 @@ pub fn host_key_check<'a, 'b>(
             HkCheck::Image => add_fields.image_public_host_key_hash(),
             HkCheck::Attest => add_fields.attestation_public_host_key_hash(),
         }) {
-        Some(phkh) => contains_phkh(&hkd_hashes, phkh, kind, check_enforced),
-        None if check_enforced => CheckState::Err(format!(
-            "The Attestation result does not contain an {}, but checking was enabled.",
-            kind
-        )),
-        None => CheckState::Data(HostKeyCheck::default()),
+        Some(phkh) => contains_phkh(&candidates, phkh, kind),
+        None if check_enforced => CheckState::failed(
+            kind.name(),
+            format!("The Attestation result does not contain an {kind}, but checking was enabled."),
+        ),
+        None => CheckState::skipped(
+            kind.name(),
+            format!("The Attestation result does not contain an {kind}"),
+        ),
     };
-
-    info!("✓ Check {kind}");
     Ok(res)
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use pv::attest::AttestationFlags;
 
     use super::*;
+    use crate::cmd::check::{
+        test::{att_res_add, check_opt, try_check_opt},
+        CheckStatus,
+    };
+
+    const HKD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt");
+    const HKD2: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/simulate/host.crt"
+    );
+
+    fn hkd_hash(hkd: &str) -> Vec<u8> {
+        load_host_keys(&[hkd]).unwrap()[0].1.to_vec()
+    }
+
+    /// Check `phkh` as image host-key hash against the host-key options `args`
+    fn check_image_phkh(args: &[&str], phkh: &[u8]) -> CheckState {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        let att_res = att_res_add(phkh, &flags);
+        host_key_check(&check_opt(args), HkCheck::Image, &att_res).unwrap()
+    }
+
+    #[test]
+    fn check_one_of_two() {
+        let phkh = hkd_hash(HKD2);
+        let state = check_image_phkh(&["-k", HKD, "-k", HKD2], &phkh);
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert!(state.message.ends_with(&format!("'{HKD2}'")));
+        assert_eq!(state.expected, state.actual);
+
+        let old = format!("{:#}", HexSlice::from(&[0x42; 32]));
+        let state = check_image_phkh(&["-k", HKD2, "--host-key-hash", &old], &[0x42; 32]);
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert_eq!(
+            state.message,
+            "The image public host-key hash matches a given host-key hash"
+        );
+        assert_eq!(state.expected, Some(old));
+        assert!(state.candidates.is_empty());
+    }
+
+    #[test]
+    fn check_none_of_two() {
+        let hash = format!("{:#}", HexSlice::from(&[0x42; 32]));
+        let state = check_image_phkh(&["-k", HKD, "--host-key-hash", &hash], &hkd_hash(HKD2));
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert!(state.expected.is_none());
+        assert_eq!(
+            state.candidates,
+            [
+                format!("{:#}", HexSlice::from(&hkd_hash(HKD))),
+                hash.clone()
+            ]
+        );
+        assert!(state
+            .to_string()
+            .ends_with(&format!("    candidate: {hash}\n")));
+    }
+
+    #[test]
+    fn host_key_hash_invalid() {
+        assert!(try_check_opt(["--host-key-hash", "0x1234"]).is_err());
+        assert!(try_check_opt(["--host-key-check", "att-key-hash"]).is_err());
+    }
 
     #[test]
     fn check_hash_neq() {
@@ -145,8 +240,12 @@ mod test {
             [concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt").to_string()];
         let hash = load_host_keys(&hostkey).unwrap();
 
-        let res = contains_phkh(&hash, &HexSlice::from(&[0; 32]), HkCheck::Image, true);
-        assert!(matches!(res, CheckState::Err(_)));
+        let res = contains_phkh(
+            &candidates(&hash, &[]),
+            &HexSlice::from(&[0; 32]),
+            HkCheck::Image,
+        );
+        assert_eq!(res.status, CheckStatus::Failed);
     }
 
     #[test]
@@ -157,8 +256,34 @@ mod test {
         ];
         let hash = load_host_keys(&hostkey).unwrap();
 
-        let res = contains_phkh(&hash, &HexSlice::from(&hash[0].1), HkCheck::Image, true);
-        assert!(matches!(res, CheckState::Err(_)));
+        let res = contains_phkh(
+            &candidates(&hash, &[]),
+            &HexSlice::from(&hash[0].1),
+            HkCheck::Image,
+        );
+        assert_eq!(res.status, CheckStatus::Passed);
+        assert_eq!(res.expected, res.actual);
+        assert_eq!(
+            res.message,
+            format!(
+                "The image public host-key hash matches the host-key document '{0}', the \
+                 host-key document '{0}'",
+                hostkey[0]
+            )
+        );
+
+        // a host-key document and its hash
+        let phkh: [u8; HOST_KEY_HASH_SIZE] = hash[0].1.as_ref().try_into().unwrap();
+        let res = contains_phkh(
+            &candidates(&hash[..1], &[phkh]),
+            &HexSlice::from(&phkh),
+            HkCheck::Image,
+        );
+        assert_eq!(res.status, CheckStatus::Passed);
+        assert!(res.message.ends_with(&format!(
+            "the host-key document '{}', a given host-key hash",
+            hostkey[0]
+        )));
     }
 
     #[test]
@@ -167,10 +292,13 @@ mod test {
             [concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt").to_string()];
         let hash = load_host_keys(&hostkey).unwrap();
 
-        let res = contains_phkh(&hash, &HexSlice::from(&hash[0].1), HkCheck::Image, true);
-        assert!(matches!(
-            res,
-            CheckState::Data(s) if s.hash.unwrap() == PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt"))
-        ))
+        let res = contains_phkh(
+            &candidates(&hash, &[]),
+            &HexSlice::from(&hash[0].1),
+            HkCheck::Image,
+        );
+        assert_eq!(res.status, CheckStatus::Passed);
+        assert_eq!(res.expected, res.actual);
+        assert!(res.message.ends_with(&format!("'{}'", hostkey[0])));
     }
 }
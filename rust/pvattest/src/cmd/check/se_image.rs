@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::{Context, Result};
+use log::debug;
+use pv::{misc::open_file, se_hdr::SeHdr};
+use utils::HexSlice;
+
+use super::CheckState;
+use crate::{additional::AttestationResult, cli::CheckOpt};
+
+/// Name of the SE-image check in the check result
+pub const SE_IMAGE_CHECK: &str = "se-image";
+
+/// Check if a keyslot of the SE image matches the image public host-key hash of the response
+pub fn se_image_check(opt: &CheckOpt, att_res: &AttestationResult<'_>) -> Result<CheckState> {
+    let Some(path) = &opt.se_image else {
+        return Ok(CheckState::skipped(
+            SE_IMAGE_CHECK,
+            "No Secure Execution image given",
+        ));
+    };
+    let hdr = SeHdr::from_se_image(&mut open_file(path)?).with_context(|| {
+        format!(
+            "Cannot read the Secure Execution header of '{}'",
+            path.display()
+        )
+    })?;
+    debug!(
+        "SE-header tag of '{}': {:#}",
+        path.display(),
+        HexSlice::from(hdr.tags().tag())
+    );
+
+    let Some(phkh) = att_res
+        .add_fields
+        .as_ref()
+        .and_then(|add_fields| add_fields.image_public_host_key_hash())
+    else {
+        return Ok(CheckState::skipped(
+            SE_IMAGE_CHECK,
+            "The Attestation result does not contain an image public host-key hash",
+        ));
+    };
+
+    let phkhs = hdr.phkhs();
+    let state = match phkhs.iter().position(|hash| hash == phkh.as_ref()) {
+        Some(slot) => CheckState::passed(
+            SE_IMAGE_CHECK,
+            format!(
+                "The image public host-key hash matches keyslot {} of {} of the Secure Execution \
+                 image '{}'",
+                slot + 1,
+                phkhs.len(),
+                path.display()
+            ),
+        )
+        .expected(phkhs[slot]),
+        None => CheckState::failed(
+            SE_IMAGE_CHECK,
+            format!(
+                "No keyslot of the Secure Execution image '{}' matches the image public host-key \
+                 hash",
+                path.display()
+            ),
+        )
+        .candidates(phkhs),
+    };
+    Ok(state.actual(phkh))
+}
+
+#[cfg(test)]
+mod test {
+    use pv::attest::AttestationFlags;
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::cmd::check::{
+        test::{att_res, att_res_add, check_opt},
+        CheckStatus,
+    };
+
+    /// SE header with the keyslots for `tests/assets/host.pem.crt` and `simulate/host.crt`
+    const HDR: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/simulate/two_keyslots.hdr"
+    );
+    const PHKH_1: &str = "0x596e8bf9ceb88e686c74ff007c6df5124e00156981279011f0c6c388b6e95892";
+    const PHKH_2: &str = "0x24933f75ee107c806fe602a7da7b610e54369af96a847f2dcc4fcfdbd81fbc05";
+
+    /// Check the SE image against a response with `add` as additional data
+    fn check(image: &str, add: &[u8], flags: &AttestationFlags) -> Result<CheckState> {
+        se_image_check(&check_opt(["--se-image", image]), &att_res_add(add, flags))
+    }
+
+    fn image_phkh(phkh: &str) -> (Vec<u8>, AttestationFlags) {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        (pv::misc::decode_hex_formatted(phkh).unwrap(), flags)
+    }
+
+    #[test]
+    fn keyslot_match() {
+        let (add, flags) = image_phkh(PHKH_2);
+        let state = check(HDR, &add, &flags).unwrap();
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert_eq!(
+            state.message,
+            format!(
+                "The image public host-key hash matches keyslot 2 of 2 of the Secure Execution \
+                 image '{HDR}'"
+            )
+        );
+        assert_eq!(state.expected.as_deref(), Some(PHKH_2));
+        assert_eq!(state.expected, state.actual);
+
+        // also for the header in a bootable image
+        let dir = TemporaryDirectory::new().unwrap();
+        let img = dir.path().join("img");
+        let mut data = vec![0; 0x13000];
+        let hdr = std::fs::read(HDR).unwrap();
+        data[0x12000..0x12000 + hdr.len()].copy_from_slice(&hdr);
+        std::fs::write(&img, data).unwrap();
+        let (add, flags) = image_phkh(PHKH_1);
+        let state = check(img.to_str().unwrap(), &add, &flags).unwrap();
+        assert_eq!(state.status, CheckStatus::Passed);
+        assert!(state.message.contains("keyslot 1 of 2"));
+    }
+
+    #[test]
+    fn keyslot_mismatch() {
+        let (add, flags) = image_phkh(&format!("0x{}", "42".repeat(32)));
+        let state = check(HDR, &add, &flags).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert!(state.expected.is_none());
+        assert_eq!(state.candidates, [PHKH_1, PHKH_2]);
+    }
+
+    #[test]
+    fn no_image_phkh() {
+        // only the attestation public host-key hash
+        let mut flags = AttestationFlags::default();
+        flags.set_attest_phkh();
+        let state = check(HDR, &[0x42; 32], &flags).unwrap();
+        assert_eq!(state.status, CheckStatus::Skipped);
+
+        let state = se_image_check(&check_opt::<_, &str>([]), &att_res()).unwrap();
+        assert_eq!(state.status, CheckStatus::Skipped);
+    }
+
+    #[test]
+    fn no_se_image() {
+        let (add, flags) = image_phkh(PHKH_1);
+        let host_key = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt");
+        let err = check(host_key, &add, &flags).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!(
+                "Cannot read the Secure Execution header of '{host_key}': Input does not contain \
+                 a Secure Execution header"
+            )
+        );
+        assert!(check("missing", &add, &flags).is_err());
+    }
+}
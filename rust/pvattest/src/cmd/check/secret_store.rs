@@ -4,42 +4,99 @@
 
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use log::info;
-use openssl::hash::DigestBytes;
-use openssl::hash::{hash, MessageDigest};
-use pv::{misc::read_file, secret::AddSecretRequest};
-use serde::Serialize;
+use anyhow::{Context, Result};
+use pv::{
+    attest::SecretStoreState,
+    misc::{open_file, read_file_limited},
+    secret::{AddSecretReceipt, AddSecretRequest},
+};
+use utils::{sorted_dir_files, FileOrder};
 
 use super::{bail_check, CheckState};
-use crate::{additional::AttestationResult, cli::CheckOpt};
+use crate::{
+    additional::AttestationResult,
+    cli::{CheckOpt, SecretInput, SecretOrder},
+};
 
-#[derive(Debug, Serialize)]
-pub struct SecretStoreCheck<'a> {
-    add_secret_requests: &'a [PathBuf],
-    locked: bool,
+/// Name of the secret store check in the check result
+pub const SECRET_STORE_CHECK: &str = "secret-store";
+
+/// Sorted add-secret requests in `dir` (non-recursive)
+fn dir_requests(dir: &Path, order: SecretOrder) -> Result<Vec<PathBuf>> {
+    let order = match order {
+        SecretOrder::Name => FileOrder::Name,
+        SecretOrder::Mtime => FileOrder::Mtime,
+    };
+    sorted_dir_files(dir, order).with_context(|| {
+        format!(
+            "Cannot read the add-secret request directory '{}'",
+            dir.display()
+        )
+    })
 }
 
-const REQUEST_TAG_SIZE: usize = 16;
+/// Expand directories in `inputs` to the add-secret requests contained in them
+fn expand_requests(inputs: &[SecretInput], order: SecretOrder) -> Result<Vec<SecretInput>> {
+    let mut requests = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        match input {
+            SecretInput::Request(path) if path.is_dir() => requests.extend(
+                dir_requests(path, order)?
+                    .into_iter()
+                    .map(SecretInput::Request),
+            ),
+            input => requests.push(input.clone()),
+        }
+    }
+    Ok(requests)
+}
 
-fn secret_store_hash<A: AsRef<Path>>(asrcbs: &[A], locked: bool) -> Result<DigestBytes> {
-    let mut requests = Vec::with_capacity(asrcbs.len() * REQUEST_TAG_SIZE + 1);
-    for asrcb in asrcbs {
-        let asrcb = read_file(asrcb, "Add-secret request")?;
-        let mut tag = AddSecretRequest::bin_tag(&asrcb)?;
-        requests.append(&mut tag);
+fn secret_store_hash(inputs: &[SecretInput], locked: bool) -> Result<[u8; 64]> {
+    let mut state = SecretStoreState::new();
+    // the previous receipt, if the previous input was a receipt
+    let mut previous: Option<Vec<u8>> = None;
+    for input in inputs {
+        match input {
+            SecretInput::Request(asrcb) => {
+                // read the tag only, not the (encrypted) secret
+                let mut file = open_file(asrcb)?;
+                let tag = AddSecretRequest::bin_tag_from_reader(&mut file).with_context(|| {
+                    format!("'{}' is not a valid add-secret request", asrcb.display())
+                })?;
+                state.add(tag)?;
+                previous = None;
+            }
+            SecretInput::Tag(tag) => {
+                state.add(*tag)?;
+                previous = None;
+            }
+            SecretInput::Receipt(path) => {
+                let ctx = || format!("'{}' is not a valid add-secret receipt", path.display());
+                let data =
+                    read_file_limited(path, "add-secret receipt", AddSecretReceipt::MAX_SIZE)?;
+                let receipt: AddSecretReceipt = serde_json::from_slice(&data).with_context(ctx)?;
+                receipt
+                    .apply(&mut state, previous.as_deref())
+                    .with_context(ctx)?;
+                previous = Some(data);
+            }
+        }
+    }
+    if locked {
+        state.lock();
     }
-    requests.push(locked as u8);
-    Ok(hash(MessageDigest::sha512(), &requests)?)
+    Ok(state.hash()?)
 }
 
-pub fn secret_store_check<'a>(
-    opt: &'a CheckOpt,
-    att_res: &AttestationResult,
-) -> Result<CheckState<SecretStoreCheck<'a>>> {
+pub fn secret_store_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState> {
     // The locked flag is the feature gate of this check
     let locked = match opt.secret_store_locked {
-        None => return Ok(CheckState::None),
+        None => {
+            return Ok(CheckState::skipped(
+                SECRET_STORE_CHECK,
+                "No secret store state given",
+            ))
+        }
         Some(state) => state,
     };
 
@@ -50,25 +107,150 @@ pub fn secret_store_check<'a>(
     {
         Some(h) => h,
         None => bail_check!(
+            SECRET_STORE_CHECK,
             "The Attestation response contains no secret-store-hash, but checking was enabled"
         ),
     };
 
-    if secret_store_hash(&opt.secret, locked)?.as_ref() != att_store_hash.as_ref() {
-        bail_check!("The calculated secret-store-hash does not match with the provided hash");
-    }
-    info!("✓ Secret Store hash");
-
-    Ok(CheckState::Data(SecretStoreCheck {
-        add_secret_requests: &opt.secret,
-        locked,
-    }))
+    let asrcbs = expand_requests(&opt.secrets.0, opt.secret_order)?;
+    let calc_store_hash = secret_store_hash(&asrcbs, locked)?;
+    let state = match calc_store_hash.as_ref() == att_store_hash.as_ref() {
+        true => CheckState::passed(
+            SECRET_STORE_CHECK,
+            format!(
+                "The secret store contains the {} given add-secret request(s) and is {}locked",
+                asrcbs.len(),
+                if locked { "" } else { "not " }
+            ),
+        ),
+        false => CheckState::failed(
+            SECRET_STORE_CHECK,
+            "The calculated secret-store-hash does not match with the provided hash",
+        ),
+    };
+    Ok(state.expected(calc_store_hash).actual(att_store_hash))
 }
+
 #[cfg(test)]
 mod test {
-    use super::secret_store_hash;
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use pv::attest::AttestationFlags;
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::{cmd::check::test::try_check_opt, exchange::ExchangeFormatResponse};
 
     const ASRCB_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/asrcb");
+    /// Secret store hash of `assoc_simple_default_cuid_one` and a locked secret store
+    const HASH_ASSOC_SIMPLE_LOCKED: &str = concat!(
+        "2b821d92506d4475ad33bb0b720ba296c6e34222b7df5bc03c5734b971e30a1c",
+        "75d528bddf10909e3d5edffbee9ded1a25b85ed0fd15ad2cd19add45554e27ad"
+    );
+
+    fn try_parse_check<I: IntoIterator<Item = String>>(secrets: I) -> Result<CheckOpt> {
+        let locked = ["--secret-store-locked", "true"].map(String::from);
+        try_check_opt(locked.into_iter().chain(secrets))
+    }
+
+    fn secrets_opt(asrcbs: &[&str]) -> CheckOpt {
+        let secrets = asrcbs
+            .iter()
+            .flat_map(|a| ["--secret".to_string(), format!("{ASRCB_DIR}/{a}")]);
+        try_parse_check(secrets).unwrap()
+    }
+
+    /// Hex-encoded tag of the asset `asrcb`
+    fn asset_tag(asrcb: &str) -> String {
+        let data = fs::read(format!("{ASRCB_DIR}/{asrcb}")).unwrap();
+        pv::misc::encode_hex(AddSecretRequest::bin_tag(&data).unwrap())
+    }
+
+    fn files<A: AsRef<Path>>(paths: &[A]) -> Vec<SecretInput> {
+        paths
+            .iter()
+            .map(|p| SecretInput::Request(p.as_ref().to_owned()))
+            .collect()
+    }
+
+    fn names(inputs: &[SecretInput]) -> Vec<String> {
+        inputs
+            .iter()
+            .map(|input| match input {
+                SecretInput::Request(p) => p.file_name().unwrap().to_string_lossy().into_owned(),
+                input => panic!("Unexpected input {input:?}"),
+            })
+            .collect()
+    }
+
+    fn response(secret_store_hash: &[u8]) -> ExchangeFormatResponse {
+        let add = Some(secret_store_hash.to_vec());
+        ExchangeFormatResponse::new(vec![0; 0x20], vec![0; 0x40], add, None, [0; 16]).unwrap()
+    }
+
+    fn secret_store_flags() -> AttestationFlags {
+        let mut flags = AttestationFlags::default();
+        flags.set_secret_store_hash();
+        flags
+    }
+
+    #[test]
+    fn check_passed_json() {
+        let opt = secrets_opt(&["assoc_simple_default_cuid_one"]);
+        let hash = secret_store_hash(&opt.secrets.0, true).unwrap();
+        let resp = response(&hash);
+        let att_res = AttestationResult::from_exchange(&resp, &secret_store_flags()).unwrap();
+
+        let state = secret_store_check(&opt, &att_res).unwrap();
+        let exp = r#"{
+  "name": "secret-store",
+  "status": "passed",
+  "expected": "0x{HASH}",
+  "actual": "0x{HASH}",
+  "message": "The secret store contains the 1 given add-secret request(s) and is locked"
+}"#;
+        assert_eq!(
+            serde_json::to_string_pretty(&state).unwrap(),
+            exp.replace("{HASH}", HASH_ASSOC_SIMPLE_LOCKED)
+        );
+    }
+
+    #[test]
+    fn check_failed_json() {
+        let opt = secrets_opt(&["assoc_simple_default_cuid_one"]);
+        let resp = response(&[0; 64]);
+        let att_res = AttestationResult::from_exchange(&resp, &secret_store_flags()).unwrap();
+
+        let state = secret_store_check(&opt, &att_res).unwrap();
+        let exp = r#"{
+  "name": "secret-store",
+  "status": "failed",
+  "expected": "0x{HASH}",
+  "actual": "0x{ZERO}",
+  "message": "The calculated secret-store-hash does not match with the provided hash"
+}"#;
+        assert_eq!(
+            serde_json::to_string_pretty(&state).unwrap(),
+            exp.replace("{HASH}", HASH_ASSOC_SIMPLE_LOCKED)
+                .replace("{ZERO}", &"00".repeat(64))
+        );
+    }
+
+    #[test]
+    fn check_skipped() {
+        let mut opt = secrets_opt(&[]);
+        opt.secret_store_locked = None;
+        let resp = response(&[0; 64]);
+        let att_res = AttestationResult::from_exchange(&resp, &secret_store_flags()).unwrap();
+
+        let state = secret_store_check(&opt, &att_res).unwrap();
+        assert!(serde_json::to_string(&state)
+            .unwrap()
+            .contains(r#""status":"skipped""#));
+    }
 
     #[test]
     fn hash() {
@@ -83,7 +265,7 @@ mod test {
             format!("{ASRCB_DIR}/null_none_default_cuid_seven"),
             format!("{ASRCB_DIR}/null_none_dump_cuid_one"),
         ];
-        let hash = secret_store_hash(&asrcbs, true).unwrap();
+        let hash = secret_store_hash(&files(&asrcbs), true).unwrap();
         let exp = [
             0xd0, 0x48, 0x70, 0x2b, 0x4a, 0x79, 0x47, 0x8b, 0x98, 0x5e, 0x92, 0xe7, 0xed, 0xff,
             0x45, 0x3f, 0x63, 0xf2, 0x4, 0x4e, 0x7d, 0x72, 0xfa, 0xf1, 0x2e, 0xfd, 0x2e, 0xae,
@@ -93,7 +275,7 @@ mod test {
         ];
         assert_eq!(&exp, hash.as_ref());
 
-        let hash = secret_store_hash(&asrcbs, false).unwrap();
+        let hash = secret_store_hash(&files(&asrcbs), false).unwrap();
         let exp = [
             0x51, 0xce, 0x62, 0xaf, 0x1f, 0x67, 0xb9, 0xe3, 0x25, 0x4b, 0x18, 0x4e, 0x33, 0xb2,
             0xaa, 0xd3, 0x10, 0x7, 0x58, 0x1a, 0x39, 0xe9, 0x9c, 0xde, 0xb0, 0x29, 0x98, 0xa3,
@@ -106,7 +288,7 @@ mod test {
 
     #[test]
     fn hash_empty() {
-        let hash = secret_store_hash::<&str>(&[], true).unwrap();
+        let hash = secret_store_hash(&[], true).unwrap();
         let exp = [
             0x7b, 0x54, 0xb6, 0x68, 0x36, 0xc1, 0xfb, 0xdd, 0x13, 0xd2, 0x44, 0x1d, 0x9e, 0x14,
             0x34, 0xdc, 0x62, 0xca, 0x67, 0x7f, 0xb6, 0x8f, 0x5f, 0xe6, 0x6a, 0x46, 0x4b, 0xaa,
@@ -116,7 +298,7 @@ mod test {
         ];
         assert_eq!(&exp, hash.as_ref());
 
-        let hash = secret_store_hash::<&str>(&[], false).unwrap();
+        let hash = secret_store_hash(&[], false).unwrap();
         let exp = [
             0xb8, 0x24, 0x4d, 0x2, 0x89, 0x81, 0xd6, 0x93, 0xaf, 0x7b, 0x45, 0x6a, 0xf8, 0xef,
             0xa4, 0xca, 0xd6, 0x3d, 0x28, 0x2e, 0x19, 0xff, 0x14, 0x94, 0x2c, 0x24, 0x6e, 0x50,
@@ -126,4 +308,297 @@ mod test {
         ];
         assert_eq!(&exp, hash.as_ref());
     }
+
+    /// Copy the assets into a new directory as `00`, `01`, ... with increasing mtimes
+    fn asrcb_dir(assets: &[&str]) -> TemporaryDirectory {
+        let dir = TemporaryDirectory::new().unwrap();
+        let start = SystemTime::now() - Duration::from_secs(3600);
+        for (i, asset) in assets.iter().enumerate() {
+            let path = dir.path().join(format!("{i:02}"));
+            fs::copy(format!("{ASRCB_DIR}/{asset}"), &path).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(start + Duration::from_secs(i as u64))
+                .unwrap();
+        }
+        dir
+    }
+
+    fn set_mtime(path: &Path, mtime: SystemTime) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    const DIR_ASSETS: [&str; 3] = [
+        "assoc_simple_default_cuid_one",
+        "null_none_default_cuid_seven",
+        "assoc_derived_default_cuid_one",
+    ];
+
+    #[test]
+    fn dir_name_order() {
+        let dir = asrcb_dir(&DIR_ASSETS);
+        // sub-directories are ignored
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        // the mtime must not influence the name order
+        set_mtime(&dir.path().join("00"), SystemTime::now());
+
+        let asrcbs = expand_requests(&files(&[dir.path()]), SecretOrder::Name).unwrap();
+        assert_eq!(names(&asrcbs), ["00", "01", "02"]);
+
+        let exp: Vec<_> = DIR_ASSETS
+            .iter()
+            .map(|a| format!("{ASRCB_DIR}/{a}"))
+            .collect();
+        assert_eq!(
+            secret_store_hash(&asrcbs, true).unwrap().as_ref(),
+            secret_store_hash(&files(&exp), true).unwrap().as_ref()
+        );
+    }
+
+    #[test]
+    fn dir_mtime_order() {
+        let dir = asrcb_dir(&DIR_ASSETS);
+        // make "00" the newest file
+        set_mtime(&dir.path().join("00"), SystemTime::now());
+
+        let single = format!("{ASRCB_DIR}/null_none_dump_cuid_one");
+        let paths = [PathBuf::from(&single), dir.path().to_owned()];
+        let asrcbs = expand_requests(&files(&paths), SecretOrder::Mtime).unwrap();
+        assert_eq!(
+            names(&asrcbs),
+            ["null_none_dump_cuid_one", "01", "02", "00"]
+        );
+
+        let exp: Vec<_> = [single]
+            .into_iter()
+            .chain([1, 2, 0].map(|i| format!("{ASRCB_DIR}/{}", DIR_ASSETS[i])))
+            .collect();
+        assert_eq!(
+            secret_store_hash(&asrcbs, false).unwrap().as_ref(),
+            secret_store_hash(&files(&exp), false).unwrap().as_ref()
+        );
+    }
+
+    #[test]
+    fn dir_invalid_request() {
+        let dir = asrcb_dir(&DIR_ASSETS[..1]);
+        let invalid = dir.path().join("01");
+        fs::write(&invalid, b"no add-secret request").unwrap();
+
+        let asrcbs = expand_requests(&files(&[dir.path()]), SecretOrder::Name).unwrap();
+        let err = secret_store_hash(&asrcbs, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("'{}' is not a valid add-secret request", invalid.display())
+        );
+    }
+
+    #[test]
+    fn dir_missing() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let missing = dir.path().join("missing");
+        let inputs = files(&[&missing]);
+        assert_eq!(expand_requests(&inputs, SecretOrder::Name).unwrap(), inputs);
+        let err = secret_store_hash(&inputs, true).unwrap_err();
+        assert!(err.to_string().contains("missing"), "{err}");
+    }
+
+    #[test]
+    fn tags_mixed_with_files() {
+        let assets = [
+            "assoc_derived_default_cuid_one",
+            "assoc_simple_default_cuid_one",
+            "null_none_default_cuid_one",
+            "null_none_default_ncuid_one",
+            "null_simple_default_cuid_one",
+        ];
+        let file = |a: &str| format!("{ASRCB_DIR}/{a}");
+        let args = [
+            "--secret-tag".to_string(),
+            asset_tag(assets[0]),
+            "--secret".to_string(),
+            format!("{},{}", file(assets[1]), file(assets[2])),
+            "--secret-tag".to_string(),
+            format!("0x{}", asset_tag(assets[3])),
+            "--secret".to_string(),
+            file(assets[4]),
+        ];
+        let opt = try_parse_check(args).unwrap();
+        assert!(matches!(
+            opt.secrets.0[..],
+            [
+                SecretInput::Tag(_),
+                SecretInput::Request(_),
+                SecretInput::Request(_),
+                SecretInput::Tag(_),
+                SecretInput::Request(_)
+            ]
+        ));
+
+        let exp: Vec<_> = assets.iter().map(|a| file(a)).collect();
+        for locked in [true, false] {
+            assert_eq!(
+                secret_store_hash(&opt.secrets.0, locked).unwrap().as_ref(),
+                secret_store_hash(&files(&exp), locked).unwrap().as_ref()
+            );
+        }
+
+        // tags only
+        let tags = assets
+            .iter()
+            .flat_map(|a| ["--secret-tag".to_string(), asset_tag(a)]);
+        let opt = try_parse_check(tags).unwrap();
+        assert_eq!(
+            secret_store_hash(&opt.secrets.0, true).unwrap().as_ref(),
+            secret_store_hash(&files(&exp), true).unwrap().as_ref()
+        );
+    }
+
+    /// Unlocked secret-store hash after adding [`DIR_ASSETS`]
+    const HASH_DIR_ASSETS: &str = concat!(
+        "a661c0466fbc983a738fe36fb6ce6d3eac08bccd0b1fca77c5859ad12b7d0f9b",
+        "7cc200c7eb0b0b4357728fbd7984d715a55d5f1902869df90b507862280b9812"
+    );
+
+    /// Write the receipts of adding [`DIR_ASSETS`] one after another to `dir`
+    fn receipts(dir: &TemporaryDirectory) -> Vec<PathBuf> {
+        let mut state = SecretStoreState::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut paths = Vec::new();
+        for (i, asset) in DIR_ASSETS.iter().enumerate() {
+            let asrcb = fs::read(format!("{ASRCB_DIR}/{asset}")).unwrap();
+            let receipt = AddSecretReceipt::new(&state, &asrcb, 1, previous.as_deref()).unwrap();
+            state = receipt.verify().unwrap();
+            let data = utils::canonical_json::to_vec(&receipt).unwrap();
+            let path = dir.path().join(format!("receipt{i}.json"));
+            fs::write(&path, &data).unwrap();
+            paths.push(path);
+            previous = Some(data);
+        }
+        paths
+    }
+
+    fn receipt_args<P: AsRef<Path>>(paths: &[P]) -> Vec<String> {
+        paths
+            .iter()
+            .flat_map(|p| {
+                [
+                    "--secret-receipt".to_string(),
+                    p.as_ref().display().to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn receipt_chain() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let receipts = receipts(&dir);
+        let opt = try_parse_check(receipt_args(&receipts)).unwrap();
+        assert_eq!(
+            pv::misc::encode_hex(secret_store_hash(&opt.secrets.0, false).unwrap()),
+            HASH_DIR_ASSETS
+        );
+
+        let exp: Vec<_> = DIR_ASSETS
+            .iter()
+            .map(|a| format!("{ASRCB_DIR}/{a}"))
+            .collect();
+        for locked in [true, false] {
+            assert_eq!(
+                secret_store_hash(&opt.secrets.0, locked).unwrap().as_ref(),
+                secret_store_hash(&files(&exp), locked).unwrap().as_ref()
+            );
+        }
+
+        // the first secret as request, the rest as receipts
+        let args = ["--secret".to_string(), exp[0].clone()]
+            .into_iter()
+            .chain(receipt_args(&receipts[1..]));
+        let opt = try_parse_check(args).unwrap();
+        assert!(matches!(
+            opt.secrets.0[..],
+            [
+                SecretInput::Request(_),
+                SecretInput::Receipt(_),
+                SecretInput::Receipt(_)
+            ]
+        ));
+        assert_eq!(
+            pv::misc::encode_hex(secret_store_hash(&opt.secrets.0, false).unwrap()),
+            HASH_DIR_ASSETS
+        );
+    }
+
+    #[test]
+    fn receipt_chain_broken() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let receipts = receipts(&dir);
+        let err_msg = |paths: &[&PathBuf]| {
+            let opt = try_parse_check(receipt_args(paths)).unwrap();
+            format!("{:#}", secret_store_hash(&opt.secrets.0, true).unwrap_err())
+        };
+
+        // missing, reordered, and duplicated receipts
+        for chain in [
+            [&receipts[0], &receipts[2]],
+            [&receipts[1], &receipts[0]],
+            [&receipts[0], &receipts[0]],
+        ] {
+            let err = err_msg(&chain);
+            assert!(
+                err.contains("The add-secret receipt does not continue the secret store"),
+                "{err}"
+            );
+        }
+
+        // unsuccessful add-secret request
+        let mut receipt: serde_json::Value =
+            serde_json::from_slice(&fs::read(&receipts[0]).unwrap()).unwrap();
+        receipt["uv_rc"] = 2.into();
+        fs::write(&receipts[0], serde_json::to_vec(&receipt).unwrap()).unwrap();
+        let err = err_msg(&[&receipts[0]]);
+        assert!(
+            err.ends_with("The Ultravisor did not add the secret"),
+            "{err}"
+        );
+
+        fs::write(&receipts[0], b"{}").unwrap();
+        assert!(err_msg(&[&receipts[0]]).starts_with("'"));
+    }
+
+    #[test]
+    fn tags_invalid() {
+        let parse = |tag: &str| {
+            try_parse_check(["--secret-tag".to_string(), tag.to_string()])
+                .unwrap_err()
+                .to_string()
+        };
+        let err = parse("0xzz");
+        assert!(
+            err.contains(
+                "'0xzz' is not a valid tag: Invalid hex string: Invalid character at position 2"
+            ),
+            "{err}"
+        );
+        let short = "00".repeat(15);
+        let err = parse(&short);
+        assert!(
+            err.contains(&format!(
+                "'{short}' is not a valid tag: Must be 16 bytes long, but is 15 bytes long"
+            )),
+            "{err}"
+        );
+        assert!(!parse(&"00".repeat(17)).is_empty());
+
+        // requires the secret store state
+        assert!(try_check_opt(["--secret-tag".to_string(), "00".repeat(16)]).is_err());
+    }
 }
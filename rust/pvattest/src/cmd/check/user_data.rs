@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::{bail, Context, Result};
+use pv::{
+    misc::{read_certs, read_file},
+    request::{
+        openssl::pkey::{PKey, Public},
+        verify_signature_digest, Digest, SignatureVerification,
+    },
+};
+use std::path::Path;
+
+use super::{bail_check, CheckState};
+use crate::{additional::AttestationResult, cli::CheckOpt};
+
+/// Name of the user-data check in the check result
+pub const USER_DATA_CHECK: &str = "user-data";
+/// Name of the user-data signature check in the check result
+pub const USER_DATA_SIGNATURE_CHECK: &str = "user-data-signature";
+
+/// Size of the signature-size field at the end of signed user-data
+const SIGN_SIZE_LEN: usize = 2;
+
+/// Split signed user-data into the signed payload and the signature
+///
+/// ```none
+/// LAYOUT
+/// |-------------------------|
+/// | payload                 |
+/// | signature               |
+/// | signature size (2) (BE) |
+/// |-------------------------|
+/// ```
+fn split_signed(user_data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let Some(data_len) = user_data.len().checked_sub(SIGN_SIZE_LEN) else {
+        bail!("The user-data is too small to contain a signature");
+    };
+    let (data, sign_size) = user_data.split_at(data_len);
+    let sign_size = u16::from_be_bytes([sign_size[0], sign_size[1]]) as usize;
+    match data_len.checked_sub(sign_size) {
+        Some(payload_len) if sign_size > 0 => Ok(data.split_at(payload_len)),
+        _ => bail!(
+            "The user-data contains an invalid signature size of {sign_size} bytes ({data_len} bytes available)"
+        ),
+    }
+}
+
+/// Read the public key to verify the user-data signature
+///
+/// The file contains either a X509 certificate or a public key in PEM or DER format.
+fn read_user_data_key(path: &Path) -> Result<PKey<Public>> {
+    let data = read_file(path, "user-data certificate")?;
+    if let Some(cert) = read_certs(&data).ok().and_then(|c| c.into_iter().next()) {
+        return Ok(cert.public_key()?);
+    }
+    PKey::public_key_from_pem(&data)
+        .or_else(|_| PKey::public_key_from_der(&data))
+        .with_context(|| {
+            format!(
+                "'{}' contains neither a X509 certificate nor a public key",
+                path.display()
+            )
+        })
+}
+
+/// Check if the user-data of the attestation response is signed by the key of --user-data-cert
+pub fn user_data_signature_check(
+    opt: &CheckOpt,
+    att_res: &AttestationResult,
+) -> Result<CheckState> {
+    let key = match &opt.user_data_cert {
+        Some(path) => read_user_data_key(path)?,
+        None => {
+            return Ok(CheckState::skipped(
+                USER_DATA_SIGNATURE_CHECK,
+                "No user-data certificate given",
+            ))
+        }
+    };
+    let user_data = match &att_res.user_data {
+        Some(user_data) => user_data,
+        None => bail_check!(
+            USER_DATA_SIGNATURE_CHECK,
+            "The attestation response contains no user-data"
+        ),
+    };
+    let (payload, sign) = match split_signed(user_data.as_ref()) {
+        Ok(split) => split,
+        Err(e) => bail_check!(USER_DATA_SIGNATURE_CHECK, e),
+    };
+
+    let state = match verify_signature_digest(&key, Digest::Sha512, payload, sign)? {
+        SignatureVerification::Verified => CheckState::passed(
+            USER_DATA_SIGNATURE_CHECK,
+            "The user-data signature is valid",
+        ),
+        SignatureVerification::Mismatch { reason } => CheckState::failed(
+            USER_DATA_SIGNATURE_CHECK,
+            format!("The user-data signature does not verify: {reason}"),
+        ),
+    };
+    Ok(state.actual(sign))
+}
+
+/// Check if the user-data matches with the user-data in the attestation response
+///
+/// If the user-data is signed, i.e., --user-data-cert is given, only the signed payload is
+/// compared.
+pub fn user_data_check(opt: &CheckOpt, att_res: &AttestationResult) -> Result<CheckState> {
+    let user_data = match &opt.user_data {
+        Some(file) => read_file(file, "user-data")?,
+        None => return Ok(CheckState::skipped(USER_DATA_CHECK, "No user-data given")),
+    };
+
+    let att = match &att_res.user_data {
+        Some(att) if opt.user_data_cert.is_some() => match split_signed(att.as_ref()) {
+            Ok((payload, _)) => Some(payload),
+            Err(e) => bail_check!(USER_DATA_CHECK, e),
+        },
+        att => att.as_ref().map(|att| att.as_ref()),
+    };
+    let state = match att {
+        Some(att) if att == user_data.as_slice() => {
+            CheckState::passed(USER_DATA_CHECK, "The user-data matches").actual(att)
+        }
+        Some(att) => CheckState::failed(
+            USER_DATA_CHECK,
+            "The provided user-data does not match the user-data from the attestation response",
+        )
+        .actual(att),
+        None => CheckState::failed(
+            USER_DATA_CHECK,
+            "The attestation response contains no user-data",
+        ),
+    };
+    Ok(state.expected(user_data))
+}
+
+#[cfg(test)]
+mod test {
+    use openssl::{
+        hash::MessageDigest,
+        pkey::{PKeyRef, Private},
+        rsa::{Padding, Rsa},
+        sign::Signer,
+    };
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::cmd::check::{
+        test::{att_res, check_opt},
+        CheckStatus,
+    };
+
+    const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/cert");
+    const KEY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/keys");
+    const PAYLOAD: &[u8] = b"nonce and policy";
+
+    fn private_key(path: &str) -> PKey<Private> {
+        PKey::private_key_from_pem(&std::fs::read(path).unwrap()).unwrap()
+    }
+
+    /// Sign `payload` and append the signature and its size
+    fn sign(key: &PKeyRef<Private>, payload: &[u8]) -> Vec<u8> {
+        let mut signer = Signer::new(MessageDigest::sha512(), key).unwrap();
+        if key.rsa().is_ok() {
+            signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        }
+        signer.update(payload).unwrap();
+        let sign = signer.sign_to_vec().unwrap();
+        let mut user_data = payload.to_vec();
+        user_data.extend_from_slice(&sign);
+        user_data.extend_from_slice(&(sign.len() as u16).to_be_bytes());
+        user_data
+    }
+
+    fn user_data_res(user_data: Option<&[u8]>) -> AttestationResult<'_> {
+        AttestationResult {
+            user_data: user_data.map(|u| u.into()),
+            ..att_res()
+        }
+    }
+
+    /// Check options with `--user-data-cert cert` and the expected user-data written to `dir`
+    fn user_data_opt(dir: &TemporaryDirectory, cert: &Path, expected: Option<&[u8]>) -> CheckOpt {
+        let mut args = vec!["--user-data-cert".into(), cert.as_os_str().to_owned()];
+        if let Some(expected) = expected {
+            let path = dir.path().join("expected");
+            std::fs::write(&path, expected).unwrap();
+            args.extend(["--user-data".into(), path.into_os_string()]);
+        }
+        check_opt(args)
+    }
+
+    fn statuses(opt: &CheckOpt, user_data: Option<&[u8]>) -> (CheckStatus, CheckStatus) {
+        let att_res = user_data_res(user_data);
+        (
+            user_data_signature_check(opt, &att_res).unwrap().status,
+            user_data_check(opt, &att_res).unwrap().status,
+        )
+    }
+
+    #[test]
+    fn signed_ec() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let user_data = sign(&private_key(&format!("{CERT_DIR}/host.key")), PAYLOAD);
+        let cert = Path::new(CERT_DIR).join("host.crt");
+        let opt = user_data_opt(&dir, &cert, Some(PAYLOAD));
+        assert_eq!(
+            statuses(&opt, Some(&user_data)),
+            (CheckStatus::Passed, CheckStatus::Passed)
+        );
+
+        // raw public key instead of a certificate
+        let key = private_key(&format!("{KEY_DIR}/ecsecp256k1.pem"));
+        let user_data = sign(&key, PAYLOAD);
+        let pub_key = dir.path().join("pub.pem");
+        std::fs::write(&pub_key, key.public_key_to_pem().unwrap()).unwrap();
+        let opt = user_data_opt(&dir, &pub_key, None);
+        assert_eq!(
+            statuses(&opt, Some(&user_data)),
+            (CheckStatus::Passed, CheckStatus::Skipped)
+        );
+    }
+
+    #[test]
+    fn signed_rsa() {
+        let dir = TemporaryDirectory::new().unwrap();
+        // the signature of larger keys does not fit into 256 bytes user-data
+        let key = PKey::from_rsa(Rsa::generate(1024).unwrap()).unwrap();
+        let user_data = sign(&key, PAYLOAD);
+        let pub_key = dir.path().join("pub.der");
+        std::fs::write(&pub_key, key.public_key_to_der().unwrap()).unwrap();
+        let opt = user_data_opt(&dir, &pub_key, Some(PAYLOAD));
+        assert_eq!(
+            statuses(&opt, Some(&user_data)),
+            (CheckStatus::Passed, CheckStatus::Passed)
+        );
+    }
+
+    #[test]
+    fn signed_tampered() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let mut user_data = sign(&private_key(&format!("{CERT_DIR}/host.key")), PAYLOAD);
+        user_data[0] ^= 1;
+        let opt = user_data_opt(&dir, &Path::new(CERT_DIR).join("host.crt"), Some(PAYLOAD));
+        assert_eq!(
+            statuses(&opt, Some(&user_data)),
+            (CheckStatus::Failed, CheckStatus::Failed)
+        );
+    }
+
+    #[test]
+    fn signed_wrong_key() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let user_data = sign(&private_key(&format!("{CERT_DIR}/host.key")), PAYLOAD);
+        let opt = user_data_opt(&dir, &Path::new(CERT_DIR).join("host2.crt"), Some(PAYLOAD));
+        let state = user_data_signature_check(&opt, &user_data_res(Some(&user_data))).unwrap();
+        assert_eq!(state.status, CheckStatus::Failed);
+        assert!(state
+            .message
+            .starts_with("The user-data signature does not verify"));
+        // the payload itself still matches
+        assert_eq!(
+            user_data_check(&opt, &user_data_res(Some(&user_data)))
+                .unwrap()
+                .status,
+            CheckStatus::Passed
+        );
+    }
+
+    #[test]
+    fn signed_missing() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let opt = user_data_opt(&dir, &Path::new(CERT_DIR).join("host.crt"), Some(PAYLOAD));
+        assert_eq!(
+            statuses(&opt, None),
+            (CheckStatus::Failed, CheckStatus::Failed)
+        );
+        // unsigned user-data
+        assert_eq!(
+            statuses(&opt, Some(PAYLOAD)),
+            (CheckStatus::Failed, CheckStatus::Failed)
+        );
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_signed(&[1, 2, 3, 0, 2]).unwrap(),
+            ([1].as_slice(), [2, 3].as_slice())
+        );
+        assert_eq!(
+            split_signed(&[2, 3, 0, 2]).unwrap(),
+            ([].as_slice(), [2, 3].as_slice())
+        );
+        assert!(split_signed(&[2]).is_err());
+        assert!(split_signed(&[1, 2, 0, 0]).is_err());
+        assert!(split_signed(&[1, 2, 0, 3]).is_err());
+    }
+
+    #[test]
+    fn user_data_key() {
+        assert!(read_user_data_key(&Path::new(CERT_DIR).join("host.crt")).is_ok());
+        let err = read_user_data_key(&Path::new(CERT_DIR).join("host.key")).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("neither a X509 certificate nor a public key"));
+    }
+}
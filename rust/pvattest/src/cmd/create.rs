@@ -4,16 +4,20 @@
 
 use crate::{
     cli::{AttAddFlags, CreateAttOpt},
+    cmd::{read_arpk, read_hex_or_file},
     exchange::{ExchangeFormatRequest, ExchangeFormatVersion},
 };
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use log::{debug, warn};
 use pv::{
-    attest::{AttestationFlags, AttestationMeasAlg, AttestationRequest, AttestationVersion},
-    misc::{create_file, write_file},
-    request::{ReqEncrCtx, Request, SymKey, SymKeyType},
+    attest::{
+        AttestationFlags, AttestationMeasAlg, AttestationRequest, AttestationVersion, ReplySizes,
+    },
+    misc::write_file,
+    request::{ReqEncrCtx, Request, Summary},
 };
 use std::process::ExitCode;
+use utils::{is_tty, open_output};
 
 fn flags(cli_flags: &[AttAddFlags]) -> AttestationFlags {
     let mut att_flags = AttestationFlags::default();
@@ -34,36 +38,279 @@ pub fn create(opt: &CreateAttOpt) -> Result<ExitCode> {
 
     let mut arcb = AttestationRequest::new(att_version, meas_alg, flags(&opt.add_data))?;
     debug!("Generated Attestation request");
+    if let Some(nonce) = &opt.nonce_in {
+        arcb.set_nonce(read_hex_or_file(nonce, "nonce")?);
+        debug!("Using the given nonce");
+    } else if opt.no_nonce {
+        arcb.remove_nonce();
+        debug!("Using no nonce");
+    }
 
-    // Add host-key documents
-    opt.certificate_args
-        .get_verified_hkds("attestation request")?
-        .into_iter()
-        .for_each(|k| arcb.add_hostkey(k));
-    debug!("Added all host-keys");
+    // Add host-key documents, one keyslot each, all sharing the same ARPK
+    let hkds = opt
+        .certificate_args
+        .get_verified_hkds("attestation request")?;
+    arcb.add_hostkeys(&hkds);
+    debug!("Added {} host-key(s)", hkds.len());
+    debug!("{}", arcb.summary());
 
+    let arpk = opt.arpk_in.as_deref().map(read_arpk).transpose()?;
     let encr_ctx =
-        ReqEncrCtx::random(SymKeyType::Aes256Gcm).context("Failed to generate random input")?;
+        ReqEncrCtx::new_aes_256(None, None, arpk).context("Failed to generate random input")?;
     let ser_arcb = arcb.encrypt(&encr_ctx)?;
     warn!("Successfully generated the request");
 
-    let mut output = create_file(&opt.output)?;
-    let exch_ctx = ExchangeFormatRequest::new(
-        ser_arcb,
-        meas_alg.exp_size(),
-        arcb.flags().expected_additional_size(),
-    )?;
+    if is_tty(&opt.output) {
+        warn!("Writing the binary attestation request to a terminal");
+    }
+    let mut output = open_output(&opt.output, opt.force)?;
+    // the sizes are stored in the exchange format, so that 'perform' allocates them
+    let sizes = ReplySizes::for_flags(arcb.flags(), meas_alg);
+    debug!("Using the reply sizes {sizes:?}");
+    // architected sizes, far below u32::MAX
+    let exch_ctx =
+        ExchangeFormatRequest::new(ser_arcb, sizes.measurement as u32, sizes.additional as u32)?;
     exch_ctx.write(&mut output, ExchangeFormatVersion::One)?;
 
-    let arpk = match encr_ctx.prot_key() {
-        SymKey::Aes256(k) => k,
-        _ => bail!("Unexpected key type"),
-    };
-    write_file(
-        &opt.arpk,
-        arpk.value(),
-        "Attestation request Protection Key",
-    )?;
+    // the key of --arpk-in is never written
+    if let Some(path) = &opt.arpk {
+        let arpk = encr_ctx.prot_key().expect_gcm()?;
+        write_file(path, arpk.value(), "Attestation request Protection Key")?;
+    }
 
     Ok(ExitCode::SUCCESS)
 }
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use pv::{
+        misc::open_file,
+        request::{openssl::pkey::PKey, SymKey},
+    };
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::cli::{CliOptions, Command, NONCE_SIZE};
+
+    const HKD: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/assets/simulate/host.crt"
+    );
+    const ARPK: [u8; 32] = [0x42; 32];
+
+    const NONCE: [u8; NONCE_SIZE] = [0x17; NONCE_SIZE];
+    const NONCE_HEX: &str = "0x17171717171717171717171717171717";
+
+    fn create_opt(args: &[&str]) -> Result<CreateAttOpt, clap::Error> {
+        let args = ["pvattest", "create", "-k", HKD, "--no-verify"]
+            .iter()
+            .chain(args);
+        match CliOptions::try_parse_from(args)?.cmd {
+            Command::Create(opt) => Ok(*opt),
+            cmd => panic!("Unexpected command {cmd:?}"),
+        }
+    }
+
+    #[test]
+    fn arpk_in() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        std::fs::write(&arpk, ARPK).unwrap();
+        let opt = create_opt(&[
+            "-o",
+            req.to_str().unwrap(),
+            "--arpk-in",
+            arpk.to_str().unwrap(),
+        ])
+        .unwrap();
+        create(&opt).unwrap();
+
+        // the request is protected by the given key, which stays untouched
+        let exchange = ExchangeFormatRequest::read(&mut open_file(&req).unwrap()).unwrap();
+        let key = SymKey::Aes256(ARPK.into());
+        assert!(AttestationRequest::decrypt_bin(&exchange.arcb, &key).is_ok());
+        let other = SymKey::Aes256([0x17; 32].into());
+        assert!(AttestationRequest::decrypt_bin(&exchange.arcb, &other).is_err());
+        assert_eq!(std::fs::read(&arpk).unwrap(), ARPK);
+    }
+
+    #[test]
+    fn multiple_hkds() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        let opt = create_opt(&[
+            "-k",
+            HKD,
+            "-k",
+            HKD,
+            "-o",
+            req.to_str().unwrap(),
+            "-a",
+            arpk.to_str().unwrap(),
+        ])
+        .unwrap();
+        create(&opt).unwrap();
+
+        let exchange = ExchangeFormatRequest::read(&mut open_file(&req).unwrap()).unwrap();
+        // number of keyslots in the request header
+        assert_eq!(exchange.arcb[0x27], 3);
+        let host_key = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/assets/simulate/host.key"
+        );
+        let host_key = PKey::private_key_from_pem(&std::fs::read(host_key).unwrap()).unwrap();
+        let key = AttestationRequest::unwrap_arpk_bin(&exchange.arcb, &host_key).unwrap();
+        assert_eq!(key.value(), std::fs::read(&arpk).unwrap());
+    }
+
+    #[test]
+    fn reply_sizes() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        let read_sizes = |add_data: &str| {
+            let mut args = vec![
+                "-o",
+                req.to_str().unwrap(),
+                "-a",
+                arpk.to_str().unwrap(),
+                "--force",
+            ];
+            if !add_data.is_empty() {
+                args.extend(["--add-data", add_data]);
+            }
+            create(&create_opt(&args).unwrap()).unwrap();
+            let exchange = ExchangeFormatRequest::read(&mut open_file(&req).unwrap()).unwrap();
+            (exchange.exp_measurement, exchange.exp_additional)
+        };
+
+        assert_eq!(read_sizes(""), (64, 0));
+        assert_eq!(read_sizes("phkh-img,firmware-state"), (64, 0x160));
+        assert_eq!(
+            read_sizes("phkh-img,phkh-att,secret-store-hash,firmware-state"),
+            (64, 0x1c0)
+        );
+    }
+
+    #[test]
+    fn arpk_in_invalid() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        let args = [
+            "-o",
+            req.to_str().unwrap(),
+            "--arpk-in",
+            arpk.to_str().unwrap(),
+        ];
+
+        let err = create(&create_opt(&args).unwrap()).unwrap_err().to_string();
+        let prefix = format!(
+            "Cannot read the attestation request protection key '{}': ",
+            arpk.display()
+        );
+        assert!(err.starts_with(&prefix), "{err}");
+
+        std::fs::write(&arpk, [0x42; 16]).unwrap();
+        let err = create(&create_opt(&args).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Invalid attestation request protection key '{}': Attestation request protection key must be exactly 32 bytes long",
+                arpk.display()
+            )
+        );
+        assert!(!req.exists());
+    }
+
+    #[test]
+    fn output_force() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        std::fs::write(&req, "existing").unwrap();
+        let args = ["-o", req.to_str().unwrap(), "-a", arpk.to_str().unwrap()];
+
+        let err = create(&create_opt(&args).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert_eq!(std::fs::read(&req).unwrap(), b"existing");
+
+        create(&create_opt(&[args.as_slice(), &["--force"]].concat()).unwrap()).unwrap();
+        ExchangeFormatRequest::read(&mut open_file(&req).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn arpk_conflict() {
+        let err = create_opt(&["-o", "req", "--arpk-in", "in", "--arpk", "out"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        let err = create_opt(&["-o", "req"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    }
+
+    /// Create a request with `args` and return the decrypted nonce
+    fn create_nonce(dir: &TemporaryDirectory, args: &[&str]) -> Result<Option<[u8; NONCE_SIZE]>> {
+        let (req, arpk) = (dir.path().join("req"), dir.path().join("arpk"));
+        let mut args = args.to_vec();
+        args.extend([
+            "-o",
+            req.to_str().unwrap(),
+            "-a",
+            arpk.to_str().unwrap(),
+            "--force",
+        ]);
+        create(&create_opt(&args).unwrap())?;
+
+        let exchange = ExchangeFormatRequest::read(&mut open_file(&req).unwrap()).unwrap();
+        let key: [u8; 32] = std::fs::read(&arpk).unwrap().try_into().unwrap();
+        let key = SymKey::Aes256(key.into());
+        let (auth, conf) = AttestationRequest::decrypt_bin(&exchange.arcb, &key).unwrap();
+        assert_eq!(auth.flags().nonce(), conf.nonce().is_some());
+        Ok(conf.nonce().as_ref().map(|n| *n.value()))
+    }
+
+    #[test]
+    fn nonce_in() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let raw = dir.path().join("raw");
+        std::fs::write(&raw, NONCE).unwrap();
+        let hex = dir.path().join("hex");
+        std::fs::write(&hex, format!("{NONCE_HEX}\n")).unwrap();
+
+        for nonce in [NONCE_HEX, raw.to_str().unwrap(), hex.to_str().unwrap()] {
+            let res = create_nonce(&dir, &["--nonce-in", nonce]).unwrap();
+            assert_eq!(res, Some(NONCE));
+        }
+        // random nonce by default
+        let res = create_nonce(&dir, &[]).unwrap();
+        assert!(res.is_some_and(|n| n != NONCE));
+        assert_eq!(create_nonce(&dir, &["--no-nonce"]).unwrap(), None);
+    }
+
+    #[test]
+    fn nonce_in_invalid() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let short = dir.path().join("short");
+        std::fs::write(&short, "0x1717").unwrap();
+        let err = create_nonce(&dir, &["--nonce-in", short.to_str().unwrap()]).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("Must contain 16 bytes, but contains 2 bytes"));
+        assert!(!dir.path().join("req").exists());
+
+        let err = create_opt(&["-o", "req", "-a", "arpk", "--nonce-in", "0x1717"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn nonce_conflict() {
+        let args = [
+            "-o",
+            "req",
+            "-a",
+            "arpk",
+            "--nonce-in",
+            NONCE_HEX,
+            "--no-nonce",
+        ];
+        let err = create_opt(&args).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+}
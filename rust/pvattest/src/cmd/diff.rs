@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+use pv::{
+    attest::{diff as diff_responses, AttestationFlags, AttestationRequest, DecryptedAttestation},
+    misc::open_file,
+};
+use std::process::ExitCode;
+use utils::open_output;
+
+use crate::{
+    cli::DiffOpt,
+    cmd::{read_arpk, write_output},
+    exchange::ExchangeFormatResponse,
+};
+
+/// Read an attestation response and decrypt the flags of its request
+fn read_response(path: &str, arpk: &str) -> Result<(ExchangeFormatResponse, AttestationFlags)> {
+    let arpk = read_arpk(arpk)?;
+    let exchange = ExchangeFormatResponse::read(&mut open_file(path)?)?;
+    let (auth, _) = AttestationRequest::decrypt_bin(exchange.arcb(), &arpk)
+        .with_context(|| format!("Cannot decrypt the attestation request in '{path}'"))?;
+    let flags = *auth.flags();
+    Ok((exchange, flags))
+}
+
+pub fn diff(opt: &DiffOpt) -> Result<ExitCode> {
+    let (arpk_a, arpk_b) = match opt.arpk.as_slice() {
+        [arpk] => (arpk, arpk),
+        [arpk_a, arpk_b] => (arpk_a, arpk_b),
+        _ => bail!("Specify at most two attestation request protection keys"),
+    };
+    let output = opt
+        .output
+        .as_ref()
+        .map(|o| open_output(o, opt.force))
+        .transpose()?;
+
+    let (resp_a, flags_a) = read_response(&opt.first, arpk_a)?;
+    let (resp_b, flags_b) = read_response(&opt.second, arpk_b)?;
+    let att_a = DecryptedAttestation::new(
+        resp_a.config_uid(),
+        resp_a.user(),
+        resp_a.additional(),
+        &flags_a,
+    )?;
+    let att_b = DecryptedAttestation::new(
+        resp_b.config_uid(),
+        resp_b.user(),
+        resp_b.additional(),
+        &flags_b,
+    )?;
+
+    let diff = diff_responses(&att_a, &att_b);
+    warn!("{diff}");
+    if let Some(mut output) = output {
+        write_output(&mut output, opt.format, &diff)?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -5,13 +5,37 @@
 use crate::{
     cli::PerformAttOptComb,
     exchange::{ExchangeFormatRequest, ExchangeFormatResponse, ExchangeFormatVersion},
+    remote,
+    transport::{self, transport, UvTransport},
 };
 use anyhow::Result;
-use pv::{
-    misc::{create_file, open_file, read_file},
-    uv::{AttestationCmd, UvDevice},
-};
-use std::process::ExitCode;
+use log::{info, warn};
+use pv::misc::{open_file, read_file};
+use std::{io::Cursor, process::ExitCode};
+use utils::{is_tty, open_output};
+
+/// Send the attestation request `ex_in` with `user_data` to the Ultravisor `uv`
+pub fn perform_request(
+    uv: &dyn UvTransport,
+    ex_in: ExchangeFormatRequest,
+    user_data: Option<Vec<u8>>,
+) -> Result<ExchangeFormatResponse> {
+    let response = transport::attest(
+        uv,
+        &ex_in.arcb,
+        user_data.clone(),
+        ex_in.exp_measurement,
+        ex_in.exp_additional,
+    )?;
+
+    ExchangeFormatResponse::new(
+        ex_in.arcb,
+        response.measurement,
+        response.additional,
+        user_data,
+        response.cuid,
+    )
+}
 
 pub fn perform<'a, P>(opt: P) -> Result<ExitCode>
 where
@@ -19,8 +43,10 @@ where
 {
     let opt = opt.into();
     let mut input = open_file(opt.input)?;
-    let mut output = create_file(opt.output)?;
-    let uvdevice = UvDevice::open()?;
+    if is_tty(opt.output) {
+        warn!("Writing the binary attestation response to a terminal");
+    }
+    let mut output = open_output(opt.output, opt.force)?;
 
     let ex_in = ExchangeFormatRequest::read(&mut input)?;
     let user_data = opt
@@ -28,27 +54,284 @@ where
         .map(|u| read_file(u, "user-data"))
         .transpose()?;
 
-    let mut cmd = AttestationCmd::new_request(
-        ex_in.arcb.clone().into(),
-        user_data.clone(),
-        ex_in.exp_measurement,
-        ex_in.exp_additional,
-    )?;
+    let ex_out = match opt.remote {
+        Some(addr) => {
+            let mut request = Vec::new();
+            ex_in.write(&mut request, ExchangeFormatVersion::One)?;
+            let response = remote::attest(addr, request, user_data)?;
+            info!("Performed the attestation at '{addr}'");
+            ExchangeFormatResponse::read(&mut Cursor::new(response))?
+        }
+        None => perform_request(transport(opt.backend)?.as_ref(), ex_in, user_data)?,
+    };
+    ex_out.write(&mut output, ExchangeFormatVersion::One)?;
 
-    uvdevice.send_cmd(&mut cmd)?;
+    Ok(ExitCode::SUCCESS)
+}
 
-    let measurement = cmd.measurement();
-    let additional = cmd.additional_owned();
-    let cuid = cmd.cuid();
+/// Tests of the perform flow with a mocked uvdevice, they run on any architecture
+#[cfg(test)]
+mod mock_test {
+    use super::*;
+    use pv::uv::{MockCmd, MockResponse, MockUvDevice};
 
-    let ex_out = ExchangeFormatResponse::new(
-        ex_in.arcb,
-        measurement.to_owned(),
-        additional,
-        user_data,
-        cuid.to_owned(),
-    )?;
-    ex_out.write(&mut output, ExchangeFormatVersion::One)?;
+    #[test]
+    fn perform_mock() {
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::Attestation {
+            measurement: vec![0x11; 64],
+            additional: Some(vec![0x22; 0x20]),
+            cuid: [0x33; 16],
+        });
+        let ex_in = ExchangeFormatRequest::new(b"arcb".to_vec(), 64, 0x40).unwrap();
+        let ex_out = perform_request(&uv, ex_in, Some(b"user".to_vec())).unwrap();
 
-    Ok(ExitCode::SUCCESS)
+        assert_eq!(ex_out.arcb(), b"arcb");
+        assert_eq!(ex_out.measurement(), [0x11; 64]);
+        assert_eq!(ex_out.additional(), Some([0x22; 0x20].as_slice()));
+        assert_eq!(ex_out.user(), Some(b"user".as_slice()));
+        assert_eq!(ex_out.config_uid(), &[0x33; 16]);
+        assert_eq!(
+            uv.issued(),
+            [MockCmd::Attestation {
+                arcb: b"arcb".to_vec(),
+                user_data: b"user".to_vec()
+            }]
+        );
+    }
+
+    #[test]
+    fn perform_mock_failure() {
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::Error { rc: 0x010c, rrc: 0 });
+        let ex_in = ExchangeFormatRequest::new(b"arcb".to_vec(), 64, 0).unwrap();
+        let err = perform_request(&uv, ex_in, None).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Unable to decrypt attestation request control block"),
+            "{err}"
+        );
+        assert_eq!(uv.issued().len(), 1);
+    }
+}
+
+#[cfg(all(test, feature = "simulate"))]
+mod test {
+    use clap::Parser;
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::{
+        cli::{CliOptions, Command},
+        cmd::{create, verify, CheckFailed},
+    };
+
+    const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/simulate/");
+
+    fn run(args: &[&str]) -> Result<ExitCode> {
+        let args = ["pvattest"].iter().chain(args);
+        match CliOptions::try_parse_from(args)?.cmd {
+            Command::Create(opt) => create(&opt),
+            Command::Perform(opt) => perform(&opt),
+            Command::Verify(opt) => verify(&opt),
+            cmd => panic!("Unexpected command {cmd:?}"),
+        }
+    }
+
+    fn path(dir: &TemporaryDirectory, name: &str) -> String {
+        dir.path().join(name).to_str().unwrap().to_owned()
+    }
+
+    fn pipeline(dir: &TemporaryDirectory, add_data: &str, user_data: Option<&str>) -> Result<()> {
+        let (req, resp, arpk) = (path(dir, "req"), path(dir, "resp"), path(dir, "arpk"));
+        let hkd = format!("{ASSETS}host.crt");
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+
+        let mut create_args = vec!["create", "-k", &hkd, "--no-verify", "-o", &req, "-a", &arpk];
+        if !add_data.is_empty() {
+            create_args.extend(["--add-data", add_data]);
+        }
+        run(&create_args)?;
+
+        let mut perform_args = vec!["perform", "--simulate", &hdr, &req, &resp];
+        if let Some(user_data) = user_data {
+            perform_args.extend(["--user-data", user_data]);
+        }
+        run(&perform_args)?;
+        Ok(())
+    }
+
+    fn try_verify_resp(dir: &TemporaryDirectory) -> Result<ExitCode> {
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+        let (resp, arpk) = (path(dir, "resp"), path(dir, "arpk"));
+        run(&["verify", "-i", &resp, "-a", &arpk, "--hdr", &hdr])
+    }
+
+    fn verify_resp(dir: &TemporaryDirectory) -> ExitCode {
+        try_verify_resp(dir).unwrap()
+    }
+
+    #[test]
+    fn simulate_min() {
+        let dir = TemporaryDirectory::new().unwrap();
+        pipeline(&dir, "", None).unwrap();
+        assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn simulate_full() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let user_data = path(&dir, "user");
+        std::fs::write(&user_data, b"simulated user-data").unwrap();
+        pipeline(
+            &dir,
+            "phkh-img,phkh-att,secret-store-hash,firmware-state",
+            Some(&user_data),
+        )
+        .unwrap();
+        assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn simulate_tampered() {
+        let dir = TemporaryDirectory::new().unwrap();
+        pipeline(&dir, "phkh-img", None).unwrap();
+
+        // flip a bit of the configuration UID at the very end of the response
+        let resp = path(&dir, "resp");
+        let mut data = std::fs::read(&resp).unwrap();
+        *data.last_mut().unwrap() ^= 1;
+        std::fs::write(&resp, data).unwrap();
+
+        assert!(try_verify_resp(&dir).unwrap_err().is::<CheckFailed>());
+
+        // a different protection key does not decrypt the request
+        std::fs::write(path(&dir, "arpk"), [0x42; 32]).unwrap();
+        assert!(try_verify_resp(&dir).unwrap_err().is::<CheckFailed>());
+    }
+
+    #[test]
+    fn simulate_arpk_in() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, resp, arpk) = (path(&dir, "req"), path(&dir, "resp"), path(&dir, "arpk"));
+        let hkd = format!("{ASSETS}host.crt");
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+        std::fs::write(&arpk, [0x42; 32]).unwrap();
+
+        // two requests for the same pre-provisioned key
+        for _ in 0..2 {
+            run(&[
+                "create",
+                "-k",
+                &hkd,
+                "--no-verify",
+                "-o",
+                &req,
+                "--arpk-in",
+                &arpk,
+                "--force",
+            ])
+            .unwrap();
+            run(&["perform", "--force", "--simulate", &hdr, &req, &resp]).unwrap();
+            assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+        }
+        assert_eq!(std::fs::read(&arpk).unwrap(), [0x42; 32]);
+    }
+
+    #[test]
+    fn simulate_nonce() {
+        use pv::{
+            attest::{AttestationItems, AttestationMeasurement, AttestationRequest},
+            request::{openssl::pkey::PKey, BootHdrTags, SymKey},
+        };
+
+        const NONCE: [u8; 16] = [0x17; 16];
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, resp, arpk) = (path(&dir, "req"), path(&dir, "resp"), path(&dir, "arpk"));
+        let hkd = format!("{ASSETS}host.crt");
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+        let nonce = format!("0x{}", "17".repeat(16));
+        let create_args = ["create", "-k", &hkd, "--no-verify", "-o", &req, "-a", &arpk];
+
+        run(&[create_args.as_slice(), &["--nonce-in", &nonce]].concat()).unwrap();
+        run(&["perform", "--simulate", &hdr, &req, &resp]).unwrap();
+        assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+
+        // the measurement covers exactly the given nonce
+        let exchange = ExchangeFormatResponse::read(&mut open_file(&resp).unwrap()).unwrap();
+        let key: [u8; 32] = std::fs::read(&arpk).unwrap().try_into().unwrap();
+        let (auth, conf) =
+            AttestationRequest::decrypt_bin(exchange.arcb(), &SymKey::Aes256(key.into())).unwrap();
+        assert_eq!(conf.nonce().as_ref().unwrap().value(), &NONCE);
+        let tags = BootHdrTags::from_se_image(&mut open_file(&hdr).unwrap()).unwrap();
+        let meas_key = PKey::hmac(conf.measurement_key()).unwrap();
+        let measure = |nonce: Option<&[u8; 16]>| {
+            let items = AttestationItems::new(&tags, exchange.config_uid(), None, nonce, None);
+            AttestationMeasurement::calculate(items, auth.mai(), &meas_key).unwrap()
+        };
+        assert!(measure(Some(&NONCE)).eq_secure(exchange.measurement()));
+        assert!(!measure(Some(&[0x18; 16])).eq_secure(exchange.measurement()));
+        assert!(!measure(None).eq_secure(exchange.measurement()));
+
+        // overwrite the request and response of the first run
+        run(&[create_args.as_slice(), &["--no-nonce", "--force"]].concat()).unwrap();
+        run(&["perform", "--force", "--simulate", &hdr, &req, &resp]).unwrap();
+        assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn simulate_remote() {
+        use crate::{
+            cli::MAX_FRAME_SIZE,
+            remote::{serve_connections, Listener},
+            transport::SimulatedUv,
+        };
+        use pv::request::BootHdrTags;
+        use std::{net::TcpListener, sync::Arc};
+
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, resp, arpk) = (path(&dir, "req"), path(&dir, "resp"), path(&dir, "arpk"));
+        let hkd = format!("{ASSETS}host.crt");
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+        let user_data = path(&dir, "user");
+        std::fs::write(&user_data, b"remote user-data").unwrap();
+
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap().to_string();
+        let tags = BootHdrTags::from_se_image(&mut open_file(&hdr).unwrap()).unwrap();
+        let uv = Arc::new(SimulatedUv::new(tags).unwrap());
+        std::thread::spawn(move || serve_connections(&Listener::Tcp(tcp), uv, 1, MAX_FRAME_SIZE));
+
+        run(&["create", "-k", &hkd, "--no-verify", "-o", &req, "-a", &arpk]).unwrap();
+        let args = ["perform", "--remote", &addr, &req, &resp, "-u", &user_data];
+        run(&args).unwrap();
+        assert_eq!(verify_resp(&dir), ExitCode::SUCCESS);
+
+        // --remote conflicts with the local Ultravisor
+        let args = [
+            "perform",
+            "--remote",
+            &addr,
+            "--simulate",
+            &hdr,
+            &req,
+            &resp,
+        ];
+        assert!(run(&args).is_err());
+    }
+
+    #[test]
+    fn simulate_wrong_hostkey() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (req, resp, arpk) = (path(&dir, "req"), path(&dir, "resp"), path(&dir, "arpk"));
+        let hkd = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/host.pem.crt");
+        let hdr = format!("{ASSETS}secure_guest.hdr");
+
+        run(&["create", "-k", hkd, "--no-verify", "-o", &req, "-a", &arpk]).unwrap();
+        let err = run(&["perform", "--simulate", &hdr, &req, &resp]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(pv::Error::NoMatchingKeyslot)
+        ));
+    }
 }
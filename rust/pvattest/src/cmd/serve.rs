@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::Result;
+use log::info;
+use std::process::ExitCode;
+
+use crate::{
+    cli::ServeOpt,
+    remote::{serve_connections, Listener},
+    transport::transport,
+};
+
+pub fn serve(opt: &ServeOpt) -> Result<ExitCode> {
+    let uv = transport(&opt.backend)?;
+    let listener = Listener::bind(&opt.listen)?;
+    info!("Listening for attestation requests at '{}'", opt.listen);
+    // Panic: clap limits the request size to MAX_FRAME_SIZE
+    let max_request_size = opt.max_request_size.try_into().unwrap();
+    serve_connections(
+        &listener,
+        uv.into(),
+        opt.max_connections.into(),
+        max_request_size,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -2,34 +2,56 @@
 //
 // Copyright IBM Corp. 2024
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::{debug, warn};
 use pv::{
     attest::{AttestationItems, AttestationMeasurement, AttestationRequest},
-    misc::{create_file, open_file, read_exact_file, write_file},
-    request::{openssl::pkey::PKey, BootHdrTags, Confidential, SymKey},
+    misc::{open_file, write_file},
+    request::{openssl::pkey::PKey, BootHdrTags},
 };
-use std::process::ExitCode;
-use utils::HexSlice;
+use std::{io::Write, process::ExitCode};
+use utils::{open_output, HexSlice};
 
 use crate::{
     additional::AttestationResult,
     cli::{OutputType, VerifyOpt},
-    exchange::ExchangeFormatResponse,
-    EXIT_CODE_ATTESTATION_FAIL,
+    cmd::{read_arpk, write_output, CheckFailed},
+    response::read_response,
 };
 
+/// Write the decoded additional-data of `res` in `format` to `output`
+fn print_additional<W: Write>(
+    output: &mut W,
+    format: OutputType,
+    res: &AttestationResult,
+) -> Result<()> {
+    match &res.add_fields {
+        Some(add) => write_output(output, format, add),
+        None => {
+            warn!("The response contains no additional-data");
+            Ok(())
+        }
+    }
+}
+
 pub fn verify(opt: &VerifyOpt) -> Result<ExitCode> {
-    let mut input = open_file(&opt.input)?;
     let mut img = open_file(&opt.hdr)?;
-    let output = opt.output.as_ref().map(create_file).transpose()?;
-    let arpk = SymKey::Aes256(
-        read_exact_file(&opt.arpk, "Attestation request protection key").map(Confidential::new)?,
-    );
+    let output = opt
+        .output
+        .as_ref()
+        .map(|o| open_output(o, opt.force))
+        .transpose()?;
+    let arpk = read_arpk(&opt.arpk)?;
     let tags = BootHdrTags::from_se_image(&mut img)?;
-    let exchange = ExchangeFormatResponse::read(&mut input)?;
+    let exchange = read_response(&opt.input, &opt.input_opt)?;
 
-    let (auth, conf) = AttestationRequest::decrypt_bin(exchange.arcb(), &arpk)?;
+    let (auth, conf) = match AttestationRequest::decrypt_bin(exchange.arcb(), &arpk) {
+        Err(pv::Error::GcmTagMismatch) => bail!(CheckFailed::new(
+            "Attestation request verification failed. The request was not protected by the given \
+             protection key or was modified."
+        )),
+        res => res?,
+    };
     let meas_key = PKey::hmac(conf.measurement_key())?;
     let items = AttestationItems::new(
         &tags,
@@ -46,8 +68,10 @@ pub fn verify(opt: &VerifyOpt) -> Result<ExitCode> {
         debug!("Measurement values:");
         debug!("Recieved: {}", HexSlice::from(uv_meas));
         debug!("Calculated: {}", HexSlice::from(measurement.as_ref()));
-        warn!("Attestation measurement verification failed. Calculated and received attestation measurement are not equal.");
-        return Ok(ExitCode::from(EXIT_CODE_ATTESTATION_FAIL));
+        bail!(CheckFailed::new(
+            "Attestation measurement verification failed. Calculated and received attestation \
+             measurement are not equal."
+        ));
     }
     warn!("Attestation measurement verified");
     // Error impossible CUID is present Attestation verified
@@ -55,9 +79,11 @@ pub fn verify(opt: &VerifyOpt) -> Result<ExitCode> {
 
     warn!("{pr_data}");
     if let Some(mut output) = output {
-        match opt.format {
-            OutputType::Yaml => serde_yaml::to_writer(&mut output, &pr_data)?,
-        };
+        write_output(&mut output, opt.format, &pr_data)?;
+    }
+
+    if let Some(format) = opt.print_additional {
+        print_additional(&mut std::io::stdout(), format, &pr_data)?;
     }
 
     if let Some(user_data) = &opt.user_data {
@@ -71,3 +97,49 @@ pub fn verify(opt: &VerifyOpt) -> Result<ExitCode> {
 
     Ok(ExitCode::SUCCESS)
 }
+
+#[cfg(test)]
+mod test {
+    use pv::attest::{AdditionalData, AttestationFlags};
+
+    use super::*;
+
+    #[test]
+    fn print_additional_formats() {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        let data = [[1; 0x20].as_slice(), &[2; 4]].concat();
+        let res = AttestationResult {
+            cuid: (&[0; 16]).into(),
+            add: Some(data.as_slice().into()),
+            add_fields: Some(AdditionalData::from_slice_sized(&data, &flags).unwrap()),
+            user_data: None,
+        };
+
+        let mut json = Vec::new();
+        print_additional(&mut json, OutputType::Json, &res).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "image_phkh": format!("0x{}", "01".repeat(0x20)),
+                "unrecognized": "0x02020202",
+            })
+        );
+
+        let mut human = Vec::new();
+        print_additional(&mut human, OutputType::Human, &res).unwrap();
+        let human = String::from_utf8(human).unwrap();
+        assert!(human.starts_with("Image PHKH\n"));
+        assert!(human.contains("Unrecognized\n"));
+
+        let mut empty = Vec::new();
+        let res = AttestationResult {
+            add: None,
+            add_fields: None,
+            ..res
+        };
+        print_additional(&mut empty, OutputType::Yaml, &res).unwrap();
+        assert!(empty.is_empty());
+    }
+}
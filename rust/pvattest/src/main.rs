@@ -7,6 +7,9 @@ mod additional;
 mod cli;
 mod cmd;
 mod exchange;
+mod remote;
+mod response;
+mod transport;
 
 use clap::{CommandFactory, Parser};
 use cli::{CliOptions, Command};
@@ -14,7 +17,7 @@ use log::trace;
 use std::process::ExitCode;
 use utils::{print_cli_error, print_error, print_version, PvLogger};
 
-use crate::cmd::{check, create, perform, verify, CMD_FN, UV_CMD_FN};
+use crate::cmd::{check, create, diff, perform, serve, verify, CheckFailed, CMD_FN, UV_CMD_FN};
 
 static LOGGER: PvLogger = PvLogger;
 const FEATURES: &[&[&str]] = &[CMD_FN, UV_CMD_FN];
@@ -41,15 +44,21 @@ fn main() -> ExitCode {
     let res = match &cli.cmd {
         Command::Create(opt) => create(opt),
         Command::Perform(opt) => perform(opt),
+        Command::Serve(opt) => serve(opt),
         Command::Verify(opt) => verify(opt),
         Command::Version => {
             print_version!("2024", log_level; FEATURES.concat());
             Ok(ExitCode::SUCCESS)
         }
         Command::Check(opt) => check(opt),
+        Command::Diff(opt) => diff(opt),
     };
     match res {
         Ok(c) => c,
+        Err(e) if e.is::<CheckFailed>() => {
+            print_error(&e, log_level);
+            EXIT_CODE_ATTESTATION_FAIL.into()
+        }
         Err(e) => print_error(&e, log_level),
     }
 }
@@ -0,0 +1,670 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+//! Remote attestation protocol between 'pvattest perform --remote' and 'pvattest serve'
+//!
+//! The client sends one request frame per connection. The server answers with either a response
+//! frame or an error frame and closes the connection.
+//!
+//! ```none
+//! FRAME
+//! |-----------------------|
+//! | magic (8)             |
+//! | version (2) (BE)      |
+//! | kind (1)              |
+//! | reserved (1)          |
+//! | payload size (4) (BE) |
+//! | payload               |
+//! |-----------------------|
+//!
+//! REQUEST PAYLOAD
+//! |------------------------|
+//! | flags (1)              |
+//! | reserved (1)           |
+//! | user-data size (2)(BE) |
+//! | user-data              |
+//! | exchange format req    |
+//! |------------------------|
+//! ```
+//!
+//! The payload of a response frame is the exchange format response, the payload of an error frame
+//! an UTF-8 encoded message.
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, warn};
+use pv::assert_size;
+use std::{
+    fs::File,
+    io::{Cursor, Read, Write},
+    mem::size_of,
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+use zerocopy::{AsBytes, BigEndian, FromBytes, FromZeroes, U16, U32};
+
+use crate::{
+    cli::{RemoteAddr, MAX_FRAME_SIZE},
+    cmd::perform::perform_request,
+    exchange::{ExchangeFormatRequest, ExchangeFormatVersion},
+    transport::UvTransport,
+};
+
+const FRAME_MAGIC: [u8; 8] = *b"pvattrmt";
+const FRAME_VERSION: u16 = 1;
+/// Time after which a stalled connection is closed
+const TIMEOUT: Duration = Duration::from_secs(30);
+const BACKLOG: libc::c_int = 128;
+
+/// The request contains user-data
+const REQ_FLAG_USER_DATA: u8 = 0x80;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Request = 1,
+    Response = 2,
+    Error = 3,
+}
+
+impl TryFrom<u8> for FrameKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(Self::Request),
+            2 => Ok(Self::Response),
+            3 => Ok(Self::Error),
+            kind => bail!("Invalid remote attestation frame: Unknown kind {kind}"),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+struct FrameHdr {
+    magic: [u8; 8],
+    version: U16<BigEndian>,
+    kind: u8,
+    reserved: u8,
+    size: U32<BigEndian>,
+}
+assert_size!(FrameHdr, 16);
+
+#[repr(C)]
+#[derive(Debug, AsBytes, FromZeroes, FromBytes)]
+struct RequestHdr {
+    flags: u8,
+    reserved: u8,
+    user_data_size: U16<BigEndian>,
+}
+assert_size!(RequestHdr, 4);
+
+fn write_frame<W: Write + ?Sized>(writer: &mut W, kind: FrameKind, payload: &[u8]) -> Result<()> {
+    let size = match u32::try_from(payload.len()) {
+        Ok(size) if size <= MAX_FRAME_SIZE => size,
+        _ => bail!(
+            "The remote attestation frame is too large: {} bytes (max {MAX_FRAME_SIZE})",
+            payload.len()
+        ),
+    };
+    let hdr = FrameHdr {
+        magic: FRAME_MAGIC,
+        version: FRAME_VERSION.into(),
+        kind: kind as u8,
+        reserved: 0,
+        size: size.into(),
+    };
+    writer.write_all(hdr.as_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a frame and reject payloads larger than `max_size` before reading them
+fn read_frame<R: Read + ?Sized>(reader: &mut R, max_size: u32) -> Result<(FrameKind, Vec<u8>)> {
+    let mut hdr = FrameHdr::new_zeroed();
+    reader
+        .read_exact(hdr.as_bytes_mut())
+        .context("Cannot read the remote attestation frame")?;
+    if hdr.magic != FRAME_MAGIC {
+        bail!("Invalid remote attestation frame: Invalid magic");
+    }
+    if hdr.version.get() != FRAME_VERSION {
+        bail!(
+            "Unsupported remote attestation protocol version {} (supported: {FRAME_VERSION})",
+            hdr.version.get()
+        );
+    }
+    let kind = hdr.kind.try_into()?;
+    let size = hdr.size.get();
+    if size > max_size {
+        bail!("The remote attestation frame is too large: {size} bytes (max {max_size})");
+    }
+    let mut payload = vec![0; size as usize];
+    reader
+        .read_exact(&mut payload)
+        .context("Cannot read the remote attestation frame")?;
+    Ok((kind, payload))
+}
+
+/// Attestation request as sent by the client
+#[derive(Debug, PartialEq, Eq)]
+struct RemoteRequest {
+    user_data: Option<Vec<u8>>,
+    /// Exchange format request
+    request: Vec<u8>,
+}
+
+impl RemoteRequest {
+    fn encode(&self) -> Result<Vec<u8>> {
+        let user_data = self.user_data.as_deref().unwrap_or_default();
+        let hdr = RequestHdr {
+            flags: match self.user_data {
+                Some(_) => REQ_FLAG_USER_DATA,
+                None => 0,
+            },
+            reserved: 0,
+            user_data_size: u16::try_from(user_data.len())
+                .map_err(|_| anyhow!("The user-data is too large"))?
+                .into(),
+        };
+        Ok([hdr.as_bytes(), user_data, &self.request].concat())
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self> {
+        let err = || anyhow!("Invalid remote attestation request");
+        let hdr = RequestHdr::read_from_prefix(payload).ok_or_else(err)?;
+        let payload = &payload[size_of::<RequestHdr>()..];
+        let user_data_size = hdr.user_data_size.get() as usize;
+        if payload.len() < user_data_size {
+            return Err(err());
+        }
+        let (user_data, request) = payload.split_at(user_data_size);
+        let user_data = match hdr.flags & REQ_FLAG_USER_DATA {
+            0 if user_data.is_empty() => None,
+            0 => return Err(err()),
+            _ => Some(user_data.to_vec()),
+        };
+        Ok(Self {
+            user_data,
+            request: request.to_vec(),
+        })
+    }
+}
+
+/// A connection between client and server
+pub trait Connection: Read + Write + Send {}
+impl<T: Read + Write + Send> Connection for T {}
+
+/// Listens for connections of remote attestation clients
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    Vsock(vsock::VsockListener),
+}
+
+impl Listener {
+    pub fn bind(addr: &RemoteAddr) -> Result<Self> {
+        let ctx = || format!("Cannot listen at '{addr}'");
+        match addr {
+            RemoteAddr::Tcp(addr) => Ok(Self::Tcp(TcpListener::bind(addr).with_context(ctx)?)),
+            RemoteAddr::Vsock { cid, port } => Ok(Self::Vsock(
+                vsock::VsockListener::bind(*cid, *port).with_context(ctx)?,
+            )),
+        }
+    }
+
+    /// Accept a connection and return it with a description of the peer
+    fn accept(&self) -> Result<(Box<dyn Connection>, String)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, peer) = listener.accept()?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Ok((Box::new(stream), peer.to_string()))
+            }
+            Self::Vsock(listener) => {
+                let (file, peer) = listener.accept()?;
+                vsock::set_timeout(&file, TIMEOUT)?;
+                Ok((Box::new(file), peer))
+            }
+        }
+    }
+}
+
+fn connect(addr: &RemoteAddr) -> Result<Box<dyn Connection>> {
+    let ctx = || format!("Cannot connect to '{addr}'");
+    match addr {
+        RemoteAddr::Tcp(addr) => {
+            let stream = TcpStream::connect(addr).with_context(ctx)?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            Ok(Box::new(stream))
+        }
+        RemoteAddr::Vsock { cid, port } => {
+            let file: File = vsock::connect(*cid, *port).with_context(ctx)?;
+            vsock::set_timeout(&file, TIMEOUT)?;
+            Ok(Box::new(file))
+        }
+    }
+}
+
+/// Send the exchange format `request` to the server at `addr` and return the exchange format
+/// response
+pub fn attest(addr: &RemoteAddr, request: Vec<u8>, user_data: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    let mut conn = connect(addr)?;
+    let payload = RemoteRequest { user_data, request }.encode()?;
+    write_frame(conn.as_mut(), FrameKind::Request, &payload)
+        .with_context(|| format!("Cannot send the attestation request to '{addr}'"))?;
+    match read_frame(conn.as_mut(), MAX_FRAME_SIZE)? {
+        (FrameKind::Response, response) => Ok(response),
+        (FrameKind::Error, msg) => bail!(
+            "'{addr}' cannot perform the attestation: {}",
+            String::from_utf8_lossy(&msg)
+        ),
+        (kind, _) => bail!("Invalid remote attestation frame: Unexpected kind {kind:?}"),
+    }
+}
+
+fn process_request(uv: &dyn UvTransport, payload: &[u8]) -> Result<Vec<u8>> {
+    let req = RemoteRequest::decode(payload)?;
+    let ex_in = ExchangeFormatRequest::read(&mut Cursor::new(req.request))?;
+    let ex_out = perform_request(uv, ex_in, req.user_data)?;
+    let mut response = Vec::new();
+    ex_out.write(&mut response, ExchangeFormatVersion::One)?;
+    Ok(response)
+}
+
+/// Serve one request and send the error, if any, to the client
+fn handle_connection(
+    conn: &mut dyn Connection,
+    uv: &dyn UvTransport,
+    max_request_size: u32,
+) -> Result<()> {
+    let res = match read_frame(conn, max_request_size) {
+        Ok((FrameKind::Request, payload)) => process_request(uv, &payload),
+        Ok((kind, _)) => Err(anyhow!(
+            "Invalid remote attestation frame: Unexpected kind {kind:?}"
+        )),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(response) => write_frame(conn, FrameKind::Response, &response),
+        Err(e) => {
+            // best effort, the client may already be gone
+            let _ = write_frame(conn, FrameKind::Error, format!("{e:#}").as_bytes());
+            Err(e)
+        }
+    }
+}
+
+/// Counting semaphore that limits the number of connections served at the same time
+#[derive(Debug)]
+struct Slots {
+    free: Mutex<usize>,
+    cond: Condvar,
+}
+
+struct SlotGuard(Arc<Slots>);
+
+impl Slots {
+    fn acquire(self: &Arc<Self>) -> SlotGuard {
+        // Panic: the lock is never held during a panic
+        let mut free = self.free.lock().unwrap();
+        while *free == 0 {
+            free = self.cond.wait(free).unwrap();
+        }
+        *free -= 1;
+        SlotGuard(self.clone())
+    }
+}
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        // Panic: the lock is never held during a panic
+        *self.0.free.lock().unwrap() += 1;
+        self.0.cond.notify_one();
+    }
+}
+
+/// Serve all connections of `listener` with the Ultravisor `uv`
+///
+/// Serves up to `max_connections` connections at the same time in separate threads. Further
+/// connections wait in the backlog of the listener. Requests larger than `max_request_size` bytes
+/// are rejected. Only returns on error.
+pub fn serve_connections(
+    listener: &Listener,
+    uv: Arc<dyn UvTransport + Send + Sync>,
+    max_connections: usize,
+    max_request_size: u32,
+) -> Result<()> {
+    let slots = Arc::new(Slots {
+        free: Mutex::new(max_connections),
+        cond: Condvar::new(),
+    });
+    loop {
+        let slot = slots.acquire();
+        let (mut conn, peer) = match listener.accept() {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Cannot accept a connection: {e:#}");
+                continue;
+            }
+        };
+        let uv = uv.clone();
+        thread::Builder::new()
+            .name(format!("pvattest {peer}"))
+            .spawn(move || {
+                let _slot = slot;
+                match handle_connection(conn.as_mut(), uv.as_ref(), max_request_size) {
+                    Ok(()) => debug!("Performed an attestation request of '{peer}'"),
+                    Err(e) => warn!("Cannot perform the attestation request of '{peer}': {e:#}"),
+                }
+            })
+            .context("Cannot start a thread for the connection")?;
+    }
+}
+
+/// Minimal `AF_VSOCK` stream sockets
+mod vsock {
+    use std::{
+        fs::File,
+        io,
+        mem::size_of,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        time::Duration,
+    };
+
+    use super::BACKLOG;
+
+    fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+        match ret {
+            ret if ret < 0 => Err(io::Error::last_os_error()),
+            ret => Ok(ret),
+        }
+    }
+
+    fn sockaddr(cid: u32, port: u32) -> libc::sockaddr_vm {
+        // SAFETY: sockaddr_vm is a plain C-struct, all zeros is a valid value.
+        let mut addr: libc::sockaddr_vm = unsafe { std::mem::zeroed() };
+        addr.svm_family = libc::AF_VSOCK as libc::sa_family_t;
+        addr.svm_cid = cid;
+        addr.svm_port = port;
+        addr
+    }
+
+    fn socket() -> io::Result<OwnedFd> {
+        // SAFETY: socket does not access memory.
+        let fd = cvt(unsafe {
+            libc::socket(libc::AF_VSOCK, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0)
+        })?;
+        // SAFETY: fd is a new and valid file descriptor owned by nobody else.
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    pub fn connect(cid: u32, port: u32) -> io::Result<File> {
+        let fd = socket()?;
+        let addr = sockaddr(cid, port);
+        // SAFETY: addr is a valid sockaddr_vm of the passed size and outlives the call.
+        cvt(unsafe {
+            libc::connect(
+                fd.as_raw_fd(),
+                &addr as *const _ as *const libc::sockaddr,
+                size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+            )
+        })?;
+        Ok(fd.into())
+    }
+
+    pub fn set_timeout(file: &File, timeout: Duration) -> io::Result<()> {
+        let tv = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+        };
+        for opt in [libc::SO_RCVTIMEO, libc::SO_SNDTIMEO] {
+            // SAFETY: tv is a valid timeval of the passed size and outlives the call.
+            cvt(unsafe {
+                libc::setsockopt(
+                    file.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    opt,
+                    &tv as *const _ as *const libc::c_void,
+                    size_of::<libc::timeval>() as libc::socklen_t,
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    pub struct VsockListener(OwnedFd);
+
+    impl VsockListener {
+        pub fn bind(cid: u32, port: u32) -> io::Result<Self> {
+            let fd = socket()?;
+            let addr = sockaddr(cid, port);
+            // SAFETY: addr is a valid sockaddr_vm of the passed size and outlives the call.
+            cvt(unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const _ as *const libc::sockaddr,
+                    size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+                )
+            })?;
+            // SAFETY: listen does not access memory.
+            cvt(unsafe { libc::listen(fd.as_raw_fd(), BACKLOG) })?;
+            Ok(Self(fd))
+        }
+
+        pub fn accept(&self) -> io::Result<(File, String)> {
+            let mut addr = sockaddr(0, 0);
+            let mut len = size_of::<libc::sockaddr_vm>() as libc::socklen_t;
+            // SAFETY: addr is a valid sockaddr_vm of size len and outlives the call.
+            let fd = cvt(unsafe {
+                libc::accept4(
+                    self.0.as_raw_fd(),
+                    &mut addr as *mut _ as *mut libc::sockaddr,
+                    &mut len,
+                    libc::SOCK_CLOEXEC,
+                )
+            })?;
+            // SAFETY: fd is a new and valid file descriptor owned by nobody else.
+            let file = unsafe { OwnedFd::from_raw_fd(fd) }.into();
+            Ok((file, format!("vsock:{}:{}", addr.svm_cid, addr.svm_port)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use pv::uv::ConfigUid;
+
+    use super::*;
+    use crate::{exchange::ExchangeFormatResponse, transport::AttestationResponse};
+
+    const CUID: ConfigUid = [0x17; 16];
+
+    /// Ultravisor mock that answers with the first byte of the request as measurement
+    #[derive(Default)]
+    struct MockUv {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    impl UvTransport for MockUv {
+        fn attest(
+            &self,
+            arcb: Vec<u8>,
+            user_data: Option<Vec<u8>>,
+            exp_measurement: u32,
+            exp_additional: u32,
+        ) -> Result<AttestationResponse> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            if arcb.starts_with(b"fail") {
+                bail!("Mocked Ultravisor failure");
+            }
+            assert_eq!(exp_additional, 0);
+            let mut measurement = vec![arcb[0]; exp_measurement as usize];
+            if let Some(user_data) = user_data {
+                measurement[1..=user_data.len()].copy_from_slice(&user_data);
+            }
+            Ok(AttestationResponse {
+                measurement,
+                additional: None,
+                cuid: CUID,
+            })
+        }
+    }
+
+    fn request(arcb: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ExchangeFormatRequest::new(arcb.to_vec(), 64, 0)
+            .unwrap()
+            .write(&mut buf, ExchangeFormatVersion::One)
+            .unwrap();
+        buf
+    }
+
+    /// Start a server with the mocked Ultravisor on a loopback port
+    fn server(max_connections: usize, max_request_size: u32) -> (RemoteAddr, Arc<MockUv>) {
+        let listener = Listener::Tcp(TcpListener::bind("127.0.0.1:0").unwrap());
+        let addr = match &listener {
+            Listener::Tcp(l) => RemoteAddr::Tcp(l.local_addr().unwrap().to_string()),
+            Listener::Vsock(_) => unreachable!(),
+        };
+        let uv = Arc::new(MockUv::default());
+        let serve_uv = uv.clone();
+        thread::spawn(move || {
+            serve_connections(&listener, serve_uv, max_connections, max_request_size)
+        });
+        (addr, uv)
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, FrameKind::Response, b"payload").unwrap();
+        assert_eq!(buf.len(), size_of::<FrameHdr>() + 7);
+        assert_eq!(&buf[..8], b"pvattrmt");
+        let (kind, payload) = read_frame(&mut buf.as_slice(), MAX_FRAME_SIZE).unwrap();
+        assert_eq!(kind, FrameKind::Response);
+        assert_eq!(payload, b"payload");
+
+        // truncated payload
+        assert!(read_frame(&mut &buf[..buf.len() - 1], MAX_FRAME_SIZE).is_err());
+    }
+
+    #[test]
+    fn frame_invalid() {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, FrameKind::Request, &[]).unwrap();
+        let modified = |off: usize, val: u8| {
+            let mut frame = frame.clone();
+            frame[off] = val;
+            read_frame(&mut frame.as_slice(), MAX_FRAME_SIZE)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(modified(0, b'X').ends_with("Invalid magic"));
+        assert!(modified(9, 2).starts_with("Unsupported remote attestation protocol version 2"));
+        assert!(modified(10, 7).ends_with("Unknown kind 7"));
+
+        // the size is checked before the payload is allocated
+        let hdr = FrameHdr {
+            magic: FRAME_MAGIC,
+            version: FRAME_VERSION.into(),
+            kind: FrameKind::Request as u8,
+            reserved: 0,
+            size: (MAX_FRAME_SIZE + 1).into(),
+        };
+        let err = read_frame(&mut hdr.as_bytes(), MAX_FRAME_SIZE).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("The remote attestation frame is too large"));
+        let payload = vec![0; MAX_FRAME_SIZE as usize + 1];
+        assert!(write_frame(&mut Vec::new(), FrameKind::Request, &payload).is_err());
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        for user_data in [None, Some(vec![]), Some(vec![1, 2, 3])] {
+            let req = RemoteRequest {
+                user_data,
+                request: vec![4, 5],
+            };
+            assert_eq!(RemoteRequest::decode(&req.encode().unwrap()).unwrap(), req);
+        }
+        assert!(RemoteRequest::decode(&[0x80, 0]).is_err());
+        assert!(RemoteRequest::decode(&[0x80, 0, 0, 4, 1]).is_err());
+        assert!(RemoteRequest::decode(&[0, 0, 0, 1, 1]).is_err());
+    }
+
+    #[test]
+    fn loopback() {
+        let (addr, uv) = server(2, MAX_FRAME_SIZE);
+        let clients: Vec<_> = (0..8u8)
+            .map(|i| {
+                let addr = addr.clone();
+                thread::spawn(move || {
+                    let resp = attest(&addr, request(&[i; 32]), Some(vec![i; 4])).unwrap();
+                    (i, resp)
+                })
+            })
+            .collect();
+        for client in clients {
+            let (i, resp) = client.join().unwrap();
+            let resp = ExchangeFormatResponse::read(&mut Cursor::new(resp)).unwrap();
+            assert_eq!(resp.arcb, [i; 32]);
+            assert_eq!(resp.measurement[..5], [i, i, i, i, i]);
+            assert_eq!(resp.user.as_deref(), Some([i; 4].as_slice()));
+            assert_eq!(resp.config_uid, CUID);
+        }
+        assert!(uv.max_active.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn loopback_errors() {
+        let (addr, _) = server(1, MAX_FRAME_SIZE);
+        let err = attest(&addr, request(b"failing request"), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("'{addr}' cannot perform the attestation: Mocked Ultravisor failure")
+        );
+        let err = attest(&addr, b"no exchange format".to_vec(), None).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("'{addr}' cannot perform the attestation: ")));
+
+        // the server keeps serving after errors
+        assert!(attest(&addr, request(b"arcb"), None).is_ok());
+    }
+
+    #[test]
+    fn loopback_max_request_size() {
+        let req = RemoteRequest {
+            user_data: None,
+            request: request(b"arcb"),
+        };
+        let size = req.encode().unwrap().len() as u32;
+        let (addr, _) = server(1, size);
+        assert!(attest(&addr, request(b"arcb"), None).is_ok());
+        let err = attest(&addr, request(b"arcb"), Some(vec![0])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "'{addr}' cannot perform the attestation: The remote attestation frame is too \
+                 large: {} bytes (max {size})",
+                size + 1
+            )
+        );
+    }
+}
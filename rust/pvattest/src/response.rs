@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+//! Reading attestation responses in the formats of [`ResponseFormat`]
+//!
+//! Besides the binary exchange format written by 'pvattest perform', responses may be
+//! base64-encoded or wrapped into a JSON object with base64-encoded fields, e.g., by cloud
+//! middlewares.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::*;
+use log::debug;
+use pv::misc::read_file;
+use std::{io::Cursor, path::Path};
+
+use crate::{
+    cli::{JsonFields, ResponseFormat, ResponseInputOpt},
+    exchange::ExchangeFormatResponse,
+};
+
+/// Decode base64, ignoring whitespace like line breaks
+fn decode_base64(data: &[u8]) -> Result<Vec<u8>> {
+    let data: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    Ok(BASE64_STANDARD.decode(data)?)
+}
+
+fn decode_binary(data: &[u8]) -> Result<ExchangeFormatResponse> {
+    ExchangeFormatResponse::read(&mut Cursor::new(data))
+}
+
+fn decode_json(data: &[u8], fields: &JsonFields) -> Result<ExchangeFormatResponse> {
+    let json: serde_json::Value = serde_json::from_slice(data)?;
+    let obj = json
+        .as_object()
+        .ok_or_else(|| anyhow!("The JSON value is no object"))?;
+    let field = |name: &str| -> Result<Option<Vec<u8>>> {
+        match obj.get(name) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::String(s)) => decode_base64(s.as_bytes())
+                .map(Some)
+                .with_context(|| format!("The JSON field '{name}' is not base64-encoded")),
+            Some(_) => bail!("The JSON field '{name}' is no string"),
+        }
+    };
+
+    let resp = field(&fields.response)?
+        .ok_or_else(|| anyhow!("The JSON object has no field '{}'", fields.response))?;
+    let resp = decode_binary(&resp).with_context(|| {
+        format!(
+            "Invalid attestation response in the JSON field '{}'",
+            fields.response
+        )
+    })?;
+    let additional = match &fields.additional {
+        Some(name) => field(name)?.map(|add| (name, add)),
+        None => None,
+    };
+    match (additional, resp.additional()) {
+        (Some((name, add)), Some(resp_add)) if add != resp_add => bail!(
+            "The additional-data in the JSON field '{name}' does not match the additional-data \
+             of the attestation response"
+        ),
+        (Some((_, add)), None) => ExchangeFormatResponse::new(
+            resp.arcb,
+            resp.measurement,
+            Some(add),
+            resp.user,
+            resp.config_uid,
+        ),
+        _ => Ok(resp),
+    }
+}
+
+/// Decode an attestation response in `format`
+///
+/// [`ResponseFormat::Auto`] tries all formats and reports the errors of all of them if none
+/// matches.
+fn decode_response(
+    data: &[u8],
+    format: ResponseFormat,
+    fields: &JsonFields,
+) -> Result<ExchangeFormatResponse> {
+    match format {
+        ResponseFormat::Binary => decode_binary(data),
+        ResponseFormat::Base64 => decode_binary(&decode_base64(data)?),
+        ResponseFormat::Json => decode_json(data, fields),
+        ResponseFormat::Auto => {
+            let mut errs = Vec::new();
+            for format in [
+                ResponseFormat::Binary,
+                ResponseFormat::Base64,
+                ResponseFormat::Json,
+            ] {
+                match decode_response(data, format, fields) {
+                    Ok(resp) => {
+                        debug!("Detected the {format} format for the attestation response");
+                        return Ok(resp);
+                    }
+                    Err(e) => errs.push(format!("{format}: {e:#}")),
+                }
+            }
+            bail!("Cannot detect the format. Tried {}", errs.join("; "))
+        }
+    }
+}
+
+/// Read the attestation response in `path` in the format selected by `opt`
+pub fn read_response<P: AsRef<Path>>(
+    path: P,
+    opt: &ResponseInputOpt,
+) -> Result<ExchangeFormatResponse> {
+    let path = path.as_ref();
+    let data = read_file(path, "attestation response")?;
+    // the reasons are the interesting part, so do not hide them in the error chain
+    decode_response(&data, opt.input_format, &opt.json_fields).map_err(|e| {
+        anyhow!(
+            "Cannot read the attestation response '{}': {e:#}",
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/exp/exchange/");
+
+    fn asset(name: &str) -> Vec<u8> {
+        read_file(format!("{ASSETS}{name}"), "test asset").unwrap()
+    }
+
+    fn fields() -> JsonFields {
+        JsonFields {
+            response: "response".to_string(),
+            additional: Some("additional-data".to_string()),
+        }
+    }
+
+    fn decode(data: &[u8], format: ResponseFormat) -> Result<ExchangeFormatResponse> {
+        decode_response(data, format, &fields())
+    }
+
+    #[test]
+    fn formats() {
+        let exp = decode_binary(&asset("full_resp.bin")).unwrap();
+        let b64 = asset("full_resp.b64");
+        let json = asset("full_resp.json");
+
+        for format in [ResponseFormat::Auto, ResponseFormat::Binary] {
+            assert_eq!(decode(&asset("full_resp.bin"), format).unwrap(), exp);
+        }
+        for format in [ResponseFormat::Auto, ResponseFormat::Base64] {
+            assert_eq!(decode(&b64, format).unwrap(), exp);
+        }
+        for format in [ResponseFormat::Auto, ResponseFormat::Json] {
+            assert_eq!(decode(&json, format).unwrap(), exp);
+        }
+
+        // forced decoders do not fall back
+        assert!(decode(&b64, ResponseFormat::Binary).is_err());
+        assert!(decode(&json, ResponseFormat::Base64).is_err());
+        assert!(decode(&asset("full_resp.bin"), ResponseFormat::Json).is_err());
+    }
+
+    #[test]
+    fn json_fields() {
+        let min = asset("min_resp.bin");
+        let add = decode_binary(&asset("add_resp.bin")).unwrap();
+        let add_b64 = BASE64_STANDARD.encode(add.additional().unwrap());
+        let json = serde_json::json!({
+            "resp": BASE64_STANDARD.encode(&min),
+            "add": add_b64,
+        })
+        .to_string();
+        let fields = |add: Option<&str>| JsonFields {
+            response: "resp".to_string(),
+            additional: add.map(str::to_string),
+        };
+
+        let resp = decode_json(json.as_bytes(), &fields(Some("add"))).unwrap();
+        assert_eq!(resp.additional(), add.additional());
+        let resp = decode_json(json.as_bytes(), &fields(None)).unwrap();
+        assert_eq!(resp, decode_binary(&min).unwrap());
+        // a missing additional-data field is fine
+        let resp = decode_json(json.as_bytes(), &fields(Some("missing"))).unwrap();
+        assert_eq!(resp, decode_binary(&min).unwrap());
+
+        // the response contains additional-data already
+        let json = serde_json::json!({
+            "resp": BASE64_STANDARD.encode(asset("add_resp.bin")),
+            "add": add_b64,
+            "other": BASE64_STANDARD.encode([0; 4]),
+        })
+        .to_string();
+        let resp = decode_json(json.as_bytes(), &fields(Some("add"))).unwrap();
+        assert_eq!(resp, add);
+        let err = decode_json(json.as_bytes(), &fields(Some("other"))).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("does not match the additional-data of the attestation response"));
+        let err = decode_json(
+            json.as_bytes(),
+            &JsonFields {
+                response: "response".to_string(),
+                additional: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "The JSON object has no field 'response'");
+        let err = decode_json(b"[1]", &fields(None)).unwrap_err();
+        assert_eq!(err.to_string(), "The JSON value is no object");
+        let err = decode_json(br#"{"resp": 1}"#, &fields(None)).unwrap_err();
+        assert_eq!(err.to_string(), "The JSON field 'resp' is no string");
+    }
+
+    #[test]
+    fn corrupt_base64() {
+        let mut b64 = asset("full_resp.b64");
+        b64[10] = b'*';
+        let err = decode(&b64, ResponseFormat::Base64).unwrap_err();
+        assert!(
+            err.to_string().starts_with("Invalid symbol 42, offset 10"),
+            "{err}"
+        );
+
+        let err = decode(&b64, ResponseFormat::Auto).unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(
+            msg.starts_with("Cannot detect the format. Tried binary: "),
+            "{msg}"
+        );
+        assert!(
+            msg.contains("; base64: Invalid symbol 42, offset 10"),
+            "{msg}"
+        );
+        assert!(msg.contains("; json: "), "{msg}");
+
+        let json = br#"{"response": "AAAA*AAA"}"#;
+        let err = decode(json, ResponseFormat::Json).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "The JSON field 'response' is not base64-encoded: Invalid symbol 42, offset 4."
+        );
+    }
+
+    #[test]
+    fn read_response_path() {
+        let opt = ResponseInputOpt {
+            input_format: ResponseFormat::Auto,
+            json_fields: fields(),
+        };
+        let resp = read_response(format!("{ASSETS}full_resp.json"), &opt).unwrap();
+        assert_eq!(resp, decode_binary(&asset("full_resp.bin")).unwrap());
+
+        let err = read_response(format!("{ASSETS}full_req.bin"), &opt).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot read the attestation response '"));
+    }
+}
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use log::warn;
+use pv::{
+    attest::{AttestationFlags, AttestationRequest},
+    uv::{AttestationCmd, ConfigUid, UvCmdExecutor, UvInfo},
+    PvCoreError,
+};
+
+use crate::cli::{AttAddFlags, UvBackendOpt};
+
+#[cfg(feature = "simulate")]
+mod simulate;
+#[cfg(feature = "simulate")]
+pub use simulate::SimulatedUv;
+
+/// Ultravisor return code for unsupported plaintext attestation flags
+const RC_UNSUPPORTED_FLAGS: u16 = 0x010a;
+
+/// Response of an Ultravisor to an attestation request
+#[derive(Debug)]
+pub struct AttestationResponse {
+    pub measurement: Vec<u8>,
+    pub additional: Option<Vec<u8>>,
+    pub cuid: ConfigUid,
+}
+
+/// Something that can perform the Retrieve Attestation Measurement UVC
+pub trait UvTransport {
+    /// Sends the attestation request `arcb` and returns the response of the Ultravisor.
+    fn attest(
+        &self,
+        arcb: Vec<u8>,
+        user_data: Option<Vec<u8>>,
+        exp_measurement: u32,
+        exp_additional: u32,
+    ) -> Result<AttestationResponse>;
+
+    /// Returns the plaintext attestation flags the Ultravisor supports in MSB0 bit ordering.
+    ///
+    /// `None` if the supported flags are unknown.
+    fn supported_flags(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<U: UvCmdExecutor> UvTransport for U {
+    fn attest(
+        &self,
+        arcb: Vec<u8>,
+        user_data: Option<Vec<u8>>,
+        exp_measurement: u32,
+        exp_additional: u32,
+    ) -> Result<AttestationResponse> {
+        let mut cmd =
+            AttestationCmd::new_request(arcb.into(), user_data, exp_measurement, exp_additional)?;
+        UvCmdExecutor::attest(self, &mut cmd)?;
+
+        Ok(AttestationResponse {
+            measurement: cmd.measurement().to_owned(),
+            additional: cmd.additional_owned(),
+            cuid: cmd.cuid().to_owned(),
+        })
+    }
+
+    fn supported_flags(&self) -> Option<u64> {
+        UvInfo::from_sysfs().ok()?.att_pflags().map(u64::from)
+    }
+}
+
+/// Whether `flags` request the additional-data `add`, and the bit of `add` in the flags
+fn requested(add: AttAddFlags, flags: &AttestationFlags) -> (bool, u8) {
+    match add {
+        AttAddFlags::PhkhImg => (flags.image_phkh(), 2),
+        AttAddFlags::PhkhAtt => (flags.attest_phkh(), 3),
+        AttAddFlags::SecretStoreHash => (flags.secret_store_hash(), 4),
+        AttAddFlags::FirmwareState => (flags.firmware_state(), 5),
+    }
+}
+
+/// Names of the additional-data requested by `arcb` that are not set in `supported`
+fn unsupported_add_data(arcb: &[u8], supported: u64) -> Result<Vec<String>> {
+    let auth = AttestationRequest::auth_bin(arcb)?;
+    Ok(AttAddFlags::value_variants()
+        .iter()
+        .filter(|add| {
+            let (requested, bit) = requested(**add, auth.flags());
+            requested && (supported >> (63 - bit)) & 1 == 0
+        })
+        .filter_map(|add| add.to_possible_value())
+        .map(|v| v.get_name().to_owned())
+        .collect())
+}
+
+/// Send the attestation request `arcb` to the Ultravisor `uv`
+///
+/// If the Ultravisor rejects the plaintext flags of the request, the error names the requested
+/// additional-data that the Ultravisor does not support. The request cannot be retried without
+/// them, as the flags are protected by the attestation request protection key. Only the creator
+/// of the request can create a new one.
+pub fn attest(
+    uv: &dyn UvTransport,
+    arcb: &[u8],
+    user_data: Option<Vec<u8>>,
+    exp_measurement: u32,
+    exp_additional: u32,
+) -> Result<AttestationResponse> {
+    let err = match uv.attest(arcb.to_vec(), user_data, exp_measurement, exp_additional) {
+        Err(err) => err,
+        res => return res,
+    };
+    let unsupported_flags = matches!(
+        err.downcast_ref::<PvCoreError>(),
+        Some(PvCoreError::Uv { rc, .. }) if *rc == RC_UNSUPPORTED_FLAGS
+    );
+    let unsupported = match uv.supported_flags() {
+        Some(supported) if unsupported_flags => unsupported_add_data(arcb, supported)?,
+        _ => return Err(err),
+    };
+    if unsupported.is_empty() {
+        return Err(err);
+    }
+    let unsupported = unsupported.join(", ");
+    warn!("The Ultravisor does not support the requested additional-data: {unsupported}");
+    Err(err.context(format!(
+        "The Ultravisor does not support the additional-data {unsupported}. Create a new request \
+         without --add-data {unsupported}"
+    )))
+}
+
+/// Boxed [`UvTransport`] that can be shared between threads
+pub type SharedUvTransport = Box<dyn UvTransport + Send + Sync>;
+
+/// Check that the Ultravisor `uv` supports attestation
+#[cfg(any(test, target_arch = "s390x"))]
+fn checked<U: UvCmdExecutor + Send + Sync + 'static>(uv: U) -> Result<SharedUvTransport> {
+    if !UvInfo::query(&uv)?.supports_attestation() {
+        bail!("This machine's Ultravisor does not support attestation");
+    }
+    Ok(Box::new(uv))
+}
+
+#[cfg(target_arch = "s390x")]
+fn uvdevice(opt: &UvBackendOpt) -> Result<SharedUvTransport> {
+    checked(opt.device.open()?)
+}
+
+#[cfg(not(target_arch = "s390x"))]
+fn uvdevice(_: &UvBackendOpt) -> Result<SharedUvTransport> {
+    bail!("Command only available on s390x or with '--simulate'")
+}
+
+/// Open the Ultravisor selected by `opt`
+pub fn transport(opt: &UvBackendOpt) -> Result<SharedUvTransport> {
+    #[cfg(feature = "simulate")]
+    if let Some(hdr) = &opt.simulate {
+        use pv::{misc::open_file, request::BootHdrTags};
+
+        warn!("WARNING: Using the INSECURE Ultravisor simulation. Never trust the response!");
+        let tags = BootHdrTags::from_se_image(&mut open_file(hdr)?)?;
+        return Ok(Box::new(SimulatedUv::new(tags)?));
+    }
+    uvdevice(opt)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use pv::{
+        misc::read_certs,
+        request::{ReqEncrCtx, Request, SymKeyType},
+    };
+
+    use super::*;
+
+    const HOST_CRT: &[u8] = include_bytes!("../tests/assets/simulate/host.crt");
+
+    /// Ultravisor that supports the flags `supported` and counts the attestation calls
+    struct MockUv {
+        supported: Option<u64>,
+        rc: Option<u16>,
+        calls: Cell<usize>,
+    }
+
+    impl MockUv {
+        fn new(supported: Option<u64>, rc: Option<u16>) -> Self {
+            Self {
+                supported,
+                rc,
+                calls: Cell::new(0),
+            }
+        }
+    }
+
+    impl UvTransport for MockUv {
+        fn attest(
+            &self,
+            _: Vec<u8>,
+            _: Option<Vec<u8>>,
+            _: u32,
+            _: u32,
+        ) -> Result<AttestationResponse> {
+            self.calls.set(self.calls.get() + 1);
+            if let Some(rc) = self.rc {
+                return Err(PvCoreError::Uv {
+                    rc,
+                    rrc: 0,
+                    msg: "mock failure",
+                }
+                .into());
+            }
+            Ok(AttestationResponse {
+                measurement: vec![0; 64],
+                additional: None,
+                cuid: [0; 16],
+            })
+        }
+
+        fn supported_flags(&self) -> Option<u64> {
+            self.supported
+        }
+    }
+
+    /// Flag mask with the MSB0 `bits` set
+    fn mask(bits: &[u8]) -> u64 {
+        bits.iter().fold(0, |mask, bit| mask | 1 << (63 - bit))
+    }
+
+    /// Attestation request for image PHKH and firmware state
+    fn arcb() -> Vec<u8> {
+        let mut flags = AttestationFlags::default();
+        flags.set_image_phkh();
+        flags.set_firmware_state();
+        let mut arcb = AttestationRequest::new(
+            pv::attest::AttestationVersion::One,
+            pv::attest::AttestationMeasAlg::HmacSha512,
+            flags,
+        )
+        .unwrap();
+        arcb.add_hostkey(read_certs(HOST_CRT).unwrap()[0].public_key().unwrap());
+        let ctx = ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap();
+        arcb.encrypt(&ctx).unwrap()
+    }
+
+    #[test]
+    fn unsupported_add_data() {
+        let uv = MockUv::new(Some(mask(&[1, 2])), Some(RC_UNSUPPORTED_FLAGS));
+        let err = attest(&uv, &arcb(), None, 64, 0x160).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("The Ultravisor does not support the additional-data firmware-state."));
+        assert!(err.downcast_ref::<PvCoreError>().is_some());
+        assert_eq!(uv.calls.get(), 1);
+    }
+
+    #[test]
+    fn all_supported() {
+        let uv = MockUv::new(Some(mask(&[1, 2, 3, 4, 5])), None);
+        assert!(attest(&uv, &arcb(), None, 64, 0x160).is_ok());
+        assert_eq!(uv.calls.get(), 1);
+
+        // other reasons for unsupported flags are not explained
+        let uv = MockUv::new(Some(mask(&[1, 2, 3, 4, 5])), Some(RC_UNSUPPORTED_FLAGS));
+        let err = attest(&uv, &arcb(), None, 64, 0x160).unwrap_err();
+        assert!(err.is::<PvCoreError>());
+        assert!(err.to_string().starts_with("Ultravisor: 'mock failure'"));
+    }
+
+    #[test]
+    fn uvdevice_support() {
+        use pv::uv::{AttestationCmd, MockUvDevice};
+
+        assert!(checked(MockUvDevice::new()).is_ok());
+        let err = checked(MockUvDevice::new().unsupported::<AttestationCmd>())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "This machine's Ultravisor does not support attestation"
+        );
+    }
+
+    #[test]
+    fn unknown_support() {
+        for (supported, rc) in [(None, RC_UNSUPPORTED_FLAGS), (Some(mask(&[1])), 0x010c)] {
+            let uv = MockUv::new(supported, Some(rc));
+            let err = attest(&uv, &arcb(), None, 64, 0x160).unwrap_err();
+            assert!(err.to_string().starts_with("Ultravisor: 'mock failure'"));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+//! INSECURE software model of the attestation Ultravisor call.
+//!
+//! The private host-key of the simulated host is part of this source tree. Anybody can create
+//! valid responses. Use the simulation for testing only.
+
+use anyhow::{bail, Result};
+use log::debug;
+use pv::{
+    attest::{AttestationFlags, AttestationItems, AttestationMeasurement, AttestationRequest},
+    request::{
+        openssl::pkey::{PKey, Private},
+        BootHdrTags, EcPubKeyCoord,
+    },
+    uv::{AttestationCmd, ConfigUid},
+};
+
+use super::{AttestationResponse, UvTransport};
+
+/// Private host-key of the simulated host
+const SIM_HOST_KEY: &[u8] = include_bytes!("../../tests/assets/simulate/host.key");
+/// Configuration UID of every simulated guest
+const SIM_CUID: ConfigUid = [0x5e; 16];
+const SECRET_STORE_HASH_SIZE: usize = 0x40;
+const FW_STATE_SIZE: usize = 0x140;
+
+/// Simulated Ultravisor running a single IBM Secure Execution guest
+///
+/// Derives the response from the request in the same way as the Ultravisor does, using a fixed
+/// simulated host-key. Requests must be created for the host-key document
+/// `tests/assets/simulate/host.crt`.
+#[derive(Debug)]
+pub struct SimulatedUv {
+    host_key: PKey<Private>,
+    tags: BootHdrTags,
+}
+
+impl SimulatedUv {
+    /// Simulate an Ultravisor running a guest booted from an image with the SE-header `tags`.
+    pub fn new(tags: BootHdrTags) -> Result<Self> {
+        Ok(Self {
+            host_key: PKey::private_key_from_pem(SIM_HOST_KEY)?,
+            tags,
+        })
+    }
+
+    /// Additional data as requested by `flags`
+    ///
+    /// The guest image is built for the simulated host, has no secrets, and no firmware state.
+    fn additional(&self, flags: &AttestationFlags) -> Result<Option<Vec<u8>>> {
+        let phkh = EcPubKeyCoord::try_from(self.host_key.as_ref())?.sha256()?;
+        let mut additional = Vec::new();
+        if flags.image_phkh() {
+            additional.extend_from_slice(&phkh);
+        }
+        if flags.attest_phkh() {
+            additional.extend_from_slice(&phkh);
+        }
+        if flags.secret_store_hash() {
+            additional.extend_from_slice(&[0; SECRET_STORE_HASH_SIZE]);
+        }
+        if flags.firmware_state() {
+            additional.extend_from_slice(&[0; FW_STATE_SIZE]);
+        }
+        Ok(Some(additional).filter(|a| !a.is_empty()))
+    }
+}
+
+impl UvTransport for SimulatedUv {
+    fn attest(
+        &self,
+        arcb: Vec<u8>,
+        user_data: Option<Vec<u8>>,
+        exp_measurement: u32,
+        exp_additional: u32,
+    ) -> Result<AttestationResponse> {
+        // same input checks as for the real UVC
+        AttestationCmd::new_request(
+            arcb.as_slice().into(),
+            user_data.clone(),
+            exp_measurement,
+            exp_additional,
+        )?;
+
+        let arpk = AttestationRequest::unwrap_arpk_bin(&arcb, &self.host_key)?;
+        let (auth, conf) = AttestationRequest::decrypt_bin(&arcb, &arpk)?;
+        debug!("Simulated Ultravisor decrypted the attestation request");
+
+        let mai = auth.mai();
+        if mai.exp_size() > exp_measurement {
+            bail!("The expected measurement size is too small for the measurement algorithm");
+        }
+        let additional = self.additional(auth.flags())?;
+        if additional.as_ref().map_or(0, Vec::len) > exp_additional as usize {
+            bail!("The expected additional data size is too small for the requested data");
+        }
+
+        let meas_key = PKey::hmac(conf.measurement_key())?;
+        let items = AttestationItems::new(
+            &self.tags,
+            &SIM_CUID,
+            user_data.as_deref(),
+            conf.nonce().as_ref().map(|v| v.value()),
+            additional.as_deref(),
+        );
+        let measurement = AttestationMeasurement::calculate(items, mai, &meas_key)?;
+
+        Ok(AttestationResponse {
+            measurement: measurement.as_ref().to_owned(),
+            additional,
+            cuid: SIM_CUID,
+        })
+    }
+}
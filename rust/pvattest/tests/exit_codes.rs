@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+#![allow(missing_docs)]
+
+use std::process::Command;
+
+use utils::TemporaryDirectory;
+
+const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/simulate/");
+const EXIT_CODE_ERROR: i32 = 1;
+const EXIT_CODE_ATTESTATION_FAIL: i32 = 2;
+
+/// Run pvattest with `args` and return its exit code
+fn pvattest(args: &[&str]) -> i32 {
+    let out = Command::new(env!("CARGO_BIN_EXE_pvattest"))
+        .args(args)
+        .output()
+        .unwrap();
+    out.status.code().unwrap()
+}
+
+fn path(dir: &TemporaryDirectory, name: &str) -> String {
+    dir.path().join(name).to_str().unwrap().to_owned()
+}
+
+#[test]
+fn missing_input() {
+    let dir = TemporaryDirectory::new().unwrap();
+    let (resp, arpk) = (path(&dir, "resp"), path(&dir, "arpk"));
+    let hdr = format!("{ASSETS}secure_guest.hdr");
+
+    assert_eq!(pvattest(&["check", &resp, "-"]), EXIT_CODE_ERROR);
+    assert_eq!(
+        pvattest(&["verify", "-i", &resp, "-a", &arpk, "--hdr", &hdr]),
+        EXIT_CODE_ERROR
+    );
+}
+
+/// Copy the asset `name` into `dir`
+fn copy_asset(dir: &TemporaryDirectory, name: &str) -> String {
+    let dst = path(dir, name);
+    std::fs::copy(format!("{ASSETS}{name}"), &dst).unwrap();
+    dst
+}
+
+// The responses in the assets are recorded from the simulation for `secure_guest.hdr`
+// (`response.bin` with `response.arpk`) and for `two_keyslots.hdr` with the image public
+// host-key hash as additional data (`response_phkh_img.bin`).
+
+#[test]
+fn corrupted_response() {
+    let dir = TemporaryDirectory::new().unwrap();
+    let resp = copy_asset(&dir, "response.bin");
+    let arpk = format!("{ASSETS}response.arpk");
+    let hdr = format!("{ASSETS}secure_guest.hdr");
+    let verify = ["verify", "-i", &resp, "-a", &arpk, "--hdr", &hdr];
+
+    assert_eq!(pvattest(&verify), 0);
+    assert_eq!(pvattest(&["check", &resp, "-"]), 0);
+
+    // policy failure vs. a policy that cannot be checked
+    let cuid = format!("0x{}", "00".repeat(16));
+    assert_eq!(
+        pvattest(&["check", &resp, "-", "--cuid", &cuid]),
+        EXIT_CODE_ATTESTATION_FAIL
+    );
+    let missing = path(&dir, "missing");
+    assert_eq!(
+        pvattest(&["check", &resp, "-", "--user-data", &missing]),
+        EXIT_CODE_ERROR
+    );
+
+    // flip a bit of the configuration UID at the very end of the response
+    let mut data = std::fs::read(&resp).unwrap();
+    *data.last_mut().unwrap() ^= 1;
+    std::fs::write(&resp, data).unwrap();
+    assert_eq!(pvattest(&verify), EXIT_CODE_ATTESTATION_FAIL);
+}
+
+#[test]
+fn se_image_keyslots() {
+    let hkd = format!("{ASSETS}host.crt");
+    // the second keyslot is for the host key the simulation uses
+    let hdr = format!("{ASSETS}two_keyslots.hdr");
+    let other = format!("{ASSETS}secure_guest.hdr");
+
+    // no image public host-key hash in the response
+    let resp = format!("{ASSETS}response.bin");
+    assert_eq!(pvattest(&["check", &resp, "-", "--se-image", &other]), 0);
+
+    let resp = format!("{ASSETS}response_phkh_img.bin");
+    assert_eq!(pvattest(&["check", &resp, "-", "--se-image", &hdr]), 0);
+    assert_eq!(
+        pvattest(&["check", &resp, "-", "--se-image", &other]),
+        EXIT_CODE_ATTESTATION_FAIL
+    );
+    assert_eq!(
+        pvattest(&["check", &resp, "-", "--se-image", &hkd]),
+        EXIT_CODE_ERROR
+    );
+}
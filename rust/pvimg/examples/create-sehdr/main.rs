@@ -15,7 +15,7 @@ use anyhow::{anyhow, Context, Error};
 use clap::{Parser, ValueHint};
 use log::{info, warn};
 use pv::{
-    misc::{decode_hex, open_file, read_certs, read_file, try_parse_u64},
+    misc::{decode_hex, open_file, read_certs, read_confidential_file, read_file, try_parse_u64},
     request::SymKeyType,
     Error as PvError, Result,
 };
@@ -250,7 +250,7 @@ fn main() -> anyhow::Result<()> {
     if let Some(cck) = args.cck {
         info!("CCK ................: {}", cck.display());
         builder
-            .with_cck(read_file(&cck, "CCK")?.into())
+            .with_cck(read_confidential_file(&cck, "CCK", None)?)
             .with_context(|| format!("Invalid CCK in '{}'", &cck.display()))?;
     }
 
@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use log::info;
-use pv::{misc::read_file, request::Confidential};
+use pv::{misc::read_confidential_file, request::Confidential};
 
 use crate::cli::CreateBootImageExperimentalArgs;
 
@@ -37,7 +37,7 @@ pub fn read_user_provided_keys(
                 );
                 Some((
                     key_path.to_owned(),
-                    Confidential::new(read_file(key_path, "image components key")?),
+                    read_confidential_file(key_path, "image components key", None)?,
                 ))
             }
             None => None,
@@ -52,10 +52,11 @@ pub fn read_user_provided_keys(
                 );
                 Some((
                     key_path.to_owned(),
-                    Confidential::new(read_file(
+                    read_confidential_file(
                         key_path,
                         "Secure Execution header protection key",
-                    )?),
+                        None,
+                    )?,
                 ))
             }
             None => None,
@@ -71,7 +72,7 @@ pub fn read_user_provided_keys(
                 );
                 Some((
                     key_path.to_owned(),
-                    (Confidential::new(read_file(key_path, "customer communication key (CCK)")?)),
+                    read_confidential_file(key_path, "customer communication key (CCK)", None)?,
                 ))
             }
             None => None,
@@ -7,7 +7,7 @@ use std::io::Write;
 use anyhow::Result;
 use log::info;
 use pv::{
-    misc::{open_file, read_file},
+    misc::{open_file, read_confidential_file},
     request::SymKey,
 };
 use pvimg::{
@@ -28,8 +28,10 @@ pub fn info(opt: &InfoArgs) -> Result<OwnExitCode> {
     SeHdr::seek_sehdr(&mut input, None)?;
     let hdr = SeHdr::try_from_io(input)?;
     if let Some(key_path) = &opt.hdr_key {
-        let key =
-            SymKey::try_from_data(hdr.key_type(), read_file(key_path, "Reading key")?.into())?;
+        let key = SymKey::try_from_data(
+            hdr.key_type(),
+            read_confidential_file(key_path, "Reading key", None)?,
+        )?;
         serde_json::to_writer_pretty(&mut output, &hdr.decrypt(&key)?)?;
     } else {
         serde_json::to_writer_pretty(&mut output, &hdr)?;
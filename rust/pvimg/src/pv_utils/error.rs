@@ -21,8 +21,8 @@ pub enum Error {
     )]
     ImgAlreadyFinalized,
 
-    #[error("Invalid target key hash")]
-    InvalidTargetKeyHash,
+    #[error("Invalid target key hash {0}")]
+    InvalidTargetKeyHash(String),
 
     #[error("Invalid UV key hashes")]
     InvalidUvKeyHashes,
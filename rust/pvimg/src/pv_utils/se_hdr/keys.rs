@@ -5,11 +5,11 @@
 use std::mem::size_of;
 
 use deku::{ctx::Endian, DekuRead, DekuWrite};
-use openssl::{
-    hash::{hash, MessageDigest},
-    pkey::{PKey, PKeyRef, Public},
+use openssl::pkey::{PKey, PKeyRef, Public};
+use pv::{
+    request::{EcPubKeyCoord, Keyslot},
+    static_assert,
 };
-use pv::{request::EcPubKeyCoord, static_assert};
 use serde::Serialize;
 
 use crate::{
@@ -23,9 +23,7 @@ use crate::{
 ///
 /// This function will return an error if OpenSSL could not hash the key.
 pub fn phkh_v1<T: AsRef<PKeyRef<Public>>>(key: T) -> Result<[u8; 32]> {
-    let phk: EcPubKeyCoord = key.as_ref().try_into()?;
-    let binding = hash(MessageDigest::sha256(), phk.as_ref())?;
-    try_copy_slice_to_array(&binding)
+    Ok(Keyslot::host_key_hash(key.as_ref())?)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, DekuRead, DekuWrite, Serialize)]
@@ -2,10 +2,11 @@
 //
 // Copyright IBM Corp. 2024
 
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
 
 use enum_dispatch::enum_dispatch;
 use pv::misc::decode_hex;
+use utils::read_text_lines;
 
 use super::try_copy_slice_to_array;
 use crate::error::{Error, Result};
@@ -61,10 +62,10 @@ impl TryFrom<&str> for UvKeyHashV1 {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let bytes = decode_hex(value)?;
-        if bytes.len() != 32 {
-            return Err(Error::InvalidTargetKeyHash);
-        }
+        let bytes = decode_hex(value)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| Error::InvalidTargetKeyHash(format!("{value:?}")))?;
 
         Ok(Self(bytes.try_into().unwrap()))
     }
@@ -93,10 +94,7 @@ impl UvKeyHashesV1 {
     where
         R: Read,
     {
-        let buf_reader = BufReader::new(reader);
-        let lines: Vec<String> = buf_reader
-            .lines()
-            .collect::<std::result::Result<Vec<_>, std::io::Error>>()?;
+        let lines = read_text_lines(reader, "UV key hashes")?;
         let hashes: Vec<UvKeyHashV1> = lines
             .into_iter()
             .map(UvKeyHashV1::try_from)
@@ -156,4 +154,23 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn from_reader_crlf() {
+        let data = format!(
+            "\u{feff}{}\r\n",
+            ["0b729fd62241b339840d61b964a06bb6a1fd4976d9ebea2b4fb48d44de3a2461"; 15].join(" \r\n")
+        );
+        let result = UvKeyHashesV1::read_from_io(Cursor::new(data)).expect("should not fail");
+        assert_eq!(result.res[12], result.pchkh);
+    }
+
+    #[test]
+    fn invalid_hash() {
+        let err = UvKeyHashV1::try_from("0b729fd6\u{a0}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Invalid target key hash "0b729fd6\u{a0}""#
+        );
+    }
 }
@@ -3,10 +3,13 @@
 // Copyright IBM Corp. 2023, 2024
 
 use std::fmt::Display;
+use std::path::PathBuf;
 
-use clap::error::ErrorKind::ValueValidation;
+use clap::error::ErrorKind::{ArgumentConflict, MissingSubcommand, ValueValidation};
 use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
-use utils::{CertificateOptions, DeprecatedVerbosityOptions, STDOUT};
+#[cfg(target_arch = "s390x")]
+use utils::UvDeviceOptions;
+use utils::{CertVerifyOptions, CertificateOptions, DeprecatedVerbosityOptions, STDOUT};
 
 /// Manage secrets for IBM Secure Execution guests.
 ///
@@ -53,9 +56,49 @@ pub struct CreateSecretOpt {
     pub force: bool,
 
     /// Write the generated request to FILE.
-    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
+    ///
+    /// With '--batch', FILE must be an existing directory. The requests are written to
+    /// `NNN_NAME.bin` in this directory, where NNN is the three-digit number of the entry in the
+    /// batch file, starting with 001, and NAME the name of the secret with white-spaces mapped to
+    /// `_` (`meta` for meta secrets).
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::AnyPath,)]
     pub output: String,
 
+    /// Create one request for each secret listed in the YAML FILE.
+    ///
+    /// Reads and verifies the host-key documents only once for all requests. Each entry of the
+    /// list has a `type` (`meta`, `association`, or `retrievable`) and, besides meta secrets, a
+    /// `name`. Retrievable secrets require the secret `kind` (same values as '--type' of
+    /// 'create retrievable') and the `input` file of the secret. Association secrets take an
+    /// optional `input` and `output-secret` file. Each entry can add `flags` to the ones of the
+    /// command line. The secret information is written to `NAME.yaml` in the output directory.
+    /// If an entry fails, the already written files are kept unless '--atomic' is specified.
+    /// Conflicts with a COMMAND.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub batch: Option<String>,
+
+    /// Remove all files written so far if an entry of the batch fails.
+    #[arg(long, requires("batch"))]
+    pub atomic: bool,
+
+    /// Write the metadata of the generated request to FILE.
+    ///
+    /// The metadata contains the request tag, the secret type, the name and ID of the secret,
+    /// and the public host-key hashes of all keyslots. Besides the name, all values are read from
+    /// the written request. Conflicts with '--batch'.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with("batch"))]
+    pub metadata_out: Option<String>,
+
+    /// Define the format of the metadata written to '--metadata-out'.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_enum,
+        default_value_t,
+        requires("metadata_out")
+    )]
+    pub metadata_format: MetadataFormat,
+
     /// Use the content of FILE as an extension secret.
     ///
     /// The file must be exactly 32 bytes long. If this request is the first, all subsequent
@@ -94,7 +137,7 @@ pub struct CreateSecretOpt {
     pub cuid: Option<String>,
 
     #[command(subcommand)]
-    pub secret: AddSecretType,
+    pub secret: Option<AddSecretType>,
 
     // FLAGS
     // each flag must conflict with `flags`
@@ -149,6 +192,15 @@ pub struct CreateSecretOpt {
     pub use_name: bool,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
+pub enum MetadataFormat {
+    /// Use json format.
+    #[default]
+    Json,
+    /// Use yaml format.
+    Yaml,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum AddSecretType {
     /// Create a meta secret.
@@ -219,6 +271,16 @@ pub enum AddSecretType {
     },
 }
 
+impl AddSecretType {
+    /// Name of the secret, `None` for meta secrets
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Meta => None,
+            Self::Association { name, .. } | Self::Retrievable { name, .. } => Some(name),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum RetrieveableSecretInpKind {
     /// A plaintext secret.
@@ -262,6 +324,57 @@ pub struct AddSecretOpt {
     #[arg(value_name = "FILE", value_hint = ValueHint::FilePath,)]
     #[cfg(target_arch = "s390x")]
     pub input: String,
+
+    /// Warn if the secret store usage reaches PERCENT after adding the secret.
+    ///
+    /// Requires that the Ultravisor reports the maximum number of secrets. If the secret store is
+    /// already full, no add-secret request is sent.
+    #[arg(long, value_name = "PERCENT", default_value = "80%", value_parser = parse_percent)]
+    #[cfg(target_arch = "s390x")]
+    pub warn_at: u8,
+
+    /// Write a receipt of the added secret to FILE.
+    ///
+    /// The receipt is a JSON document that contains the tag of the add-secret request, the
+    /// secret-store hash before and after adding the secret, the Ultravisor return code, and the
+    /// SHA-256 digests of the request and of the previous receipt. Pass a chain of receipts to
+    /// 'pvattest check --secret-receipt' to verify the secret-store hash of an attestation
+    /// response. If the secret store is not empty, --previous-receipt is required. The receipt is
+    /// written after the secret was added.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    #[cfg(target_arch = "s390x")]
+    pub receipt: Option<PathBuf>,
+
+    /// Use the receipt FILE of the last add-secret request as the secret store state.
+    ///
+    /// FILE must be the receipt of the add-secret request that was sent to the Ultravisor last.
+    /// The new receipt refers to it.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, requires("receipt"))]
+    #[cfg(target_arch = "s390x")]
+    pub previous_receipt: Option<PathBuf>,
+
+    #[cfg(target_arch = "s390x")]
+    #[command(flatten)]
+    pub device: UvDeviceOptions,
+}
+
+/// Parse a percentage with an optional '%' suffix
+#[cfg(target_arch = "s390x")]
+fn parse_percent(s: &str) -> Result<u8, String> {
+    match s.strip_suffix('%').unwrap_or(s).parse() {
+        Ok(p) if p <= 100 => Ok(p),
+        _ => Err(format!("'{s}' is not a percentage between 0% and 100%")),
+    }
+}
+
+/// Parse a hex string with an optional '0x' prefix
+#[cfg(target_arch = "s390x")]
+fn parse_hex_prefix(s: &str) -> Result<String, String> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    match hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(hex.to_string()),
+        false => Err(format!("'{s}' is not a hex string")),
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
@@ -272,6 +385,8 @@ pub enum ListSecretOutputType {
     Human,
     /// Use yaml format.
     Yaml,
+    /// Use json format.
+    Json,
     /// Use the format the ultravisor uses to pass the list.
     Bin,
 }
@@ -288,6 +403,32 @@ pub struct ListSecretOpt {
     #[arg(long, value_enum, default_value_t)]
     #[cfg(target_arch = "s390x")]
     pub format: ListSecretOutputType,
+
+    /// List only secrets of the given type.
+    ///
+    /// TYPE is the name of the secret type as listed, e.g., AES-256-KEY, or its numeric code in
+    /// decimal or hexadecimal with a `0x` prefix. Codes of types unknown to this tool are accepted
+    /// as well.
+    #[arg(long = "type", value_name = "TYPE")]
+    #[cfg(target_arch = "s390x")]
+    pub stype: Option<String>,
+
+    /// List only secrets with an ID starting with the given hex string.
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_prefix)]
+    #[cfg(target_arch = "s390x")]
+    pub id: Option<String>,
+
+    #[cfg(target_arch = "s390x")]
+    #[command(flatten)]
+    pub device: UvDeviceOptions,
+}
+
+// all members s390x only
+#[derive(Args, Debug)]
+pub struct LockSecretOpt {
+    #[cfg(target_arch = "s390x")]
+    #[command(flatten)]
+    pub device: UvDeviceOptions,
 }
 
 #[derive(Args, Debug)]
@@ -306,6 +447,21 @@ pub struct VerifyOpt {
     #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath,)]
     pub user_cert: Option<String>,
 
+    /// Check that the request was created for one of the host-key documents in FILE.
+    ///
+    /// Succeeds if the public-key hash of at least one keyslot matches the host key of one of the
+    /// host-key documents and reports the matching documents. Can be specified multiple times.
+    /// The check works offline. The host-key documents are not verified, use `create` for that.
+    #[arg(
+        short = 'k',
+        long = "host-key-document",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        use_value_delimiter = true,
+        value_delimiter = ',',
+    )]
+    pub host_key_documents: Vec<PathBuf>,
+
     /// Store the result in FILE
     ///
     /// If the request contained abirtary user-data the output contains this user-data with padded
@@ -314,6 +470,69 @@ pub struct VerifyOpt {
     pub output: String,
 }
 
+#[derive(Args, Debug)]
+pub struct AuditOpt {
+    /// Audit all add-secret requests in DIR.
+    ///
+    /// All regular files in DIR are audited, sub-directories are ignored.
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    pub requests: String,
+
+    #[command(flatten)]
+    pub certificate_args: CertificateOptions,
+
+    /// Write a JSON list of all stale add-secret requests to FILE.
+    ///
+    /// An add-secret request is stale if none of its host-key hashes matches one of the given
+    /// host-key documents. Files that could not be audited are not part of the list.
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub stale_json: Option<String>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
+pub enum HkdInfoFormat {
+    /// Human-focused, non-parsable output format
+    #[default]
+    Human,
+    /// Use json format.
+    Json,
+    /// Use yaml format.
+    Yaml,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("hkd_verify").required(true).args(["no_verify", "certs"])))]
+pub struct HkdInfoOpt {
+    /// Report the validity of the host-key document HKD.
+    ///
+    /// Can be specified multiple times.
+    #[arg(value_name = "HKD", required = true, value_hint = ValueHint::FilePath)]
+    pub host_key_documents: Vec<PathBuf>,
+
+    /// Disable the host-key document verification.
+    ///
+    /// Reports the validity window of the host-key documents only, without their chain of trust.
+    #[arg(long)]
+    pub no_verify: bool,
+
+    #[command(flatten)]
+    pub verify_args: CertVerifyOptions,
+
+    /// Warn if a certificate or CRL expires in less than DAYS days.
+    ///
+    /// A near expiry is reported as warning and does not fail the command.
+    #[arg(long, value_name = "DAYS")]
+    pub warn_expiry: Option<u32>,
+
+    /// Define the output format.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: HkdInfoFormat,
+
+    /// Store the result in FILE
+    #[arg(short, long, value_name = "FILE", default_value = STDOUT, value_hint = ValueHint::FilePath,)]
+    pub output: String,
+}
+
 // all members s390x only
 #[derive(Args, Debug)]
 pub struct RetrSecretOptions {
@@ -338,8 +557,24 @@ pub struct RetrSecretOptions {
 
     /// Define the output format for the retrieved secret
     #[cfg(target_arch = "s390x")]
-    #[arg(long, value_enum, default_value_t)]
+    #[arg(
+        long = "output-format",
+        visible_alias = "outform",
+        value_enum,
+        default_value_t
+    )]
     pub outform: RetrOutFmt,
+
+    /// Write the secret in binary to a terminal.
+    ///
+    /// By default, the secret is not written in binary if the output is a terminal.
+    #[cfg(target_arch = "s390x")]
+    #[arg(long)]
+    pub force: bool,
+
+    #[cfg(target_arch = "s390x")]
+    #[command(flatten)]
+    pub device: UvDeviceOptions,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug, Default)]
@@ -364,6 +599,8 @@ pub enum RetrOutFmt {
     Pem,
     /// Write the secret in binary.
     Bin,
+    /// Write the secret as lowercase hex string with a trailing newline.
+    Hex,
 }
 
 #[derive(Subcommand, Debug)]
@@ -387,12 +624,14 @@ pub enum Command {
     ///
     /// Lock the secret store (s390x only). After this command executed successfully, all
     /// subsequent add-secret requests will fail. Only available on s390x.
-    Lock,
+    Lock(LockSecretOpt),
 
     /// List all ultravisor secrets (s390x only).
     ///
     /// Lists the IDs of all non-null secrets currently stored in the ultravisor for the currently
-    /// running IBM Secure Execution guest. Only available on s390x.
+    /// running IBM Secure Execution guest. If the ultravisor reports the maximum number of
+    /// secrets, the number of used and total secret slots is listed as well. Only available on
+    /// s390x.
     List(ListSecretOpt),
 
     /// Verify that an add-secret request is sane.
@@ -402,6 +641,21 @@ pub enum Command {
     /// provided key. Outputs the arbitrary user-data.
     Verify(VerifyOpt),
 
+    /// Audit add-secret requests against a set of host-key documents.
+    ///
+    /// Reports for each add-secret request in a directory whether it was created for at least one
+    /// of the given host-key documents. Requests created for none of them are stale and must be
+    /// regenerated, e.g., after a host-key rotation. Unreadable or corrupt files are reported and
+    /// do not stop the audit.
+    Audit(AuditOpt),
+
+    /// Report the validity of host-key documents.
+    ///
+    /// Verifies the host-key documents and reports the validity windows of the host-key documents,
+    /// the certificates of their chain of trust, and the CRLs used for the verification. Also
+    /// reports which of them expires first and when.
+    HkdInfo(HkdInfoOpt),
+
     /// Retrieve a secret from the UV secret store (s390x only).
     #[command(visible_alias = "retr")]
     Retrieve(RetrSecretOptions),
@@ -414,12 +668,23 @@ pub enum Command {
 /// Additional checks to assure, option integrity
 pub fn validate_cli(cli: &CliOptions) -> Result<(), clap::Error> {
     if let Command::Create(opt) = &cli.cmd {
-        if let AddSecretType::Association {
+        match (&opt.secret, &opt.batch) {
+            (None, None) => {
+                return Err(CliOptions::command()
+                    .error(MissingSubcommand, "Specify either a COMMAND or '--batch'."))
+            }
+            (Some(_), Some(_)) => {
+                return Err(CliOptions::command()
+                    .error(ArgumentConflict, "'--batch' cannot be used with a COMMAND."))
+            }
+            _ => (),
+        }
+        if let Some(AddSecretType::Association {
             name,
             stdout,
             input_secret: _,
             output_secret: secret_out,
-        } = &opt.secret
+        }) = &opt.secret
         {
             if *stdout {
                 return Ok(());
@@ -459,6 +724,10 @@ mod test {
             vec!["pvsecret", "list"],
             #[cfg(target_arch = "s390x")]
             vec!["pvsecret", "add", "abc"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "add", "abc", "--warn-at", "90%"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "add", "abc", "--warn-at", "50"],
             #[cfg(not(target_arch = "s390x"))]
             vec!["pvsecret", "add"],
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "abc", "--no-verify", "meta"],
@@ -470,17 +739,37 @@ mod test {
                 "--root-ca", "tttt", "--cck", "cck", "--cuid-hex", "0x11223344556677889900aabbccddeeff", "--pcf", "0x123", "association", "name", "--stdout",
                 "--output-secret", "secret"],
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "abc", "--no-verify", "association", "name", "--output-secret", "secret"],
+            vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "dir", "--no-verify", "--batch", "batch.yaml", "--atomic"],
+            vec!["pvsecret", "verify", "abc", "-k", "abc", "--host-key-document", "def,ghi"],
+            vec!["pvsecret", "audit", "--requests", "dir", "-k", "abc,def", "--no-verify"],
+            vec!["pvsecret", "audit", "--requests", "dir", "-k", "abc", "-C", "ibm", "--stale-json", "stale"],
+            vec!["pvsecret", "hkd-info", "abc", "def", "--no-verify"],
+            vec!["pvsecret", "hkd-info", "abc", "-C", "ibm", "--crl", "crl", "--warn-expiry", "30", "--format", "json"],
             #[cfg(target_arch = "s390x")]
             vec!["pvsecret", "list", "--format", "human"],
             #[cfg(target_arch = "s390x")]
             vec!["pvsecret", "list", "--format", "yaml"],
             #[cfg(target_arch = "s390x")]
             vec!["pvsecret", "list", "--format", "bin"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "list", "--format", "json", "--type", "aes-256-key", "--id", "0x1a2B"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "list", "--type", "0x30"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "retrieve", "abc", "--output-format", "hex"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "retr", "abc", "--outform", "bin", "--force"],
         ];
         // Test for the minimal amount of flags to yield an invalid combination
         let invalid_args = [
             vec!["pvsecret"],
             vec!["pvsecret", "list", "--yaml", "--bin"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "add", "abc", "--warn-at", "101%"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "list", "--id", "0xabcg"],
+            #[cfg(target_arch = "s390x")]
+            vec!["pvsecret", "retrieve", "abc", "--output-format", "der"],
             vec!["pvsecret", "create", "--hdr", "abc", "-o", "abc", "--no-verify" ,"null"],
             vec!["pvsecret", "create", "-k", "abc", "-o", "abc", "--no-verify", "null"],
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "--no-verify", "null"],
@@ -490,6 +779,13 @@ mod test {
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "abc", "--no-verify", "--cuid", "abc", "--cuid_hex", "9",  "null"],
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "abc", "--no-verify", "association"],
             vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "abc", "--no-verify", "association", "name", "--output-secret", "secret", "--input-secret", "secret"],
+            vec!["pvsecret", "create", "-k", "abc", "--hdr", "abc", "-o", "dir", "--no-verify", "--atomic", "meta"],
+            vec!["pvsecret", "audit", "-k", "abc", "--no-verify"],
+            vec!["pvsecret", "audit", "--requests", "dir", "--no-verify"],
+            vec!["pvsecret", "audit", "--requests", "dir", "-k", "abc"],
+            vec!["pvsecret", "hkd-info", "--no-verify"],
+            vec!["pvsecret", "hkd-info", "abc"],
+            vec!["pvsecret", "hkd-info", "abc", "--no-verify", "--warn-expiry", "-1"],
             ];
         for arg in valid_args {
             let res = CliOptions::try_parse_from(&arg);
@@ -506,6 +802,26 @@ mod test {
         }
     }
 
+    #[test]
+    fn validate_batch() {
+        let validate = |args: &[&str]| validate_cli(&CliOptions::try_parse_from(args).unwrap());
+        let create = [
+            "pvsecret",
+            "create",
+            "-k",
+            "abc",
+            "--hdr",
+            "abc",
+            "-o",
+            "dir",
+            "--no-verify",
+        ];
+        assert!(validate(&[&create[..], &["--batch", "batch.yaml"]].concat()).is_ok());
+        assert!(validate(&[&create[..], &["meta"]].concat()).is_ok());
+        assert!(validate(&create).is_err());
+        assert!(validate(&[&create[..], &["--batch", "batch.yaml", "meta"]].concat()).is_err());
+    }
+
     #[test]
     fn verify_cli() {
         use clap::CommandFactory;
@@ -8,9 +8,15 @@ pub use create::create;
 mod verify;
 pub use verify::verify;
 
-pub const CMD_FN: &[&str] = &["+create", "+verify"];
+mod audit;
+pub use audit::audit;
 
-#[cfg(target_arch = "s390x")]
+mod hkd_info;
+pub use hkd_info::hkd_info;
+
+pub const CMD_FN: &[&str] = &["+create", "+verify", "+audit", "+hkd-info"];
+
+#[cfg(any(test, target_arch = "s390x"))]
 mod add;
 #[cfg(target_arch = "s390x")]
 mod list;
@@ -32,7 +38,7 @@ mod uv_cmd {
 
 #[cfg(not(target_arch = "s390x"))]
 mod uv_cmd {
-    use crate::cli::{AddSecretOpt, ListSecretOpt, RetrSecretOptions};
+    use crate::cli::{AddSecretOpt, ListSecretOpt, LockSecretOpt, RetrSecretOptions};
     use anyhow::{bail, Result};
     macro_rules! not_supp {
         ($name: ident $( ,$opt: ty )?) => {
@@ -44,7 +50,7 @@ mod uv_cmd {
     not_supp!(add, AddSecretOpt);
     not_supp!(list, ListSecretOpt);
     not_supp!(retr, RetrSecretOptions);
-    not_supp!(lock);
+    not_supp!(lock, LockSecretOpt);
     pub const UV_CMD_FN: &[&str] = &[];
 }
 pub use uv_cmd::*;
@@ -2,18 +2,220 @@
 //
 // Copyright IBM Corp. 2023
 
-use crate::cli::AddSecretOpt;
-use anyhow::{Context, Result};
-use log::warn;
-use pv::uv::{AddCmd, UvDevice};
-use utils::get_reader_from_cli_file_arg;
+use std::path::Path;
 
-/// Do an Add Secret UVC
-pub fn add(opt: &AddSecretOpt) -> Result<()> {
-    let mut rd_in = get_reader_from_cli_file_arg(&opt.input)?;
-    let mut cmd =
-        AddCmd::new(&mut rd_in).context(format!("Processing input file {}", opt.input))?;
-    UvDevice::open()?.send_cmd(&mut cmd)?;
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use pv::{
+    attest::SecretStoreState,
+    misc::read_file_limited,
+    secret::AddSecretReceipt,
+    uv::{AddCmd, SecretList, SecretStoreUsage, UvCmdExecutor, UvInfo},
+    PvCoreError,
+};
+use utils::{atomic_write, canonical_json};
+
+/// Check that the secret store has a free slot and warn if it is filled above `warn_at` percent
+/// after the add
+fn check_store_usage<U: UvCmdExecutor>(uv: &U, info: &UvInfo, warn_at: u8) -> Result<()> {
+    let Some(max) = info.max_secrets() else {
+        debug!("The Ultravisor does not report the maximum number of secrets");
+        return Ok(());
+    };
+    let used = SecretList::from_uv(uv)?.total_num_secrets();
+    let usage = SecretStoreUsage::new(used, max)
+        .ok_or(PvCoreError::SecretStoreFull(max))?
+        .add_one()?;
+    if usage.exceeds(warn_at) {
+        warn!("The secret store is filled to at least {warn_at}% after adding the secret. {usage}");
+    }
+    Ok(())
+}
+
+/// Secret store state before the add and the previous receipt (as read from disk)
+///
+/// The state is taken from `previous` or is the empty secret store. It must contain as many
+/// secrets as the Ultravisor lists, otherwise the receipt would not match the secret store.
+fn receipt_state<U: UvCmdExecutor>(
+    uv: &U,
+    previous: Option<&Path>,
+) -> Result<(SecretStoreState, Option<Vec<u8>>)> {
+    let (state, previous) = match previous {
+        Some(path) => {
+            let ctx = || format!("'{}' is no valid add-secret receipt", path.display());
+            let data = read_file_limited(path, "previous receipt", AddSecretReceipt::MAX_SIZE)?;
+            let receipt: AddSecretReceipt = serde_json::from_slice(&data).with_context(ctx)?;
+            let state = receipt.verify().with_context(ctx)?;
+            (state, Some(data))
+        }
+        None => (SecretStoreState::new(), None),
+    };
+    let num_secrets = SecretList::from_uv(uv)?.total_num_secrets();
+    if num_secrets != state.tags().len() {
+        bail!(
+            "The secret store contains {num_secrets} secret(s), but the receipt state contains {} secret(s). Specify the receipt of the last add-secret request with --previous-receipt",
+            state.tags().len()
+        );
+    }
+    Ok((state, previous))
+}
+
+/// Send the add-secret request `asrcb` with the command `cmd` to the Ultravisor `uv` described by
+/// `info`
+///
+/// Writes a receipt to `receipt`, if given, that refers to `previous_receipt`.
+fn add_secret<U: UvCmdExecutor>(
+    uv: &U,
+    info: &UvInfo,
+    mut cmd: AddCmd,
+    asrcb: &[u8],
+    warn_at: u8,
+    receipt: Option<&Path>,
+    previous_receipt: Option<&Path>,
+) -> Result<()> {
+    if !info.supports_add_secret() {
+        bail!("This machine's Ultravisor does not support adding secrets");
+    }
+    check_store_usage(uv, info, warn_at)?;
+    let receipt_state = match receipt {
+        Some(_) => Some(receipt_state(uv, previous_receipt)?),
+        None => None,
+    };
+    let rc = uv.add_secret(&mut cmd)?;
     warn!("Successfully added the secret");
+
+    if let (Some(path), Some((state, previous))) = (receipt, receipt_state) {
+        let ctx = || {
+            format!(
+                "The secret was added, but the receipt '{}' could not be written",
+                path.display()
+            )
+        };
+        let receipt = AddSecretReceipt::new(&state, asrcb, rc as u16, previous.as_deref())
+            .with_context(ctx)?;
+        let data = canonical_json::to_vec(&receipt).with_context(ctx)?;
+        atomic_write(path, &data, 0o644).with_context(ctx)?;
+    }
     Ok(())
 }
+
+/// Do an Add Secret UVC
+#[cfg(target_arch = "s390x")]
+pub fn add(opt: &crate::cli::AddSecretOpt) -> Result<()> {
+    use std::io::Read;
+
+    let mut rd_in = utils::get_reader_from_cli_file_arg(&opt.input)?;
+    let mut asrcb = Vec::new();
+    rd_in
+        .read_to_end(&mut asrcb)
+        .context(format!("Processing input file {}", opt.input))?;
+    let cmd = AddCmd::new(&mut asrcb.as_slice())
+        .context(format!("Processing input file {}", opt.input))?;
+    let uv = opt.device.open()?;
+    add_secret(
+        &uv,
+        &UvInfo::query(&uv)?,
+        cmd,
+        &asrcb,
+        opt.warn_at,
+        opt.receipt.as_deref(),
+        opt.previous_receipt.as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use pv::uv::{MockCmd, MockResponse, MockUvDevice};
+    use utils::TemporaryDirectory;
+
+    use super::*;
+
+    const ASRCB: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../pv/tests/assets/exp/asrcb/null_none_default_cuid_one"
+    );
+    const UV_QUERY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../pv_core/tests/assets/uv_query"
+    );
+
+    fn info(machine: &str) -> UvInfo {
+        UvInfo::from_sysfs_dir(format!("{UV_QUERY}/{machine}")).unwrap()
+    }
+
+    fn run(uv: &MockUvDevice, machine: &str, receipt: Option<&Path>) -> Result<Vec<u8>> {
+        let asrcb = std::fs::read(ASRCB).unwrap();
+        let cmd = AddCmd::new(&mut asrcb.as_slice()).unwrap();
+        add_secret(uv, &info(machine), cmd, &asrcb, 80, receipt, None)?;
+        Ok(asrcb)
+    }
+
+    #[test]
+    fn add() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let receipt = dir.path().join("receipt");
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::SecretList(SecretList::new(0, vec![])))
+            .respond(MockResponse::SecretList(SecretList::new(0, vec![])))
+            .respond(MockResponse::Success);
+
+        let asrcb = run(&uv, "z16", Some(&receipt)).unwrap();
+        assert_eq!(
+            uv.issued(),
+            [
+                MockCmd::ListSecrets,
+                MockCmd::ListSecrets,
+                MockCmd::AddSecret(asrcb)
+            ]
+        );
+        let receipt: AddSecretReceipt =
+            serde_json::from_slice(&std::fs::read(&receipt).unwrap()).unwrap();
+        assert_eq!(receipt.verify().unwrap().tags().len(), 1);
+    }
+
+    #[test]
+    fn add_unsupported() {
+        let uv = MockUvDevice::new();
+        let err = run(&uv, "z15", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This machine's Ultravisor does not support adding secrets"
+        );
+        assert!(uv.issued().is_empty());
+    }
+
+    #[test]
+    fn add_store_full() {
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::SecretList(SecretList::new(510, vec![])));
+        assert!(run(&uv, "z16", None).is_err());
+        assert_eq!(uv.issued(), [MockCmd::ListSecrets]);
+    }
+
+    #[test]
+    fn add_uv_error() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let receipt = dir.path().join("receipt");
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::SecretList(SecretList::new(0, vec![])))
+            .respond(MockResponse::SecretList(SecretList::new(0, vec![])))
+            .respond(MockResponse::Error { rc: 0x0102, rrc: 0 });
+
+        let err = run(&uv, "z16", Some(&receipt)).unwrap_err();
+        assert!(err.to_string().contains("secret store locked"), "{err}");
+        assert_eq!(uv.issued().len(), 3);
+        assert!(!receipt.exists());
+    }
+
+    #[test]
+    fn add_receipt_mismatch() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let uv = MockUvDevice::new();
+        uv.respond(MockResponse::SecretList(SecretList::new(1, vec![])))
+            .respond(MockResponse::SecretList(SecretList::new(1, vec![])));
+
+        let err = run(&uv, "z16", Some(&dir.path().join("receipt"))).unwrap_err();
+        assert!(err.to_string().contains("--previous-receipt"), "{err}");
+        assert_eq!(uv.issued(), [MockCmd::ListSecrets, MockCmd::ListSecrets]);
+    }
+}
@@ -0,0 +1,327 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use pv::{misc::read_file_limited, request::Keyslot, secret::AddSecretRequest, uv::AddCmd};
+use utils::{get_writer_from_cli_file_arg, sorted_dir_files, FileOrder};
+
+use crate::cli::AuditOpt;
+
+/// SHA-256 hash of a public host-key as it is stored in a keyslot
+type HostKeyHash = [u8; 32];
+
+/// Audit result of a single add-secret request
+#[derive(Debug, PartialEq, Eq)]
+enum AuditStatus {
+    /// The request was created for these host-key documents
+    Usable(Vec<PathBuf>),
+    /// The request was created for none of the host-key documents
+    Stale,
+    /// The file could not be audited
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct AuditEntry {
+    path: PathBuf,
+    status: AuditStatus,
+}
+
+impl Display for AuditEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.display();
+        match &self.status {
+            AuditStatus::Usable(hkds) => {
+                let hkds: Vec<_> = hkds.iter().map(|h| format!("'{}'", h.display())).collect();
+                write!(f, "usable: '{path}' (host-key {})", hkds.join(", "))
+            }
+            AuditStatus::Stale => write!(f, "stale:  '{path}'"),
+            AuditStatus::Failed(msg) => write!(f, "error:  '{path}': {msg}"),
+        }
+    }
+}
+
+fn audit_request(path: &Path, hkds: &[(PathBuf, HostKeyHash)]) -> Result<AuditStatus> {
+    let asrcb = read_file_limited(path, "add-secret request", AddCmd::MAX_SIZE)?;
+    let hashes = AddSecretRequest::bin_host_key_hashes(&asrcb)
+        .context("The file is no valid add-secret request")?;
+    let matching: Vec<_> = hkds
+        .iter()
+        .filter(|(_, hash)| hashes.contains(hash))
+        .map(|(hkd, _)| hkd.to_owned())
+        .collect();
+    match matching.is_empty() {
+        true => Ok(AuditStatus::Stale),
+        false => Ok(AuditStatus::Usable(matching)),
+    }
+}
+
+/// Audit all add-secret requests in `dir`
+///
+/// Errors of individual requests are part of the result. Only an unreadable `dir` is an error.
+fn audit_dir(dir: &Path, hkds: &[(PathBuf, HostKeyHash)]) -> Result<Vec<AuditEntry>> {
+    let requests = sorted_dir_files(dir, FileOrder::Name).with_context(|| {
+        format!(
+            "Cannot read the add-secret request directory '{}'",
+            dir.display()
+        )
+    })?;
+    Ok(requests
+        .into_iter()
+        .map(|path| {
+            let status = audit_request(&path, hkds)
+                .unwrap_or_else(|e| AuditStatus::Failed(format!("{e:#}")));
+            AuditEntry { path, status }
+        })
+        .collect())
+}
+
+/// Write the paths of all stale requests as JSON list
+fn write_stale_json<W: Write>(entries: &[AuditEntry], mut wr: W) -> Result<()> {
+    let stale: Vec<_> = entries
+        .iter()
+        .filter(|e| e.status == AuditStatus::Stale)
+        .map(|e| e.path.display().to_string())
+        .collect();
+    serde_json::to_writer_pretty(&mut wr, &stale)?;
+    writeln!(wr)?;
+    Ok(())
+}
+
+/// Audit add-secret requests against a set of host-key documents
+pub fn audit(opt: &AuditOpt) -> Result<()> {
+    let keys = opt.certificate_args.get_verified_hkds("secret")?;
+    let hkds = opt
+        .certificate_args
+        .host_key_documents
+        .iter()
+        .zip(keys.iter())
+        .map(|(hkd, key)| Ok((hkd.to_owned(), Keyslot::host_key_hash(key.as_ref())?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let entries = audit_dir(Path::new(&opt.requests), &hkds)?;
+    entries.iter().for_each(|entry| println!("{entry}"));
+
+    let count = |f: fn(&AuditStatus) -> bool| entries.iter().filter(|e| f(&e.status)).count();
+    let stale = count(|s| matches!(s, AuditStatus::Stale));
+    let failed = count(|s| matches!(s, AuditStatus::Failed(_)));
+    println!(
+        "{} add-secret request(s): {} usable, {stale} stale, {failed} could not be audited",
+        entries.len(),
+        entries.len() - stale - failed,
+    );
+    if entries.is_empty() {
+        warn!("No add-secret requests found in '{}'", opt.requests);
+    }
+
+    if let Some(path) = &opt.stale_json {
+        write_stale_json(&entries, get_writer_from_cli_file_arg(path)?)
+            .with_context(|| format!("Cannot write the stale add-secret requests to '{path}'"))?;
+        info!("Wrote the stale add-secret requests to '{path}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use clap::Parser;
+    use pv::{
+        misc::read_certs,
+        request::{
+            openssl::pkey::{PKey, Public},
+            BootHdrTags, ReqEncrCtx, Request, SymKeyType,
+        },
+        secret::{AddSecretFlags, AddSecretVersion, GuestSecret},
+    };
+    use utils::TemporaryDirectory;
+
+    use super::*;
+
+    const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/cert");
+    const TAGS: BootHdrTags = BootHdrTags::new([1; 64], [2; 64], [3; 64], [4; 16]);
+
+    fn hkd(name: &str) -> (PathBuf, PKey<Public>) {
+        let path = PathBuf::from(format!("{CERT_DIR}/{name}"));
+        let key = read_certs(fs::read(&path).unwrap()).unwrap()[0]
+            .public_key()
+            .unwrap();
+        (path, key)
+    }
+
+    fn write_asrcb(dir: &TemporaryDirectory, name: &str, host_keys: &[&PKey<Public>]) {
+        let mut asrcb = AddSecretRequest::new(
+            AddSecretVersion::One,
+            GuestSecret::Null,
+            TAGS,
+            AddSecretFlags::default(),
+        );
+        host_keys
+            .iter()
+            .for_each(|k| asrcb.add_hostkey((*k).to_owned()));
+        let ctx = ReqEncrCtx::random(SymKeyType::Aes256Gcm).unwrap();
+        fs::write(dir.path().join(name), asrcb.encrypt(&ctx).unwrap()).unwrap();
+    }
+
+    /// Requests for the key sets {host} and {host2}, one for both, and invalid files
+    fn fixtures() -> TemporaryDirectory {
+        let (_, host) = hkd("host.crt");
+        let (_, host2) = hkd("host2.crt");
+        let dir = TemporaryDirectory::new().unwrap();
+        write_asrcb(&dir, "a_host", &[&host]);
+        write_asrcb(&dir, "b_host2", &[&host2]);
+        write_asrcb(&dir, "c_both", &[&host2, &host]);
+        write_asrcb(&dir, "d_host2", &[&host2]);
+        fs::write(dir.path().join("e_corrupt"), b"asrcbM no request").unwrap();
+        fs::create_dir(dir.path().join("f_subdir")).unwrap();
+        dir
+    }
+
+    fn statuses(entries: &[AuditEntry]) -> Vec<(String, &AuditStatus)> {
+        entries
+            .iter()
+            .map(|e| {
+                let name = e.path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, &e.status)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn audit_key_sets() {
+        let dir = fixtures();
+        let (host_path, host) = hkd("host.crt");
+        let (host2_path, host2) = hkd("host2.crt");
+        let hkds = [(
+            host_path.clone(),
+            Keyslot::host_key_hash(host.as_ref()).unwrap(),
+        )];
+
+        let entries = audit_dir(dir.path(), &hkds).unwrap();
+        let res = statuses(&entries);
+        assert_eq!(res.len(), 5);
+        assert_eq!(
+            res[..4],
+            [
+                (
+                    "a_host".into(),
+                    &AuditStatus::Usable(vec![host_path.clone()])
+                ),
+                ("b_host2".into(), &AuditStatus::Stale),
+                (
+                    "c_both".into(),
+                    &AuditStatus::Usable(vec![host_path.clone()])
+                ),
+                ("d_host2".into(), &AuditStatus::Stale),
+            ]
+        );
+        assert_eq!(res[4].0, "e_corrupt");
+        assert!(matches!(res[4].1, AuditStatus::Failed(_)));
+
+        let hkds = [
+            (
+                host_path.clone(),
+                Keyslot::host_key_hash(host.as_ref()).unwrap(),
+            ),
+            (
+                host2_path.clone(),
+                Keyslot::host_key_hash(host2.as_ref()).unwrap(),
+            ),
+        ];
+        let entries = audit_dir(dir.path(), &hkds).unwrap();
+        let res = statuses(&entries);
+        assert_eq!(
+            res[..4],
+            [
+                (
+                    "a_host".into(),
+                    &AuditStatus::Usable(vec![host_path.clone()])
+                ),
+                (
+                    "b_host2".into(),
+                    &AuditStatus::Usable(vec![host2_path.clone()])
+                ),
+                (
+                    "c_both".into(),
+                    &AuditStatus::Usable(vec![host_path, host2_path.clone()])
+                ),
+                ("d_host2".into(), &AuditStatus::Usable(vec![host2_path])),
+            ]
+        );
+    }
+
+    #[test]
+    fn audit_report() {
+        let dir = fixtures();
+        let (host_path, host) = hkd("host.crt");
+        let hkds = [(
+            host_path.clone(),
+            Keyslot::host_key_hash(host.as_ref()).unwrap(),
+        )];
+        let entries = audit_dir(dir.path(), &hkds).unwrap();
+
+        let path = |name: &str| dir.path().join(name).display().to_string();
+        assert_eq!(
+            entries[0].to_string(),
+            format!(
+                "usable: '{}' (host-key '{}')",
+                path("a_host"),
+                host_path.display()
+            )
+        );
+        assert_eq!(
+            entries[1].to_string(),
+            format!("stale:  '{}'", path("b_host2"))
+        );
+        assert!(entries[4]
+            .to_string()
+            .starts_with(&format!("error:  '{}': ", path("e_corrupt"))));
+
+        let mut json = Vec::new();
+        write_stale_json(&entries, &mut json).unwrap();
+        let stale: Vec<String> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(stale, [path("b_host2"), path("d_host2")]);
+    }
+
+    #[test]
+    fn audit_cmd() {
+        let dir = fixtures();
+        let json = dir.path().join("f_subdir/stale.json");
+        let opt = crate::cli::CliOptions::try_parse_from([
+            "pvsecret",
+            "audit",
+            "--requests",
+            dir.path().to_str().unwrap(),
+            "-k",
+            &format!("{CERT_DIR}/host2.crt"),
+            "--no-verify",
+            "--stale-json",
+            json.to_str().unwrap(),
+        ])
+        .unwrap();
+        match opt.cmd {
+            crate::cli::Command::Audit(opt) => audit(&opt).unwrap(),
+            cmd => panic!("Unexpected command {cmd:?}"),
+        }
+        let stale: Vec<String> = serde_json::from_slice(&fs::read(json).unwrap()).unwrap();
+        assert_eq!(stale, [dir.path().join("a_host").display().to_string()]);
+    }
+
+    #[test]
+    fn audit_missing_dir() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let err = audit_dir(&dir.path().join("missing"), &[]).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot read the add-secret request directory"));
+    }
+}
@@ -2,27 +2,33 @@
 //
 // Copyright IBM Corp. 2023, 2024
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context, Error, Result};
 use log::{debug, info, trace, warn};
 use pv::{
     misc::{
-        decode_hex, open_file, pv_guest_bit_set, read_exact_file, read_file, try_parse_u128,
-        try_parse_u64, write,
+        decode_hex, open_file, pv_guest_bit_set, read_confidential_file, read_exact_file,
+        read_file, try_parse_u128, try_parse_u64, write,
     },
     request::{
         openssl::pkey::{PKey, Private},
-        BootHdrTags, ReqEncrCtx, Request, SymKeyType,
+        BootHdrTags, ReqEncrCtx, Request, Summary, SymKeyType,
+    },
+    secret::{
+        AddSecretContext, AddSecretFlags, AddSecretRequest, AddSecretVersion, ExtSecret,
+        GuestSecret,
     },
-    secret::{AddSecretFlags, AddSecretRequest, AddSecretVersion, ExtSecret, GuestSecret},
     uv::ConfigUid,
 };
 use serde_yaml::Value;
-use utils::get_writer_from_cli_file_arg;
+use utils::{get_writer_from_cli_file_arg, read_text};
 
 use crate::cli::{AddSecretType, CreateSecretFlags, CreateSecretOpt, RetrieveableSecretInpKind};
 
+mod batch;
+mod metadata;
+
 fn write_out<P, D>(path: &P, data: D, ctx: &str) -> pv::Result<()>
 where
     P: AsRef<Path>,
@@ -34,7 +40,7 @@ where
 }
 
 fn retrievable(name: &str, secret: &str, kind: &RetrieveableSecretInpKind) -> Result<GuestSecret> {
-    let secret_data = read_file(secret, &format!("retrievable {kind}"))?.into();
+    let secret_data = read_confidential_file(secret, &format!("retrievable {kind}"), None)?;
 
     match kind {
         RetrieveableSecretInpKind::Plain => GuestSecret::plaintext(name, secret_data),
@@ -61,26 +67,44 @@ pub fn create(opt: &CreateSecretOpt) -> Result<()> {
         }
     }
 
-    let mut asrcb = build_asrcb(opt)?;
-    debug!("Generated Add-secret request");
+    let mut ctx = build_ctx(opt)?;
+    debug!("Generated Add-secret context");
 
     // Add host-key documents
     opt.certificate_args
         .get_verified_hkds("secret")?
         .into_iter()
-        .for_each(|k| asrcb.add_hostkey(k));
-
+        .for_each(|k| ctx.add_hostkey(k));
     debug!("Added all host-keys");
 
-    // build + encrypt the request
-    let rq =
-        ReqEncrCtx::random(SymKeyType::Aes256Gcm).context("Failed to generate random input")?;
-    let ser_asrbc = asrcb.encrypt(&rq)?;
+    let user_data = read_user_data(opt)?;
+    match (&opt.secret, &opt.batch) {
+        (Some(secret), _) => create_single(opt, &ctx, &user_data, secret),
+        (None, Some(batch)) => batch::create_batch(opt, &ctx, &user_data, batch),
+        // checked by the CLI validation
+        (None, None) => bail!("No secret specified"),
+    }
+}
+
+fn create_single(
+    opt: &CreateSecretOpt,
+    ctx: &AddSecretContext,
+    user_data: &UserData,
+    secret: &AddSecretType,
+) -> Result<()> {
+    let asrcb = build_asrcb(
+        ctx,
+        build_secret(secret, opt.use_name)?,
+        flags(opt, &[])?,
+        user_data,
+    )?;
+    let ser_asrbc = encrypt(&asrcb)?;
     warn!("Successfully generated the request");
-    write_out(&opt.output, ser_asrbc, "add-secret request")?;
+    write_out(&opt.output, &ser_asrbc, "add-secret request")?;
     info!("Successfully wrote the request to '{}'", &opt.output);
+    metadata::write_metadata(opt, &ser_asrbc, secret.name())?;
 
-    write_secret(&opt.secret, asrcb.guest_secret(), &opt.output)
+    write_secret(secret, asrcb.guest_secret(), &opt.output)
 }
 
 /// Read+parse the first key from the buffer.
@@ -90,11 +114,34 @@ fn read_private_key(buf: &[u8]) -> Result<PKey<Private>> {
         .map_err(Error::new)
 }
 
-/// Set-up the `add-secret request` from command-line arguments
-fn build_asrcb(opt: &CreateSecretOpt) -> Result<AddSecretRequest> {
-    debug!("Build add-secret request");
+/// Set-up the settings shared by all requests from command-line arguments
+fn build_ctx(opt: &CreateSecretOpt) -> Result<AddSecretContext> {
+    debug!("Build add-secret context");
+
+    let mut se_hdr = open_file(&opt.hdr)?;
+    let mut ctx = AddSecretContext::new(
+        AddSecretVersion::One,
+        BootHdrTags::from_se_image(&mut se_hdr)
+            .with_context(|| format!("Provided SE-header in '{}' is malformed", &opt.hdr))?,
+    );
 
-    let mut secret = match &opt.secret {
+    // Set CUID
+    read_cuid(&mut ctx, opt)?;
+
+    // Set extension secret
+    if let Some(path) = &opt.extension_secret {
+        ctx.set_ext_secret(ExtSecret::Simple(
+            read_exact_file(path, "extension secret")?.into(),
+        ))?;
+    } else if let Some(path) = &opt.cck {
+        ctx.set_ext_secret(ExtSecret::Derived(read_exact_file(path, "CCK")?.into()))?;
+    }
+    Ok(ctx)
+}
+
+/// Set-up the guest secret from command-line arguments
+fn build_secret(secret: &AddSecretType, use_name: bool) -> Result<GuestSecret> {
+    let mut secret = match secret {
         AddSecretType::Meta => GuestSecret::Null,
         AddSecretType::Association {
             name,
@@ -110,41 +157,30 @@ fn build_asrcb(opt: &CreateSecretOpt) -> Result<AddSecretRequest> {
             name, secret, kind, ..
         } => retrievable(name, secret, kind)?,
     };
-    trace!("AddSecret: {secret:x?}");
+    trace!("AddSecret: {}", secret.summary());
 
-    opt.use_name.then(|| secret.no_hash_name());
+    use_name.then(|| secret.no_hash_name());
+    Ok(secret)
+}
 
+/// Add-secret request flags from the command line and `extra` flags
+fn flags(opt: &CreateSecretOpt, extra: &[CreateSecretFlags]) -> Result<AddSecretFlags> {
     let mut flags = match &opt.pcf {
         Some(v) => (&try_parse_u64(v, "pcf")?).into(),
         None => AddSecretFlags::default(),
     };
-    opt.flags.iter().for_each(|v| match v {
+    opt.flags.iter().chain(extra).for_each(|v| match v {
         CreateSecretFlags::DisableDump => flags.set_disable_dump(),
     });
     debug!("FLAGS: {flags:x?}");
+    Ok(flags)
+}
 
-    let mut se_hdr = open_file(&opt.hdr)?;
-    let mut asrcb = AddSecretRequest::new(
-        AddSecretVersion::One,
-        secret,
-        BootHdrTags::from_se_image(&mut se_hdr)
-            .with_context(|| format!("Provided SE-header in '{}' is malformed", &opt.hdr))?,
-        flags,
-    );
-
-    // Set CUID
-    read_cuid(&mut asrcb, opt)?;
-
-    // Set extension secret
-    if let Some(path) = &opt.extension_secret {
-        asrcb.set_ext_secret(ExtSecret::Simple(
-            read_exact_file(path, "extension secret")?.into(),
-        ))?;
-    } else if let Some(path) = &opt.cck {
-        asrcb.set_ext_secret(ExtSecret::Derived(read_exact_file(path, "CCK")?.into()))?;
-    }
+/// User data and the optional user-signing key
+type UserData = Option<(Vec<u8>, Option<PKey<Private>>)>;
 
-    // add user data
+/// Read the user data and the user-signing key from command-line arguments
+fn read_user_data(opt: &CreateSecretOpt) -> Result<UserData> {
     let user_data = opt
         .user_data
         .as_ref()
@@ -157,19 +193,43 @@ fn build_asrcb(opt: &CreateSecretOpt) -> Result<AddSecretRequest> {
     let user_key = opt
         .user_sign_key
         .as_ref()
-        .map(|p| read_file(p, "User-signing key"))
+        .map(|p| read_confidential_file(p, "User-signing key", None))
         .transpose()?
         .map(|buf| {
-            read_private_key(&buf).context("Cannot read {secret} as private key from PEM or DER")
+            read_private_key(buf.value())
+                .context("Cannot read {secret} as private key from PEM or DER")
         })
         .transpose()?;
 
-    if user_data.is_some() || user_key.is_some() {
-        asrcb.set_user_data(user_data.unwrap_or_default(), user_key)?;
+    Ok(match (user_data, user_key) {
+        (None, None) => None,
+        (user_data, user_key) => Some((user_data.unwrap_or_default(), user_key)),
+    })
+}
+
+/// Set-up the `add-secret request` for `secret`
+fn build_asrcb(
+    ctx: &AddSecretContext,
+    secret: GuestSecret,
+    flags: AddSecretFlags,
+    user_data: &UserData,
+) -> Result<AddSecretRequest> {
+    debug!("Build add-secret request");
+    let mut asrcb = ctx.request(secret, flags);
+    if let Some((data, key)) = user_data {
+        asrcb.set_user_data(data.clone(), key.clone())?;
     }
+    debug!("{}", asrcb.summary());
     Ok(asrcb)
 }
 
+/// Encrypt the request with a fresh random context
+fn encrypt(asrcb: &AddSecretRequest) -> Result<Vec<u8>> {
+    let rq =
+        ReqEncrCtx::random(SymKeyType::Aes256Gcm).context("Failed to generate random input")?;
+    asrcb.encrypt(&rq).map_err(Error::from)
+}
+
 // Try to extract a Config-UId from a yaml structure
 // The cuid field can be embedded in an abritray amount of Mappings
 // The function takes the first cuid it founds (width search).
@@ -199,38 +259,61 @@ fn try_from_val(val: Value) -> Result<ConfigUid> {
         _ => None,
     }
     .ok_or(anyhow!("No 'cuid' entry found"))?;
-    let cuid = cuid
+    let hex = cuid
         .strip_prefix("0x")
-        .ok_or(anyhow!("CUID value starts not with 0x".to_string()))?
-        .to_owned();
-    if cuid.len() != ::std::mem::size_of::<ConfigUid>() * 2 {
-        return Err(anyhow!(format!("len invalid ({})", cuid.len())));
+        .ok_or(anyhow!("The CUID value {cuid:?} does not start with 0x"))?;
+    if hex.len() != ::std::mem::size_of::<ConfigUid>() * 2 {
+        bail!(
+            "The CUID value {cuid:?} has an invalid length ({})",
+            hex.len()
+        );
     }
-    let cuid: ConfigUid = decode_hex(&cuid)?
+    let cuid: ConfigUid = decode_hex(hex)
+        .with_context(|| format!("Cannot parse the CUID value {cuid:?}"))?
         .try_into()
         .map_err(|_| anyhow!("Cannot parse hex number".to_string()))?;
     Ok(cuid)
 }
 
-fn read_cuid(asrcb: &mut AddSecretRequest, opt: &CreateSecretOpt) -> Result<()> {
+fn read_cuid(ctx: &mut AddSecretContext, opt: &CreateSecretOpt) -> Result<()> {
     if let Some(path) = &opt.cuid {
         let cuid = match read_exact_file(path, "The CUID-file") {
             Ok(v) => v,
             Err(_) => {
                 let buf = read_file(path, "The CUID-file")?;
-                let val: Value = serde_yaml::from_slice(&buf).context(
+                let text = read_text(buf.as_slice(), "CUID-file")?;
+                let val: Value = serde_yaml::from_str(&text).context(
                     "The CUID-file does not contain a 128bit value or a yaml with a 'cuid' field",
                 )?;
                 try_from_val(val)?
             }
         };
-        asrcb.set_cuid(cuid);
+        ctx.set_cuid(cuid);
     } else if let Some(v) = &opt.cuid_hex {
-        asrcb.set_cuid(try_parse_u128(v, "CUID")?);
+        ctx.set_cuid(try_parse_u128(v, "CUID")?);
     }
     Ok(())
 }
 
+/// Name of a secret with white-spaces mapped to `_`
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+/// Path of the secret information file `NAME.yaml` next to `outp_path`
+fn yaml_path<P: AsRef<Path>>(name: &str, outp_path: P) -> Result<PathBuf> {
+    let mut yaml_path = outp_path
+        .as_ref()
+        .parent()
+        .with_context(|| format!("Cannot open directory of {:?}", outp_path.as_ref()))?
+        .to_owned();
+    yaml_path.push(file_name(name));
+    yaml_path.set_extension("yaml");
+    Ok(yaml_path)
+}
+
 // Write non confidential data (=name+id) to a yaml stdout
 fn write_yaml<P: AsRef<Path>>(
     name: &str,
@@ -238,7 +321,10 @@ fn write_yaml<P: AsRef<Path>>(
     stdout: &bool,
     outp_path: P,
 ) -> Result<()> {
-    debug!("Non-confidential secret information: {guest_secret:x?}");
+    debug!(
+        "Non-confidential secret information: {}",
+        guest_secret.summary()
+    );
 
     let secret_info = serde_yaml::to_string(guest_secret)?;
     if stdout.to_owned() {
@@ -246,17 +332,7 @@ fn write_yaml<P: AsRef<Path>>(
         return Ok(());
     }
 
-    let gen_name: String = name
-        .chars()
-        .map(|c| if c.is_whitespace() { '_' } else { c })
-        .collect();
-    let mut yaml_path = outp_path
-        .as_ref()
-        .parent()
-        .with_context(|| format!("Cannot open directory of {:?}", outp_path.as_ref()))?
-        .to_owned();
-    yaml_path.push(gen_name);
-    yaml_path.set_extension("yaml");
+    let yaml_path = yaml_path(name, outp_path)?;
     write_out(&yaml_path, secret_info, "secret information")?;
     warn!(
         "Successfully wrote secret info to '{}'",
@@ -307,4 +383,30 @@ mod test {
         let key = super::read_private_key(key);
         assert!(key.is_err());
     }
+
+    fn parse_cuid(text: &str) -> anyhow::Result<super::ConfigUid> {
+        let text = utils::read_text(text.as_bytes(), "CUID-file")?;
+        super::try_from_val(serde_yaml::from_str(&text)?)
+    }
+
+    #[test]
+    fn cuid_crlf_bom() {
+        let cuid = "\u{feff}cuid: \"0x00112233445566778899aabbccddeeff\"  \r\n";
+        assert_eq!(
+            parse_cuid(cuid).unwrap(),
+            [
+                0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+                0xee, 0xff
+            ]
+        );
+    }
+
+    #[test]
+    fn cuid_invalid() {
+        let err = parse_cuid("cuid: \"0x00112233445566778899aabbccddeef\\t\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Cannot parse the CUID value "0x00112233445566778899aabbccddeef\t""#
+        );
+    }
 }
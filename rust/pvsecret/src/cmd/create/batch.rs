@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use log::{debug, info, warn};
+use pv::{
+    misc::{read_exact_file, read_file},
+    secret::{AddSecretContext, GuestSecret},
+};
+use serde::{de, Deserialize, Deserializer};
+use utils::read_text;
+
+use super::{
+    build_asrcb, encrypt, file_name, flags, retrievable, write_out, write_yaml, yaml_path, UserData,
+};
+use crate::cli::{CreateSecretFlags, CreateSecretOpt, RetrieveableSecretInpKind};
+
+/// A value of a clap [`ValueEnum`] in the batch file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Choice<T>(T);
+
+impl<'de, T: ValueEnum> Deserialize<'de> for Choice<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(de)?;
+        T::from_str(&s, true).map(Self).map_err(de::Error::custom)
+    }
+}
+
+/// One secret of the batch file
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "kebab-case",
+    deny_unknown_fields
+)]
+enum BatchSecret {
+    Meta {
+        #[serde(default)]
+        flags: Vec<Choice<CreateSecretFlags>>,
+    },
+    Association {
+        name: String,
+        input: Option<String>,
+        output_secret: Option<String>,
+        #[serde(default)]
+        flags: Vec<Choice<CreateSecretFlags>>,
+    },
+    Retrievable {
+        name: String,
+        kind: Choice<RetrieveableSecretInpKind>,
+        input: String,
+        #[serde(default)]
+        flags: Vec<Choice<CreateSecretFlags>>,
+    },
+}
+
+impl BatchSecret {
+    fn name(&self) -> Option<&str> {
+        match self {
+            Self::Meta { .. } => None,
+            Self::Association { name, .. } | Self::Retrievable { name, .. } => Some(name),
+        }
+    }
+
+    fn flags(&self) -> Vec<CreateSecretFlags> {
+        match self {
+            Self::Meta { flags }
+            | Self::Association { flags, .. }
+            | Self::Retrievable { flags, .. } => flags.iter().map(|f| f.0).collect(),
+        }
+    }
+
+    /// File name of the request without the entry number
+    fn request_name(&self) -> String {
+        self.name().map_or("meta".to_string(), file_name)
+    }
+
+    fn guest_secret(&self, use_name: bool) -> Result<GuestSecret> {
+        let mut secret = match self {
+            Self::Meta { .. } => GuestSecret::Null,
+            Self::Association { name, input, .. } => GuestSecret::association(
+                name,
+                input
+                    .as_ref()
+                    .map(|p| read_exact_file(p, "Association secret"))
+                    .transpose()?,
+            )?,
+            Self::Retrievable {
+                name, kind, input, ..
+            } => retrievable(name, input, &kind.0)?,
+        };
+        use_name.then(|| secret.no_hash_name());
+        Ok(secret)
+    }
+}
+
+/// Read the list of secrets from the batch file `path`
+fn read_batch(path: &str) -> Result<Vec<BatchSecret>> {
+    let buf = read_file(path, "batch file")?;
+    let text = read_text(buf.as_slice(), "batch file")?;
+    let batch =
+        parse_batch(&text).with_context(|| format!("Cannot parse the batch file {path}"))?;
+    debug!("Read {} secrets from the batch file {path}", batch.len());
+    Ok(batch)
+}
+
+fn parse_batch(text: &str) -> Result<Vec<BatchSecret>> {
+    let batch: Vec<BatchSecret> = serde_yaml::from_str(text)?;
+    if batch.is_empty() {
+        bail!("The batch file contains no secrets");
+    }
+
+    // the secret information files must not overwrite each other
+    let mut names = HashMap::new();
+    for (nr, name) in batch
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, secret)| secret.name().map(|name| (idx + 1, name)))
+    {
+        if name.contains('/') {
+            bail!("The name of entry {nr} contains a '/'");
+        }
+        if let Some(prev) = names.insert(yaml_path(name, "out")?, nr) {
+            bail!("The names of entry {prev} and entry {nr} map to the same file");
+        }
+    }
+    Ok(batch)
+}
+
+/// Create and write the request for the secret `nr` of the batch
+///
+/// Paths are added to `written` before the respective file is written.
+fn create_entry(
+    opt: &CreateSecretOpt,
+    ctx: &AddSecretContext,
+    user_data: &UserData,
+    dir: &Path,
+    (nr, secret): (usize, &BatchSecret),
+    written: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let asrcb = build_asrcb(
+        ctx,
+        secret.guest_secret(opt.use_name)?,
+        flags(opt, &secret.flags())?,
+        user_data,
+    )?;
+    let ser_asrcb = encrypt(&asrcb)?;
+
+    let path = dir.join(format!("{nr:03}_{}.bin", secret.request_name()));
+    written.push(path.clone());
+    write_out(&path, ser_asrcb, "add-secret request")?;
+    info!("Successfully wrote the request to '{}'", path.display());
+
+    if let Some(name) = secret.name() {
+        written.push(yaml_path(name, &path)?);
+        write_yaml(name, asrcb.guest_secret(), &false, &path)?;
+    }
+    if let BatchSecret::Association {
+        output_secret: Some(out),
+        ..
+    } = secret
+    {
+        written.push(out.into());
+        write_out(
+            out,
+            asrcb.guest_secret().confidential(),
+            "Association secret",
+        )?;
+    }
+    Ok(())
+}
+
+/// Remove the files of a failed batch
+fn remove_files(written: &[PathBuf]) {
+    for path in written {
+        match fs::remove_file(path) {
+            Ok(()) => info!("Removed '{}'", path.display()),
+            Err(e) => warn!("Cannot remove '{}': {e}", path.display()),
+        }
+    }
+}
+
+/// Create one add-secret request for each secret of the batch file `batch`
+pub fn create_batch(
+    opt: &CreateSecretOpt,
+    ctx: &AddSecretContext,
+    user_data: &UserData,
+    batch: &str,
+) -> Result<()> {
+    let secrets = read_batch(batch)?;
+    let dir = Path::new(&opt.output);
+    if !dir.is_dir() {
+        bail!("The output '{}' is no directory", opt.output);
+    }
+
+    let mut written = vec![];
+    for (idx, secret) in secrets.iter().enumerate() {
+        let nr = idx + 1;
+        let res = create_entry(opt, ctx, user_data, dir, (nr, secret), &mut written);
+        if let Err(e) = res {
+            if opt.atomic {
+                remove_files(&written);
+            }
+            return Err(e.context(match secret.name() {
+                Some(name) => format!("Cannot create the request of batch entry {nr} ('{name}')"),
+                None => format!("Cannot create the request of batch entry {nr} (meta)"),
+            }));
+        }
+    }
+    warn!(
+        "Successfully generated {} requests in '{}'",
+        secrets.len(),
+        opt.output
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pv::{
+        misc::read_certs,
+        request::BootHdrTags,
+        secret::{AddSecretRequest, AddSecretVersion, AsrcbInfo},
+        uv::ListableSecretType,
+    };
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::cli::{CliOptions, Command};
+    use clap::Parser;
+
+    const HOST_KEY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../pv/tests/assets/keys/host.pem.crt"
+    );
+    const TAGS: BootHdrTags = BootHdrTags::new([1; 64], [2; 64], [3; 64], [4; 16]);
+
+    fn ctx() -> AddSecretContext {
+        let key = read_certs(fs::read(HOST_KEY).unwrap()).unwrap()[0]
+            .public_key()
+            .unwrap();
+        let mut ctx = AddSecretContext::new(AddSecretVersion::One, TAGS);
+        ctx.add_hostkey(key);
+        ctx
+    }
+
+    /// Batch file with three entries in `dir` and the output directory `dir/out`
+    fn fixture(dir: &TemporaryDirectory, last: &str) -> (String, PathBuf) {
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+        fs::write(path("assoc"), [0x11; 32]).unwrap();
+        fs::write(path("aes"), [0x22; 32]).unwrap();
+        let batch = format!(
+            "- type: association\n  name: assoc\n  input: {}\n\
+             - type: retrievable\n  name: aes key\n  kind: aes\n  input: {}\n  flags: [disable-dump]\n\
+             {last}",
+            path("assoc"),
+            path("aes")
+        );
+        fs::write(path("batch.yaml"), batch).unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        (path("batch.yaml"), out)
+    }
+
+    fn create_opt(batch: &str, out: &Path, atomic: bool) -> CreateSecretOpt {
+        let mut args = vec![
+            "pvsecret",
+            "create",
+            "-k",
+            HOST_KEY,
+            "--no-verify",
+            "--hdr",
+            "hdr",
+            "-o",
+            out.to_str().unwrap(),
+            "--batch",
+            batch,
+        ];
+        if atomic {
+            args.push("--atomic");
+        }
+        match CliOptions::try_parse_from(args).unwrap().cmd {
+            Command::Create(opt) => *opt,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let batch = parse_batch(
+            "- type: meta\n\
+             - type: association\n  name: a\n  output-secret: a.secret\n\
+             - type: retrievable\n  name: b\n  kind: AES-XTS\n  input: b\n  flags: [disable-dump]\n",
+        )
+        .unwrap();
+        assert_eq!(batch.len(), 3);
+        assert!(matches!(batch[0], BatchSecret::Meta { .. }));
+        assert_eq!(batch[0].request_name(), "meta");
+        assert!(matches!(
+            &batch[1],
+            BatchSecret::Association { input: None, output_secret: Some(s), .. } if s == "a.secret"
+        ));
+        assert!(matches!(
+            batch[2],
+            BatchSecret::Retrievable {
+                kind: Choice(RetrieveableSecretInpKind::AesXts),
+                ..
+            }
+        ));
+        assert_eq!(batch[2].flags(), [CreateSecretFlags::DisableDump]);
+
+        for batch in [
+            "",
+            "[]",
+            "- type: null\n",
+            "- type: meta\n  name: a\n",
+            "- type: association\n",
+            "- type: retrievable\n  name: b\n  kind: rsa\n  input: b\n",
+            "- type: retrievable\n  name: b\n  kind: aes\n",
+            "- type: meta\n  flags: [enable-dump]\n",
+            "- type: association\n  name: a/b\n",
+            "- type: association\n  name: a b\n- type: association\n  name: a\tb\n",
+        ] {
+            assert!(parse_batch(batch).is_err(), "{batch:?}");
+        }
+    }
+
+    #[test]
+    fn create_three() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let (batch, out) = fixture(&dir, "- type: meta\n");
+        let opt = create_opt(&batch, &out, false);
+        create_batch(&opt, &ctx(), &None, &batch).unwrap();
+
+        let exp = [
+            ("001_assoc.bin", ListableSecretType::ASSOCIATION, false),
+            ("002_aes_key.bin", ListableSecretType::AES_256_KEY, true),
+            ("003_meta.bin", ListableSecretType::NULL, false),
+        ];
+        let mut tags = vec![];
+        for (name, kind, disable_dump) in exp {
+            let asrcb = fs::read(out.join(name)).unwrap();
+            let info = AsrcbInfo::parse(&asrcb).unwrap();
+            assert_eq!(info.secret_kind(), kind);
+            assert_eq!(info.disable_dump(), disable_dump);
+            assert_eq!(info.host_key_hashes().len(), 1);
+
+            let tag = AddSecretRequest::bin_tag(&asrcb).unwrap();
+            assert_eq!(tag, asrcb[asrcb.len() - 0x10..]);
+            assert!(!tags.contains(&tag));
+            tags.push(tag);
+        }
+        assert!(out.join("assoc.yaml").is_file());
+        assert!(out.join("aes_key.yaml").is_file());
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 5);
+    }
+
+    #[test]
+    fn create_fail() {
+        let missing = "- type: retrievable\n  name: missing\n  kind: plain\n  input: /missing\n";
+        for atomic in [false, true] {
+            let dir = TemporaryDirectory::new().unwrap();
+            let (batch, out) = fixture(&dir, missing);
+            let opt = create_opt(&batch, &out, atomic);
+            let err = create_batch(&opt, &ctx(), &None, &batch).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Cannot create the request of batch entry 3 ('missing')"
+            );
+            let files = fs::read_dir(&out).unwrap().count();
+            assert_eq!(files, if atomic { 0 } else { 4 });
+        }
+
+        // nothing is written if the batch file is invalid
+        let dir = TemporaryDirectory::new().unwrap();
+        let (batch, out) = fixture(&dir, "- type: invalid\n");
+        let opt = create_opt(&batch, &out, false);
+        assert!(create_batch(&opt, &ctx(), &None, &batch).is_err());
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0);
+
+        let opt = create_opt(&batch, &dir.path().join("missing"), false);
+        assert!(create_batch(&opt, &ctx(), &None, &batch).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use anyhow::{Context, Result};
+use log::warn;
+use pv::{
+    request::Keyslot,
+    secret::{AddSecretRequest, AsrcbInfo},
+    uv::{ListableSecretType, SecretId},
+};
+use serde::Serialize;
+use utils::HexSlice;
+
+use super::write_out;
+use crate::cli::{CreateSecretOpt, MetadataFormat};
+
+/// Non-confidential metadata of an add-secret request
+///
+/// All values but the name are taken from the serialized request, so that the metadata matches
+/// the request as it was written.
+#[derive(Debug, Serialize)]
+struct RequestMetadata<'a> {
+    tag: HexSlice<'a>,
+    secret_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_id: Option<SecretId>,
+    host_key_hashes: Vec<HexSlice<'a>>,
+}
+
+impl<'a> RequestMetadata<'a> {
+    fn new(
+        info: &AsrcbInfo,
+        tag: &'a [u8],
+        host_key_hashes: &'a [[u8; Keyslot::PHKH_SIZE as usize]],
+        name: Option<&'a str>,
+    ) -> Self {
+        let secret_type = match info.secret_kind() {
+            ListableSecretType::NULL => "Null".to_string(),
+            kind => ListableSecretType::from(kind).to_string(),
+        };
+        Self {
+            tag: tag.into(),
+            secret_type,
+            name,
+            secret_id: info.secret_id().cloned(),
+            host_key_hashes: host_key_hashes.iter().map(HexSlice::from).collect(),
+        }
+    }
+}
+
+/// Write the metadata of the serialized request `asrcb` to '--metadata-out', if specified
+pub fn write_metadata(opt: &CreateSecretOpt, asrcb: &[u8], name: Option<&str>) -> Result<()> {
+    let Some(path) = &opt.metadata_out else {
+        return Ok(());
+    };
+    let ctx = "Cannot read the metadata of the add-secret request";
+    let info = AsrcbInfo::parse(asrcb).context(ctx)?;
+    let tag = AddSecretRequest::bin_tag(asrcb).context(ctx)?;
+    let host_key_hashes = AddSecretRequest::bin_host_key_hashes(asrcb).context(ctx)?;
+    let metadata = RequestMetadata::new(&info, &tag, &host_key_hashes, name);
+    let data = match opt.metadata_format {
+        MetadataFormat::Json => {
+            let mut data = serde_json::to_string_pretty(&metadata)?;
+            data.push('\n');
+            data
+        }
+        MetadataFormat::Yaml => serde_yaml::to_string(&metadata)?,
+    };
+    write_out(path, data, "request metadata")?;
+    warn!("Successfully wrote the request metadata to '{path}'");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use clap::Parser;
+    use pv::{
+        misc::read_certs,
+        request::BootHdrTags,
+        secret::{AddSecretContext, AddSecretVersion},
+    };
+    use serde_json::Value;
+    use utils::TemporaryDirectory;
+
+    use super::*;
+    use crate::{
+        cli::{CliOptions, Command},
+        cmd::create::create_single,
+    };
+
+    const HOST_KEY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../pv/tests/assets/keys/host.pem.crt"
+    );
+
+    fn ctx() -> AddSecretContext {
+        let key = read_certs(fs::read(HOST_KEY).unwrap()).unwrap()[0]
+            .public_key()
+            .unwrap();
+        let mut ctx = AddSecretContext::new(
+            AddSecretVersion::One,
+            BootHdrTags::new([1; 64], [2; 64], [3; 64], [4; 16]),
+        );
+        ctx.add_hostkey(key);
+        ctx
+    }
+
+    fn try_parse(out: &Path, args: &[&str]) -> clap::error::Result<CreateSecretOpt> {
+        let mut all = vec![
+            "pvsecret",
+            "create",
+            "-k",
+            HOST_KEY,
+            "--no-verify",
+            "--hdr",
+            "hdr",
+            "-o",
+            out.to_str().unwrap(),
+        ];
+        all.extend(args);
+        match CliOptions::try_parse_from(all)?.cmd {
+            Command::Create(opt) => Ok(*opt),
+            _ => unreachable!(),
+        }
+    }
+
+    fn create(out: &Path, args: &[&str]) {
+        let opt = try_parse(out, args).unwrap();
+        create_single(&opt, &ctx(), &None, opt.secret.as_ref().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn json() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let out = dir.path().join("req.bin");
+        let meta = dir.path().join("req.json");
+        let meta_arg = meta.to_str().unwrap();
+        create(
+            &out,
+            &["--metadata-out", meta_arg, "association", "my assoc"],
+        );
+
+        let asrcb = fs::read(&out).unwrap();
+        let info = AsrcbInfo::parse(&asrcb).unwrap();
+        let json: Value = serde_json::from_slice(&fs::read(&meta).unwrap()).unwrap();
+        let tag = AddSecretRequest::bin_tag(&asrcb).unwrap();
+        let tag = format!("{:#}", HexSlice::from(&tag));
+        assert_eq!(json["tag"], tag);
+        assert_eq!(json["secret_type"], "Association");
+        assert_eq!(json["name"], "my assoc");
+        assert_eq!(
+            json["secret_id"],
+            format!("{:#x}", info.secret_id().unwrap())
+        );
+        let hashes: Vec<_> = info
+            .host_key_hashes()
+            .iter()
+            .map(|h| Value::from(format!("{:#}", HexSlice::from(h))))
+            .collect();
+        assert_eq!(hashes.len(), 1);
+        assert_eq!(json["host_key_hashes"], Value::from(hashes));
+    }
+
+    #[test]
+    fn yaml_meta() {
+        let dir = TemporaryDirectory::new().unwrap();
+        let out = dir.path().join("req.bin");
+        let meta = dir.path().join("req.yaml");
+        let meta_arg = meta.to_str().unwrap();
+        create(
+            &out,
+            &[
+                "--metadata-out",
+                meta_arg,
+                "--metadata-format",
+                "yaml",
+                "meta",
+            ],
+        );
+
+        let asrcb = fs::read(&out).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_slice(&fs::read(&meta).unwrap()).unwrap();
+        let tag = AddSecretRequest::bin_tag(&asrcb).unwrap();
+        let tag = format!("{:#}", HexSlice::from(&tag));
+        assert_eq!(yaml["tag"].as_str(), Some(tag.as_str()));
+        assert_eq!(yaml["secret_type"].as_str(), Some("Null"));
+        assert!(yaml.get("name").is_none());
+        assert!(yaml.get("secret_id").is_none());
+    }
+
+    #[test]
+    fn cli() {
+        let out = Path::new("out");
+        assert!(try_parse(out, &["--metadata-format", "yaml", "meta"]).is_err());
+        assert!(try_parse(out, &["--metadata-out", "m", "--batch", "b"]).is_err());
+        let opt = try_parse(out, &["--metadata-out", "m", "meta"]).unwrap();
+        assert_eq!(opt.metadata_format, MetadataFormat::Json);
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use log::warn;
+use pv::request::{HkdVerifier, NoVerifyHkd};
+use utils::{get_writer_from_cli_file_arg, read_hkd, HkdReport};
+
+use crate::cli::{HkdInfoFormat, HkdInfoOpt};
+
+/// Read and verify the host-key document in `path` and report its validity
+fn hkd_report(path: &Path, verifier: &dyn HkdVerifier) -> Result<HkdReport> {
+    let hkd = read_hkd(path)?;
+    let report = verifier
+        .verify_report(&hkd)
+        .map_err(|source| pv::Error::HkdVerifyFile {
+            hkd: path.display().to_string(),
+            source: Box::new(source),
+        })?;
+    Ok(HkdReport::new(path, report))
+}
+
+fn write_infos<W: Write>(infos: &[HkdReport], format: HkdInfoFormat, mut wr: W) -> Result<()> {
+    match format {
+        HkdInfoFormat::Human => infos.iter().try_for_each(|info| write!(wr, "{info}"))?,
+        HkdInfoFormat::Json => {
+            serde_json::to_writer_pretty(&mut wr, infos)?;
+            writeln!(wr)?;
+        }
+        HkdInfoFormat::Yaml => serde_yaml::to_writer(&mut wr, infos)?,
+    }
+    Ok(())
+}
+
+fn hkd_infos(opt: &HkdInfoOpt) -> Result<Vec<HkdReport>> {
+    let verifier: Box<dyn HkdVerifier> = match opt.no_verify {
+        true => Box::new(NoVerifyHkd),
+        false => Box::new(opt.verify_args.verifier()?),
+    };
+    opt.host_key_documents
+        .iter()
+        .map(|path| hkd_report(path, verifier.as_ref()))
+        .collect()
+}
+
+/// Report the validity of host-key documents
+pub fn hkd_info(opt: &HkdInfoOpt) -> Result<()> {
+    let infos = hkd_infos(opt)?;
+    if let Some(days) = opt.warn_expiry {
+        infos
+            .iter()
+            .filter_map(|info| info.expiry_warning(days))
+            .for_each(|warning| warn!("{warning}"));
+    }
+
+    write_infos(
+        &infos,
+        opt.format,
+        get_writer_from_cli_file_arg(&opt.output)?,
+    )
+    .with_context(|| {
+        format!(
+            "Cannot write the host-key document report to '{}'",
+            opt.output
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::cli::{CliOptions, Command};
+
+    const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/cert");
+
+    fn cert(name: &str) -> String {
+        format!("{CERT_DIR}/{name}")
+    }
+
+    fn hkd_info_opt(args: &[&str]) -> HkdInfoOpt {
+        let args = ["pvsecret", "hkd-info"].iter().chain(args);
+        match CliOptions::try_parse_from(args).unwrap().cmd {
+            Command::HkdInfo(opt) => opt,
+            cmd => panic!("Unexpected command {cmd:?}"),
+        }
+    }
+
+    fn verify_args() -> Vec<String> {
+        [
+            "--cert",
+            &cert("ibm.crt"),
+            "--cert",
+            &cert("inter_ca.crt"),
+            "--crl",
+            &cert("ibm.crl"),
+            "--crl",
+            &cert("inter_ca.crl"),
+            "--root-ca",
+            &cert("root_ca.chained.crt"),
+            "--offline",
+        ]
+        .map(str::to_string)
+        .into()
+    }
+
+    #[test]
+    fn verified() {
+        let host = cert("host.crt");
+        let mut args = vec![host.as_str()];
+        let verify_args = verify_args();
+        args.extend(verify_args.iter().map(String::as_str));
+        let infos = hkd_infos(&hkd_info_opt(&args)).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].report().certificates().len(), 4);
+        assert_eq!(infos[0].report().crls().len(), 2);
+        assert!(!infos[0].report().is_expired());
+
+        let mut out = Vec::new();
+        write_infos(&infos, HkdInfoFormat::Json, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["path"], host);
+        assert_eq!(json[0]["certificates"][0]["kind"], "host_key_document");
+        assert_eq!(json[0]["certificates"][3]["kind"], "root_ca");
+        assert!(json[0]["expires_in"].as_u64().unwrap() > 0);
+
+        let mut out = Vec::new();
+        write_infos(&infos, HkdInfoFormat::Human, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(&format!("{host}:\n  host-key document '")));
+        assert!(
+            out.contains("    not after:   2388-12-23T14:52:39Z\n"),
+            "{out}"
+        );
+        assert!(out.contains("  expires in "), "{out}");
+
+        // the verification of an expired host-key document fails
+        let expired = cert("host_crt_expired.crt");
+        let mut args = vec![expired.as_str()];
+        args.extend(verify_args.iter().map(String::as_str));
+        assert!(hkd_infos(&hkd_info_opt(&args)).is_err());
+    }
+
+    #[test]
+    fn no_verify() {
+        let expired = cert("host_crt_expired.crt");
+        let infos = hkd_infos(&hkd_info_opt(&[&expired, "--no-verify"])).unwrap();
+        assert_eq!(infos[0].report().certificates().len(), 1);
+        assert!(infos[0].report().is_expired());
+
+        let mut out = Vec::new();
+        write_infos(&infos, HkdInfoFormat::Yaml, &mut out).unwrap();
+        let yaml: serde_yaml::Value = serde_yaml::from_slice(&out).unwrap();
+        assert_eq!(yaml[0]["expires_in"].as_u64(), Some(0));
+        assert!(yaml[0]["crls"].as_sequence().unwrap().is_empty());
+    }
+}
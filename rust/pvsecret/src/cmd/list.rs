@@ -2,48 +2,66 @@
 //
 // Copyright IBM Corp. 2023
 
-use std::io::ErrorKind;
-
 use crate::cli::{ListSecretOpt, ListSecretOutputType};
-use anyhow::{Context, Error, Result};
-use log::{info, warn};
-use pv::uv::{ListCmd, SecretList, UvDevice};
+use anyhow::{bail, Context, Result};
+use log::warn;
+use pv::uv::{ListableSecretType, SecretList, SecretStoreUsage, UvInfo};
+use serde::Serialize;
 use utils::{get_writer_from_cli_file_arg, STDOUT};
 
-const SECRET_LIST_BUF_SIZE: usize = 4;
-
-/// Do a List Secrets UVC
-pub fn list_uvc(uv: &UvDevice) -> Result<SecretList> {
-    let mut cmd = ListCmd::with_pages(SECRET_LIST_BUF_SIZE);
-    let more_data = match uv.send_cmd(&mut cmd) {
-        Ok(v) => Ok(v),
-        Err(pv::PvCoreError::Io(e)) if e.kind() == ErrorKind::InvalidInput => {
-            info!("Uvdevice does not suport longer list. Fallback to one page list.");
-            cmd = ListCmd::default();
-            uv.send_cmd(&mut cmd)
-        }
-        Err(e) => Err(e),
-    }?
-    .more_data();
-    if more_data {
-        warn!("The secret list contains more data but the uvdevice cannot show all.");
-    }
-
-    cmd.try_into().map_err(Error::new)
+/// Secret list with the usage of the secret store, if available
+#[derive(Serialize)]
+struct ListOutput<'a> {
+    #[serde(flatten)]
+    list: &'a SecretList,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slots: Option<SecretStoreUsage>,
 }
 
 /// Do a List Secrets UVC and output the list in the requested format
 pub fn list(opt: &ListSecretOpt) -> Result<()> {
-    let uv = UvDevice::open()?;
-    let secret_list = list_uvc(&uv)?;
+    let uv = opt.device.open()?;
+    let info = UvInfo::query(&uv)?;
+    if !info.supports_list_secrets() {
+        bail!("This machine's Ultravisor does not support listing secrets");
+    }
+    let mut secret_list = SecretList::from_uv(&uv)?;
+    if let Some(stype) = &opt.stype {
+        let stype: ListableSecretType = stype.parse()?;
+        secret_list.retain(|e| e.stype() == stype);
+    }
+    if let Some(id) = &opt.id {
+        secret_list.retain(|e| e.secret_id().has_hex_prefix(id));
+    }
+    let usage = info
+        .max_secrets()
+        .and_then(|max| SecretStoreUsage::new(secret_list.total_num_secrets(), max));
     let mut wr_out = get_writer_from_cli_file_arg(&opt.output)?;
 
     match &opt.format {
         ListSecretOutputType::Human => {
+            if let Some(usage) = usage {
+                writeln!(wr_out, "{usage}").context("Cannot generate output")?;
+            }
             write!(wr_out, "{secret_list}").context("Cannot generate output")?
         }
-        ListSecretOutputType::Yaml => write!(wr_out, "{}", serde_yaml::to_string(&secret_list)?)
-            .context("Cannot generate yaml output")?,
+        ListSecretOutputType::Yaml => {
+            let output = ListOutput {
+                list: &secret_list,
+                slots: usage,
+            };
+            write!(wr_out, "{}", serde_yaml::to_string(&output)?)
+                .context("Cannot generate yaml output")?
+        }
+        ListSecretOutputType::Json => {
+            let output = ListOutput {
+                list: &secret_list,
+                slots: usage,
+            };
+            serde_json::to_writer_pretty(&mut wr_out, &output)
+                .context("Cannot generate json output")?;
+            writeln!(wr_out).context("Cannot generate json output")?
+        }
         ListSecretOutputType::Bin => secret_list
             .encode(&mut wr_out)
             .context("Cannot encode secret list")?,
@@ -2,13 +2,18 @@
 //
 // Copyright IBM Corp. 2023
 
-use anyhow::Result;
+use crate::cli::LockSecretOpt;
+use anyhow::{bail, Result};
 use log::warn;
-use pv::uv::{LockCmd, UvDevice};
+use pv::uv::{LockCmd, UvCmdExecutor, UvInfo};
 
 /// Do a Lock Secret Store UVC
-pub fn lock() -> Result<()> {
-    UvDevice::open()?.send_cmd(&mut LockCmd)?;
+pub fn lock(opt: &LockSecretOpt) -> Result<()> {
+    let uv = opt.device.open()?;
+    if !UvInfo::query(&uv)?.supports_lock_secrets() {
+        bail!("This machine's Ultravisor does not support locking the secret store");
+    }
+    uv.lock_secrets(&mut LockCmd)?;
     warn!("Successfully locked secret store");
     Ok(())
 }
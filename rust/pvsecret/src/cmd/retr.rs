@@ -2,21 +2,23 @@
 //
 // Copyright IBM Corp. 2024
 
-use super::list::list_uvc;
 use crate::cli::{RetrInpFmt, RetrOutFmt, RetrSecretOptions};
 use anyhow::{anyhow, bail, Context, Result};
 use log::{debug, info};
 use pv::{
     misc::open_file,
     misc::write,
+    request::{Confidential, Summary},
     secret::{GuestSecret, RetrievedSecret},
-    uv::{RetrieveCmd, SecretId, UvDevice},
+    uv::{RetrieveCmd, SecretId, SecretList, UvCmdExecutor, UvInfo},
 };
-use utils::get_writer_from_cli_file_arg;
+use utils::{atomic_write, is_tty, read_text, STDOUT};
 
-fn retrieve(id: &SecretId) -> Result<RetrievedSecret> {
-    let uv = UvDevice::open()?;
-    let secrets = list_uvc(&uv)?;
+fn retrieve<U: UvCmdExecutor>(id: &SecretId, uv: &U) -> Result<RetrievedSecret> {
+    if !UvInfo::query(uv)?.supports_retrieve_secrets() {
+        bail!("This machine's Ultravisor does not support retrievable secrets");
+    }
+    let secrets = SecretList::from_uv(uv)?;
     let secret = match secrets.find(id) {
         Some(s) => s,
         // hash it + try again if it is ASCII-representable
@@ -30,18 +32,38 @@ fn retrieve(id: &SecretId) -> Result<RetrievedSecret> {
     };
 
     info!("Try to retrieve secret at index: {}", secret.index());
-    debug!("Try to retrieve: {secret:?}");
+    debug!("Try to retrieve: {}", secret.summary());
 
     let mut uv_cmd = RetrieveCmd::from_entry(secret)?;
-    uv.send_cmd(&mut uv_cmd)?;
+    uv.retrieve_secret(&mut uv_cmd)?;
 
     Ok(RetrievedSecret::from_cmd(uv_cmd))
 }
 
+/// Refuse to write a binary secret to a terminal unless forced
+fn check_output(outform: RetrOutFmt, force: bool, tty: bool) -> Result<()> {
+    if outform == RetrOutFmt::Bin && tty && !force {
+        bail!("Refusing to write the secret in binary to a terminal. Specify an output file, another output format, or --force");
+    }
+    Ok(())
+}
+
+/// Convert the retrieved secret into the output format
+fn encode(secret: RetrievedSecret, outform: RetrOutFmt) -> Result<Confidential<Vec<u8>>> {
+    Ok(match outform {
+        RetrOutFmt::Bin => secret.into_bytes(),
+        RetrOutFmt::Hex => secret.to_hex(),
+        RetrOutFmt::Pem => secret.to_pem()?.into_bytes(),
+    })
+}
+
 pub fn retr(opt: &RetrSecretOptions) -> Result<()> {
-    let mut output = get_writer_from_cli_file_arg(&opt.output)?;
+    check_output(opt.outform, opt.force, is_tty(&opt.output))?;
     let id = match &opt.inform {
-        RetrInpFmt::Yaml => match serde_yaml::from_reader(&mut open_file(&opt.input)?)? {
+        RetrInpFmt::Yaml => match serde_yaml::from_str(&read_text(
+            open_file(&opt.input)?,
+            "secret information file",
+        )?)? {
             GuestSecret::Retrievable { id, .. } => id,
             gs => bail!("The file contains a {gs}-secret, which is not retrievable."),
         },
@@ -51,18 +73,82 @@ pub fn retr(opt: &RetrSecretOptions) -> Result<()> {
         RetrInpFmt::Name => SecretId::from_string(&opt.input),
     };
 
-    let retr_secret =
-        retrieve(&id).context("Could not retrieve the secret from the UV secret store.")?;
+    let retr_secret = retrieve(&id, &opt.device.open()?)
+        .context("Could not retrieve the secret from the UV secret store.")?;
 
-    let out_data = match opt.outform {
-        RetrOutFmt::Bin => retr_secret.into_bytes(),
-        RetrOutFmt::Pem => retr_secret.to_pem()?.into_bytes(),
-    };
-    write(
-        &mut output,
-        out_data.value(),
-        &opt.output,
-        "IBM Protected Key",
-    )?;
+    let out_data = encode(retr_secret, opt.outform)?;
+    match opt.output.as_str() {
+        STDOUT => write(
+            &mut std::io::stdout().lock(),
+            out_data.value(),
+            &opt.output,
+            "IBM Protected Key",
+        )?,
+        // only the owner may read the secret
+        path => atomic_write(path, out_data.value(), 0o600)
+            .with_context(|| format!("Cannot write the secret to '{path}'"))?,
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use pv::uv::{AesSizes, ListableSecretType, RetrievableSecret, SecretEntry, UvCmd};
+
+    use super::*;
+
+    fn plaintext() -> RetrievedSecret {
+        let secret = [0, 4, 0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let entry = SecretEntry::new(
+            0,
+            ListableSecretType::Retrievable(RetrievableSecret::PlainText),
+            SecretId::default(),
+            secret.len() as u32,
+        );
+        let mut cmd = RetrieveCmd::from_entry(entry).unwrap();
+        cmd.data().unwrap().copy_from_slice(&secret);
+        RetrievedSecret::from_cmd(cmd)
+    }
+
+    fn aes() -> RetrievedSecret {
+        let entry = SecretEntry::new(
+            0,
+            ListableSecretType::Retrievable(RetrievableSecret::Aes(AesSizes::Bits128)),
+            SecretId::default(),
+            48,
+        );
+        let mut cmd = RetrieveCmd::from_entry(entry).unwrap();
+        cmd.data().unwrap().fill(0x11);
+        RetrievedSecret::from_cmd(cmd)
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            encode(plaintext(), RetrOutFmt::Bin).unwrap().value(),
+            &[0xde, 0xad, 0xbe, 0xef]
+        );
+        assert_eq!(
+            encode(plaintext(), RetrOutFmt::Hex).unwrap().value(),
+            b"deadbeef\n"
+        );
+        assert_eq!(
+            encode(plaintext(), RetrOutFmt::Pem).unwrap().value(),
+            b"-----BEGIN PLAINTEXT SECRET-----\n3q2+7w==\n-----END PLAINTEXT SECRET-----\n"
+        );
+        assert!(
+            String::from_utf8(encode(aes(), RetrOutFmt::Pem).unwrap().into_inner())
+                .unwrap()
+                .starts_with("-----BEGIN IBM PROTECTED KEY-----\nkind: AES-128-KEY\n")
+        );
+    }
+
+    #[test]
+    fn tty() {
+        assert!(check_output(RetrOutFmt::Bin, false, true).is_err());
+        assert!(check_output(RetrOutFmt::Bin, true, true).is_ok());
+        assert!(check_output(RetrOutFmt::Bin, false, false).is_ok());
+        assert!(check_output(RetrOutFmt::Hex, false, true).is_ok());
+        assert!(check_output(RetrOutFmt::Pem, false, true).is_ok());
+    }
+}
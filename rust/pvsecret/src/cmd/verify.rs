@@ -2,25 +2,45 @@
 //
 // Copyright IBM Corp. 2024
 
+use std::path::Path;
+
 use crate::cli::VerifyOpt;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use log::warn;
 use pv::misc::{read_certs, read_file};
 use pv::{
     request::openssl::pkey::{PKey, Public},
-    secret::verify_asrcb_and_get_user_data,
+    secret::{verify_asrcb_and_get_user_data, AsrcbInfo},
 };
 use utils::{get_reader_from_cli_file_arg, get_writer_from_cli_file_arg};
 
 /// read the content of a DER or PEM x509 and return the public key
-fn read_sgn_key(path: &str) -> Result<PKey<Public>> {
-    read_certs(read_file(path, "user-signing key")?)?
+fn read_cert_key<P: AsRef<Path>>(path: P, ctx: &str) -> Result<PKey<Public>> {
+    read_certs(read_file(path, ctx)?)?
         .first()
         .ok_or(anyhow!("File does not contain a X509 certificate"))?
         .public_key()
         .map_err(anyhow::Error::new)
 }
 
+/// Check that at least one of the host-key documents matches a keyslot of the request
+fn check_host_keys<P: AsRef<Path>>(info: &AsrcbInfo, hkds: &[P]) -> Result<()> {
+    let mut matched = false;
+    for hkd in hkds {
+        let hkd = hkd.as_ref();
+        let key = read_cert_key(hkd, "host-key document")
+            .with_context(|| format!("Cannot read host-key document {}", hkd.display()))?;
+        if info.has_host_key(&key)? {
+            warn!("The request matches host-key document '{}'", hkd.display());
+            matched = true;
+        }
+    }
+    if !matched {
+        bail!("The request was created for none of the given host-key documents");
+    }
+    Ok(())
+}
+
 pub fn verify(opt: &VerifyOpt) -> Result<()> {
     let mut rd_in = get_reader_from_cli_file_arg(&opt.input)?;
     let mut data_in = Vec::with_capacity(0x1000);
@@ -28,10 +48,18 @@ pub fn verify(opt: &VerifyOpt) -> Result<()> {
         .read_to_end(&mut data_in)
         .with_context(|| format!("Cannot read input file {}", opt.input))?;
 
+    // no context, the parse error itself must be visible without -v
+    let info = AsrcbInfo::parse(&data_in)?;
+    // stdout may be used for the user data
+    eprint!("{info}");
+    if !opt.host_key_documents.is_empty() {
+        check_host_keys(&info, &opt.host_key_documents)?;
+    }
+
     let verify_cert = opt
         .user_cert
         .as_ref()
-        .map(|p| read_sgn_key(p))
+        .map(|p| read_cert_key(p, "user-signing key"))
         .transpose()
         .context("Cannot read user-verification certificate.")?;
 
@@ -46,3 +74,27 @@ pub fn verify(opt: &VerifyOpt) -> Result<()> {
     warn!("Successfully verified the request.");
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const ASRCB_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/exp/asrcb");
+    const KEY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/keys");
+    const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pv/tests/assets/cert");
+
+    #[test]
+    fn host_keys() {
+        let asrcb = std::fs::read(format!("{ASRCB_DIR}/null_none_default_cuid_one")).unwrap();
+        let info = AsrcbInfo::parse(&asrcb).unwrap();
+        let host = PathBuf::from(format!("{KEY_DIR}/host.pem.crt"));
+        let other = PathBuf::from(format!("{CERT_DIR}/host.crt"));
+
+        check_host_keys(&info, &[&host]).unwrap();
+        check_host_keys(&info, &[&other, &host]).unwrap();
+        assert!(check_host_keys(&info, &[&other]).is_err());
+        assert!(check_host_keys(&info, &[PathBuf::from("missing")]).is_err());
+    }
+}
@@ -41,10 +41,12 @@ fn main() -> ExitCode {
     let res = match &cli.cmd {
         Command::Add(opt) => cmd::add(opt),
         Command::List(opt) => cmd::list(opt),
-        Command::Lock => cmd::lock(),
+        Command::Lock(opt) => cmd::lock(opt),
         Command::Create(opt) => cmd::create(opt),
         Command::Version => Ok(print_version!("2024", log_level; FEATURES.concat())),
         Command::Verify(opt) => cmd::verify(opt),
+        Command::Audit(opt) => cmd::audit(opt),
+        Command::HkdInfo(opt) => cmd::hkd_info(opt),
         Command::Retrieve(opt) => cmd::retr(opt),
     };
 
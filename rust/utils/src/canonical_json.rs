@@ -0,0 +1,315 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+//! Canonical JSON serialization for signed or MACed documents.
+//!
+//! The default output of `serde_json` depends on the order of the struct fields and the map
+//! implementation. A signature over such output breaks if the Rust definition changes. Sign or
+//! MAC the output of [`to_vec`] instead. The canonical form is:
+//!
+//! * UTF-8 without insignificant whitespace.
+//! * Object members sorted by their keys, compared by their UTF-8 bytes.
+//! * Integers in shortest decimal form. Floating point numbers are rejected.
+//! * Strings with only `"`, `\`, and the control characters U+0000 to U+001F escaped. The control
+//!   characters use `\b`, `\t`, `\n`, `\f`, `\r`, or lowercase `\u00xx`. All other characters are
+//!   written verbatim.
+//! * Strings (including keys) with code points below U+0300 only. Other strings are rejected.
+//!
+//! The restriction of the strings replaces a Unicode normalization. Strings of code points below
+//! U+0300 are in Normalization Form C (NFC), as none of these code points decomposes or composes
+//! with another one. Hence, equivalent strings always have the same canonical form. Strings with
+//! other code points would need normalization tables, which are not available. The documents
+//! signed by these tools contain identifiers, hex strings, and similar ASCII data only.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// First code point that is not supported in strings
+const STRING_CHAR_LIMIT: char = '\u{300}';
+
+/// Error cases of [`to_vec`]
+#[derive(Debug)]
+pub enum CanonicalJsonError {
+    /// The value cannot be represented as JSON
+    Serialize(serde_json::Error),
+    /// The value contains a floating point number
+    Float(String),
+    /// The value contains a string with a code point from U+0300 onwards
+    UnsupportedChar(String),
+}
+
+impl Display for CanonicalJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Serialize(e) => write!(f, "Cannot serialize the value as JSON: {e}"),
+            Self::Float(n) => write!(
+                f,
+                "Canonical JSON supports integers only, but the value contains {n}"
+            ),
+            Self::UnsupportedChar(s) => write!(
+                f,
+                "Canonical JSON supports strings without characters from U+0300 onwards only, \
+                 but the value contains {s:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CanonicalJsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Serialize `value` as canonical JSON
+///
+/// See the [module documentation](self) for the canonical form.
+///
+/// # Errors
+///
+/// This function will return an error if `value` cannot be serialized as JSON, or contains
+/// floating point numbers or strings with code points from U+0300 onwards.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CanonicalJsonError> {
+    let value = serde_json::to_value(value).map_err(CanonicalJsonError::Serialize)?;
+    let mut out = Vec::new();
+    write_value(&mut out, &value)?;
+    Ok(out)
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) -> Result<(), CanonicalJsonError> {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(true) => out.extend_from_slice(b"true"),
+        Value::Bool(false) => out.extend_from_slice(b"false"),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => out.extend_from_slice(n.to_string().as_bytes()),
+            (_, Some(n)) => out.extend_from_slice(n.to_string().as_bytes()),
+            _ => return Err(CanonicalJsonError::Float(n.to_string())),
+        },
+        Value::String(s) => write_string(out, s)?,
+        Value::Array(values) => {
+            out.push(b'[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(out, value)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(members) => {
+            // do not rely on the map implementation of serde_json
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push(b'{');
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_string(out, key)?;
+                out.push(b':');
+                write_value(out, value)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) -> Result<(), CanonicalJsonError> {
+    if s.chars().any(|c| c >= STRING_CHAR_LIMIT) {
+        return Err(CanonicalJsonError::UnsupportedChar(s.to_string()));
+    }
+    out.push(b'"');
+    for c in s.chars() {
+        match c {
+            '"' => out.extend_from_slice(b"\\\""),
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\u{8}' => out.extend_from_slice(b"\\b"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\u{c}' => out.extend_from_slice(b"\\f"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            c if c < ' ' => out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes()),
+            c => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    out.push(b'"');
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use openssl::{hash::MessageDigest, nid::Nid, sign::Signer};
+    use pv::request::{gen_ec_key, verify_signature_digest, Digest, SignatureVerification};
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Entry {
+        name: &'static str,
+        size: u32,
+        tags: Vec<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Manifest {
+        version: u8,
+        offset: i64,
+        entries: Vec<Entry>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        comment: Option<String>,
+        attributes: HashMap<&'static str, Option<bool>>,
+    }
+
+    /// [`Manifest`] with the fields in a different order
+    #[derive(Serialize)]
+    struct ManifestReordered {
+        attributes: HashMap<&'static str, Option<bool>>,
+        comment: Option<String>,
+        entries: Vec<Entry>,
+        offset: i64,
+        version: u8,
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                name: "kernel",
+                size: 0x1000,
+                tags: vec!["boot", "signed"],
+            },
+            Entry {
+                name: "initrd \"new\"",
+                size: 0,
+                tags: vec![],
+            },
+        ]
+    }
+
+    fn attributes() -> HashMap<&'static str, Option<bool>> {
+        [("zeta", Some(true)), ("alpha", None), ("Beta", Some(false))].into()
+    }
+
+    fn manifest() -> Manifest {
+        Manifest {
+            version: 1,
+            offset: -42,
+            entries: entries(),
+            comment: None,
+            attributes: attributes(),
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let exp = concat!(
+            r#"{"attributes":{"Beta":false,"alpha":null,"zeta":true},"#,
+            r#""entries":[{"name":"kernel","size":4096,"tags":["boot","signed"]},"#,
+            r#"{"name":"initrd \"new\"","size":0,"tags":[]}],"offset":-42,"version":1}"#
+        );
+        assert_eq!(
+            String::from_utf8(to_vec(&manifest()).unwrap()).unwrap(),
+            exp
+        );
+
+        let reordered = ManifestReordered {
+            attributes: attributes(),
+            comment: Some("Ünïcödé\ttext\u{1}".to_string()),
+            entries: entries(),
+            offset: i64::MIN,
+            version: 1,
+        };
+        let exp = concat!(
+            r#"{"attributes":{"Beta":false,"alpha":null,"zeta":true},"#,
+            r#""comment":"Ünïcödé\ttext\u0001","#,
+            r#""entries":[{"name":"kernel","size":4096,"tags":["boot","signed"]},"#,
+            r#"{"name":"initrd \"new\"","size":0,"tags":[]}],"#,
+            r#""offset":-9223372036854775808,"version":1}"#
+        );
+        assert_eq!(String::from_utf8(to_vec(&reordered).unwrap()).unwrap(), exp);
+    }
+
+    #[test]
+    fn independent_of_field_order() {
+        let reordered = ManifestReordered {
+            attributes: attributes(),
+            comment: None,
+            entries: entries(),
+            offset: -42,
+            version: 1,
+        };
+        // serializes the None comment as null
+        let mut value = serde_json::to_value(&reordered).unwrap();
+        value.as_object_mut().unwrap().remove("comment");
+        assert_eq!(to_vec(&manifest()).unwrap(), to_vec(&value).unwrap());
+
+        let key = gen_ec_key(Nid::SECP521R1).unwrap();
+        let mut signer = Signer::new(MessageDigest::sha512(), &key).unwrap();
+        signer.update(&to_vec(&manifest()).unwrap()).unwrap();
+        let sign = signer.sign_to_vec().unwrap();
+        assert!(matches!(
+            verify_signature_digest(&key, Digest::Sha512, &to_vec(&value).unwrap(), &sign),
+            Ok(SignatureVerification::Verified)
+        ));
+
+        // any change breaks the signature
+        value["offset"] = (-41).into();
+        assert!(matches!(
+            verify_signature_digest(&key, Digest::Sha512, &to_vec(&value).unwrap(), &sign),
+            Ok(SignatureVerification::Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_as_json() {
+        let map: BTreeMap<String, Vec<u64>> =
+            [("b".into(), vec![u64::MAX]), ("a".into(), vec![])].into();
+        let canonical = to_vec(&map).unwrap();
+        assert_eq!(canonical, br#"{"a":[],"b":[18446744073709551615]}"#);
+        assert_eq!(
+            serde_json::from_slice::<BTreeMap<String, Vec<u64>>>(&canonical).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            to_vec("\u{8}\u{c}\n\r\t\u{1f}\"\\/\u{7f}\u{ff}").unwrap(),
+            "\"\\b\\f\\n\\r\\t\\u001f\\\"\\\\/\u{7f}\u{ff}\"".as_bytes()
+        );
+    }
+
+    #[test]
+    fn rejected() {
+        assert!(matches!(to_vec(&1.0), Err(CanonicalJsonError::Float(_))));
+        assert!(matches!(
+            to_vec(&[(1, 0.5)]),
+            Err(CanonicalJsonError::Float(_))
+        ));
+        // decomposed 'é'
+        assert!(matches!(
+            to_vec("e\u{301}"),
+            Err(CanonicalJsonError::UnsupportedChar(_))
+        ));
+        let key: BTreeMap<_, _> = [("\u{212b}", 1)].into();
+        assert!(matches!(
+            to_vec(&key),
+            Err(CanonicalJsonError::UnsupportedChar(_))
+        ));
+        let map: HashMap<Vec<u8>, u8> = [(vec![1], 1)].into();
+        assert!(matches!(
+            to_vec(&map),
+            Err(CanonicalJsonError::Serialize(_))
+        ));
+    }
+}
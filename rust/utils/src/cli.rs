@@ -9,11 +9,14 @@ use pv::{
     misc::{create_file, open_file, read_certs},
     request::{
         openssl::pkey::{PKey, Public},
-        HkdVerifier,
+        openssl::x509::X509,
+        CertVerifier, CertVerifierBuilder, HkdVerifier, NoVerifyHkd, OcspMode,
     },
+    uv::UvDevice,
     Error, Result,
 };
-use std::io::{Read, Write};
+use std::fs::OpenOptions;
+use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
@@ -44,11 +47,19 @@ pub struct CertificateOptions {
     #[arg(long)]
     pub no_verify: bool,
 
+    #[command(flatten)]
+    pub verify_args: CertVerifyOptions,
+}
+
+/// CLI Argument collection for verifying host-key documents with a chain of trust.
+#[derive(Args, Debug, Clone, PartialEq, Eq, Default)]
+pub struct CertVerifyOptions {
     /// Use FILE as a certificate to verify the host-key or keys.
     ///
     /// The certificates are used to establish a chain of trust for the verification
     /// of the host-key documents. Specify this option twice to specify the IBM Z signing key and
     /// the intermediate CA certificate (signed by the root CA).
+    /// A file may also contain the complete chain of trust and its CRLs in PEM format.
     #[arg(
         short= 'C',
         long = "cert",
@@ -75,15 +86,100 @@ pub struct CertificateOptions {
     pub crls: Vec<PathBuf>,
 
     /// Make no attempt to download CRLs.
+    ///
+    /// All CRLs of the chain of trust and of the host-key documents must be given with '--crl'.
     #[arg(long, requires("certs"))]
     pub offline: bool,
 
+    /// Check the revocation status of the host-key documents with OCSP.
+    ///
+    /// The OCSP responder is taken from the authority information access extension of the
+    /// host-key document. The verification fails if the responder does not report the host-key
+    /// document as good or cannot be reached.
+    #[arg(long, requires("certs"), conflicts_with("offline"))]
+    pub ocsp: bool,
+
+    /// Check the CRLs if no OCSP responder of a host-key document can be reached.
+    #[arg(long, requires("ocsp"))]
+    pub ocsp_fallback_crl: bool,
+
     /// Use FILE as the root-CA certificate for the verification.
     ///
-    /// If omitted, the system wide-root CAs installed on the system are used.
-    /// Use this only if you trust the specified certificate.
-    #[arg(long, requires("certs"))]
-    pub root_ca: Option<PathBuf>,
+    /// If omitted, the system wide-root CAs installed on the system are used. The specified root
+    /// CAs replace them, and the chain of trust may be anchored by any of the specified root CAs.
+    /// Specify this option multiple times to use multiple root CAs. Use this only if you trust
+    /// the specified certificates.
+    #[arg(
+        long,
+        requires("certs"),
+        value_name = "ROOT_CA",
+        value_hint = ValueHint::FilePath,
+    )]
+    pub root_ca: Vec<PathBuf>,
+
+    /// Use the root CAs installed on the system in addition to the ones specified with '--root-ca'.
+    ///
+    /// The chain of trust may be anchored by the system wide-root CAs or by any of the specified
+    /// root CAs.
+    #[arg(long, requires("root_ca"))]
+    pub add_default_roots: bool,
+}
+
+impl CertVerifyOptions {
+    /// The system root CAs are trusted unless root CAs are given without '--add-default-roots'
+    fn use_default_roots(&self) -> bool {
+        self.root_ca.is_empty() || self.add_default_roots
+    }
+
+    /// Returns the verifier of this [`CertVerifyOptions`] based on the given CLI options.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the chain of trust could not be established.
+    pub fn verifier(&self) -> Result<CertVerifier> {
+        let mut builder = CertVerifierBuilder::new()
+            .offline(self.offline)
+            .default_roots(self.use_default_roots());
+        for path in &self.certs {
+            builder = builder.add_cert(path);
+        }
+        for path in &self.crls {
+            builder = builder.add_crl(path);
+        }
+        for path in &self.root_ca {
+            builder = builder.add_root_ca(path);
+        }
+        let verifier = builder.build()?;
+        Ok(match (self.ocsp, self.ocsp_fallback_crl) {
+            (false, _) => verifier,
+            (true, false) => verifier.with_ocsp(OcspMode::Required),
+            (true, true) => verifier.with_ocsp(OcspMode::FallbackCrl),
+        })
+    }
+}
+
+/// Read the host-key document in `hkd`
+///
+/// # Errors
+///
+/// This function will return an error if the file cannot be read or contains no certificate.
+pub fn read_hkd<P: AsRef<Path>>(hkd: P) -> Result<X509> {
+    let hkd = hkd.as_ref();
+    let hk = read_file(hkd, "host-key document")?;
+    let certs = read_certs(&hk).map_err(|source| Error::HkdNotPemOrDer {
+        hkd: hkd.display().to_string(),
+        source,
+    })?;
+    if certs.len() > 1 {
+        warn!(
+            "The host-key document in '{}' contains more than one certificate!",
+            hkd.display()
+        )
+    }
+    certs
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::NoHkdInFile(hkd.display().to_string()))
 }
 
 impl CertificateOptions {
@@ -95,7 +191,6 @@ impl CertificateOptions {
     ///
     /// This function will return an error if [`crate::request::HkdVerifier`] cannot be created.
     fn verifier(&self, protectee: &'static str) -> Result<Box<dyn HkdVerifier>> {
-        use pv::request::{CertVerifier, NoVerifyHkd};
         match self.no_verify {
             true => {
                 log::warn!(
@@ -103,12 +198,7 @@ impl CertificateOptions {
                 );
                 Ok(Box::new(NoVerifyHkd))
             }
-            false => Ok(Box::new(CertVerifier::new(
-                &self.certs,
-                &self.crls,
-                self.root_ca.as_ref(),
-                self.offline,
-            )?)),
+            false => Ok(Box::new(self.verify_args.verifier()?)),
         }
     }
 
@@ -126,24 +216,11 @@ impl CertificateOptions {
 
         let mut res = Vec::with_capacity(hkds.len());
         for hkd in hkds {
-            let hk = read_file(hkd, "host-key document")?;
-            let certs = read_certs(&hk).map_err(|source| Error::HkdNotPemOrDer {
+            let c = read_hkd(hkd)?;
+            verifier.verify(&c).map_err(|source| Error::HkdVerifyFile {
                 hkd: hkd.display().to_string(),
-                source,
+                source: Box::new(source),
             })?;
-            if certs.is_empty() {
-                return Err(Error::NoHkdInFile(hkd.display().to_string()));
-            }
-            if certs.len() != 1 {
-                warn!(
-                    "The host-key document in '{}' contains more than one certificate!",
-                    hkd.display()
-                )
-            }
-
-            // Panic: len is == 1 -> unwrap will succeed/not panic
-            let c = certs.first().unwrap();
-            verifier.verify(c)?;
             res.push(c.public_key()?);
             info!("Use host-key document at '{}'", hkd.display());
         }
@@ -174,6 +251,46 @@ pub fn get_reader_from_cli_file_arg<P: AsRef<Path>>(path: P) -> Result<Box<dyn R
     }
 }
 
+/// Opens `path` for writing. [`STDOUT`] opens the standard output.
+///
+/// An existing file is only truncated and overwritten if `force` is set.
+///
+/// # Errors
+///
+/// This function will return an error if the file exists and `force` is not
+/// set, or if the file could not be created.
+pub fn open_output<P: AsRef<Path>>(path: P, force: bool) -> std::io::Result<Box<dyn Write>> {
+    let path = path.as_ref();
+    if path == Path::new(STDOUT) {
+        return Ok(Box::new(std::io::stdout()));
+    }
+    let mut options = OpenOptions::new();
+    options.write(true);
+    match force {
+        true => options.create(true).truncate(true),
+        false => options.create_new(true),
+    };
+    let file = options.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => std::io::Error::new(
+            e.kind(),
+            format!(
+                "The output file '{}' already exists, use '--force' to overwrite it",
+                path.display()
+            ),
+        ),
+        _ => std::io::Error::new(e.kind(), format!("Cannot create '{}': {e}", path.display())),
+    })?;
+    Ok(Box::new(file))
+}
+
+/// Checks whether the output to `path` goes to a terminal.
+///
+/// This is the case if `path` is [`STDOUT`] and the standard output is a
+/// terminal. Use it to warn before writing binary data.
+pub fn is_tty<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref() == Path::new(STDOUT) && std::io::stdout().is_terminal()
+}
+
 /// Print an error that occurred during CLI parsing
 pub fn print_cli_error(e: clap::Error, mut cmd: Command) -> ExitCode {
     let ret = if e.use_stderr() {
@@ -202,6 +319,40 @@ where
     ExitCode::FAILURE
 }
 
+/// CLI Argument collection for selecting the uvdevice.
+#[derive(Args, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UvDeviceOptions {
+    /// Use the uvdevice at PATH.
+    ///
+    /// By default, the environment variable `PV_UV_DEVICE` is used, or if not set, the uvdevice
+    /// is searched at `/dev/uv` and `/dev/misc/uv`.
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    pub device: Option<PathBuf>,
+
+    /// Do not verify that the device is the uvdevice.
+    ///
+    /// By default, the device must be a character device with the major and minor number of the
+    /// uvdevice.
+    #[arg(long)]
+    pub device_no_check: bool,
+}
+
+impl UvDeviceOptions {
+    /// Environment variable that selects the uvdevice if `--device` is not given.
+    pub const ENV_PATH: &'static str = "PV_UV_DEVICE";
+
+    /// Open the selected uvdevice.
+    ///
+    /// The path is taken from `--device`, or from [`Self::ENV_PATH`] if that is not given.
+    pub fn open(&self) -> Result<UvDevice> {
+        let path = self
+            .device
+            .clone()
+            .or_else(|| std::env::var_os(Self::ENV_PATH).map(PathBuf::from));
+        Ok(UvDevice::open_with(path, !self.device_no_check)?)
+    }
+}
+
 #[derive(Args, Debug, Clone, Default)]
 pub struct VerbosityOptions {
     #[arg(
@@ -279,16 +430,151 @@ impl DeprecatedVerbosityOptions {
 
 #[cfg(test)]
 mod test {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::Mutex;
+
     use clap::Parser;
 
+    use crate::TemporaryDirectory;
+
     use super::*;
 
+    /// Serializes the tests that replace a standard stream
+    static STDIO: Mutex<()> = Mutex::new(());
+
+    /// Runs `f` while the file descriptor `fd` is replaced by `replacement`
+    fn with_fd<T>(fd: i32, replacement: &OwnedFd, f: impl FnOnce() -> T) -> T {
+        let _guard = STDIO.lock().unwrap();
+        // SAFETY: `fd` is a standard stream; the duplicate is restored and closed below.
+        let saved = unsafe { OwnedFd::from_raw_fd(libc::dup(fd)) };
+        // SAFETY: both file descriptors are valid.
+        assert!(unsafe { libc::dup2(replacement.as_raw_fd(), fd) } >= 0);
+        let ret = f();
+        // SAFETY: both file descriptors are valid.
+        assert!(unsafe { libc::dup2(saved.as_raw_fd(), fd) } >= 0);
+        ret
+    }
+
+    fn pipe() -> (OwnedFd, OwnedFd) {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for two file descriptors.
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        // SAFETY: the file descriptors were just created and are owned by nobody else.
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+    }
+
+    #[test]
+    fn reader_stdin() {
+        let (rd, wr) = pipe();
+        std::fs::File::from(wr).write_all(b"piped").unwrap();
+
+        let mut buf = Vec::new();
+        with_fd(libc::STDIN_FILENO, &rd, || {
+            get_reader_from_cli_file_arg(STDIN)
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap()
+        });
+        assert_eq!(buf, b"piped");
+    }
+
+    #[test]
+    fn writer_stdout() {
+        let (rd, wr) = pipe();
+        with_fd(libc::STDOUT_FILENO, &wr, || {
+            let mut out = get_writer_from_cli_file_arg(STDOUT).unwrap();
+            out.write_all(b"piped").unwrap();
+            out.flush().unwrap();
+            assert!(!is_tty(STDOUT));
+        });
+        drop(wr);
+
+        let mut buf = Vec::new();
+        std::fs::File::from(rd).read_to_end(&mut buf).unwrap();
+        // other threads of the test harness might write to stdout in the meantime
+        assert!(buf.windows(5).any(|w| w == b"piped"));
+        assert!(!Path::new(STDOUT).exists());
+    }
+
+    #[test]
+    fn writer_file() {
+        let tmp_dir = TemporaryDirectory::new().unwrap();
+        let path = tmp_dir.path().join("out");
+
+        get_writer_from_cli_file_arg(&path)
+            .unwrap()
+            .write_all(b"first")
+            .unwrap();
+        get_writer_from_cli_file_arg(&path)
+            .unwrap()
+            .write_all(b"2nd")
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"2nd");
+
+        let mut buf = String::new();
+        get_reader_from_cli_file_arg(&path)
+            .unwrap()
+            .read_to_string(&mut buf)
+            .unwrap();
+        assert_eq!(buf, "2nd");
+        assert!(!is_tty(&path));
+    }
+
+    #[test]
+    fn open_output_stdout() {
+        let (rd, wr) = pipe();
+        with_fd(libc::STDOUT_FILENO, &wr, || {
+            let mut out = open_output(STDOUT, false).unwrap();
+            out.write_all(b"piped").unwrap();
+            out.flush().unwrap();
+        });
+        drop(wr);
+
+        let mut buf = Vec::new();
+        std::fs::File::from(rd).read_to_end(&mut buf).unwrap();
+        assert!(buf.windows(5).any(|w| w == b"piped"));
+        assert!(!Path::new(STDOUT).exists());
+    }
+
+    #[test]
+    fn open_output_overwrite() {
+        let tmp_dir = TemporaryDirectory::new().unwrap();
+        let path = tmp_dir.path().join("out");
+
+        open_output(&path, false)
+            .unwrap()
+            .write_all(b"first")
+            .unwrap();
+        let err = open_output(&path, false).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(err.to_string().contains("'--force'"), "{err}");
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+
+        open_output(&path, true).unwrap().write_all(b"2nd").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"2nd");
+    }
+
+    #[test]
+    fn reader_missing() {
+        let err = get_reader_from_cli_file_arg("does-not-exist")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`does-not-exist`"), "{err}");
+    }
+
     #[test]
     #[rustfmt::skip]
     fn cli_args() {
         //Verify only that some arguments are optional, we do not want to test clap, only the
         //configuration
-        let valid_args = [vec!["pgr", "-k", "hkd.crt", "--no-verify"], vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt"]];
+        let valid_args = [
+            vec!["pgr", "-k", "hkd.crt", "--no-verify"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--ocsp"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--ocsp", "--ocsp-fallback-crl"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--root-ca", "a.crt", "--root-ca", "b.crt"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--root-ca", "a.crt", "--add-default-roots"],
+        ];
         // Test for the minimal amount of flags to yield an invalid combination
         let invalid_args = [
             vec!["pgr", "-k", "hkd.crt"],
@@ -299,6 +585,9 @@ mod test {
             vec!["pgr", "--offline"],
             vec!["pgr", "--crl", "abc.crl"],
             vec!["pgr", "--root-ca", "root.crt"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--ocsp", "--offline"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--ocsp-fallback-crl"],
+            vec!["pgr", "-k", "hkd.crt", "--crt", "abc.crt", "--add-default-roots"],
         ];
         #[derive(Parser, Debug)]
         struct TestParser {
@@ -316,4 +605,56 @@ mod test {
             assert!(res.is_err());
         }
     }
+
+    #[test]
+    fn default_roots() {
+        let opt = |args: &[&str]| {
+            #[derive(Parser, Debug)]
+            struct TestParser {
+                #[command(flatten)]
+                pub verify_args: CertVerifyOptions,
+            }
+            let args = ["pgr", "--crt", "abc.crt"].iter().chain(args);
+            TestParser::try_parse_from(args).unwrap().verify_args
+        };
+        assert!(opt(&[]).use_default_roots());
+        // given root CAs replace the system roots unless explicitly added
+        assert!(!opt(&["--root-ca", "a.crt"]).use_default_roots());
+        assert!(opt(&["--root-ca", "a.crt", "--add-default-roots"]).use_default_roots());
+    }
+
+    #[test]
+    fn get_verified_hkds_names_failing_hkd() {
+        let cert = |name: &str| {
+            PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../pv/tests/assets/cert/"
+            ))
+            .join(name)
+        };
+        let mut opt = CertificateOptions {
+            host_key_documents: vec![cert("host.crt"), cert("host2.crt")],
+            verify_args: CertVerifyOptions {
+                certs: vec![cert("inter_ca.crt"), cert("ibm.crt")],
+                crls: vec![cert("inter_ca.crl"), cert("ibm.crl")],
+                root_ca: vec![cert("root_ca.chained.crt")],
+                offline: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(opt.get_verified_hkds("test").unwrap().len(), 2);
+
+        opt.host_key_documents.push(cert("host_rev.crt"));
+        match opt.get_verified_hkds("test") {
+            Err(Error::HkdVerifyFile { hkd, source }) => {
+                assert!(hkd.ends_with("host_rev.crt"), "{hkd}");
+                assert!(matches!(
+                    *source,
+                    Error::HkdVerify(pv::HkdVerifyErrorType::HkdRevoked)
+                ));
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
 }
@@ -4,10 +4,13 @@
 
 use std::{
     ffi::{CString, OsStr},
-    fs::{File, OpenOptions},
+    fs::{File, OpenOptions, Permissions},
     io::{self, Seek, SeekFrom, Write},
-    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt},
-    path::Path,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{OpenOptionsExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
 };
 
 use pv::{Error, FileAccessErrorType, PvCoreError, Result};
@@ -181,6 +184,158 @@ impl Write for AtomicFile {
     }
 }
 
+/// Writer that atomically replaces a file when [`AtomicWriter::commit`] is
+/// called.
+///
+/// A thin layer over [`AtomicFile`] that additionally sets the permissions of
+/// the destination and makes the rename durable. If [`AtomicWriter::commit`]
+/// is never called, the temporary file is removed when it goes out of scope
+/// and the destination is left untouched. Created by [`atomic_writer`].
+#[derive(Debug)]
+pub struct AtomicWriter {
+    file: AtomicFile,
+    mode: u32,
+}
+
+impl AtomicWriter {
+    /// Returns the path of the destination file.
+    pub fn dest(&self) -> &Path {
+        &self.file.path.path
+    }
+
+    /// Sets the permissions and renames the temporary file over the
+    /// destination using [`AtomicFile::finish`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the permissions could not be set
+    /// or the rename fails. The temporary file is removed and the destination
+    /// is left untouched in this case.
+    pub fn commit(self) -> Result<()> {
+        let dir = match self.dest().parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        self.file
+            .as_ref()
+            .set_permissions(Permissions::from_mode(self.mode))?;
+        self.file.finish(AtomicFileOperation::Replace)?;
+        // Make the rename itself durable.
+        Ok(File::open(dir)?.sync_all()?)
+    }
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Creates an [`AtomicWriter`] for `dest` that sets the permissions `mode` on
+/// commit.
+///
+/// The temporary file is created next to `dest`, see [`AtomicFile::new`]. An
+/// existing `dest` is replaced on commit.
+///
+/// # Errors
+///
+/// This function will return an error if `dest` has no file name or the
+/// temporary file could not be created.
+///
+/// # Example
+///
+/// ```
+/// # use utils::{atomic_writer, TemporaryDirectory};
+/// # use std::io::Write;
+/// # let dir = TemporaryDirectory::new().unwrap();
+/// # let dest = dir.path().join("output");
+/// let mut writer = atomic_writer(&dest, 0o644).unwrap();
+/// writer.write_all(b"data").unwrap();
+/// writer.commit().unwrap();
+/// ```
+pub fn atomic_writer<P: AsRef<Path>>(dest: P, mode: u32) -> Result<AtomicWriter> {
+    let dest = dest.as_ref();
+    if dest.file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is no valid file name", dest.display()),
+        )
+        .into());
+    }
+    Ok(AtomicWriter {
+        file: AtomicFile::new(dest, &mut OpenOptions::new())?,
+        mode,
+    })
+}
+
+/// Atomically replaces `dest` with `contents` and sets the permissions
+/// `mode`.
+///
+/// Either the old or the new contents are visible at `dest`, even if the
+/// process is interrupted. See [`atomic_writer`] for streaming the data.
+///
+/// # Errors
+///
+/// This function will return an error if the temporary file could not be
+/// created, written, or renamed. `dest` is left untouched in this case.
+///
+/// # Example
+///
+/// ```
+/// # use utils::{atomic_write, TemporaryDirectory};
+/// # let dir = TemporaryDirectory::new().unwrap();
+/// # let dest = dir.path().join("output");
+/// atomic_write(&dest, b"data", 0o600).unwrap();
+/// assert_eq!(std::fs::read(dest).unwrap(), b"data");
+/// ```
+pub fn atomic_write<P: AsRef<Path>>(dest: P, contents: &[u8], mode: u32) -> Result<()> {
+    let mut writer = atomic_writer(dest, mode)?;
+    writer.write_all(contents)?;
+    writer.commit()
+}
+
+/// Order of the files returned by [`sorted_dir_files`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOrder {
+    /// Sort the files by their name.
+    Name,
+    /// Sort the files by their modification time, oldest first. The name is the tie-breaker.
+    Mtime,
+}
+
+/// Returns the regular files of `dir` (non-recursive) sorted by `order`.
+///
+/// Symlinks are followed. Entries whose metadata cannot be read, e.g., broken symlinks, are
+/// returned as well, so that the caller reports the error when reading them.
+///
+/// # Errors
+///
+/// This function will return an error if `dir` could not be read.
+pub fn sorted_dir_files<P: AsRef<Path>>(dir: P, order: FileOrder) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let err = |source| PvCoreError::FileAccess {
+        ty: FileAccessErrorType::Open,
+        path: dir.to_owned(),
+        source,
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(err)? {
+        let path = entry.map_err(err)?.path();
+        let mtime = match std::fs::metadata(&path) {
+            Ok(meta) if !meta.is_file() => continue,
+            Ok(meta) if order == FileOrder::Mtime => meta.modified().ok(),
+            _ => None,
+        };
+        files.push((mtime, path));
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
 #[derive(Debug)]
 struct TempPath {
     temp_path: Box<Path>,
@@ -245,10 +400,20 @@ impl Drop for TempPath {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Write;
+    use std::{io::Write, os::unix::fs::PermissionsExt};
 
-    use super::{AtomicFile, AtomicFileOperation};
+    use super::{
+        atomic_write, atomic_writer, sorted_dir_files, AtomicFile, AtomicFileOperation, FileOrder,
+    };
     use crate::TemporaryDirectory;
+    use pv::Error;
+
+    fn dir_entries(dir: &TemporaryDirectory) -> Vec<std::ffi::OsString> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect()
+    }
 
     #[test]
     fn atomicfile_basic_functionality() {
@@ -304,4 +469,103 @@ mod tests {
         drop(writer);
         assert!(!tmp_file.exists());
     }
+
+    #[test]
+    fn atomic_writer_drop() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let dest = tmp_dir.path().join("atomic_drop");
+        std::fs::write(&dest, b"old").unwrap();
+
+        let mut writer = atomic_writer(&dest, 0o600).unwrap();
+        writer.write_all(b"new").unwrap();
+        drop(writer);
+
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old");
+        assert_eq!(dir_entries(&tmp_dir), ["atomic_drop"]);
+    }
+
+    #[test]
+    fn atomic_writer_commit() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let dest = tmp_dir.path().join("atomic_commit");
+        std::fs::write(&dest, b"old data").unwrap();
+
+        let mut writer = atomic_writer(&dest, 0o640).unwrap();
+        assert_eq!(writer.dest(), dest);
+        writer.write_all(b"new").unwrap();
+        // not visible before the commit
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old data");
+        writer.commit().unwrap();
+
+        assert_eq!(std::fs::read(&dest).unwrap(), b"new");
+        let mode = dest.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o640);
+        assert_eq!(dir_entries(&tmp_dir), ["atomic_commit"]);
+    }
+
+    #[test]
+    fn atomic_write_mode() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let dest = tmp_dir.path().join("atomic_write");
+
+        atomic_write(&dest, b"data", 0o644).unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"data");
+        let mode = dest.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o644);
+
+        atomic_write(&dest, b"secret", 0o600).unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"secret");
+        let mode = dest.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o600);
+    }
+
+    #[test]
+    fn atomic_writer_other_dir() {
+        let cwd = std::fs::read_dir(".").unwrap().count();
+        let tmp_dir = TemporaryDirectory::new_in_temp_dir().expect("should work");
+        let dest = tmp_dir.path().join("atomic_other");
+
+        let writer = atomic_writer(&dest, 0o600).unwrap();
+        // the temporary file resides next to the destination
+        assert_eq!(dir_entries(&tmp_dir).len(), 1);
+        assert_eq!(std::fs::read_dir(".").unwrap().count(), cwd);
+        writer.commit().unwrap();
+        assert_eq!(dir_entries(&tmp_dir), ["atomic_other"]);
+    }
+
+    #[test]
+    fn atomic_writer_invalid() {
+        let err = atomic_writer("/", 0o600).expect_err("no file name");
+        assert!(matches!(err, Error::Io(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn sorted_dir_files_order() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1000);
+        for (i, name) in ["c", "a", "b"].iter().enumerate() {
+            let file = std::fs::File::create(tmp_dir.path().join(name)).unwrap();
+            file.set_modified(mtime - std::time::Duration::from_secs(i as u64))
+                .unwrap();
+        }
+        std::fs::create_dir(tmp_dir.path().join("dir")).unwrap();
+        std::os::unix::fs::symlink("missing", tmp_dir.path().join("broken")).unwrap();
+
+        let names = |order| -> Vec<_> {
+            sorted_dir_files(tmp_dir.path(), order)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.file_name().unwrap().to_owned())
+                .collect()
+        };
+        assert_eq!(names(FileOrder::Name), ["a", "b", "broken", "c"]);
+        assert_eq!(names(FileOrder::Mtime), ["broken", "b", "a", "c"]);
+    }
+
+    #[test]
+    fn sorted_dir_files_missing() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        sorted_dir_files(tmp_dir.path().join("missing"), FileOrder::Name)
+            .expect_err("missing directory");
+    }
 }
@@ -3,7 +3,7 @@
 // Copyright IBM Corp. 2024
 
 use serde::Serialize;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, LowerHex, UpperHex};
 
 /// Displays/Serializes an u8-slice into a Hex-string
 ///
@@ -57,6 +57,52 @@ impl Display for HexSlice<'_> {
     }
 }
 
+impl LowerHex for HexSlice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl UpperHex for HexSlice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
+/// Displays a [`HexSlice`] with a separator between the bytes
+///
+/// Created by [`HexSlice::separated`].
+#[derive(Debug, Clone)]
+pub struct SeparatedHexSlice<'a> {
+    hex: &'a HexSlice<'a>,
+    sep: char,
+}
+
+impl Display for SeparatedHexSlice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, byte) in self.hex.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", self.sep)?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> HexSlice<'a> {
+    /// Displays the bytes with `sep` in between, e.g. `aa:bb:cc` for `:`.
+    pub fn separated(&'a self, sep: char) -> SeparatedHexSlice<'a> {
+        SeparatedHexSlice { hex: self, sep }
+    }
+}
+
 impl AsRef<[u8]> for HexSlice<'_> {
     fn as_ref(&self) -> &[u8] {
         self.0
@@ -98,4 +144,59 @@ mod test {
 
         assert_eq!(exp, format!("{hex:-#}"));
     }
+
+    #[test]
+    fn display_empty() {
+        let hex = HexSlice::from(&[]);
+
+        assert_eq!("", format!("{hex}"));
+        assert_eq!("0x", format!("{hex:#}"));
+        assert_eq!("", format!("{hex:x}"));
+        assert_eq!("", format!("{}", hex.separated(':')));
+    }
+
+    #[test]
+    fn lower_upper_hex() {
+        let hex = HexSlice::from(&[0xab, 0x0c, 0xde]);
+
+        assert_eq!("ab0cde", format!("{hex:x}"));
+        assert_eq!("0xab0cde", format!("{hex:#x}"));
+        assert_eq!("AB0CDE", format!("{hex:X}"));
+        assert_eq!("0xAB0CDE", format!("{hex:#X}"));
+    }
+
+    #[test]
+    fn separated() {
+        let hex = HexSlice::from(&[0xaa, 0x0b, 0xcc]);
+
+        assert_eq!("aa:0b:cc", hex.separated(':').to_string());
+        assert_eq!("aa 0b cc", hex.separated(' ').to_string());
+        assert_eq!("aa", HexSlice::from(&[0xaa]).separated(':').to_string());
+    }
+
+    #[test]
+    fn serialize() {
+        let hex = HexSlice::from(&[0x01, 0xff]);
+
+        serde_test::assert_ser_tokens(&hex, &[serde_test::Token::Str("0x01ff")]);
+    }
+
+    #[test]
+    fn decode_roundtrip() {
+        let exp = [0xaa, 0x0b, 0xcc];
+        let hex = HexSlice::from(&exp);
+
+        assert_eq!(
+            pv::misc::decode_hex_formatted(hex.to_string()).unwrap(),
+            exp
+        );
+        assert_eq!(
+            pv::misc::decode_hex_formatted(format!("{hex:#}")).unwrap(),
+            exp
+        );
+        assert_eq!(
+            pv::misc::decode_hex_formatted(hex.separated(':').to_string()).unwrap(),
+            exp
+        );
+    }
 }
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{fmt::Display, path::Path};
+
+use pv::request::VerificationReport;
+use serde::Serialize;
+
+/// Validity report of a host-key document file
+///
+/// Serializes as the [`VerificationReport`] with the additional member `path`. Displays as
+/// indented human-readable text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HkdReport {
+    path: String,
+    #[serde(flatten)]
+    report: VerificationReport,
+}
+
+impl HkdReport {
+    /// Creates a new [`HkdReport`] of the host-key document in `path`.
+    pub fn new<P: AsRef<Path>>(path: P, report: VerificationReport) -> Self {
+        Self {
+            path: path.as_ref().display().to_string(),
+            report,
+        }
+    }
+
+    /// The path of the host-key document
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The validity report of the host-key document
+    pub fn report(&self) -> &VerificationReport {
+        &self.report
+    }
+
+    /// Warning if the report expires in less than `days` days, prefixed with the path
+    ///
+    /// See [`VerificationReport::expiry_warning`].
+    pub fn expiry_warning(&self, days: u32) -> Option<String> {
+        self.report
+            .expiry_warning(days)
+            .map(|warning| format!("'{}': {warning}", self.path))
+    }
+}
+
+impl Display for HkdReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:", self.path)?;
+        for crt in self.report.certificates() {
+            writeln!(f, "  {} '{}'", crt.kind(), crt.subject())?;
+            writeln!(f, "    serial:      {}", crt.serial())?;
+            writeln!(f, "    not before:  {}", crt.not_before())?;
+            writeln!(f, "    not after:   {}", crt.not_after())?;
+        }
+        for crl in self.report.crls() {
+            writeln!(f, "  CRL of '{}'", crl.issuer())?;
+            writeln!(f, "    this update: {}", crl.this_update())?;
+            if let Some(next) = crl.next_update() {
+                writeln!(f, "    next update: {next}")?;
+            }
+        }
+        match self.report.is_expired() {
+            true => writeln!(f, "  expired: {}", self.report.expiring()),
+            false => writeln!(
+                f,
+                "  expires in {} day(s): {}",
+                self.report.expires_in_days(),
+                self.report.expiring()
+            ),
+        }
+    }
+}
@@ -3,25 +3,40 @@
 //! Not intened to be used outside of s390-tools.
 //!
 //! Copyright IBM Corp. 2023, 2024
+pub mod canonical_json;
 mod cli;
 mod exit_code;
 mod file;
 mod hexslice;
+mod hkd_report;
+mod lock;
 mod log;
+mod size;
+mod text;
 mod tmpfile;
+mod version;
 
 pub use ::log::LevelFilter;
 
 pub use crate::{
     cli::{
-        get_reader_from_cli_file_arg, get_writer_from_cli_file_arg, print_cli_error, print_error,
-        CertificateOptions, DeprecatedVerbosityOptions, VerbosityOptions, STDIN, STDOUT,
+        get_reader_from_cli_file_arg, get_writer_from_cli_file_arg, is_tty, open_output,
+        print_cli_error, print_error, read_hkd, CertVerifyOptions, CertificateOptions,
+        DeprecatedVerbosityOptions, UvDeviceOptions, VerbosityOptions, STDIN, STDOUT,
     },
     exit_code::{docstring, ExitCodeDoc, ExitCodeTrait, ExitCodeVariantDoc},
-    file::{AtomicFile, AtomicFileOperation},
-    hexslice::HexSlice,
+    file::{
+        atomic_write, atomic_writer, sorted_dir_files, AtomicFile, AtomicFileOperation,
+        AtomicWriter, FileOrder,
+    },
+    hexslice::{HexSlice, SeparatedHexSlice},
+    hkd_report::HkdReport,
+    lock::FileLock,
     log::PvLogger,
-    tmpfile::TemporaryDirectory,
+    size::{format_size, parse_size, SizeParseError, SizeValueParser},
+    text::{read_text, read_text_lines},
+    tmpfile::{TemporaryDirectory, TemporaryFile},
+    version::{Version, VersionError, VersionReq},
 };
 
 /// Get the s390-tools release string
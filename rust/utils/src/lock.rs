@@ -0,0 +1,313 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Interval between two attempts of [`FileLock::exclusive_timeout`] and
+/// [`FileLock::shared_timeout`]
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Rust wrapper for `libc::flock`
+///
+/// Returns `Ok(false)` if `LOCK_NB` is part of `operation` and the lock is held
+/// by somebody else.
+fn flock(file: &File, operation: libc::c_int) -> io::Result<bool> {
+    loop {
+        // SAFETY: The file descriptor is valid as long as `file` lives.
+        let ret = unsafe { libc::flock(file.as_raw_fd(), operation) };
+        if ret == 0 {
+            return Ok(true);
+        }
+        let err = io::Error::last_os_error();
+        match err.kind() {
+            io::ErrorKind::Interrupted => continue,
+            io::ErrorKind::WouldBlock => return Ok(false),
+            _ => return Err(err),
+        }
+    }
+}
+
+/// This type holds an advisory lock on a file that is automatically released
+/// when it goes out of scope. It utilizes the `flock` function and its
+/// semantics.
+///
+/// The lock file is created if it does not exist and is not removed
+/// afterwards. Locks are bound to the open file, therefore two [`FileLock`]s
+/// for the same path exclude each other even within the same process.
+///
+/// # Example
+///
+/// ```
+/// # use utils::{FileLock, TemporaryDirectory};
+/// # let dir = TemporaryDirectory::new().unwrap();
+/// # let path = dir.path().join("lock");
+/// let lock = FileLock::exclusive(&path).unwrap();
+/// assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+/// drop(lock);
+/// assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+/// ```
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    fn open(path: &Path) -> io::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Cannot open the lock file '{}': {e}", path.display()),
+                )
+            })
+    }
+
+    fn lock(path: &Path, operation: libc::c_int) -> io::Result<Option<Self>> {
+        let file = Self::open(path)?;
+        Ok(flock(&file, operation)?.then(|| Self {
+            file,
+            path: path.to_owned(),
+        }))
+    }
+
+    fn lock_timeout(
+        path: &Path,
+        operation: libc::c_int,
+        timeout: Duration,
+    ) -> io::Result<Option<Self>> {
+        let file = Self::open(path)?;
+        let start = Instant::now();
+        loop {
+            if flock(&file, operation | libc::LOCK_NB)? {
+                return Ok(Some(Self {
+                    file,
+                    path: path.to_owned(),
+                }));
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(None);
+            }
+            std::thread::sleep(RETRY_INTERVAL.min(timeout - elapsed));
+        }
+    }
+
+    /// Acquires an exclusive lock on `path`, waiting until it is available.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn exclusive<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::lock(path.as_ref(), libc::LOCK_EX)
+            .map(|lock| lock.expect("Blocking flock returned without the lock"))
+    }
+
+    /// Acquires a shared lock on `path`, waiting until it is available.
+    ///
+    /// Any number of shared locks can be held at the same time, but none
+    /// while an exclusive lock is held.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn shared<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::lock(path.as_ref(), libc::LOCK_SH)
+            .map(|lock| lock.expect("Blocking flock returned without the lock"))
+    }
+
+    /// Tries to acquire an exclusive lock on `path` without waiting.
+    ///
+    /// Returns `Ok(None)` if a lock is held by somebody else.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn try_exclusive<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        Self::lock(path.as_ref(), libc::LOCK_EX | libc::LOCK_NB)
+    }
+
+    /// Tries to acquire a shared lock on `path` without waiting.
+    ///
+    /// Returns `Ok(None)` if an exclusive lock is held by somebody else.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn try_shared<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        Self::lock(path.as_ref(), libc::LOCK_SH | libc::LOCK_NB)
+    }
+
+    /// Acquires an exclusive lock on `path`, waiting at most `timeout`.
+    ///
+    /// Returns `Ok(None)` if the lock is still held by somebody else after
+    /// `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn exclusive_timeout<P: AsRef<Path>>(
+        path: P,
+        timeout: Duration,
+    ) -> io::Result<Option<Self>> {
+        Self::lock_timeout(path.as_ref(), libc::LOCK_EX, timeout)
+    }
+
+    /// Acquires a shared lock on `path`, waiting at most `timeout`.
+    ///
+    /// Returns `Ok(None)` if an exclusive lock is still held by somebody else
+    /// after `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the lock file could not be
+    /// opened or created, or if the lock could not be acquired.
+    pub fn shared_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> io::Result<Option<Self>> {
+        Self::lock_timeout(path.as_ref(), libc::LOCK_SH, timeout)
+    }
+
+    /// Returns a reference to the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for FileLock {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock as well.
+        let _ = flock(&self.file, libc::LOCK_UN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Barrier,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::FileLock;
+    use crate::TemporaryDirectory;
+
+    #[test]
+    fn file_lock_is_send() {
+        fn is_send<T: Send>() {}
+        is_send::<FileLock>();
+    }
+
+    #[test]
+    fn file_lock_creates_file() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("lock");
+        assert!(!path.exists());
+
+        let lock = FileLock::exclusive(&path).unwrap();
+        assert_eq!(lock.path(), path);
+        drop(lock);
+        // the lock file is kept
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn file_lock_exclusive() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("lock");
+
+        let lock = FileLock::exclusive(&path).unwrap();
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        assert!(FileLock::try_shared(&path).unwrap().is_none());
+        drop(lock);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn file_lock_shared() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("lock");
+
+        let lock = FileLock::shared(&path).unwrap();
+        let lock2 = FileLock::try_shared(&path).unwrap();
+        assert!(lock2.is_some());
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(lock);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+        drop(lock2);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn file_lock_threads() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("lock");
+        let released = Arc::new(AtomicBool::new(false));
+        let locked = Arc::new(Barrier::new(2));
+
+        let thread = {
+            let (path, released, locked) = (path.clone(), released.clone(), locked.clone());
+            std::thread::spawn(move || {
+                let lock = FileLock::exclusive(&path).unwrap();
+                locked.wait();
+                std::thread::sleep(Duration::from_millis(100));
+                released.store(true, Ordering::SeqCst);
+                drop(lock);
+            })
+        };
+
+        locked.wait();
+        let _lock = FileLock::exclusive(&path).unwrap();
+        assert!(released.load(Ordering::SeqCst));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn file_lock_timeout() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("lock");
+        let timeout = Duration::from_millis(50);
+
+        let lock = FileLock::exclusive(&path).unwrap();
+        let start = Instant::now();
+        assert!(FileLock::exclusive_timeout(&path, timeout)
+            .unwrap()
+            .is_none());
+        assert!(start.elapsed() >= timeout);
+        assert!(FileLock::shared_timeout(&path, timeout).unwrap().is_none());
+
+        drop(lock);
+        assert!(FileLock::shared_timeout(&path, timeout).unwrap().is_some());
+    }
+
+    #[test]
+    fn file_lock_no_dir() {
+        let tmp_dir = TemporaryDirectory::new().expect("should work");
+        let path = tmp_dir.path().join("does/not/exist");
+        let err = FileLock::try_exclusive(path).expect_err("no directory");
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+}
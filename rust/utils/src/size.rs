@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use clap::{
+    builder::{StringValueParser, TypedValueParser},
+    Arg, Command,
+};
+use std::{
+    ffi::OsStr,
+    fmt::{Display, Formatter},
+    ops::{Bound, RangeBounds},
+};
+
+/// Binary size suffixes and the binary logarithm of their factors, smallest first
+const SUFFIXES: [(char, u32); 4] = [('K', 10), ('M', 20), ('G', 30), ('T', 40)];
+
+/// Error returned by [`parse_size`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeParseError {
+    /// The string is empty
+    Empty,
+    /// The size is negative
+    Negative,
+    /// The string does not start with a decimal number
+    InvalidNumber(String),
+    /// The number is followed by something else than one size suffix
+    InvalidSuffix(String),
+    /// The size does not fit into 64 bits
+    Overflow(String),
+    /// The size is not in the accepted range, see [`SizeValueParser::range`]
+    OutOfRange { size: u64, min: u64, max: u64 },
+}
+
+impl Display for SizeParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty size"),
+            Self::Negative => write!(f, "The size must not be negative"),
+            Self::InvalidNumber(s) => write!(f, "'{s}' does not start with a decimal number"),
+            Self::InvalidSuffix(s) => {
+                write!(f, "Invalid size suffix '{s}'. Must be one of K, M, G, or T")
+            }
+            Self::Overflow(s) => write!(
+                f,
+                "The size '{s}' is too large. Must be at most {} bytes",
+                u64::MAX
+            ),
+            Self::OutOfRange { size, min, max } => write!(
+                f,
+                "The size {} is out of range. Must be between {} and {}",
+                format_size(*size),
+                format_size(*min),
+                format_size(*max)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SizeParseError {}
+
+/// Parses a size in bytes.
+///
+/// Accepts a decimal number followed by an optional binary suffix K, M, G, or
+/// T (1024-based, case-insensitive).
+///
+/// # Errors
+///
+/// This function will return an error if the string is empty, negative,
+/// contains anything but the number and one suffix, or if the size does not
+/// fit into 64 bits.
+///
+/// # Example
+///
+/// ```
+/// # use utils::parse_size;
+/// assert_eq!(parse_size("512").unwrap(), 512);
+/// assert_eq!(parse_size("16k").unwrap(), 16 * 1024);
+/// assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, SizeParseError> {
+    if s.is_empty() {
+        return Err(SizeParseError::Empty);
+    }
+    if s.starts_with('-') {
+        return Err(SizeParseError::Negative);
+    }
+    let (num, suffix) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    if num.is_empty() {
+        return Err(SizeParseError::InvalidNumber(s.to_string()));
+    }
+    let shift = match suffix.chars().collect::<Vec<_>>()[..] {
+        [] => 0,
+        [c] => SUFFIXES
+            .iter()
+            .find(|(sfx, _)| sfx.eq_ignore_ascii_case(&c))
+            .map(|(_, shift)| *shift)
+            .ok_or_else(|| SizeParseError::InvalidSuffix(suffix.to_string()))?,
+        _ => return Err(SizeParseError::InvalidSuffix(suffix.to_string())),
+    };
+    // num contains only digits, so parsing fails only on overflow
+    num.parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| SizeParseError::Overflow(s.to_string()))
+}
+
+/// Formats a size in bytes.
+///
+/// Uses the largest suffix of [`parse_size`] that represents the size
+/// exactly, so that the result is as short as possible and parses to the
+/// same size.
+///
+/// # Example
+///
+/// ```
+/// # use utils::format_size;
+/// assert_eq!(format_size(512), "512");
+/// assert_eq!(format_size(16 * 1024), "16K");
+/// assert_eq!(format_size(1536), "1536");
+/// ```
+pub fn format_size(size: u64) -> String {
+    match SUFFIXES
+        .iter()
+        .rev()
+        .find(|(_, shift)| size != 0 && size.trailing_zeros() >= *shift)
+    {
+        Some((sfx, shift)) => format!("{}{sfx}", size >> shift),
+        None => size.to_string(),
+    }
+}
+
+/// Clap value parser for sizes, see [`parse_size`]
+///
+/// # Example
+///
+/// ```
+/// # use clap::Parser;
+/// # use utils::SizeValueParser;
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[arg(long, value_parser = SizeValueParser::new().range(1..=1 << 20))]
+///     size: u64,
+/// }
+/// let cli = Cli::parse_from(["cli", "--size", "4k"]);
+/// assert_eq!(cli.size, 4096);
+/// assert!(Cli::try_parse_from(["cli", "--size", "2M"]).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeValueParser {
+    min: u64,
+    max: u64,
+}
+
+impl SizeValueParser {
+    /// Creates a [`SizeValueParser`] that accepts all sizes.
+    pub fn new() -> Self {
+        Self {
+            min: 0,
+            max: u64::MAX,
+        }
+    }
+
+    /// Only accept sizes in `range`.
+    pub fn range<R: RangeBounds<u64>>(self, range: R) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&min) => min,
+            Bound::Excluded(&min) => min.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&max) => max,
+            Bound::Excluded(&max) => max.saturating_sub(1),
+            Bound::Unbounded => u64::MAX,
+        };
+        Self { min, max }
+    }
+
+    fn parse(&self, s: &str) -> Result<u64, SizeParseError> {
+        let size = parse_size(s)?;
+        if !(self.min..=self.max).contains(&size) {
+            return Err(SizeParseError::OutOfRange {
+                size,
+                min: self.min,
+                max: self.max,
+            });
+        }
+        Ok(size)
+    }
+}
+
+impl Default for SizeValueParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypedValueParser for SizeValueParser {
+    type Value = u64;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let parser = *self;
+        StringValueParser::new()
+            .try_map(move |s| parser.parse(&s))
+            .parse_ref(cmd, arg, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let cases = [
+            ("0", 0),
+            ("0K", 0),
+            ("1", 1),
+            ("0010", 10),
+            ("512", 512),
+            ("1k", 1024),
+            ("1K", 1024),
+            ("1023K", 1023 * 1024),
+            ("1025k", 1025 * 1024),
+            ("16M", 16 << 20),
+            ("2g", 2 << 30),
+            ("3T", 3 << 40),
+            ("1536", 1536),
+            ("18446744073709551615", u64::MAX),
+            ("16777215T", 0xffff_ff00_0000_0000),
+        ];
+        for (s, exp) in cases {
+            assert_eq!(parse_size(s), Ok(exp), "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        use SizeParseError::*;
+        let cases = [
+            ("", Empty),
+            ("-1", Negative),
+            ("-0", Negative),
+            ("-1K", Negative),
+            ("K", InvalidNumber("K".to_string())),
+            ("+1", InvalidNumber("+1".to_string())),
+            (" 1", InvalidNumber(" 1".to_string())),
+            ("0x10", InvalidSuffix("x10".to_string())),
+            ("1.5K", InvalidSuffix(".5K".to_string())),
+            ("1 K", InvalidSuffix(" K".to_string())),
+            ("1K ", InvalidSuffix("K ".to_string())),
+            ("16MB", InvalidSuffix("MB".to_string())),
+            ("16KiB", InvalidSuffix("KiB".to_string())),
+            ("1P", InvalidSuffix("P".to_string())),
+            ("1Kä", InvalidSuffix("Kä".to_string())),
+            (
+                "18446744073709551616",
+                Overflow("18446744073709551616".to_string()),
+            ),
+            ("16777216T", Overflow("16777216T".to_string())),
+            (
+                "99999999999999999999999K",
+                Overflow("99999999999999999999999K".to_string()),
+            ),
+        ];
+        for (s, exp) in cases {
+            assert_eq!(parse_size(s), Err(exp), "{s}");
+        }
+    }
+
+    #[test]
+    fn format() {
+        let cases = [
+            (0, "0"),
+            (1, "1"),
+            (1023, "1023"),
+            (1024, "1K"),
+            (1025, "1025"),
+            (1536, "1536"),
+            (1023 * 1024, "1023K"),
+            (1024 * 1024, "1M"),
+            (1025 * 1024, "1025K"),
+            (3 << 30, "3G"),
+            (1 << 40, "1T"),
+            (1 << 50, "1024T"),
+            (0xffff_ff00_0000_0000, "16777215T"),
+            (u64::MAX, "18446744073709551615"),
+        ];
+        for (size, exp) in cases {
+            assert_eq!(format_size(size), exp, "{size}");
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        for size in [
+            0,
+            1,
+            1023,
+            1024,
+            4095,
+            4096,
+            1 << 20,
+            (1 << 40) + 1,
+            u64::MAX,
+        ] {
+            assert_eq!(parse_size(&format_size(size)), Ok(size));
+        }
+    }
+
+    #[test]
+    fn value_parser() {
+        let cmd = Command::new("test");
+        let parse = |p: SizeValueParser, s: &str| p.parse_ref(&cmd, None, OsStr::new(s));
+
+        assert_eq!(parse(SizeValueParser::new(), "4k").unwrap(), 4096);
+        assert_eq!(parse(SizeValueParser::new(), "0").unwrap(), 0);
+        assert!(parse(SizeValueParser::new(), "4x").is_err());
+
+        let p = SizeValueParser::new().range(1..=1 << 20);
+        assert_eq!(parse(p, "1").unwrap(), 1);
+        assert_eq!(parse(p, "1M").unwrap(), 1 << 20);
+        assert!(parse(p, "0").is_err());
+        assert!(parse(p, "1025K").is_err());
+
+        let p = SizeValueParser::new().range(4096..);
+        assert_eq!(parse(p, &u64::MAX.to_string()).unwrap(), u64::MAX);
+        assert_eq!(
+            p.parse("4095"),
+            Err(SizeParseError::OutOfRange {
+                size: 4095,
+                min: 4096,
+                max: u64::MAX
+            })
+        );
+        assert_eq!(
+            SizeValueParser::new().range(..1024).parse("1K"),
+            Err(SizeParseError::OutOfRange {
+                size: 1024,
+                min: 0,
+                max: 1023
+            })
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let err = SizeValueParser::new()
+            .range(1..=1 << 20)
+            .parse("2M")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The size 2M is out of range. Must be between 1 and 1M"
+        );
+        assert_eq!(
+            parse_size("16MB").unwrap_err().to_string(),
+            "Invalid size suffix 'MB'. Must be one of K, M, G, or T"
+        );
+    }
+}
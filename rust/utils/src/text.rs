@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use log::warn;
+use std::io::{self, Read};
+
+const UTF8_BOM: &str = "\u{feff}";
+const UTF16_BOMS: [[u8; 2]; 2] = [[0xff, 0xfe], [0xfe, 0xff]];
+
+/// Reads textual input and returns its lines.
+///
+/// Same as [`read_text`], but additionally trims trailing whitespace of each line. Use it for
+/// line-based input, e.g., a list of hashes.
+///
+/// # Errors
+///
+/// This function will return an error if the input could not be read, or if it is not valid
+/// UTF-8. UTF-16 input is detected and rejected with a dedicated message.
+///
+/// # Example
+///
+/// ```
+/// # use utils::read_text_lines;
+/// let input = "\u{feff}first \r\nsecond\r\n".as_bytes();
+/// assert_eq!(read_text_lines(input, "input").unwrap(), ["first", "second"]);
+/// ```
+pub fn read_text_lines<R: Read>(reader: R, ctx: &str) -> io::Result<Vec<String>> {
+    Ok(read_text(reader, ctx)?
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .collect())
+}
+
+/// Reads textual input and normalizes its encoding.
+///
+/// Textual input edited on other systems may start with a byte order mark and end its lines
+/// with `\r\n`. This function strips a leading UTF-8 byte order mark and converts `\r\n` line
+/// endings to `\n`. Warns if the input contains a byte order mark or `\r\n` line endings.
+/// Everything else is left as is, so that structured input, e.g., YAML, is passed to its parser
+/// unchanged. `ctx` names the input in messages, e.g., `"CUID file"`.
+///
+/// # Errors
+///
+/// This function will return an error if the input could not be read, or if it is not valid
+/// UTF-8. UTF-16 input is detected and rejected with a dedicated message.
+pub fn read_text<R: Read>(mut reader: R, ctx: &str) -> io::Result<String> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    if UTF16_BOMS.iter().any(|bom| buf.starts_with(bom)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("The {ctx} is UTF-16 encoded, but only UTF-8 is supported"),
+        ));
+    }
+    let text = String::from_utf8(buf).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "The {ctx} is not valid UTF-8 (invalid byte at position {})",
+                e.utf8_error().valid_up_to()
+            ),
+        )
+    })?;
+
+    let text = match text.strip_prefix(UTF8_BOM) {
+        Some(text) => {
+            warn!("The {ctx} starts with a byte order mark. Ignoring it");
+            text
+        }
+        None => &text,
+    };
+    match text.contains("\r\n") {
+        true => {
+            warn!("The {ctx} contains Windows line endings (CRLF)");
+            Ok(text.replace("\r\n", "\n"))
+        }
+        false => Ok(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plain() {
+        let lines = read_text_lines("a\nb\n".as_bytes(), "test").unwrap();
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(read_text("a\nb".as_bytes(), "test").unwrap(), "a\nb");
+        assert!(read_text_lines("".as_bytes(), "test").unwrap().is_empty());
+        assert_eq!(read_text("".as_bytes(), "test").unwrap(), "");
+    }
+
+    #[test]
+    fn bom() {
+        let lines = read_text_lines("\u{feff}a\nb".as_bytes(), "test").unwrap();
+        assert_eq!(lines, ["a", "b"]);
+        // only a leading BOM is stripped
+        let lines = read_text_lines("a\n\u{feff}b".as_bytes(), "test").unwrap();
+        assert_eq!(lines, ["a", "\u{feff}b"]);
+    }
+
+    #[test]
+    fn crlf() {
+        let lines = read_text_lines("a\r\nb\r\n\r\nc".as_bytes(), "test").unwrap();
+        assert_eq!(lines, ["a", "b", "", "c"]);
+        assert_eq!(
+            read_text("cuid: 0x12\r\n".as_bytes(), "test").unwrap(),
+            "cuid: 0x12\n"
+        );
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let lines = read_text_lines("a \t\n  b  \n".as_bytes(), "test").unwrap();
+        assert_eq!(lines, ["a", "  b"]);
+        // significant in structured input, e.g., YAML block scalars
+        assert_eq!(
+            read_text("a: |\n  b  \n".as_bytes(), "test").unwrap(),
+            "a: |\n  b  \n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let err = read_text_lines([b'a', 0xff, b'b'].as_slice(), "test file").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "The test file is not valid UTF-8 (invalid byte at position 1)"
+        );
+
+        let err = read_text_lines([0xff, 0xfe, b'a', 0].as_slice(), "test file").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The test file is UTF-16 encoded, but only UTF-8 is supported"
+        );
+    }
+}
@@ -4,7 +4,15 @@
 
 use std::{
     ffi::{CString, OsStr},
-    os::unix::prelude::OsStrExt,
+    fs::{File, Permissions},
+    io::Write,
+    os::{
+        fd::FromRawFd,
+        unix::{
+            fs::{MetadataExt, OpenOptionsExt, PermissionsExt},
+            prelude::OsStrExt,
+        },
+    },
     path::{Path, PathBuf},
 };
 
@@ -32,6 +40,33 @@ fn mkdtemp<P: AsRef<Path>>(template: P) -> Result<PathBuf, std::io::Error> {
     }
 }
 
+/// Rust wrapper for `libc::mkstemp`
+fn mkstemp<P: AsRef<Path>>(template: P) -> Result<(PathBuf, File), std::io::Error> {
+    let template_cstr = CString::new(template.as_ref().as_os_str().as_bytes())?;
+    let template_raw = template_cstr.into_raw();
+    unsafe {
+        // SAFETY: template_raw is a valid CString because it was generated by
+        // the `CString::new`.
+        let fd = libc::mkstemp(template_raw);
+        // SAFETY: `template_raw` is still a valid CString because it was
+        // generated by `CString::new` and modified by `libc::mkstemp`.
+        let path_cstr = std::ffi::CString::from_raw(template_raw);
+
+        if fd < 0 {
+            drop(path_cstr);
+            Err(std::io::Error::last_os_error())
+        } else {
+            let path = OsStr::from_bytes(path_cstr.as_bytes());
+            let path = std::path::PathBuf::from(path);
+            // SAFETY: `fd` is a valid file descriptor that was just opened by
+            // `libc::mkstemp` and is owned by nobody else.
+            let file = File::from_raw_fd(fd);
+
+            Ok((path, file))
+        }
+    }
+}
+
 /// This type creates a temporary directory that is automatically removed when
 /// it goes out of scope. It utilizes the `mkdtemp` function and its semantics,
 /// with the addition of automatically including the template characters
@@ -85,16 +120,278 @@ impl TemporaryDirectory {
         })
     }
 
+    /// Creates a temporary directory in `parent` using 'tmp.' as directory
+    /// prefix.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary directory could not
+    /// be created, e.g. because `parent` does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let parent = TemporaryDirectory::new().unwrap();
+    /// let temp = TemporaryDirectory::new_in(&parent).unwrap();
+    /// ```
+    pub fn new_in<P: AsRef<Path>>(parent: P) -> Result<Self, std::io::Error> {
+        Self::with_prefix_in("tmp.", parent)
+    }
+
+    /// Creates a temporary directory in the directory for temporary files
+    /// using 'tmp.' as directory prefix.
+    ///
+    /// The directory for temporary files is determined by
+    /// [`std::env::temp_dir`] and therefore honors `$TMPDIR`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary directory could not
+    /// be created.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let temp = TemporaryDirectory::new_in_temp_dir().unwrap();
+    /// ```
+    pub fn new_in_temp_dir() -> Result<Self, std::io::Error> {
+        Self::new_in(std::env::temp_dir())
+    }
+
+    /// Creates a temporary directory in `parent` using `prefix` as directory
+    /// prefix.
+    ///
+    /// `parent` may be relative to the current working directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary directory could not
+    /// be created, e.g. because `parent` does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let parent = TemporaryDirectory::new().unwrap();
+    /// let temp = TemporaryDirectory::with_prefix_in("test", &parent).unwrap();
+    /// ```
+    pub fn with_prefix_in<P: AsRef<Path>, Q: AsRef<Path>>(
+        prefix: P,
+        parent: Q,
+    ) -> Result<Self, std::io::Error> {
+        let parent = parent.as_ref();
+        let mut name = prefix.as_ref().as_os_str().to_owned();
+        name.push("XXXXXX");
+
+        let temp_dir = mkdtemp(parent.join(name)).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Cannot create a temporary directory in '{}': {e}",
+                    parent.display()
+                ),
+            )
+        })?;
+        Ok(Self {
+            path: temp_dir.into_boxed_path(),
+        })
+    }
+
+    /// Creates a temporary directory in `parent` using `prefix` as directory
+    /// prefix with additional options.
+    ///
+    /// * `mode` - if set, the permissions of the directory are changed to
+    ///   `mode` after creation. Otherwise, the directory has the permissions
+    ///   `0700`.
+    /// * `secure` - if set, verifies that the created directory is secure. See
+    ///   [`TemporaryDirectory::check_secure`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary directory could not
+    /// be created, its permissions could not be changed, or it is not secure.
+    /// The directory is removed in this case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// // directory readable by the group
+    /// let temp = TemporaryDirectory::with_options("test", ".", Some(0o750), true).unwrap();
+    /// ```
+    pub fn with_options<P: AsRef<Path>, Q: AsRef<Path>>(
+        prefix: P,
+        parent: Q,
+        mode: Option<u32>,
+        secure: bool,
+    ) -> Result<Self, std::io::Error> {
+        let temp_dir = Self::with_prefix_in(prefix, parent)?;
+        if let Some(mode) = mode {
+            std::fs::set_permissions(temp_dir.path(), Permissions::from_mode(mode))?;
+        }
+        if secure {
+            temp_dir.check_secure()?;
+        }
+        Ok(temp_dir)
+    }
+
+    /// Verifies that the temporary directory is secure.
+    ///
+    /// A secure directory is no symbolic link, is owned by the effective user
+    /// ID of this process, and is neither writable by the group nor others.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the directory is not secure or
+    /// its metadata could not be read.
+    pub fn check_secure(&self) -> std::io::Result<()> {
+        let insecure = |reason: String| {
+            std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!(
+                    "The temporary directory '{}' is not secure: {reason}",
+                    self.path.display()
+                ),
+            )
+        };
+
+        let meta = std::fs::symlink_metadata(&self.path)?;
+        if meta.file_type().is_symlink() {
+            return Err(insecure("It is a symbolic link".to_string()));
+        }
+        if !meta.is_dir() {
+            return Err(insecure("It is not a directory".to_string()));
+        }
+        // SAFETY: `geteuid` has no preconditions and always succeeds.
+        let euid = unsafe { libc::geteuid() };
+        if meta.uid() != euid {
+            return Err(insecure(format!(
+                "It is owned by UID {} instead of {euid}",
+                meta.uid()
+            )));
+        }
+        if meta.mode() & 0o022 != 0 {
+            return Err(insecure(format!(
+                "It is writable by the group or others (mode {:04o})",
+                meta.mode() & 0o7777
+            )));
+        }
+        Ok(())
+    }
+
     /// Returns a reference to the path of the created temporary directory.
     pub fn path(&self) -> &Path {
         self.path.as_ref()
     }
 
-    /// Takes ownership and releases the memory and makes sure no destructor is
-    /// called and therefore the temporary directory will not be removed.
-    fn forget(mut self) {
-        self.path = PathBuf::new().into_boxed_path();
-        std::mem::forget(self);
+    /// Returns the path of the file `name` in the temporary directory.
+    ///
+    /// `name` must be a plain file name, so that the path cannot escape the
+    /// temporary directory.
+    fn file_path<P: AsRef<Path>>(&self, name: P) -> std::io::Result<PathBuf> {
+        let name = name.as_ref();
+        let bytes = name.as_os_str().as_bytes();
+        if bytes.is_empty() || bytes.contains(&b'/') || bytes == b"." || bytes == b".." {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid file name '{}' for the temporary directory",
+                    name.display()
+                ),
+            ));
+        }
+        Ok(self.path.join(name))
+    }
+
+    /// Creates the new file `name` with the permissions `mode` in the
+    /// temporary directory.
+    ///
+    /// Returns the path and the file opened for writing. The file is removed
+    /// together with the temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` contains a path separator
+    /// or is `..`, if the file already exists, or if it could not be created.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// # use std::io::Write;
+    /// let temp = TemporaryDirectory::new().unwrap();
+    /// let (path, mut file) = temp.create_file("key", 0o600).unwrap();
+    /// file.write_all(b"data").unwrap();
+    /// ```
+    pub fn create_file<P: AsRef<Path>>(
+        &self,
+        name: P,
+        mode: u32,
+    ) -> std::io::Result<(PathBuf, File)> {
+        let path = self.file_path(name)?;
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&path)?;
+        // The mode used at creation is restricted by the umask.
+        file.set_permissions(Permissions::from_mode(mode))?;
+        Ok((path, file))
+    }
+
+    /// Creates the new file `name` with the permissions `mode` in the
+    /// temporary directory and writes `contents` to it.
+    ///
+    /// Returns the path of the file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file could not be created
+    /// (see [`TemporaryDirectory::create_file`]) or written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let temp = TemporaryDirectory::new().unwrap();
+    /// let path = temp.write_file("data", b"data", 0o644).unwrap();
+    /// assert_eq!(std::fs::read(path).unwrap(), b"data");
+    /// ```
+    pub fn write_file<P: AsRef<Path>>(
+        &self,
+        name: P,
+        contents: &[u8],
+        mode: u32,
+    ) -> std::io::Result<PathBuf> {
+        let (path, mut file) = self.create_file(name, mode)?;
+        file.write_all(contents)?;
+        Ok(path)
+    }
+
+    /// Reads the file `name` in the temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `name` contains a path separator
+    /// or is `..`, or if the file could not be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let temp = TemporaryDirectory::new().unwrap();
+    /// temp.write_file("data", b"data", 0o600).unwrap();
+    /// assert_eq!(temp.read_file("data").unwrap(), b"data");
+    /// ```
+    pub fn read_file<P: AsRef<Path>>(&self, name: P) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.file_path(name)?)
+    }
+
+    /// Releases the path so that the destructor will not remove the directory.
+    fn forget(&mut self) -> PathBuf {
+        std::mem::replace(&mut self.path, PathBuf::new().into_boxed_path()).into()
     }
 
     /// Removes the created temporary directory and it's contents.
@@ -103,11 +400,52 @@ impl TemporaryDirectory {
     ///
     /// This function will return an error if the temporary directory could not
     /// removed.
-    pub fn close(self) -> std::io::Result<()> {
+    pub fn close(mut self) -> std::io::Result<()> {
         let ret = std::fs::remove_dir_all(&self.path);
         self.forget();
         ret
     }
+
+    /// Keeps the temporary directory and returns its path.
+    ///
+    /// The directory and its contents are not removed afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryDirectory;
+    /// let temp = TemporaryDirectory::new().unwrap();
+    /// let path = temp.keep();
+    /// assert!(path.exists());
+    /// # std::fs::remove_dir_all(path).unwrap();
+    /// ```
+    pub fn keep(mut self) -> PathBuf {
+        self.forget()
+    }
+
+    /// Moves the temporary directory and its contents to `dest` and returns
+    /// `dest`.
+    ///
+    /// The directory is not removed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `dest` already exists or the
+    /// temporary directory could not be renamed, e.g. because `dest` is on
+    /// another file system. The temporary directory is still removed on drop
+    /// in this case.
+    pub fn persist<P: AsRef<Path>>(mut self, dest: P) -> std::io::Result<PathBuf> {
+        let dest = dest.as_ref();
+        // Creating `dest` fails if it exists. Renaming a directory onto an
+        // empty directory replaces it, so `dest` is never overwritten.
+        std::fs::create_dir(dest)?;
+        if let Err(e) = std::fs::rename(&self.path, dest) {
+            let _ = std::fs::remove_dir(dest);
+            return Err(e);
+        }
+        self.forget();
+        Ok(dest.to_owned())
+    }
 }
 
 impl AsRef<Path> for TemporaryDirectory {
@@ -118,13 +456,142 @@ impl AsRef<Path> for TemporaryDirectory {
 
 impl Drop for TemporaryDirectory {
     fn drop(&mut self) {
-        let _ = std::fs::remove_dir_all(&self.path);
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// This type creates a temporary file that is automatically removed when it
+/// goes out of scope. It utilizes the `mkstemp` function and its semantics,
+/// with the addition of automatically including the template characters
+/// `XXXXXX`. The file is created with the permissions `0600`.
+#[derive(Debug)]
+pub struct TemporaryFile {
+    path: Box<Path>,
+    file: File,
+}
+
+impl TemporaryFile {
+    /// Creates a temporary file in the current working directory using 'tmp.'
+    /// as file prefix.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary file could not be
+    /// created.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryFile;
+    /// let temp = TemporaryFile::new().unwrap();
+    /// ```
+    pub fn new() -> Result<Self, std::io::Error> {
+        Self::with_prefix("tmp.")
+    }
+
+    /// Creates a temporary file in the current working directory using
+    /// `prefix` as file prefix.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary file could not be
+    /// created.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use utils::TemporaryFile;
+    /// # use std::io::Write;
+    /// let temp = TemporaryFile::with_prefix("test").unwrap();
+    /// temp.file().write_all(b"data").unwrap();
+    /// ```
+    pub fn with_prefix<P: AsRef<Path>>(prefix: P) -> Result<Self, std::io::Error> {
+        let mut template = prefix.as_ref().to_owned();
+        let template_os_string = template.as_mut_os_string();
+        template_os_string.push("XXXXXX");
+
+        let (path, file) = mkstemp(template_os_string)?;
+        Ok(Self {
+            path: path.into_boxed_path(),
+            file,
+        })
+    }
+
+    /// Returns a reference to the path of the created temporary file.
+    pub fn path(&self) -> &Path {
+        self.path.as_ref()
+    }
+
+    /// Returns a reference to the opened temporary file.
+    ///
+    /// The file is opened for reading and writing.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Releases the path so that the destructor will not remove the file.
+    fn forget(&mut self) {
+        self.path = PathBuf::new().into_boxed_path();
+    }
+
+    /// Closes and removes the created temporary file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary file could not be
+    /// removed.
+    pub fn close(mut self) -> std::io::Result<()> {
+        let ret = std::fs::remove_file(&self.path);
+        self.forget();
+        ret
+    }
+
+    /// Closes the temporary file and moves it to `dest`.
+    ///
+    /// The file is not removed afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the temporary file could not be
+    /// renamed. The temporary file is removed in this case.
+    pub fn persist<P: AsRef<Path>>(mut self, dest: P) -> std::io::Result<()> {
+        std::fs::rename(&self.path, dest)?;
+        self.forget();
+        Ok(())
+    }
+}
+
+impl AsRef<Path> for TemporaryFile {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl AsMut<File> for TemporaryFile {
+    fn as_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+}
+
+impl Drop for TemporaryFile {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_file(&self.path);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{mkdtemp, TemporaryDirectory};
+    use std::{
+        fs::Permissions,
+        io::{Read, Seek, Write},
+        os::unix::fs::PermissionsExt,
+    };
+
+    use super::{mkdtemp, mkstemp, TemporaryDirectory, TemporaryFile};
 
     #[test]
     fn mkdtemp_test() {
@@ -199,10 +666,370 @@ mod tests {
         assert_eq!(path.as_os_str().len(), "XXXXXX".len());
     }
 
+    #[test]
+    fn temporary_directory_new_in_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        let temp_dir = TemporaryDirectory::new_in(parent.path()).expect("should work");
+        let path = temp_dir.path().to_owned();
+
+        assert!(path.is_dir());
+        assert_eq!(path.parent().unwrap(), parent.path());
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .expect("works")
+            .starts_with("tmp."));
+    }
+
+    #[test]
+    fn temporary_directory_with_prefix_in_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        // parent with trailing separator
+        let parent_sep = format!("{}/", parent.path().display());
+        let temp_dir = TemporaryDirectory::with_prefix_in("yay", &parent_sep).expect("should work");
+        let path = temp_dir.path().to_owned();
+
+        assert!(path.is_dir());
+        assert_eq!(
+            path.canonicalize().unwrap().parent().unwrap(),
+            parent.path().canonicalize().unwrap()
+        );
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .expect("works")
+            .starts_with("yay"));
+    }
+
+    #[test]
+    fn temporary_directory_relative_parent_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        assert!(parent.path().is_relative());
+        let nested = TemporaryDirectory::new_in(parent.path()).expect("should work");
+        let temp_dir = TemporaryDirectory::with_prefix_in("", nested.path()).expect("should work");
+
+        assert!(temp_dir.path().is_relative());
+        assert!(temp_dir.path().starts_with(nested.path()));
+        assert_eq!(temp_dir.path().file_name().unwrap().len(), "XXXXXX".len());
+    }
+
+    #[test]
+    fn temporary_directory_new_in_temp_dir_test() {
+        let temp_dir = TemporaryDirectory::new_in_temp_dir().expect("should work");
+
+        assert!(temp_dir.path().is_dir());
+        assert_eq!(temp_dir.path().parent().unwrap(), std::env::temp_dir());
+    }
+
+    #[test]
+    fn temporary_directory_parent_does_not_exist_test() {
+        let err = TemporaryDirectory::new_in("NA-yay/parent").expect_err("parent does not exist");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("'NA-yay/parent'"));
+    }
+
+    #[test]
+    fn temporary_directory_nested_cleanup_test() {
+        // child is removed before the parent
+        let parent = TemporaryDirectory::new().expect("should work");
+        let child = TemporaryDirectory::new_in(&parent).expect("should work");
+        let (parent_path, child_path) = (parent.path().to_owned(), child.path().to_owned());
+        drop(child);
+        assert!(!child_path.exists());
+        assert!(parent_path.exists());
+        drop(parent);
+        assert!(!parent_path.exists());
+
+        // parent is removed before the child
+        let parent = TemporaryDirectory::new().expect("should work");
+        let child = TemporaryDirectory::new_in(&parent).expect("should work");
+        let (parent_path, child_path) = (parent.path().to_owned(), child.path().to_owned());
+        parent.close().unwrap();
+        assert!(!child_path.exists());
+        assert!(!parent_path.exists());
+        child.close().expect_err("already removed");
+    }
+
+    #[test]
+    fn temporary_directory_default_mode_test() {
+        let temp_dir =
+            TemporaryDirectory::with_options("yay", ".", None, true).expect("should work");
+        let mode = temp_dir.path().metadata().unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o7777, 0o700);
+    }
+
+    #[test]
+    fn temporary_directory_mode_test() {
+        let temp_dir =
+            TemporaryDirectory::with_options("yay", ".", Some(0o750), true).expect("should work");
+        let mode = temp_dir.path().metadata().unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o7777, 0o750);
+        assert!(temp_dir
+            .path()
+            .file_name()
+            .unwrap()
+            .to_str()
+            .expect("works")
+            .starts_with("yay"));
+    }
+
+    #[test]
+    fn temporary_directory_insecure_mode_test() {
+        // insecure, but allowed without the secure check
+        let temp_dir =
+            TemporaryDirectory::with_options("", ".", Some(0o770), false).expect("should work");
+        let mode = temp_dir.path().metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o770);
+
+        let err = TemporaryDirectory::with_options("insecure", ".", Some(0o770), true)
+            .expect_err("group writable");
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("(mode 0770)"));
+        // the insecure directory was removed
+        let leftover = std::fs::read_dir(".")
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .any(|n| n.to_str().is_some_and(|n| n.starts_with("insecure")));
+        assert!(!leftover);
+    }
+
+    #[test]
+    fn temporary_directory_check_secure_test() {
+        let temp_dir = TemporaryDirectory::with_options("", ".", None, true).expect("should work");
+        temp_dir.check_secure().unwrap();
+
+        // loosen the mode behind its back
+        std::fs::set_permissions(temp_dir.path(), Permissions::from_mode(0o702)).unwrap();
+        let err = temp_dir.check_secure().expect_err("world writable");
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("writable by the group or others"));
+    }
+
+    #[test]
+    fn temporary_directory_check_secure_symlink_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        let temp_dir = TemporaryDirectory::new_in(&parent).expect("should work");
+        let target = temp_dir.path().with_extension("target");
+        std::fs::rename(temp_dir.path(), &target).unwrap();
+        std::os::unix::fs::symlink(&target, temp_dir.path()).unwrap();
+
+        let err = temp_dir.check_secure().expect_err("symbolic link");
+        assert!(err.to_string().contains("symbolic link"));
+    }
+
+    #[test]
+    fn temporary_directory_keep_test() {
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let exp_path = temp_dir.path().to_owned();
+        std::fs::write(temp_dir.path().join("file"), b"keep").unwrap();
+
+        let path = temp_dir.keep();
+        assert_eq!(path, exp_path);
+        assert_eq!(std::fs::read(path.join("file")).unwrap(), b"keep");
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn temporary_directory_persist_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        let dest = parent.path().join("persisted");
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let path = temp_dir.path().to_owned();
+        std::fs::write(temp_dir.path().join("file"), b"persist").unwrap();
+
+        assert_eq!(temp_dir.persist(&dest).unwrap(), dest);
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(dest.join("file")).unwrap(), b"persist");
+    }
+
+    #[test]
+    fn temporary_directory_persist_exists_test() {
+        let dest = TemporaryDirectory::new().expect("should work");
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let path = temp_dir.path().to_owned();
+        std::fs::write(temp_dir.path().join("file"), b"persist").unwrap();
+
+        let err = temp_dir.persist(&dest).expect_err("destination exists");
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        // the destination is untouched and the temporary directory removed
+        assert!(dest.path().is_dir());
+        assert!(!dest.path().join("file").exists());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn temporary_directory_create_file_test() {
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let (path, mut file) = temp_dir.create_file("file", 0o640).expect("should work");
+        file.write_all(b"create").unwrap();
+
+        assert_eq!(path, temp_dir.path().join("file"));
+        let mode = path.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o640);
+        assert_eq!(temp_dir.read_file("file").unwrap(), b"create");
+
+        let err = temp_dir
+            .create_file("file", 0o600)
+            .expect_err("file exists");
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn temporary_directory_write_file_test() {
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let dir_path = temp_dir.path().to_owned();
+        let path = temp_dir
+            .write_file("file", b"write", 0o664)
+            .expect("should work");
+
+        let mode = path.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o664);
+        assert_eq!(std::fs::read(&path).unwrap(), b"write");
+
+        drop(temp_dir);
+        assert!(!path.exists());
+        assert!(!dir_path.exists());
+    }
+
+    #[test]
+    fn temporary_directory_file_traversal_test() {
+        let parent = TemporaryDirectory::new().expect("should work");
+        let temp_dir = TemporaryDirectory::new_in(&parent).expect("should work");
+
+        for name in ["", ".", "..", "../file", "sub/file", "/tmp/file", "file/"] {
+            let err = temp_dir.create_file(name, 0o600).expect_err("invalid name");
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+            let err = temp_dir
+                .write_file(name, b"", 0o600)
+                .expect_err("invalid name");
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+            let err = temp_dir.read_file(name).expect_err("invalid name");
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        assert!(!parent.path().join("file").exists());
+        // dots within a file name are fine
+        temp_dir.write_file("..file", b"", 0o600).unwrap();
+    }
+
     #[test]
     fn temporary_directory_as_ref_test() {
         let temp_dir = TemporaryDirectory::new().expect("should work");
 
         assert_eq!(temp_dir.path(), temp_dir.as_ref());
     }
+
+    #[test]
+    fn mkstemp_test() {
+        let template_inv_not_last_characters = "XXXXXXyay";
+        let template_inv_path_does_not_exist = "../NA-yay/XXXXXX";
+
+        let template = "yayXXXXXX";
+
+        let _err = mkstemp(template_inv_not_last_characters).expect_err("invalid template");
+        let _err =
+            mkstemp(template_inv_path_does_not_exist).expect_err("path does not exist template");
+
+        let (path, _file) = mkstemp(template).expect("mkstemp should work");
+        assert!(path.is_file());
+        assert!(path.as_os_str().to_str().expect("works").starts_with("yay"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn temporary_file_resides_in_cwd() {
+        let temp_file = TemporaryFile::new().expect("should work");
+        let path = temp_file.path().to_owned();
+        let cwd = std::env::current_dir().unwrap();
+
+        assert_eq!(path.canonicalize().unwrap().parent().unwrap(), cwd);
+    }
+
+    #[test]
+    fn temporary_file_permissions_test() {
+        let temp_file = TemporaryFile::new().expect("should work");
+        let mode = temp_file.path().metadata().unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn temporary_file_close_test() {
+        let temp_file = TemporaryFile::new().expect("should work");
+        let path = temp_file.path().to_owned();
+        assert!(path.exists());
+
+        // Test that close removes the file
+        temp_file.close().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn temporary_file_drop_test() {
+        let temp_file = TemporaryFile::new().expect("should work");
+        let path = temp_file.path().to_owned();
+        assert!(path.exists());
+
+        // Test that the destructor removes the file
+        drop(temp_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn temporary_file_persist_test() {
+        let temp_dir = TemporaryDirectory::new().expect("should work");
+        let dest = temp_dir.path().join("persisted");
+        let mut temp_file = TemporaryFile::new().expect("should work");
+        let path = temp_file.path().to_owned();
+        temp_file.as_mut().write_all(b"persist").unwrap();
+
+        temp_file.persist(&dest).unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"persist");
+    }
+
+    #[test]
+    fn temporary_file_write_read_test() {
+        let mut temp_file = TemporaryFile::new().expect("should work");
+        temp_file.file().write_all(b"data").unwrap();
+        temp_file.as_mut().rewind().unwrap();
+
+        let mut buf = String::new();
+        temp_file.file().read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "data");
+    }
+
+    #[test]
+    fn temporary_file_prefix_test() {
+        let prefix = "yay";
+        let temp_file = TemporaryFile::with_prefix(prefix).expect("should work");
+
+        let path = temp_file.path().to_owned();
+        assert!(path.exists());
+        assert!(path
+            .as_os_str()
+            .to_str()
+            .expect("works")
+            .starts_with(prefix));
+    }
+
+    #[test]
+    fn temporary_file_empty_prefix_test() {
+        let temp_file = TemporaryFile::with_prefix("").expect("should work");
+        let path = temp_file.path().to_owned();
+        assert!(path.exists());
+        // Path consists only of the rendered template.
+        assert_eq!(path.as_os_str().len(), "XXXXXX".len());
+    }
+
+    #[test]
+    fn temporary_file_as_ref_test() {
+        let temp_file = TemporaryFile::new().expect("should work");
+
+        assert_eq!(temp_file.path(), temp_file.as_ref());
+    }
 }
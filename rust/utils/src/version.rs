@@ -0,0 +1,449 @@
+// SPDX-License-Identifier: MIT
+//
+// Copyright IBM Corp. 2024
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// A version of the form `major.minor[.patch][-suffix]`
+///
+/// The suffix is a pre-release, e.g., `rc1` in `1.2.3-rc1`. It consists of dot-separated
+/// identifiers of ASCII alphanumerics and hyphens. Versions are compared numerically first and by
+/// their suffix afterwards, following the precedence rules of semantic versioning: A version with
+/// suffix is lower than the same version without suffix. Numeric suffix identifiers are compared
+/// numerically and are lower than alphanumeric ones, which are compared lexically. A missing
+/// patch level is equal to patch level zero.
+///
+/// Serializes as string.
+///
+/// # Example
+///
+/// ```
+/// # use utils::Version;
+/// let v: Version = "1.10-rc1".parse().unwrap();
+/// assert!(v > "1.9.7".parse().unwrap());
+/// assert!(v < "1.10".parse().unwrap());
+/// assert_eq!(v.to_string(), "1.10-rc1");
+/// assert_eq!("1.2".parse::<Version>(), "1.2.0".parse());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Version {
+    major: u32,
+    minor: u32,
+    patch: Option<u32>,
+    suffix: String,
+}
+
+/// Error while parsing a [`Version`] or a [`VersionReq`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    /// The version string is empty
+    Empty,
+    /// The minor version is missing
+    MissingMinor,
+    /// A numeric component is empty or too large
+    InvalidNumber(String),
+    /// The version has more than three numeric components
+    TooManyComponents,
+    /// The suffix is not a valid pre-release
+    InvalidSuffix(String),
+    /// A constraint does not start with `>=`, `=`, or `<`
+    InvalidOperator(String),
+}
+
+impl Display for VersionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty version"),
+            Self::MissingMinor => write!(f, "The version has no minor version"),
+            Self::InvalidNumber(n) => write!(f, "Invalid version number '{n}'"),
+            Self::TooManyComponents => write!(f, "The version has more than three numbers"),
+            Self::InvalidSuffix(s) => write!(f, "Invalid version suffix '{s}'"),
+            Self::InvalidOperator(c) => write!(
+                f,
+                "Invalid version constraint '{c}': Must start with '>=', '=', or '<'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+impl Version {
+    /// Creates a new [`Version`] without suffix.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch: Some(patch),
+            suffix: String::new(),
+        }
+    }
+
+    /// Returns the major version.
+    pub const fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Returns the minor version.
+    pub const fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Returns the patch level. Zero if not specified.
+    pub fn patch(&self) -> u32 {
+        self.patch.unwrap_or_default()
+    }
+
+    /// Returns the suffix without the leading `-`. Empty if not specified.
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    fn key(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch())
+    }
+
+    /// Compares the suffixes by the precedence of semantic versioning pre-releases
+    fn cmp_suffix(&self, other: &Self) -> Ordering {
+        match (self.suffix.is_empty(), other.suffix.is_empty()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Greater,
+            (false, true) => return Ordering::Less,
+            (false, false) => (),
+        }
+        // numeric identifiers are lower than alphanumeric ones
+        fn ids(suffix: &str) -> impl Iterator<Item = (bool, Option<u64>, &str)> {
+            suffix.split('.').map(|id| {
+                let num = id.parse::<u64>().ok();
+                (num.is_none(), num, id)
+            })
+        }
+        ids(&self.suffix).cmp(ids(&other.suffix))
+    }
+}
+
+/// Checks that `suffix` consists of non-empty dot-separated identifiers of ASCII alphanumerics
+/// and hyphens
+fn valid_suffix(suffix: &str) -> bool {
+    suffix
+        .split('.')
+        .all(|id| !id.is_empty() && id.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-'))
+}
+
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(VersionError::Empty);
+        }
+        let parse = |n: &str| {
+            n.parse::<u32>()
+                .map_err(|_| VersionError::InvalidNumber(n.to_string()))
+        };
+
+        // the numeric components, optionally followed by `-` and the suffix
+        let (nums, suffix) = match s.split_once('-') {
+            Some((_, suffix)) if !valid_suffix(suffix) => {
+                return Err(VersionError::InvalidSuffix(suffix.to_string()))
+            }
+            Some((nums, suffix)) => (nums, suffix),
+            None => (s, ""),
+        };
+        let nums = nums.split('.').map(parse).collect::<Result<Vec<_>, _>>()?;
+        let (major, minor, patch) = match nums[..] {
+            [_] => return Err(VersionError::MissingMinor),
+            [major, minor] => (major, minor, None),
+            [major, minor, patch] => (major, minor, Some(patch)),
+            _ => return Err(VersionError::TooManyComponents),
+        };
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            suffix: suffix.to_string(),
+        })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        if !self.suffix.is_empty() {
+            write!(f, "-{}", self.suffix)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key()
+            .cmp(&other.key())
+            .then_with(|| self.cmp_suffix(other))
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Operator of a single [`VersionReq`] constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VersionOp {
+    GreaterEq,
+    Eq,
+    Less,
+}
+
+impl VersionOp {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::GreaterEq => ">=",
+            Self::Eq => "=",
+            Self::Less => "<",
+        }
+    }
+
+    fn matches(&self, version: &Version, req: &Version) -> bool {
+        match self {
+            Self::GreaterEq => version >= req,
+            Self::Eq => version == req,
+            Self::Less => version < req,
+        }
+    }
+}
+
+/// A set of version constraints, e.g., `>=1.2, <2.0`
+///
+/// Constraints are separated by commas and start with `>=`, `=`, or `<`. A version matches if it
+/// fulfills all constraints. Serializes as string.
+///
+/// # Example
+///
+/// ```
+/// # use utils::{Version, VersionReq};
+/// let req: VersionReq = ">=1.2, <2.0".parse().unwrap();
+/// assert!(req.matches(&"1.10.1".parse().unwrap()));
+/// assert!(!req.matches(&Version::new(2, 0, 0)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq(Vec<(VersionOp, Version)>);
+
+impl VersionReq {
+    /// Returns true if `version` fulfills all constraints.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.0.iter().all(|(op, req)| op.matches(version, req))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|c| {
+                let c = c.trim();
+                let (op, version) = [VersionOp::GreaterEq, VersionOp::Eq, VersionOp::Less]
+                    .into_iter()
+                    .find_map(|op| c.strip_prefix(op.as_str()).map(|v| (op, v)))
+                    .ok_or_else(|| match c.is_empty() {
+                        true => VersionError::Empty,
+                        false => VersionError::InvalidOperator(c.to_string()),
+                    })?;
+                // e.g. `<=` or `>`
+                if version.starts_with(['<', '>', '=']) {
+                    return Err(VersionError::InvalidOperator(c.to_string()));
+                }
+                Ok((op, version.parse()?))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let constraints: Vec<_> = self
+            .0
+            .iter()
+            .map(|(op, v)| format!("{}{v}", op.as_str()))
+            .collect();
+        write!(f, "{}", constraints.join(", "))
+    }
+}
+
+impl Serialize for VersionReq {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionReq {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        for (s, exp) in [
+            ("1.2", (1, 2, 0, "")),
+            ("1.2.3", (1, 2, 3, "")),
+            (" 10.0.1 ", (10, 0, 1, "")),
+            ("1.2.3-rc1", (1, 2, 3, "rc1")),
+            ("1.2-alpha.1", (1, 2, 0, "alpha.1")),
+            ("1.2.3-x-y", (1, 2, 3, "x-y")),
+        ] {
+            let v = v(s);
+            assert_eq!((v.major(), v.minor(), v.patch(), v.suffix()), exp, "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_err() {
+        for (s, exp) in [
+            ("", VersionError::Empty),
+            ("  ", VersionError::Empty),
+            ("1", VersionError::MissingMinor),
+            ("1-rc1", VersionError::MissingMinor),
+            ("1.", VersionError::InvalidNumber("".into())),
+            (".1", VersionError::InvalidNumber("".into())),
+            ("1..2", VersionError::InvalidNumber("".into())),
+            ("v1.2", VersionError::InvalidNumber("v1".into())),
+            ("1.2a", VersionError::InvalidNumber("2a".into())),
+            ("1.2.3.4", VersionError::TooManyComponents),
+            ("1.2.3-", VersionError::InvalidSuffix("".into())),
+            ("1.2.3-rc..1", VersionError::InvalidSuffix("rc..1".into())),
+            ("1.2.3-rc_1", VersionError::InvalidSuffix("rc_1".into())),
+            (
+                "1.99999999999",
+                VersionError::InvalidNumber("99999999999".into()),
+            ),
+        ] {
+            assert_eq!(s.parse::<Version>(), Err(exp), "{s}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        for s in ["1.2", "1.2.0", "1.2.3-rc1", "0.11-alpha.1"] {
+            assert_eq!(v(s).to_string(), s);
+        }
+        assert_eq!(Version::new(1, 2, 3).to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn compare() {
+        use Ordering::*;
+        for (a, b, exp) in [
+            ("1.2", "1.2.0", Equal),
+            ("1.2.3", "1.2.3", Equal),
+            ("1.2", "1.10", Less),
+            ("1.10", "1.9.9", Greater),
+            ("2.0", "1.99.99", Greater),
+            ("1.2.3", "1.2.3-rc1", Greater),
+            ("1.2-rc1", "1.2.0-rc1", Equal),
+            ("1.2.3-rc1", "1.2.3-rc2", Less),
+            ("1.2.3-rc2", "1.2.4-rc1", Less),
+            ("1.2-beta", "1.2-alpha", Greater),
+            ("1.2-alpha", "1.2-alpha.1", Less),
+            ("1.2-alpha.2", "1.2-alpha.10", Less),
+            ("1.2-alpha.10", "1.2-alpha.beta", Less),
+            ("1.2-1", "1.2-alpha", Less),
+        ] {
+            assert_eq!(v(a).cmp(&v(b)), exp, "{a} {b}");
+            assert_eq!(v(b).cmp(&v(a)), exp.reverse(), "{b} {a}");
+            assert_eq!(v(a) == v(b), exp == Equal, "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn req() {
+        for (req, version, exp) in [
+            (">=1.2", "1.2", true),
+            (">=1.2", "1.1.9", false),
+            ("=1.2.3", "1.2.3", true),
+            ("=1.2", "1.2.0", true),
+            ("=1.2.3", "1.2.3-rc1", false),
+            (">=1.2.3", "1.2.3-rc1", false),
+            ("<1.2.3", "1.2.3-rc1", true),
+            ("<2.0", "1.99", true),
+            ("<2.0", "2.0", false),
+            (">=1.2, <2.0", "1.5", true),
+            (">=1.2,<2.0", "2.1", false),
+            (" >= 1.2 , < 2.0 ", "1.2.1", true),
+        ] {
+            let r: VersionReq = req.parse().unwrap();
+            assert_eq!(r.matches(&v(version)), exp, "{req} {version}");
+        }
+        let req: VersionReq = " >= 1.2 ,<2.0".parse().unwrap();
+        assert_eq!(req.to_string(), ">=1.2, <2.0");
+    }
+
+    #[test]
+    fn req_err() {
+        for (s, exp) in [
+            ("", VersionError::Empty),
+            (">=1.2,", VersionError::Empty),
+            ("1.2", VersionError::InvalidOperator("1.2".into())),
+            ("<=1.2", VersionError::InvalidOperator("<=1.2".into())),
+            (">=1", VersionError::MissingMinor),
+        ] {
+            assert_eq!(s.parse::<VersionReq>(), Err(exp), "{s}");
+        }
+    }
+
+    #[test]
+    fn serde() {
+        assert_tokens(&v("1.2.3-rc1"), &[Token::Str("1.2.3-rc1")]);
+        let req: VersionReq = ">=1.2, <2.0".parse().unwrap();
+        assert_tokens(&req, &[Token::Str(">=1.2, <2.0")]);
+        assert_de_tokens_error::<Version>(&[Token::Str("1")], "The version has no minor version");
+    }
+}